@@ -0,0 +1,55 @@
+//! Benchmarks of the folder alias resolution, with and without the
+//! folder aliases cache.
+//!
+//! Run them with `cargo bench --bench folder_alias`.
+
+use std::{collections::HashMap, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use email::{account::config::AccountConfig, folder::config::FolderConfig};
+
+/// Number of folders resolved per iteration, roughly what a sync of
+/// a large account does per folder.
+const RESOLUTIONS: usize = 1000;
+
+fn account_config() -> AccountConfig {
+    let aliases = HashMap::from_iter([
+        ("inbox".into(), "INBOX".into()),
+        ("sent".into(), "$HOME/Sent Items".into()),
+        ("drafts".into(), "~/Drafts".into()),
+        ("trash".into(), "${HOME}/Deleted Items".into()),
+    ]);
+
+    AccountConfig {
+        folder: Some(FolderConfig {
+            aliases: Some(aliases),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn resolve(config: &AccountConfig, cached: bool) {
+    for _ in 0..RESOLUTIONS {
+        if !cached {
+            config.clear_folder_aliases_cache();
+        }
+
+        black_box(config.get_folder_alias(black_box("sent")));
+        black_box(config.get_folder_alias(black_box("Archives")));
+        black_box(config.find_folder_kind_from_alias(black_box("~/Drafts")));
+    }
+}
+
+fn folder_alias(c: &mut Criterion) {
+    let config = account_config();
+    let mut group = c.benchmark_group("folder_alias");
+
+    group.bench_function("uncached", |b| b.iter(|| resolve(&config, false)));
+    group.bench_function("cached", |b| b.iter(|| resolve(&config, true)));
+
+    group.finish();
+}
+
+criterion_group!(benches, folder_alias);
+criterion_main!(benches);
@@ -63,6 +63,7 @@ pub async fn main() {
                     page: 1,
                     page_size: 10,
                     query: Some(query),
+                    changed_since: None,
                 },
             )
             .await
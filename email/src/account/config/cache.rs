@@ -0,0 +1,151 @@
+//! # Folder aliases cache
+//!
+//! Module dedicated to the folder aliases cache. Folder aliases are
+//! resolved on nearly every backend feature call, and each
+//! resolution shell expands the alias (environment variables, home
+//! directory). The [`FolderAliasesCache`] memoizes these expansions.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{PoisonError, RwLock},
+};
+
+use shellexpand_utils::shellexpand_str;
+
+/// The maximum number of entries of the cache.
+///
+/// Folder names can come from user input, the cache is cleared when
+/// this limit is reached to keep its size bounded.
+pub const MAX_ENTRIES: usize = 1024;
+
+/// The folder aliases cache.
+///
+/// Expansions are indexed by the raw, unexpanded string. Changing
+/// the folder aliases configuration changes the raw strings, which
+/// naturally invalidates the cache. Only changes of the environment
+/// require the cache to be cleared manually, see
+/// [`FolderAliasesCache::clear`].
+///
+/// The cache is not part of the configuration: all caches are equal,
+/// and they are never (de)serialized.
+#[derive(Default)]
+pub struct FolderAliasesCache {
+    expansions: RwLock<HashMap<String, String>>,
+}
+
+impl FolderAliasesCache {
+    /// Shell expand the given string, using the cache if possible.
+    pub fn expand(&self, raw: &str) -> String {
+        let expansions = self
+            .expansions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(expanded) = expansions.get(raw) {
+            return expanded.clone();
+        }
+
+        drop(expansions);
+
+        let expanded = shellexpand_str(raw);
+
+        let mut expansions = self
+            .expansions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if expansions.len() >= MAX_ENTRIES {
+            expansions.clear();
+        }
+
+        expansions.insert(raw.to_owned(), expanded.clone());
+        expanded
+    }
+
+    /// Return the number of cached expansions.
+    pub fn len(&self) -> usize {
+        self.expansions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Return `true` if the cache does not contain any expansion.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached expansions.
+    pub fn clear(&self) {
+        self.expansions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear()
+    }
+}
+
+impl Clone for FolderAliasesCache {
+    fn clone(&self) -> Self {
+        let expansions = self
+            .expansions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        Self {
+            expansions: RwLock::new(expansions),
+        }
+    }
+}
+
+impl fmt::Debug for FolderAliasesCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FolderAliasesCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl PartialEq for FolderAliasesCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for FolderAliasesCache {}
+
+#[cfg(test)]
+mod tests {
+    use super::{FolderAliasesCache, MAX_ENTRIES};
+
+    #[test]
+    fn expand() {
+        let cache = FolderAliasesCache::default();
+        assert!(cache.is_empty());
+
+        assert_eq!(cache.expand("INBOX"), "INBOX");
+        assert_eq!(cache.expand("INBOX"), "INBOX");
+        assert_eq!(cache.len(), 1);
+
+        assert_eq!(cache.clone().len(), 1);
+        assert_eq!(cache, FolderAliasesCache::default());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn expand_bounded() {
+        let cache = FolderAliasesCache::default();
+
+        for i in 0..MAX_ENTRIES {
+            cache.expand(&format!("folder-{i}"));
+        }
+
+        assert_eq!(cache.len(), MAX_ENTRIES);
+
+        cache.expand("overflow");
+        assert_eq!(cache.len(), 1);
+    }
+}
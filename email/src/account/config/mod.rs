@@ -3,6 +3,7 @@
 //! This module contains the representation of the user's current
 //! account configuration named [`AccountConfig`].
 
+pub mod cache;
 #[cfg(feature = "oauth2")]
 pub mod oauth2;
 pub mod passwd;
@@ -28,20 +29,32 @@
 use process::Command;
 use shellexpand_utils::{shellexpand_path, shellexpand_str, try_shellexpand_path};
 
+use self::cache::FolderAliasesCache;
 #[cfg(feature = "pgp")]
 use self::pgp::PgpConfig;
 #[cfg(feature = "sync")]
 use super::sync::config::SyncConfig;
 #[doc(inline)]
 pub use super::{Error, Result};
+#[cfg(feature = "dkim")]
+use crate::message::send::config::DkimConfig;
+#[cfg(feature = "network")]
+use crate::network::config::NetworkConfig;
 use crate::{
+    backend::retry::RetryConfig,
     date::from_mail_parser_to_chrono_datetime,
     debug,
     email::config::EmailTextPlainFormat,
     envelope::{config::EnvelopeConfig, Envelope},
     flag::config::FlagConfig,
     folder::{config::FolderConfig, FolderKind, DRAFTS, INBOX, SENT, TRASH},
-    message::config::MessageConfig,
+    message::{
+        config::MessageConfig,
+        send::{
+            config::{PreSendHook, SaveCopyKind, SendRateLimitConfig, SendRouteConfig},
+            is_sent_message_saved_by_provider,
+        },
+    },
     template::{
         config::TemplateConfig,
         forward::config::{ForwardTemplatePostingStyle, ForwardTemplateSignatureStyle},
@@ -118,6 +131,19 @@ pub struct AccountConfig {
     /// The message configuration.
     pub template: Option<TemplateConfig>,
 
+    /// The list of VIP senders.
+    ///
+    /// An entry is either a full email address (`boss@example.com`)
+    /// or a domain prefixed with `@` (`@example.com`). Envelopes sent
+    /// by a VIP sender are marked as such, see [`Envelope::is_vip`].
+    pub vip_senders: Option<Vec<String>>,
+
+    /// The retry configuration of backend features.
+    ///
+    /// Backend features failing with a transient error are retried
+    /// when defined, see [`RetryLayer`](crate::backend::retry::RetryLayer).
+    pub retry: Option<RetryConfig>,
+
     /// The account synchronization configuration.
     #[cfg(feature = "sync")]
     pub sync: Option<SyncConfig>,
@@ -125,6 +151,19 @@ pub struct AccountConfig {
     /// The PGP configuration.
     #[cfg(feature = "pgp")]
     pub pgp: Option<PgpConfig>,
+
+    /// The network configuration.
+    ///
+    /// Shared by all the network contexts of the account (IMAP,
+    /// SMTP, ManageSieve, NNTP, LMTP and OAuth 2.0 token requests).
+    #[cfg(feature = "network")]
+    pub network: Option<NetworkConfig>,
+
+    /// The folder aliases cache.
+    ///
+    /// This cache is internal, it is not part of the configuration.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub folder_aliases_cache: FolderAliasesCache,
 }
 
 impl AccountConfig {
@@ -212,6 +251,45 @@ pub fn does_sync_dir_exist(&self) -> bool {
         }
     }
 
+    /// Return `true` if the given email address belongs to a VIP
+    /// sender.
+    ///
+    /// Both addresses and domains are compared case-insensitively.
+    pub fn is_vip_sender(&self, addr: &str) -> bool {
+        let Some(vips) = self.vip_senders.as_ref() else {
+            return false;
+        };
+
+        let addr = addr.trim();
+        let domain = addr.rsplit_once('@').map(|(_, domain)| domain);
+
+        vips.iter()
+            .map(|vip| vip.trim())
+            .any(|vip| match vip.strip_prefix('@') {
+                Some(vip_domain) => domain
+                    .map(|domain| domain.eq_ignore_ascii_case(vip_domain))
+                    .unwrap_or_default(),
+                None => vip.eq_ignore_ascii_case(addr),
+            })
+    }
+
+    /// Execute the VIP envelope received hook.
+    ///
+    /// Falls back to the envelope received hook if no VIP hook is
+    /// defined.
+    #[cfg(feature = "watch")]
+    pub async fn exec_received_vip_envelope_hook(&self, envelope: &Envelope) {
+        let hook = self
+            .envelope
+            .as_ref()
+            .and_then(|c| c.watch.as_ref())
+            .and_then(|c| c.received_vip.as_ref().or(c.received.as_ref()));
+
+        if let Some(hook) = hook.as_ref() {
+            self.exec_envelope_hook(hook, envelope).await
+        }
+    }
+
     /// Execute the envelope received hook.
     #[cfg(feature = "watch")]
     pub async fn exec_received_envelope_hook(&self, envelope: &Envelope) {
@@ -297,7 +375,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
             let summary = replace(&notify.summary, &envelope);
             let body = replace(&notify.body, &envelope);
 
-            let res = tokio::task::spawn_blocking(move || {
+            let res = crate::runtime::spawn_blocking(move || {
                 Notification::new().summary(&summary).body(&body).show()
             })
             .await;
@@ -333,7 +411,7 @@ pub fn find_folder_alias(&self, from_name: &str) -> Option<String> {
             .and_then(|aliases| {
                 aliases.iter().find_map(|(name, alias)| {
                     if name.eq_ignore_ascii_case(from_name.trim()) {
-                        Some(shellexpand_str(alias))
+                        Some(self.folder_aliases_cache.expand(alias))
                     } else {
                         None
                     }
@@ -345,7 +423,16 @@ pub fn find_folder_alias(&self, from_name: &str) -> Option<String> {
     /// folder itself.
     pub fn get_folder_alias(&self, folder: &str) -> String {
         self.find_folder_alias(folder)
-            .unwrap_or_else(|| shellexpand_str(folder))
+            .unwrap_or_else(|| self.folder_aliases_cache.expand(folder))
+    }
+
+    /// Clear the folder aliases cache.
+    ///
+    /// Changes of the folder aliases configuration are detected
+    /// automatically, clearing the cache is only needed when the
+    /// environment used for shell expansion changes.
+    pub fn clear_folder_aliases_cache(&self) {
+        self.folder_aliases_cache.clear()
     }
 
     /// Get the inbox folder alias.
@@ -384,6 +471,49 @@ pub fn is_delete_message_style_flag(&self) -> bool {
             .is_some()
     }
 
+    /// Get the custom order of listed folders, if configured.
+    ///
+    /// See [`FolderListConfig::order`](crate::folder::list::config::FolderListConfig::order).
+    pub fn get_folder_list_order(&self) -> Option<&[String]> {
+        self.folder
+            .as_ref()
+            .and_then(|c| c.list.as_ref())
+            .and_then(|c| c.order.as_deref())
+    }
+
+    /// Get the path to the local folder metadata store, if
+    /// configured.
+    ///
+    /// See [`FolderConfig::metadata_store`].
+    pub fn get_folder_metadata_store_path(&self) -> Option<PathBuf> {
+        self.folder
+            .as_ref()
+            .and_then(|c| c.metadata_store.as_ref())
+            .map(shellexpand_path)
+    }
+
+    /// Return `true` if flags that a backend cannot represent should
+    /// be reported.
+    ///
+    /// See [`FlagConfig::strict`].
+    pub fn is_flag_strict_mode_enabled(&self) -> bool {
+        self.flag
+            .as_ref()
+            .and_then(|c| c.strict)
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if flags that a backend cannot represent should
+    /// be preserved in a local store.
+    ///
+    /// See [`FlagConfig::preserve_unsupported`].
+    pub fn should_preserve_unsupported_flags(&self) -> bool {
+        self.flag
+            .as_ref()
+            .and_then(|c| c.preserve_unsupported)
+            .unwrap_or_default()
+    }
+
     /// Get all folder aliases.
     pub fn get_folder_aliases(&self) -> Option<&HashMap<String, String>> {
         self.folder.as_ref().and_then(|c| c.aliases.as_ref())
@@ -399,9 +529,10 @@ pub fn find_folder_kind_from_alias(&self, alias: &str) -> Option<FolderKind> {
             .as_ref()
             .and_then(|c| c.aliases.as_ref())
             .and_then(|aliases| {
-                let from_alias = shellexpand_str(alias);
+                let from_alias = self.folder_aliases_cache.expand(alias);
                 aliases.iter().find_map(|(kind_or_name, alias)| {
-                    if shellexpand_str(alias).eq_ignore_ascii_case(&from_alias) {
+                    let alias = self.folder_aliases_cache.expand(alias);
+                    if alias.eq_ignore_ascii_case(&from_alias) {
                         Some(kind_or_name.into())
                     } else {
                         None
@@ -458,6 +589,14 @@ pub fn get_message_read_headers(&self) -> Vec<String> {
             ])
     }
 
+    /// Find the maximum size of messages that can be read.
+    pub fn find_message_read_max_size(&self) -> Option<u64> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.read.as_ref())
+            .and_then(|c| c.max_size)
+    }
+
     /// Get the message writing headers if defined, otherwise return
     /// the default ones.
     pub fn get_message_write_headers(&self) -> Vec<String> {
@@ -483,16 +622,139 @@ pub fn find_message_pre_send_hook(&self) -> Option<&Command> {
             .and_then(|c| c.pre_hook.as_ref())
     }
 
+    /// Get the chain of message pre-send hooks.
+    pub fn get_message_pre_send_hooks(&self) -> &[PreSendHook] {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.pre_hooks.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Get the sent message copy behaviour if defined, otherwise
+    /// return the default one.
+    pub fn get_message_send_save_copy(&self) -> SaveCopyKind {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.save_copy.clone())
+            .unwrap_or_default()
+    }
+
+    /// Find the DKIM signature configuration.
+    #[cfg(feature = "dkim")]
+    pub fn find_message_send_dkim(&self) -> Option<&DkimConfig> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.dkim.as_ref())
+    }
+
+    /// Find the outbox folder alias, if configured.
+    pub fn find_message_send_outbox_folder_alias(&self) -> Option<String> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.outbox.as_deref())
+            .map(|folder| self.get_folder_alias(folder))
+    }
+
     /// Return `true` if a copy of sent messages should be saved in
     /// the sent folder.
+    ///
+    /// When the copy behaviour is [`SaveCopyKind::Auto`], no copy is
+    /// saved if the provider is known to already save sent messages.
     pub fn should_save_copy_sent_message(&self) -> bool {
+        match self.get_message_send_save_copy() {
+            SaveCopyKind::Always => true,
+            SaveCopyKind::Never => false,
+            SaveCopyKind::Auto => !is_sent_message_saved_by_provider(&self.email),
+        }
+    }
+
+    /// Get the rules choosing a transport per message.
+    pub fn get_message_send_routes(&self) -> Vec<SendRouteConfig> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.routes.clone())
+            .unwrap_or_default()
+    }
+
+    /// Find the name of the fallback transport, if any.
+    pub fn find_message_send_fallback(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.fallback.as_deref())
+    }
+
+    /// Find the backend retry configuration, if any.
+    pub fn find_retry(&self) -> Option<&RetryConfig> {
+        self.retry.as_ref()
+    }
+
+    /// Find the outbound rate limit configuration, if any.
+    pub fn find_message_send_rate_limit(&self) -> Option<&SendRateLimitConfig> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.rate_limit.as_ref())
+    }
+
+    /// Get the recipients automatically added to the `Cc` header of
+    /// messages being sent.
+    pub fn get_message_send_auto_cc(&self) -> Vec<String> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.auto_cc.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the recipients automatically added to the `Bcc` header of
+    /// messages being sent.
+    pub fn get_message_send_auto_bcc(&self) -> Vec<String> {
         self.message
             .as_ref()
             .and_then(|c| c.send.as_ref())
-            .and_then(|c| c.save_copy)
+            .and_then(|c| c.auto_bcc.clone())
             .unwrap_or_default()
     }
 
+    /// Return `true` if the backend should save a copy of sent
+    /// messages by itself, right after sending them.
+    pub fn should_auto_save_copy_sent_message(&self) -> bool {
+        let enabled = self
+            .message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.auto_save_copy)
+            .unwrap_or_default();
+
+        enabled && self.should_save_copy_sent_message()
+    }
+
+    /// Return `true` if line endings of messages being sent should be
+    /// normalized to CRLF.
+    pub fn should_normalize_sent_message_line_endings(&self) -> bool {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.normalize_line_endings)
+            .unwrap_or(true)
+    }
+
+    /// Return `true` if line endings of messages being added to a
+    /// remote folder should be normalized to CRLF.
+    pub fn should_normalize_added_message_line_endings(&self) -> bool {
+        self.message
+            .as_ref()
+            .and_then(|c| c.write.as_ref())
+            .and_then(|c| c.normalize_line_endings)
+            .unwrap_or(true)
+    }
+
     /// Generate a template interpreter with prefilled options from
     /// the current user account configuration.
     pub fn generate_tpl_interpreter(&self) -> MimeInterpreterBuilder {
@@ -729,4 +991,19 @@ fn rename_file_if_duplicate() {
             Ok(path) if path == PathBuf::from("downloads/file.ext_5.ext2")
         ));
     }
+
+    #[test]
+    fn is_vip_sender() {
+        let config = super::AccountConfig {
+            vip_senders: Some(vec!["boss@example.com".into(), "@vip.org".into()]),
+            ..Default::default()
+        };
+
+        assert!(config.is_vip_sender("boss@example.com"));
+        assert!(config.is_vip_sender("Boss@Example.com"));
+        assert!(config.is_vip_sender("anyone@VIP.org"));
+        assert!(!config.is_vip_sender("intern@example.com"));
+        assert!(!config.is_vip_sender("someone@notvip.org"));
+        assert!(!super::AccountConfig::default().is_vip_sender("boss@example.com"));
+    }
 }
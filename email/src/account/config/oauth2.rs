@@ -10,7 +10,7 @@
 
 #[doc(inline)]
 pub use super::{Error, Result};
-use crate::debug;
+use crate::{debug, network::config::NetworkConfig};
 
 /// The OAuth 2.0 configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -81,6 +81,11 @@ pub fn get_first_available_port() -> Result<u16> {
             .ok_or(Error::GetAvailablePortError)
     }
 
+    /// Get the three secrets of the OAuth 2.0 configuration.
+    pub fn secrets(&self) -> [&Secret; 3] {
+        [&self.client_secret, &self.access_token, &self.refresh_token]
+    }
+
     /// Resets the three secrets of the OAuth 2.0 configuration.
     pub async fn reset(&self) -> Result<()> {
         self.client_secret
@@ -104,6 +109,16 @@ pub async fn reset(&self) -> Result<()> {
     pub async fn configure(
         &self,
         get_client_secret: impl Fn() -> io::Result<String>,
+    ) -> Result<()> {
+        self.configure_with_network(None, get_client_secret).await
+    }
+
+    /// Same as [`OAuth2Config::configure`], except that token
+    /// requests honor the given network configuration.
+    pub async fn configure_with_network(
+        &self,
+        network: Option<&NetworkConfig>,
+        get_client_secret: impl Fn() -> io::Result<String>,
     ) -> Result<()> {
         if self.access_token.get().await.is_ok() {
             return Ok(());
@@ -153,6 +168,10 @@ pub async fn configure(
             auth_code_grant = auth_code_grant.with_pkce();
         }
 
+        if let Some(network) = network {
+            auth_code_grant = auth_code_grant.with_http_client(build_http_client(network).await?);
+        }
+
         for scope in self.scopes.clone() {
             auth_code_grant = auth_code_grant.with_scope(scope);
         }
@@ -186,6 +205,15 @@ pub async fn configure(
     /// Runs the refresh access token OAuth 2.0 flow by exchanging a
     /// refresh token with a new pair of access/refresh token.
     pub async fn refresh_access_token(&self) -> Result<String> {
+        self.refresh_access_token_with_network(None).await
+    }
+
+    /// Same as [`OAuth2Config::refresh_access_token`], except that
+    /// token requests honor the given network configuration.
+    pub async fn refresh_access_token_with_network(
+        &self,
+        network: Option<&NetworkConfig>,
+    ) -> Result<String> {
         let redirect_port = OAuth2Config::get_first_available_port()?;
 
         let client_secret = self
@@ -212,7 +240,14 @@ pub async fn refresh_access_token(&self) -> Result<String> {
             .await
             .map_err(Error::GetRefreshTokenOauthError)?;
 
-        let (access_token, refresh_token) = RefreshAccessToken::new()
+        let mut refresh_access_token = RefreshAccessToken::new();
+
+        if let Some(network) = network {
+            refresh_access_token =
+                refresh_access_token.with_http_client(build_http_client(network).await?);
+        }
+
+        let (access_token, refresh_token) = refresh_access_token
             .refresh_access_token(&client, refresh_token)
             .await
             .map_err(Error::RefreshAccessTokenOauthError)?;
@@ -242,6 +277,14 @@ pub async fn access_token(&self) -> Result<String> {
     }
 }
 
+/// Build an HTTP client honoring the given network configuration.
+async fn build_http_client(network: &NetworkConfig) -> Result<oauth::reqwest::Client> {
+    network
+        .build_http_client()
+        .await
+        .map_err(Error::BuildOauthHttpClientError)
+}
+
 /// Method for presenting an OAuth 2.0 bearer token to a service for
 /// authentication.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -251,16 +294,37 @@ pub async fn access_token(&self) -> Result<String> {
     serde(rename_all = "lowercase")
 )]
 pub enum OAuth2Method {
+    /// Pick the method automatically, based on the mechanisms
+    /// supported by the server.
     #[default]
+    #[cfg_attr(feature = "derive", serde(alias = "AUTO"))]
+    Auto,
     #[cfg_attr(feature = "derive", serde(alias = "XOAUTH2"))]
     XOAuth2,
     #[cfg_attr(feature = "derive", serde(alias = "OAUTHBEARER"))]
     OAuthBearer,
 }
 
+impl OAuth2Method {
+    /// Resolve the method against the mechanisms supported by the
+    /// server.
+    ///
+    /// The automatic method prefers XOAUTH2, which was the only
+    /// method supported before, and falls back to OAUTHBEARER when
+    /// XOAUTH2 is not supported. Other methods are returned as is.
+    pub fn resolve(&self, xoauth2_supported: bool, oauthbearer_supported: bool) -> Self {
+        match self {
+            Self::Auto if !xoauth2_supported && oauthbearer_supported => Self::OAuthBearer,
+            Self::Auto => Self::XOAuth2,
+            method => method.clone(),
+        }
+    }
+}
+
 impl fmt::Display for OAuth2Method {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Self::Auto => write!(f, "auto"),
             Self::XOAuth2 => write!(f, "XOAUTH2"),
             Self::OAuthBearer => write!(f, "OAUTHBEARER"),
         }
@@ -296,3 +360,19 @@ fn into_iter(self) -> Self::IntoIter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::OAuth2Method;
+
+    #[test]
+    fn resolve_method() {
+        let auto = OAuth2Method::Auto;
+        assert_eq!(auto.resolve(true, true), OAuth2Method::XOAuth2);
+        assert_eq!(auto.resolve(false, true), OAuth2Method::OAuthBearer);
+        assert_eq!(auto.resolve(false, false), OAuth2Method::XOAuth2);
+
+        let bearer = OAuth2Method::OAuthBearer;
+        assert_eq!(bearer.resolve(true, false), OAuth2Method::OAuthBearer);
+    }
+}
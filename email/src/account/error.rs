@@ -33,6 +33,9 @@ pub enum Error {
     #[cfg(feature = "oauth2")]
     #[error("cannot wait for oauth2 redirection error")]
     WaitForOauthRedirectionError(#[source] oauth::v2_0::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot build oauth2 http client")]
+    BuildOauthHttpClientError(#[source] crate::network::Error),
 
     #[error("cannot get oauth2 access token from global keyring")]
     GetAccessTokenOauthError(#[source] secret::Error),
@@ -104,6 +107,21 @@ pub enum Error {
     #[cfg(feature = "pgp-native")]
     #[error("cannot create keyring entry from key: {0}")]
     KeyringError(#[from] keyring::Error),
+    #[cfg(feature = "secrets-bundle")]
+    #[error("cannot export secret from keyring entry {1}")]
+    ExportSecretError(#[source] secret::Error, String),
+    #[cfg(feature = "secrets-bundle")]
+    #[error("cannot import secret into keyring entry {1}")]
+    ImportSecretError(#[source] secret::Error, String),
+    #[cfg(feature = "secrets-bundle")]
+    #[error("cannot encrypt secrets bundle")]
+    EncryptSecretsBundleError(#[source] pgp::Error),
+    #[cfg(feature = "secrets-bundle")]
+    #[error("cannot decrypt secrets bundle")]
+    DecryptSecretsBundleError(#[source] pgp::Error),
+    #[cfg(feature = "secrets-bundle")]
+    #[error("cannot parse secrets bundle: {0}")]
+    ParseSecretsBundleError(String),
     #[error("cannot find any MX record at {0}")]
     GetMxRecordNotFoundError(String),
     #[error("cannot find any mailconf TXT record at {0}")]
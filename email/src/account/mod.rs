@@ -3,10 +3,18 @@
 //! This module contains everything related to account configuration,
 //! plus everything you need to synchronize a remote account using a
 //! local Maildir backend. It also contains common code related to
-//! PGP.
+//! PGP, as well as the encrypted backup of account secrets.
 
 pub mod config;
+#[cfg(feature = "autoconfig")]
+crate::compat::deprecated_module!(
+    discover => crate::autoconfig,
+    "0.26.0",
+    "use `email::autoconfig` instead"
+);
 mod error;
+#[cfg(feature = "secrets-bundle")]
+pub mod secrets;
 #[cfg(feature = "sync")]
 pub mod sync;
 
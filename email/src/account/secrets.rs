@@ -0,0 +1,271 @@
+//! # Secrets bundle
+//!
+//! Module dedicated to account secrets backup. Secrets stored in the
+//! global keyring (passwords, OAuth 2.0 tokens) cannot be copied
+//! along with configuration files. The [`SecretsBundle`] gathers
+//! them into a single passphrase-encrypted bundle, which can be
+//! imported back into the keyring of another machine.
+
+use std::collections::BTreeMap;
+
+use secret::Secret;
+
+#[doc(inline)]
+pub use super::{Error, Result};
+use crate::debug;
+
+/// The first line of a serialized bundle, used to detect invalid
+/// bundles as well as to version the format.
+const HEADER: &str = "secrets-bundle v1";
+
+/// The secrets bundle.
+///
+/// The bundle maps keyring entry keys to their secret values. Only
+/// keyring-based secrets are exported: raw secrets are already part
+/// of the configuration, and command-based secrets cannot be
+/// imported back.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SecretsBundle {
+    entries: BTreeMap<String, String>,
+}
+
+impl SecretsBundle {
+    /// Create a new empty bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of secrets contained in the bundle.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return `true` if the bundle does not contain any secret.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the keyring entry keys of the bundle.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Get the secret value matching the given keyring entry key.
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.entries.get(key.as_ref()).map(String::as_str)
+    }
+
+    /// Insert the given secret value at the given keyring entry key.
+    pub fn insert(&mut self, key: impl ToString, secret: impl ToString) {
+        self.entries.insert(key.to_string(), secret.to_string());
+    }
+
+    /// Export the value of the given secret into the bundle.
+    ///
+    /// This function has no effect on non keyring-based secrets, or
+    /// on keyring entries without value.
+    pub async fn export_secret(&mut self, secret: &Secret) -> Result<()> {
+        let Secret::KeyringEntry(entry) = secret else {
+            return Ok(());
+        };
+
+        let key = &entry.key;
+
+        match secret.find().await {
+            Ok(Some(value)) => {
+                self.insert(key, value);
+                Ok(())
+            }
+            Ok(None) => {
+                debug!("no secret found in keyring matching `{key}`, skipping it");
+                Ok(())
+            }
+            Err(err) => Err(Error::ExportSecretError(err, key.clone())),
+        }
+    }
+
+    /// Export the values of the given secrets into the bundle.
+    ///
+    /// See [`SecretsBundle::export_secret`].
+    pub async fn export_secrets<'a>(
+        &mut self,
+        secrets: impl IntoIterator<Item = &'a Secret>,
+    ) -> Result<()> {
+        for secret in secrets {
+            self.export_secret(secret).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Import all the secrets of the bundle into the global keyring.
+    ///
+    /// Existing keyring entries matching the same keys are
+    /// overridden.
+    pub async fn import(&self) -> Result<()> {
+        for (key, value) in &self.entries {
+            debug!("importing secret into keyring at `{key}`");
+
+            Secret::try_new_keyring_entry(key.as_str())
+                .map_err(|err| Error::ImportSecretError(err, key.clone()))?
+                .set_only_keyring(value)
+                .await
+                .map_err(|err| Error::ImportSecretError(err, key.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt the bundle using the given passphrase.
+    ///
+    /// The result is an armored PGP message, which can be safely
+    /// written to disk.
+    pub async fn encrypt(&self, passphrase: impl ToString) -> Result<Vec<u8>> {
+        pgp::encrypt_with_passphrase(passphrase, self.to_bytes())
+            .await
+            .map_err(Error::EncryptSecretsBundleError)
+    }
+
+    /// Decrypt a bundle previously encrypted with the given
+    /// passphrase.
+    pub async fn decrypt(passphrase: impl ToString, encrypted_bytes: Vec<u8>) -> Result<Self> {
+        let bytes = pgp::decrypt_with_passphrase(passphrase, encrypted_bytes)
+            .await
+            .map_err(Error::DecryptSecretsBundleError)?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    /// Serialize the bundle, one tab-separated entry per line.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut contents = String::from(HEADER);
+
+        for (key, value) in &self.entries {
+            contents.push('\n');
+            contents.push_str(&escape(key));
+            contents.push('\t');
+            contents.push_str(&escape(value));
+        }
+
+        contents.push('\n');
+        contents.into_bytes()
+    }
+
+    /// Parse a bundle previously serialized with
+    /// [`SecretsBundle::to_bytes`].
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let contents = String::from_utf8_lossy(bytes);
+        let mut lines = contents.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(Error::ParseSecretsBundleError("invalid header".into()));
+        }
+
+        let mut bundle = Self::new();
+
+        for (i, line) in lines.enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let parsed = line
+                .split_once('\t')
+                .and_then(|(key, value)| Some((unescape(key)?, unescape(value)?)));
+
+            let Some((key, value)) = parsed else {
+                let reason = format!("invalid entry at line {}", i + 2);
+                return Err(Error::ParseSecretsBundleError(reason));
+            };
+
+            bundle.entries.insert(key, value);
+        }
+
+        Ok(bundle)
+    }
+}
+
+/// Escape backslashes, tabulations and line breaks so that entries
+/// fit on a single line.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Reverse [`escape`]. Returns `None` in case of invalid escape
+/// sequence.
+fn unescape(s: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, unescape, SecretsBundle};
+
+    fn bundle() -> SecretsBundle {
+        let mut bundle = SecretsBundle::new();
+        bundle.insert("account-imap-passwd", "p@ss\tw\\rd");
+        bundle.insert("account-smtp-oauth2-access-token", "token\nwith line break");
+        bundle
+    }
+
+    #[test]
+    fn escape_roundtrip() {
+        let s = "a\\b\tc\nd\re";
+        assert_eq!(escape(s), "a\\\\b\\tc\\nd\\re");
+        assert_eq!(unescape(&escape(s)).as_deref(), Some(s));
+        assert_eq!(unescape("invalid\\x"), None);
+        assert_eq!(unescape("invalid\\"), None);
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let bundle = bundle();
+        let bytes = bundle.to_bytes();
+
+        assert_eq!(SecretsBundle::from_bytes(&bytes).unwrap(), bundle);
+        assert!(SecretsBundle::from_bytes(b"invalid").is_err());
+        assert!(SecretsBundle::from_bytes(b"secrets-bundle v1\nno-tab\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn encrypt_then_decrypt() {
+        let bundle = bundle();
+        let encrypted = bundle.encrypt("passphrase").await.unwrap();
+
+        let decrypted = SecretsBundle::decrypt("passphrase", encrypted.clone())
+            .await
+            .unwrap();
+        assert_eq!(decrypted, bundle);
+
+        assert!(SecretsBundle::decrypt("invalid", encrypted).await.is_err());
+    }
+}
@@ -0,0 +1,785 @@
+//! # Cache
+//!
+//! Module dedicated to the read-through cache of backends.
+//!
+//! The [`CachedBackend`] wraps a [`Backend`] and stores envelopes and
+//! messages in a local [`CacheStore`]. Reads are served from the
+//! store, while stale folders are refreshed from the wrapped backend
+//! in the background. Writes go straight to the wrapped backend, then
+//! update or invalidate the store. Watching a folder through the
+//! cached backend keeps its store up to date.
+//!
+//! Features not covered by the cache are available from the wrapped
+//! backend, see [`CachedBackend::remote`].
+
+pub mod store;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+#[cfg(feature = "watch")]
+use tokio::sync::{
+    mpsc::{self, UnboundedSender},
+    oneshot::{Receiver, Sender},
+};
+
+#[doc(inline)]
+pub use self::store::CacheStore;
+use super::{context::BackendContext, kit, Backend, Result};
+#[cfg(feature = "watch")]
+use crate::envelope::watch::{WatchEnvelopes, WatchEvent};
+use crate::{
+    account::config::{AccountConfig, HasAccountConfig},
+    debug,
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flag, Flags},
+    folder::TRASH,
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, Messages,
+    },
+    runtime, warn, AnyResult,
+};
+
+/// The default maximum age of cached envelopes.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// The read-through caching backend.
+///
+/// Clones share the same store and the same wrapped backend.
+pub struct CachedBackend<C: BackendContext + 'static> {
+    remote: Arc<Backend<C>>,
+    store: Arc<CacheStore>,
+
+    /// The maximum age of cached envelopes before they get refreshed.
+    max_age: Duration,
+
+    /// The last refresh of cached folders.
+    refreshed: Arc<Mutex<HashMap<String, Instant>>>,
+
+    /// The folders being refreshed in the background.
+    refreshing: Arc<Mutex<HashSet<String>>>,
+
+    /// The generation of cached folders, bumped by every write.
+    ///
+    /// Refreshes started before a write may finish after it: their
+    /// envelopes are stale, which is why they are dropped when the
+    /// generation of the folder changed in the meantime.
+    generations: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl<C: BackendContext + 'static> CachedBackend<C> {
+    /// Wrap the given backend, using the given directory as store.
+    pub fn new(remote: impl Into<Arc<Backend<C>>>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            remote: remote.into(),
+            store: Arc::new(CacheStore::new(dir)),
+            max_age: DEFAULT_MAX_AGE,
+            refreshed: Default::default(),
+            refreshing: Default::default(),
+            generations: Default::default(),
+        }
+    }
+
+    /// Set the maximum age of cached envelopes, using the builder
+    /// pattern.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Get the wrapped backend.
+    pub fn remote(&self) -> &Backend<C> {
+        &self.remote
+    }
+
+    /// Get the store of the cache.
+    pub fn store(&self) -> &CacheStore {
+        &self.store
+    }
+
+    fn folder(&self, folder: &str) -> String {
+        kit::resolve_folder_alias(&self.remote.account_config, folder)
+    }
+
+    /// Return `true` if the cached envelopes of the given folder
+    /// need to be refreshed.
+    async fn is_stale(&self, folder: &str) -> bool {
+        match self.refreshed.lock().await.get(folder) {
+            Some(refreshed) => refreshed.elapsed() > self.max_age,
+            None => true,
+        }
+    }
+
+    /// Return the current generation of the given folder.
+    async fn generation(&self, folder: &str) -> u64 {
+        let generations = self.generations.lock().await;
+        generations.get(folder).copied().unwrap_or_default()
+    }
+
+    /// Bump the generation of the given folder.
+    ///
+    /// This must be done before updating the store, so that a
+    /// refresh saving its envelopes either happens before the update
+    /// or gets dropped.
+    async fn bump_generation(&self, folder: &str) {
+        let mut generations = self.generations.lock().await;
+        *generations.entry(folder.to_owned()).or_default() += 1;
+    }
+
+    /// Remove the cached envelopes of the given folders.
+    ///
+    /// Envelopes are removed rather than marked as stale, so that the
+    /// next listing waits for the wrapped backend and reflects the
+    /// write that caused the invalidation.
+    async fn invalidate(&self, folders: &[&str]) -> Result<()> {
+        for folder in folders {
+            let folder = self.folder(folder);
+            self.bump_generation(&folder).await;
+            self.refreshed.lock().await.remove(&folder);
+            self.store.remove_envelopes(&folder).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the cached envelopes of the given folder by the ones
+    /// of the wrapped backend.
+    ///
+    /// Envelopes are not saved if the folder has been written to
+    /// while listing the wrapped backend.
+    async fn refresh(&self, folder: &str) -> AnyResult<Envelopes> {
+        debug!("refreshing cached envelopes of folder {folder}");
+
+        let refreshed_at = Instant::now();
+        let generation = self.generation(folder).await;
+        let opts = ListEnvelopesOptions::default();
+        let envelopes = self.remote.list_envelopes(folder, opts).await?;
+
+        {
+            // the lock is held while saving, so that writes cannot
+            // bump the generation in between
+            let generations = self.generations.lock().await;
+
+            if generations.get(folder).copied().unwrap_or_default() != generation {
+                debug!("folder {folder} changed while refreshing, dropping envelopes");
+                return Ok(envelopes);
+            }
+
+            self.store.save_envelopes(folder, &envelopes).await?;
+        }
+
+        self.refreshed
+            .lock()
+            .await
+            .insert(folder.to_owned(), refreshed_at);
+
+        Ok(envelopes)
+    }
+
+    /// Refresh the cached envelopes of the given folder in the
+    /// background, unless a refresh is already running.
+    async fn spawn_refresh(&self, folder: String) {
+        if !self.refreshing.lock().await.insert(folder.clone()) {
+            return;
+        }
+
+        let this = self.clone();
+
+        runtime::spawn(async move {
+            if let Err(_err) = this.refresh(&folder).await {
+                warn!("cannot refresh cached envelopes of folder {folder}: {_err}");
+                debug!("{_err:?}");
+            }
+
+            this.refreshing.lock().await.remove(&folder);
+        });
+    }
+
+    /// Update the flags of the cached envelopes matching the given
+    /// identifiers.
+    async fn update_flags(
+        &self,
+        folder: &str,
+        id: &Id,
+        update: impl Fn(&mut Flags) + Send + Sync,
+    ) -> Result<()> {
+        let folder = self.folder(folder);
+        let ids: HashSet<&str> = id.iter().collect();
+
+        self.bump_generation(&folder).await;
+
+        self.store
+            .update_envelopes(&folder, |envelopes| {
+                envelopes
+                    .iter_mut()
+                    .filter(|envelope| ids.contains(envelope.id.as_str()))
+                    .for_each(|envelope| update(&mut envelope.flags));
+            })
+            .await
+    }
+
+    /// Remove the cached envelopes and messages matching the given
+    /// identifiers.
+    async fn remove_messages_from_store(&self, folder: &str, id: &Id) -> Result<()> {
+        let folder = self.folder(folder);
+        let ids: HashSet<&str> = id.iter().collect();
+
+        self.bump_generation(&folder).await;
+
+        self.store
+            .update_envelopes(&folder, |envelopes| {
+                envelopes.retain(|envelope| !ids.contains(envelope.id.as_str()));
+            })
+            .await?;
+
+        for id in ids {
+            self.store.remove_message(&folder, id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply the given watch event to the cached envelopes of the
+    /// given folder.
+    #[cfg(feature = "watch")]
+    async fn apply_event(&self, folder: &str, event: &WatchEvent) -> Result<()> {
+        self.bump_generation(folder).await;
+
+        match event {
+            WatchEvent::Received(envelope) => {
+                self.store
+                    .update_envelopes(folder, |envelopes| {
+                        envelopes.retain(|e| e.id != envelope.id);
+                        envelopes.push(envelope.clone());
+                    })
+                    .await
+            }
+            WatchEvent::FlagsChanged { envelope, .. } => {
+                self.store
+                    .update_envelopes(folder, |envelopes| {
+                        envelopes
+                            .iter_mut()
+                            .filter(|e| e.id == envelope.id)
+                            .for_each(|e| e.flags = envelope.flags.clone());
+                    })
+                    .await
+            }
+            WatchEvent::Expunged(envelope) => {
+                self.store
+                    .update_envelopes(folder, |envelopes| {
+                        envelopes.retain(|e| e.id != envelope.id);
+                    })
+                    .await?;
+                self.store.remove_message(folder, &envelope.id).await
+            }
+        }
+    }
+
+    /// Watch the given folder, applying events to the store then
+    /// forwarding them to the given sender, if any.
+    #[cfg(feature = "watch")]
+    async fn watch(
+        &self,
+        folder: &str,
+        forward: Option<UnboundedSender<WatchEvent>>,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let this = self.clone();
+        let cached_folder = self.folder(folder);
+
+        let consumer = runtime::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if let Err(_err) = this.apply_event(&cached_folder, &event).await {
+                    warn!("cannot apply watch event to cache, invalidating it: {_err}");
+                    debug!("{_err:?}");
+                    this.refreshed.lock().await.remove(&cached_folder);
+                }
+
+                if let Some(forward) = &forward {
+                    let _ = forward.send(event);
+                }
+            }
+        });
+
+        let res = self
+            .remote
+            .watch_envelope_events(folder, events, wait_for_shutdown_request, shutdown)
+            .await;
+
+        // the sender is dropped by the wrapped backend once watching
+        // stops, which ends the consumer
+        if let Err(_err) = consumer.await {
+            debug!("cannot join watch events consumer: {_err}");
+        }
+
+        res
+    }
+}
+
+impl<C: BackendContext + 'static> Clone for CachedBackend<C> {
+    fn clone(&self) -> Self {
+        Self {
+            remote: self.remote.clone(),
+            store: self.store.clone(),
+            max_age: self.max_age,
+            refreshed: self.refreshed.clone(),
+            refreshing: self.refreshing.clone(),
+            generations: self.generations.clone(),
+        }
+    }
+}
+
+impl<C: BackendContext + 'static> HasAccountConfig for CachedBackend<C> {
+    fn account_config(&self) -> &AccountConfig {
+        &self.remote.account_config
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> ListEnvelopes for CachedBackend<C> {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        // NOTE: the cache only holds whole folders, which is why
+        // incremental listings and filtered listings are served by
+        // the wrapped backend
+        let filtered = opts
+            .query
+            .as_ref()
+            .is_some_and(|query| query.filter.is_some());
+
+        if opts.changed_since.is_some() || filtered {
+            return self.remote.list_envelopes(folder, opts).await;
+        }
+
+        let folder = self.folder(folder);
+
+        let mut envelopes = match self.store.load_envelopes(&folder).await? {
+            Some(envelopes) => {
+                if self.is_stale(&folder).await {
+                    self.spawn_refresh(folder).await;
+                }
+                envelopes
+            }
+            None => self.refresh(&folder).await?,
+        };
+
+        kit::paginate_envelopes(&mut envelopes, &opts)?;
+
+        if envelopes.next_cursor().is_none() {
+            envelopes.set_next_cursor(opts.next_cursor(&envelopes));
+        }
+
+        envelopes.mark_vip_senders(&self.remote.account_config);
+
+        Ok(envelopes)
+    }
+}
+
+#[cfg(feature = "watch")]
+#[async_trait]
+impl<C: BackendContext + 'static> WatchEnvelopes for CachedBackend<C> {
+    async fn watch_envelopes(
+        &self,
+        folder: &str,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        self.watch(folder, None, wait_for_shutdown_request, shutdown)
+            .await
+    }
+
+    async fn watch_envelope_events(
+        &self,
+        folder: &str,
+        events: UnboundedSender<WatchEvent>,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        self.watch(folder, Some(events), wait_for_shutdown_request, shutdown)
+            .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> AddFlags for CachedBackend<C> {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        self.remote.add_flags(folder, id, flags).await?;
+        self.update_flags(folder, id, |f| f.extend(flags.iter().cloned()))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> SetFlags for CachedBackend<C> {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        self.remote.set_flags(folder, id, flags).await?;
+        self.update_flags(folder, id, |f| *f = flags.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> RemoveFlags for CachedBackend<C> {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        self.remote.remove_flags(folder, id, flags).await?;
+        self.update_flags(folder, id, |f| f.retain(|flag| !flags.contains(flag)))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> AddMessage for CachedBackend<C> {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        let id = self
+            .remote
+            .add_message_with_flags(folder, msg, flags)
+            .await?;
+        self.invalidate(&[folder]).await?;
+        Ok(id)
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<SingleId>> {
+        let ids = self.remote.add_messages_with_flags(folder, msgs).await?;
+        self.invalidate(&[folder]).await?;
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> PeekMessages for CachedBackend<C> {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let cached_folder = self.folder(folder);
+        let mut msgs = Vec::new();
+
+        for id in id.iter() {
+            if let Some(msg) = self.store.get_message(&cached_folder, id).await? {
+                msgs.push(msg);
+                continue;
+            }
+
+            let fetched = self.remote.peek_messages(folder, &Id::single(id)).await?;
+
+            let Some(msg) = fetched.first() else {
+                debug!("cannot find message {id} in folder {folder}, skipping it");
+                continue;
+            };
+
+            let msg = msg.raw()?.to_vec();
+            self.store.save_message(&cached_folder, id, &msg).await?;
+            msgs.push(msg);
+        }
+
+        Ok(Messages::from(msgs))
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> GetMessages for CachedBackend<C> {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let msgs = self.peek_messages(folder, id).await?;
+
+        // messages are already read, which is why failing to mark
+        // them as seen only emits a warning
+        if let Err(_err) = self.add_flag(folder, id, Flag::Seen).await {
+            warn!("cannot mark messages as seen: {_err}");
+            debug!("{_err:?}");
+        }
+
+        Ok(msgs)
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> CopyMessages for CachedBackend<C> {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        self.remote
+            .copy_messages(from_folder, to_folder, id)
+            .await?;
+        self.invalidate(&[to_folder]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> MoveMessages for CachedBackend<C> {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        self.remote
+            .move_messages(from_folder, to_folder, id)
+            .await?;
+        self.remove_messages_from_store(from_folder, id).await?;
+        self.invalidate(&[to_folder]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> DeleteMessages for CachedBackend<C> {
+    async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        self.remote.delete_messages(folder, id).await?;
+        // deleted messages are either moved to the trash folder or
+        // flagged as deleted, depending on the account
+        self.invalidate(&[folder, TRASH]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext + 'static> RemoveMessages for CachedBackend<C> {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        self.remote.remove_messages(folder, id).await?;
+        self.remove_messages_from_store(folder, id).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::sync::mpsc::{self, UnboundedSender};
+
+    use super::CachedBackend;
+    use crate::{
+        account::config::AccountConfig,
+        backend::{
+            context::{BackendContext, BackendContextBuilder},
+            feature::BackendFeature,
+            BackendBuilder,
+        },
+        envelope::{
+            list::{ListEnvelopes, ListEnvelopesOptions},
+            Envelope, Envelopes, Id, SingleId,
+        },
+        flag::Flags,
+        message::{add::AddMessage, r#move::MoveMessages, remove::RemoveMessages},
+        AnyResult,
+    };
+
+    type Folders = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Hooks slowing down listings.
+    #[derive(Clone)]
+    struct Slow {
+        /// Notified once a listing has read the messages.
+        listed: UnboundedSender<()>,
+
+        /// Locked by listings before returning.
+        gate: Arc<tokio::sync::Mutex<()>>,
+    }
+
+    #[derive(Clone)]
+    struct TestContextBuilder(Folders, Option<Slow>);
+
+    struct TestContext(Folders, Option<Slow>);
+
+    impl BackendContext for TestContext {}
+
+    struct TestFeatures(Folders, Option<Slow>);
+
+    #[async_trait]
+    impl ListEnvelopes for TestFeatures {
+        async fn list_envelopes(
+            &self,
+            folder: &str,
+            _opts: ListEnvelopesOptions,
+        ) -> AnyResult<Envelopes> {
+            let envelopes: Envelopes = {
+                let messages = self.0.lock().unwrap();
+                messages
+                    .iter()
+                    .filter(|(f, _)| f == folder)
+                    .map(|(_, id)| Envelope {
+                        id: id.clone(),
+                        ..Default::default()
+                    })
+                    .collect()
+            };
+
+            if let Some(slow) = &self.1 {
+                let _ = slow.listed.send(());
+                let _gate = slow.gate.lock().await;
+            }
+
+            Ok(envelopes)
+        }
+    }
+
+    #[async_trait]
+    impl AddMessage for TestFeatures {
+        async fn add_message_with_flags(
+            &self,
+            folder: &str,
+            _msg: &[u8],
+            _flags: &Flags,
+        ) -> AnyResult<SingleId> {
+            let mut messages = self.0.lock().unwrap();
+            let id = (messages.len() + 1).to_string();
+            messages.push((folder.to_owned(), id.clone()));
+            Ok(id.into())
+        }
+    }
+
+    #[async_trait]
+    impl MoveMessages for TestFeatures {
+        async fn move_messages(&self, from: &str, to: &str, id: &Id) -> AnyResult<()> {
+            let ids: Vec<&str> = id.iter().collect();
+            let mut messages = self.0.lock().unwrap();
+            messages
+                .iter_mut()
+                .filter(|(f, id)| f == from && ids.contains(&id.as_str()))
+                .for_each(|(f, _)| *f = to.to_owned());
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl RemoveMessages for TestFeatures {
+        async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+            let ids: Vec<&str> = id.iter().collect();
+            let mut messages = self.0.lock().unwrap();
+            messages.retain(|(f, id)| f != folder || !ids.contains(&id.as_str()));
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl BackendContextBuilder for TestContextBuilder {
+        type Context = TestContext;
+
+        fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelopes>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestFeatures(ctx.0.clone(), ctx.1.clone())))
+            }))
+        }
+
+        fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestFeatures(ctx.0.clone(), ctx.1.clone())))
+            }))
+        }
+
+        fn move_messages(&self) -> Option<BackendFeature<Self::Context, dyn MoveMessages>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestFeatures(ctx.0.clone(), ctx.1.clone())))
+            }))
+        }
+
+        fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMessages>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestFeatures(ctx.0.clone(), ctx.1.clone())))
+            }))
+        }
+
+        async fn build(self) -> AnyResult<Self::Context> {
+            Ok(TestContext(self.0, self.1))
+        }
+    }
+
+    async fn ids(backend: &CachedBackend<TestContext>, folder: &str) -> Vec<String> {
+        let opts = ListEnvelopesOptions::default();
+        let envelopes = backend.list_envelopes(folder, opts).await.unwrap();
+        let mut ids: Vec<String> = envelopes.iter().map(|e| e.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let messages = Folders::default();
+        let config = Arc::new(AccountConfig::default());
+        let ctx = TestContextBuilder(messages.clone(), None);
+        let remote = BackendBuilder::new(config, ctx).build().await.unwrap();
+        let backend = CachedBackend::new(remote, dir.path());
+
+        assert!(ids(&backend, "INBOX").await.is_empty());
+        assert!(ids(&backend, "Archives").await.is_empty());
+
+        // added messages are listed right away
+        backend.add_message("INBOX", b"").await.unwrap();
+        backend.add_message("INBOX", b"").await.unwrap();
+        backend.add_message("INBOX", b"").await.unwrap();
+        assert_eq!(ids(&backend, "INBOX").await, ["1", "2", "3"]);
+
+        backend
+            .move_messages("INBOX", "Archives", &Id::single("1"))
+            .await
+            .unwrap();
+        backend
+            .remove_messages("INBOX", &Id::single("2"))
+            .await
+            .unwrap();
+        assert_eq!(ids(&backend, "Archives").await, ["1"]);
+
+        // moved and removed messages are dropped from the cached
+        // envelopes, without listing the wrapped backend
+        messages.lock().unwrap().clear();
+        assert_eq!(ids(&backend, "INBOX").await, ["3"]);
+    }
+
+    #[tokio::test]
+    async fn refresh_during_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let (listed, mut listings) = mpsc::unbounded_channel();
+        let gate = Arc::new(tokio::sync::Mutex::new(()));
+        let slow = Slow {
+            listed,
+            gate: gate.clone(),
+        };
+        let config = Arc::new(AccountConfig::default());
+        let ctx = TestContextBuilder(Folders::default(), Some(slow));
+        let remote = BackendBuilder::new(config, ctx).build().await.unwrap();
+        let backend = CachedBackend::new(remote, dir.path()).with_max_age(Duration::ZERO);
+
+        assert!(ids(&backend, "INBOX").await.is_empty());
+        listings.recv().await.unwrap();
+
+        // a background refresh lists the empty folder, then waits
+        let closed = gate.lock().await;
+        assert!(ids(&backend, "INBOX").await.is_empty());
+        listings.recv().await.unwrap();
+
+        // a message is added while the refresh is running
+        backend.add_message("INBOX", b"").await.unwrap();
+
+        // the refresh finishes after the write
+        drop(closed);
+        while !backend.refreshing.lock().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        // the stale envelopes of the refresh have been dropped
+        assert_eq!(ids(&backend, "INBOX").await, ["1"]);
+    }
+}
@@ -0,0 +1,325 @@
+//! Module dedicated to the local cache store.
+//!
+//! The store is a directory containing one sub-directory per folder.
+//! Each folder directory contains an `envelopes` index and a
+//! `messages` directory holding raw messages, named after their
+//! percent-encoded identifier.
+//!
+//! Each line of the index contains the fields of an envelope,
+//! separated by tabulations and percent-encoded: identifier, flags,
+//! Message-ID, In-Reply-To, References, From name and address, To
+//! name and address, subject, date and attachment marker.
+//! Backend-specific extensions are not cached.
+
+use std::{io, path::PathBuf};
+
+use chrono::DateTime;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    backend::{Error, Result},
+    envelope::{Address, Envelope, Envelopes},
+    flag::{Flag, Flags},
+};
+
+/// The name of the envelopes index file of a folder.
+const ENVELOPES_FILE: &str = "envelopes";
+
+/// The name of the messages directory of a folder.
+const MESSAGES_DIR: &str = "messages";
+
+/// The local cache store.
+#[derive(Debug)]
+pub struct CacheStore {
+    dir: PathBuf,
+
+    /// The lock preventing concurrent updates of envelopes indexes
+    /// from overriding each other.
+    lock: Mutex<()>,
+}
+
+impl CacheStore {
+    /// Create a new store located in the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn folder_dir(&self, folder: &str) -> PathBuf {
+        self.dir.join(urlencoding::encode(folder).as_ref())
+    }
+
+    fn message_path(&self, folder: &str, id: &str) -> PathBuf {
+        self.folder_dir(folder)
+            .join(MESSAGES_DIR)
+            .join(urlencoding::encode(id).as_ref())
+    }
+
+    /// Load the cached envelopes of the given folder.
+    ///
+    /// Returns `None` if the folder has never been cached.
+    pub async fn load_envelopes(&self, folder: &str) -> Result<Option<Envelopes>> {
+        let path = self.folder_dir(folder).join(ENVELOPES_FILE);
+
+        match fs::read_to_string(&path).await {
+            Ok(content) => Ok(Some(content.lines().filter_map(parse_envelope).collect())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::ReadCacheError(err, path)),
+        }
+    }
+
+    /// Replace the cached envelopes of the given folder.
+    pub async fn save_envelopes(&self, folder: &str, envelopes: &Envelopes) -> Result<()> {
+        let _lock = self.lock.lock().await;
+        self.write_envelopes(folder, envelopes).await
+    }
+
+    /// Update the cached envelopes of the given folder using the
+    /// given function.
+    ///
+    /// Does nothing if the folder has never been cached.
+    pub async fn update_envelopes(
+        &self,
+        folder: &str,
+        update: impl FnOnce(&mut Envelopes) + Send,
+    ) -> Result<()> {
+        let _lock = self.lock.lock().await;
+
+        if let Some(mut envelopes) = self.load_envelopes(folder).await? {
+            update(&mut envelopes);
+            self.write_envelopes(folder, &envelopes).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the cached envelopes of the given folder, so that the
+    /// folder is considered as never cached.
+    pub async fn remove_envelopes(&self, folder: &str) -> Result<()> {
+        let _lock = self.lock.lock().await;
+        let path = self.folder_dir(folder).join(ENVELOPES_FILE);
+
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::WriteCacheError(err, path)),
+        }
+    }
+
+    async fn write_envelopes(&self, folder: &str, envelopes: &Envelopes) -> Result<()> {
+        let dir = self.folder_dir(folder);
+        let path = dir.join(ENVELOPES_FILE);
+
+        fs::create_dir_all(&dir)
+            .await
+            .map_err(|err| Error::WriteCacheError(err, dir.clone()))?;
+
+        let content: String = envelopes.iter().map(format_envelope).collect();
+
+        // the index is written to a temporary file first, so that
+        // readers never see a partially written index
+        let tmp_path = dir.join(format!("{ENVELOPES_FILE}.tmp"));
+        fs::write(&tmp_path, content)
+            .await
+            .map_err(|err| Error::WriteCacheError(err, tmp_path.clone()))?;
+        fs::rename(&tmp_path, &path)
+            .await
+            .map_err(|err| Error::WriteCacheError(err, path))
+    }
+
+    /// Get the cached raw message matching the given identifier.
+    pub async fn get_message(&self, folder: &str, id: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.message_path(folder, id);
+
+        match fs::read(&path).await {
+            Ok(msg) => Ok(Some(msg)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::ReadCacheError(err, path)),
+        }
+    }
+
+    /// Cache the given raw message.
+    pub async fn save_message(&self, folder: &str, id: &str, msg: &[u8]) -> Result<()> {
+        let path = self.message_path(folder, id);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .await
+                .map_err(|err| Error::WriteCacheError(err, dir.to_owned()))?;
+        }
+
+        fs::write(&path, msg)
+            .await
+            .map_err(|err| Error::WriteCacheError(err, path))
+    }
+
+    /// Remove the cached raw message matching the given identifier.
+    pub async fn remove_message(&self, folder: &str, id: &str) -> Result<()> {
+        let path = self.message_path(folder, id);
+
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::WriteCacheError(err, path)),
+        }
+    }
+}
+
+/// Format the given envelope as a line of the envelopes index.
+fn format_envelope(envelope: &Envelope) -> String {
+    let encode = |field: &str| urlencoding::encode(field).into_owned();
+    let encode_all = |fields: &mut dyn Iterator<Item = String>| {
+        fields
+            .map(|field| encode(&field))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let fields = [
+        encode(&envelope.id),
+        encode_all(&mut envelope.flags.iter().map(Flag::to_string)),
+        encode(&envelope.message_id),
+        encode(envelope.in_reply_to.as_deref().unwrap_or_default()),
+        encode_all(&mut envelope.references.iter().cloned()),
+        encode(envelope.from.name.as_deref().unwrap_or_default()),
+        encode(&envelope.from.addr),
+        encode(envelope.to.name.as_deref().unwrap_or_default()),
+        encode(&envelope.to.addr),
+        encode(&envelope.subject),
+        encode(&envelope.date.to_rfc3339()),
+        String::from(if envelope.has_attachment { "1" } else { "0" }),
+    ];
+
+    fields.join("\t") + "\n"
+}
+
+/// Parse the given line of the envelopes index.
+///
+/// Parsing is lenient: invalid lines are ignored.
+fn parse_envelope(line: &str) -> Option<Envelope> {
+    let decode = |field: &str| urlencoding::decode(field).ok().map(|f| f.into_owned());
+    let decode_all =
+        |fields: &str| -> Vec<String> { fields.split_whitespace().filter_map(decode).collect() };
+    let some = |field: String| Some(field).filter(|field| !field.is_empty());
+
+    let mut fields = line.split('\t');
+    let mut next = || fields.next();
+
+    let id = decode(next()?).filter(|id| !id.is_empty())?;
+    let flags: Flags = decode_all(next()?)
+        .iter()
+        .map(|flag| Flag::from(flag.as_str()))
+        .collect();
+    let message_id = decode(next()?)?;
+    let in_reply_to = some(decode(next()?)?);
+    let references = decode_all(next()?);
+    let from = Address::new(some(decode(next()?)?), decode(next()?)?);
+    let to = Address::new(some(decode(next()?)?), decode(next()?)?);
+    let subject = decode(next()?)?;
+    let date = DateTime::parse_from_rfc3339(&decode(next()?)?).ok()?;
+    let has_attachment = next()? == "1";
+
+    if next().is_some() {
+        return None;
+    }
+
+    Some(Envelope {
+        id,
+        message_id,
+        in_reply_to,
+        references,
+        flags,
+        from,
+        to,
+        subject,
+        date,
+        has_attachment,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{format_envelope, parse_envelope, CacheStore};
+    use crate::{
+        envelope::{Address, Envelope, Envelopes},
+        flag::{Flag, Flags},
+    };
+
+    fn envelope() -> Envelope {
+        Envelope {
+            id: "42".into(),
+            message_id: "<b@localhost>".into(),
+            in_reply_to: Some("<a@localhost>".into()),
+            references: vec!["<a@localhost>".into()],
+            flags: Flags::from_iter([Flag::Seen, Flag::Custom("$label 1".into())]),
+            from: Address::new(Some("Alice\tDoe".into()), "alice@localhost"),
+            to: Address::new(None, "bob@localhost"),
+            subject: "Re: hello\tworld".into(),
+            date: DateTime::parse_from_rfc3339("2024-01-02T03:04:05+01:00").unwrap(),
+            has_attachment: true,
+            ..Default::default()
+        }
+    }
+
+    fn assert_same(a: &Envelope, b: &Envelope) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.message_id, b.message_id);
+        assert_eq!(a.in_reply_to, b.in_reply_to);
+        assert_eq!(a.references, b.references);
+        assert_eq!(a.flags, b.flags);
+        assert_eq!(a.from.name, b.from.name);
+        assert_eq!(a.from.addr, b.from.addr);
+        assert_eq!(a.to.name, b.to.name);
+        assert_eq!(a.to.addr, b.to.addr);
+        assert_eq!(a.subject, b.subject);
+        assert_eq!(a.date, b.date);
+        assert_eq!(a.has_attachment, b.has_attachment);
+    }
+
+    #[test]
+    fn envelope_lines() {
+        let envelope = envelope();
+        let line = format_envelope(&envelope);
+        let parsed = parse_envelope(line.trim_end()).unwrap();
+        assert_same(&envelope, &parsed);
+
+        assert!(parse_envelope("").is_none());
+        assert!(parse_envelope("42\tseen").is_none());
+    }
+
+    #[tokio::test]
+    async fn store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CacheStore::new(dir.path());
+
+        assert!(store.load_envelopes("INBOX").await.unwrap().is_none());
+
+        let envelopes = Envelopes::from_iter([envelope()]);
+        store.save_envelopes("INBOX", &envelopes).await.unwrap();
+        store
+            .update_envelopes("INBOX", |envelopes| envelopes[0].flags.clear())
+            .await
+            .unwrap();
+
+        let loaded = store.load_envelopes("INBOX").await.unwrap().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded[0].flags.is_empty());
+
+        assert!(store.get_message("INBOX", "42").await.unwrap().is_none());
+        store.save_message("INBOX", "42", b"Hello!").await.unwrap();
+        let msg = store.get_message("INBOX", "42").await.unwrap();
+        assert_eq!(msg.as_deref(), Some(b"Hello!".as_slice()));
+
+        store.remove_message("INBOX", "42").await.unwrap();
+        assert!(store.get_message("INBOX", "42").await.unwrap().is_none());
+
+        store.remove_envelopes("INBOX").await.unwrap();
+        assert!(store.load_envelopes("INBOX").await.unwrap().is_none());
+        store.remove_envelopes("INBOX").await.unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! # Backend cancellation
+//!
+//! Module dedicated to the cancellation of backend features. Long
+//! operations (large fetches, watching, synchronization) can be
+//! aborted from the outside using a [`CancellationToken`]:
+//!
+//! - for all the features of a backend, see
+//!   [`BackendBuilder::with_cancellation_token`](super::BackendBuilder::with_cancellation_token)
+//!
+//! - for a single call, see [`cancellable`]
+//!
+//! Cancelled calls fail with [`Error::FeatureCancelledError`].
+//! Cancelling the token of a backend cancels its child tokens as
+//! well, see [`CancellationToken::child_token`].
+//!
+//! Cancelled calls are dropped in the middle of their commands, like
+//! timed out ones: the IMAP backend re-connects such clients before
+//! using them again, see `imap::ImapClientGuard`.
+
+use std::future::Future;
+
+use async_trait::async_trait;
+#[cfg(feature = "watch")]
+use tokio::{
+    sync::oneshot::{self, Receiver},
+    task::JoinHandle,
+};
+#[doc(inline)]
+pub use tokio_util::sync::CancellationToken;
+
+use super::{
+    feature::{BackendFeatureDescriptor, BackendFeatureLayer, Next},
+    Error,
+};
+#[cfg(feature = "watch")]
+use crate::runtime;
+use crate::{debug, AnyResult};
+
+/// Run the given feature call until it completes or until the given
+/// token is cancelled.
+///
+/// The call is dropped as soon as the token is cancelled.
+pub async fn cancellable<T>(
+    token: &CancellationToken,
+    feature: &'static str,
+    f: impl Future<Output = AnyResult<T>>,
+) -> AnyResult<T> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => {
+            debug!("{feature} cancelled");
+            Err(Error::FeatureCancelledError(feature).into())
+        }
+        res = f => res,
+    }
+}
+
+/// Forward the given shutdown request, or the cancellation of the
+/// given token, to the returned receiver.
+///
+/// Watching features stop cleanly on shutdown requests (for example
+/// IMAP IDLE commands are terminated), which is why they are not
+/// dropped on cancellation. The returned task should be aborted once
+/// watching stops.
+#[cfg(feature = "watch")]
+pub(crate) fn forward_shutdown_request(
+    token: CancellationToken,
+    wait_for_shutdown_request: Receiver<()>,
+) -> (Receiver<()>, JoinHandle<()>) {
+    let (shutdown_request, wait_for_forwarded_request) = oneshot::channel();
+
+    let task = runtime::spawn(async move {
+        tokio::select! {
+            _ = token.cancelled() => {
+                debug!("watching cancelled");
+            }
+            _ = wait_for_shutdown_request => (),
+        }
+
+        let _ = shutdown_request.send(());
+    });
+
+    (wait_for_forwarded_request, task)
+}
+
+/// The cancellation backend feature layer.
+#[derive(Clone, Debug)]
+pub struct CancellationLayer {
+    token: CancellationToken,
+}
+
+impl CancellationLayer {
+    /// Create a new cancellation layer using the given token.
+    pub fn new(token: CancellationToken) -> Self {
+        Self { token }
+    }
+}
+
+#[async_trait]
+impl BackendFeatureLayer for CancellationLayer {
+    async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+        cancellable(&self.token, feature.name, next.run()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{cancellable, CancellationToken};
+    use crate::{
+        backend::kit::{classify_error, ErrorClass},
+        runtime,
+    };
+
+    #[tokio::test]
+    async fn cancel() {
+        let token = CancellationToken::new();
+
+        let res = cancellable(&token, "list_folders", async { Ok(42) }).await;
+        assert_eq!(res.unwrap(), 42);
+
+        let child = token.child_token();
+        let cancel = {
+            let token = token.clone();
+            runtime::spawn(async move {
+                runtime::sleep(Duration::from_millis(10)).await;
+                token.cancel();
+            })
+        };
+
+        let res = cancellable(&child, "list_folders", async {
+            runtime::sleep(Duration::from_secs(60)).await;
+            Ok(42)
+        })
+        .await;
+        cancel.await.unwrap();
+
+        let err = res.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot call backend feature list_folders: cancelled"
+        );
+        assert_eq!(classify_error(&err), ErrorClass::Permanent);
+    }
+}
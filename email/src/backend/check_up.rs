@@ -0,0 +1,305 @@
+//! # Backend check-up
+//!
+//! Module dedicated to the diagnostics of backends. The
+//! [`CheckUp`](super::feature::CheckUp) feature returns a
+//! [`CheckUpReport`] made of [`CheckUpProbe`]s, one per step required
+//! to use the backend (DNS resolution, TCP connection, TLS handshake,
+//! authentication…). Each probe holds its own status and latency, so
+//! that account setup wizards and status pages can tell exactly which
+//! step fails, and how slow each step is.
+
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "network")]
+use crate::network::{self, config::NetworkConfig, stream::SharedStreamConnector, tls::TlsOptions};
+
+use super::Error;
+use crate::{debug, AnyResult};
+
+/// The kind of a check-up probe.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CheckUpProbeKind {
+    /// The resolution of the server host name.
+    Dns,
+
+    /// The TCP connection to the server.
+    Tcp,
+
+    /// The TLS handshake with the server.
+    Tls,
+
+    /// The authentication to the server.
+    Authentication,
+
+    /// The detection of the capabilities required by the backend.
+    Capabilities,
+
+    /// The access to a folder (mailbox, maildir, database…).
+    FolderAccess,
+
+    /// The execution of a command by an already authenticated
+    /// session.
+    Session,
+
+    /// The execution of a local command.
+    Command,
+}
+
+impl fmt::Display for CheckUpProbeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns => write!(f, "DNS resolution"),
+            Self::Tcp => write!(f, "TCP connection"),
+            Self::Tls => write!(f, "TLS handshake"),
+            Self::Authentication => write!(f, "authentication"),
+            Self::Capabilities => write!(f, "capabilities"),
+            Self::FolderAccess => write!(f, "folder access"),
+            Self::Session => write!(f, "session"),
+            Self::Command => write!(f, "command"),
+        }
+    }
+}
+
+/// The status of a check-up probe.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CheckUpStatus {
+    /// The probe passed.
+    Passed,
+
+    /// The probe has not been run, for the given reason.
+    Skipped(String),
+
+    /// The probe failed with the given error message.
+    Failed(String),
+}
+
+/// The check-up probe.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheckUpProbe {
+    /// The kind of the probe.
+    pub kind: CheckUpProbeKind,
+
+    /// The status of the probe.
+    pub status: CheckUpStatus,
+
+    /// The time taken by the probe.
+    ///
+    /// Skipped probes have a zero latency.
+    pub latency: Duration,
+}
+
+impl CheckUpProbe {
+    /// Return `true` if the probe failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status, CheckUpStatus::Failed(_))
+    }
+}
+
+/// The check-up report.
+///
+/// Probes are kept in the order they have been run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CheckUpReport {
+    /// The probes of the report.
+    pub probes: Vec<CheckUpProbe>,
+}
+
+impl CheckUpReport {
+    /// Create a new empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the given probe, then record its status and latency.
+    ///
+    /// The output of the probe is returned if it passed.
+    pub async fn probe<T, E: ToString>(
+        &mut self,
+        kind: CheckUpProbeKind,
+        f: impl Future<Output = Result<T, E>>,
+    ) -> Option<T> {
+        let start = Instant::now();
+        let res = f.await;
+        let latency = start.elapsed();
+
+        let (status, output) = match res {
+            Ok(output) => (CheckUpStatus::Passed, Some(output)),
+            Err(err) => {
+                let err = err.to_string();
+                debug!("check-up probe {kind} failed after {latency:?}: {err}");
+                (CheckUpStatus::Failed(err), None)
+            }
+        };
+
+        self.probes.push(CheckUpProbe {
+            kind,
+            status,
+            latency,
+        });
+
+        output
+    }
+
+    /// Record the given probe as skipped, for the given reason.
+    pub fn skip(&mut self, kind: CheckUpProbeKind, reason: impl ToString) {
+        self.probes.push(CheckUpProbe {
+            kind,
+            status: CheckUpStatus::Skipped(reason.to_string()),
+            latency: Duration::ZERO,
+        });
+    }
+
+    /// Find the first probe of the given kind.
+    pub fn find(&self, kind: CheckUpProbeKind) -> Option<&CheckUpProbe> {
+        self.probes.iter().find(|probe| probe.kind == kind)
+    }
+
+    /// Return `true` if no probe failed.
+    pub fn is_ok(&self) -> bool {
+        !self.probes.iter().any(CheckUpProbe::is_failed)
+    }
+
+    /// Turn the report into a result, failing with the first failed
+    /// probe if any.
+    pub fn into_result(self) -> AnyResult<Self> {
+        let failed = self.probes.iter().find_map(|probe| match &probe.status {
+            CheckUpStatus::Failed(err) => Some((probe.kind, err.clone())),
+            _ => None,
+        });
+
+        match failed {
+            Some((kind, err)) => Err(Error::CheckUpFailedError(kind, err).into()),
+            None => Ok(self),
+        }
+    }
+
+    /// Probe the connection to the given server: resolve its host
+    /// name, connect to it, then negotiate TLS if options are given.
+    ///
+    /// When a proxy is defined, the host name of the proxy is
+    /// resolved instead, the one of the server being resolved by the
+    /// proxy. Connections opened by a custom stream connector are
+    /// not resolved. Probes following a failed one are skipped.
+    ///
+    /// Returns `true` if all probes passed.
+    #[cfg(feature = "network")]
+    pub async fn probe_connection(
+        &mut self,
+        network: Option<&NetworkConfig>,
+        connector: Option<&SharedStreamConnector>,
+        host: &str,
+        port: u16,
+        tls: Option<TlsOptions<'_>>,
+    ) -> bool {
+        let resolved = match (connector, network.and_then(|n| n.proxy.as_ref())) {
+            (Some(_), _) => {
+                self.skip(CheckUpProbeKind::Dns, "custom stream connector");
+                true
+            }
+            (None, Some(proxy)) => {
+                let resolve = resolve(network, &proxy.host, proxy.port);
+                self.probe(CheckUpProbeKind::Dns, resolve).await.is_some()
+            }
+            (None, None) => {
+                let resolve = resolve(network, host, port);
+                self.probe(CheckUpProbeKind::Dns, resolve).await.is_some()
+            }
+        };
+
+        if !resolved {
+            self.skip(CheckUpProbeKind::Tcp, "DNS resolution failed");
+            if tls.is_some() {
+                self.skip(CheckUpProbeKind::Tls, "DNS resolution failed");
+            }
+            return false;
+        }
+
+        let connect = async {
+            match connector {
+                Some(connector) => connector.connect(host, port).await,
+                None => network::connect(network, host, port).await,
+            }
+        };
+
+        let Some(tcp) = self.probe(CheckUpProbeKind::Tcp, connect).await else {
+            if tls.is_some() {
+                self.skip(CheckUpProbeKind::Tls, "TCP connection failed");
+            }
+            return false;
+        };
+
+        match tls {
+            Some(opts) => {
+                let handshake = network::tls::connect(tcp, host, port, opts);
+                self.probe(CheckUpProbeKind::Tls, handshake).await.is_some()
+            }
+            None => true,
+        }
+    }
+}
+
+/// Resolve the given server host name, failing if no address is
+/// found.
+#[cfg(feature = "network")]
+async fn resolve(network: Option<&NetworkConfig>, host: &str, port: u16) -> Result<(), String> {
+    match network::resolve(network, host, port).await {
+        Ok(addrs) if addrs.is_empty() => Err(format!("no address found for {host}")),
+        Ok(_addrs) => {
+            debug!("resolved {host} to {_addrs:?}");
+            Ok(())
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckUpProbeKind, CheckUpReport, CheckUpStatus};
+    use crate::backend::kit::{classify_error, ErrorClass};
+
+    #[tokio::test]
+    async fn report() {
+        let mut report = CheckUpReport::new();
+        assert!(report.is_ok());
+
+        let tcp = report
+            .probe(CheckUpProbeKind::Tcp, async { Ok::<_, String>(42) })
+            .await;
+        assert_eq!(tcp, Some(42));
+
+        let auth = report
+            .probe(CheckUpProbeKind::Authentication, async {
+                Err::<(), _>("invalid credentials")
+            })
+            .await;
+        assert_eq!(auth, None);
+
+        report.skip(CheckUpProbeKind::FolderAccess, "authentication failed");
+
+        assert!(!report.is_ok());
+        assert_eq!(report.probes.len(), 3);
+        assert_eq!(
+            report.find(CheckUpProbeKind::Tcp).unwrap().status,
+            CheckUpStatus::Passed
+        );
+        assert_eq!(
+            report
+                .find(CheckUpProbeKind::Authentication)
+                .unwrap()
+                .status,
+            CheckUpStatus::Failed(String::from("invalid credentials"))
+        );
+        assert!(report.find(CheckUpProbeKind::Dns).is_none());
+
+        let err = report.into_result().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot check up backend: authentication failed: invalid credentials"
+        );
+        assert_eq!(classify_error(&err), ErrorClass::Authentication);
+    }
+}
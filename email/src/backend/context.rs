@@ -8,17 +8,30 @@
 use async_trait::async_trait;
 use paste::paste;
 
-use super::feature::{BackendFeature, CheckUp};
+use super::{
+    check_up::CheckUpReport,
+    feature::{BackendFeature, CheckUp},
+};
+#[cfg(feature = "stream")]
+use crate::envelope::stream::StreamEnvelopes;
 #[cfg(feature = "thread")]
 use crate::envelope::thread::ThreadEnvelopes;
 #[cfg(feature = "watch")]
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
     envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+        add::AddFolder,
+        delete::DeleteFolder,
+        expunge::ExpungeFolder,
+        get::GetFolder,
+        list::ListFolders,
+        metadata::{GetFolderMetadata, SetFolderMetadata},
         purge::PurgeFolder,
+        quota::GetQuota,
+        search::SearchFolders,
+        stats::GetFolderStats,
     },
     message::{
         add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
@@ -60,13 +73,23 @@ async fn check(&self) -> AnyResult<()> {
             let ctx = self.clone().build().await?;
 
             if let Some(feature) = feature(&ctx) {
-                feature.check_up().await?;
+                feature.check_up().await?.into_result()?;
             }
         }
 
         Ok(())
     }
 
+    /// Probe the connection to the server before building the
+    /// context, see
+    /// [`BackendBuilder::check_up`](super::BackendBuilder::check_up).
+    ///
+    /// Returns `true` if all probes passed. Backends not connecting
+    /// to any server do not need to implement it.
+    async fn probe_connection(&self, _report: &mut CheckUpReport) -> bool {
+        true
+    }
+
     fn check_configuration(&self) -> AnyResult<()> {
         Ok(())
     }
@@ -82,15 +105,24 @@ async fn configure(&mut self) -> AnyResult<()> {
     feature!(ExpungeFolder);
     feature!(PurgeFolder);
     feature!(DeleteFolder);
+    feature!(GetQuota);
+    feature!(SearchFolders);
+    feature!(GetFolder);
+    feature!(GetFolderStats);
+    feature!(GetFolderMetadata);
+    feature!(SetFolderMetadata);
     feature!(GetEnvelope);
     feature!(ListEnvelopes);
     #[cfg(feature = "thread")]
     feature!(ThreadEnvelopes);
+    #[cfg(feature = "stream")]
+    feature!(StreamEnvelopes);
     #[cfg(feature = "watch")]
     feature!(WatchEnvelopes);
     feature!(AddFlags);
     feature!(SetFlags);
     feature!(RemoveFlags);
+    feature!(ListFlags);
     feature!(AddMessage);
     feature!(SendMessage);
     feature!(PeekMessages);
@@ -161,9 +193,14 @@ fn try_to_sync_cache_builder(
             flag: account_config.flag.clone(),
             message: account_config.message.clone(),
             template: account_config.template.clone(),
+            vip_senders: account_config.vip_senders.clone(),
+            retry: account_config.retry.clone(),
             sync: None,
             #[cfg(feature = "pgp")]
             pgp: account_config.pgp.clone(),
+            #[cfg(feature = "network")]
+            network: account_config.network.clone(),
+            folder_aliases_cache: Default::default(),
         });
 
         let config = Arc::new(MaildirConfig {
@@ -1,7 +1,8 @@
-use std::{any::Any, result};
+use std::{any::Any, io, path::PathBuf, result, time::Duration};
 
 use thiserror::Error;
 
+use super::check_up::CheckUpProbeKind;
 use crate::{AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
@@ -20,12 +21,22 @@ pub enum Error {
     PurgeFolderNotAvailableError,
     #[error("cannot delete folder: feature not available, or backend configuration for this functionality is not set")]
     DeleteFolderNotAvailableError,
+    #[error("cannot get quota: feature not available, or backend configuration for this functionality is not set")]
+    GetQuotaNotAvailableError,
+    #[error("cannot get folder stats: feature not available, or backend configuration for this functionality is not set")]
+    GetFolderStatsNotAvailableError,
+    #[error("cannot get folder metadata: feature not available, or backend configuration for this functionality is not set")]
+    GetFolderMetadataNotAvailableError,
+    #[error("cannot set folder metadata: feature not available, or backend configuration for this functionality is not set")]
+    SetFolderMetadataNotAvailableError,
     #[error("cannot list envelopes: feature not available, or backend configuration for this functionality is not set")]
     ListEnvelopesNotAvailableError,
     #[error("cannot thread envelopes: feature not available, or backend configuration for this functionality is not set")]
     ThreadEnvelopesNotAvailableError,
     #[error("cannot watch for envelopes changes: feature not available, or backend configuration for this functionality is not set")]
     WatchEnvelopesNotAvailableError,
+    #[error("cannot watch for envelope events: feature not available, or backend configuration for this functionality is not set")]
+    WatchEnvelopeEventsNotAvailableError,
     #[error("cannot get envelope: feature not available, or backend configuration for this functionality is not set")]
     GetEnvelopeNotAvailableError,
     #[error("cannot add flag(s): feature not available, or backend configuration for this functionality is not set")]
@@ -34,6 +45,8 @@ pub enum Error {
     SetFlagsNotAvailableError,
     #[error("cannot remove flag(s): feature not available, or backend configuration for this functionality is not set")]
     RemoveFlagsNotAvailableError,
+    #[error("cannot list flags: feature not available, or backend configuration for this functionality is not set")]
+    ListFlagsNotAvailableError,
     #[error("cannot add message: feature not available, or backend configuration for this functionality is not set")]
     AddMessageNotAvailableError,
     #[error("cannot add message with flags: feature not available, or backend configuration for this functionality is not set")]
@@ -52,6 +65,20 @@ pub enum Error {
     DeleteMessagesNotAvailableError,
     #[error("cannot remove messages: feature not available, or backend configuration for this functionality is not set")]
     RemoveMessagesNotAvailableError,
+    #[error("cannot call backend feature {0}: timed out after {1:?}")]
+    FeatureTimedOutError(&'static str, Duration),
+    #[error("cannot call backend feature {0}: cancelled")]
+    FeatureCancelledError(&'static str),
+    #[error("cannot call backend feature {0}: a feature layer did not run the call")]
+    FeatureLayerSkippedError(&'static str),
+    #[error("cannot get page {0}: out of bounds")]
+    PageOutOfBoundsError(usize),
+    #[error("cannot check up backend: {0} failed: {1}")]
+    CheckUpFailedError(CheckUpProbeKind, String),
+    #[error("cannot read cache at {1}")]
+    ReadCacheError(#[source] io::Error, PathBuf),
+    #[error("cannot write cache at {1}")]
+    WriteCacheError(#[source] io::Error, PathBuf),
 }
 
 impl AnyError for Error {
@@ -0,0 +1,193 @@
+//! # Extensions
+//!
+//! Module dedicated to backend-specific data attached to envelopes
+//! and folders. Keys are namespaced by backend (for example
+//! `imap.modseq`), so that backends do not step on each other.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+};
+
+/// The IMAP modification sequence of an envelope, as defined by the
+/// CONDSTORE extension.
+pub const IMAP_MODSEQ: &str = "imap.modseq";
+
+/// The IMAP hierarchy delimiter of a folder.
+pub const IMAP_DELIMITER: &str = "imap.delimiter";
+
+/// The Notmuch tags of an envelope.
+pub const NOTMUCH_TAGS: &str = "notmuch.tags";
+
+/// A backend-specific value.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExtensionValue {
+    /// A boolean value.
+    Bool(bool),
+
+    /// A signed integer value.
+    Int(i64),
+
+    /// An unsigned integer value.
+    UInt(u64),
+
+    /// A string value.
+    String(String),
+
+    /// A list of values.
+    List(Vec<ExtensionValue>),
+}
+
+impl From<bool> for ExtensionValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for ExtensionValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u64> for ExtensionValue {
+    fn from(value: u64) -> Self {
+        Self::UInt(value)
+    }
+}
+
+impl From<String> for ExtensionValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for ExtensionValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl<T: Into<ExtensionValue>> From<Vec<T>> for ExtensionValue {
+    fn from(values: Vec<T>) -> Self {
+        Self::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// The backend-specific data of an envelope or a folder, indexed by
+/// namespaced keys.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Extensions(BTreeMap<String, ExtensionValue>);
+
+impl Extensions {
+    /// Insert the given value at the given namespaced key, and
+    /// return the previous value, if any.
+    pub fn set(
+        &mut self,
+        key: impl ToString,
+        value: impl Into<ExtensionValue>,
+    ) -> Option<ExtensionValue> {
+        self.0.insert(key.to_string(), value.into())
+    }
+
+    /// Return the boolean value at the given key, if any.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.0.get(key)? {
+            ExtensionValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the integer value at the given key, if any.
+    ///
+    /// Unsigned integers are converted when they fit.
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.0.get(key)? {
+            ExtensionValue::Int(value) => Some(*value),
+            ExtensionValue::UInt(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Return the unsigned integer value at the given key, if any.
+    ///
+    /// Signed integers are converted when they fit.
+    pub fn get_uint(&self, key: &str) -> Option<u64> {
+        match self.0.get(key)? {
+            ExtensionValue::UInt(value) => Some(*value),
+            ExtensionValue::Int(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Return the string value at the given key, if any.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.0.get(key)? {
+            ExtensionValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the string values of the list at the given key, if
+    /// any. Values that are not strings are skipped.
+    pub fn get_strs(&self, key: &str) -> Option<Vec<&str>> {
+        match self.0.get(key)? {
+            ExtensionValue::List(values) => Some(
+                values
+                    .iter()
+                    .filter_map(|value| match value {
+                        ExtensionValue::String(value) => Some(value.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl Deref for Extensions {
+    type Target = BTreeMap<String, ExtensionValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Extensions {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromIterator<(String, ExtensionValue)> for Extensions {
+    fn from_iter<T: IntoIterator<Item = (String, ExtensionValue)>>(iter: T) -> Self {
+        Self(BTreeMap::from_iter(iter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExtensionValue, Extensions, IMAP_MODSEQ, NOTMUCH_TAGS};
+
+    #[test]
+    fn typed_accessors() {
+        let mut extensions = Extensions::default();
+        extensions.set(IMAP_MODSEQ, 42u64);
+        extensions.set(NOTMUCH_TAGS, vec!["inbox", "unread"]);
+        extensions.set("custom.flag", true);
+
+        assert_eq!(extensions.get_uint(IMAP_MODSEQ), Some(42));
+        assert_eq!(extensions.get_int(IMAP_MODSEQ), Some(42));
+        assert_eq!(extensions.get_str(IMAP_MODSEQ), None);
+        assert_eq!(
+            extensions.get_strs(NOTMUCH_TAGS),
+            Some(vec!["inbox", "unread"])
+        );
+        assert_eq!(extensions.get_bool("custom.flag"), Some(true));
+        assert_eq!(extensions.get_bool("unknown"), None);
+
+        let prev = extensions.set(IMAP_MODSEQ, 43u64);
+        assert_eq!(prev, Some(ExtensionValue::UInt(42)));
+    }
+}
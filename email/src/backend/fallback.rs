@@ -0,0 +1,727 @@
+//! # Fallback backend
+//!
+//! Module dedicated to the composite fallback backend, which is the
+//! core of offline modes. The [`FallbackBackend`] combines a primary
+//! backend (for example IMAP) with a secondary one (for example the
+//! local Maildir kept in sync with the IMAP server): features are
+//! called on the primary backend first, then transparently on the
+//! secondary backend if the primary one is unreachable or does not
+//! implement the feature.
+//!
+//! Other errors (authentication, permanent errors) are returned as
+//! they are, so that the secondary backend does not hide actual
+//! issues. Sending messages only falls back when the primary backend
+//! does not implement it, since the primary backend may have sent the
+//! message before the error occurred.
+//!
+//! Features writing to folders, flags or messages never fall back
+//! while the primary backend is built: they may have been processed
+//! before the error occurred, and message identifiers issued by the
+//! primary backend (for example IMAP UIDs) do not match the ones of
+//! the secondary backend (for example Maildir identifiers). They are
+//! called on the secondary backend only when the primary one could
+//! not be built, in which case identifiers come from the secondary
+//! backend as well.
+//!
+//! For the same reason, reads taking message identifiers, listing
+//! cursors or changes tokens (getting, peeking or threading a given
+//! message, listing the next page of envelopes…) do not fall back
+//! either while the primary backend is built.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+#[cfg(feature = "watch")]
+use tokio::sync::{
+    mpsc::UnboundedSender,
+    oneshot::{Receiver, Sender},
+};
+
+use super::{
+    context::{BackendContext, BackendContextBuilder},
+    feature::BackendFeatureDescriptor,
+    kit::{classify_error, ErrorClass},
+    Backend, BackendBuilder,
+};
+#[cfg(feature = "stream")]
+use crate::envelope::stream::{EnvelopesStream, StreamEnvelopes};
+#[cfg(feature = "watch")]
+use crate::envelope::watch::{WatchEnvelopes, WatchEvent};
+#[cfg(feature = "thread")]
+use crate::envelope::{thread::ThreadEnvelopes, ThreadedEnvelopes};
+use crate::{
+    account::config::{AccountConfig, HasAccountConfig},
+    debug,
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    folder::{
+        add::AddFolder,
+        delete::DeleteFolder,
+        expunge::ExpungeFolder,
+        get::GetFolder,
+        list::ListFolders,
+        metadata::{FolderMetadata, GetFolderMetadata, SetFolderMetadata},
+        purge::PurgeFolder,
+        quota::{GetQuota, Quota},
+        search::SearchFolders,
+        stats::{FolderStats, GetFolderStats},
+        Folder, Folders,
+    },
+    message::{
+        add::AddMessage,
+        copy::CopyMessages,
+        delete::DeleteMessages,
+        get::GetMessages,
+        peek::PeekMessages,
+        r#move::MoveMessages,
+        remove::RemoveMessages,
+        send::{SendMessage, SendMessageOptions, SendReport, SentMessage},
+        Messages,
+    },
+    warn, AnyBoxedError, AnyResult,
+};
+
+/// Return `true` if the given feature should be called on the
+/// secondary backend after failing on the primary one with the given
+/// error.
+fn should_fall_back(feature: BackendFeatureDescriptor, err: &AnyBoxedError) -> bool {
+    match classify_error(err) {
+        ErrorClass::NotAvailable => true,
+        ErrorClass::Transient => feature.idempotent,
+        ErrorClass::Authentication | ErrorClass::Permanent => false,
+    }
+}
+
+/// Call the given read-only feature on the primary backend, then on
+/// the secondary backend if needed.
+///
+/// The error of the primary backend is kept when the secondary
+/// backend does not implement the feature either. Non-idempotent
+/// features (prefixed with `non_idempotent`) only fall back when the
+/// primary backend does not implement them.
+macro_rules! fall_back {
+    ($self:ident.$feat:ident($($arg:expr),*)) => {
+        fall_back!(@call idempotent $self.$feat($($arg),*))
+    };
+    (non_idempotent $self:ident.$feat:ident($($arg:expr),*)) => {
+        fall_back!(@call non_idempotent $self.$feat($($arg),*))
+    };
+    (@call $kind:ident $self:ident.$feat:ident($($arg:expr),*)) => {{
+        let feature = BackendFeatureDescriptor::$kind(stringify!($feat));
+
+        match &$self.primary {
+            Some(primary) => match primary.$feat($($arg),*).await {
+                Err(err) if should_fall_back(feature, &err) => {
+                    warn!("cannot {feature} using primary backend, falling back: {err}");
+                    debug!("{err:?}");
+
+                    match $self.secondary.$feat($($arg),*).await {
+                        Err(sec_err)
+                            if classify_error(&sec_err) == ErrorClass::NotAvailable =>
+                        {
+                            Err(err)
+                        }
+                        res => res,
+                    }
+                }
+                res => res,
+            },
+            None => $self.secondary.$feat($($arg),*).await,
+        }
+    }};
+}
+
+/// Call the given write feature, or the given read feature taking
+/// identifiers issued by the primary backend, on the primary backend,
+/// or on the secondary backend if the primary one could not be built.
+///
+/// Errors of the primary backend are always returned, see the
+/// [module documentation](self).
+macro_rules! no_fall_back {
+    ($self:ident.$feat:ident($($arg:expr),*)) => {{
+        match &$self.primary {
+            Some(primary) => primary.$feat($($arg),*).await,
+            None => $self.secondary.$feat($($arg),*).await,
+        }
+    }};
+}
+
+/// Return `true` if the given options hold a listing cursor or a
+/// changes token, which are issued by the primary backend.
+fn has_primary_state(opts: &ListEnvelopesOptions) -> bool {
+    opts.after.is_some() || opts.changed_since.is_some()
+}
+
+/// The fallback backend builder.
+pub struct FallbackBackendBuilder<P, S>
+where
+    P: BackendContextBuilder,
+    S: BackendContextBuilder,
+{
+    /// The primary backend builder.
+    pub primary: BackendBuilder<P>,
+    /// The secondary backend builder.
+    pub secondary: BackendBuilder<S>,
+}
+
+impl<P, S> FallbackBackendBuilder<P, S>
+where
+    P: BackendContextBuilder,
+    S: BackendContextBuilder,
+{
+    /// Create a new fallback backend builder from the given primary
+    /// and secondary context builders.
+    ///
+    /// All features of both backends are taken from their context
+    /// builder. Use [`FallbackBackendBuilder::from_builders`] to
+    /// customize backends.
+    pub fn new(account_config: Arc<AccountConfig>, primary: P, secondary: S) -> Self {
+        Self {
+            primary: BackendBuilder::new(account_config.clone(), primary),
+            secondary: BackendBuilder::new(account_config, secondary),
+        }
+    }
+
+    /// Create a new fallback backend builder from the given primary
+    /// and secondary backend builders.
+    pub fn from_builders(primary: BackendBuilder<P>, secondary: BackendBuilder<S>) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Build the fallback backend.
+    ///
+    /// The secondary backend must be built successfully. The primary
+    /// backend is allowed to fail if it is unreachable, in which case
+    /// all features are called on the secondary backend: build the
+    /// backend again to reconnect.
+    pub async fn build(self) -> AnyResult<FallbackBackend<P::Context, S::Context>> {
+        let account_config = self.primary.account_config.clone();
+        let secondary = self.secondary.build().await?;
+
+        let primary = match self.primary.build().await {
+            Ok(primary) => Some(primary),
+            Err(err) if classify_error(&err) == ErrorClass::Transient => {
+                warn!("cannot build primary backend, using secondary one only: {err}");
+                debug!("{err:?}");
+                None
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(FallbackBackend {
+            account_config,
+            primary,
+            secondary,
+        })
+    }
+}
+
+/// The composite fallback backend.
+///
+/// See the [module documentation](self).
+pub struct FallbackBackend<P, S>
+where
+    P: BackendContext,
+    S: BackendContext,
+{
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+    /// The primary backend, if it could be built.
+    pub primary: Option<Backend<P>>,
+    /// The secondary backend.
+    pub secondary: Backend<S>,
+}
+
+impl<P: BackendContext, S: BackendContext> FallbackBackend<P, S> {
+    /// Return `true` if the primary backend could not be built, in
+    /// which case all features are called on the secondary backend.
+    pub fn is_offline(&self) -> bool {
+        self.primary.is_none()
+    }
+}
+
+impl<P: BackendContext, S: BackendContext> HasAccountConfig for FallbackBackend<P, S> {
+    fn account_config(&self) -> &AccountConfig {
+        &self.account_config
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> AddFolder for FallbackBackend<P, S> {
+    async fn add_folder(&self, folder: &str) -> AnyResult<()> {
+        no_fall_back!(self.add_folder(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> ListFolders for FallbackBackend<P, S> {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        fall_back!(self.list_folders())
+    }
+
+    async fn folder_identity(&self, folder: &str) -> AnyResult<Option<String>> {
+        fall_back!(self.folder_identity(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> ExpungeFolder for FallbackBackend<P, S> {
+    async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
+        no_fall_back!(self.expunge_folder(folder))
+    }
+
+    async fn expunge_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        no_fall_back!(self.expunge_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> PurgeFolder for FallbackBackend<P, S> {
+    async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
+        no_fall_back!(self.purge_folder(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> DeleteFolder for FallbackBackend<P, S> {
+    async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
+        no_fall_back!(self.delete_folder(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> SearchFolders for FallbackBackend<P, S> {
+    async fn search_folders(&self, patterns: &[String]) -> AnyResult<Folders> {
+        fall_back!(self.search_folders(patterns))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetFolder for FallbackBackend<P, S> {
+    async fn get_folder(&self, folder: &str) -> AnyResult<Option<Folder>> {
+        fall_back!(self.get_folder(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetFolderStats for FallbackBackend<P, S> {
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats> {
+        fall_back!(self.get_folder_stats(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetQuota for FallbackBackend<P, S> {
+    async fn get_quota(&self, folder: &str) -> AnyResult<Vec<Quota>> {
+        fall_back!(self.get_quota(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetFolderMetadata for FallbackBackend<P, S> {
+    async fn get_folder_metadata(&self, folder: &str) -> AnyResult<FolderMetadata> {
+        fall_back!(self.get_folder_metadata(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> SetFolderMetadata for FallbackBackend<P, S> {
+    async fn set_folder_metadata(&self, folder: &str, metadata: &FolderMetadata) -> AnyResult<()> {
+        no_fall_back!(self.set_folder_metadata(folder, metadata))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetEnvelope for FallbackBackend<P, S> {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        no_fall_back!(self.get_envelope(folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> ListEnvelopes for FallbackBackend<P, S> {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        if has_primary_state(&opts) {
+            return no_fall_back!(self.list_envelopes(folder, opts.clone()));
+        }
+
+        fall_back!(self.list_envelopes(folder, opts.clone()))
+    }
+}
+
+#[cfg(feature = "thread")]
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> ThreadEnvelopes for FallbackBackend<P, S> {
+    async fn thread_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<ThreadedEnvelopes> {
+        if has_primary_state(&opts) {
+            return no_fall_back!(self.thread_envelopes(folder, opts.clone()));
+        }
+
+        fall_back!(self.thread_envelopes(folder, opts.clone()))
+    }
+
+    async fn thread_envelope(
+        &self,
+        folder: &str,
+        id: SingleId,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<ThreadedEnvelopes> {
+        no_fall_back!(self.thread_envelope(folder, id.clone(), opts.clone()))
+    }
+}
+
+#[cfg(feature = "stream")]
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> StreamEnvelopes for FallbackBackend<P, S> {
+    async fn stream_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<EnvelopesStream> {
+        if has_primary_state(&opts) {
+            return no_fall_back!(self.stream_envelopes(folder, opts.clone()));
+        }
+
+        fall_back!(self.stream_envelopes(folder, opts.clone()))
+    }
+}
+
+// NOTE: shutdown channels cannot be given twice, which is why
+// watching does not fall back: the secondary backend is only used
+// when the primary one could not be built
+#[cfg(feature = "watch")]
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> WatchEnvelopes for FallbackBackend<P, S> {
+    async fn watch_envelopes(
+        &self,
+        folder: &str,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        match &self.primary {
+            Some(primary) => {
+                primary
+                    .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
+                    .await
+            }
+            None => {
+                self.secondary
+                    .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
+                    .await
+            }
+        }
+    }
+
+    async fn watch_envelope_events(
+        &self,
+        folder: &str,
+        events: UnboundedSender<WatchEvent>,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        match &self.primary {
+            Some(primary) => {
+                primary
+                    .watch_envelope_events(folder, events, wait_for_shutdown_request, shutdown)
+                    .await
+            }
+            None => {
+                self.secondary
+                    .watch_envelope_events(folder, events, wait_for_shutdown_request, shutdown)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> AddFlags for FallbackBackend<P, S> {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        no_fall_back!(self.add_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> SetFlags for FallbackBackend<P, S> {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        no_fall_back!(self.set_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> RemoveFlags for FallbackBackend<P, S> {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        no_fall_back!(self.remove_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> ListFlags for FallbackBackend<P, S> {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        fall_back!(self.list_flags(folder))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> AddMessage for FallbackBackend<P, S> {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        no_fall_back!(self.add_message_with_flags(folder, msg, flags))
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<SingleId>> {
+        no_fall_back!(self.add_messages_with_flags(folder, msgs))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> SendMessage for FallbackBackend<P, S> {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
+        fall_back!(non_idempotent self.send_message(msg))
+    }
+
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        fall_back!(non_idempotent self.send_message_with_options(msg, opts))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> PeekMessages for FallbackBackend<P, S> {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        no_fall_back!(self.peek_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> GetMessages for FallbackBackend<P, S> {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        no_fall_back!(self.get_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> CopyMessages for FallbackBackend<P, S> {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        no_fall_back!(self.copy_messages(from_folder, to_folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> MoveMessages for FallbackBackend<P, S> {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        no_fall_back!(self.move_messages(from_folder, to_folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> DeleteMessages for FallbackBackend<P, S> {
+    async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        no_fall_back!(self.delete_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<P: BackendContext, S: BackendContext> RemoveMessages for FallbackBackend<P, S> {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        no_fall_back!(self.remove_messages(folder, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+
+    use super::{FallbackBackend, FallbackBackendBuilder};
+    use crate::{
+        account::config::AccountConfig,
+        backend::{
+            context::{BackendContext, BackendContextBuilder},
+            feature::BackendFeature,
+            Error,
+        },
+        envelope::{get::GetEnvelope, Envelope, Id, SingleId},
+        flag::{add::AddFlags, Flag, Flags},
+        folder::{list::ListFolders, Folder, Folders},
+        AnyResult,
+    };
+
+    #[derive(Clone, Copy, Debug)]
+    enum Status {
+        Online,
+        Offline,
+        Broken,
+        Unreachable,
+    }
+
+    #[derive(Clone)]
+    struct TestContextBuilder(&'static str, Status);
+
+    struct TestContext(&'static str, Status);
+
+    impl BackendContext for TestContext {}
+
+    struct TestListFolders(&'static str, Status);
+
+    #[async_trait]
+    impl ListFolders for TestListFolders {
+        async fn list_folders(&self) -> AnyResult<Folders> {
+            match self.1 {
+                Status::Offline => {
+                    Err(Error::FeatureTimedOutError("list_folders", Duration::ZERO).into())
+                }
+                Status::Broken => Err(Error::PageOutOfBoundsError(1).into()),
+                _ => Ok(Folders::from_iter([Folder {
+                    name: self.0.into(),
+                    ..Default::default()
+                }])),
+            }
+        }
+    }
+
+    struct TestAddFlags(Status);
+
+    #[async_trait]
+    impl AddFlags for TestAddFlags {
+        async fn add_flags(&self, _folder: &str, _id: &Id, _flags: &Flags) -> AnyResult<()> {
+            match self.0 {
+                Status::Offline => {
+                    Err(Error::FeatureTimedOutError("add_flags", Duration::ZERO).into())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    struct TestGetEnvelope(&'static str, Status);
+
+    #[async_trait]
+    impl GetEnvelope for TestGetEnvelope {
+        async fn get_envelope(&self, _folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+            match self.1 {
+                Status::Offline => {
+                    Err(Error::FeatureTimedOutError("get_envelope", Duration::ZERO).into())
+                }
+                _ => Ok(Envelope {
+                    id: id.to_string(),
+                    message_id: self.0.into(),
+                    ..Default::default()
+                }),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BackendContextBuilder for TestContextBuilder {
+        type Context = TestContext;
+
+        fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestGetEnvelope(ctx.0, ctx.1)))
+            }))
+        }
+
+        fn add_flags(&self) -> Option<BackendFeature<Self::Context, dyn AddFlags>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestAddFlags(ctx.1)))
+            }))
+        }
+
+        fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(TestListFolders(ctx.0, ctx.1)))
+            }))
+        }
+
+        async fn build(self) -> AnyResult<Self::Context> {
+            match self.1 {
+                Status::Unreachable => {
+                    Err(Error::FeatureTimedOutError("build", Duration::ZERO).into())
+                }
+                _ => Ok(TestContext(self.0, self.1)),
+            }
+        }
+    }
+
+    async fn build(status: Status) -> AnyResult<FallbackBackend<TestContext, TestContext>> {
+        let config = Arc::new(AccountConfig::default());
+        let primary = TestContextBuilder("primary", status);
+        let secondary = TestContextBuilder("secondary", Status::Online);
+
+        FallbackBackendBuilder::new(config, primary, secondary)
+            .build()
+            .await
+    }
+
+    async fn list_folders(status: Status) -> AnyResult<String> {
+        let folders = build(status).await?.list_folders().await?;
+        Ok(folders[0].name.clone())
+    }
+
+    async fn add_flags(status: Status) -> AnyResult<()> {
+        let flags = Flags::from_iter([Flag::Seen]);
+        build(status)
+            .await?
+            .add_flags("INBOX", &Id::single("1"), &flags)
+            .await
+    }
+
+    async fn get_envelope(status: Status) -> AnyResult<String> {
+        let id = SingleId::from("1");
+        let envelope = build(status).await?.get_envelope("INBOX", &id).await?;
+        Ok(envelope.message_id)
+    }
+
+    #[tokio::test]
+    async fn fall_back() {
+        assert_eq!(list_folders(Status::Online).await.unwrap(), "primary");
+        assert_eq!(list_folders(Status::Offline).await.unwrap(), "secondary");
+        assert_eq!(
+            list_folders(Status::Unreachable).await.unwrap(),
+            "secondary"
+        );
+
+        // permanent errors do not fall back
+        assert!(list_folders(Status::Broken).await.is_err());
+
+        // write features only use the secondary backend when the
+        // primary one could not be built
+        assert!(add_flags(Status::Online).await.is_ok());
+        assert!(add_flags(Status::Offline).await.is_err());
+        assert!(add_flags(Status::Unreachable).await.is_ok());
+
+        // identifiers issued by the primary backend are not given to
+        // the secondary one
+        assert_eq!(get_envelope(Status::Online).await.unwrap(), "primary");
+        assert!(get_envelope(Status::Offline).await.is_err());
+        assert_eq!(
+            get_envelope(Status::Unreachable).await.unwrap(),
+            "secondary"
+        );
+    }
+}
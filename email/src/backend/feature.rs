@@ -3,22 +3,35 @@
 //! A [`BackendFeature`] is an action like adding folder, listing
 //! envelopes or sending message. A feature needs a backend context to
 //! be executed.
+//!
+//! Cross-cutting behaviour (logging, metrics, tracing spans,
+//! auditing…) can be added to all features at once using
+//! [`BackendFeatureLayer`]s, see
+//! [`BackendBuilder::with_layer`](super::BackendBuilder::with_layer).
 
-use std::sync::Arc;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 
-use super::{context::BackendContext, AnyResult};
+use super::{check_up::CheckUpReport, context::BackendContext, AnyResult, Error};
 
 /// Backend builder feature for checking up configuration and context
 /// integrity.
 ///
-/// This feature is used to check the integrity of the context.
+/// This feature is used to check the integrity of the context. The
+/// returned report details every probe run by the backend, see
+/// [`CheckUpReport`]. Failing probes do not make the check-up fail,
+/// errors are reserved to the check-up itself.
 #[async_trait]
 pub trait CheckUp: Send + Sync {
-    /// Define how the no operation should be executed.
-    async fn check_up(&self) -> AnyResult<()> {
-        Ok(())
+    /// Define how the check-up should be executed.
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        Ok(CheckUpReport::default())
     }
 }
 
@@ -71,3 +84,219 @@ fn from(value: T) -> Self {
         Self::Backend(Arc::new(value))
     }
 }
+
+/// The backend feature descriptor.
+///
+/// Describes the feature being called to layers, see
+/// [`BackendFeatureLayer`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BackendFeatureDescriptor {
+    /// The name of the feature, for example `list_envelopes`.
+    pub name: &'static str,
+
+    /// Can the feature be called again after a failure.
+    ///
+    /// Features adding, copying, moving or sending messages are not
+    /// idempotent: the server may have processed the call before the
+    /// failure occurred, calling them again would duplicate messages
+    /// (or move them twice).
+    pub idempotent: bool,
+}
+
+impl BackendFeatureDescriptor {
+    /// Describe an idempotent feature from its name.
+    pub const fn idempotent(name: &'static str) -> Self {
+        Self {
+            name,
+            idempotent: true,
+        }
+    }
+
+    /// Describe a non-idempotent feature from its name.
+    pub const fn non_idempotent(name: &'static str) -> Self {
+        Self {
+            name,
+            idempotent: false,
+        }
+    }
+}
+
+impl fmt::Display for BackendFeatureDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The future of a backend feature call, as seen by layers.
+///
+/// The output of the feature is not exposed to layers, only whether
+/// the call succeeded or not.
+pub type BackendFeatureFuture<'a> = Pin<Box<dyn Future<Output = AnyResult<()>> + Send + 'a>>;
+
+/// The next step of a backend feature call.
+///
+/// It is either the next layer, or the feature itself for the
+/// innermost layer.
+#[derive(Clone)]
+pub struct Next<'a>(Arc<dyn Fn() -> BackendFeatureFuture<'a> + Send + Sync + 'a>);
+
+impl<'a> Next<'a> {
+    /// Create a new next step from the given function.
+    pub fn new(f: impl Fn() -> BackendFeatureFuture<'a> + Send + Sync + 'a) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Run the next step.
+    ///
+    /// The next step can be run multiple times, each run calls the
+    /// feature again.
+    pub fn run(&self) -> BackendFeatureFuture<'a> {
+        (self.0)()
+    }
+}
+
+/// The backend feature layer.
+///
+/// A layer is a middleware wrapping every feature call of a backend,
+/// whatever the feature is. It receives the descriptor of the called
+/// feature (see [`BackendFeatureDescriptor`]) and the next step of
+/// the call, which should be run for the call to proceed.
+///
+/// ```rust,ignore
+/// struct LogLayer;
+///
+/// #[async_trait]
+/// impl BackendFeatureLayer for LogLayer {
+///     async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+///         let res = next.run().await;
+///         println!("{feature}: {}", if res.is_ok() { "ok" } else { "err" });
+///         res
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait BackendFeatureLayer: Send + Sync {
+    /// Wrap the given feature call.
+    async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()>;
+}
+
+/// Call the given feature through the given layers.
+///
+/// Layers are applied from the first one (outermost) to the last one
+/// (innermost).
+pub async fn call_with_layers<'a, T, F, Fut>(
+    layers: &'a [Arc<dyn BackendFeatureLayer>],
+    feature: BackendFeatureDescriptor,
+    f: F,
+) -> AnyResult<T>
+where
+    T: Send + 'a,
+    F: Fn() -> Fut + Send + Sync + 'a,
+    Fut: Future<Output = AnyResult<T>> + Send + 'a,
+{
+    if layers.is_empty() {
+        return f().await;
+    }
+
+    // layers only see the outcome of the call, the output is kept
+    // aside until the last layer returns
+    let output = Arc::new(Mutex::new(None));
+
+    let mut next = {
+        let output = output.clone();
+        Next::new(move || {
+            let output = output.clone();
+            let call = f();
+            Box::pin(async move {
+                let value = call.await?;
+                if let Ok(mut output) = output.lock() {
+                    *output = Some(value);
+                }
+                Ok(())
+            })
+        })
+    };
+
+    for layer in layers.iter().rev() {
+        let layer = layer.as_ref();
+        let inner = next;
+        next = Next::new(move || layer.call(feature, inner.clone()));
+    }
+
+    next.run().await?;
+
+    let value = output.lock().ok().and_then(|mut output| output.take());
+    value.ok_or_else(|| Error::FeatureLayerSkippedError(feature.name).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{call_with_layers, BackendFeatureDescriptor, BackendFeatureLayer, Next};
+    use crate::AnyResult;
+
+    struct NamedLayer(&'static str, Arc<Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl BackendFeatureLayer for NamedLayer {
+        async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+            self.1.lock().unwrap().push(format!("{} {feature}", self.0));
+            next.run().await
+        }
+    }
+
+    struct TwiceLayer;
+
+    #[async_trait]
+    impl BackendFeatureLayer for TwiceLayer {
+        async fn call(&self, _feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+            next.run().await?;
+            next.run().await
+        }
+    }
+
+    #[tokio::test]
+    async fn layers() {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let calls = &AtomicUsize::new(0);
+
+        let layers: Vec<Arc<dyn BackendFeatureLayer>> = vec![
+            Arc::new(NamedLayer("outer", logs.clone())),
+            Arc::new(TwiceLayer),
+            Arc::new(NamedLayer("inner", logs.clone())),
+        ];
+
+        let feature = BackendFeatureDescriptor::idempotent("list_folders");
+        let output = call_with_layers(&layers, feature, move || async move {
+            Ok(calls.fetch_add(1, Ordering::SeqCst) + 1)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(output, 2);
+        assert_eq!(
+            *logs.lock().unwrap(),
+            [
+                "outer list_folders",
+                "inner list_folders",
+                "inner list_folders"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn no_layers() {
+        let feature = BackendFeatureDescriptor::idempotent("list_folders");
+        let output = call_with_layers(&[], feature, || async { Ok(42) })
+            .await
+            .unwrap();
+
+        assert_eq!(output, 42);
+    }
+}
@@ -0,0 +1,371 @@
+//! # Backend kit
+//!
+//! Module dedicated to helpers shared by the built-in backends. They
+//! are exposed so that custom backends (for example on top of a
+//! proprietary API) can behave like the built-in ones without
+//! copying their internals:
+//!
+//! - [`page_range`] and [`paginate_envelopes`] apply the pagination
+//!   of [`ListEnvelopesOptions`]
+//!
+//! - [`sort_envelopes`] applies the sort order of the search query
+//!
+//! - [`resolve_folder_alias`] and [`find_folder_kind`] resolve folder
+//!   names using the account folder aliases
+//!
+//! - [`normalize_flags`] parses flags the way built-in backends do
+//!
+//! - [`classify_error`] tells whether a backend feature error is
+//!   worth retrying, and [`user_message`] turns it into an
+//!   actionable message for end users
+
+use std::{any::Any, error, io, ops::Range};
+
+use super::{check_up::CheckUpProbeKind, Error, Result};
+use crate::{
+    account::config::AccountConfig,
+    envelope::{list::ListEnvelopesOptions, Envelopes},
+    flag::{Flag, Flags},
+    folder::FolderKind,
+    runtime::Elapsed,
+    AnyError,
+};
+
+/// Return the range of items matching the given page, out of the
+/// given total number of items.
+///
+/// A page size of 0 means no pagination: the whole range is
+/// returned. Returns `None` if the page begins after the last item.
+pub fn page_range(total: usize, page: usize, page_size: usize) -> Option<Range<usize>> {
+    if page_size == 0 {
+        return Some(0..total);
+    }
+
+    let begin = page * page_size;
+
+    if begin > total {
+        return None;
+    }
+
+    Some(begin..total.min(begin + page_size))
+}
+
+/// Sort the given envelopes, then only keep the ones matching the
+/// pagination of the given options.
+///
+/// When the options define a cursor, the page index is ignored and
+/// the cursor of the next page is set, see
+/// [`ListEnvelopesOptions::apply_cursor`].
+pub fn paginate_envelopes(envelopes: &mut Envelopes, opts: &ListEnvelopesOptions) -> Result<()> {
+    if opts.after.is_some() {
+        sort_envelopes(envelopes, opts);
+        opts.apply_cursor(envelopes);
+        return Ok(());
+    }
+
+    let range = page_range(envelopes.len(), opts.page, opts.page_size)
+        .ok_or(Error::PageOutOfBoundsError(opts.page + 1))?;
+
+    sort_envelopes(envelopes, opts);
+    *envelopes = envelopes[range].into();
+
+    Ok(())
+}
+
+/// Sort the given envelopes using the sort order of the given
+/// options.
+///
+/// Envelopes are sorted by date, newest first, if the options do not
+/// define any sort order.
+pub fn sort_envelopes(envelopes: &mut Envelopes, opts: &ListEnvelopesOptions) {
+    opts.sort_envelopes(envelopes)
+}
+
+/// Return the folder name the given folder alias points to.
+///
+/// See [`AccountConfig::get_folder_alias`].
+pub fn resolve_folder_alias(config: &AccountConfig, folder: &str) -> String {
+    config.get_folder_alias(folder)
+}
+
+/// Find the kind of the given folder name, from the account folder
+/// aliases first, then from the name itself.
+pub fn find_folder_kind(config: &AccountConfig, name: &str) -> Option<FolderKind> {
+    config
+        .find_folder_kind_from_alias(name)
+        .or_else(|| name.parse().ok())
+}
+
+/// Parse the given raw flags.
+///
+/// Known flags are matched case-insensitively and with their usual
+/// synonyms (like `replied` for [`Flag::Answered`]). Unknown flags
+/// are kept as [`Flag::Custom`].
+pub fn normalize_flags<T: AsRef<str>>(flags: impl IntoIterator<Item = T>) -> Flags {
+    flags
+        .into_iter()
+        .filter(|flag| !flag.as_ref().trim().is_empty())
+        .map(|flag| Flag::from(flag.as_ref()))
+        .collect()
+}
+
+/// The class of a backend feature error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorClass {
+    /// The feature is not available for the backend.
+    NotAvailable,
+
+    /// The server rejected the credentials of the account.
+    Authentication,
+
+    /// The error is transient (timeout, connection reset etc): the
+    /// operation may succeed if retried.
+    Transient,
+
+    /// The error is permanent: retrying the operation would fail the
+    /// same way.
+    Permanent,
+}
+
+impl ErrorClass {
+    /// Return an actionable, non-technical message describing errors
+    /// of this class for the given account name.
+    ///
+    /// The message is meant to be shown to end users, alongside the
+    /// technical error when needed.
+    pub fn user_message(&self, account: &str) -> String {
+        match self {
+            Self::NotAvailable => format!(
+                "This action is not available for account {account}: check that its backend \
+                 supports it, or enable it in settings"
+            ),
+            Self::Authentication => format!(
+                "Your credentials seem incorrect for account {account}: update them in settings"
+            ),
+            Self::Transient => format!(
+                "The server of account {account} cannot be reached: check your connection, \
+                 then try again"
+            ),
+            Self::Permanent => format!(
+                "Something went wrong with account {account}: if the problem persists, check \
+                 its settings"
+            ),
+        }
+    }
+}
+
+/// Classify the given backend feature error.
+///
+/// The whole chain of sources is inspected, so backend-specific
+/// errors wrapping authentication failures, I/O errors or timeouts
+/// are classified accordingly.
+pub fn classify_error(err: &(dyn AnyError + Send)) -> ErrorClass {
+    let any = err.as_any();
+
+    if let Some(err) = any.downcast_ref::<Error>() {
+        return match err {
+            Error::PageOutOfBoundsError(_) => ErrorClass::Permanent,
+            Error::FeatureLayerSkippedError(_) => ErrorClass::Permanent,
+            Error::FeatureTimedOutError(..) => ErrorClass::Transient,
+            Error::FeatureCancelledError(_) => ErrorClass::Permanent,
+            Error::ReadCacheError(..) | Error::WriteCacheError(..) => ErrorClass::Permanent,
+            Error::CheckUpFailedError(kind, _) => match kind {
+                CheckUpProbeKind::Dns | CheckUpProbeKind::Tcp => ErrorClass::Transient,
+                CheckUpProbeKind::Authentication => ErrorClass::Authentication,
+                _ => ErrorClass::Permanent,
+            },
+            _ => ErrorClass::NotAvailable,
+        };
+    }
+
+    if let Some(class) = classify_one(any) {
+        return class;
+    }
+
+    let mut source = err.source();
+
+    while let Some(err) = source {
+        if let Some(class) = classify_one(err) {
+            return class;
+        }
+
+        source = err.source();
+    }
+
+    ErrorClass::Permanent
+}
+
+/// Return the end-user message of the given backend feature error,
+/// based on its class.
+///
+/// See [`classify_error`] and [`ErrorClass::user_message`].
+pub fn user_message(err: &(dyn AnyError + Send), account: &str) -> String {
+    classify_error(err).user_message(account)
+}
+
+/// Downcast abstraction over [`Any`] and [`error::Error`], so that
+/// both the error and its sources can be classified the same way.
+trait Downcast {
+    fn downcast<T: error::Error + 'static>(&self) -> Option<&T>;
+}
+
+impl Downcast for dyn Any {
+    fn downcast<T: error::Error + 'static>(&self) -> Option<&T> {
+        self.downcast_ref()
+    }
+}
+
+impl Downcast for dyn error::Error + 'static {
+    fn downcast<T: error::Error + 'static>(&self) -> Option<&T> {
+        self.downcast_ref()
+    }
+}
+
+/// Classify a single error of a chain, without inspecting its
+/// sources.
+fn classify_one(err: &(impl Downcast + ?Sized)) -> Option<ErrorClass> {
+    #[cfg(feature = "imap")]
+    if let Some(err) = err.downcast::<crate::imap::Error>() {
+        use crate::imap::Error::*;
+
+        if matches!(
+            err,
+            LoginError(_)
+                | AuthenticateError(_)
+                | AuthenticatePlainError(_)
+                | AuthenticateXOauth2Error(_)
+                | AuthenticateOAuthBearerError(_)
+        ) {
+            return Some(ErrorClass::Authentication);
+        }
+
+        if matches!(
+            err,
+            ConnectTimedOutError(..)
+                | LoginTimedOutError(_)
+                | IdleTimedOutError
+                | NotifyClosedError
+        ) {
+            return Some(ErrorClass::Transient);
+        }
+    }
+
+    // the IMAP server closed the connection, for example after
+    // sending BYE
+    #[cfg(feature = "imap")]
+    if let Some(imap_next::stream::Error::Closed) =
+        err.downcast::<imap_next::stream::Error<imap_next::client::Error>>()
+    {
+        return Some(ErrorClass::Transient);
+    }
+
+    #[cfg(feature = "smtp")]
+    if let Some(crate::smtp::Error::GetPasswdEmptySmtpError) = err.downcast() {
+        return Some(ErrorClass::Authentication);
+    }
+
+    #[cfg(feature = "smtp")]
+    if let Some(crate::smtp::Error::SendMessageTimedOutError) = err.downcast() {
+        return Some(ErrorClass::Transient);
+    }
+
+    #[cfg(feature = "smtp")]
+    match err.downcast() {
+        Some(mail_send::Error::AuthenticationFailed(_)) => {
+            return Some(ErrorClass::Authentication);
+        }
+        Some(mail_send::Error::Timeout) => {
+            return Some(ErrorClass::Transient);
+        }
+        Some(mail_send::Error::Io(err)) if is_transient_io_error(err) => {
+            return Some(ErrorClass::Transient);
+        }
+        _ => (),
+    }
+
+    if err.downcast::<Elapsed>().is_some() {
+        return Some(ErrorClass::Transient);
+    }
+
+    if err.downcast().is_some_and(is_transient_io_error) {
+        return Some(ErrorClass::Transient);
+    }
+
+    None
+}
+
+fn is_transient_io_error(err: &io::Error) -> bool {
+    use io::ErrorKind::*;
+
+    matches!(
+        err.kind(),
+        TimedOut
+            | Interrupted
+            | WouldBlock
+            | UnexpectedEof
+            | BrokenPipe
+            | NotConnected
+            | ConnectionReset
+            | ConnectionAborted
+            | ConnectionRefused
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::Any, io};
+
+    use thiserror::Error;
+
+    use super::{classify_error, normalize_flags, page_range, ErrorClass};
+    use crate::{
+        backend::Error,
+        flag::{Flag, Flags},
+        AnyBoxedError, AnyError,
+    };
+
+    #[derive(Debug, Error)]
+    #[error("cannot reach custom backend")]
+    struct CustomError(#[source] io::Error);
+
+    impl AnyError for CustomError {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn page_range_bounds() {
+        assert_eq!(page_range(10, 0, 0), Some(0..10));
+        assert_eq!(page_range(10, 0, 3), Some(0..3));
+        assert_eq!(page_range(10, 3, 3), Some(9..10));
+        assert_eq!(page_range(9, 3, 3), Some(9..9));
+        assert_eq!(page_range(9, 4, 3), None);
+    }
+
+    #[test]
+    fn normalized_flags() {
+        let flags = normalize_flags(["SEEN", " replied", "", "$label"]);
+        let expected =
+            Flags::from_iter([Flag::Seen, Flag::Answered, Flag::Custom("$label".into())]);
+        assert_eq!(flags, expected);
+    }
+
+    #[test]
+    fn classified_errors() {
+        let err = AnyBoxedError::from(Error::ListFoldersNotAvailableError);
+        assert_eq!(classify_error(&err), ErrorClass::NotAvailable);
+
+        let err = io::Error::from(io::ErrorKind::ConnectionReset);
+        let err: AnyBoxedError = Box::new(CustomError(err));
+        assert_eq!(classify_error(&err), ErrorClass::Transient);
+
+        let err = io::Error::from(io::ErrorKind::PermissionDenied);
+        let err: AnyBoxedError = Box::new(CustomError(err));
+        assert_eq!(classify_error(&err), ErrorClass::Permanent);
+        assert_eq!(
+            err.user_message("work"),
+            "Something went wrong with account work: if the problem persists, check its settings"
+        );
+    }
+}
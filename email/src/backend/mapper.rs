@@ -11,16 +11,26 @@
     context::{BackendContext, BackendContextBuilder},
     feature::{BackendFeature, CheckUp},
 };
+#[cfg(feature = "stream")]
+use crate::envelope::stream::StreamEnvelopes;
 #[cfg(feature = "thread")]
 use crate::envelope::thread::ThreadEnvelopes;
 #[cfg(feature = "watch")]
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
     envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+        add::AddFolder,
+        delete::DeleteFolder,
+        expunge::ExpungeFolder,
+        get::GetFolder,
+        list::ListFolders,
+        metadata::{GetFolderMetadata, SetFolderMetadata},
         purge::PurgeFolder,
+        quota::GetQuota,
+        search::SearchFolders,
+        stats::GetFolderStats,
     },
     message::{
         add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
@@ -75,15 +85,24 @@ fn map_feature<T: ?Sized + 'static>(
     some_feature_mapper!(ExpungeFolder);
     some_feature_mapper!(PurgeFolder);
     some_feature_mapper!(DeleteFolder);
+    some_feature_mapper!(GetQuota);
+    some_feature_mapper!(SearchFolders);
+    some_feature_mapper!(GetFolder);
+    some_feature_mapper!(GetFolderStats);
+    some_feature_mapper!(GetFolderMetadata);
+    some_feature_mapper!(SetFolderMetadata);
     some_feature_mapper!(GetEnvelope);
     some_feature_mapper!(ListEnvelopes);
     #[cfg(feature = "thread")]
     some_feature_mapper!(ThreadEnvelopes);
+    #[cfg(feature = "stream")]
+    some_feature_mapper!(StreamEnvelopes);
     #[cfg(feature = "watch")]
     some_feature_mapper!(WatchEnvelopes);
     some_feature_mapper!(AddFlags);
     some_feature_mapper!(SetFlags);
     some_feature_mapper!(RemoveFlags);
+    some_feature_mapper!(ListFlags);
     some_feature_mapper!(AddMessage);
     some_feature_mapper!(SendMessage);
     some_feature_mapper!(PeekMessages);
@@ -138,15 +157,24 @@ fn map_feature<T: ?Sized + 'static>(
     feature_mapper!(ExpungeFolder);
     feature_mapper!(PurgeFolder);
     feature_mapper!(DeleteFolder);
+    feature_mapper!(GetQuota);
+    feature_mapper!(SearchFolders);
+    feature_mapper!(GetFolder);
+    feature_mapper!(GetFolderStats);
+    feature_mapper!(GetFolderMetadata);
+    feature_mapper!(SetFolderMetadata);
     feature_mapper!(GetEnvelope);
     feature_mapper!(ListEnvelopes);
     #[cfg(feature = "thread")]
     feature_mapper!(ThreadEnvelopes);
+    #[cfg(feature = "stream")]
+    feature_mapper!(StreamEnvelopes);
     #[cfg(feature = "watch")]
     feature_mapper!(WatchEnvelopes);
     feature_mapper!(AddFlags);
     feature_mapper!(SetFlags);
     feature_mapper!(RemoveFlags);
+    feature_mapper!(ListFlags);
     feature_mapper!(AddMessage);
     feature_mapper!(SendMessage);
     feature_mapper!(PeekMessages);
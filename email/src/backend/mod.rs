@@ -45,54 +45,113 @@
 //! ```rust,ignore
 #![doc = include_str!("../../tests/static_backend.rs")]
 //! ```
-
+//!
+//! ## Backend kit
+//!
+//! The [`kit`] module exposes helpers used by built-in backends
+//! (pagination, sorting, folder aliases, flags parsing, errors
+//! classification), so that custom backends behave the same way.
+//!
+//! ## Extensions
+//!
+//! Backend-specific data (IMAP modification sequences, Notmuch
+//! tags…) is exposed through the [`extensions`] of envelopes and
+//! folders, under keys namespaced by backend.
+
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod cancel;
+pub mod check_up;
 pub mod context;
 mod error;
+pub mod extensions;
+pub mod fallback;
 pub mod feature;
+pub mod kit;
 pub mod mapper;
+pub mod retry;
+pub mod timeout;
 pub mod macros {
     pub use email_macros::BackendContext;
 }
 
 #[cfg(feature = "sync")]
 use std::hash::DefaultHasher;
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+#[cfg(feature = "stream")]
+use futures::{stream, StreamExt};
 use paste::paste;
 #[cfg(feature = "watch")]
-use tokio::sync::oneshot::{Receiver, Sender};
+use tokio::{
+    sync::{
+        mpsc::UnboundedSender,
+        oneshot::{Receiver, Sender},
+    },
+    task::JoinHandle,
+};
 
+#[cfg(feature = "watch")]
+use self::cancel::forward_shutdown_request;
 #[doc(inline)]
 pub use self::error::{Error, Result};
 use self::{
+    cancel::{cancellable, CancellationLayer, CancellationToken},
+    check_up::{CheckUpProbeKind, CheckUpReport},
     context::{BackendContext, BackendContextBuilder},
-    feature::{BackendFeature, BackendFeatureSource, CheckUp},
+    feature::{
+        call_with_layers, BackendFeature, BackendFeatureDescriptor, BackendFeatureLayer,
+        BackendFeatureSource, CheckUp,
+    },
+    retry::RetryLayer,
+    timeout::TimeoutLayer,
 };
+#[cfg(feature = "stream")]
+use crate::envelope::stream::{EnvelopesStream, StreamEnvelopes};
 #[cfg(feature = "watch")]
-use crate::envelope::watch::WatchEnvelopes;
+use crate::envelope::watch::{WatchEnvelopes, WatchEvent};
 #[cfg(feature = "thread")]
 use crate::envelope::{thread::ThreadEnvelopes, ThreadedEnvelopes};
 #[cfg(feature = "sync")]
 use crate::sync::hash::SyncHash;
 use crate::{
     account::config::{AccountConfig, HasAccountConfig},
+    debug,
     envelope::{
         get::GetEnvelope,
         list::{ListEnvelopes, ListEnvelopesOptions},
         Envelope, Envelopes, Id, SingleId,
     },
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags, Flag, Flags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
-        purge::PurgeFolder, Folders,
+        add::AddFolder,
+        delete::DeleteFolder,
+        expunge::ExpungeFolder,
+        get::{find_folder, GetFolder},
+        list::ListFolders,
+        metadata::{FolderMetadata, GetFolderMetadata, SetFolderMetadata},
+        purge::PurgeFolder,
+        quota::{GetQuota, Quota},
+        search::{filter_folders, SearchFolders},
+        stats::{FolderStats, GetFolderStats},
+        Folder, Folders, SENT,
     },
     message::{
-        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
-        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+        add::AddMessage,
+        copy::CopyMessages,
+        delete::DeleteMessages,
+        get::GetMessages,
+        peek::PeekMessages,
+        r#move::MoveMessages,
+        remove::RemoveMessages,
+        send::{
+            rate_limit::SendRateLimiter, recipients::add_auto_recipients, SendMessage,
+            SendMessageOptions, SendReport, SentMessage,
+        },
         Messages,
     },
-    AnyResult,
+    warn, AnyResult,
 };
 
 /// The basic backend implementation.
@@ -111,6 +170,14 @@ pub struct Backend<C>
     pub account_config: Arc<AccountConfig>,
     /// The backend context.
     pub context: Arc<C>,
+    /// The outbound rate limiter, shared by all sends of the
+    /// backend.
+    pub send_rate_limiter: SendRateLimiter,
+    /// The layers wrapping every feature call, from the outermost to
+    /// the innermost one.
+    pub layers: Vec<Arc<dyn BackendFeatureLayer>>,
+    /// The token cancelling every feature call of the backend.
+    pub cancellation_token: Option<CancellationToken>,
 
     /// The add folder backend feature.
     pub add_folder: Option<BackendFeature<C, dyn AddFolder>>,
@@ -122,6 +189,18 @@ pub struct Backend<C>
     pub purge_folder: Option<BackendFeature<C, dyn PurgeFolder>>,
     /// The delete folder backend feature.
     pub delete_folder: Option<BackendFeature<C, dyn DeleteFolder>>,
+    /// The get quota backend feature.
+    pub get_quota: Option<BackendFeature<C, dyn GetQuota>>,
+    /// The search folders backend feature.
+    pub search_folders: Option<BackendFeature<C, dyn SearchFolders>>,
+    /// The get folder backend feature.
+    pub get_folder: Option<BackendFeature<C, dyn GetFolder>>,
+    /// The get folder stats backend feature.
+    pub get_folder_stats: Option<BackendFeature<C, dyn GetFolderStats>>,
+    /// The get folder metadata backend feature.
+    pub get_folder_metadata: Option<BackendFeature<C, dyn GetFolderMetadata>>,
+    /// The set folder metadata backend feature.
+    pub set_folder_metadata: Option<BackendFeature<C, dyn SetFolderMetadata>>,
 
     /// The get envelope backend feature.
     pub get_envelope: Option<BackendFeature<C, dyn GetEnvelope>>,
@@ -130,6 +209,9 @@ pub struct Backend<C>
     /// The thread envelopes backend feature.
     #[cfg(feature = "thread")]
     pub thread_envelopes: Option<BackendFeature<C, dyn ThreadEnvelopes>>,
+    /// The stream envelopes backend feature.
+    #[cfg(feature = "stream")]
+    pub stream_envelopes: Option<BackendFeature<C, dyn StreamEnvelopes>>,
     /// The watch envelopes backend feature.
     #[cfg(feature = "watch")]
     pub watch_envelopes: Option<BackendFeature<C, dyn WatchEnvelopes>>,
@@ -140,6 +222,8 @@ pub struct Backend<C>
     pub set_flags: Option<BackendFeature<C, dyn SetFlags>>,
     /// The remove flags backend feature.
     pub remove_flags: Option<BackendFeature<C, dyn RemoveFlags>>,
+    /// The list flags backend feature.
+    pub list_flags: Option<BackendFeature<C, dyn ListFlags>>,
 
     /// The add message backend feature.
     pub add_message: Option<BackendFeature<C, dyn AddMessage>>,
@@ -159,6 +243,42 @@ pub struct Backend<C>
     pub remove_messages: Option<BackendFeature<C, dyn RemoveMessages>>,
 }
 
+impl<C: BackendContext> Backend<C> {
+    /// Call the given feature through the layers of the backend.
+    async fn call_feature<'a, T, F, Fut>(
+        &'a self,
+        feature: BackendFeatureDescriptor,
+        f: F,
+    ) -> AnyResult<T>
+    where
+        T: Send + 'a,
+        F: Fn() -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = AnyResult<T>> + Send + 'a,
+    {
+        call_with_layers(&self.layers, feature, f).await
+    }
+
+    /// Forward the cancellation of the backend to the given watching
+    /// shutdown request, see [`forward_shutdown_request`].
+    ///
+    /// The returned task, if any, should be aborted once watching
+    /// stops.
+    #[cfg(feature = "watch")]
+    fn forward_shutdown_request(
+        &self,
+        wait_for_shutdown_request: Receiver<()>,
+    ) -> (Receiver<()>, Option<JoinHandle<()>>) {
+        match self.cancellation_token.clone() {
+            Some(token) => {
+                let (wait_for_shutdown_request, forward) =
+                    forward_shutdown_request(token, wait_for_shutdown_request);
+                (wait_for_shutdown_request, Some(forward))
+            }
+            None => (wait_for_shutdown_request, None),
+        }
+    }
+}
+
 impl<C: BackendContext> HasAccountConfig for Backend<C> {
     fn account_config(&self) -> &AccountConfig {
         &self.account_config
@@ -168,72 +288,237 @@ fn account_config(&self) -> &AccountConfig {
 #[async_trait]
 impl<C: BackendContext> AddFolder for Backend<C> {
     async fn add_folder(&self, folder: &str) -> AnyResult<()> {
-        self.add_folder
+        let feature = self
+            .add_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFolderNotAvailableError)?
-            .add_folder(folder)
-            .await
+            .ok_or(Error::AddFolderNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("add_folder"), || {
+            feature.add_folder(folder)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ListFolders for Backend<C> {
     async fn list_folders(&self) -> AnyResult<Folders> {
-        self.list_folders
+        let feature = self
+            .list_folders
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ListFoldersNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("list_folders"), || {
+            feature.list_folders()
+        })
+        .await
+    }
+
+    async fn folder_identity(&self, folder: &str) -> AnyResult<Option<String>> {
+        let feature = self
+            .list_folders
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListFoldersNotAvailableError)?
-            .list_folders()
-            .await
+            .ok_or(Error::ListFoldersNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("folder_identity"),
+            || feature.folder_identity(folder),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ExpungeFolder for Backend<C> {
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.expunge_folder
+        let feature = self
+            .expunge_folder
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ExpungeFolderNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("expunge_folder"),
+            || feature.expunge_folder(folder),
+        )
+        .await
+    }
+
+    async fn expunge_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        let feature = self
+            .expunge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ExpungeFolderNotAvailableError)?
-            .expunge_folder(folder)
-            .await
+            .ok_or(Error::ExpungeFolderNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("expunge_messages"),
+            || feature.expunge_messages(folder, id),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PurgeFolder for Backend<C> {
     async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.purge_folder
+        let feature = self
+            .purge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PurgeFolderNotAvailableError)?
-            .purge_folder(folder)
-            .await
+            .ok_or(Error::PurgeFolderNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("purge_folder"), || {
+            feature.purge_folder(folder)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteFolder for Backend<C> {
     async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
-        self.delete_folder
+        let feature = self
+            .delete_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteFolderNotAvailableError)?
-            .delete_folder(folder)
-            .await
+            .ok_or(Error::DeleteFolderNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("delete_folder"),
+            || feature.delete_folder(folder),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> SearchFolders for Backend<C> {
+    async fn search_folders(&self, patterns: &[String]) -> AnyResult<Folders> {
+        match self.search_folders.as_ref().and_then(|f| f(&self.context)) {
+            Some(feature) => {
+                self.call_feature(
+                    BackendFeatureDescriptor::idempotent("search_folders"),
+                    || feature.search_folders(patterns),
+                )
+                .await
+            }
+            // NOTE: backends unable to search folders by themselves
+            // fall back to listing all folders then filtering them
+            None => {
+                debug!("search folders feature not available, filtering listed folders");
+                let folders = self.list_folders().await?;
+                Ok(filter_folders(folders, patterns))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetFolder for Backend<C> {
+    async fn get_folder(&self, folder: &str) -> AnyResult<Option<Folder>> {
+        match self.get_folder.as_ref().and_then(|f| f(&self.context)) {
+            Some(feature) => {
+                self.call_feature(BackendFeatureDescriptor::idempotent("get_folder"), || {
+                    feature.get_folder(folder)
+                })
+                .await
+            }
+            // NOTE: backends unable to get a single folder fall back
+            // to listing all folders then finding the matching one
+            None => {
+                debug!("get folder feature not available, finding listed folder");
+                let folder = self.account_config.get_folder_alias(folder);
+                let folders = self.list_folders().await?;
+                Ok(find_folder(folders, &folder))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetFolderStats for Backend<C> {
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats> {
+        let feature = self
+            .get_folder_stats
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::GetFolderStatsNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("get_folder_stats"),
+            || feature.get_folder_stats(folder),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetQuota for Backend<C> {
+    async fn get_quota(&self, folder: &str) -> AnyResult<Vec<Quota>> {
+        let feature = self
+            .get_quota
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::GetQuotaNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("get_quota"), || {
+            feature.get_quota(folder)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetFolderMetadata for Backend<C> {
+    async fn get_folder_metadata(&self, folder: &str) -> AnyResult<FolderMetadata> {
+        let feature = self
+            .get_folder_metadata
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::GetFolderMetadataNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("get_folder_metadata"),
+            || feature.get_folder_metadata(folder),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> SetFolderMetadata for Backend<C> {
+    async fn set_folder_metadata(&self, folder: &str, metadata: &FolderMetadata) -> AnyResult<()> {
+        let feature = self
+            .set_folder_metadata
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::SetFolderMetadataNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("set_folder_metadata"),
+            || feature.set_folder_metadata(folder, metadata),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetEnvelope for Backend<C> {
     async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
-        self.get_envelope
+        let feature = self
+            .get_envelope
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetEnvelopeNotAvailableError)?
-            .get_envelope(folder, id)
-            .await
+            .ok_or(Error::GetEnvelopeNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("get_envelope"), || {
+            feature.get_envelope(folder, id)
+        })
+        .await
     }
 }
 
@@ -244,12 +529,28 @@ async fn list_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<Envelopes> {
-        self.list_envelopes
+        let feature = self
+            .list_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListEnvelopesNotAvailableError)?
-            .list_envelopes(folder, opts)
-            .await
+            .ok_or(Error::ListEnvelopesNotAvailableError)?;
+
+        let mut envelopes = self
+            .call_feature(
+                BackendFeatureDescriptor::idempotent("list_envelopes"),
+                || feature.list_envelopes(folder, opts.clone()),
+            )
+            .await?;
+
+        // backends paginating envelopes server-side do not need to
+        // set the next cursor themselves
+        if envelopes.next_cursor().is_none() {
+            envelopes.set_next_cursor(opts.next_cursor(&envelopes));
+        }
+
+        envelopes.mark_vip_senders(&self.account_config);
+
+        Ok(envelopes)
     }
 }
 
@@ -261,12 +562,17 @@ async fn thread_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
+        let feature = self
+            .thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelopes(folder, opts)
-            .await
+            .ok_or(Error::ThreadEnvelopesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("thread_envelopes"),
+            || feature.thread_envelopes(folder, opts.clone()),
+        )
+        .await
     }
 
     async fn thread_envelope(
@@ -275,15 +581,70 @@ async fn thread_envelope(
         id: SingleId,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
+        let feature = self
+            .thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelope(folder, id, opts)
-            .await
+            .ok_or(Error::ThreadEnvelopesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("thread_envelope"),
+            || feature.thread_envelope(folder, id.clone(), opts.clone()),
+        )
+        .await
     }
 }
 
+#[cfg(feature = "stream")]
+#[async_trait]
+impl<C: BackendContext> StreamEnvelopes for Backend<C> {
+    async fn stream_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<EnvelopesStream> {
+        let feature = self
+            .stream_envelopes
+            .as_ref()
+            .and_then(|feature| feature(&self.context));
+
+        // NOTE: backends that cannot stream envelopes fall back to
+        // listing all envelopes at once
+        let Some(feature) = feature else {
+            let opts = ListEnvelopesOptions {
+                page: 0,
+                page_size: 0,
+                after: None,
+                changed_since: None,
+                ..opts
+            };
+
+            let envelopes = self.list_envelopes(folder, opts).await?;
+            return Ok(stream::iter(envelopes.into_iter().map(Ok)).boxed());
+        };
+
+        let config = self.account_config.clone();
+        let stream = self
+            .call_feature(
+                BackendFeatureDescriptor::idempotent("stream_envelopes"),
+                || feature.stream_envelopes(folder, opts.clone()),
+            )
+            .await?;
+
+        let stream = stream.map(move |envelope| {
+            envelope.map(|mut envelope| {
+                envelope.mark_vip_sender(&config);
+                envelope
+            })
+        });
+
+        Ok(stream.boxed())
+    }
+}
+
+// NOTE: watching runs until a shutdown is requested, which is why
+// it is not wrapped by feature layers: cancelling the backend
+// requests the shutdown instead
 #[cfg(feature = "watch")]
 #[async_trait]
 impl<C: BackendContext> WatchEnvelopes for Backend<C> {
@@ -293,48 +654,115 @@ async fn watch_envelopes(
         wait_for_shutdown_request: Receiver<()>,
         shutdown: Sender<()>,
     ) -> AnyResult<()> {
-        self.watch_envelopes
+        let feature = self
+            .watch_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::WatchEnvelopesNotAvailableError)?
+            .ok_or(Error::WatchEnvelopesNotAvailableError)?;
+
+        let (wait_for_shutdown_request, forward) =
+            self.forward_shutdown_request(wait_for_shutdown_request);
+
+        let res = feature
             .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
-            .await
+            .await;
+
+        if let Some(forward) = forward {
+            forward.abort();
+        }
+
+        res
+    }
+
+    async fn watch_envelope_events(
+        &self,
+        folder: &str,
+        events: UnboundedSender<WatchEvent>,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        let feature = self
+            .watch_envelopes
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::WatchEnvelopesNotAvailableError)?;
+
+        let (wait_for_shutdown_request, forward) =
+            self.forward_shutdown_request(wait_for_shutdown_request);
+
+        let res = feature
+            .watch_envelope_events(folder, events, wait_for_shutdown_request, shutdown)
+            .await;
+
+        if let Some(forward) = forward {
+            forward.abort();
+        }
+
+        res
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> AddFlags for Backend<C> {
     async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.add_flags
+        let feature = self
+            .add_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFlagsNotAvailableError)?
-            .add_flags(folder, id, flags)
-            .await
+            .ok_or(Error::AddFlagsNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("add_flags"), || {
+            feature.add_flags(folder, id, flags)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> SetFlags for Backend<C> {
     async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.set_flags
+        let feature = self
+            .set_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SetFlagsNotAvailableError)?
-            .set_flags(folder, id, flags)
-            .await
+            .ok_or(Error::SetFlagsNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("set_flags"), || {
+            feature.set_flags(folder, id, flags)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveFlags for Backend<C> {
     async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.remove_flags
+        let feature = self
+            .remove_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveFlagsNotAvailableError)?
-            .remove_flags(folder, id, flags)
-            .await
+            .ok_or(Error::RemoveFlagsNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("remove_flags"), || {
+            feature.remove_flags(folder, id, flags)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> ListFlags for Backend<C> {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        let feature = self
+            .list_flags
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ListFlagsNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("list_flags"), || {
+            feature.list_flags(folder)
+        })
+        .await
     }
 }
 
@@ -346,96 +774,206 @@ async fn add_message_with_flags(
         msg: &[u8],
         flags: &Flags,
     ) -> AnyResult<SingleId> {
-        self.add_message
+        let feature = self
+            .add_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddMessageNotAvailableError)?
-            .add_message_with_flags(folder, msg, flags)
-            .await
+            .ok_or(Error::AddMessageNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::non_idempotent("add_message_with_flags"),
+            || feature.add_message_with_flags(folder, msg, flags),
+        )
+        .await
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<SingleId>> {
+        let feature = self
+            .add_message
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::AddMessageNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::non_idempotent("add_messages_with_flags"),
+            || feature.add_messages_with_flags(folder, msgs),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> SendMessage for Backend<C> {
-    async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
-        self.send_message
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
+        let msg = add_auto_recipients(&self.account_config, msg, &Default::default());
+
+        let feature = self
+            .send_message
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::SendMessageNotAvailableError)?;
+
+        let permit = self.send_rate_limiter.acquire().await;
+        let sent = self
+            .call_feature(
+                BackendFeatureDescriptor::non_idempotent("send_message"),
+                || feature.send_message(&msg),
+            )
+            .await?;
+        drop(permit);
+
+        self.auto_save_copy(&msg).await;
+        Ok(sent)
+    }
+
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        let msg = add_auto_recipients(&self.account_config, msg, opts);
+
+        let feature = self
+            .send_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SendMessageNotAvailableError)?
-            .send_message(msg)
-            .await
+            .ok_or(Error::SendMessageNotAvailableError)?;
+
+        let permit = self.send_rate_limiter.acquire().await;
+        let report = self
+            .call_feature(
+                BackendFeatureDescriptor::non_idempotent("send_message_with_options"),
+                || feature.send_message_with_options(&msg, opts),
+            )
+            .await?;
+        drop(permit);
+
+        self.auto_save_copy(&msg).await;
+        Ok(report)
+    }
+}
+
+impl<C: BackendContext> Backend<C> {
+    /// Save a copy of the given sent message to the sent folder, if
+    /// enabled by the account configuration.
+    ///
+    /// The message being already sent, failing to save the copy only
+    /// emits a warning: returning an error would lead callers to
+    /// send the message again.
+    async fn auto_save_copy(&self, msg: &[u8]) {
+        if !self.account_config.should_auto_save_copy_sent_message() {
+            return;
+        }
+
+        if let Err(_err) = self.add_message_with_flag(SENT, msg, Flag::Seen).await {
+            warn!("cannot save copy of sent message: {_err}");
+            debug!("{_err:?}");
+        }
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PeekMessages for Backend<C> {
     async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.peek_messages
+        let feature = self
+            .peek_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PeekMessagesNotAvailableError)?
-            .peek_messages(folder, id)
-            .await
+            .ok_or(Error::PeekMessagesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("peek_messages"),
+            || feature.peek_messages(folder, id),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetMessages for Backend<C> {
     async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.get_messages
+        let feature = self
+            .get_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetMessagesNotAvailableError)?
-            .get_messages(folder, id)
-            .await
+            .ok_or(Error::GetMessagesNotAvailableError)?;
+
+        self.call_feature(BackendFeatureDescriptor::idempotent("get_messages"), || {
+            feature.get_messages(folder, id)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> CopyMessages for Backend<C> {
     async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.copy_messages
+        let feature = self
+            .copy_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::CopyMessagesNotAvailableError)?
-            .copy_messages(from_folder, to_folder, id)
-            .await
+            .ok_or(Error::CopyMessagesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::non_idempotent("copy_messages"),
+            || feature.copy_messages(from_folder, to_folder, id),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> MoveMessages for Backend<C> {
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.move_messages
+        let feature = self
+            .move_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::MoveMessagesNotAvailableError)?
-            .move_messages(from_folder, to_folder, id)
-            .await
+            .ok_or(Error::MoveMessagesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::non_idempotent("move_messages"),
+            || feature.move_messages(from_folder, to_folder, id),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteMessages for Backend<C> {
     async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.delete_messages
+        let feature = self
+            .delete_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteMessagesNotAvailableError)?
-            .delete_messages(folder, id)
-            .await
+            .ok_or(Error::DeleteMessagesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("delete_messages"),
+            || feature.delete_messages(folder, id),
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveMessages for Backend<C> {
     async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.remove_messages
+        let feature = self
+            .remove_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveMessagesNotAvailableError)?
-            .remove_messages(folder, id)
-            .await
+            .ok_or(Error::RemoveMessagesNotAvailableError)?;
+
+        self.call_feature(
+            BackendFeatureDescriptor::idempotent("remove_messages"),
+            || feature.remove_messages(folder, id),
+        )
+        .await
     }
 }
 
@@ -511,6 +1049,13 @@ pub struct BackendBuilder<CB>
     pub account_config: Arc<AccountConfig>,
     /// The backend context builder.
     pub ctx_builder: CB,
+    /// The layers wrapping every feature call of the built backend.
+    pub layers: Vec<Arc<dyn BackendFeatureLayer>>,
+    /// The maximum duration of every feature call of the built
+    /// backend.
+    pub timeout: Option<Duration>,
+    /// The token cancelling every feature call of the built backend.
+    pub cancellation_token: Option<CancellationToken>,
 
     /// The noop backend builder feature.
     pub check_up: BackendFeatureSource<CB::Context, dyn CheckUp>,
@@ -525,6 +1070,18 @@ pub struct BackendBuilder<CB>
     pub purge_folder: BackendFeatureSource<CB::Context, dyn PurgeFolder>,
     /// The delete folder backend builder feature.
     pub delete_folder: BackendFeatureSource<CB::Context, dyn DeleteFolder>,
+    /// The get quota backend builder feature.
+    pub get_quota: BackendFeatureSource<CB::Context, dyn GetQuota>,
+    /// The search folders backend builder feature.
+    pub search_folders: BackendFeatureSource<CB::Context, dyn SearchFolders>,
+    /// The get folder backend builder feature.
+    pub get_folder: BackendFeatureSource<CB::Context, dyn GetFolder>,
+    /// The get folder stats backend builder feature.
+    pub get_folder_stats: BackendFeatureSource<CB::Context, dyn GetFolderStats>,
+    /// The get folder metadata backend builder feature.
+    pub get_folder_metadata: BackendFeatureSource<CB::Context, dyn GetFolderMetadata>,
+    /// The set folder metadata backend builder feature.
+    pub set_folder_metadata: BackendFeatureSource<CB::Context, dyn SetFolderMetadata>,
 
     /// The get envelope backend builder feature.
     pub get_envelope: BackendFeatureSource<CB::Context, dyn GetEnvelope>,
@@ -533,6 +1090,9 @@ pub struct BackendBuilder<CB>
     /// The thread envelopes backend builder feature.
     #[cfg(feature = "thread")]
     pub thread_envelopes: BackendFeatureSource<CB::Context, dyn ThreadEnvelopes>,
+    /// The stream envelopes backend builder feature.
+    #[cfg(feature = "stream")]
+    pub stream_envelopes: BackendFeatureSource<CB::Context, dyn StreamEnvelopes>,
     /// The watch envelopes backend builder feature.
     #[cfg(feature = "watch")]
     pub watch_envelopes: BackendFeatureSource<CB::Context, dyn WatchEnvelopes>,
@@ -543,6 +1103,8 @@ pub struct BackendBuilder<CB>
     pub set_flags: BackendFeatureSource<CB::Context, dyn SetFlags>,
     /// The remove flags backend builder feature.
     pub remove_flags: BackendFeatureSource<CB::Context, dyn RemoveFlags>,
+    /// The list flags backend builder feature.
+    pub list_flags: BackendFeatureSource<CB::Context, dyn ListFlags>,
 
     /// The add message backend builder feature.
     pub add_message: BackendFeatureSource<CB::Context, dyn AddMessage>,
@@ -572,15 +1134,24 @@ impl<CB> BackendBuilder<CB>
     feature_accessors!(ExpungeFolder);
     feature_accessors!(PurgeFolder);
     feature_accessors!(DeleteFolder);
+    feature_accessors!(GetQuota);
+    feature_accessors!(SearchFolders);
+    feature_accessors!(GetFolder);
+    feature_accessors!(GetFolderStats);
+    feature_accessors!(GetFolderMetadata);
+    feature_accessors!(SetFolderMetadata);
     feature_accessors!(GetEnvelope);
     feature_accessors!(ListEnvelopes);
     #[cfg(feature = "thread")]
     feature_accessors!(ThreadEnvelopes);
+    #[cfg(feature = "stream")]
+    feature_accessors!(StreamEnvelopes);
     #[cfg(feature = "watch")]
     feature_accessors!(WatchEnvelopes);
     feature_accessors!(AddFlags);
     feature_accessors!(SetFlags);
     feature_accessors!(RemoveFlags);
+    feature_accessors!(ListFlags);
     feature_accessors!(AddMessage);
     feature_accessors!(SendMessage);
     feature_accessors!(PeekMessages);
@@ -598,6 +1169,9 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
         Self {
             account_config,
             ctx_builder,
+            layers: Vec::new(),
+            timeout: None,
+            cancellation_token: None,
 
             check_up: BackendFeatureSource::Context,
 
@@ -606,17 +1180,26 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             expunge_folder: BackendFeatureSource::Context,
             purge_folder: BackendFeatureSource::Context,
             delete_folder: BackendFeatureSource::Context,
+            get_quota: BackendFeatureSource::Context,
+            search_folders: BackendFeatureSource::Context,
+            get_folder: BackendFeatureSource::Context,
+            get_folder_stats: BackendFeatureSource::Context,
+            get_folder_metadata: BackendFeatureSource::Context,
+            set_folder_metadata: BackendFeatureSource::Context,
 
             get_envelope: BackendFeatureSource::Context,
             list_envelopes: BackendFeatureSource::Context,
             #[cfg(feature = "thread")]
             thread_envelopes: BackendFeatureSource::Context,
+            #[cfg(feature = "stream")]
+            stream_envelopes: BackendFeatureSource::Context,
             #[cfg(feature = "watch")]
             watch_envelopes: BackendFeatureSource::Context,
 
             add_flags: BackendFeatureSource::Context,
             set_flags: BackendFeatureSource::Context,
             remove_flags: BackendFeatureSource::Context,
+            list_flags: BackendFeatureSource::Context,
 
             add_message: BackendFeatureSource::Context,
             send_message: BackendFeatureSource::Context,
@@ -629,18 +1212,87 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
         }
     }
 
+    /// Add the given layer, wrapping every feature call of the built
+    /// backend.
+    ///
+    /// Layers added first wrap layers added after them.
+    pub fn add_layer(&mut self, layer: impl BackendFeatureLayer + 'static) {
+        self.layers.push(Arc::new(layer));
+    }
+
+    /// Add the given layer, using the builder pattern.
+    pub fn with_layer(mut self, layer: impl BackendFeatureLayer + 'static) -> Self {
+        self.add_layer(layer);
+        self
+    }
+
+    /// Set the maximum duration of every feature call of the built
+    /// backend.
+    ///
+    /// Calls taking longer fail with
+    /// [`Error::FeatureTimedOutError`]. When retries are enabled,
+    /// the duration applies to each attempt.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = Some(duration);
+    }
+
+    /// Set the maximum duration of every feature call of the built
+    /// backend, using the builder pattern.
+    pub fn with_timeout(mut self, duration: Duration) -> Self {
+        self.set_timeout(duration);
+        self
+    }
+
+    /// Set the token cancelling every feature call of the built
+    /// backend, as well as the build of its context.
+    ///
+    /// Cancelled calls fail with [`Error::FeatureCancelledError`].
+    /// Watching features are not cancelled abruptly: they are asked
+    /// to shut down instead. See the [`cancel`] module.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation_token = Some(token);
+    }
+
+    /// Set the token cancelling every feature call of the built
+    /// backend, using the builder pattern.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.set_cancellation_token(token);
+        self
+    }
+
     /// Disable all features for this backend builder.
     pub fn without_features(mut self) -> Self {
         self.set_list_folders(BackendFeatureSource::None);
         self
     }
 
-    pub async fn check_up(self) -> AnyResult<()> {
-        let ctx = self.ctx_builder.clone().build().await?;
-        match self.get_check_up().and_then(move |f| f(&ctx)) {
-            Some(f) => f.check_up().await,
-            None => Ok(()),
+    /// Probe the connection to the server, build the context, then
+    /// check it up.
+    ///
+    /// Network backends authenticate while building their context,
+    /// which is why a build failure is reported as a failed
+    /// authentication probe. The returned report may contain failed
+    /// probes, see [`CheckUpReport::into_result`] for turning them
+    /// into an error.
+    pub async fn check_up(self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+
+        if !self.ctx_builder.probe_connection(&mut report).await {
+            report.skip(CheckUpProbeKind::Authentication, "connection failed");
+            return Ok(report);
         }
+
+        let build = self.ctx_builder.clone().build();
+        let Some(ctx) = report.probe(CheckUpProbeKind::Authentication, build).await else {
+            return Ok(report);
+        };
+
+        if let Some(f) = self.get_check_up().and_then(move |f| f(&ctx)) {
+            let ctx_report = f.check_up().await?;
+            report.probes.extend(ctx_report.probes);
+        }
+
+        Ok(report)
     }
 
     pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
@@ -649,17 +1301,26 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         let expunge_folder = self.get_expunge_folder();
         let purge_folder = self.get_purge_folder();
         let delete_folder = self.get_delete_folder();
+        let get_quota = self.get_get_quota();
+        let search_folders = self.get_search_folders();
+        let get_folder = self.get_get_folder();
+        let get_folder_stats = self.get_get_folder_stats();
+        let get_folder_metadata = self.get_get_folder_metadata();
+        let set_folder_metadata = self.get_set_folder_metadata();
 
         let get_envelope = self.get_get_envelope();
         let list_envelopes = self.get_list_envelopes();
         #[cfg(feature = "thread")]
         let thread_envelopes = self.get_thread_envelopes();
+        #[cfg(feature = "stream")]
+        let stream_envelopes = self.get_stream_envelopes();
         #[cfg(feature = "watch")]
         let watch_envelopes = self.get_watch_envelopes();
 
         let add_flags = self.get_add_flags();
         let set_flags = self.get_set_flags();
         let remove_flags = self.get_remove_flags();
+        let list_flags = self.get_list_flags();
 
         let add_message = self.get_add_message();
         let send_message = self.get_send_message();
@@ -670,26 +1331,62 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         let delete_messages = self.get_delete_messages();
         let remove_messages = self.get_remove_messages();
 
+        let send_rate_limiter = SendRateLimiter::from_account_config(&self.account_config);
+
+        // the retry and timeout layers come last, so that the other
+        // layers only see the outcome of the last attempt, and so
+        // that each attempt has its own timeout
+        let mut layers = self.layers;
+        if let Some(config) = self.account_config.find_retry() {
+            layers.push(Arc::new(RetryLayer::new(config.into())));
+        }
+        if let Some(duration) = self.timeout {
+            layers.push(Arc::new(TimeoutLayer::new(duration)));
+        }
+
+        // the cancellation layer comes first, so that cancelling a
+        // call also stops its retries
+        if let Some(token) = self.cancellation_token.clone() {
+            layers.insert(0, Arc::new(CancellationLayer::new(token)));
+        }
+
+        let context = match &self.cancellation_token {
+            Some(token) => cancellable(token, "build", self.ctx_builder.build()).await?,
+            None => self.ctx_builder.build().await?,
+        };
+
         Ok(Backend {
             account_config: self.account_config,
-            context: Arc::new(self.ctx_builder.build().await?),
+            context: Arc::new(context),
+            send_rate_limiter,
+            layers,
+            cancellation_token: self.cancellation_token,
 
             add_folder,
             list_folders,
             expunge_folder,
             purge_folder,
             delete_folder,
+            get_quota,
+            search_folders,
+            get_folder,
+            get_folder_stats,
+            get_folder_metadata,
+            set_folder_metadata,
 
             get_envelope,
             list_envelopes,
             #[cfg(feature = "thread")]
             thread_envelopes,
+            #[cfg(feature = "stream")]
+            stream_envelopes,
             #[cfg(feature = "watch")]
             watch_envelopes,
 
             add_flags,
             set_flags,
             remove_flags,
+            list_flags,
 
             add_message,
             send_message,
@@ -712,6 +1409,9 @@ fn clone(&self) -> Self {
         Self {
             account_config: self.account_config.clone(),
             ctx_builder: self.ctx_builder.clone(),
+            layers: self.layers.clone(),
+            timeout: self.timeout,
+            cancellation_token: self.cancellation_token.clone(),
 
             check_up: self.check_up.clone(),
 
@@ -720,17 +1420,26 @@ fn clone(&self) -> Self {
             expunge_folder: self.expunge_folder.clone(),
             purge_folder: self.purge_folder.clone(),
             delete_folder: self.delete_folder.clone(),
+            get_quota: self.get_quota.clone(),
+            search_folders: self.search_folders.clone(),
+            get_folder: self.get_folder.clone(),
+            get_folder_stats: self.get_folder_stats.clone(),
+            get_folder_metadata: self.get_folder_metadata.clone(),
+            set_folder_metadata: self.set_folder_metadata.clone(),
 
             get_envelope: self.get_envelope.clone(),
             list_envelopes: self.list_envelopes.clone(),
             #[cfg(feature = "thread")]
             thread_envelopes: self.thread_envelopes.clone(),
+            #[cfg(feature = "stream")]
+            stream_envelopes: self.stream_envelopes.clone(),
             #[cfg(feature = "watch")]
             watch_envelopes: self.watch_envelopes.clone(),
 
             add_flags: self.add_flags.clone(),
             set_flags: self.set_flags.clone(),
             remove_flags: self.remove_flags.clone(),
+            list_flags: self.list_flags.clone(),
 
             add_message: self.add_message.clone(),
             send_message: self.send_message.clone(),
@@ -0,0 +1,31 @@
+/// The backend retry configuration.
+///
+/// Backend features failing with a transient error (connection
+/// reset, server closing the connection, timeout…) are retried with
+/// an exponential backoff: the delay between two attempts doubles
+/// after each attempt, up to the maximum delay.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct RetryConfig {
+    /// The maximum number of attempts, including the first one.
+    /// Defaults to 3. Using 1 disables retries.
+    pub max_attempts: Option<u32>,
+
+    /// The delay before the first retry, in milliseconds. Defaults
+    /// to 500.
+    pub initial_delay: Option<u64>,
+
+    /// The maximum delay between two attempts, in milliseconds.
+    /// Defaults to 30000.
+    pub max_delay: Option<u64>,
+
+    /// Should randomize delays.
+    ///
+    /// Randomization prevents clients failing at the same time from
+    /// retrying at the same time. Defaults to `true`.
+    pub jitter: Option<bool>,
+}
@@ -0,0 +1,263 @@
+//! # Backend retry
+//!
+//! Module dedicated to the retry of backend features. Connections
+//! to remote servers get reset, servers close idle connections
+//! (IMAP BYE), and most of the time the same call succeeds a few
+//! moments later. The [`RetryLayer`] retries feature calls failing
+//! with a [transient](ErrorClass::Transient) error, following a
+//! [`RetryPolicy`].
+//!
+//! Backends built from an account configuration defining a retry
+//! configuration (see [`RetryConfig`]) retry by themselves. Other
+//! backends can add the layer using
+//! [`BackendBuilder::with_layer`](super::BackendBuilder::with_layer).
+
+pub mod config;
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+#[doc(inline)]
+pub use self::config::RetryConfig;
+use super::{
+    feature::{BackendFeatureDescriptor, BackendFeatureLayer, Next},
+    kit::{classify_error, ErrorClass},
+};
+use crate::{debug, runtime, AnyResult};
+
+/// The default maximum number of attempts.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The default delay before the first retry.
+pub const DEFAULT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// The default maximum delay between two attempts.
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// The retry policy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+
+    /// The maximum delay between two attempts.
+    pub max_delay: Duration,
+
+    /// Should randomize delays.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_delay: DEFAULT_INITIAL_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Return the delay to wait after the given failed attempt,
+    /// starting from 1.
+    ///
+    /// Randomized delays are picked between half the delay and the
+    /// delay itself.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay);
+
+        if !self.jitter {
+            return delay;
+        }
+
+        let half = delay / 2;
+        let random = RandomState::new().build_hasher().finish();
+        half + half.mul_f64((random % 1000) as f64 / 1000.)
+    }
+}
+
+impl From<&RetryConfig> for RetryPolicy {
+    fn from(config: &RetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            initial_delay: config
+                .initial_delay
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_INITIAL_DELAY),
+            max_delay: config
+                .max_delay
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_MAX_DELAY),
+            jitter: config.jitter.unwrap_or(true),
+        }
+    }
+}
+
+/// The retry backend feature layer.
+///
+/// Only transient errors are retried, see [`classify_error`].
+/// Non-idempotent features (adding, copying, moving or sending
+/// messages) are never retried, see
+/// [`BackendFeatureDescriptor::idempotent`].
+#[derive(Clone, Debug, Default)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    /// Create a new retry layer using the given policy.
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+#[async_trait]
+impl BackendFeatureLayer for RetryLayer {
+    async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+        if !feature.idempotent {
+            return next.run().await;
+        }
+
+        let mut attempt = 1;
+
+        loop {
+            match next.run().await {
+                Err(err)
+                    if attempt < self.policy.max_attempts
+                        && classify_error(&err) == ErrorClass::Transient =>
+                {
+                    let delay = self.policy.delay(attempt);
+                    debug!("cannot {feature} (attempt {attempt}), retrying in {delay:?}: {err}");
+                    runtime::sleep(delay).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::Any,
+        io,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use thiserror::Error;
+
+    use super::{RetryLayer, RetryPolicy};
+    use crate::{
+        backend::feature::{call_with_layers, BackendFeatureDescriptor, BackendFeatureLayer},
+        AnyBoxedError, AnyError, AnyResult,
+    };
+
+    #[derive(Debug, Error)]
+    #[error("cannot reach custom backend")]
+    struct CustomError(#[source] io::Error);
+
+    impl AnyError for CustomError {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn delays() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+        assert_eq!(policy.delay(100), Duration::from_secs(30));
+
+        let policy = RetryPolicy::default();
+
+        for attempt in 1..10 {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_millis(250));
+            assert!(delay <= Duration::from_secs(30));
+        }
+    }
+
+    async fn call(
+        feature: BackendFeatureDescriptor,
+        max_attempts: u32,
+        failures: u32,
+    ) -> (AnyResult<()>, u32) {
+        let layers: Vec<Arc<dyn BackendFeatureLayer>> =
+            vec![Arc::new(RetryLayer::new(policy(max_attempts)))];
+        let attempts = &AtomicU32::new(0);
+
+        let res = call_with_layers(&layers, feature, move || async move {
+            if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                let err = io::Error::from(io::ErrorKind::ConnectionReset);
+                let err: AnyBoxedError = Box::new(CustomError(err));
+                Err(err)
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+
+        (res, attempts.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn retries() {
+        let feature = BackendFeatureDescriptor::idempotent("list_folders");
+
+        let (res, attempts) = call(feature, 3, 2).await;
+        assert!(res.is_ok());
+        assert_eq!(attempts, 3);
+
+        let (res, attempts) = call(feature, 3, 3).await;
+        assert!(res.is_err());
+        assert_eq!(attempts, 3);
+
+        // messages are never sent twice
+        let feature = BackendFeatureDescriptor::non_idempotent("send_message");
+        let (res, attempts) = call(feature, 3, 1).await;
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+
+        // messages are never copied or moved twice
+        for feature in ["copy_messages", "move_messages"] {
+            let feature = BackendFeatureDescriptor::non_idempotent(feature);
+            let (res, attempts) = call(feature, 3, 1).await;
+            assert!(res.is_err());
+            assert_eq!(attempts, 1);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! # Backend timeout
+//!
+//! Module dedicated to the timeout of backend features. A slow or
+//! hung server should not block callers forever: the
+//! [`TimeoutLayer`] makes feature calls fail with
+//! [`Error::FeatureTimedOutError`] once the given duration has
+//! elapsed, see
+//! [`BackendBuilder::with_timeout`](super::BackendBuilder::with_timeout).
+//!
+//! Timed out calls are dropped in the middle of their commands. The
+//! IMAP backend re-connects such clients before using them again,
+//! see `imap::ImapClientGuard`.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::{
+    feature::{BackendFeatureDescriptor, BackendFeatureLayer, Next},
+    Error,
+};
+use crate::{debug, runtime, AnyResult};
+
+/// The timeout backend feature layer.
+#[derive(Clone, Debug)]
+pub struct TimeoutLayer {
+    duration: Duration,
+}
+
+impl TimeoutLayer {
+    /// Create a new timeout layer using the given duration.
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+}
+
+#[async_trait]
+impl BackendFeatureLayer for TimeoutLayer {
+    async fn call(&self, feature: BackendFeatureDescriptor, next: Next<'_>) -> AnyResult<()> {
+        match runtime::timeout(self.duration, next.run()).await {
+            Ok(res) => res,
+            Err(_) => {
+                debug!("{feature} timed out after {:?}", self.duration);
+                Err(Error::FeatureTimedOutError(feature.name, self.duration).into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::TimeoutLayer;
+    use crate::{
+        backend::{
+            feature::{call_with_layers, BackendFeatureDescriptor, BackendFeatureLayer},
+            kit::{classify_error, ErrorClass},
+        },
+        runtime,
+    };
+
+    #[tokio::test]
+    async fn timeout() {
+        let layers: Vec<Arc<dyn BackendFeatureLayer>> =
+            vec![Arc::new(TimeoutLayer::new(Duration::from_millis(50)))];
+        let feature = BackendFeatureDescriptor::idempotent("list_folders");
+
+        let output = call_with_layers(&layers, feature, || async { Ok(42) })
+            .await
+            .unwrap();
+        assert_eq!(output, 42);
+
+        let err = call_with_layers(&layers, feature, || async {
+            runtime::sleep(Duration::from_secs(5)).await;
+            Ok(42)
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "cannot call backend feature list_folders: timed out after 50ms"
+        );
+        assert_eq!(classify_error(&err), ErrorClass::Transient);
+    }
+}
@@ -0,0 +1,44 @@
+//! # Compatibility layer
+//!
+//! Module dedicated to the stability of the public API. Modules and
+//! types regularly move around, which used to break every downstream
+//! consumer at each release. Instead, old paths are kept as
+//! deprecated shims pointing to the new ones, so that consumers get
+//! a deprecation warning telling them where the item moved. Shims
+//! are kept at least until the next minor release.
+//!
+//! Shims are generated by the [`deprecated_module`] and
+//! [`deprecated_type`] macros, and are checked by the `api`
+//! integration test suite together with the stable paths.
+
+/// Generate a deprecated module re-exporting all the items of the
+/// given module.
+///
+/// ```rust,ignore
+/// deprecated_module!(smtp => crate::smtp, "0.26.0", "use `email::smtp` instead");
+/// ```
+macro_rules! deprecated_module {
+    ($name:ident => $($path:ident)::+, $since:literal, $note:literal) => {
+        #[doc(hidden)]
+        #[deprecated(since = $since, note = $note)]
+        pub mod $name {
+            pub use $($path)::+::*;
+        }
+    };
+}
+
+/// Generate a deprecated alias of the given type.
+///
+/// ```rust,ignore
+/// deprecated_type!(NewTplBuilder => NewTemplateBuilder, "0.26.0", "use `NewTemplateBuilder`");
+/// ```
+macro_rules! deprecated_type {
+    ($name:ident $(<$($lt:lifetime),+>)? => $($path:ident)::+, $since:literal, $note:literal) => {
+        #[doc(hidden)]
+        #[deprecated(since = $since, note = $note)]
+        pub type $name $(<$($lt),+>)? = $($path)::+ $(<$($lt),+>)?;
+    };
+}
+
+pub(crate) use deprecated_module;
+pub(crate) use deprecated_type;
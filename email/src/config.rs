@@ -80,10 +80,15 @@ pub fn account(&self, name: impl AsRef<str>) -> Result<AccountConfig> {
             flag: account_config.flag.clone(),
             message: account_config.message.clone(),
             template: account_config.template.clone(),
+            vip_senders: account_config.vip_senders.clone(),
+            retry: account_config.retry.clone(),
             #[cfg(feature = "sync")]
             sync: account_config.sync.clone(),
             #[cfg(feature = "pgp")]
             pgp: account_config.pgp.clone(),
+            #[cfg(feature = "network")]
+            network: account_config.network.clone(),
+            folder_aliases_cache: Default::default(),
         })
     }
 }
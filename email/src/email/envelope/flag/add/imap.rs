@@ -1,6 +1,5 @@
 use async_trait::async_trait;
 use imap_next::imap_types::sequence::{Sequence, SequenceSet};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{AddFlags, Flags};
 use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult, Error};
@@ -29,12 +28,11 @@ impl AddFlags for AddImapFlags {
     async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("adding imap flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("add_flags").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
         let uids: SequenceSet = match id {
             Id::Single(id) => Sequence::try_from(id.as_str())
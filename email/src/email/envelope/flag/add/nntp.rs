@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+
+use super::{AddFlags, Flag, Flags};
+use crate::{debug, envelope::Id, info, nntp::NntpContextSync, AnyResult};
+
+/// Add NNTP flags.
+///
+/// Newsgroups have no notion of flags: only the [`Flag::Seen`] flag
+/// is supported, using the local read state.
+#[derive(Clone)]
+pub struct AddNntpFlags {
+    ctx: NntpContextSync,
+}
+
+impl AddNntpFlags {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn AddFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn AddFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl AddFlags for AddNntpFlags {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("adding NNTP flag(s) {flags} to articles {id} from newsgroup {folder}");
+
+        if !flags.contains(&Flag::Seen) {
+            debug!("NNTP backend only supports the seen flag, ignoring flag(s) {flags}");
+            return Ok(());
+        }
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.mark_articles(folder, id, true).await?;
+
+        Ok(())
+    }
+}
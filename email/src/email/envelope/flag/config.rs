@@ -11,4 +11,20 @@ pub struct FlagConfig {
     #[cfg(feature = "sync")]
     /// Configuration dedicated to flag synchronization.
     pub sync: Option<FlagSyncConfig>,
+
+    /// Report flags that a backend cannot represent instead of
+    /// silently dropping them.
+    ///
+    /// Backends do not share the same flag capabilities: Maildir for
+    /// example cannot represent IMAP keywords. When enabled, the
+    /// synchronization checks flags after writing them and collects
+    /// the dropped ones in its report. Defaults to `false`.
+    pub strict: Option<bool>,
+
+    /// Preserve flags that a backend cannot represent in a local
+    /// sidecar [`FlagStore`](super::store::FlagStore).
+    ///
+    /// Only supported by the Maildir backend, which stores them in a
+    /// `.flags` file at the root of the Maildir. Defaults to `false`.
+    pub preserve_unsupported: Option<bool>,
 }
@@ -27,6 +27,26 @@ pub fn from_imap_flag_fetches(fetches: &[FlagFetch<'_>]) -> Self {
         }))
     }
 
+    /// Build flags from the given IMAP flags, as returned by the
+    /// FLAGS and PERMANENTFLAGS responses.
+    ///
+    /// Unlike fetched flags, keywords are kept as custom flags.
+    /// Unknown system flags are skipped.
+    pub fn from_imap_flags<'a>(flags: impl IntoIterator<Item = &'a ImapFlag<'a>>) -> Self {
+        Flags::from_iter(flags.into_iter().filter_map(|flag| match flag {
+            ImapFlag::Seen => Some(Flag::Seen),
+            ImapFlag::Answered => Some(Flag::Answered),
+            ImapFlag::Flagged => Some(Flag::Flagged),
+            ImapFlag::Deleted => Some(Flag::Deleted),
+            ImapFlag::Draft => Some(Flag::Draft),
+            ImapFlag::Keyword(keyword) => Some(Flag::custom(keyword.as_ref())),
+            flag => {
+                trace!("skipping unknown IMAP flag {flag}");
+                None
+            }
+        }))
+    }
+
     pub fn to_imap_flags_iter(
         &self,
     ) -> impl IntoIterator<Item = ImapFlag<'static>> + fmt::Debug + Clone + '_ {
@@ -96,3 +116,23 @@ fn try_from(flag: Flag) -> Result<SearchKey<'a>, Self::Error> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use imap_next::imap_types::flag::Flag as ImapFlag;
+
+    use crate::flag::{Flag, Flags};
+
+    #[test]
+    fn from_imap_flags() {
+        let flags = [
+            ImapFlag::Seen,
+            ImapFlag::Draft,
+            ImapFlag::Keyword("$Label1".try_into().unwrap()),
+            ImapFlag::system("Unknown".try_into().unwrap()),
+        ];
+
+        let expected = Flags::from_iter([Flag::Seen, Flag::Draft, Flag::custom("$Label1")]);
+        assert_eq!(Flags::from_imap_flags(&flags), expected);
+    }
+}
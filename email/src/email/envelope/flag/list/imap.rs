@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use imap_next::imap_types::flag::FlagPerm;
+
+use super::{Flags, ListFlags};
+use crate::{debug, imap::ImapContext, info, AnyResult};
+
+#[derive(Clone, Debug)]
+pub struct ListImapFlags {
+    ctx: ImapContext,
+}
+
+impl ListImapFlags {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListImapFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing imap flags from folder {folder}");
+
+        let mut client = self.ctx.client_for("list_flags").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let data = client.select_mailbox(&folder_encoded).await?;
+
+        // the FLAGS response lists flags defined in the mailbox,
+        // while the PERMANENTFLAGS response lists flags that can be
+        // changed permanently, which may include extra keywords
+        let permanent_flags = data
+            .permanent_flags
+            .iter()
+            .flatten()
+            .filter_map(|flag| match flag {
+                FlagPerm::Flag(flag) => Some(flag),
+                FlagPerm::Asterisk => None,
+            });
+
+        let flags = data.flags.iter().flatten().chain(permanent_flags);
+        let flags = Flags::from_imap_flags(flags);
+        debug!("found imap flags: {flags}");
+
+        Ok(flags)
+    }
+}
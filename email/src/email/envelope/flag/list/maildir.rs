@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+
+use super::{Flags, ListFlags};
+use crate::{debug, email::error::Error, info, maildir::MaildirContextSync, trace, AnyResult};
+
+#[derive(Clone)]
+pub struct ListMaildirFlags {
+    ctx: MaildirContextSync,
+}
+
+impl ListMaildirFlags {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListMaildirFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing maildir flags from folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+
+        let mut flags = Flags::default();
+
+        for entry in mdir.read().map_err(Error::ListMaildirEntriesError)? {
+            match Flags::try_from(entry) {
+                Ok(entry_flags) => flags.extend(entry_flags.iter().cloned()),
+                Err(_err) => {
+                    debug!("cannot get maildir entry flags, skipping it: {_err}");
+                    trace!("{_err:?}");
+                }
+            }
+        }
+
+        if let Some(store) = ctx.load_flag_store().await? {
+            let folder = ctx.account_config.get_folder_alias(folder);
+            flags.extend(store.get_all(&folder).iter().cloned());
+        }
+
+        debug!("found maildir flags: {flags}");
+
+        Ok(flags)
+    }
+}
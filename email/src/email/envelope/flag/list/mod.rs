@@ -0,0 +1,21 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+#[cfg(feature = "notmuch")]
+pub mod notmuch;
+
+use async_trait::async_trait;
+
+use super::Flags;
+use crate::AnyResult;
+
+#[async_trait]
+pub trait ListFlags: Send + Sync {
+    /// List all the flags in use in the given folder.
+    ///
+    /// The returned set contains system flags as well as custom
+    /// flags (also known as keywords or tags), which is useful for
+    /// building flag filters dynamically.
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags>;
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+
+use super::{Flags, ListFlags};
+use crate::{
+    debug, email::error::Error, folder::FolderKind, info, notmuch::NotmuchContextSync, AnyResult,
+};
+
+#[derive(Clone)]
+pub struct ListNotmuchFlags {
+    ctx: NotmuchContextSync,
+}
+
+impl ListNotmuchFlags {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListNotmuchFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing notmuch flags from folder {folder}");
+
+        let config = &self.ctx.account_config;
+        let ctx = self.ctx.lock().await;
+        let db = ctx.open_db()?;
+
+        let ref folder = config.get_folder_alias(folder);
+        let query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        };
+        debug!("notmuch query: {query:?}");
+
+        let query_builder = db.create_query(&query).map_err(Error::NotMuchFailure)?;
+        let msgs = query_builder
+            .search_messages()
+            .map_err(Error::NotMuchFailure)?;
+
+        let mut flags = Flags::default();
+
+        for msg in msgs {
+            flags.extend(Flags::from(&msg).iter().cloned());
+        }
+
+        db.close().map_err(Error::NotMuchFailure)?;
+        debug!("found notmuch flags: {flags}");
+
+        Ok(flags)
+    }
+}
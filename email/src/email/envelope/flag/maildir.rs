@@ -7,12 +7,84 @@
 
 use maildirs::MaildirEntry;
 
-use super::{Flag, Flags};
+use super::{store::FlagStore, Flag, Flags};
 use crate::{
     debug,
     email::error::{Error, Result},
+    envelope::Envelope,
+    maildir::MaildirContext,
 };
 
+impl Flags {
+    /// Return the flags that cannot be represented by Maildir.
+    pub fn unsupported_by_maildir(&self) -> Flags {
+        self.iter()
+            .filter(|flag| maildirs::Flag::try_from(*flag).is_err())
+            .cloned()
+            .collect()
+    }
+}
+
+impl MaildirContext {
+    /// Load the local flag store, if flags unsupported by Maildir
+    /// should be preserved.
+    pub async fn load_flag_store(&self) -> Result<Option<FlagStore>> {
+        match self.get_flag_store_path() {
+            Some(path) => Ok(Some(FlagStore::load(&path).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Merge the flags preserved in the local flag store into the
+    /// given envelopes.
+    pub async fn merge_stored_flags<'a>(
+        &self,
+        folder: &str,
+        envelopes: impl IntoIterator<Item = &'a mut Envelope>,
+    ) -> Result<()> {
+        let Some(store) = self.load_flag_store().await? else {
+            return Ok(());
+        };
+
+        let folder = self.account_config.get_folder_alias(folder);
+
+        for envelope in envelopes {
+            let flags = store.get(&folder, &envelope.id);
+            envelope.flags.extend(flags.iter().cloned());
+        }
+
+        Ok(())
+    }
+
+    /// Update the flags preserved in the local flag store for the
+    /// given envelopes.
+    ///
+    /// The given function receives the stored flags of each envelope
+    /// and updates them. Does nothing if flags unsupported by Maildir
+    /// should not be preserved.
+    pub async fn update_stored_flags<'a>(
+        &self,
+        folder: &str,
+        ids: impl IntoIterator<Item = &'a str>,
+        update: impl Fn(&mut Flags),
+    ) -> Result<()> {
+        let Some(path) = self.get_flag_store_path() else {
+            return Ok(());
+        };
+
+        let folder = self.account_config.get_folder_alias(folder);
+        let mut store = FlagStore::load(&path).await?;
+
+        for id in ids {
+            let mut flags = store.get(&folder, id);
+            update(&mut flags);
+            store.set(&folder, id, flags);
+        }
+
+        store.save(&path).await
+    }
+}
+
 impl TryFrom<MaildirEntry> for Flags {
     type Error = Error;
 
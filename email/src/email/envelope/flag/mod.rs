@@ -7,12 +7,14 @@
 pub mod config;
 #[cfg(feature = "imap")]
 pub mod imap;
+pub mod list;
 #[cfg(feature = "maildir")]
 pub mod maildir;
-#[cfg(feature = "notmuch")]
+#[cfg(any(feature = "notmuch", feature = "notmuch-remote"))]
 pub mod notmuch;
 pub mod remove;
 pub mod set;
+pub mod store;
 #[cfg(feature = "sync")]
 pub mod sync;
 
@@ -1,21 +1,24 @@
 //! Module dedicated to Notmuch email envelope flags.
 //!
-//! This module contains flag-related mapping functions from the
-//! [notmuch] crate types.
+//! This module contains flag-related mapping functions from Notmuch
+//! tags, shared by the local and the remote Notmuch backends.
 
+#[cfg(feature = "notmuch")]
 use notmuch::Message;
 
-use crate::flag::Flags;
-
 use super::Flag;
+use crate::flag::Flags;
 
-impl From<&Message> for Flags {
-    fn from(msg: &Message) -> Self {
+impl Flags {
+    /// Build flags from the given Notmuch tags.
+    ///
+    /// Messages without the `unread` tag are considered seen.
+    pub fn from_notmuch_tags(tags: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
         let mut flags = Flags::default();
         let mut unread = false;
 
-        for tag in msg.tags() {
-            match tag.as_str() {
+        for tag in tags {
+            match tag.as_ref() {
                 "draft" => {
                     flags.insert(Flag::Draft);
                 }
@@ -41,3 +44,10 @@ fn from(msg: &Message) -> Self {
         flags
     }
 }
+
+#[cfg(feature = "notmuch")]
+impl From<&Message> for Flags {
+    fn from(msg: &Message) -> Self {
+        Flags::from_notmuch_tags(msg.tags())
+    }
+}
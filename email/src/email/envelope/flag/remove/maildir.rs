@@ -45,6 +45,14 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
                 })
             })?;
 
+        let unsupported = flags.unsupported_by_maildir();
+        if !unsupported.is_empty() {
+            ctx.update_stored_flags(folder, id.iter(), |stored| {
+                stored.retain(|flag| !unsupported.contains(flag))
+            })
+            .await?;
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+
+use super::{Flag, Flags, RemoveFlags};
+use crate::{debug, envelope::Id, info, nntp::NntpContextSync, AnyResult};
+
+/// Remove NNTP flags.
+///
+/// Newsgroups have no notion of flags: only the [`Flag::Seen`] flag
+/// is supported, using the local read state.
+#[derive(Clone)]
+pub struct RemoveNntpFlags {
+    ctx: NntpContextSync,
+}
+
+impl RemoveNntpFlags {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn RemoveFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn RemoveFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl RemoveFlags for RemoveNntpFlags {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("removing NNTP flag(s) {flags} to articles {id} from newsgroup {folder}");
+
+        if !flags.contains(&Flag::Seen) {
+            debug!("NNTP backend only supports the seen flag, ignoring flag(s) {flags}");
+            return Ok(());
+        }
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.mark_articles(folder, id, false).await?;
+
+        Ok(())
+    }
+}
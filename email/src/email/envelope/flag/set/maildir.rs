@@ -45,6 +45,10 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
                 })
             })?;
 
+        let unsupported = flags.unsupported_by_maildir();
+        ctx.update_stored_flags(folder, id.iter(), |stored| *stored = unsupported.clone())
+            .await?;
+
         Ok(())
     }
 }
@@ -2,6 +2,8 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 
@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+
+use super::{Flag, Flags, SetFlags};
+use crate::{envelope::Id, info, nntp::NntpContextSync, AnyResult};
+
+/// Set NNTP flags.
+///
+/// Newsgroups have no notion of flags: only the [`Flag::Seen`] flag
+/// is supported, using the local read state. Articles are marked as
+/// unread when the given flags do not contain it.
+#[derive(Clone)]
+pub struct SetNntpFlags {
+    ctx: NntpContextSync,
+}
+
+impl SetNntpFlags {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn SetFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn SetFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SetFlags for SetNntpFlags {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("setting NNTP flag(s) {flags} to articles {id} from newsgroup {folder}");
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.mark_articles(folder, id, flags.contains(&Flag::Seen))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Module dedicated to the local flag store.
+//!
+//! Backends that cannot represent some flags (like Maildir, which
+//! only knows a fixed set of flags) can preserve them in a local
+//! sidecar file instead of dropping them. Each line contains a folder
+//! name, an envelope identifier and the flags of the envelope,
+//! separated by tabulations. Names, identifiers and flags are
+//! percent-encoded:
+//!
+//! ```text
+//! Archives%2F2024	1700000000.M1P2.host	%24Label1 %24Forwarded
+//! ```
+
+use std::{collections::BTreeMap, fmt, io, path::Path};
+
+use tokio::fs;
+
+use super::{Flag, Flags};
+use crate::email::error::{Error, Result};
+
+/// The local flag store.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FlagStore {
+    folders: BTreeMap<String, BTreeMap<String, Flags>>,
+}
+
+impl FlagStore {
+    /// Parse the store from the given content.
+    ///
+    /// Parsing is lenient: invalid lines and flags are ignored.
+    pub fn parse(input: &str) -> Self {
+        let mut store = Self::default();
+
+        for line in input.lines() {
+            let mut parts = line.splitn(3, '\t');
+
+            let (Some(Ok(folder)), Some(Ok(id)), Some(flags)) = (
+                parts.next().map(urlencoding::decode),
+                parts.next().map(urlencoding::decode),
+                parts.next(),
+            ) else {
+                continue;
+            };
+
+            if folder.is_empty() || id.is_empty() {
+                continue;
+            }
+
+            let flags = flags
+                .split_whitespace()
+                .filter_map(|flag| urlencoding::decode(flag).ok())
+                .map(|flag| Flag::from(flag.as_ref()))
+                .collect();
+
+            store.set(&folder, &id, flags);
+        }
+
+        store
+    }
+
+    /// Load the store from the given file.
+    ///
+    /// A missing file is considered as an empty store.
+    pub async fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path).await {
+            Ok(content) => Ok(Self::parse(&content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::ReadFlagStoreError(err, path.to_owned())),
+        }
+    }
+
+    /// Save the store to the given file.
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .await
+                .map_err(|err| Error::WriteFlagStoreError(err, path.to_owned()))?;
+        }
+
+        fs::write(path, self.to_string())
+            .await
+            .map_err(|err| Error::WriteFlagStoreError(err, path.to_owned()))
+    }
+
+    /// Get the flags of the given envelope.
+    pub fn get(&self, folder: &str, id: &str) -> Flags {
+        self.folders
+            .get(folder)
+            .and_then(|envelopes| envelopes.get(id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get the flags of all the envelopes of the given folder.
+    pub fn get_all(&self, folder: &str) -> Flags {
+        self.folders
+            .get(folder)
+            .into_iter()
+            .flat_map(|envelopes| envelopes.values())
+            .flat_map(|flags| flags.iter().cloned())
+            .collect()
+    }
+
+    /// Set the flags of the given envelope.
+    pub fn set(&mut self, folder: &str, id: &str, flags: Flags) {
+        if flags.is_empty() {
+            if let Some(envelopes) = self.folders.get_mut(folder) {
+                envelopes.remove(id);
+
+                if envelopes.is_empty() {
+                    self.folders.remove(folder);
+                }
+            }
+        } else {
+            self.folders
+                .entry(folder.to_owned())
+                .or_default()
+                .insert(id.to_owned(), flags);
+        }
+    }
+}
+
+impl fmt::Display for FlagStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (folder, envelopes) in &self.folders {
+            for (id, flags) in envelopes {
+                let folder = urlencoding::encode(folder);
+                let id = urlencoding::encode(id);
+                write!(f, "{folder}\t{id}\t")?;
+
+                for (i, flag) in flags.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", urlencoding::encode(&flag.to_string()))?;
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlagStore;
+    use crate::envelope::{Flag, Flags};
+
+    #[test]
+    fn parse_and_display() {
+        let store = FlagStore::parse(concat!(
+            "Archives%2F2024\t1.M1.host\t%24Label1 %24Forwarded\n",
+            "INBOX\t2.M2.host\t\n",
+            "INBOX\t3.M3.host\tNonJunk\n",
+            "\t4.M4.host\tinvalid\n",
+            "INBOX\n",
+        ));
+
+        assert_eq!(
+            store.get("Archives/2024", "1.M1.host"),
+            Flags::from_iter([Flag::custom("$Label1"), Flag::custom("$Forwarded")]),
+        );
+        assert_eq!(store.get("INBOX", "2.M2.host"), Flags::default());
+        assert_eq!(
+            store.get("INBOX", "3.M3.host"),
+            Flags::from_iter([Flag::custom("NonJunk")]),
+        );
+
+        assert_eq!(
+            store.to_string(),
+            "Archives%2F2024\t1.M1.host\t%24Forwarded %24Label1\nINBOX\t3.M3.host\tNonJunk\n"
+        );
+    }
+
+    #[test]
+    fn set_flags() {
+        let mut store = FlagStore::default();
+        let flags = Flags::from_iter([Flag::custom("$Label1")]);
+
+        store.set("INBOX", "1.M1.host", flags.clone());
+        assert_eq!(store.get("INBOX", "1.M1.host"), flags);
+
+        store.set("INBOX", "1.M1.host", Flags::default());
+        assert!(store.to_string().is_empty());
+    }
+}
@@ -1,5 +1,4 @@
 use async_trait::async_trait;
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{Envelope, GetEnvelope};
 use crate::{debug, envelope::SingleId, imap::ImapContext, info, AnyResult};
@@ -28,12 +27,11 @@ impl GetEnvelope for GetImapEnvelope {
     async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
         info!("getting imap envelope {id:?} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("get_envelope").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
         client.select_mailbox(&folder_encoded).await?;
 
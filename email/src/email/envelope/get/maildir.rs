@@ -31,7 +31,8 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
         let mdir = session.get_maildir_from_folder_alias(folder)?;
 
         let entry = mdir.get(id.to_string()).map_err(Error::from)?;
-        let envelope = Envelope::try_from(entry)?;
+        let mut envelope = Envelope::try_from(entry)?;
+        session.merge_stored_flags(folder, [&mut envelope]).await?;
         trace!("maildir envelope: {envelope:#?}");
 
         Ok(envelope)
@@ -13,6 +13,7 @@
 use once_cell::sync::Lazy;
 
 use crate::{
+    backend::extensions::IMAP_MODSEQ,
     envelope::{Envelope, Envelopes},
     flag::Flags,
     message::Message,
@@ -30,6 +31,16 @@
     ])
 });
 
+/// The IMAP fetch items needed to retrieve the modification sequence
+/// of messages, as defined by the CONDSTORE extension.
+pub static FETCH_MOD_SEQS: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
+    MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+        MessageDataItemName::Uid,
+        MessageDataItemName::Flags,
+        MessageDataItemName::ModSeq,
+    ])
+});
+
 impl Envelopes {
     pub fn from_imap_data_items(fetches: HashMap<NonZeroU32, Vec1<MessageDataItem>>) -> Self {
         fetches
@@ -54,6 +65,7 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let mut flags = Flags::default();
         let mut msg = Vec::default();
         let mut has_attachment = false;
+        let mut mod_seq = None;
 
         for item in items {
             match item {
@@ -70,6 +82,12 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
                         msg.push(b'\n');
                     }
 
+                    if let Some(in_reply_to) = envelope.in_reply_to.0.as_ref() {
+                        msg.extend(b"In-Reply-To: ");
+                        msg.extend(in_reply_to.as_ref());
+                        msg.push(b'\n');
+                    }
+
                     if let Some(date) = envelope.date.0.as_ref() {
                         msg.extend(b"Date: ");
                         msg.extend(date.as_ref());
@@ -149,6 +167,9 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
                 MessageDataItem::BodyStructure(body) => {
                     has_attachment = has_at_least_one_attachment([body]);
                 }
+                MessageDataItem::ModSeq(seq) => {
+                    mod_seq = Some(seq.get());
+                }
                 _ => (),
             }
         }
@@ -156,6 +177,11 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let msg = Message::from(msg);
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+
+        if let Some(mod_seq) = mod_seq {
+            env.extensions.set(IMAP_MODSEQ, mod_seq);
+        }
+
         env
     }
 }
@@ -0,0 +1,221 @@
+//! Module dedicated to cursor-based pagination of envelopes.
+//!
+//! The core structure of this module is the [`EnvelopesCursor`].
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    email::error::Error,
+    envelope::{Address, Envelope},
+};
+
+/// The version of the cursor token format.
+const TOKEN_VERSION: &str = "c1";
+
+/// The envelopes pagination cursor.
+///
+/// The cursor points to the last envelope of a page. Unlike page
+/// indexes, it does not shift when envelopes are added to or removed
+/// from the folder between two listings: the next page starts right
+/// after the envelope the cursor points to, in the sort order of the
+/// listing, even if this envelope does not exist anymore.
+///
+/// The cursor is meant to be opaque: it can be turned into a string
+/// token using [`ToString`], and parsed back using [`FromStr`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EnvelopesCursor {
+    /// The identifier of the envelope.
+    id: String,
+
+    /// The sort keys of the envelope.
+    from: Address,
+    to: Address,
+    subject: String,
+    date: DateTime<FixedOffset>,
+}
+
+impl EnvelopesCursor {
+    /// Return the identifier of the envelope the cursor points to.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Return the date of the envelope the cursor points to.
+    pub fn date(&self) -> &DateTime<FixedOffset> {
+        &self.date
+    }
+
+    /// Compare the given envelope with the envelope the cursor
+    /// points to, using the given envelopes comparator.
+    pub fn cmp_envelope(
+        &self,
+        envelope: &Envelope,
+        cmp: impl Fn(&Envelope, &Envelope) -> Ordering,
+    ) -> Ordering {
+        let cursor = Envelope {
+            id: self.id.clone(),
+            from: self.from.clone(),
+            to: self.to.clone(),
+            subject: self.subject.clone(),
+            date: self.date,
+            ..Default::default()
+        };
+
+        cmp(envelope, &cursor)
+    }
+}
+
+impl From<&Envelope> for EnvelopesCursor {
+    fn from(envelope: &Envelope) -> Self {
+        Self {
+            id: envelope.id.clone(),
+            from: envelope.from.clone(),
+            to: envelope.to.clone(),
+            subject: envelope.subject.clone(),
+            date: envelope.date,
+        }
+    }
+}
+
+impl fmt::Display for EnvelopesCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encode_addr = |addr: &Address| {
+            let name = match &addr.name {
+                Some(name) => format!("+{}", urlencoding::encode(name)),
+                None => String::from("-"),
+            };
+            format!("{name}:{}", urlencoding::encode(&addr.addr))
+        };
+
+        write!(
+            f,
+            "{TOKEN_VERSION}:{}:{}:{}:{}:{}",
+            urlencoding::encode(&self.id),
+            urlencoding::encode(&self.date.to_rfc3339()),
+            encode_addr(&self.from),
+            encode_addr(&self.to),
+            urlencoding::encode(&self.subject),
+        )
+    }
+}
+
+impl FromStr for EnvelopesCursor {
+    type Err = Error;
+
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let err = || Error::ParseEnvelopesCursorError(token.to_owned());
+
+        let decode = |part: Option<&str>| {
+            let part = part.ok_or_else(err)?;
+            let part = urlencoding::decode(part).map_err(|_| err())?;
+            Ok::<_, Error>(part.into_owned())
+        };
+
+        let mut parts = token.split(':');
+
+        if parts.next() != Some(TOKEN_VERSION) {
+            return Err(err());
+        }
+
+        let id = decode(parts.next())?;
+        let date = DateTime::parse_from_rfc3339(&decode(parts.next())?).map_err(|_| err())?;
+
+        let mut decode_addr = || {
+            let name = match parts.next() {
+                Some("-") => None,
+                Some(name) => Some(decode(name.strip_prefix('+'))?),
+                None => return Err(err()),
+            };
+            let addr = decode(parts.next())?;
+            Ok(Address { name, addr })
+        };
+
+        let from = decode_addr()?;
+        let to = decode_addr()?;
+        let subject = decode(parts.next())?;
+
+        if parts.next().is_some() {
+            return Err(err());
+        }
+
+        Ok(Self {
+            id,
+            from,
+            to,
+            subject,
+            date,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::EnvelopesCursor;
+    use crate::envelope::{list::ListEnvelopesOptions, Address, Envelope, Envelopes};
+
+    #[test]
+    fn cursor_token() {
+        let envelope = Envelope {
+            id: "42".into(),
+            from: Address::new(Some("Alice: Doe"), "alice@localhost"),
+            to: Address::new_nameless("bob@localhost"),
+            subject: "Hello: world!".into(),
+            date: DateTime::parse_from_rfc3339("2024-05-16T10:00:00+02:00").unwrap(),
+            ..Default::default()
+        };
+
+        let cursor = EnvelopesCursor::from(&envelope);
+        let token = cursor.to_string();
+
+        assert_eq!(token.parse::<EnvelopesCursor>().unwrap(), cursor);
+        assert!("c1:42".parse::<EnvelopesCursor>().is_err());
+        assert!("invalid".parse::<EnvelopesCursor>().is_err());
+    }
+
+    #[test]
+    fn apply_cursor() {
+        let envelope = |id: &str, date: &str| Envelope {
+            id: id.into(),
+            date: DateTime::parse_from_rfc3339(date).unwrap(),
+            ..Default::default()
+        };
+
+        let mut envelopes = Envelopes::from_iter([
+            envelope("1", "2024-05-16T08:00:00Z"),
+            envelope("2", "2024-05-16T09:00:00Z"),
+            envelope("3", "2024-05-16T09:00:00Z"),
+            envelope("4", "2024-05-16T10:00:00Z"),
+        ]);
+
+        let mut opts = ListEnvelopesOptions {
+            page_size: 2,
+            ..Default::default()
+        };
+
+        opts.sort_envelopes(&mut envelopes);
+
+        let mut page = envelopes.clone();
+        opts.apply_cursor(&mut page);
+        let ids: Vec<_> = page.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["4", "2"]);
+
+        // the envelope the cursor points to has been deleted
+        opts.after = page.next_cursor().cloned();
+        envelopes.retain(|e| e.id != "2");
+
+        let mut page = envelopes.clone();
+        opts.apply_cursor(&mut page);
+        let ids: Vec<_> = page.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["3", "1"]);
+        assert!(page.next_cursor().is_some());
+
+        opts.after = page.next_cursor().cloned();
+        opts.apply_cursor(&mut envelopes);
+        assert!(envelopes.is_empty());
+        assert!(envelopes.next_cursor().is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, num::NonZeroU32, result};
+use std::{collections::HashMap, num::NonZeroU32, result, time::Instant};
 
 use async_trait::async_trait;
 use chrono::TimeDelta;
@@ -9,16 +9,15 @@
     search::SearchKey,
     sequence::{SeqOrUid, Sequence, SequenceSet},
 };
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use super::{Envelopes, EnvelopesChangesToken, ListEnvelopes, ListEnvelopesOptions};
 use crate::{
     debug,
     email::error::Error,
     envelope::Envelope,
     imap,
-    imap::ImapContext,
-    info,
+    imap::{batch::FetchBatchSizer, ImapClient, ImapContext},
+    info, runtime,
     search_query::{
         filter::SearchEmailsFilterQuery,
         sort::{SearchEmailsSorter, SearchEmailsSorterKind, SearchEmailsSorterOrder},
@@ -27,8 +26,6 @@
     trace, AnyResult, Result,
 };
 
-static MAX_SEQUENCE_SIZE: u8 = u8::MAX; // 255
-
 #[derive(Clone, Debug)]
 pub struct ListImapEnvelopes {
     ctx: ImapContext,
@@ -58,22 +55,68 @@ async fn list_envelopes(
     ) -> AnyResult<Envelopes> {
         info!("listing IMAP envelopes from mailbox {folder}");
 
-        let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client_for("list_envelopes").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!(name = folder_encoded, "UTF7-encoded mailbox");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!(name = folder_encoded, "encoded mailbox");
 
         let data = client.select_mailbox(folder_encoded.clone()).await?;
         let folder_size = data.exists.unwrap_or_default() as usize;
         debug!(name = folder_encoded, ?data, "mailbox selected");
 
+        if let Some(token) = opts.changed_since {
+            if client.ext_condstore_supported() {
+                let uid_validity = data.uid_validity.map(NonZeroU32::get).unwrap_or_default();
+
+                let since = if token.uid_validity == uid_validity {
+                    token.highest_mod_seq
+                } else {
+                    debug!(
+                        "UIDVALIDITY of mailbox {folder_encoded} changed, listing all envelopes"
+                    );
+                    0
+                };
+
+                let (mut envelopes, highest_mod_seq) = if folder_size == 0 {
+                    (Envelopes::default(), since)
+                } else {
+                    let changes = fetch_envelopes_changes(&mut client, since).await?;
+                    (changes.envelopes, changes.highest_mod_seq)
+                };
+
+                debug!(
+                    "found {} imap envelopes changed since {since}",
+                    envelopes.len()
+                );
+
+                opts.sort_envelopes(&mut envelopes);
+                envelopes.set_changes_token(Some(EnvelopesChangesToken {
+                    uid_validity,
+                    highest_mod_seq,
+                }));
+
+                return Ok(envelopes);
+            }
+
+            debug!("IMAP server does not support CONDSTORE, listing all envelopes");
+        }
+
         if folder_size == 0 {
             return Ok(Envelopes::default());
         }
 
-        let envelopes = if let Some(query) = opts.query.as_ref() {
+        // cursor-based pagination relies on the envelopes order, which
+        // requires the UIDs of the whole (sorted) folder
+        let query = match (&opts.query, &opts.after) {
+            (None, Some(_)) => Some(SearchEmailsQuery {
+                filter: None,
+                sort: None,
+            }),
+            (query, _) => query.clone(),
+        };
+
+        let envelopes = if let Some(query) = query.as_ref() {
             let sort_supported = client.ext_sort_supported();
             let sort_criteria = query.to_imap_sort_criteria();
             let search_criteria = query.to_imap_search_criteria();
@@ -92,70 +135,35 @@ async fn list_envelopes(
             drop(client);
 
             // if the SORT extension is supported by the client,
-            // envelopes can be paginated straight away
-            let uids = if sort_supported {
-                paginate(&uids, opts.page, opts.page_size)?
+            // envelopes can be paginated straight away, unless the
+            // cursor points to an envelope that does not exist anymore
+            let page = if !sort_supported {
+                None
+            } else if let Some(cursor) = opts.after.as_ref() {
+                paginate_after(&uids, cursor.id(), opts.page_size)
             } else {
-                &uids
+                Some(paginate(&uids, opts.page, opts.page_size)?)
             };
 
-            let uids_chunks = uids.chunks(MAX_SEQUENCE_SIZE as usize);
-            let uids_chunks_len = uids_chunks.len();
-
-            #[cfg(feature = "tracing")]
-            tracing::debug!(?uids, "fetching envelopes using {uids_chunks_len} chunks");
-
-            let mut fetches = FuturesUnordered::from_iter(uids_chunks.map(|uids| {
-                let ctx = self.ctx.clone();
-                let mbox = folder_encoded.clone();
-                let uids = SequenceSet::try_from(uids.to_vec()).unwrap();
-
-                tokio::spawn(async move {
-                    let mut client = ctx.client().await;
-                    client.select_mailbox(mbox).await?;
-                    client.fetch_envelopes(uids).await
-                })
-            }))
-            .enumerate()
-            .fold(
-                Ok(HashMap::<String, Envelope>::default()),
-                |all_envelopes, (n, envelopes)| async move {
-                    let Ok(mut all_envelopes) = all_envelopes else {
-                        return all_envelopes;
-                    };
-
-                    match envelopes {
-                        Err(err) => {
-                            return Err(imap::Error::JoinClientError(err));
-                        }
-                        Ok(Err(err)) => {
-                            return Err(err);
-                        }
-                        Ok(Ok(envelopes)) => {
-                            #[cfg(feature = "tracing")]
-                            tracing::debug!("fetched envelopes chunk {}/{uids_chunks_len}", n + 1);
-
-                            for envelope in envelopes {
-                                all_envelopes.insert(envelope.id.clone(), envelope);
-                            }
-
-                            Ok(all_envelopes)
-                        }
-                    }
-                },
-            )
-            .await?;
+            let uids = page.unwrap_or(&uids);
+
+            let mut fetches = fetch_envelopes_batches(&self.ctx, &folder_encoded, uids).await?;
 
             let mut envelopes: Envelopes = uids
                 .iter()
                 .flat_map(|uid| fetches.remove(&uid.to_string()))
                 .collect();
 
-            // if the SORT extension is NOT supported by the client,
-            // envelopes are sorted and paginated only now
-            if !sort_supported {
+            // if envelopes could not be paginated using UIDs, they
+            // are sorted and paginated only now
+            if page.is_none() {
                 opts.sort_envelopes(&mut envelopes);
-                apply_pagination(&mut envelopes, opts.page, opts.page_size)?;
+
+                if opts.after.is_some() {
+                    opts.apply_cursor(&mut envelopes);
+                } else {
+                    apply_pagination(&mut envelopes, opts.page, opts.page_size)?;
+                }
             }
 
             envelopes
@@ -173,6 +181,115 @@ async fn list_envelopes(
     }
 }
 
+/// Fetch the envelopes matching the given UIDs, using adaptive
+/// batches spread over the clients of the pool.
+///
+/// A new batch is sent as soon as a client is free, its size being
+/// adjusted to the latency of the previous batches (see
+/// [`FetchBatchSizer`]). The chosen batch sizes are reported to the
+/// metrics sink, if any.
+pub(crate) async fn fetch_envelopes_batches(
+    ctx: &ImapContext,
+    mbox: &str,
+    uids: &[NonZeroU32],
+) -> imap::Result<HashMap<String, Envelope>> {
+    let mut sizer = FetchBatchSizer::from(ctx.imap_config.fetch_batch.as_ref());
+    let concurrency = ctx.pool_size().max(1);
+    let mut remaining = uids;
+    let mut fetches = FuturesUnordered::new();
+    let mut all_envelopes = HashMap::<String, Envelope>::default();
+
+    loop {
+        while fetches.len() < concurrency && !remaining.is_empty() {
+            let size = sizer.next_size(remaining.len());
+            let (batch, rest) = remaining.split_at(size);
+            remaining = rest;
+
+            let ctx = ctx.clone();
+            let mbox = mbox.to_owned();
+            let uids = SequenceSet::try_from(batch.to_vec()).unwrap();
+
+            fetches.push(runtime::spawn(async move {
+                let mut client = ctx.client_for("list_envelopes").await;
+                client.select_mailbox(mbox).await?;
+
+                let start = Instant::now();
+                let envelopes = client.fetch_envelopes(uids).await?;
+                imap::Result::Ok((size, start.elapsed(), envelopes))
+            }));
+        }
+
+        let Some(res) = fetches.next().await else {
+            break;
+        };
+
+        let (size, latency, envelopes) = res.map_err(imap::Error::JoinClientError)??;
+        sizer.record(size, latency);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("fetched envelopes batch of {size} in {latency:?}");
+
+        for envelope in envelopes {
+            all_envelopes.insert(envelope.id.clone(), envelope);
+        }
+    }
+
+    ctx.record_fetch_batches("list_envelopes", sizer.sizes());
+
+    Ok(all_envelopes)
+}
+
+/// The changes of a mailbox since a given modification sequence.
+pub(crate) struct EnvelopesChanges {
+    /// The envelopes changed since the given modification sequence.
+    pub envelopes: Envelopes,
+
+    /// The highest modification sequence of the mailbox.
+    pub highest_mod_seq: u64,
+}
+
+/// Fetch the envelopes of the selected mailbox changed since the
+/// given modification sequence, using the CONDSTORE extension.
+///
+/// The modification sequences of changed messages are fetched first
+/// using the `CHANGEDSINCE` fetch modifier, then their envelopes.
+/// Expunged messages are not reported.
+pub(crate) async fn fetch_envelopes_changes(
+    client: &mut ImapClient,
+    since: u64,
+) -> imap::Result<EnvelopesChanges> {
+    let mod_seqs = client.fetch_mod_seqs_changed_since(since).await?;
+    let highest_mod_seq = mod_seqs.values().copied().fold(since, u64::max);
+
+    let mut uids: Vec<NonZeroU32> = mod_seqs.into_keys().collect();
+    uids.sort();
+
+    let mut envelopes = Vec::with_capacity(uids.len());
+    let mut sizer = FetchBatchSizer::from(client.imap_config.fetch_batch.as_ref());
+    let mut remaining = uids.as_slice();
+
+    while !remaining.is_empty() {
+        let size = sizer.next_size(remaining.len());
+        let (batch, rest) = remaining.split_at(size);
+        remaining = rest;
+
+        let uids = SequenceSet::try_from(batch.to_vec()).unwrap();
+        let start = Instant::now();
+        envelopes.extend(client.fetch_envelopes(uids).await?);
+        sizer.record(size, start.elapsed());
+    }
+
+    debug!(
+        "fetched changed envelopes using batches of {:?}",
+        sizer.sizes()
+    );
+
+    Ok(EnvelopesChanges {
+        envelopes: Envelopes::from_iter(envelopes),
+        highest_mod_seq,
+    })
+}
+
 impl SearchEmailsQuery {
     pub fn to_imap_search_criteria(&self) -> Vec1<SearchKey<'static>> {
         self.filter
@@ -305,6 +422,23 @@ fn paginate<T>(items: &[T], page: usize, page_size: usize) -> Result<&[T]> {
     Ok(&items[0..page_size.min(total)])
 }
 
+/// Return the page of UIDs coming right after the given envelope
+/// identifier, or `None` if the identifier cannot be found.
+fn paginate_after<'a>(
+    uids: &'a [NonZeroU32],
+    id: &str,
+    page_size: usize,
+) -> Option<&'a [NonZeroU32]> {
+    let pos = uids.iter().position(|uid| uid.to_string() == id)?;
+    let uids = &uids[pos + 1..];
+
+    if page_size == 0 {
+        return Some(uids);
+    }
+
+    Some(&uids[..page_size.min(uids.len())])
+}
+
 fn apply_pagination(
     envelopes: &mut Envelopes,
     page: usize,
@@ -320,8 +454,7 @@ fn apply_pagination(
         return Ok(());
     }
 
-    let page_size = page_size.min(total);
-    *envelopes = Envelopes(envelopes[0..page_size].to_vec());
+    envelopes.truncate(page_size.min(total));
     Ok(())
 }
 
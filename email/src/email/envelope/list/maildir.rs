@@ -5,6 +5,7 @@
 
 use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
 use crate::{
+    backend::kit,
     debug,
     email::error::Error,
     envelope::Envelope,
@@ -52,28 +53,25 @@ async fn list_envelopes(
 
         let entries = mdir.read().map_err(Error::ListMaildirEntriesError)?;
         let mut envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref());
+        ctx.merge_stored_flags(folder, envelopes.iter_mut()).await?;
         debug!("found {} maildir envelopes", envelopes.len());
         trace!("{envelopes:#?}");
 
-        let page_begin = opts.page * opts.page_size;
-        debug!("page begin: {}", page_begin);
-        if page_begin > envelopes.len() {
-            return Err(Error::GetEnvelopesOutOfBoundsMaildirError(
-                folder.to_owned(),
-                page_begin + 1,
-            )
-            .into());
+        if opts.after.is_some() {
+            kit::sort_envelopes(&mut envelopes, &opts);
+            opts.apply_cursor(&mut envelopes);
+            return Ok(envelopes);
         }
 
-        let page_end = envelopes.len().min(if opts.page_size == 0 {
-            envelopes.len()
-        } else {
-            page_begin + opts.page_size
-        });
-        debug!("page end: {}", page_end);
+        let page_range =
+            kit::page_range(envelopes.len(), opts.page, opts.page_size).ok_or_else(|| {
+                let page_begin = opts.page * opts.page_size;
+                Error::GetEnvelopesOutOfBoundsMaildirError(folder.to_owned(), page_begin + 1)
+            })?;
+        debug!("page range: {page_range:?}");
 
-        opts.sort_envelopes(&mut envelopes);
-        *envelopes = envelopes[page_begin..page_end].into();
+        kit::sort_envelopes(&mut envelopes, &opts);
+        *envelopes = envelopes[page_range].into();
 
         Ok(envelopes)
     }
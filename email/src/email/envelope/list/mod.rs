@@ -1,15 +1,22 @@
 pub mod config;
+pub mod cursor;
 #[cfg(feature = "imap")]
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+#[cfg(feature = "notmuch-remote")]
+pub mod notmuch_remote;
 
 use std::cmp::Ordering;
 
 use async_trait::async_trait;
 
+#[doc(inline)]
+pub use self::cursor::EnvelopesCursor;
 use super::{Envelope, Envelopes};
 use crate::{
     email::search_query::SearchEmailsQuery,
@@ -33,6 +40,49 @@ pub struct ListEnvelopesOptions {
     pub page_size: usize,
     pub page: usize,
     pub query: Option<SearchEmailsQuery>,
+
+    /// List only envelopes changed since the given token.
+    ///
+    /// Backends supporting incremental listing return the new token
+    /// along with envelopes, see [`Envelopes::changes_token`]. Use
+    /// the default token to list all envelopes and get an initial
+    /// token. For incremental listings, the query filter and the
+    /// pagination are ignored. Backends not supporting incremental
+    /// listing ignore this option.
+    pub changed_since: Option<EnvelopesChangesToken>,
+
+    /// List only envelopes coming after the given cursor, in the
+    /// sort order of the query.
+    ///
+    /// Unlike the page index, the cursor is not affected by envelopes
+    /// added to or removed from the folder between two listings. When
+    /// defined, the page index is ignored while the page size still
+    /// applies. The cursor of the next page is returned along with
+    /// envelopes, see [`Envelopes::next_cursor`].
+    pub after: Option<EnvelopesCursor>,
+}
+
+/// The envelopes changes token.
+///
+/// The token identifies the state of a folder at a given time. The
+/// IMAP backend relies on the CONDSTORE extension: the token is made
+/// of the folder UIDVALIDITY and of its highest modification sequence
+/// (MODSEQ).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct EnvelopesChangesToken {
+    /// The folder UIDVALIDITY.
+    ///
+    /// When the folder UIDVALIDITY changes, the token is not valid
+    /// anymore and all envelopes are listed again.
+    pub uid_validity: u32,
+
+    /// The highest modification sequence of the folder.
+    pub highest_mod_seq: u64,
 }
 
 impl SearchEmailsSorter {
@@ -54,18 +104,58 @@ pub fn cmp_envelopes(&self, a: &Envelope, b: &Envelope) -> Ordering {
 }
 
 impl ListEnvelopesOptions {
-    pub fn sort_envelopes(&self, envelopes: &mut Envelopes) {
-        envelopes.sort_by(|a, b| {
-            if let Some(sorters) = self.query.as_ref().and_then(|q| q.sort.as_ref()) {
-                for sorter in sorters {
-                    let cmp = sorter.cmp_envelopes(a, b);
-                    if cmp.is_ne() {
-                        return cmp;
-                    }
+    /// Compare the given envelopes using the sort order of the query.
+    ///
+    /// Envelopes are sorted by date, newest first, if the query does
+    /// not define any sort order. Envelopes sorted equally are then
+    /// sorted by identifier, so that the order is deterministic.
+    pub fn cmp_envelopes(&self, a: &Envelope, b: &Envelope) -> Ordering {
+        if let Some(sorters) = self.query.as_ref().and_then(|q| q.sort.as_ref()) {
+            for sorter in sorters {
+                let cmp = sorter.cmp_envelopes(a, b);
+                if cmp.is_ne() {
+                    return cmp;
                 }
             }
+        }
+
+        a.date.cmp(&b.date).reverse().then_with(|| a.id.cmp(&b.id))
+    }
+
+    pub fn sort_envelopes(&self, envelopes: &mut Envelopes) {
+        envelopes.sort_by(|a, b| self.cmp_envelopes(a, b));
+    }
+
+    /// Return the cursor of the page following the given one, if the
+    /// given page is full.
+    pub fn next_cursor(&self, envelopes: &Envelopes) -> Option<EnvelopesCursor> {
+        if self.page_size == 0 || envelopes.len() < self.page_size {
+            return None;
+        }
+
+        envelopes.last().map(EnvelopesCursor::from)
+    }
+
+    /// Keep only the envelopes coming after the cursor, then keep the
+    /// first page of them and set the cursor of the next page.
+    ///
+    /// Envelopes are expected to be sorted using
+    /// [`ListEnvelopesOptions::sort_envelopes`].
+    pub fn apply_cursor(&self, envelopes: &mut Envelopes) {
+        if let Some(cursor) = self.after.as_ref() {
+            let begin = envelopes.partition_point(|envelope| {
+                cursor
+                    .cmp_envelope(envelope, |a, b| self.cmp_envelopes(a, b))
+                    .is_le()
+            });
+
+            envelopes.drain(..begin);
+        }
+
+        if self.page_size > 0 {
+            envelopes.truncate(self.page_size);
+        }
 
-            a.date.cmp(&b.date).reverse()
-        });
+        envelopes.set_next_cursor(self.next_cursor(envelopes));
     }
 }
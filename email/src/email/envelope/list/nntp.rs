@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+
+use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use crate::{debug, email::error::Error, info, nntp::NntpContextSync, trace, AnyResult};
+
+#[derive(Clone)]
+pub struct ListNntpEnvelopes {
+    ctx: NntpContextSync,
+}
+
+impl ListNntpEnvelopes {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn ListEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn ListEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListEnvelopes for ListNntpEnvelopes {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        info!("listing NNTP envelopes from newsgroup {folder}");
+
+        if opts
+            .query
+            .as_ref()
+            .and_then(|q| q.filter.as_ref())
+            .is_some()
+        {
+            debug!("NNTP backend does not support search filters, ignoring them");
+        }
+
+        let mut ctx = self.ctx.lock().await;
+        let group = ctx.client.group(folder).await?;
+
+        if group.count == 0 || group.high < group.low {
+            return Ok(Envelopes::default());
+        }
+
+        // articles are paginated from the most recent one, which has
+        // the highest number, or from the one preceding the article
+        // the cursor points to
+        let (newest, page) = match opts.after.as_ref() {
+            None => (group.high, opts.page),
+            Some(cursor) => {
+                let number: u64 = cursor
+                    .id()
+                    .parse()
+                    .map_err(|_| Error::ParseEnvelopesCursorError(cursor.to_string()))?;
+                (number.saturating_sub(1).min(group.high), 0)
+            }
+        };
+
+        let (low, high) = if opts.page_size == 0 {
+            if newest < group.low {
+                return Ok(Envelopes::default());
+            }
+            (group.low, newest)
+        } else {
+            let skip = (page * opts.page_size) as u64;
+            let Some(high) = newest.checked_sub(skip).filter(|h| *h >= group.low) else {
+                return Ok(Envelopes::default());
+            };
+            let low = high
+                .saturating_sub(opts.page_size as u64 - 1)
+                .max(group.low);
+            (low, high)
+        };
+
+        debug!("listing NNTP articles {low}-{high}");
+
+        let overviews = ctx.client.over(low, high).await?;
+        let mut envelopes = Envelopes::from_nntp_overviews(folder, &ctx.newsrc, overviews);
+        debug!("found {} NNTP envelopes", envelopes.len());
+        trace!("{envelopes:#?}");
+
+        opts.sort_envelopes(&mut envelopes);
+
+        Ok(envelopes)
+    }
+}
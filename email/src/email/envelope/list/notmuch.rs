@@ -1,15 +1,9 @@
 use async_trait::async_trait;
-use chrono::TimeDelta;
 
 use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
 use crate::{
-    debug,
-    email::error::Error,
-    folder::FolderKind,
-    info,
-    notmuch::NotmuchContextSync,
-    search_query::{filter::SearchEmailsFilterQuery, SearchEmailsQuery},
-    trace, AnyResult,
+    backend::kit, debug, email::error::Error, folder::FolderKind, info,
+    notmuch::NotmuchContextSync, trace, AnyResult,
 };
 
 #[derive(Clone)]
@@ -59,6 +53,8 @@ async fn list_envelopes(
             }
         }
 
+        ctx.exclude_tags_from_query(&mut final_query);
+
         let query_builder = db
             .create_query(&final_query)
             .map_err(Error::NotMuchFailure)?;
@@ -75,107 +71,22 @@ async fn list_envelopes(
         );
         trace!("{envelopes:#?}");
 
-        let page_begin = opts.page * opts.page_size;
-
-        if page_begin > envelopes.len() {
-            return Err(Error::GetEnvelopesOutOfBoundsNotmuchError(
-                folder.to_owned(),
-                page_begin + 1,
-            ))?;
-        }
-
-        let page_end = envelopes.len().min(if opts.page_size == 0 {
-            envelopes.len()
+        if opts.after.is_some() {
+            kit::sort_envelopes(&mut envelopes, &opts);
+            opts.apply_cursor(&mut envelopes);
         } else {
-            page_begin + opts.page_size
-        });
-
-        opts.sort_envelopes(&mut envelopes);
-        *envelopes = envelopes[page_begin..page_end].into();
+            let page_range = kit::page_range(envelopes.len(), opts.page, opts.page_size)
+                .ok_or_else(|| {
+                    let page_begin = opts.page * opts.page_size;
+                    Error::GetEnvelopesOutOfBoundsNotmuchError(folder.to_owned(), page_begin + 1)
+                })?;
+
+            kit::sort_envelopes(&mut envelopes, &opts);
+            *envelopes = envelopes[page_range].into();
+        }
 
         db.close().map_err(Error::NotMuchFailure)?;
 
         Ok(envelopes)
     }
 }
-
-impl SearchEmailsQuery {
-    pub fn to_notmuch_search_query(&self) -> String {
-        self.filter
-            .as_ref()
-            .map(|f| f.to_notmuch_search_query())
-            .unwrap_or_default()
-    }
-}
-
-impl SearchEmailsFilterQuery {
-    pub fn to_notmuch_search_query(&self) -> String {
-        let mut query = String::new();
-
-        match self {
-            SearchEmailsFilterQuery::And(left, right) => {
-                query.push_str("(");
-                query.push_str(&left.to_notmuch_search_query());
-                query.push_str(") and (");
-                query.push_str(&right.to_notmuch_search_query());
-                query.push(')');
-            }
-            SearchEmailsFilterQuery::Or(left, right) => {
-                query.push_str("(");
-                query.push_str(&left.to_notmuch_search_query());
-                query.push_str(") or (");
-                query.push_str(&right.to_notmuch_search_query());
-                query.push(')');
-            }
-            SearchEmailsFilterQuery::Not(right) => {
-                query.push_str("not (");
-                query.push_str(&right.to_notmuch_search_query());
-                query.push_str(")");
-            }
-            SearchEmailsFilterQuery::Date(date) => {
-                query.push_str("date:");
-                query.push_str(&date.to_string());
-            }
-            SearchEmailsFilterQuery::BeforeDate(date) => {
-                // notmuch dates are inclusive, so we substract one
-                // day from the before date filter.
-                let date = *date - TimeDelta::try_days(1).unwrap();
-                query.push_str("date:..");
-                query.push_str(&date.to_string());
-            }
-            SearchEmailsFilterQuery::AfterDate(date) => {
-                // notmuch dates are inclusive, so we add one day to
-                // the after date filter.
-                let date = *date + TimeDelta::try_days(1).unwrap();
-                query.push_str("date:");
-                query.push_str(&date.to_string());
-                query.push_str("..");
-            }
-            SearchEmailsFilterQuery::From(pattern) => {
-                query.push_str("from:/");
-                query.push_str(pattern);
-                query.push('/');
-            }
-
-            SearchEmailsFilterQuery::To(pattern) => {
-                query.push_str("to:/");
-                query.push_str(pattern);
-                query.push('/');
-            }
-            SearchEmailsFilterQuery::Subject(pattern) => {
-                query.push_str("subject:");
-                query.push_str(pattern);
-            }
-            SearchEmailsFilterQuery::Body(pattern) => {
-                query.push_str("body:");
-                query.push_str(pattern);
-            }
-            SearchEmailsFilterQuery::Flag(flag) => {
-                query.push_str("tag:");
-                query.push_str(&flag.to_string());
-            }
-        };
-
-        query
-    }
-}
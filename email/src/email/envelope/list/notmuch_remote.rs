@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+
+use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use crate::{
+    debug,
+    envelope::notmuch_remote::parse_notmuch_show_json,
+    folder::FolderKind,
+    info,
+    notmuch_remote::{ids_query, NotmuchRemoteContextSync},
+    trace, AnyResult,
+};
+
+#[derive(Clone)]
+pub struct ListNotmuchRemoteEnvelopes {
+    ctx: NotmuchRemoteContextSync,
+}
+
+impl ListNotmuchRemoteEnvelopes {
+    pub fn new(ctx: &NotmuchRemoteContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchRemoteContextSync) -> Box<dyn ListEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchRemoteContextSync) -> Option<Box<dyn ListEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListEnvelopes for ListNotmuchRemoteEnvelopes {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        info!("listing remote notmuch envelopes from folder {folder}");
+
+        let config = &self.ctx.account_config;
+
+        let ref folder = config.get_folder_alias(folder);
+        let mut final_query = if self.ctx.maildirpp() && FolderKind::matches_inbox(folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        };
+
+        if let Some(query) = opts.query.as_ref() {
+            let query = query.to_notmuch_search_query();
+            if !query.is_empty() {
+                final_query.push_str(" and ");
+                final_query.push_str(&query);
+            }
+        }
+
+        let ids = if let Some(cursor) = opts.after.as_ref() {
+            // messages are searched newest first, so only the ones
+            // not newer than the cursor are searched, then the ones
+            // preceding the cursor in the search order are skipped
+            let timestamp = cursor.date().timestamp();
+            final_query.push_str(&format!(" and date:..@{timestamp}"));
+
+            let mut ids = self.ctx.search_ids(&final_query, 0, 0).await?;
+
+            if let Some(pos) = ids.iter().position(|id| id == cursor.id()) {
+                ids.drain(..=pos);
+            }
+
+            if opts.page_size > 0 {
+                ids.truncate(opts.page_size);
+            }
+
+            ids
+        } else {
+            // pagination is delegated to the remote host, so that only
+            // the envelopes of the current page are transferred
+            let offset = opts.page * opts.page_size;
+            self.ctx
+                .search_ids(&final_query, offset, opts.page_size)
+                .await?
+        };
+
+        if ids.is_empty() {
+            debug!("no remote notmuch envelope matching query {final_query}");
+            return Ok(Envelopes::default());
+        }
+
+        let args = [
+            String::from("show"),
+            String::from("--format=json"),
+            String::from("--body=false"),
+            String::from("--entire-thread=false"),
+            String::from("--"),
+            ids_query(ids.iter().map(String::as_str)),
+        ];
+
+        let json = self.ctx.run(&args).await?;
+        let msgs = parse_notmuch_show_json(&json)?;
+        let mut envelopes = Envelopes::from_notmuch_remote_msgs(msgs);
+
+        debug!(
+            "found {} remote notmuch envelopes matching query {final_query}",
+            envelopes.len()
+        );
+        trace!("{envelopes:#?}");
+
+        opts.sort_envelopes(&mut envelopes);
+
+        Ok(envelopes)
+    }
+}
@@ -14,8 +14,15 @@
 pub mod list;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+#[cfg(feature = "notmuch-remote")]
+pub mod notmuch_remote;
+pub mod section;
+#[cfg(feature = "stream")]
+pub mod stream;
 #[cfg(feature = "sync")]
 pub mod sync;
 #[cfg(feature = "thread")]
@@ -33,8 +40,9 @@
 
 use chrono::{DateTime, FixedOffset, Local};
 #[cfg(feature = "thread")]
-use petgraph::graphmap::DiGraphMap;
+use petgraph::{graphmap::DiGraphMap, Direction};
 
+use self::list::{EnvelopesChangesToken, EnvelopesCursor};
 #[doc(inline)]
 pub use self::{
     address::Address,
@@ -42,8 +50,8 @@
     id::{Id, MultipleIds, SingleId},
 };
 use crate::{
-    account::config::AccountConfig, date::from_mail_parser_to_chrono_datetime, debug,
-    message::Message, trace,
+    account::config::AccountConfig, backend::extensions::Extensions,
+    date::from_mail_parser_to_chrono_datetime, debug, message::Message, trace,
 };
 
 /// The email envelope.
@@ -62,6 +70,9 @@ pub struct Envelope {
     pub message_id: String,
     /// The In-Reply-To header from the email message.
     pub in_reply_to: Option<String>,
+    /// The References header from the email message, from the
+    /// oldest to the most recent ancestor.
+    pub references: Vec<String>,
     /// The envelope flags.
     pub flags: Flags,
     /// The first address from the email message header From.
@@ -78,6 +89,16 @@ pub struct Envelope {
     /// An attachment is defined here as a MIME part that is not a
     /// `text/*`.
     pub has_attachment: bool,
+
+    /// True if the current envelope has been sent by a VIP sender.
+    ///
+    /// See [`AccountConfig::vip_senders`].
+    pub is_vip: bool,
+
+    /// The backend-specific data of the envelope.
+    ///
+    /// See [`crate::backend::extensions`].
+    pub extensions: Extensions,
 }
 
 impl Envelope {
@@ -173,6 +194,14 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
                 });
 
             envelope.in_reply_to = msg.in_reply_to().as_text().map(|mid| format!("<{mid}>"));
+
+            envelope.references = match msg.references() {
+                mail_parser::HeaderValue::Text(mid) => vec![format!("<{mid}>")],
+                mail_parser::HeaderValue::TextList(mids) => {
+                    mids.iter().map(|mid| format!("<{mid}>")).collect()
+                }
+                _ => Vec::new(),
+            };
         } else {
             trace!("cannot parse message header, skipping it");
         };
@@ -221,6 +250,12 @@ pub fn format_date(&self, config: &AccountConfig) -> String {
         date.to_string()
     }
 
+    /// Mark the envelope as VIP if its sender belongs to the VIP
+    /// senders of the [account configuration](crate::AccountConfig).
+    pub fn mark_vip_sender(&mut self, config: &AccountConfig) {
+        self.is_vip = config.is_vip_sender(&self.from.addr);
+    }
+
     /// Build a message from the current envelope.
     ///
     /// The message is just composed of two headers and contains no
@@ -264,20 +299,24 @@ fn hash<H: Hasher>(&self, state: &mut H) {
 
 /// The list of email envelopes.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct Envelopes(Vec<Envelope>);
+pub struct Envelopes {
+    envelopes: Vec<Envelope>,
+    changes_token: Option<EnvelopesChangesToken>,
+    next_cursor: Option<EnvelopesCursor>,
+}
 
 impl IntoIterator for Envelopes {
     type IntoIter = vec::IntoIter<Self::Item>;
     type Item = Envelope;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.envelopes.into_iter()
     }
 }
 
 impl From<Envelopes> for Vec<Envelope> {
     fn from(val: Envelopes) -> Self {
-        val.0
+        val.envelopes
     }
 }
 
@@ -285,19 +324,64 @@ impl Deref for Envelopes {
     type Target = Vec<Envelope>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.envelopes
     }
 }
 
 impl DerefMut for Envelopes {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.envelopes
+    }
+}
+
+impl Envelopes {
+    /// Get the changes token returned by incremental listings.
+    ///
+    /// See [`ListEnvelopesOptions::changed_since`](list::ListEnvelopesOptions::changed_since).
+    pub fn changes_token(&self) -> Option<EnvelopesChangesToken> {
+        self.changes_token
+    }
+
+    /// Set the changes token.
+    pub fn set_changes_token(&mut self, token: Option<EnvelopesChangesToken>) {
+        self.changes_token = token;
+    }
+
+    /// Get the cursor of the next page returned by paginated
+    /// listings.
+    ///
+    /// See [`ListEnvelopesOptions::after`](list::ListEnvelopesOptions::after).
+    pub fn next_cursor(&self) -> Option<&EnvelopesCursor> {
+        self.next_cursor.as_ref()
+    }
+
+    /// Set the cursor of the next page.
+    pub fn set_next_cursor(&mut self, cursor: Option<EnvelopesCursor>) {
+        self.next_cursor = cursor;
+    }
+
+    /// Mark envelopes sent by VIP senders.
+    ///
+    /// See [`Envelope::mark_vip_sender`].
+    pub fn mark_vip_senders(&mut self, config: &AccountConfig) {
+        for envelope in self.iter_mut() {
+            envelope.mark_vip_sender(config);
+        }
+    }
+
+    /// Keep only envelopes sent by VIP senders.
+    pub fn retain_vip_senders(&mut self) {
+        self.retain(|envelope| envelope.is_vip);
     }
 }
 
 impl FromIterator<Envelope> for Envelopes {
     fn from_iter<T: IntoIterator<Item = Envelope>>(iter: T) -> Self {
-        Envelopes(iter.into_iter().collect())
+        Envelopes {
+            envelopes: iter.into_iter().collect(),
+            changes_token: None,
+            next_cursor: None,
+        }
     }
 }
 
@@ -374,6 +458,122 @@ pub fn map(&self) -> &HashMap<String, Envelope> {
     pub fn graph(&self) -> &DiGraphMap<ThreadedEnvelope, u8> {
         self.borrow_graph()
     }
+
+    /// Group the threaded envelopes into [`Threads`].
+    pub fn threads(&self) -> Threads {
+        let graph = self.graph();
+
+        graph
+            .nodes()
+            .filter(|node| {
+                graph
+                    .neighbors_directed(*node, Direction::Incoming)
+                    .next()
+                    .is_none()
+            })
+            .flat_map(|node| self.collect_threads(node))
+            .collect()
+    }
+
+    /// Collect the threads starting at the given node.
+    ///
+    /// Nodes that are not part of the envelopes, like the `"0"`
+    /// placeholder, are pruned: their children are promoted to their
+    /// level.
+    fn collect_threads<'a>(&'a self, node: ThreadedEnvelope<'a>) -> Vec<Thread> {
+        let replies = self
+            .graph()
+            .neighbors_directed(node, Direction::Outgoing)
+            .flat_map(|node| self.collect_threads(node))
+            .collect();
+
+        match self.map().get(node.id) {
+            Some(envelope) => vec![Thread {
+                envelope: envelope.clone(),
+                replies,
+            }],
+            None => replies,
+        }
+    }
+}
+
+/// A thread of envelopes.
+#[cfg(feature = "thread")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Thread {
+    /// The envelope starting the thread.
+    pub envelope: Envelope,
+
+    /// The replies to the envelope.
+    pub replies: Vec<Thread>,
+}
+
+#[cfg(feature = "thread")]
+impl Thread {
+    /// Return the number of envelopes of the thread, including the
+    /// replies.
+    pub fn count(&self) -> usize {
+        1 + self.replies.iter().map(Thread::count).sum::<usize>()
+    }
+
+    /// Return the envelopes of the thread, depth-first.
+    pub fn envelopes(&self) -> Vec<&Envelope> {
+        let mut envelopes = vec![&self.envelope];
+
+        for reply in &self.replies {
+            envelopes.extend(reply.envelopes());
+        }
+
+        envelopes
+    }
+}
+
+/// The list of threads.
+///
+/// Threads group envelopes by conversation, which is the base of any
+/// conversation view. See [`ThreadedEnvelopes::threads`].
+#[cfg(feature = "thread")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Threads(Vec<Thread>);
+
+#[cfg(feature = "thread")]
+impl Deref for Threads {
+    type Target = Vec<Thread>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "thread")]
+impl DerefMut for Threads {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "thread")]
+impl IntoIterator for Threads {
+    type IntoIter = vec::IntoIter<Self::Item>;
+    type Item = Thread;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(feature = "thread")]
+impl FromIterator<Thread> for Threads {
+    fn from_iter<T: IntoIterator<Item = Thread>>(iter: T) -> Self {
+        Threads(iter.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "thread")]
+impl From<Threads> for Vec<Thread> {
+    fn from(val: Threads) -> Self {
+        val.0
+    }
 }
 
 #[cfg(all(feature = "thread", feature = "derive"))]
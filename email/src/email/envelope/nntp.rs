@@ -0,0 +1,46 @@
+//! Module dedicated to NNTP email envelopes.
+//!
+//! This module contains envelope-related mapping functions from the
+//! NNTP article overviews. Flags are taken from the local read
+//! state.
+
+use crate::{
+    envelope::{Envelope, Envelopes},
+    flag::{Flag, Flags},
+    message::Message,
+    nntp::{client::NntpOverview, newsrc::Newsrc},
+};
+
+impl Envelopes {
+    pub fn from_nntp_overviews(group: &str, newsrc: &Newsrc, overviews: Vec<NntpOverview>) -> Self {
+        overviews
+            .into_iter()
+            .map(|overview| Envelope::from_nntp_overview(group, newsrc, overview))
+            .collect()
+    }
+}
+
+impl Envelope {
+    pub fn from_nntp_overview(group: &str, newsrc: &Newsrc, overview: NntpOverview) -> Self {
+        let mut flags = Flags::default();
+
+        if newsrc.is_read(group, overview.number) {
+            flags.insert(Flag::Seen);
+        }
+
+        let mut headers = format!(
+            "Message-ID: {}\r\nSubject: {}\r\nFrom: {}\r\nDate: {}\r\n",
+            overview.message_id, overview.subject, overview.from, overview.date,
+        );
+
+        // the last reference is the direct parent of the article
+        if let Some(parent) = overview.references.split_whitespace().last() {
+            headers.push_str(&format!("In-Reply-To: {parent}\r\n"));
+        }
+
+        headers.push_str("\r\n");
+
+        let msg = Message::from(headers.into_bytes());
+        Envelope::from_msg(overview.number, flags, msg)
+    }
+}
@@ -4,6 +4,7 @@
 //! [notmuch] crate types.
 
 use crate::{
+    backend::extensions::NOTMUCH_TAGS,
     debug,
     envelope::{Envelope, Envelopes},
     flag::{Flag, Flags},
@@ -19,6 +20,7 @@ pub fn from_notmuch_msgs(msgs: notmuch::Messages) -> Self {
 impl Envelope {
     pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
         let id = msg.id();
+        let tags: Vec<String> = msg.tags().collect();
         let flags = Flags::from(&msg);
         let has_attachment = flags.contains(&Flag::custom("attachment"));
 
@@ -34,6 +36,7 @@ pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
 
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.extensions.set(NOTMUCH_TAGS, tags);
         env
     }
 }
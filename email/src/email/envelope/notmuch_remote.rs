@@ -0,0 +1,142 @@
+//! Module dedicated to remote Notmuch email envelopes.
+//!
+//! This module contains envelope-related mapping functions from the
+//! JSON output of `notmuch show`.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{
+    envelope::{Envelope, Envelopes},
+    flag::{Flag, Flags},
+    message::Message,
+    notmuch_remote::{Error, Result},
+};
+
+/// A message from the JSON output of `notmuch show`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotmuchRemoteMessage {
+    /// The message identifier, without angle brackets.
+    pub id: String,
+
+    /// The Notmuch tags of the message.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// The main headers of the message.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// A node of a thread from the JSON output of `notmuch show`.
+///
+/// Messages not matching the query are `null`.
+#[derive(Debug, Deserialize)]
+struct NotmuchRemoteNode(Option<NotmuchRemoteMessage>, Vec<NotmuchRemoteNode>);
+
+impl NotmuchRemoteNode {
+    fn flatten_into(self, msgs: &mut Vec<NotmuchRemoteMessage>) {
+        if let Some(msg) = self.0 {
+            msgs.push(msg);
+        }
+
+        for node in self.1 {
+            node.flatten_into(msgs);
+        }
+    }
+}
+
+/// Parse the JSON output of `notmuch show --format=json` into a flat
+/// list of messages.
+pub fn parse_notmuch_show_json(json: &[u8]) -> Result<Vec<NotmuchRemoteMessage>> {
+    let threads: Vec<Vec<NotmuchRemoteNode>> =
+        serde_json::from_slice(json).map_err(Error::ParseJsonError)?;
+
+    let mut msgs = Vec::new();
+
+    for node in threads.into_iter().flatten() {
+        node.flatten_into(&mut msgs);
+    }
+
+    Ok(msgs)
+}
+
+impl Envelopes {
+    pub fn from_notmuch_remote_msgs(msgs: impl IntoIterator<Item = NotmuchRemoteMessage>) -> Self {
+        msgs.into_iter()
+            .map(Envelope::from_notmuch_remote_msg)
+            .collect()
+    }
+}
+
+impl Envelope {
+    pub fn from_notmuch_remote_msg(msg: NotmuchRemoteMessage) -> Self {
+        let flags = Flags::from_notmuch_tags(&msg.tags);
+        let has_attachment = flags.contains(&Flag::custom("attachment"));
+
+        let message_id = format!("Message-ID: <{}>", msg.id);
+        let subject = get_header(&msg, "Subject");
+        let from = get_header(&msg, "From");
+        let date = get_header(&msg, "Date");
+        let headers = [message_id, subject, from, date].join("\r\n") + "\r\n\r\n";
+
+        // parse a fake message from the built header in order to
+        // extract the envelope
+        let raw: Message = headers.as_bytes().into();
+
+        let mut env = Envelope::from_msg(&msg.id, flags, raw);
+        env.has_attachment = has_attachment;
+        env
+    }
+}
+
+/// Safely extracts a raw header from a remote Notmuch message.
+fn get_header(msg: &NotmuchRemoteMessage, key: &str) -> String {
+    let val = msg.headers.get(key).map(String::as_str).unwrap_or_default();
+    format!("{key}: {val}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_notmuch_show_json;
+    use crate::{
+        envelope::Envelope,
+        flag::{Flag, Flags},
+    };
+
+    #[test]
+    fn parse_show_json() {
+        let json = br#"[[[
+            {
+                "id": "a@localhost",
+                "match": true,
+                "tags": ["inbox", "unread"],
+                "headers": {
+                    "Subject": "Hello",
+                    "From": "alice@localhost",
+                    "Date": "Tue, 01 Oct 2024 10:00:00 +0000"
+                }
+            },
+            [[null, [[{"id": "c@localhost", "tags": ["flagged"], "headers": {}}, []]]]]
+        ]], [[{"id": "b@localhost", "tags": [], "headers": {}}, []]]]"#;
+
+        let msgs = parse_notmuch_show_json(json).unwrap();
+        let ids: Vec<_> = msgs.iter().map(|msg| msg.id.as_str()).collect();
+        assert_eq!(ids, vec!["a@localhost", "c@localhost", "b@localhost"]);
+
+        let envelope = Envelope::from_notmuch_remote_msg(msgs[0].clone());
+        assert_eq!(envelope.id, "a@localhost");
+        assert_eq!(envelope.message_id, "<a@localhost>");
+        assert_eq!(envelope.subject, "Hello");
+        assert_eq!(envelope.flags, Flags::from_iter([Flag::custom("inbox")]));
+
+        let envelope = Envelope::from_notmuch_remote_msg(msgs[1].clone());
+        assert_eq!(
+            envelope.flags,
+            Flags::from_iter([Flag::Flagged, Flag::Seen])
+        );
+
+        assert!(parse_notmuch_show_json(b"invalid").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! # Envelope sections
+//!
+//! Module dedicated to the grouping of envelopes by date, as commonly
+//! displayed by email clients: Today, Yesterday, This week and
+//! Earlier.
+
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone};
+
+use super::{Envelope, Envelopes};
+use crate::account::config::AccountConfig;
+
+/// The envelope date section.
+///
+/// Sections are ordered from the most recent to the oldest one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EnvelopeSection {
+    /// Envelopes dated today, or in the future.
+    Today,
+
+    /// Envelopes dated yesterday.
+    Yesterday,
+
+    /// Envelopes dated this week (starting on Monday), before
+    /// yesterday.
+    ThisWeek,
+
+    /// All other envelopes.
+    Earlier,
+}
+
+impl EnvelopeSection {
+    /// Find the section of the given envelope date, relatively to
+    /// the given today date.
+    pub fn from_dates(date: NaiveDate, today: NaiveDate) -> Self {
+        if date >= today {
+            return Self::Today;
+        }
+
+        if Some(date) == today.checked_sub_days(Days::new(1)) {
+            return Self::Yesterday;
+        }
+
+        if date.iso_week() == today.iso_week() {
+            return Self::ThisWeek;
+        }
+
+        Self::Earlier
+    }
+
+    /// Return the stable key of the section.
+    ///
+    /// Keys do not change across versions, they can be used to
+    /// identify sections in user interfaces or translations.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Today => "today",
+            Self::Yesterday => "yesterday",
+            Self::ThisWeek => "this-week",
+            Self::Earlier => "earlier",
+        }
+    }
+}
+
+impl fmt::Display for EnvelopeSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let section = match self {
+            Self::Today => "Today",
+            Self::Yesterday => "Yesterday",
+            Self::ThisWeek => "This week",
+            Self::Earlier => "Earlier",
+        };
+
+        write!(f, "{section}")
+    }
+}
+
+/// The envelopes grouped by date section.
+pub type EnvelopesSections<'a> = Vec<(EnvelopeSection, Vec<&'a Envelope>)>;
+
+impl Envelopes {
+    /// Group envelopes by date section, relatively to now.
+    ///
+    /// See [`Envelopes::sections_at`].
+    pub fn sections(&self, config: &AccountConfig) -> EnvelopesSections<'_> {
+        self.sections_at(config, Local::now())
+    }
+
+    /// Group envelopes by date section, relatively to the given
+    /// date.
+    ///
+    /// Dates are compared in the local timezone when the envelope
+    /// listing datetime local timezone option is enabled, otherwise
+    /// in the timezone of each envelope date. Only non-empty sections
+    /// are returned, from the most recent to the oldest one. The
+    /// order of envelopes is preserved within sections.
+    pub fn sections_at<Tz: TimeZone>(
+        &self,
+        config: &AccountConfig,
+        now: DateTime<Tz>,
+    ) -> EnvelopesSections<'_> {
+        let local_tz = config.has_envelope_list_datetime_local_tz();
+        let mut sections = EnvelopesSections::new();
+
+        for envelope in self.iter() {
+            let (date, today) = if local_tz {
+                let date = envelope.date.with_timezone(&Local).date_naive();
+                let today = now.with_timezone(&Local).date_naive();
+                (date, today)
+            } else {
+                let date = envelope.date.date_naive();
+                let today = now.with_timezone(envelope.date.offset()).date_naive();
+                (date, today)
+            };
+
+            let section = EnvelopeSection::from_dates(date, today);
+
+            match sections.binary_search_by_key(&section, |(section, _)| *section) {
+                Ok(i) => sections[i].1.push(envelope),
+                Err(i) => sections.insert(i, (section, vec![envelope])),
+            }
+        }
+
+        sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate};
+
+    use super::EnvelopeSection;
+    use crate::{
+        account::config::AccountConfig,
+        envelope::{Envelope, Envelopes},
+    };
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn section_from_dates() {
+        // Thursday
+        let today = date(2024, 5, 16);
+
+        let section = EnvelopeSection::from_dates(date(2024, 5, 17), today);
+        assert_eq!(section, EnvelopeSection::Today);
+        let section = EnvelopeSection::from_dates(date(2024, 5, 16), today);
+        assert_eq!(section, EnvelopeSection::Today);
+        let section = EnvelopeSection::from_dates(date(2024, 5, 15), today);
+        assert_eq!(section, EnvelopeSection::Yesterday);
+        let section = EnvelopeSection::from_dates(date(2024, 5, 13), today);
+        assert_eq!(section, EnvelopeSection::ThisWeek);
+        let section = EnvelopeSection::from_dates(date(2024, 5, 12), today);
+        assert_eq!(section, EnvelopeSection::Earlier);
+
+        // Monday
+        let today = date(2024, 5, 13);
+
+        let section = EnvelopeSection::from_dates(date(2024, 5, 12), today);
+        assert_eq!(section, EnvelopeSection::Yesterday);
+        let section = EnvelopeSection::from_dates(date(2024, 5, 11), today);
+        assert_eq!(section, EnvelopeSection::Earlier);
+    }
+
+    #[test]
+    fn envelopes_sections() {
+        let envelope = |id: &str, date: &str| Envelope {
+            id: id.into(),
+            message_id: id.into(),
+            date: DateTime::parse_from_rfc3339(date).unwrap(),
+            ..Default::default()
+        };
+
+        let envelopes = Envelopes::from_iter([
+            envelope("1", "2024-05-16T08:00:00+02:00"),
+            envelope("2", "2024-05-01T08:00:00+02:00"),
+            envelope("3", "2024-05-16T01:00:00+02:00"),
+            // still yesterday in its own timezone
+            envelope("4", "2024-05-15T23:00:00-05:00"),
+        ]);
+
+        let now = DateTime::parse_from_rfc3339("2024-05-16T10:00:00+02:00").unwrap();
+        let sections = envelopes.sections_at(&AccountConfig::default(), now);
+
+        let sections: Vec<_> = sections
+            .into_iter()
+            .map(|(section, envelopes)| {
+                let ids: Vec<_> = envelopes.into_iter().map(|e| e.id.as_str()).collect();
+                (section.key(), ids)
+            })
+            .collect();
+
+        let expected = vec![
+            ("today", vec!["1", "3"]),
+            ("yesterday", vec!["4"]),
+            ("earlier", vec!["2"]),
+        ];
+
+        assert_eq!(sections, expected);
+    }
+}
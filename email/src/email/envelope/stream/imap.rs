@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use tokio::sync::mpsc;
+
+use super::{from_receiver, EnvelopesStream, StreamEnvelopes};
+use crate::{
+    debug,
+    envelope::list::{imap::fetch_envelopes_batches, ListEnvelopesOptions},
+    imap::{batch::DEFAULT_MAX_BATCH_SIZE, ImapContext},
+    info, runtime,
+    search_query::SearchEmailsQuery,
+    AnyResult,
+};
+
+#[derive(Clone, Debug)]
+pub struct StreamImapEnvelopes {
+    ctx: ImapContext,
+}
+
+impl StreamImapEnvelopes {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn StreamEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn StreamEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl StreamEnvelopes for StreamImapEnvelopes {
+    async fn stream_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<EnvelopesStream> {
+        info!("streaming IMAP envelopes from mailbox {folder}");
+
+        let mut client = self.ctx.client_for("stream_envelopes").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!(name = folder_encoded, "encoded mailbox");
+
+        let data = client.select_mailbox(folder_encoded.clone()).await?;
+
+        if data.exists.unwrap_or_default() == 0 {
+            return Ok(stream::empty().boxed());
+        }
+
+        let query = opts.query.unwrap_or(SearchEmailsQuery {
+            filter: None,
+            sort: None,
+        });
+        let search_criteria = query.to_imap_search_criteria();
+
+        // only UIDs are fetched upfront, envelopes are fetched by
+        // chunks while the stream is consumed
+        let uids = if client.ext_sort_supported() {
+            let sort_criteria = query.to_imap_sort_criteria();
+            client.sort_uids(sort_criteria, search_criteria).await?
+        } else {
+            // UIDs are ascending, most recent envelopes come first
+            let mut uids = client.search_uids(search_criteria).await?;
+            uids.reverse();
+            uids
+        };
+
+        // this client is not used anymore, so we can drop it now in
+        // order to free one client slot from the clients connection
+        // pool
+        drop(client);
+
+        debug!("streaming {} imap envelopes", uids.len());
+
+        let chunk_size = match opts.page_size {
+            0 => DEFAULT_MAX_BATCH_SIZE,
+            n => n,
+        };
+
+        // the channel bounds the number of envelopes fetched ahead of
+        // the consumer
+        let (tx, rx) = mpsc::channel(chunk_size);
+        let ctx = self.ctx.clone();
+
+        runtime::spawn(async move {
+            for uids in uids.chunks(chunk_size) {
+                let mut fetches = match fetch_envelopes_batches(&ctx, &folder_encoded, uids).await {
+                    Ok(fetches) => fetches,
+                    Err(err) => {
+                        let _ = tx.send(Err(err.into())).await;
+                        return;
+                    }
+                };
+
+                for uid in uids {
+                    if let Some(envelope) = fetches.remove(&uid.to_string()) {
+                        if tx.send(Ok(envelope)).await.is_err() {
+                            debug!("imap envelopes stream dropped, stop fetching");
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(from_receiver(rx))
+    }
+}
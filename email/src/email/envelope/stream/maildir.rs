@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use maildirs::MaildirEntry;
+
+use super::{EnvelopesStream, StreamEnvelopes};
+use crate::{
+    email::error::Error,
+    envelope::{list::ListEnvelopesOptions, Envelope},
+    info,
+    maildir::MaildirContextSync,
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct StreamMaildirEnvelopes {
+    ctx: MaildirContextSync,
+}
+
+impl StreamMaildirEnvelopes {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn StreamEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn StreamEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl StreamEnvelopes for StreamMaildirEnvelopes {
+    async fn stream_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<EnvelopesStream> {
+        info!("streaming maildir envelopes from folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let store = ctx.load_flag_store().await?;
+        let folder = ctx.account_config.get_folder_alias(folder);
+
+        // NOTE: entries only hold the path of messages, which are
+        // parsed lazily while the stream is consumed
+        let entries: Vec<MaildirEntry> = mdir
+            .read()
+            .map_err(Error::ListMaildirEntriesError)?
+            .collect();
+
+        let query = opts.query;
+
+        let envelopes = entries.into_iter().filter_map(move |entry| {
+            let msg_path = entry.path().to_owned();
+            let mut envelope = Envelope::try_from(entry).ok()?;
+
+            if let Some(query) = query.as_ref() {
+                if !query.matches_maildir_search_query(&envelope, msg_path.as_ref()) {
+                    return None;
+                }
+            }
+
+            if let Some(store) = store.as_ref() {
+                let flags = store.get(&folder, &envelope.id);
+                envelope.flags.extend(flags.iter().cloned());
+            }
+
+            Some(Ok(envelope))
+        });
+
+        Ok(stream::iter(envelopes).boxed())
+    }
+}
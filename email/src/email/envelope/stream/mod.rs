@@ -0,0 +1,89 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+
+use async_trait::async_trait;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use tokio::sync::mpsc;
+
+use super::{list::ListEnvelopesOptions, Envelope};
+use crate::AnyResult;
+
+/// The stream of envelopes returned by [`StreamEnvelopes`].
+pub type EnvelopesStream = BoxStream<'static, AnyResult<Envelope>>;
+
+#[async_trait]
+pub trait StreamEnvelopes: Send + Sync {
+    /// Stream envelopes from the given folder matching the given
+    /// options.
+    ///
+    /// Unlike [`ListEnvelopes`](super::list::ListEnvelopes),
+    /// envelopes are yielded as soon as they are fetched, so that
+    /// the first ones can be rendered while the others are still
+    /// being fetched, and they are never all held in memory.
+    ///
+    /// The query filter applies. Envelopes follow the sort order of
+    /// the query when the backend can sort them without fetching
+    /// them, otherwise the order is backend-specific. The page size
+    /// can be used by backends as the number of envelopes fetched at
+    /// once, other pagination and incremental options are ignored.
+    async fn stream_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<EnvelopesStream>;
+}
+
+/// Build an envelopes stream from the given channel receiver.
+///
+/// The stream ends when all the senders are dropped. Dropping the
+/// stream makes the senders fail, which can be used to stop
+/// producing envelopes.
+pub fn from_receiver(rx: mpsc::Receiver<AnyResult<Envelope>>) -> EnvelopesStream {
+    stream::unfold(rx, |mut rx| async move {
+        let item = rx.recv().await?;
+        Some((item, rx))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use tokio::sync::mpsc;
+
+    use super::from_receiver;
+    use crate::envelope::Envelope;
+
+    #[tokio::test]
+    async fn receiver_stream() {
+        let (tx, rx) = mpsc::channel(1);
+
+        let producer = tokio::spawn(async move {
+            for id in ["1", "2", "3"] {
+                let envelope = Envelope {
+                    id: id.into(),
+                    ..Default::default()
+                };
+
+                if tx.send(Ok(envelope)).await.is_err() {
+                    return false;
+                }
+            }
+
+            true
+        });
+
+        let ids: Vec<_> = from_receiver(rx)
+            .map(|envelope| envelope.unwrap().id)
+            .collect()
+            .await;
+
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert!(producer.await.unwrap());
+    }
+}
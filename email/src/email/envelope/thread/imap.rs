@@ -1,4 +1,7 @@
-use std::num::NonZeroU32;
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
 
 use async_trait::async_trait;
 use imap_next::imap_types::{
@@ -7,13 +10,15 @@
     sequence::{Sequence, SequenceSet},
 };
 use petgraph::{graphmap::DiGraphMap, Direction};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::ThreadEnvelopes;
+use super::{jwz, ThreadEnvelopes};
 use crate::{
+    backend::{self, kit},
     debug,
-    envelope::{list::ListEnvelopesOptions, SingleId, ThreadedEnvelope, ThreadedEnvelopes},
-    imap::ImapContext,
+    envelope::{
+        list::ListEnvelopesOptions, Envelope, SingleId, ThreadedEnvelope, ThreadedEnvelopes,
+    },
+    imap::{ImapClient, ImapContext},
     AnyResult,
 };
 
@@ -46,12 +51,11 @@ async fn thread_envelopes(
     ) -> AnyResult<ThreadedEnvelopes> {
         debug!(?opts, "thread options");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("thread_envelopes").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!(folder_encoded, "utf7 encoded folder");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!(folder_encoded, "encoded folder");
 
         let folder_size = client.select_mailbox(folder_encoded).await?.exists.unwrap() as usize;
         debug!(folder_size, "folder size");
@@ -62,19 +66,58 @@ async fn thread_envelopes(
             }));
         }
 
-        let threads = if let Some(query) = opts.query.as_ref() {
+        if !client.ext_thread_references_supported() {
+            debug!("THREAD=REFERENCES not supported, threading envelopes locally");
+
+            let mut envelopes = fetch_envelopes_to_thread(&mut client, &opts).await?;
+
+            // threads are sorted from the most recent to the oldest
+            // one, so pages start with the most recent threads
+            let threads = jwz::thread_envelopes(envelopes.values());
+            let page_range = kit::page_range(threads.len(), opts.page, opts.page_size)
+                .ok_or(backend::Error::PageOutOfBoundsError(opts.page + 1))?;
+            let edges: Vec<_> = jwz::edges(&threads[page_range])
+                .into_iter()
+                .map(|(a, b, w)| (a.to_owned(), b.to_owned(), w))
+                .collect();
+
+            let ids: HashSet<_> = edges.iter().map(|(_, b, _)| b.clone()).collect();
+            envelopes.retain(|id, _| ids.contains(id));
+
+            return Ok(ThreadedEnvelopes::new(envelopes, move |envelopes| {
+                let edges = edges.iter().map(|(a, b, w)| (a, b, *w));
+                jwz::build_graph(envelopes, edges)
+            }));
+        }
+
+        let mut threads = if let Some(query) = opts.query.as_ref() {
             let search_criteria = query.to_imap_search_criteria();
-            client.thread_envelopes(search_criteria).await.unwrap()
+            client.thread_envelopes(search_criteria).await?
         } else {
-            client.thread_envelopes(Some(SearchKey::All)).await.unwrap()
+            client.thread_envelopes(Some(SearchKey::All)).await?
         };
 
+        // threads are sorted from the oldest to the most recent one,
+        // pages start with the most recent threads
+        threads.reverse();
+
+        let page_range = kit::page_range(threads.len(), opts.page, opts.page_size)
+            .ok_or(backend::Error::PageOutOfBoundsError(opts.page + 1))?;
+        let threads: Vec<_> = threads.drain(page_range).collect();
+        debug!("threading {} imap threads", threads.len());
+
         let mut graph = DiGraphMap::<u32, u8>::new();
 
         for thread in threads {
             build_graph_from_thread(&mut graph, 0, 0, thread)
         }
 
+        if graph.node_count() == 0 {
+            return Ok(ThreadedEnvelopes::new(Default::default(), |_| {
+                Default::default()
+            }));
+        }
+
         let uids: SequenceSet = graph
             .nodes()
             .filter_map(NonZeroU32::new)
@@ -83,7 +126,7 @@ async fn thread_envelopes(
             .try_into()
             .unwrap();
 
-        let envelopes = client.fetch_envelopes_map(uids).await.unwrap();
+        let envelopes = client.fetch_envelopes_map(uids).await?;
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let mut final_graph = DiGraphMap::<ThreadedEnvelope, u8>::new();
 
@@ -119,23 +162,35 @@ async fn thread_envelope(
         id: SingleId,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("thread_envelope").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!(folder_encoded, "utf7 encoded folder");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!(folder_encoded, "encoded folder");
 
         let _folder_size = client.select_mailbox(folder_encoded).await?.exists.unwrap() as usize;
         debug!(folder_size = _folder_size, "folder size");
 
         let uid = id.parse::<u32>().unwrap();
 
+        if !client.ext_thread_references_supported() {
+            debug!("THREAD=REFERENCES not supported, threading envelopes locally");
+
+            let envelopes = fetch_envelopes_to_thread(&mut client, &opts).await?;
+            let id = uid.to_string();
+
+            return Ok(ThreadedEnvelopes::new(envelopes, move |envelopes| {
+                let threads = jwz::thread_envelopes(envelopes.values());
+                let thread = jwz::find_thread(&threads, &id);
+                jwz::build_graph(envelopes, jwz::edges(thread.as_slice()))
+            }));
+        }
+
         let threads = if let Some(query) = opts.query.as_ref() {
             let search_criteria = query.to_imap_search_criteria();
-            client.thread_envelopes(search_criteria).await.unwrap()
+            client.thread_envelopes(search_criteria).await?
         } else {
-            client.thread_envelopes(Some(SearchKey::All)).await.unwrap()
+            client.thread_envelopes(Some(SearchKey::All)).await?
         };
 
         let mut full_graph = DiGraphMap::<u32, u8>::new();
@@ -157,7 +212,7 @@ async fn thread_envelope(
             .try_into()
             .unwrap();
 
-        let envelopes = client.fetch_envelopes_map(uids).await.unwrap();
+        let envelopes = client.fetch_envelopes_map(uids).await?;
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let mut final_graph = DiGraphMap::<ThreadedEnvelope, u8>::new();
 
@@ -472,3 +527,31 @@ fn imap_codec_thread_7() {
         assert_thread_eq_graph(thread, graph);
     }
 }
+
+/// Fetch the envelopes matching the given options, in order to thread
+/// them locally.
+///
+/// This is the fallback used when the server does not support the
+/// THREAD=REFERENCES extension, see [`jwz`].
+async fn fetch_envelopes_to_thread(
+    client: &mut ImapClient,
+    opts: &ListEnvelopesOptions,
+) -> AnyResult<HashMap<String, Envelope>> {
+    let uids = match opts.query.as_ref() {
+        Some(query) => client.search_uids(query.to_imap_search_criteria()).await?,
+        None => client.search_uids(Some(SearchKey::All)).await?,
+    };
+
+    if uids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let uids: SequenceSet = uids
+        .into_iter()
+        .map(Sequence::from)
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+
+    Ok(client.fetch_envelopes_map(uids).await?)
+}
@@ -0,0 +1,355 @@
+//! # JWZ threading
+//!
+//! Module dedicated to the threading algorithm described by Jamie
+//! Zawinski (<https://www.jwz.org/doc/threading.html>), which groups
+//! envelopes into threads using their References and In-Reply-To
+//! headers. It is used by backends that cannot thread envelopes by
+//! themselves.
+//!
+//! The subject grouping step of the original algorithm is skipped,
+//! since it tends to merge unrelated conversations sharing a common
+//! subject.
+
+use std::collections::HashMap;
+
+use petgraph::graphmap::DiGraphMap;
+
+use crate::envelope::{Envelope, ThreadedEnvelope};
+
+/// A thread of envelopes built by the JWZ algorithm.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JwzThread<'a> {
+    /// The envelope starting the thread.
+    pub envelope: &'a Envelope,
+
+    /// The replies to the envelope, from the oldest to the most
+    /// recent one.
+    pub replies: Vec<JwzThread<'a>>,
+}
+
+/// A node of the thread tree, holding an envelope or standing for a
+/// referenced message that is not part of the threaded envelopes.
+#[derive(Debug, Default)]
+struct Container<'a> {
+    envelope: Option<&'a Envelope>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// The thread tree, indexed by Message-ID.
+#[derive(Debug, Default)]
+struct Containers<'a> {
+    containers: Vec<Container<'a>>,
+    ids: HashMap<&'a str, usize>,
+}
+
+impl<'a> Containers<'a> {
+    fn insert(&mut self) -> usize {
+        self.containers.push(Container::default());
+        self.containers.len() - 1
+    }
+
+    fn get_or_insert(&mut self, msg_id: &'a str) -> usize {
+        match self.ids.get(msg_id) {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.insert();
+                self.ids.insert(msg_id, idx);
+                idx
+            }
+        }
+    }
+
+    /// Return `true` if the given ancestor is the given node or one
+    /// of its ancestors.
+    fn is_ancestor(&self, ancestor: usize, mut node: usize) -> bool {
+        loop {
+            if node == ancestor {
+                return true;
+            }
+
+            match self.containers[node].parent {
+                Some(parent) => node = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn unlink(&mut self, child: usize) {
+        if let Some(parent) = self.containers[child].parent.take() {
+            self.containers[parent].children.retain(|idx| *idx != child);
+        }
+    }
+
+    /// Make the given child a child of the given parent, unless it
+    /// introduces a loop.
+    fn link(&mut self, parent: usize, child: usize) {
+        if self.is_ancestor(child, parent) {
+            return;
+        }
+
+        self.unlink(child);
+        self.containers[child].parent = Some(parent);
+        self.containers[parent].children.push(child);
+    }
+
+    /// Collect the threads of the given container.
+    ///
+    /// Containers without envelope are pruned: their children are
+    /// promoted to their level.
+    fn threads(&self, idx: usize) -> Vec<JwzThread<'a>> {
+        let container = &self.containers[idx];
+
+        let mut replies: Vec<_> = container
+            .children
+            .iter()
+            .flat_map(|idx| self.threads(*idx))
+            .collect();
+
+        match container.envelope {
+            Some(envelope) => {
+                replies.sort_by(|a, b| a.envelope.date.cmp(&b.envelope.date));
+                vec![JwzThread { envelope, replies }]
+            }
+            None => replies,
+        }
+    }
+}
+
+/// Group the given envelopes into threads.
+///
+/// Threads are sorted from the most recent to the oldest one, based
+/// on the date of their first envelope.
+pub fn thread_envelopes<'a>(
+    envelopes: impl IntoIterator<Item = &'a Envelope>,
+) -> Vec<JwzThread<'a>> {
+    let mut containers = Containers::default();
+
+    for envelope in envelopes {
+        let msg_id = envelope.message_id.as_str();
+
+        // messages sharing the same Message-ID are kept apart
+        let idx = match containers.ids.get(msg_id) {
+            Some(idx) if containers.containers[*idx].envelope.is_some() => containers.insert(),
+            _ => containers.get_or_insert(msg_id),
+        };
+
+        containers.containers[idx].envelope = Some(envelope);
+
+        let mut refs: Vec<&str> = envelope.references.iter().map(String::as_str).collect();
+
+        if let Some(in_reply_to) = envelope.in_reply_to.as_deref() {
+            if refs.last() != Some(&in_reply_to) {
+                refs.push(in_reply_to);
+            }
+        }
+
+        // references are linked together, without breaking the
+        // links built from previous envelopes
+        let mut parent = None;
+
+        for msg_id in refs {
+            let node = containers.get_or_insert(msg_id);
+
+            if let Some(parent) = parent {
+                if containers.containers[node].parent.is_none() {
+                    containers.link(parent, node);
+                }
+            }
+
+            parent = Some(node);
+        }
+
+        // the envelope own references take precedence over the
+        // links built from previous envelopes
+        match parent {
+            Some(parent) => containers.link(parent, idx),
+            None => containers.unlink(idx),
+        }
+    }
+
+    let mut threads: Vec<_> = (0..containers.containers.len())
+        .filter(|idx| containers.containers[*idx].parent.is_none())
+        .flat_map(|idx| containers.threads(idx))
+        .collect();
+
+    threads.sort_by(|a, b| b.envelope.date.cmp(&a.envelope.date));
+    threads
+}
+
+/// Find the thread of the envelope matching the given identifier.
+///
+/// The returned thread only contains the ancestors and the replies of
+/// the envelope, siblings are left out.
+pub fn find_thread<'a>(threads: &[JwzThread<'a>], id: &str) -> Option<JwzThread<'a>> {
+    threads.iter().find_map(|thread| {
+        if thread.envelope.id == id {
+            return Some(thread.clone());
+        }
+
+        let reply = find_thread(&thread.replies, id)?;
+
+        Some(JwzThread {
+            envelope: thread.envelope,
+            replies: vec![reply],
+        })
+    })
+}
+
+/// Return the edges of the given threads, from the parent envelope
+/// identifier to the child envelope identifier, weighted by depth.
+///
+/// Threads are attached to the `"0"` placeholder, following the
+/// convention of [`ThreadedEnvelopes`](crate::envelope::ThreadedEnvelopes)
+/// graphs.
+pub fn edges<'a>(threads: &[JwzThread<'a>]) -> Vec<(&'a str, &'a str, u8)> {
+    fn walk<'a>(
+        edges: &mut Vec<(&'a str, &'a str, u8)>,
+        parent: &'a str,
+        depth: u8,
+        thread: &JwzThread<'a>,
+    ) {
+        let id = thread.envelope.id.as_str();
+        edges.push((parent, id, depth));
+
+        for reply in &thread.replies {
+            walk(edges, id, depth.saturating_add(1), reply);
+        }
+    }
+
+    let mut edges = Vec::new();
+
+    for thread in threads {
+        walk(&mut edges, "0", 0, thread);
+    }
+
+    edges
+}
+
+/// Build the graph of threaded envelopes from the given edges.
+///
+/// Identifiers missing from the given envelopes are replaced by the
+/// `"0"` placeholder.
+pub fn build_graph<'a, S: AsRef<str>>(
+    envelopes: &'a HashMap<String, Envelope>,
+    edges: impl IntoIterator<Item = (S, S, u8)>,
+) -> DiGraphMap<ThreadedEnvelope<'a>, u8> {
+    let placeholder = ThreadedEnvelope {
+        id: "0",
+        message_id: "0",
+        subject: "",
+        from: "",
+        date: Default::default(),
+    };
+
+    let node = |id: S| match envelopes.get(id.as_ref()) {
+        Some(envelope) => envelope.as_threaded(),
+        None => placeholder,
+    };
+
+    let mut graph = DiGraphMap::new();
+
+    for (a, b, w) in edges {
+        graph.add_edge(node(a), node(b), w);
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::DateTime;
+
+    use super::{build_graph, edges, find_thread, thread_envelopes};
+    use crate::envelope::{Envelope, ThreadedEnvelopes};
+
+    fn envelope(id: &str, date: &str, refs: &[&str], in_reply_to: Option<&str>) -> Envelope {
+        Envelope {
+            id: id.into(),
+            message_id: format!("<{id}@localhost>"),
+            references: refs.iter().map(|r| format!("<{r}@localhost>")).collect(),
+            in_reply_to: in_reply_to.map(|r| format!("<{r}@localhost>")),
+            date: DateTime::parse_from_rfc3339(date).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn threads() {
+        let envelopes = [
+            envelope("c", "2024-01-03T00:00:00Z", &["a", "b"], Some("b")),
+            envelope("a", "2024-01-01T00:00:00Z", &[], None),
+            envelope("d", "2024-01-04T00:00:00Z", &["a"], None),
+            envelope("b", "2024-01-02T00:00:00Z", &[], Some("a")),
+            envelope("e", "2024-01-05T00:00:00Z", &[], None),
+            // the parent of f is missing, f becomes a root
+            envelope("f", "2024-01-06T00:00:00Z", &["x"], Some("x")),
+        ];
+
+        let threads = thread_envelopes(&envelopes);
+
+        assert_eq!(
+            edges(&threads),
+            [
+                ("0", "f", 0),
+                ("0", "e", 0),
+                ("0", "a", 0),
+                ("a", "b", 1),
+                ("b", "c", 2),
+                ("a", "d", 1),
+            ]
+        );
+
+        let thread = find_thread(&threads, "b").unwrap();
+
+        assert_eq!(
+            edges(&[thread]),
+            [("0", "a", 0), ("a", "b", 1), ("b", "c", 2)]
+        );
+    }
+
+    #[test]
+    fn loops() {
+        let envelopes = [
+            envelope("a", "2024-01-01T00:00:00Z", &["b"], None),
+            envelope("b", "2024-01-02T00:00:00Z", &["a"], None),
+            envelope("c", "2024-01-03T00:00:00Z", &["c"], None),
+        ];
+
+        let threads = thread_envelopes(&envelopes);
+
+        assert_eq!(
+            edges(&threads),
+            [("0", "c", 0), ("0", "b", 0), ("b", "a", 1)]
+        );
+    }
+
+    #[test]
+    fn grouped_threads() {
+        let envelopes: HashMap<_, _> = [
+            envelope("a", "2024-01-01T00:00:00Z", &[], None),
+            envelope("b", "2024-01-02T00:00:00Z", &["a"], Some("a")),
+            envelope("c", "2024-01-03T00:00:00Z", &[], None),
+        ]
+        .into_iter()
+        .map(|envelope| (envelope.id.clone(), envelope))
+        .collect();
+
+        let threaded = ThreadedEnvelopes::new(envelopes, |envelopes| {
+            let threads = thread_envelopes(envelopes.values());
+            build_graph(envelopes, edges(&threads))
+        });
+
+        let threads = threaded.threads();
+        assert_eq!(threads.len(), 2);
+
+        assert_eq!(threads[0].envelope.id, "c");
+        assert_eq!(threads[0].count(), 1);
+
+        assert_eq!(threads[1].envelope.id, "a");
+        assert_eq!(threads[1].replies[0].envelope.id, "b");
+        assert_eq!(threads[1].count(), 2);
+    }
+}
@@ -1,13 +1,8 @@
-use std::collections::HashMap;
-
 use async_trait::async_trait;
-use petgraph::{algo::astar, graphmap::DiGraphMap, Direction};
 
-use super::ThreadEnvelopes;
+use super::{jwz, ThreadEnvelopes};
 use crate::{
-    envelope::{
-        list::ListEnvelopesOptions, Envelopes, SingleId, ThreadedEnvelope, ThreadedEnvelopes,
-    },
+    envelope::{list::ListEnvelopesOptions, Envelopes, SingleId, ThreadedEnvelopes},
     maildir::MaildirContextSync,
     AnyResult, Error,
 };
@@ -48,63 +43,9 @@ async fn thread_envelopes(
             .map(|e| (e.id.clone(), e))
             .collect();
 
-        let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
-            let msg_id_mapping: HashMap<_, _> = envelopes
-                .values()
-                .map(|e| (e.message_id.as_str(), e.id.as_str()))
-                .collect();
-
-            let mut graph = DiGraphMap::<&str, u8>::new();
-
-            for envelope in envelopes.values() {
-                match envelope.in_reply_to.as_ref() {
-                    Some(msg_id) => {
-                        if let Some(id) = msg_id_mapping.get(msg_id.as_str()) {
-                            graph.add_edge(*id, envelope.id.as_str(), 0);
-                        }
-                    }
-                    None => {
-                        graph.add_edge("0", envelope.id.as_str(), 0);
-                    }
-                };
-            }
-
-            let leafs: Vec<_> = graph
-                .nodes()
-                .filter(|node| graph.neighbors_directed(node, Direction::Outgoing).count() == 0)
-                .collect();
-
-            for leaf in leafs {
-                if let Some((_, path)) = astar(&graph, "0", |n| n == leaf, |_| 0, |_| 0) {
-                    let mut pairs = path.windows(2).enumerate();
-                    while let Some((depth, [a, b])) = pairs.next() {
-                        graph[(*a, *b)] = depth as u8;
-                    }
-                };
-            }
-
-            let mut final_graph = DiGraphMap::<ThreadedEnvelope, u8>::new();
-
-            for (a, b, w) in graph.all_edges() {
-                let eb = envelopes.get(&b.to_string()).unwrap();
-                match envelopes.get(&a.to_string()) {
-                    Some(ea) => {
-                        final_graph.add_edge(ea.as_threaded(), eb.as_threaded(), *w);
-                    }
-                    None => {
-                        let ea = ThreadedEnvelope {
-                            id: "0",
-                            message_id: "0",
-                            subject: "",
-                            from: "",
-                            date: Default::default(),
-                        };
-                        final_graph.add_edge(ea, eb.as_threaded(), *w);
-                    }
-                }
-            }
-
-            final_graph
+        let envelopes = ThreadedEnvelopes::new(envelopes, |envelopes| {
+            let threads = jwz::thread_envelopes(envelopes.values());
+            jwz::build_graph(envelopes, jwz::edges(&threads))
         });
 
         Ok(envelopes)
@@ -127,66 +68,9 @@ async fn thread_envelope(
             .collect();
 
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
-            let msg_id_mapping: HashMap<_, _> = envelopes
-                .values()
-                .map(|e| (e.message_id.as_str(), e.id.as_str()))
-                .collect();
-
-            let mut graph = DiGraphMap::<&str, u8>::new();
-
-            for envelope in envelopes.values() {
-                match envelope.in_reply_to.as_ref() {
-                    Some(msg_id) => {
-                        if let Some(id) = msg_id_mapping.get(msg_id.as_str()) {
-                            graph.add_edge(*id, envelope.id.as_str(), 0);
-                        }
-                    }
-                    None => {
-                        graph.add_edge("0", envelope.id.as_str(), 0);
-                    }
-                };
-            }
-
-            let leafs: Vec<_> = graph
-                .nodes()
-                .filter(|node| graph.neighbors_directed(node, Direction::Outgoing).count() == 0)
-                .collect();
-
-            let mut graph2 = DiGraphMap::<&str, u8>::new();
-
-            for leaf in leafs {
-                if let Some((_, path)) = astar(&graph, "0", |n| n == leaf, |_| 0, |_| 0) {
-                    if path.contains(&&id.as_str()) {
-                        let mut pairs = path.windows(2).enumerate();
-                        while let Some((depth, [a, b])) = pairs.next() {
-                            graph2.add_edge(*a, *b, depth as u8);
-                        }
-                    }
-                };
-            }
-
-            let mut final_graph = DiGraphMap::<ThreadedEnvelope, u8>::new();
-
-            for (a, b, w) in graph2.all_edges() {
-                let eb = envelopes.get(&b.to_string()).unwrap();
-                match envelopes.get(&a.to_string()) {
-                    Some(ea) => {
-                        final_graph.add_edge(ea.as_threaded(), eb.as_threaded(), *w);
-                    }
-                    None => {
-                        let ea = ThreadedEnvelope {
-                            id: "0",
-                            message_id: "0",
-                            subject: "",
-                            from: "",
-                            date: Default::default(),
-                        };
-                        final_graph.add_edge(ea, eb.as_threaded(), *w);
-                    }
-                }
-            }
-
-            final_graph
+            let threads = jwz::thread_envelopes(envelopes.values());
+            let thread = jwz::find_thread(&threads, id.as_str());
+            jwz::build_graph(envelopes, jwz::edges(thread.as_slice()))
         });
 
         Ok(envelopes)
@@ -1,6 +1,7 @@
 pub mod config;
 #[cfg(feature = "imap")]
 pub mod imap;
+pub mod jwz;
 #[cfg(feature = "maildir")]
 pub mod maildir;
 
@@ -12,6 +12,12 @@ pub struct WatchEnvelopeConfig {
     /// received.
     pub received: Option<WatchHook>,
 
+    /// Watch hook configuration for when a new envelope has been
+    /// received from a VIP sender.
+    ///
+    /// Defaults to the received hook.
+    pub received_vip: Option<WatchHook>,
+
     /// Watch hook configuration hook for any other case.
     pub any: Option<WatchHook>,
 }
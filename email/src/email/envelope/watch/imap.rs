@@ -1,20 +1,56 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use tokio::sync::oneshot::{Receiver, Sender};
-use utf7_imap::encode_utf7_imap as encode_utf7;
+use imap_next::imap_types::search::SearchKey;
+use tokio::{
+    select,
+    sync::{
+        mpsc::UnboundedSender,
+        oneshot::{self, Receiver, Sender},
+    },
+    task::JoinSet,
+};
 
-use super::WatchEnvelopes;
-use crate::{debug, envelope::Envelope, imap::ImapContext, info, AnyResult};
+use super::{WatchEnvelopes, WatchEvent};
+use crate::{
+    debug,
+    envelope::{list::imap::fetch_envelopes_changes, Envelope},
+    imap::{
+        self,
+        notify::{ImapNotification, ImapNotifyClient},
+        Error, ImapClient, ImapContext,
+    },
+    info, runtime, warn, AnyBoxedError, AnyResult,
+};
+
+/// The interval between two refreshes of folders that cannot be
+/// watched using IDLE.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Debug)]
 pub struct WatchImapEnvelopes {
     ctx: ImapContext,
+
+    /// The channel envelope changes are sent to, if any.
+    events: Option<UnboundedSender<WatchEvent>>,
 }
 
 impl WatchImapEnvelopes {
     pub fn new(ctx: &ImapContext) -> Self {
-        Self { ctx: ctx.clone() }
+        Self {
+            ctx: ctx.clone(),
+            events: None,
+        }
+    }
+
+    /// Send envelope changes to the given channel, in addition to
+    /// executing the configured hooks.
+    pub fn with_events(mut self, events: UnboundedSender<WatchEvent>) -> Self {
+        self.events = Some(events);
+        self
     }
 
     pub fn new_boxed(ctx: &ImapContext) -> Box<dyn WatchEnvelopes> {
@@ -32,39 +68,359 @@ pub async fn watch_envelopes_loop(
     ) -> AnyResult<()> {
         info!("watching imap folder {folder} for envelope changes");
 
+        let mut client = self.ctx.client_for("watch_envelopes").await;
+        let mut folder = WatchedFolder::new(&mut client, folder).await?;
+
+        loop {
+            match client.idle(wait_for_shutdown_request).await {
+                Ok(()) => (),
+                Err(Error::IdleInterruptedError) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+
+            let next_envelopes = folder.examine_and_fetch(&mut client).await?;
+            self.apply_changes(&mut folder, next_envelopes).await;
+        }
+    }
+
+    /// Watch the given folders for envelopes changes.
+    ///
+    /// When the NOTIFY extension is supported, all folders are
+    /// watched using a single dedicated connection. Otherwise folders
+    /// are watched using IDLE, see
+    /// [`WatchImapEnvelopes::watch_folders_envelopes_idle_loop`].
+    pub async fn watch_folders_envelopes_loop(
+        &self,
+        folders: &[String],
+        wait_for_shutdown_request: &mut Receiver<()>,
+    ) -> AnyResult<()> {
+        if let [folder] = folders {
+            return self
+                .watch_envelopes_loop(folder, wait_for_shutdown_request)
+                .await;
+        }
+
+        if folders.is_empty() {
+            return Ok(());
+        }
+
+        let mut client = self.ctx.client_for("watch_envelopes").await;
+
+        if !client.ext_notify_supported() {
+            drop(client);
+            return self
+                .watch_folders_envelopes_idle_loop(folders, wait_for_shutdown_request)
+                .await;
+        }
+
+        let mut watched_folders = Vec::with_capacity(folders.len());
+
+        for folder in folders {
+            watched_folders.push(WatchedFolder::new(&mut client, folder).await?);
+        }
+
+        let mailboxes: Vec<_> = watched_folders.iter().map(|f| f.encoded.clone()).collect();
+        let client_builder = client.client_builder.clone();
+        drop(client);
+
+        let notify = async {
+            let mut notify = ImapNotifyClient::connect(&client_builder).await?;
+            notify.notify(&mailboxes).await?;
+            imap::Result::Ok(notify)
+        };
+
+        match notify.await {
+            Ok(notify) => {
+                info!("watching imap folders {folders:?} for envelope changes using NOTIFY");
+                self.watch_notify_loop(notify, watched_folders, wait_for_shutdown_request)
+                    .await
+            }
+            Err(_err) => {
+                warn!("cannot watch imap folders using NOTIFY, falling back to IDLE: {_err}");
+                self.watch_folders_envelopes_idle_loop(folders, wait_for_shutdown_request)
+                    .await
+            }
+        }
+    }
+
+    /// Refresh the given folders every time the NOTIFY connection
+    /// notifies a change, using a client of the pool.
+    async fn watch_notify_loop(
+        &self,
+        mut notify: ImapNotifyClient,
+        mut folders: Vec<WatchedFolder>,
+        wait_for_shutdown_request: &mut Receiver<()>,
+    ) -> AnyResult<()> {
+        // the connection is kept alive the same way IDLE commands
+        // are refreshed
+        let keep_alive = self.ctx.imap_config.find_watch_timeout().unwrap_or(29 * 60);
+        let keep_alive = Duration::from_secs(keep_alive);
+
+        loop {
+            let notification = select! {
+                notification = notify.next() => notification?,
+                _ = runtime::sleep(keep_alive) => {
+                    notify.noop().await?;
+                    continue;
+                }
+                _ = &mut *wait_for_shutdown_request => {
+                    debug!("shutdown requested, closing NOTIFY connection…");
+                    let _ = notify.logout().await;
+                    return Ok(());
+                }
+            };
+
+            debug!("received imap notification: {notification:?}");
+
+            let changed: Vec<_> = match notification {
+                ImapNotification::Selected => vec![0],
+                ImapNotification::Mailbox(mbox) => folders
+                    .iter()
+                    .position(|folder| folder.is(&mbox))
+                    .into_iter()
+                    .collect(),
+                ImapNotification::Overflow => (0..folders.len()).collect(),
+            };
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            let mut client = self.ctx.client_for("watch_envelopes").await;
+
+            for i in changed {
+                let folder = &mut folders[i];
+                let next_envelopes = folder.examine_and_fetch(&mut client).await?;
+                self.apply_changes(folder, next_envelopes).await;
+            }
+        }
+    }
+
+    /// Watch the given folders for envelopes changes using IDLE.
+    ///
+    /// IDLE requires one client of the pool per folder. In order to
+    /// keep at least one client free for other operations, only the
+    /// first folders are watched using IDLE. The remaining ones are
+    /// polled in turns using a free client of the pool.
+    pub async fn watch_folders_envelopes_idle_loop(
+        &self,
+        folders: &[String],
+        wait_for_shutdown_request: &mut Receiver<()>,
+    ) -> AnyResult<()> {
+        let idle_count = folders.len().min(self.ctx.pool_size().saturating_sub(1));
+        let (idle_folders, polled_folders) = folders.split_at(idle_count);
+
+        if !polled_folders.is_empty() {
+            debug!("not enough clients to IDLE on every folder, polling {polled_folders:?}");
+        }
+
+        let mut shutdown_requests = Vec::with_capacity(folders.len());
+        let mut watchers = JoinSet::new();
+
+        for folder in idle_folders {
+            let (shutdown_request, mut wait_for_shutdown_request) = oneshot::channel();
+            shutdown_requests.push(shutdown_request);
+
+            let watch = self.clone();
+            let folder = folder.clone();
+
+            watchers.spawn(async move {
+                watch
+                    .watch_envelopes_loop(&folder, &mut wait_for_shutdown_request)
+                    .await
+            });
+        }
+
+        if !polled_folders.is_empty() {
+            let (shutdown_request, mut wait_for_shutdown_request) = oneshot::channel();
+            shutdown_requests.push(shutdown_request);
+
+            let watch = self.clone();
+            let folders = polled_folders.to_vec();
+
+            watchers.spawn(async move {
+                watch
+                    .poll_folders_envelopes_loop(&folders, &mut wait_for_shutdown_request)
+                    .await
+            });
+        }
+
+        // stops at the first shutdown request or watcher failure
+        let res = select! {
+            _ = wait_for_shutdown_request => Ok(()),
+            Some(res) = watchers.join_next() => {
+                res.map_err(AnyBoxedError::from).and_then(|res| res)
+            }
+        };
+
+        for shutdown_request in shutdown_requests {
+            let _ = shutdown_request.send(());
+        }
+
+        while watchers.join_next().await.is_some() {}
+
+        res
+    }
+
+    /// Refresh the given folders every [`POLL_INTERVAL`], using a
+    /// client of the pool for the time of the refresh only.
+    async fn poll_folders_envelopes_loop(
+        &self,
+        folders: &[String],
+        wait_for_shutdown_request: &mut Receiver<()>,
+    ) -> AnyResult<()> {
+        let mut watched_folders = Vec::with_capacity(folders.len());
+        let mut client = self.ctx.client_for("watch_envelopes").await;
+
+        for folder in folders {
+            watched_folders.push(WatchedFolder::new(&mut client, folder).await?);
+        }
+
+        drop(client);
+
+        loop {
+            select! {
+                _ = runtime::sleep(POLL_INTERVAL) => (),
+                _ = &mut *wait_for_shutdown_request => return Ok(()),
+            }
+
+            let mut client = self.ctx.client_for("watch_envelopes").await;
+
+            for folder in &mut watched_folders {
+                let next_envelopes = folder.examine_and_fetch(&mut client).await?;
+                self.apply_changes(folder, next_envelopes).await;
+            }
+        }
+    }
+
+    /// Execute the hooks and send the events matching the changes
+    /// of the given folder, then save its next envelopes.
+    async fn apply_changes(
+        &self,
+        folder: &mut WatchedFolder,
+        next_envelopes: HashMap<String, Envelope>,
+    ) {
         let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        self.exec_hooks(config, &folder.envelopes, &next_envelopes)
+            .await;
+
+        if let Some(events) = &self.events {
+            for event in WatchEvent::diff(&folder.envelopes, &next_envelopes) {
+                if events.send(event).is_err() {
+                    debug!("watch events receiver dropped, skipping events");
+                    break;
+                }
+            }
+        }
+
+        folder.envelopes = next_envelopes;
+    }
+
+    /// Watch the given folders for envelopes changes, until a
+    /// shutdown is requested.
+    ///
+    /// See [`WatchImapEnvelopes::watch_folders_envelopes_loop`].
+    pub async fn watch_folders_envelopes(
+        &self,
+        folders: &[String],
+        mut wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        let res = self
+            .watch_folders_envelopes_loop(folders, &mut wait_for_shutdown_request)
+            .await;
 
+        // the shutdown receiver may have been dropped already
+        let _ = shutdown.send(());
+
+        res
+    }
+}
+
+/// The state of a watched folder.
+#[derive(Debug)]
+struct WatchedFolder {
+    /// The encoded folder name.
+    encoded: String,
+
+    /// Whether the CONDSTORE extension is supported.
+    condstore: bool,
+
+    /// The highest modification sequence seen so far.
+    highest_mod_seq: u64,
+
+    /// The last fetched envelopes, by identifier.
+    envelopes: HashMap<String, Envelope>,
+}
+
+impl WatchedFolder {
+    /// Examine the given folder using the given client, then fetch
+    /// its envelopes.
+    async fn new(client: &mut ImapClient, folder: &str) -> AnyResult<Self> {
+        let folder = client.get_folder_alias(folder);
+        let encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {encoded}");
+
+        let mut folder = Self {
+            encoded,
+            // with CONDSTORE, only envelopes changed since the last
+            // modification sequence are fetched after each change
+            condstore: client.ext_condstore_supported(),
+            highest_mod_seq: 0,
+            envelopes: HashMap::new(),
+        };
+
+        folder.envelopes = folder.examine_and_fetch(client).await?;
+
+        Ok(folder)
+    }
+
+    /// Return `true` if the given mailbox name sent by the server
+    /// matches the folder.
+    fn is(&self, mbox: &str) -> bool {
+        let inbox = mbox.eq_ignore_ascii_case("INBOX");
+        self.encoded == mbox || inbox && self.encoded.eq_ignore_ascii_case("INBOX")
+    }
+
+    /// Examine the folder using the given client, then fetch its
+    /// current envelopes.
+    async fn examine_and_fetch(
+        &mut self,
+        client: &mut ImapClient,
+    ) -> AnyResult<HashMap<String, Envelope>> {
         let envelopes_count = client
-            .examine_mailbox(folder_encoded)
+            .examine_mailbox(&self.encoded)
             .await?
             .exists
-            .unwrap() as usize;
-
-        let envelopes = if envelopes_count == 0 {
-            Default::default()
-        } else {
-            client.fetch_all_envelopes().await?
-        };
+            .unwrap_or_default() as usize;
 
-        let mut envelopes: HashMap<String, Envelope> =
-            HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        if envelopes_count == 0 {
+            return Ok(HashMap::new());
+        }
 
-        loop {
-            client.idle(wait_for_shutdown_request).await?;
+        if !self.condstore {
+            let envelopes = client.fetch_all_envelopes().await?;
+            return Ok(HashMap::from_iter(
+                envelopes.into_iter().map(|e| (e.id.clone(), e)),
+            ));
+        }
 
-            let next_envelopes = client.fetch_all_envelopes().await?;
-            let next_envelopes: HashMap<String, Envelope> =
-                HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        let changes = fetch_envelopes_changes(client, self.highest_mod_seq).await?;
+        self.highest_mod_seq = changes.highest_mod_seq;
 
-            self.exec_hooks(config, &envelopes, &next_envelopes).await;
+        let mut envelopes = self.envelopes.clone();
+        envelopes.extend(changes.envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
-            envelopes = next_envelopes;
+        // messages of the folder are either known or changed, so
+        // extra envelopes belong to expunged messages
+        if envelopes.len() > envelopes_count {
+            let uids = client.search_uids([SearchKey::All]).await?;
+            let ids: HashSet<_> = uids.iter().map(ToString::to_string).collect();
+            envelopes.retain(|id, _| ids.contains(id));
         }
+
+        Ok(envelopes)
     }
 }
 
@@ -80,8 +436,22 @@ async fn watch_envelopes(
             .watch_envelopes_loop(folder, &mut wait_for_shutdown_request)
             .await;
 
-        shutdown.send(()).unwrap();
+        // the shutdown receiver may have been dropped already
+        let _ = shutdown.send(());
 
         res
     }
+
+    async fn watch_envelope_events(
+        &self,
+        folder: &str,
+        events: UnboundedSender<WatchEvent>,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        self.clone()
+            .with_events(events)
+            .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
+            .await
+    }
 }
@@ -7,9 +7,62 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
-use tokio::sync::oneshot::{Receiver, Sender};
+use tokio::sync::{
+    mpsc::UnboundedSender,
+    oneshot::{Receiver, Sender},
+};
 
-use crate::{account::config::AccountConfig, debug, envelope::Envelope, AnyResult};
+use crate::{
+    account::config::AccountConfig, backend::Error, debug, envelope::Envelope, flag::Flags,
+    AnyResult,
+};
+
+/// An envelope change, as detected while watching a folder.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WatchEvent {
+    /// A new envelope has been received.
+    Received(Envelope),
+
+    /// The flags of an existing envelope changed. The previous flags
+    /// are kept alongside the updated envelope.
+    FlagsChanged {
+        prev_flags: Flags,
+        envelope: Envelope,
+    },
+
+    /// An envelope has been expunged from the folder.
+    Expunged(Envelope),
+}
+
+impl WatchEvent {
+    /// Compute the events leading from the given previous envelopes
+    /// to the given next ones, both indexed by envelope identifier.
+    pub fn diff(
+        prev_envelopes: &HashMap<String, Envelope>,
+        next_envelopes: &HashMap<String, Envelope>,
+    ) -> Vec<Self> {
+        let mut events = Vec::new();
+
+        for (id, envelope) in next_envelopes {
+            match prev_envelopes.get(id) {
+                None => events.push(Self::Received(envelope.clone())),
+                Some(prev) if prev.flags != envelope.flags => events.push(Self::FlagsChanged {
+                    prev_flags: prev.flags.clone(),
+                    envelope: envelope.clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+
+        for (id, envelope) in prev_envelopes {
+            if !next_envelopes.contains_key(id) {
+                events.push(Self::Expunged(envelope.clone()));
+            }
+        }
+
+        events
+    }
+}
 
 #[async_trait]
 pub trait WatchEnvelopes: Send + Sync {
@@ -21,6 +74,21 @@ async fn watch_envelopes(
         shutdown: Sender<()>,
     ) -> AnyResult<()>;
 
+    /// Watch the given folder for envelopes changes, and send them
+    /// as typed [`WatchEvent`]s to the given channel.
+    ///
+    /// Configured hooks are still executed. Backends unable to
+    /// detect flag changes and expunges do not implement it.
+    async fn watch_envelope_events(
+        &self,
+        _folder: &str,
+        _events: UnboundedSender<WatchEvent>,
+        _wait_for_shutdown_request: Receiver<()>,
+        _shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        Err(Error::WatchEnvelopeEventsNotAvailableError.into())
+    }
+
     async fn exec_hooks(
         &self,
         config: &AccountConfig,
@@ -31,8 +99,15 @@ async fn exec_hooks(
         for (id, envelope) in next_envelopes {
             // a new envelope has been added
             if !prev_envelopes.contains_key(id) {
-                debug!("processing received envelope event…");
-                config.exec_received_envelope_hook(envelope).await;
+                if config.is_vip_sender(&envelope.from.addr) {
+                    debug!("processing received VIP envelope event…");
+                    let mut envelope = envelope.clone();
+                    envelope.is_vip = true;
+                    config.exec_received_vip_envelope_hook(&envelope).await;
+                } else {
+                    debug!("processing received envelope event…");
+                    config.exec_received_envelope_hook(envelope).await;
+                }
             } else {
                 // TODO
                 // debug!("processing any envelope event…");
@@ -41,3 +116,60 @@ async fn exec_hooks(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::WatchEvent;
+    use crate::{
+        envelope::Envelope,
+        flag::{Flag, Flags},
+    };
+
+    fn envelopes(
+        envelopes: impl IntoIterator<Item = (&'static str, Flags)>,
+    ) -> HashMap<String, Envelope> {
+        HashMap::from_iter(envelopes.into_iter().map(|(id, flags)| {
+            let envelope = Envelope {
+                id: id.into(),
+                message_id: format!("<{id}@localhost>"),
+                flags,
+                ..Default::default()
+            };
+            (id.into(), envelope)
+        }))
+    }
+
+    #[test]
+    fn diff_envelopes() {
+        let prev = envelopes([
+            ("1", Flags::default()),
+            ("2", Flags::default()),
+            ("3", Flags::default()),
+        ]);
+        let next = envelopes([
+            ("1", Flags::default()),
+            ("2", Flags::from_iter([Flag::Seen])),
+            ("4", Flags::default()),
+        ]);
+
+        let mut events = WatchEvent::diff(&prev, &next);
+        events.sort_by_key(|event| match event {
+            WatchEvent::Received(envelope) => envelope.id.clone(),
+            WatchEvent::FlagsChanged { envelope, .. } => envelope.id.clone(),
+            WatchEvent::Expunged(envelope) => envelope.id.clone(),
+        });
+
+        let expected = vec![
+            WatchEvent::FlagsChanged {
+                prev_flags: Flags::default(),
+                envelope: next["2"].clone(),
+            },
+            WatchEvent::Expunged(prev["3"].clone()),
+            WatchEvent::Received(next["4"].clone()),
+        ];
+
+        assert_eq!(events, expected);
+    }
+}
@@ -4,12 +4,12 @@
 #[cfg(feature = "imap")]
 use imap_next::imap_types::error::ValidationError;
 use thiserror::Error;
-use tokio::task::JoinError;
 
 #[cfg(feature = "maildir")]
 use crate::flag::Flags;
 use crate::{
     envelope::{Id, SingleId},
+    runtime::JoinError,
     AnyBoxedError, AnyError,
 };
 
@@ -44,12 +44,48 @@ pub enum Error {
     GetMaildirFlagsError(#[source] maildirs::Error, PathBuf),
     #[error("cannot find message associated to envelope {0}")]
     FindMessageError(String),
+    #[error("cannot watch outbox: outbox folder not configured")]
+    OutboxNotConfiguredError,
+    #[cfg(feature = "dkim")]
+    #[error("cannot get DKIM private key")]
+    GetDkimPrivateKeyError(#[source] secret::Error),
+    #[cfg(feature = "dkim")]
+    #[error("cannot read DKIM private key")]
+    ReadDkimPrivateKeyError(#[source] io::Error),
+    #[cfg(feature = "dkim")]
+    #[error("cannot find DKIM private key: no PEM-encoded key found")]
+    DkimPrivateKeyNotFoundError,
+    #[cfg(feature = "dkim")]
+    #[error("cannot parse DKIM private key")]
+    ParseDkimPrivateKeyError(#[source] mail_auth::Error),
+    #[cfg(feature = "dkim")]
+    #[error("cannot use DKIM private key: key format does not match the signing algorithm")]
+    UnsupportedDkimPrivateKeyError,
+    #[cfg(feature = "dkim")]
+    #[error("cannot sign message using DKIM")]
+    SignDkimError(#[source] mail_auth::Error),
     #[error("cannot parse search emails query `{1}`")]
     ParseError(Vec<Rich<'static, char>>, String),
     #[error("cannot interpret message as template")]
     InterpretMessageAsTemplateError(#[source] mml::Error),
     #[error("cannot interpret message as thread template")]
     InterpretMessageAsThreadTemplateError(#[source] mml::Error),
+    #[error("cannot read attachment")]
+    ReadAttachmentError(#[source] io::Error),
+    #[error("cannot attach content: attachment exceeds {0} bytes")]
+    AttachmentTooLargeError(usize),
+    #[cfg(feature = "attachment-url")]
+    #[error("cannot create HTTP connector for attachment download")]
+    CreateAttachmentHttpConnectorError(#[source] io::Error),
+    #[cfg(feature = "attachment-url")]
+    #[error("cannot download attachment from {1}")]
+    DownloadAttachmentError(#[source] hyper_util::client::legacy::Error, hyper::Uri),
+    #[cfg(feature = "attachment-url")]
+    #[error("cannot download attachment from {1}: server responded with {0}")]
+    DownloadAttachmentStatusError(hyper::StatusCode, hyper::Uri),
+    #[cfg(feature = "attachment-url")]
+    #[error("cannot read attachment downloaded from {1}")]
+    ReadDownloadedAttachmentError(#[source] hyper::Error, hyper::Uri),
     #[error("cannot run sendmail command")]
     RunSendmailCommandError(#[source] process::Error),
     #[cfg(feature = "notmuch")]
@@ -70,6 +106,20 @@ pub enum Error {
     ParseEmailEmptyRawError,
     #[error("cannot delete local draft at {1}")]
     DeleteLocalDraftError(#[source] io::Error, PathBuf),
+    #[error("cannot read flag store at {1}")]
+    ReadFlagStoreError(#[source] io::Error, PathBuf),
+    #[error("cannot write flag store at {1}")]
+    WriteFlagStoreError(#[source] io::Error, PathBuf),
+    #[error("cannot read outbox queue at {1}")]
+    ReadOutboxQueueError(#[source] io::Error, PathBuf),
+    #[error("cannot write outbox queue at {1}")]
+    WriteOutboxQueueError(#[source] io::Error, PathBuf),
+    #[error("cannot send message: unknown transport {0}")]
+    SendMessageUnknownTransportError(String),
+    #[error("cannot send message: pre-send hook {0} failed")]
+    RunPreSendHookError(String, #[source] AnyBoxedError),
+    #[error("cannot execute pre-send hook command")]
+    ExecutePreSendHookCommandError(#[source] process::Error),
     #[error("cannot parse email: empty entries")]
     ParseEmailFromEmptyEntriesError,
     #[error("could not parse: {0}")]
@@ -124,6 +174,8 @@ pub enum Error {
     GetEnvelopesOutOfBoundsMaildirError(String, usize),
     #[error("cannot list imap envelopes: page {0} out of bounds")]
     BuildPageRangeOutOfBoundsImapError(usize),
+    #[error("cannot parse envelopes cursor {0}")]
+    ParseEnvelopesCursorError(String),
     #[error("cannot get uid of imap envelope {0}: uid is missing")]
     GetUidMissingImapError(u32),
     #[error("cannot get missing envelope {0}")]
@@ -166,6 +218,28 @@ pub enum Error {
     NotifyFailure(notify::Error),
     #[error("could not watch: {0}")]
     FileReadFailure(io::Error),
+    #[error(
+        "cannot get message {id}: size of {size} bytes exceeds the maximum of {max_size} bytes"
+    )]
+    MessageTooLarge {
+        id: String,
+        size: u64,
+        max_size: u64,
+    },
+    #[error(
+        "cannot add message to {folder}: size of {size} bytes exceeds the limit of {limit} bytes"
+    )]
+    AddMessageTooLarge {
+        folder: String,
+        size: u64,
+        limit: u64,
+    },
+    #[error("cannot build calendar invitation")]
+    BuildCalendarInvitationError(#[source] io::Error),
+    #[error("cannot parse calendar reply: {0}")]
+    ParseCalendarReplyError(&'static str),
+    #[error("cannot decode TNEF attachment: {0}")]
+    ParseTnefError(&'static str),
 
     #[error("cannot list envelopes from left sync cache")]
     ListLeftEnvelopesCachedError(#[source] AnyBoxedError),
@@ -8,4 +8,11 @@ pub struct MessageWriteConfig {
     /// Define visible headers at the top of messages when writing
     /// them (new/reply/forward).
     pub headers: Option<Vec<String>>,
+
+    /// Should normalize line endings of messages to CRLF when adding
+    /// them to a remote folder.
+    ///
+    /// IMAP requires CRLF line endings, some servers reject or mangle
+    /// messages using bare LF. Defaults to `true`.
+    pub normalize_line_endings: Option<bool>,
 }
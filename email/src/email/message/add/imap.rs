@@ -1,10 +1,17 @@
 use std::borrow::Cow;
 
 use async_trait::async_trait;
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{AddMessage, Flags};
-use crate::{debug, envelope::SingleId, imap::ImapContext, info, AnyResult};
+use crate::{
+    debug,
+    email::error::Error,
+    envelope::SingleId,
+    imap::{ImapClient, ImapContext},
+    info,
+    message::line_ending::normalize_to_crlf,
+    AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct AddImapMessage {
@@ -35,21 +42,82 @@ async fn add_message_with_flags(
     ) -> AnyResult<SingleId> {
         info!("adding imap message to folder {folder} with flags {flags}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("add_message_with_flags").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
-        let uid = client
-            .add_message(
-                &folder_encoded,
-                flags.to_imap_flags_iter(),
-                Cow::Owned(msg.to_vec()),
-            )
-            .await?;
+        append(&mut client, &folder, &folder_encoded, msg, flags).await
+    }
+
+    /// Add the given messages using the same client of the pool, so
+    /// that APPEND commands are sent one after the other on the same
+    /// connection.
+    ///
+    /// The MULTIAPPEND extension cannot be expressed by the
+    /// underlying IMAP client yet.
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<SingleId>> {
+        info!("adding {} imap messages to folder {folder}", msgs.len());
+
+        let mut client = self.ctx.client_for("add_messages_with_flags").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let mut ids = Vec::with_capacity(msgs.len());
+
+        for (msg, flags) in msgs {
+            ids.push(append(&mut client, &folder, &folder_encoded, msg, flags).await?);
+        }
 
-        Ok(SingleId::from(uid.to_string()))
+        Ok(ids)
     }
 }
+
+/// Append the given message to the given folder, using the given
+/// client.
+async fn append(
+    client: &mut ImapClient,
+    folder: &str,
+    folder_encoded: &str,
+    msg: &[u8],
+    flags: &Flags,
+) -> AnyResult<SingleId> {
+    let config = &client.account_config;
+
+    let msg = if config.should_normalize_added_message_line_endings() {
+        normalize_to_crlf(msg)
+    } else {
+        Cow::Borrowed(msg)
+    };
+
+    if let Some(limit) = client.append_limit() {
+        let size = msg.len() as u64;
+
+        if size > limit {
+            let folder = folder.to_owned();
+            return Err(Error::AddMessageTooLarge {
+                folder,
+                size,
+                limit,
+            }
+            .into());
+        }
+    }
+
+    let uid = client
+        .add_message(
+            folder_encoded,
+            flags.to_imap_flags_iter(),
+            Cow::Owned(msg.into_owned()),
+        )
+        .await?;
+
+    Ok(SingleId::from(uid.to_string()))
+}
@@ -48,6 +48,16 @@ async fn add_message_with_flags(
                 Error::StoreWithFlagsMaildirError(err, folder.to_owned(), flags.clone())
             })?;
 
-        Ok(SingleId::from(entry.id().unwrap()))
+        let id = entry.id().unwrap().to_owned();
+
+        let unsupported = flags.unsupported_by_maildir();
+        if !unsupported.is_empty() {
+            ctx.update_stored_flags(folder, [id.as_str()], |stored| {
+                *stored = unsupported.clone()
+            })
+            .await?;
+        }
+
+        Ok(SingleId::from(id))
     }
 }
@@ -42,4 +42,32 @@ async fn add_message(&self, folder: &str, msg: &[u8]) -> AnyResult<SingleId> {
         self.add_message_with_flags(folder, msg, &Default::default())
             .await
     }
+
+    /// Add the given raw email messages with their flags to the given
+    /// folder, in order.
+    ///
+    /// Backends can override this function in order to upload all
+    /// messages at once. The default implementation adds messages
+    /// one by one, and stops at the first error.
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<SingleId>> {
+        let mut ids = Vec::with_capacity(msgs.len());
+
+        for (msg, flags) in msgs {
+            ids.push(self.add_message_with_flags(folder, msg, flags).await?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Add the given raw email messages to the given folder, in
+    /// order.
+    async fn add_messages(&self, folder: &str, msgs: &[&[u8]]) -> AnyResult<Vec<SingleId>> {
+        let flags = Flags::default();
+        let msgs: Vec<_> = msgs.iter().map(|msg| (*msg, &flags)).collect();
+        self.add_messages_with_flags(folder, &msgs).await
+    }
 }
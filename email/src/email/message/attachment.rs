@@ -3,6 +3,14 @@
 //! This module contains everything related to email message
 //! attachments.
 
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::email::error::Error;
+
+/// The default maximum size of attachments read from sources, in
+/// bytes (25 MiB).
+pub const DEFAULT_MAX_SIZE: usize = 25 * 1024 * 1024;
+
 /// The email message attachment.
 ///
 /// Represents a simplified version of an email message attachment.
@@ -17,3 +25,223 @@ pub struct Attachment {
     /// The raw content of the attachment.
     pub body: Vec<u8>,
 }
+
+/// The email message attachment source.
+///
+/// Sources are read lazily, when the message template is built, and
+/// their content is capped to a maximum size. This allows
+/// applications to attach generated content or remote files without
+/// staging them in temporary files.
+pub enum AttachmentSource {
+    /// The attachment is already in memory.
+    Attachment(Attachment),
+
+    /// The attachment is read from an async stream.
+    Reader {
+        /// The optional attachment filename.
+        filename: Option<String>,
+
+        /// The attachment MIME type.
+        ///
+        /// The MIME type is guessed from the content if `None`.
+        mime: Option<String>,
+
+        /// The stream to read the content of the attachment from.
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+    },
+
+    /// The attachment is downloaded from an HTTP(S) URL.
+    ///
+    /// The filename is taken from the last segment of the URL path,
+    /// and the MIME type from the `Content-Type` header of the
+    /// response. Redirections are not followed.
+    #[cfg(feature = "attachment-url")]
+    Url(hyper::Uri),
+}
+
+impl AttachmentSource {
+    /// Read the attachment from the source.
+    ///
+    /// Fails if the content of the attachment exceeds the given
+    /// maximum size, in bytes.
+    pub async fn read(self, max_size: usize) -> Result<Attachment, Error> {
+        match self {
+            Self::Attachment(attachment) => {
+                if attachment.body.len() > max_size {
+                    return Err(Error::AttachmentTooLargeError(max_size));
+                }
+
+                Ok(attachment)
+            }
+            Self::Reader {
+                filename,
+                mime,
+                reader,
+            } => {
+                let body = read_capped(reader, max_size).await?;
+                let mime = mime.unwrap_or_else(|| tree_magic_mini::from_u8(&body).to_owned());
+
+                Ok(Attachment {
+                    filename,
+                    mime,
+                    body,
+                })
+            }
+            #[cfg(feature = "attachment-url")]
+            Self::Url(uri) => download(uri, max_size).await,
+        }
+    }
+}
+
+impl From<Attachment> for AttachmentSource {
+    fn from(attachment: Attachment) -> Self {
+        Self::Attachment(attachment)
+    }
+}
+
+/// Read the given stream until its end, failing if its content
+/// exceeds the given maximum size.
+async fn read_capped(reader: impl AsyncRead + Unpin, max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut body = Vec::new();
+
+    // reading one more byte than allowed is enough to detect
+    // oversized content without reading it entirely
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut body)
+        .await
+        .map_err(Error::ReadAttachmentError)?;
+
+    if body.len() > max_size {
+        return Err(Error::AttachmentTooLargeError(max_size));
+    }
+
+    Ok(body)
+}
+
+/// Download the attachment located at the given URL, failing if its
+/// content exceeds the given maximum size.
+#[cfg(feature = "attachment-url")]
+async fn download(uri: hyper::Uri, max_size: usize) -> Result<Attachment, Error> {
+    use http_body_util::{BodyExt, Full};
+    use hyper::{
+        body::Bytes,
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+    };
+    use hyper_rustls::HttpsConnectorBuilder;
+    use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+
+    let conn = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .map_err(Error::CreateAttachmentHttpConnectorError)?
+        .https_or_http()
+        .enable_http1()
+        .build();
+
+    let client: Client<_, Full<Bytes>> = Client::builder(TokioExecutor::new()).build(conn);
+
+    let res = client
+        .get(uri.clone())
+        .await
+        .map_err(|err| Error::DownloadAttachmentError(err, uri.clone()))?;
+
+    let status = res.status();
+    if !status.is_success() {
+        return Err(Error::DownloadAttachmentStatusError(status, uri));
+    }
+
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|val| val.to_str().ok())
+            .map(ToOwned::to_owned)
+    };
+
+    // reject oversized attachments early, when the server announces
+    // their size
+    if let Some(len) = header(CONTENT_LENGTH).and_then(|len| len.parse::<usize>().ok()) {
+        if len > max_size {
+            return Err(Error::AttachmentTooLargeError(max_size));
+        }
+    }
+
+    let mime = header(CONTENT_TYPE).map(|mime| match mime.split_once(';') {
+        Some((mime, _)) => mime.trim().to_owned(),
+        None => mime.trim().to_owned(),
+    });
+
+    let mut res_body = res.into_body();
+    let mut body = Vec::new();
+
+    while let Some(frame) = res_body.frame().await {
+        let frame = frame.map_err(|err| Error::ReadDownloadedAttachmentError(err, uri.clone()))?;
+
+        if let Ok(data) = frame.into_data() {
+            if body.len() + data.len() > max_size {
+                return Err(Error::AttachmentTooLargeError(max_size));
+            }
+
+            body.extend_from_slice(&data);
+        }
+    }
+
+    let filename = uri
+        .path()
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .map(ToOwned::to_owned);
+
+    let mime = mime.unwrap_or_else(|| tree_magic_mini::from_u8(&body).to_owned());
+
+    Ok(Attachment {
+        filename,
+        mime,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attachment, AttachmentSource};
+    use crate::email::error::Error;
+
+    #[tokio::test]
+    async fn read_from_reader() {
+        let source = AttachmentSource::Reader {
+            filename: Some("hello.txt".into()),
+            mime: Some("text/plain".into()),
+            reader: Box::new(&b"Hello, world!"[..]),
+        };
+
+        let attachment = source.read(13).await.unwrap();
+        assert_eq!(attachment.filename.as_deref(), Some("hello.txt"));
+        assert_eq!(attachment.mime, "text/plain");
+        assert_eq!(attachment.body, b"Hello, world!");
+
+        let source = AttachmentSource::Reader {
+            filename: None,
+            mime: None,
+            reader: Box::new(&b"Hello, world!"[..]),
+        };
+
+        let err = source.read(12).await.unwrap_err();
+        assert!(matches!(err, Error::AttachmentTooLargeError(12)));
+    }
+
+    #[tokio::test]
+    async fn read_from_attachment() {
+        let attachment = Attachment {
+            filename: None,
+            mime: "text/plain".into(),
+            body: b"Hello".to_vec(),
+        };
+
+        let source = AttachmentSource::from(attachment.clone());
+        assert_eq!(source.read(5).await.unwrap(), attachment);
+
+        let source = AttachmentSource::from(attachment);
+        let err = source.read(4).await.unwrap_err();
+        assert!(matches!(err, Error::AttachmentTooLargeError(4)));
+    }
+}
@@ -0,0 +1,592 @@
+//! # Calendar invitation
+//!
+//! Module dedicated to basic scheduling over email, as described by
+//! iTIP and iMIP: invitations are sent as `text/calendar` parts with
+//! the `REQUEST` method, attendees answer with the `REPLY` method.
+//!
+//! https://www.rfc-editor.org/rfc/rfc5546.html
+//! https://www.rfc-editor.org/rfc/rfc6047.html
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use mail_builder::{
+    headers::{address::Address, content_type::ContentType, raw::Raw},
+    mime::MimePart,
+    MessageBuilder,
+};
+use mail_parser::MimeHeaders;
+
+use super::Message;
+use crate::email::error::{Error, Result};
+
+/// The product identifier of generated calendar objects.
+const PRODID: &str = "-//pimalaya//email-lib//EN";
+
+/// The calendar method, as defined by iTIP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CalendarMethod {
+    /// The organizer invites attendees to an event.
+    Request,
+
+    /// An attendee answers to an invitation.
+    Reply,
+
+    /// The organizer cancels an event.
+    Cancel,
+}
+
+impl CalendarMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Request => "REQUEST",
+            Self::Reply => "REPLY",
+            Self::Cancel => "CANCEL",
+        }
+    }
+}
+
+impl fmt::Display for CalendarMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The participation status of an attendee.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum CalendarPartStat {
+    /// The attendee did not answer yet.
+    #[default]
+    NeedsAction,
+
+    /// The attendee accepted the invitation.
+    Accepted,
+
+    /// The attendee declined the invitation.
+    Declined,
+
+    /// The attendee tentatively accepted the invitation.
+    Tentative,
+
+    /// The attendee delegated the invitation.
+    Delegated,
+}
+
+impl CalendarPartStat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NeedsAction => "NEEDS-ACTION",
+            Self::Accepted => "ACCEPTED",
+            Self::Declined => "DECLINED",
+            Self::Tentative => "TENTATIVE",
+            Self::Delegated => "DELEGATED",
+        }
+    }
+}
+
+impl FromStr for CalendarPartStat {
+    type Err = Error;
+
+    fn from_str(partstat: &str) -> Result<Self> {
+        match partstat.trim().to_ascii_uppercase().as_str() {
+            "NEEDS-ACTION" => Ok(Self::NeedsAction),
+            "ACCEPTED" => Ok(Self::Accepted),
+            "DECLINED" => Ok(Self::Declined),
+            "TENTATIVE" => Ok(Self::Tentative),
+            "DELEGATED" => Ok(Self::Delegated),
+            _ => Err(Error::ParseCalendarReplyError(
+                "unknown participation status",
+            )),
+        }
+    }
+}
+
+/// The calendar address of an organizer or an attendee.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CalendarAddress {
+    /// The optional display name.
+    pub name: Option<String>,
+
+    /// The email address.
+    pub email: String,
+}
+
+impl CalendarAddress {
+    pub fn new(name: Option<impl ToString>, email: impl ToString) -> Self {
+        Self {
+            name: name.map(|name| name.to_string()),
+            email: email.to_string(),
+        }
+    }
+
+    /// Write the address as an iCalendar property.
+    fn to_property(&self, name: &str, params: &str) -> String {
+        let mut property = String::from(name);
+
+        if let Some(cn) = &self.name {
+            property.push_str(&format!(";CN=\"{}\"", cn.replace('"', "")));
+        }
+
+        property.push_str(params);
+        property.push_str(&format!(":mailto:{}", self.email));
+        property
+    }
+}
+
+/// The calendar event, sent as an invitation to attendees.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CalendarEvent {
+    /// The globally unique identifier of the event.
+    pub uid: String,
+
+    /// The revision of the event, to be incremented every time the
+    /// organizer updates it.
+    pub sequence: u32,
+
+    /// The title of the event.
+    pub summary: String,
+
+    /// The optional description of the event.
+    pub description: Option<String>,
+
+    /// The optional location of the event.
+    pub location: Option<String>,
+
+    /// The start of the event.
+    pub start: DateTime<Utc>,
+
+    /// The end of the event.
+    pub end: DateTime<Utc>,
+
+    /// The organizer of the event, who receives replies.
+    pub organizer: CalendarAddress,
+
+    /// The attendees of the event.
+    pub attendees: Vec<CalendarAddress>,
+}
+
+impl CalendarEvent {
+    /// Write the event as an iCalendar object using the given
+    /// method, stamped now.
+    pub fn to_ics(&self, method: CalendarMethod) -> String {
+        self.to_ics_at(method, Utc::now())
+    }
+
+    /// Write the event as an iCalendar object using the given
+    /// method, stamped at the given date.
+    pub fn to_ics_at(&self, method: CalendarMethod, stamp: DateTime<Utc>) -> String {
+        let mut lines = vec![
+            String::from("BEGIN:VCALENDAR"),
+            format!("PRODID:{PRODID}"),
+            String::from("VERSION:2.0"),
+            format!("METHOD:{method}"),
+            String::from("BEGIN:VEVENT"),
+            format!("UID:{}", escape_text(&self.uid)),
+            format!("SEQUENCE:{}", self.sequence),
+            format!("DTSTAMP:{}", format_date(&stamp)),
+            format!("DTSTART:{}", format_date(&self.start)),
+            format!("DTEND:{}", format_date(&self.end)),
+            format!("SUMMARY:{}", escape_text(&self.summary)),
+        ];
+
+        if let Some(description) = &self.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+
+        lines.push(self.organizer.to_property("ORGANIZER", ""));
+
+        for attendee in &self.attendees {
+            let params = ";ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE";
+            lines.push(attendee.to_property("ATTENDEE", params));
+        }
+
+        if method == CalendarMethod::Cancel {
+            lines.push(String::from("STATUS:CANCELLED"));
+        }
+
+        lines.push(String::from("END:VEVENT"));
+        lines.push(String::from("END:VCALENDAR"));
+
+        lines.iter().map(|line| fold_line(line)).collect()
+    }
+
+    /// Build the invitation message of the event.
+    ///
+    /// The message is sent from the organizer to all attendees, with
+    /// the given plain text body as an alternative to the
+    /// `text/calendar` part for clients that do not support
+    /// scheduling.
+    pub fn to_invitation_builder(&self, text: impl ToString) -> MessageBuilder<'static> {
+        let organizer = address(&self.organizer);
+        let attendees = Address::new_list(self.attendees.iter().map(address).collect());
+
+        let calendar = ContentType::new("text/calendar")
+            .attribute("method", CalendarMethod::Request.as_str())
+            .attribute("charset", "utf-8");
+
+        MessageBuilder::new()
+            .from(organizer)
+            .to(attendees)
+            .subject(self.summary.clone())
+            .header(
+                "Content-Class",
+                Raw::new("urn:content-classes:calendarmessage"),
+            )
+            .body(MimePart::new(
+                "multipart/alternative",
+                vec![
+                    MimePart::new("text/plain", text.to_string()),
+                    MimePart::new(calendar, self.to_ics(CalendarMethod::Request)),
+                ],
+            ))
+    }
+
+    /// Build the raw invitation message of the event.
+    ///
+    /// See [`CalendarEvent::to_invitation_builder`].
+    pub fn to_invitation(&self, text: impl ToString) -> Result<Vec<u8>> {
+        self.to_invitation_builder(text)
+            .write_to_vec()
+            .map_err(Error::BuildCalendarInvitationError)
+    }
+}
+
+/// The reply of an attendee to an invitation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CalendarReply {
+    /// The identifier of the event the reply belongs to.
+    pub uid: String,
+
+    /// The revision of the event the attendee answered to.
+    pub sequence: u32,
+
+    /// The attendee who answered.
+    pub attendee: CalendarAddress,
+
+    /// The participation status of the attendee.
+    pub status: CalendarPartStat,
+}
+
+impl FromStr for CalendarReply {
+    type Err = Error;
+
+    /// Parse the reply from an iCalendar object using the `REPLY`
+    /// method.
+    fn from_str(ics: &str) -> Result<Self> {
+        let mut method = None;
+        let mut in_event = false;
+        let mut reply = CalendarReply::default();
+        let mut has_attendee = false;
+
+        for line in unfold_lines(ics) {
+            let (name, params, value) = split_property(&line)
+                .ok_or(Error::ParseCalendarReplyError("invalid content line"))?;
+
+            match name.to_ascii_uppercase().as_str() {
+                "METHOD" if !in_event => {
+                    method = Some(value.trim().to_ascii_uppercase());
+                }
+                "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => {
+                    in_event = true;
+                }
+                "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                    in_event = false;
+                }
+                "UID" if in_event => {
+                    reply.uid = unescape_text(value);
+                }
+                "SEQUENCE" if in_event => {
+                    reply.sequence = value.trim().parse().unwrap_or_default();
+                }
+                // a reply contains only the attendee who answered
+                "ATTENDEE" if in_event && !has_attendee => {
+                    has_attendee = true;
+
+                    let email = value.trim();
+                    reply.attendee.email = match email.get(..7) {
+                        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &email[7..],
+                        _ => email,
+                    }
+                    .to_owned();
+
+                    for (key, value) in params {
+                        if key.eq_ignore_ascii_case("CN") {
+                            reply.attendee.name = Some(value.trim_matches('"').to_owned());
+                        } else if key.eq_ignore_ascii_case("PARTSTAT") {
+                            reply.status = value.parse()?;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if method.as_deref() != Some(CalendarMethod::Reply.as_str()) {
+            return Err(Error::ParseCalendarReplyError("method is not REPLY"));
+        }
+
+        if reply.uid.is_empty() {
+            return Err(Error::ParseCalendarReplyError("missing event UID"));
+        }
+
+        if !has_attendee {
+            return Err(Error::ParseCalendarReplyError("missing attendee"));
+        }
+
+        Ok(reply)
+    }
+}
+
+impl Message<'_> {
+    /// Find and parse the first calendar reply of the message.
+    ///
+    /// Returns `None` if the message does not contain any
+    /// `text/calendar` part using the `REPLY` method.
+    pub fn calendar_reply(&self) -> Result<Option<CalendarReply>> {
+        let parsed = self.parsed()?;
+
+        for part in &parsed.parts {
+            let Some(ctype) = part.content_type() else {
+                continue;
+            };
+
+            let is_calendar = ctype.ctype().eq_ignore_ascii_case("text")
+                && ctype
+                    .subtype()
+                    .is_some_and(|subtype| subtype.eq_ignore_ascii_case("calendar"));
+
+            if !is_calendar {
+                continue;
+            }
+
+            let is_reply = ctype
+                .attribute("method")
+                .map_or(true, |method| method.eq_ignore_ascii_case("REPLY"));
+
+            if let (true, Some(ics)) = (is_reply, part.text_contents()) {
+                return Ok(Some(ics.parse()?));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn address(addr: &CalendarAddress) -> Address<'static> {
+    Address::new_address(addr.name.clone(), addr.email.clone())
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Fold the given content line at 75 octets, then terminate it with
+/// CRLF.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+
+        folded.push(c);
+        len += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Unfold the content lines of the given iCalendar object.
+fn unfold_lines(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(line), Some(last)) => last.push_str(line),
+            _ if line.trim().is_empty() => (),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    lines
+}
+
+/// Split the given content line into its name, its parameters and
+/// its value.
+fn split_property(line: &str) -> Option<(&str, Vec<(&str, &str)>, &str)> {
+    let mut quoted = false;
+    let mut separators = Vec::new();
+    let mut colon = None;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => separators.push(i),
+            ':' if !quoted => {
+                colon = Some(i);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let colon = colon?;
+    separators.push(colon);
+
+    let name = &line[..separators[0]];
+    let params = separators
+        .windows(2)
+        .filter_map(|w| line[w[0] + 1..w[1]].split_once('='))
+        .collect();
+
+    Some((name, params, &line[colon + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{CalendarAddress, CalendarEvent, CalendarMethod, CalendarPartStat, CalendarReply};
+    use crate::message::Message;
+
+    fn event() -> CalendarEvent {
+        CalendarEvent {
+            uid: "event@localhost".into(),
+            sequence: 0,
+            summary: "Lunch, then coffee".into(),
+            description: None,
+            location: Some("Room 1".into()),
+            start: Utc.with_ymd_and_hms(2024, 5, 16, 12, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 5, 16, 13, 0, 0).unwrap(),
+            organizer: CalendarAddress::new(Some("Alice"), "alice@localhost"),
+            attendees: vec![CalendarAddress::new(None::<String>, "bob@localhost")],
+        }
+    }
+
+    #[test]
+    fn event_to_ics() {
+        let stamp = Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap();
+        let ics = event().to_ics_at(CalendarMethod::Request, stamp);
+
+        let expected = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "PRODID:-//pimalaya//email-lib//EN\r\n",
+            "VERSION:2.0\r\n",
+            "METHOD:REQUEST\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:event@localhost\r\n",
+            "SEQUENCE:0\r\n",
+            "DTSTAMP:20240501T080000Z\r\n",
+            "DTSTART:20240516T120000Z\r\n",
+            "DTEND:20240516T130000Z\r\n",
+            "SUMMARY:Lunch\\, then coffee\r\n",
+            "LOCATION:Room 1\r\n",
+            "ORGANIZER;CN=\"Alice\":mailto:alice@localhost\r\n",
+            "ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:bob@lo\r\n",
+            " calhost\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+
+        assert_eq!(ics, expected);
+    }
+
+    #[test]
+    fn event_to_invitation() {
+        let msg = event().to_invitation("You are invited.").unwrap();
+        let msg = String::from_utf8(msg).unwrap();
+
+        assert!(msg.contains("Content-Class: urn:content-classes:calendarmessage"));
+        assert!(msg.contains("text/calendar"));
+        assert!(msg.contains("METHOD:REQUEST"));
+    }
+
+    #[test]
+    fn parse_reply() {
+        let ics = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "METHOD:REPLY\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:event@localhost\r\n",
+            "SEQUENCE:1\r\n",
+            "ATTENDEE;CN=\"Bob; the builder\";PARTSTAT=DECLINED:mailto:bob@lo\r\n",
+            " calhost\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+
+        let reply: CalendarReply = ics.parse().unwrap();
+
+        let expected = CalendarReply {
+            uid: "event@localhost".into(),
+            sequence: 1,
+            attendee: CalendarAddress::new(Some("Bob; the builder"), "bob@localhost"),
+            status: CalendarPartStat::Declined,
+        };
+
+        assert_eq!(reply, expected);
+
+        let ics = event().to_ics(CalendarMethod::Request);
+        assert!(ics.parse::<CalendarReply>().is_err());
+    }
+
+    #[test]
+    fn message_calendar_reply() {
+        let msg = concat!(
+            "From: bob@localhost\r\n",
+            "To: alice@localhost\r\n",
+            "Subject: Accepted: Lunch\r\n",
+            "Content-Type: text/calendar; method=REPLY; charset=utf-8\r\n",
+            "\r\n",
+            "BEGIN:VCALENDAR\r\n",
+            "METHOD:REPLY\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:event@localhost\r\n",
+            "ATTENDEE;PARTSTAT=ACCEPTED:mailto:bob@localhost\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+
+        let msg = Message::from(msg.as_bytes());
+        let reply = msg.calendar_reply().unwrap().unwrap();
+
+        assert_eq!(reply.status, CalendarPartStat::Accepted);
+        assert_eq!(reply.attendee.email, "bob@localhost");
+    }
+}
@@ -1,6 +1,5 @@
 use async_trait::async_trait;
 use imap_next::imap_types::sequence::{Sequence, SequenceSet};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::CopyMessages;
 use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult};
@@ -29,16 +28,15 @@ impl CopyMessages for CopyImapMessages {
     async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
         info!("copying imap messages {id} from folder {from_folder} to folder {to_folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("copy_messages").await;
 
-        let from_folder = config.get_folder_alias(from_folder);
-        let from_folder_encoded = encode_utf7(from_folder.clone());
-        debug!("utf7 encoded from folder: {from_folder_encoded}");
+        let from_folder = client.get_folder_alias(from_folder);
+        let from_folder_encoded = client.encode_folder(&from_folder);
+        debug!("encoded from folder: {from_folder_encoded}");
 
-        let to_folder = config.get_folder_alias(to_folder);
-        let to_folder_encoded = encode_utf7(to_folder.clone());
-        debug!("utf7 encoded to folder: {to_folder_encoded}");
+        let to_folder = client.get_folder_alias(to_folder);
+        let to_folder_encoded = client.encode_folder(&to_folder);
+        debug!("encoded to folder: {to_folder_encoded}");
 
         let uids: SequenceSet = match id {
             Id::Single(id) => Sequence::try_from(id.as_str()).unwrap().into(),
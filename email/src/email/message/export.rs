@@ -0,0 +1,517 @@
+//! # Message export
+//!
+//! Module dedicated to the export of messages as self-contained,
+//! sanitized HTML documents, so that downstream applications can
+//! print them or convert them to PDF without fetching anything from
+//! the network:
+//!
+//! - Headers are summarized in a table, following the headers shown
+//!   when reading a message (see
+//!   [`MessageReadConfig::headers`](super::get::config::MessageReadConfig::headers)).
+//!
+//! - Inline images referenced by `cid:` URLs are embedded as data
+//!   URIs.
+//!
+//! - Dangerous content is removed: scripts, styles, frames, forms,
+//!   event handlers, unsafe URLs and remote resources (which are also
+//!   used to track readers).
+//!
+//! - Attachments that are not embedded in the body are listed after
+//!   it.
+//!
+//! ```rust,ignore
+//! let html = Message::from(raw).to_export(&account_config)?.to_html();
+//! ```
+
+use std::{collections::HashMap, fmt::Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mail_parser::{Addr, Address, HeaderValue, MimeHeaders};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::Message;
+use crate::{account::config::AccountConfig, email::error::Error};
+
+/// The elements removed from the exported body, including their
+/// content.
+const REMOVED_ELEMENTS: [&str; 13] = [
+    "head", "title", "script", "style", "iframe", "frame", "frameset", "object", "embed", "applet",
+    "noscript", "template", "svg",
+];
+
+/// The elements removed from the exported body, keeping their
+/// content.
+static REMOVED_TAGS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</?(link|meta|base|form|input|button|textarea|select|option)\b[^>]*>").unwrap()
+});
+
+/// Regex matching the elements of [`REMOVED_ELEMENTS`].
+static REMOVED_ELEMENTS_REGEXES: Lazy<Vec<(Regex, Regex)>> = Lazy::new(|| {
+    REMOVED_ELEMENTS
+        .iter()
+        .map(|tag| {
+            let element = Regex::new(&format!(r"(?is)<{tag}\b.*?</{tag}\s*>")).unwrap();
+            let stray = Regex::new(&format!(r"(?i)</?{tag}\b[^>]*>")).unwrap();
+            (element, stray)
+        })
+        .collect()
+});
+
+static COMMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<!--.*?(-->|$)").unwrap());
+
+static BODY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<body\b[^>]*>(.*?)(</body\s*>|$)").unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<([a-zA-Z][a-zA-Z0-9-]*)((?:[^>"']|"[^"]*"|'[^']*')*?)(/?)>"#).unwrap()
+});
+
+static ATTR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([^\s"'>/=]+)(?:\s*=\s*("[^"]*"|'[^']*'|[^\s"'>]+))?"#).unwrap());
+
+static NUMERIC_ENTITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)&#(x[0-9a-f]+|[0-9]+);?").unwrap());
+
+/// The attributes containing a link.
+const LINK_ATTRS: [&str; 4] = ["href", "action", "formaction", "xlink:href"];
+
+/// The attributes containing a resource loaded by the document.
+const RESOURCE_ATTRS: [&str; 5] = ["src", "background", "poster", "lowsrc", "dynsrc"];
+
+/// The sanitized export of a message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MessageExport {
+    /// The subject of the message.
+    pub subject: Option<String>,
+
+    /// The summarized headers, as pairs of name and decoded value.
+    pub headers: Vec<(String, String)>,
+
+    /// The sanitized HTML body, without `html` nor `body` element.
+    pub body: String,
+
+    /// The attachments not embedded in the body.
+    pub attachments: Vec<MessageExportAttachment>,
+}
+
+/// The summary of an attachment of an exported message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MessageExportAttachment {
+    /// The optional attachment filename.
+    pub filename: Option<String>,
+
+    /// The attachment MIME type.
+    pub mime: String,
+
+    /// The size of the attachment, in bytes.
+    pub size: usize,
+}
+
+impl MessageExport {
+    /// Render the export as a self-contained HTML document.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+
+        if let Some(subject) = self.subject.as_ref() {
+            let _ = writeln!(html, "<title>{}</title>", escape_html(subject));
+        }
+
+        html.push_str("<style>\n");
+        html.push_str("table.headers th { text-align: left; padding-right: 1em; }\n");
+        html.push_str("div.body { overflow-wrap: break-word; }\n");
+        html.push_str("</style>\n</head>\n<body>\n");
+
+        if !self.headers.is_empty() {
+            html.push_str("<table class=\"headers\">\n");
+            for (name, value) in &self.headers {
+                let _ = writeln!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape_html(name),
+                    escape_html(value)
+                );
+            }
+            html.push_str("</table>\n<hr>\n");
+        }
+
+        html.push_str("<div class=\"body\">\n");
+        html.push_str(&self.body);
+        html.push_str("\n</div>\n");
+
+        if !self.attachments.is_empty() {
+            html.push_str("<hr>\n<ul class=\"attachments\">\n");
+            for attachment in &self.attachments {
+                let _ = writeln!(
+                    html,
+                    "<li>{} ({}, {} bytes)</li>",
+                    escape_html(attachment.filename.as_deref().unwrap_or("noname")),
+                    escape_html(&attachment.mime),
+                    attachment.size,
+                );
+            }
+            html.push_str("</ul>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl Message<'_> {
+    /// Export the current message as a sanitized, self-contained
+    /// structure.
+    ///
+    /// See the [module documentation](self) for details.
+    pub fn to_export(&self, config: &AccountConfig) -> Result<MessageExport, Error> {
+        let parsed = self.parsed()?;
+
+        let headers = config
+            .get_message_read_headers()
+            .into_iter()
+            .filter_map(|name| {
+                let value = header_to_string(parsed.header(name.as_str())?)?;
+                Some((name, value))
+            })
+            .collect();
+
+        // inline images, by content id
+        let mut images = HashMap::new();
+        for part in parsed.attachments() {
+            let Some(cid) = part.content_id() else {
+                continue;
+            };
+
+            let mime = match part.content_type() {
+                Some(ctype) if ctype.ctype().eq_ignore_ascii_case("image") => {
+                    match ctype.subtype() {
+                        Some(subtype) => format!("image/{subtype}"),
+                        None => tree_magic_mini::from_u8(part.contents()).to_owned(),
+                    }
+                }
+                _ => continue,
+            };
+
+            let uri = format!("data:{mime};base64,{}", STANDARD.encode(part.contents()));
+            images.insert(
+                cid.trim_matches(['<', '>']).to_owned(),
+                (uri, part.contents()),
+            );
+        }
+
+        let html = parsed.body_html(0).unwrap_or_default();
+        let mut embedded = Vec::new();
+        let body = sanitize_html(&html, |cid| {
+            let (uri, contents) = images.get(cid)?;
+            embedded.push(*contents);
+            Some(uri.clone())
+        });
+
+        let attachments = self
+            .attachments()?
+            .into_iter()
+            .filter(|attachment| !embedded.contains(&attachment.body.as_slice()))
+            .map(|attachment| MessageExportAttachment {
+                filename: attachment.filename,
+                mime: attachment.mime,
+                size: attachment.body.len(),
+            })
+            .collect();
+
+        Ok(MessageExport {
+            subject: parsed.subject().map(ToOwned::to_owned),
+            headers,
+            body,
+            attachments,
+        })
+    }
+}
+
+/// Sanitize the given HTML.
+///
+/// The content of the `body` element is kept, if any. Inline images
+/// are resolved using the given function, which takes a content id
+/// and returns the URI of the image.
+pub fn sanitize_html(html: &str, mut find_image: impl FnMut(&str) -> Option<String>) -> String {
+    let html = COMMENT.replace_all(html, "");
+
+    let mut html = match BODY.captures(&html) {
+        Some(body) => body[1].to_owned(),
+        None => html.into_owned(),
+    };
+
+    for (element, stray) in REMOVED_ELEMENTS_REGEXES.iter() {
+        html = element.replace_all(&html, "").into_owned();
+        html = stray.replace_all(&html, "").into_owned();
+    }
+
+    let html = REMOVED_TAGS.replace_all(&html, "");
+
+    let html = TAG.replace_all(&html, |tag: &Captures| {
+        let mut out = format!("<{}", &tag[1]);
+
+        for attr in ATTR.captures_iter(&tag[2]) {
+            let name = attr[1].to_lowercase();
+            let value = attr.get(2).map(|value| {
+                let value = value.as_str();
+                value
+                    .strip_prefix(['"', '\''])
+                    .and_then(|value| value.strip_suffix(['"', '\'']))
+                    .unwrap_or(value)
+            });
+
+            if let Some(value) = sanitize_attr(&name, value, &mut find_image) {
+                let _ = write!(out, " {}", &attr[1]);
+                if let Some(value) = value {
+                    let _ = write!(out, "=\"{}\"", value.replace('"', "&quot;"));
+                }
+            }
+        }
+
+        out.push_str(&tag[3]);
+        out.push('>');
+        out
+    });
+
+    html.trim().to_owned()
+}
+
+/// Sanitize the given attribute.
+///
+/// Returns `None` if the attribute should be removed, otherwise
+/// returns its (optional) sanitized value.
+fn sanitize_attr(
+    name: &str,
+    value: Option<&str>,
+    find_image: &mut impl FnMut(&str) -> Option<String>,
+) -> Option<Option<String>> {
+    if name.starts_with("on") || name == "srcset" {
+        return None;
+    }
+
+    let Some(value) = value else {
+        return Some(None);
+    };
+
+    let url = normalize_url(value);
+
+    if name == "style" {
+        let unsafe_style = ["expression(", "url(", "javascript:", "@import"]
+            .iter()
+            .any(|pattern| url.contains(pattern));
+
+        return if unsafe_style {
+            None
+        } else {
+            Some(Some(value.to_owned()))
+        };
+    }
+
+    if LINK_ATTRS.contains(&name) {
+        let safe_scheme = ["http:", "https:", "mailto:", "tel:", "#"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme));
+        let relative = !url
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or_default()
+            .contains(':');
+
+        return if safe_scheme || relative {
+            Some(Some(value.to_owned()))
+        } else {
+            None
+        };
+    }
+
+    if RESOURCE_ATTRS.contains(&name) {
+        if let Some(cid) = url.strip_prefix("cid:") {
+            // content ids are case-sensitive
+            let cid = value.trim().get(4..).unwrap_or(cid);
+            return find_image(cid.trim_matches(['<', '>'])).map(Some);
+        }
+
+        return if url.starts_with("data:image/") {
+            Some(Some(value.to_owned()))
+        } else {
+            None
+        };
+    }
+
+    Some(Some(value.to_owned()))
+}
+
+/// Normalize the given attribute value, so that it can be checked
+/// against URL schemes: numeric character references are decoded,
+/// whitespaces and control characters are removed, and the value is
+/// lowercased.
+fn normalize_url(value: &str) -> String {
+    let value = NUMERIC_ENTITY.replace_all(value, |entity: &Captures| {
+        let code = &entity[1];
+        let code = match code.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        };
+
+        code.and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default()
+    });
+
+    value
+        .replace("&colon;", ":")
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Format the given header value as a human-readable string.
+fn header_to_string(value: &HeaderValue) -> Option<String> {
+    let value = match value {
+        HeaderValue::Address(Address::List(addrs)) => addrs
+            .iter()
+            .map(addr_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        HeaderValue::Address(Address::Group(groups)) => groups
+            .iter()
+            .map(|group| {
+                let addrs: Vec<_> = group.addresses.iter().map(addr_to_string).collect();
+                match group.name.as_ref() {
+                    Some(name) => format!("{name}: {};", addrs.join(", ")),
+                    None => addrs.join(", "),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        HeaderValue::Text(text) => text.to_string(),
+        HeaderValue::TextList(texts) => texts.join(", "),
+        HeaderValue::DateTime(date) => date.to_rfc822(),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+fn addr_to_string(addr: &Addr) -> String {
+    match (addr.name.as_ref(), addr.address.as_ref()) {
+        (Some(name), Some(email)) => format!("{name} <{email}>"),
+        (Some(name), None) => name.to_string(),
+        (None, Some(email)) => email.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Escape the given text, so that it can be inserted in HTML.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::sanitize_html;
+    use crate::{account::config::AccountConfig, message::Message};
+
+    #[test]
+    fn remove_dangerous_content() {
+        let html = concat_line!(
+            "<html><head><title>t</title><style>body { color: red }</style></head>",
+            "<body onload=\"steal()\">",
+            "<!-- tracking comment -->",
+            "<script>alert(1)</script>",
+            "<p style=\"background: url(https://tracker)\" class=\"a\">Hello</p>",
+            "<a href=\"javascript:alert(1)\">bad</a>",
+            "<a href=\"java&#x09;script&#58;alert(1)\">obfuscated</a>",
+            "<a href='https://pimalaya.org' onclick=\"steal()\">good</a>",
+            "<img src=\"https://tracker/pixel.gif\" alt=\"pixel\">",
+            "<iframe src=\"https://evil\"></iframe>",
+            "<form action=\"https://evil\"><input name=\"password\"></form>",
+            "</body></html>",
+        );
+
+        let html = sanitize_html(html, |_| None);
+
+        let expected = concat_line!(
+            "<p class=\"a\">Hello</p>",
+            "<a>bad</a>",
+            "<a>obfuscated</a>",
+            "<a href=\"https://pimalaya.org\">good</a>",
+            "<img alt=\"pixel\">",
+        );
+
+        assert_eq!(html, expected.trim());
+    }
+
+    #[test]
+    fn export_message() {
+        let config = AccountConfig::default();
+
+        let msg = Message::from(concat_line!(
+            "From: Alice <alice@localhost>",
+            "To: bob@localhost",
+            "Subject: <Report>",
+            "MIME-Version: 1.0",
+            "Content-Type: multipart/related; boundary=\"boundary\"",
+            "",
+            "--boundary",
+            "Content-Type: text/html; charset=utf-8",
+            "",
+            "<p>Chart: <img src=\"cid:chart@localhost\"></p><script>alert(1)</script>",
+            "--boundary",
+            "Content-Type: image/png",
+            "Content-ID: <chart@localhost>",
+            "Content-Disposition: inline",
+            "Content-Transfer-Encoding: base64",
+            "",
+            "iVBORw0KGgo=",
+            "--boundary",
+            "Content-Type: text/plain",
+            "Content-Disposition: attachment; filename=\"notes.txt\"",
+            "",
+            "notes",
+            "--boundary--",
+        ));
+
+        let export = msg.to_export(&config).unwrap();
+
+        assert_eq!(export.subject.as_deref(), Some("<Report>"));
+        assert_eq!(
+            export.headers,
+            [
+                ("From".into(), "Alice <alice@localhost>".into()),
+                ("To".into(), "bob@localhost".into()),
+                ("Subject".into(), "<Report>".into()),
+            ]
+        );
+        assert_eq!(
+            export.body,
+            "<p>Chart: <img src=\"data:image/png;base64,iVBORw0KGgo=\"></p>"
+        );
+        assert_eq!(export.attachments.len(), 1);
+        assert_eq!(export.attachments[0].filename.as_deref(), Some("notes.txt"));
+
+        let html = export.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>&lt;Report&gt;</title>"));
+        assert!(html.contains("<tr><th>From</th><td>Alice &lt;alice@localhost&gt;</td></tr>"));
+        assert!(html.contains("<li>notes.txt (text/plain, 5 bytes)</li>"));
+    }
+}
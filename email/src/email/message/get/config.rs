@@ -14,4 +14,12 @@ pub struct MessageReadConfig {
     /// Define the text/plain format as defined in the [RFC
     /// 2646](https://www.ietf.org/rfc/rfc2646.txt).
     pub format: Option<EmailTextPlainFormat>,
+
+    /// Define the maximum size of messages, in bytes.
+    ///
+    /// Messages bigger than this size are not downloaded, a
+    /// [`MessageTooLarge`](crate::email::Error::MessageTooLarge)
+    /// error is returned instead. This protects small devices from
+    /// malicious or bloated messages. Defaults to no limit.
+    pub max_size: Option<u64>,
 }
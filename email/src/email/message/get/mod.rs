@@ -3,8 +3,12 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+#[cfg(feature = "notmuch-remote")]
+pub mod notmuch_remote;
 
 use async_trait::async_trait;
 
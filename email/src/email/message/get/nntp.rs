@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+
+use super::{GetMessages, Messages};
+use crate::{
+    envelope::Id,
+    message::peek::{nntp::PeekNntpMessages, PeekMessages},
+    nntp::NntpContextSync,
+    AnyResult,
+};
+
+/// Get NNTP messages.
+///
+/// Newsgroups have no notion of flags, so getting messages peeks
+/// them, then marks them as read in the local read state.
+#[derive(Clone)]
+pub struct GetNntpMessages {
+    ctx: NntpContextSync,
+    peek_messages: PeekNntpMessages,
+}
+
+impl GetNntpMessages {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            peek_messages: PeekNntpMessages::new(ctx),
+        }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn GetMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn GetMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetMessages for GetNntpMessages {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let messages = self.peek_messages.peek_messages(folder, id).await?;
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.mark_articles(folder, id, true).await?;
+
+        Ok(messages)
+    }
+}
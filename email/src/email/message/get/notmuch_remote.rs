@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+
+use super::{GetMessages, Messages};
+use crate::{
+    envelope::Id,
+    info,
+    message::peek::{notmuch_remote::PeekNotmuchRemoteMessages, PeekMessages},
+    notmuch_remote::NotmuchRemoteContextSync,
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct GetNotmuchRemoteMessages {
+    ctx: NotmuchRemoteContextSync,
+    peek_messages: PeekNotmuchRemoteMessages,
+}
+
+impl GetNotmuchRemoteMessages {
+    pub fn new(ctx: &NotmuchRemoteContextSync) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            peek_messages: PeekNotmuchRemoteMessages::new(ctx),
+        }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchRemoteContextSync) -> Box<dyn GetMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchRemoteContextSync) -> Option<Box<dyn GetMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetMessages for GetNotmuchRemoteMessages {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("getting remote notmuch messages {id} from folder {folder}");
+
+        let msgs = self.peek_messages.peek_messages(folder, id).await?;
+
+        // marks messages as seen
+        self.ctx.tag(["-unread"], id.iter()).await?;
+
+        Ok(msgs)
+    }
+}
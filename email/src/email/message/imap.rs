@@ -17,6 +17,12 @@
     }])
 });
 
+/// The IMAP fetch items needed to retrieve the size of messages,
+/// without downloading them.
+pub static FETCH_MESSAGES_SIZES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
+    MacroOrMessageDataItemNames::MessageDataItemNames(vec![MessageDataItemName::Rfc822Size])
+});
+
 /// Same as [`FETCH_MESSAGES`], but with peek set a `true`.
 pub static PEEK_MESSAGES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
     MacroOrMessageDataItemNames::MessageDataItemNames(vec![MessageDataItemName::BodyExt {
@@ -0,0 +1,210 @@
+//! Module dedicated to email message line endings.
+//!
+//! Messages coming from Maildir usually use LF line endings, whereas
+//! IMAP and SMTP require CRLF (see [RFC 5322 section
+//! 2.3](https://www.rfc-editor.org/rfc/rfc5322#section-2.3)). This
+//! module contains helpers to detect and normalize line endings of
+//! raw messages.
+
+use std::borrow::Cow;
+
+use crate::debug;
+
+/// The line ending style of a raw message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    /// All lines end with CRLF.
+    Crlf,
+
+    /// All lines end with a bare LF.
+    Lf,
+
+    /// All lines end with a bare CR.
+    Cr,
+
+    /// Lines end with different styles.
+    Mixed,
+
+    /// The message does not contain any line ending.
+    None,
+}
+
+/// The line endings report of a raw message.
+///
+/// Counts the occurrences of each kind of line ending found in a raw
+/// message.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LineEndingReport {
+    /// The number of CRLF line endings.
+    pub crlf: usize,
+
+    /// The number of bare LF line endings.
+    pub lf: usize,
+
+    /// The number of bare CR line endings.
+    pub cr: usize,
+}
+
+impl LineEndingReport {
+    /// Detect line endings of the given raw message.
+    pub fn detect(bytes: &[u8]) -> Self {
+        let mut report = Self::default();
+        let mut bytes = bytes.iter().peekable();
+
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\r' if bytes.next_if_eq(&&b'\n').is_some() => report.crlf += 1,
+                b'\r' => report.cr += 1,
+                b'\n' => report.lf += 1,
+                _ => (),
+            }
+        }
+
+        report
+    }
+
+    /// Get the line ending style matching the current report.
+    pub fn line_ending(&self) -> LineEnding {
+        match (self.crlf > 0, self.lf > 0, self.cr > 0) {
+            (false, false, false) => LineEnding::None,
+            (true, false, false) => LineEnding::Crlf,
+            (false, true, false) => LineEnding::Lf,
+            (false, false, true) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// Return `true` if the message does not contain any bare CR or
+    /// bare LF.
+    pub fn is_crlf(&self) -> bool {
+        self.lf == 0 && self.cr == 0
+    }
+
+    /// Return `true` if the message does not contain any CRLF or
+    /// bare CR.
+    pub fn is_lf(&self) -> bool {
+        self.crlf == 0 && self.cr == 0
+    }
+}
+
+/// Detect line endings of the given raw message.
+///
+/// Shortcut for [`LineEndingReport::detect`].
+pub fn detect(bytes: &[u8]) -> LineEndingReport {
+    LineEndingReport::detect(bytes)
+}
+
+/// Normalize line endings of the given raw message to CRLF.
+///
+/// Bare LF and bare CR are replaced by CRLF. The message is borrowed
+/// back if no change is required.
+pub fn normalize_to_crlf(bytes: &[u8]) -> Cow<'_, [u8]> {
+    let report = LineEndingReport::detect(bytes);
+
+    if report.is_crlf() {
+        return Cow::Borrowed(bytes);
+    }
+
+    debug!(?report, "normalizing message line endings to CRLF");
+
+    let mut normalized = Vec::with_capacity(bytes.len() + report.lf + report.cr);
+    let mut bytes = bytes.iter().peekable();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' => {
+                bytes.next_if_eq(&&b'\n');
+                normalized.extend_from_slice(b"\r\n");
+            }
+            b'\n' => normalized.extend_from_slice(b"\r\n"),
+            byte => normalized.push(*byte),
+        }
+    }
+
+    Cow::Owned(normalized)
+}
+
+/// Normalize line endings of the given raw message to LF.
+///
+/// CRLF and bare CR are replaced by LF. The message is borrowed back
+/// if no change is required.
+pub fn normalize_to_lf(bytes: &[u8]) -> Cow<'_, [u8]> {
+    let report = LineEndingReport::detect(bytes);
+
+    if report.is_lf() {
+        return Cow::Borrowed(bytes);
+    }
+
+    debug!(?report, "normalizing message line endings to LF");
+
+    let mut normalized = Vec::with_capacity(bytes.len());
+    let mut bytes = bytes.iter().peekable();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' => {
+                bytes.next_if_eq(&&b'\n');
+                normalized.push(b'\n');
+            }
+            byte => normalized.push(*byte),
+        }
+    }
+
+    Cow::Owned(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{LineEnding, LineEndingReport};
+
+    #[test]
+    fn detect() {
+        let report = LineEndingReport::detect(b"");
+        assert_eq!(report, LineEndingReport::default());
+        assert_eq!(report.line_ending(), LineEnding::None);
+
+        let report = LineEndingReport::detect(b"From: a\r\nTo: b\r\n\r\nbody");
+        assert_eq!(report.crlf, 3);
+        assert_eq!(report.line_ending(), LineEnding::Crlf);
+
+        let report = LineEndingReport::detect(b"From: a\nTo: b\n\nbody");
+        assert_eq!(report.lf, 3);
+        assert_eq!(report.line_ending(), LineEnding::Lf);
+
+        let report = LineEndingReport::detect(b"From: a\rTo: b\r");
+        assert_eq!(report.cr, 2);
+        assert_eq!(report.line_ending(), LineEnding::Cr);
+
+        let report = LineEndingReport::detect(b"From: a\r\nTo: b\n\rbody\r");
+        assert_eq!(report.crlf, 1);
+        assert_eq!(report.lf, 1);
+        assert_eq!(report.cr, 2);
+        assert_eq!(report.line_ending(), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn normalize_to_crlf() {
+        let msg = b"From: a\r\nTo: b\r\n\r\nbody";
+        assert!(matches!(super::normalize_to_crlf(msg), Cow::Borrowed(_)));
+
+        let msg = b"From: a\nTo: b\r\n\rbody\n";
+        assert_eq!(
+            super::normalize_to_crlf(msg).as_ref(),
+            b"From: a\r\nTo: b\r\n\r\nbody\r\n"
+        );
+    }
+
+    #[test]
+    fn normalize_to_lf() {
+        let msg = b"From: a\nTo: b\n\nbody";
+        assert!(matches!(super::normalize_to_lf(msg), Cow::Borrowed(_)));
+
+        let msg = b"From: a\r\nTo: b\n\rbody\r\n";
+        assert_eq!(
+            super::normalize_to_lf(msg).as_ref(),
+            b"From: a\nTo: b\n\nbody\n"
+        );
+    }
+}
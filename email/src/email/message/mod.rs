@@ -8,19 +8,26 @@
 
 pub mod add;
 pub mod attachment;
+pub mod calendar;
 pub mod config;
 pub mod copy;
 pub mod delete;
+#[cfg(feature = "message-export")]
+pub mod export;
 pub mod get;
 #[cfg(feature = "imap")]
 pub mod imap;
+pub mod line_ending;
 pub mod r#move;
 pub mod peek;
+pub mod received;
 pub mod remove;
 pub mod send;
 #[cfg(feature = "sync")]
 pub mod sync;
 pub mod template;
+#[cfg(feature = "tnef")]
+pub mod tnef;
 
 use std::{borrow::Cow, sync::Arc};
 
@@ -40,6 +47,22 @@
 };
 use crate::{account::config::AccountConfig, email::error::Error};
 
+/// Check the size of the given message against the given maximum
+/// size, if any.
+///
+/// Backends call this function before downloading messages, so that
+/// big messages are never buffered into memory.
+pub fn check_size(id: impl ToString, size: u64, max_size: Option<u64>) -> Result<(), Error> {
+    match max_size {
+        Some(max_size) if size > max_size => Err(Error::MessageTooLarge {
+            id: id.to_string(),
+            size,
+            max_size,
+        }),
+        _ => Ok(()),
+    }
+}
+
 /// The message wrapper.
 #[self_referencing]
 pub struct Message<'a> {
@@ -70,21 +93,59 @@ pub fn raw(&self) -> Result<&[u8], Error> {
     }
 
     /// Returns the list of message attachment.
+    ///
+    /// When the `tnef` feature is enabled, Outlook TNEF attachments
+    /// (`winmail.dat`) are replaced by the attachments they contain.
     pub fn attachments(&self) -> Result<Vec<Attachment>, Error> {
-        Ok(self
-            .parsed()?
-            .attachments()
-            .map(|part| {
-                Attachment {
-                    filename: part.attachment_name().map(ToOwned::to_owned),
-                    // better to guess the real mime type from the
-                    // body instead of using the one given from the
-                    // content type
-                    mime: tree_magic_mini::from_u8(part.contents()).to_owned(),
-                    body: part.contents().to_owned(),
-                }
+        let mut attachments = Vec::new();
+
+        for part in self.parsed()?.attachments() {
+            #[cfg(feature = "tnef")]
+            if let Some(inner) = Self::tnef_attachments(part) {
+                attachments.extend(inner);
+                continue;
+            }
+
+            attachments.push(Attachment {
+                filename: part.attachment_name().map(ToOwned::to_owned),
+                // better to guess the real mime type from the
+                // body instead of using the one given from the
+                // content type
+                mime: tree_magic_mini::from_u8(part.contents()).to_owned(),
+                body: part.contents().to_owned(),
+            });
+        }
+
+        Ok(attachments)
+    }
+
+    /// Decode the attachments of the given part if it is a TNEF
+    /// attachment.
+    ///
+    /// Returns `None` if the part is not a TNEF attachment, or if it
+    /// cannot be decoded: it is then kept as a normal attachment.
+    #[cfg(feature = "tnef")]
+    fn tnef_attachments(part: &mail_parser::MessagePart) -> Option<Vec<Attachment>> {
+        let mime = part
+            .content_type()
+            .map(|ctype| match ctype.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ctype.ctype()),
+                None => ctype.ctype().to_owned(),
             })
-            .collect())
+            .unwrap_or_default();
+
+        if !tnef::is_tnef(&mime, part.attachment_name()) {
+            return None;
+        }
+
+        match tnef::decode(part.contents()) {
+            Ok(attachments) if !attachments.is_empty() => Some(attachments),
+            Ok(_) => None,
+            Err(_err) => {
+                crate::debug!("cannot decode tnef attachment: {_err}");
+                None
+            }
+        }
     }
 
     /// Creates a new template builder from an account configuration.
@@ -169,7 +230,12 @@ enum RawMessages {
     Imap(Vec<Vec1<MessageDataItem<'static>>>),
     #[cfg(feature = "maildir")]
     MailEntries(Vec<MaildirEntry>),
-    #[cfg(feature = "notmuch")]
+    #[cfg(any(
+        feature = "cache",
+        feature = "nntp",
+        feature = "notmuch",
+        feature = "notmuch-remote"
+    ))]
     Notmuch(Vec<Vec<u8>>),
     #[allow(dead_code)]
     None,
@@ -201,7 +267,12 @@ fn emails_builder<'a>(raw: &'a mut RawMessages) -> Vec<Message<'a>> {
                 .collect(),
             #[cfg(feature = "maildir")]
             RawMessages::MailEntries(entries) => entries.iter_mut().map(Message::from).collect(),
-            #[cfg(feature = "notmuch")]
+            #[cfg(any(
+                feature = "cache",
+                feature = "nntp",
+                feature = "notmuch",
+                feature = "notmuch-remote"
+            ))]
             RawMessages::Notmuch(raw) => raw
                 .iter()
                 .map(|raw| Message::from(raw.as_slice()))
@@ -247,7 +318,12 @@ fn try_from(entries: Vec<MaildirEntry>) -> Result<Self, Error> {
     }
 }
 
-#[cfg(feature = "notmuch")]
+#[cfg(any(
+    feature = "cache",
+    feature = "nntp",
+    feature = "notmuch",
+    feature = "notmuch-remote"
+))]
 impl From<Vec<Vec<u8>>> for Messages {
     fn from(raw: Vec<Vec<u8>>) -> Self {
         MessagesBuilder {
@@ -266,10 +342,26 @@ mod tests {
 
     use crate::{
         account::config::AccountConfig,
-        message::{config::MessageConfig, get::config::MessageReadConfig, Message},
+        email::Error,
+        message::{check_size, config::MessageConfig, get::config::MessageReadConfig, Message},
         template::Template,
     };
 
+    #[test]
+    fn check_message_size() {
+        assert!(check_size("1", 1024, None).is_ok());
+        assert!(check_size("1", 1024, Some(1024)).is_ok());
+
+        match check_size("1", 1025, Some(1024)) {
+            Err(Error::MessageTooLarge { id, size, max_size }) => {
+                assert_eq!(id, "1");
+                assert_eq!(size, 1025);
+                assert_eq!(max_size, 1024);
+            }
+            res => panic!("expected message too large error, got {res:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn to_read_tpl() {
         let config = AccountConfig::default();
@@ -1,9 +1,8 @@
 use async_trait::async_trait;
 use imap_next::imap_types::sequence::{Sequence, SequenceSet};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{Messages, PeekMessages};
-use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult};
+use crate::{debug, envelope::Id, imap::ImapContext, info, message::check_size, AnyResult};
 
 #[derive(Clone, Debug)]
 pub struct PeekImapMessages {
@@ -29,12 +28,13 @@ impl PeekMessages for PeekImapMessages {
     async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         info!("peeking imap messages {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client_for("peek_messages").await;
         let config = &client.account_config;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let max_size = config.find_message_read_max_size();
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
         let uids: SequenceSet = match id {
             Id::Single(id) => Sequence::try_from(id.as_str()).unwrap().into(),
@@ -47,6 +47,13 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         };
 
         client.select_mailbox(&folder_encoded).await?;
+
+        if max_size.is_some() {
+            for (uid, size) in client.fetch_messages_sizes(uids.clone()).await? {
+                check_size(uid, size.into(), max_size)?;
+            }
+        }
+
         let msgs = client.peek_messages(uids).await?;
 
         Ok(msgs)
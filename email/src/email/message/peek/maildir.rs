@@ -1,7 +1,11 @@
+use std::fs;
+
 use async_trait::async_trait;
 
 use super::{Messages, PeekMessages};
-use crate::{envelope::Id, info, maildir::MaildirContextSync, AnyResult, Error};
+use crate::{
+    envelope::Id, info, maildir::MaildirContextSync, message::check_size, AnyResult, Error,
+};
 
 #[derive(Clone)]
 pub struct PeekMaildirMessages {
@@ -51,6 +55,17 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
             .collect();
         msgs.sort_by_key(|(pos, _)| *pos);
 
+        let max_size = ctx.account_config.find_message_read_max_size();
+
+        if max_size.is_some() {
+            for (_, entry) in &msgs {
+                let size = fs::metadata(entry.path())
+                    .map_err(Error::FileReadFailure)?
+                    .len();
+                check_size(entry.id().unwrap_or_default(), size, max_size)?;
+            }
+        }
+
         let msgs: Messages = msgs
             .into_iter()
             .map(|(_, entry)| entry)
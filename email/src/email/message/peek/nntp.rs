@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+
+use super::{Messages, PeekMessages};
+use crate::{
+    envelope::Id,
+    info,
+    message::check_size,
+    nntp::{Error, NntpContextSync},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct PeekNntpMessages {
+    ctx: NntpContextSync,
+}
+
+impl PeekNntpMessages {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn PeekMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn PeekMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl PeekMessages for PeekNntpMessages {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("peeking NNTP articles {id} from newsgroup {folder}");
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.client.group(folder).await?;
+
+        let max_size = ctx.account_config.find_message_read_max_size();
+
+        let mut articles = Vec::new();
+
+        for id in id.iter() {
+            let number = id
+                .parse()
+                .map_err(|_| Error::ParseArticleNumberError(id.to_owned()))?;
+
+            if max_size.is_some() {
+                let overview = ctx.client.over(number, number).await?;
+                let size = overview.first().and_then(|overview| overview.bytes);
+
+                // servers may not give the size of articles
+                if let Some(size) = size {
+                    check_size(id, size, max_size)?;
+                }
+            }
+
+            articles.push(ctx.client.article(number).await?);
+        }
+
+        Ok(Messages::from(articles))
+    }
+}
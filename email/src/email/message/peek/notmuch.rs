@@ -3,7 +3,10 @@
 use async_trait::async_trait;
 
 use super::{Messages, PeekMessages};
-use crate::{email::error::Error, envelope::Id, info, notmuch::NotmuchContextSync, AnyResult};
+use crate::{
+    email::error::Error, envelope::Id, info, message::check_size, notmuch::NotmuchContextSync,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct PeekNotmuchMessages {
@@ -31,6 +34,7 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
 
         let ctx = self.ctx.lock().await;
         let db = ctx.open_db()?;
+        let max_size = ctx.account_config.find_message_read_max_size();
 
         let msgs: Messages = id
             .iter()
@@ -43,6 +47,12 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
                     })?
                     .filename()
                     .to_owned();
+
+                if max_size.is_some() {
+                    let size = fs::metadata(&path).map_err(Error::FileReadFailure)?.len();
+                    check_size(ids, size, max_size)?;
+                }
+
                 let msg = fs::read(path).map_err(Error::FileReadFailure)?;
                 Ok(msg)
             })
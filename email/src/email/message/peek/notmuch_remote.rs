@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+
+use super::{Messages, PeekMessages};
+use crate::{
+    envelope::Id,
+    info,
+    notmuch_remote::{Error, NotmuchRemoteContextSync},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct PeekNotmuchRemoteMessages {
+    ctx: NotmuchRemoteContextSync,
+}
+
+impl PeekNotmuchRemoteMessages {
+    pub fn new(ctx: &NotmuchRemoteContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchRemoteContextSync) -> Box<dyn PeekMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchRemoteContextSync) -> Option<Box<dyn PeekMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl PeekMessages for PeekNotmuchRemoteMessages {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("peeking remote notmuch messages {id} from folder {folder}");
+
+        let mut msgs = Vec::new();
+
+        for id in id.iter() {
+            let msg = self.ctx.show_raw(id).await?;
+
+            if msg.is_empty() {
+                let err = Error::FindMessageEmptyError(folder.to_owned(), id.to_owned());
+                return Err(err.into());
+            }
+
+            msgs.push(msg);
+        }
+
+        Ok(Messages::from(msgs))
+    }
+}
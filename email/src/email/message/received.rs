@@ -0,0 +1,328 @@
+//! # Received chain
+//!
+//! Module dedicated to the analysis of the `Received` header chain
+//! of a message. Each relay prepends a `Received` header when it
+//! accepts a message, so reading the chain in reverse order gives the
+//! path followed by the message, from its origin to the recipient.
+//! This is useful to debug delivery issues, and as a trust indicator.
+//!
+//! https://www.rfc-editor.org/rfc/rfc5321.html#section-4.4
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+use super::Message;
+use crate::email::error::Result;
+
+/// A hop of the `Received` chain, parsed from one `Received` header.
+///
+/// Parsing is lenient: relays do not always follow the RFC, so
+/// unknown clauses are ignored and missing ones are left to `None`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReceivedHop {
+    /// The host name the message was received from.
+    pub from: Option<String>,
+
+    /// The IP address the message was received from.
+    pub ip: Option<IpAddr>,
+
+    /// The host name of the relay that received the message.
+    pub by: Option<String>,
+
+    /// The protocol used to receive the message, for example `ESMTPS`.
+    pub protocol: Option<String>,
+
+    /// The identifier given to the message by the relay.
+    pub id: Option<String>,
+
+    /// The date the relay received the message.
+    pub date: Option<DateTime<FixedOffset>>,
+}
+
+impl ReceivedHop {
+    /// Parse a hop from the given raw `Received` header value.
+    pub fn parse(value: &str) -> Self {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let (clauses, date) = match value.rsplit_once(';') {
+            Some((clauses, date)) => (clauses, parse_date(date)),
+            None => (value.as_str(), None),
+        };
+
+        let mut hop = Self {
+            date,
+            ..Default::default()
+        };
+
+        let mut tokens = tokenize(clauses).into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            let Token::Word(keyword) = token else {
+                continue;
+            };
+
+            let Some(Token::Word(arg)) = tokens.next_if(|token| matches!(token, Token::Word(_)))
+            else {
+                continue;
+            };
+
+            match keyword.to_ascii_lowercase().as_str() {
+                "from" => {
+                    hop.ip = parse_ip(&arg);
+                    hop.from = Some(arg);
+
+                    while let Some(Token::Comment(comment)) =
+                        tokens.next_if(|token| matches!(token, Token::Comment(_)))
+                    {
+                        if hop.ip.is_none() {
+                            hop.ip = parse_ip(&comment);
+                        }
+                    }
+                }
+                "by" => hop.by = Some(arg),
+                "with" => hop.protocol = Some(arg),
+                "id" => hop.id = Some(arg),
+                _ => (),
+            }
+        }
+
+        hop
+    }
+}
+
+/// An anomaly found in the `Received` chain.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ReceivedAnomaly {
+    /// The hop at the given index has no date, or its date cannot be
+    /// parsed.
+    MissingDate(usize),
+
+    /// The hop at the given index is dated before the previous one:
+    /// one of the relays has a wrong clock, or the header has been
+    /// forged.
+    TimeWentBackwards(usize),
+}
+
+/// The `Received` chain of a message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReceivedChain {
+    /// The hops of the chain, from the origin of the message to its
+    /// recipient.
+    pub hops: Vec<ReceivedHop>,
+}
+
+impl ReceivedChain {
+    /// Build a chain from the given raw `Received` header values, in
+    /// the order they appear in the message (most recent first).
+    pub fn parse<T: AsRef<str>>(values: impl IntoIterator<Item = T>) -> Self {
+        let mut hops: Vec<_> = values
+            .into_iter()
+            .map(|value| ReceivedHop::parse(value.as_ref()))
+            .collect();
+
+        hops.reverse();
+
+        Self { hops }
+    }
+
+    /// Return the time spent by the message between the first and
+    /// the last dated hops.
+    pub fn transit_time(&self) -> Option<Duration> {
+        let mut dates = self.hops.iter().filter_map(|hop| hop.date);
+        let first = dates.next()?;
+        let last = dates.last()?;
+        Some(last.signed_duration_since(first))
+    }
+
+    /// Return the time spent by the message between the previous hop
+    /// and the hop at the given index.
+    pub fn hop_delay(&self, index: usize) -> Option<Duration> {
+        let prev = self.hops.get(index.checked_sub(1)?)?.date?;
+        let curr = self.hops.get(index)?.date?;
+        Some(curr.signed_duration_since(prev))
+    }
+
+    /// Return the anomalies found in the chain.
+    pub fn anomalies(&self) -> Vec<ReceivedAnomaly> {
+        let mut anomalies = Vec::new();
+        let mut prev_date = None;
+
+        for (index, hop) in self.hops.iter().enumerate() {
+            let Some(date) = hop.date else {
+                anomalies.push(ReceivedAnomaly::MissingDate(index));
+                continue;
+            };
+
+            if prev_date.is_some_and(|prev_date| date < prev_date) {
+                anomalies.push(ReceivedAnomaly::TimeWentBackwards(index));
+            }
+
+            prev_date = Some(date);
+        }
+
+        anomalies
+    }
+
+    /// Return `true` if the chain does not contain any anomaly.
+    pub fn is_consistent(&self) -> bool {
+        self.anomalies().is_empty()
+    }
+}
+
+impl Message<'_> {
+    /// Parse the `Received` header chain of the message.
+    pub fn received_chain(&self) -> Result<ReceivedChain> {
+        let values = self
+            .parsed()?
+            .headers_raw()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Received"))
+            .map(|(_, value)| value);
+
+        Ok(ReceivedChain::parse(values))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Comment(String),
+}
+
+/// Split the clauses of a `Received` header into words and
+/// (possibly nested) comments.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut comment = String::new();
+    let mut depth = 0usize;
+
+    for c in input.chars() {
+        match c {
+            '(' => {
+                if depth == 0 && !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                } else if depth > 0 {
+                    comment.push(c);
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    tokens.push(Token::Comment(std::mem::take(&mut comment)));
+                } else {
+                    comment.push(c);
+                }
+            }
+            _ if depth > 0 => comment.push(c),
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+
+    tokens
+}
+
+/// Find the first IP address between square brackets, or the given
+/// input as a whole if it is an IP address.
+fn parse_ip(input: &str) -> Option<IpAddr> {
+    if let Ok(ip) = input.parse() {
+        return Some(ip);
+    }
+
+    input.split('[').skip(1).find_map(|part| {
+        let (ip, _) = part.split_once(']')?;
+        let ip = ip.strip_prefix("IPv6:").unwrap_or(ip);
+        ip.parse().ok()
+    })
+}
+
+fn parse_date(input: &str) -> Option<DateTime<FixedOffset>> {
+    let date = tokenize(input)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => Some(word),
+            Token::Comment(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    DateTime::parse_from_rfc2822(&date).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::{ReceivedAnomaly, ReceivedChain, ReceivedHop};
+    use crate::message::Message;
+
+    #[test]
+    fn parse_hop() {
+        let hop = ReceivedHop::parse(concat!(
+            "from mail.example.org (mail.example.org [192.0.2.1])\r\n",
+            "\tby mx.localhost (Postfix) with ESMTPS id 4VfR2x;\r\n",
+            "\tThu, 16 May 2024 10:00:00 +0200 (CEST)",
+        ));
+
+        assert_eq!(hop.from.as_deref(), Some("mail.example.org"));
+        assert_eq!(hop.ip, Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(hop.by.as_deref(), Some("mx.localhost"));
+        assert_eq!(hop.protocol.as_deref(), Some("ESMTPS"));
+        assert_eq!(hop.id.as_deref(), Some("4VfR2x"));
+        assert_eq!(hop.date.unwrap().to_rfc3339(), "2024-05-16T10:00:00+02:00");
+
+        let hop = ReceivedHop::parse("from [IPv6:2001:db8::1] by localhost; invalid date");
+        assert_eq!(hop.ip, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(hop.date, None);
+    }
+
+    #[test]
+    fn message_received_chain() {
+        let msg = concat!(
+            "Received: from relay.localhost by mx.localhost with ESMTP;\r\n",
+            " Thu, 16 May 2024 10:00:30 +0200\r\n",
+            "Received: from client.localhost by relay.localhost with ESMTPSA;\r\n",
+            " Thu, 16 May 2024 08:00:00 +0000\r\n",
+            "From: alice@localhost\r\n",
+            "To: bob@localhost\r\n",
+            "\r\n",
+            "Hello!\r\n",
+        );
+
+        let chain = Message::from(msg.as_bytes()).received_chain().unwrap();
+
+        assert_eq!(chain.hops.len(), 2);
+        assert_eq!(chain.hops[0].by.as_deref(), Some("relay.localhost"));
+        assert_eq!(chain.hops[1].by.as_deref(), Some("mx.localhost"));
+        assert_eq!(chain.transit_time(), Some(Duration::seconds(30)));
+        assert_eq!(chain.hop_delay(1), Some(Duration::seconds(30)));
+        assert!(chain.is_consistent());
+    }
+
+    #[test]
+    fn received_chain_anomalies() {
+        let chain = ReceivedChain::parse([
+            "by c.localhost; Thu, 16 May 2024 10:00:00 +0000",
+            "by b.localhost",
+            "by a.localhost; Thu, 16 May 2024 10:05:00 +0000",
+        ]);
+
+        let expected = vec![
+            ReceivedAnomaly::MissingDate(1),
+            ReceivedAnomaly::TimeWentBackwards(2),
+        ];
+
+        assert_eq!(chain.anomalies(), expected);
+        assert_eq!(chain.transit_time(), Some(Duration::minutes(-5)));
+    }
+}
@@ -1,6 +1,5 @@
 use async_trait::async_trait;
 use imap_next::imap_types::sequence::{Sequence, SequenceSet};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::RemoveMessages;
 use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult};
@@ -29,12 +28,11 @@ impl RemoveMessages for RemoveImapMessages {
     async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         info!("removing imap messages {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("remove_messages").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded from folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded from folder: {folder_encoded}");
 
         let uids: SequenceSet = match id {
             Id::Single(id) => Sequence::try_from(id.as_str()).unwrap().into(),
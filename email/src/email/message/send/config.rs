@@ -1,4 +1,12 @@
+use std::{fmt, future::Future, ops::Deref, path::PathBuf, pin::Pin, sync::Arc};
+#[cfg(feature = "derive")]
+use std::{marker::PhantomData, result};
+
 use process::Command;
+#[cfg(feature = "dkim")]
+use secret::Secret;
+
+use crate::AnyResult;
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -9,7 +17,36 @@
 pub struct MessageSendConfig {
     /// Should save a copy to the sent folder of the message being
     /// sent.
-    pub save_copy: Option<bool>,
+    ///
+    /// Accepts a boolean or a [`SaveCopyKind`]. Defaults to
+    /// [`SaveCopyKind::Never`].
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, deserialize_with = "some_bool_or_kind")
+    )]
+    pub save_copy: Option<SaveCopyKind>,
+
+    /// Should the backend save the copy of sent messages by itself.
+    ///
+    /// When enabled, each message successfully sent via the backend
+    /// is appended to the sent folder with the seen flag, following
+    /// the [`save_copy`](Self::save_copy) behaviour. Defaults to
+    /// `false`, which means that a copy is only saved via
+    /// [`SendMessageThenSaveCopy`](super::SendMessageThenSaveCopy).
+    pub auto_save_copy: Option<bool>,
+
+    /// The recipients automatically added to the `Cc` header of
+    /// messages being sent.
+    ///
+    /// Recipients already present in the message are not added
+    /// twice. See
+    /// [`add_auto_recipients`](super::recipients::add_auto_recipients).
+    pub auto_cc: Option<Vec<String>>,
+
+    /// The recipients automatically added to the `Bcc` header of
+    /// messages being sent, for example the address of the account
+    /// itself or a compliance archive address.
+    pub auto_bcc: Option<Vec<String>>,
 
     /// The hook called just before sending a message.
     ///
@@ -17,4 +54,432 @@ pub struct MessageSendConfig {
     /// (stdin) and returns the modified raw message to the standard
     /// output (stdout).
     pub pre_hook: Option<Command>,
+
+    /// The chain of hooks called just before sending a message.
+    ///
+    /// Hooks are called in order, after the [`pre_hook`] if any:
+    /// each hook takes the raw message returned by the previous one.
+    /// See [`run_pre_send_hooks`](super::hook::run_pre_send_hooks).
+    ///
+    /// [`pre_hook`]: MessageSendConfig::pre_hook
+    pub pre_hooks: Option<Vec<PreSendHook>>,
+
+    /// Should normalize line endings of the message being sent to
+    /// CRLF.
+    ///
+    /// SMTP requires CRLF line endings, some servers reject or mangle
+    /// messages using bare LF. Defaults to `true`.
+    pub normalize_line_endings: Option<bool>,
+
+    /// The folder watched for queued messages.
+    ///
+    /// Messages appended to this folder by any client are sent, then
+    /// moved to the sent folder. Messages flagged as drafts are
+    /// only sent once the draft flag is removed. See
+    /// [`SendOutboxMessages`](super::outbox::SendOutboxMessages).
+    pub outbox: Option<String>,
+
+    /// The offline outbox queue configuration.
+    ///
+    /// When defined, messages that cannot be sent because the
+    /// transport is unreachable are queued on disk, then retried
+    /// later. Messages scheduled to be sent later are stored in the
+    /// same queue. See [`OutboxQueue`](super::queue::OutboxQueue).
+    pub queue: Option<OutboxQueueConfig>,
+
+    /// The rules choosing a transport per message.
+    ///
+    /// Rules are evaluated in order, the first matching rule gives
+    /// the transport used to send the message. Messages not matching
+    /// any rule are sent using the default transport. See
+    /// [`SendMessageRouter`](super::route::SendMessageRouter).
+    pub routes: Option<Vec<SendRouteConfig>>,
+
+    /// The name of the transport used when the transport chosen for
+    /// a message cannot be reached.
+    ///
+    /// The fallback transport is only used for transient errors,
+    /// like connection failures or timeouts, once the chosen
+    /// transport gave up retrying. See
+    /// [`SendMessageRouter`](super::route::SendMessageRouter).
+    pub fallback: Option<String>,
+
+    /// The outbound rate limit configuration.
+    ///
+    /// When defined, messages are sent at a pace that does not trip
+    /// the sending limits of the provider, which matters when
+    /// flushing a large outbox. See
+    /// [`SendRateLimiter`](super::rate_limit::SendRateLimiter).
+    pub rate_limit: Option<SendRateLimitConfig>,
+
+    /// The DKIM signature configuration.
+    ///
+    /// When defined, messages are signed right before being sent
+    /// via SMTP or sendmail.
+    #[cfg(feature = "dkim")]
+    pub dkim: Option<DkimConfig>,
+}
+
+/// The rule choosing a transport for a message.
+///
+/// All the conditions defined by the rule need to match for the rule
+/// to match. Patterns are case-insensitive globs, where `*` matches
+/// any sequence of characters and `?` matches a single character.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SendRouteConfig {
+    /// The name of the transport used when the rule matches.
+    pub transport: String,
+
+    /// The pattern all the recipients of the message need to match,
+    /// for example `*@internal.corp`.
+    ///
+    /// Messages having at least one recipient not matching the
+    /// pattern do not match the rule.
+    pub recipients: Option<String>,
+
+    /// The header the message needs to contain.
+    pub header: Option<SendRouteHeaderConfig>,
+}
+
+/// The header condition of a [`SendRouteConfig`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SendRouteHeaderConfig {
+    /// The name of the header, case-insensitive.
+    pub name: String,
+
+    /// The pattern the value of the header needs to match.
+    pub value: String,
+}
+
+/// The outbound rate limit configuration.
+///
+/// Limits are enforced per account.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SendRateLimitConfig {
+    /// The maximum number of messages sent per minute.
+    ///
+    /// Unlimited by default.
+    pub per_minute: Option<u32>,
+
+    /// The maximum number of messages sent in a row before the rate
+    /// applies.
+    ///
+    /// Defaults to 1, which spreads messages evenly over the minute.
+    pub burst: Option<u32>,
+
+    /// The maximum number of messages being sent at the same time.
+    ///
+    /// Unlimited by default.
+    pub concurrency: Option<usize>,
+}
+
+/// The pre-send hook configuration.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct PreSendHook {
+    /// The name of the hook, used to identify it in send results.
+    ///
+    /// Defaults to the position of the hook in the chain.
+    pub name: Option<String>,
+
+    /// Execute the shell command.
+    ///
+    /// The command should take a raw message as standard input
+    /// (stdin) and returns the modified raw message to the standard
+    /// output (stdout).
+    pub cmd: Option<Command>,
+
+    /// Execute the given pre-send function.
+    ///
+    /// The pre-send function cannot be de/serialized. The function
+    /// should take a raw message and return the modified raw
+    /// message. It is called after the command, if any.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub callback: Option<PreSendHookFn>,
+
+    /// What to do when the hook fails.
+    ///
+    /// Defaults to [`PreSendHookPolicy::Continue`].
+    pub on_error: Option<PreSendHookPolicy>,
+}
+
+impl Eq for PreSendHook {
+    //
+}
+
+impl PartialEq for PreSendHook {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.cmd == other.cmd && self.on_error == other.on_error
+    }
+}
+
+/// Pre-send function.
+///
+/// This is just a wrapper around a function that takes a raw message
+/// and returns the modified raw message.
+#[derive(Clone)]
+pub struct PreSendHookFn(
+    #[allow(clippy::type_complexity)]
+    Arc<dyn Fn(Vec<u8>) -> Pin<Box<dyn Future<Output = AnyResult<Vec<u8>>> + Send>> + Send + Sync>,
+);
+
+impl PreSendHookFn {
+    /// Create a new pre-send function.
+    pub fn new<F: Future<Output = AnyResult<Vec<u8>>> + Send + 'static>(
+        f: impl Fn(Vec<u8>) -> F + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(move |msg| Box::pin(f(msg))))
+    }
+}
+
+impl Deref for PreSendHookFn {
+    type Target = Arc<
+        dyn Fn(Vec<u8>) -> Pin<Box<dyn Future<Output = AnyResult<Vec<u8>>> + Send>> + Send + Sync,
+    >;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for PreSendHookFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PreSendHookFn()")
+    }
+}
+
+/// The policy applied when a pre-send hook fails.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum PreSendHookPolicy {
+    /// Abort the sending of the message.
+    Abort,
+
+    /// Continue with the next hook, using the message as it was
+    /// before the failing hook.
+    #[default]
+    Continue,
+}
+
+/// The offline outbox queue configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct OutboxQueueConfig {
+    /// The directory where queued messages are stored.
+    pub dir: PathBuf,
+
+    /// The delay in seconds before the first retry.
+    ///
+    /// The delay doubles after each failed attempt. Defaults to 30
+    /// seconds.
+    pub retry_delay: Option<u64>,
+
+    /// The maximum delay in seconds between two retries.
+    ///
+    /// Defaults to one hour.
+    pub max_retry_delay: Option<u64>,
+
+    /// The maximum number of attempts before giving up.
+    ///
+    /// Messages are retried forever if not defined.
+    pub max_attempts: Option<u32>,
+
+    /// The grace period in seconds during which sending a message
+    /// can be undone.
+    ///
+    /// See [`OutboxQueue::send_with_undo`](super::queue::OutboxQueue::send_with_undo).
+    /// Defaults to 0 (no grace period).
+    pub undo_delay: Option<u64>,
+}
+
+/// The DKIM signature configuration.
+///
+/// The public key matching the private key must be published in DNS
+/// as a TXT record at `<selector>._domainkey.<domain>`.
+#[cfg(feature = "dkim")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct DkimConfig {
+    /// The signing domain (the `d=` tag).
+    pub domain: String,
+
+    /// The selector of the public key (the `s=` tag).
+    pub selector: String,
+
+    /// The PEM-encoded private key, either PKCS#1 or PKCS#8 for RSA
+    /// keys, PKCS#8 for Ed25519 keys.
+    pub private_key: Secret,
+
+    /// The signing algorithm. Defaults to [`DkimAlgorithm::RsaSha256`].
+    pub algorithm: Option<DkimAlgorithm>,
+
+    /// The headers to sign, in addition to the `From` header which
+    /// is always signed.
+    ///
+    /// Defaults to [`DEFAULT_DKIM_HEADERS`](super::dkim::DEFAULT_DKIM_HEADERS).
+    pub headers: Option<Vec<String>>,
+}
+
+/// The DKIM signing algorithm.
+#[cfg(feature = "dkim")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum DkimAlgorithm {
+    /// RSA with SHA-256, supported by all verifiers.
+    #[default]
+    RsaSha256,
+
+    /// Ed25519 with SHA-256, see RFC 8463. Not supported by all
+    /// verifiers yet.
+    Ed25519Sha256,
+}
+
+/// The sent message copy behaviour.
+///
+/// Some providers (like Gmail or Outlook) automatically save a copy
+/// of messages sent via SMTP in the Sent folder. Saving another copy
+/// on the client side leads to duplicates.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum SaveCopyKind {
+    /// Always save a copy of sent messages.
+    Always,
+
+    /// Never save a copy of sent messages.
+    #[default]
+    Never,
+
+    /// Save a copy of sent messages, unless the provider is known to
+    /// already do it.
+    Auto,
+}
+
+impl SaveCopyKind {
+    /// Return `true` if the current kind matches
+    /// [`SaveCopyKind::Always`].
+    pub fn is_always(&self) -> bool {
+        matches!(self, Self::Always)
+    }
+
+    /// Return `true` if the current kind matches
+    /// [`SaveCopyKind::Never`].
+    pub fn is_never(&self) -> bool {
+        matches!(self, Self::Never)
+    }
+
+    /// Return `true` if the current kind matches
+    /// [`SaveCopyKind::Auto`].
+    pub fn is_auto(&self) -> bool {
+        matches!(self, Self::Auto)
+    }
+}
+
+impl fmt::Display for SaveCopyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Always => write!(f, "always"),
+            Self::Never => write!(f, "never"),
+            Self::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl From<bool> for SaveCopyKind {
+    fn from(value: bool) -> Self {
+        if value {
+            Self::Always
+        } else {
+            Self::Never
+        }
+    }
+}
+
+#[cfg(feature = "derive")]
+fn some_bool_or_kind<'de, D>(deserializer: D) -> result::Result<Option<SaveCopyKind>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct SomeBoolOrKind(PhantomData<fn() -> Option<SaveCopyKind>>);
+
+    impl<'de> serde::de::Visitor<'de> for SomeBoolOrKind {
+        type Value = Option<SaveCopyKind>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("some or none")
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> result::Result<Self::Value, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct BoolOrKind(PhantomData<fn() -> SaveCopyKind>);
+
+            impl<'de> serde::de::Visitor<'de> for BoolOrKind {
+                type Value = SaveCopyKind;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("boolean or string")
+                }
+
+                fn visit_bool<E>(self, v: bool) -> result::Result<Self::Value, E>
+                where
+                    E: serde::de::Error,
+                {
+                    Ok(v.into())
+                }
+
+                fn visit_str<E>(self, v: &str) -> result::Result<Self::Value, E>
+                where
+                    E: serde::de::Error,
+                {
+                    serde::Deserialize::deserialize(serde::de::value::StrDeserializer::new(v))
+                }
+            }
+
+            deserializer
+                .deserialize_any(BoolOrKind(PhantomData))
+                .map(Option::Some)
+        }
+    }
+
+    deserializer.deserialize_option(SomeBoolOrKind(PhantomData))
 }
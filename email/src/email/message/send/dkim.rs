@@ -0,0 +1,121 @@
+//! # DKIM
+//!
+//! Module dedicated to the DKIM signature of messages being sent.
+//! Self-hosted setups relaying messages directly to recipients'
+//! servers need it, otherwise their messages are likely to be
+//! considered as spam.
+//!
+//! https://www.rfc-editor.org/rfc/rfc6376.html
+
+use std::borrow::Cow;
+
+use mail_auth::{
+    common::{
+        crypto::{Ed25519Key, RsaKey, Sha256},
+        headers::HeaderWriter,
+    },
+    dkim::DkimSigner,
+};
+use rustls_pemfile::private_key;
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+
+use super::config::{DkimAlgorithm, DkimConfig};
+use crate::{
+    account::config::AccountConfig,
+    debug,
+    email::error::{Error, Result},
+};
+
+/// The headers signed by default, when they are present.
+pub const DEFAULT_DKIM_HEADERS: [&str; 11] = [
+    "From",
+    "Sender",
+    "Reply-To",
+    "To",
+    "Cc",
+    "Subject",
+    "Date",
+    "Message-ID",
+    "In-Reply-To",
+    "References",
+    "MIME-Version",
+];
+
+/// Sign the given raw message, if the account has a DKIM
+/// configuration.
+///
+/// This should be the last step before transmission, see
+/// [`DkimConfig::sign`].
+pub async fn sign_message<'a>(config: &AccountConfig, msg: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+    match config.find_message_send_dkim() {
+        Some(dkim) => Ok(Cow::Owned(dkim.sign(msg.as_ref()).await?)),
+        None => Ok(msg),
+    }
+}
+
+impl DkimConfig {
+    /// Sign the given raw message.
+    ///
+    /// The returned message is the given one prefixed by the
+    /// `DKIM-Signature` header. It should not be modified anymore,
+    /// otherwise the signature would not be valid.
+    pub async fn sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        debug!(
+            "signing message using DKIM key {}._domainkey.{}",
+            self.selector, self.domain
+        );
+
+        let pem = self
+            .private_key
+            .get()
+            .await
+            .map_err(Error::GetDkimPrivateKeyError)?;
+
+        let key = private_key(&mut pem.as_bytes())
+            .map_err(Error::ReadDkimPrivateKeyError)?
+            .ok_or(Error::DkimPrivateKeyNotFoundError)?;
+
+        let mut headers = vec![String::from("From")];
+
+        match &self.headers {
+            Some(extra) => headers.extend(extra.iter().cloned()),
+            None => headers.extend(DEFAULT_DKIM_HEADERS.into_iter().skip(1).map(String::from)),
+        };
+
+        let signature = match (self.algorithm.clone().unwrap_or_default(), &key) {
+            (DkimAlgorithm::RsaSha256, PrivateKeyDer::Pkcs1(key)) => {
+                let key = RsaKey::<Sha256>::from_der(key.secret_pkcs1_der())
+                    .map_err(Error::ParseDkimPrivateKeyError)?;
+                self.signer(key, headers).sign(msg)
+            }
+            (DkimAlgorithm::RsaSha256, PrivateKeyDer::Pkcs8(key)) => {
+                let key = RsaKey::<Sha256>::from_pkcs8_der(key.secret_pkcs8_der())
+                    .map_err(Error::ParseDkimPrivateKeyError)?;
+                self.signer(key, headers).sign(msg)
+            }
+            (DkimAlgorithm::Ed25519Sha256, PrivateKeyDer::Pkcs8(key)) => {
+                let key = Ed25519Key::from_pkcs8_maybe_unchecked_der(key.secret_pkcs8_der())
+                    .map_err(Error::ParseDkimPrivateKeyError)?;
+                self.signer(key, headers).sign(msg)
+            }
+            _ => return Err(Error::UnsupportedDkimPrivateKeyError),
+        }
+        .map_err(Error::SignDkimError)?;
+
+        let mut signed = signature.to_header().into_bytes();
+        signed.extend_from_slice(msg);
+
+        Ok(signed)
+    }
+
+    fn signer<K: mail_auth::common::crypto::SigningKey>(
+        &self,
+        key: K,
+        headers: Vec<String>,
+    ) -> DkimSigner<K, mail_auth::dkim::Done> {
+        DkimSigner::from_key(key)
+            .domain(&self.domain)
+            .selector(&self.selector)
+            .headers(headers)
+    }
+}
@@ -0,0 +1,219 @@
+//! Module dedicated to pre-send hooks.
+//!
+//! Accounts can define a chain of hooks called just before sending
+//! a message (see [`PreSendHook`]), for example to sign, encrypt or
+//! lint it. Each hook takes the raw message returned by the previous
+//! one. A failing hook either aborts the sending, or is skipped
+//! depending on its [`PreSendHookPolicy`]. What happened to each hook
+//! is surfaced in the [`SendReport`](super::SendReport).
+
+use std::borrow::Cow;
+
+use super::config::{PreSendHook, PreSendHookPolicy};
+use crate::{account::config::AccountConfig, debug, email::error::Error, AnyResult};
+
+/// The name of the legacy single pre-send hook.
+pub const LEGACY_PRE_SEND_HOOK: &str = "pre-hook";
+
+/// The result of a pre-send hook.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PreSendHookResult {
+    /// The name of the hook.
+    pub name: String,
+
+    /// What happened to the hook.
+    pub status: PreSendHookStatus,
+}
+
+/// The status of a pre-send hook.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PreSendHookStatus {
+    /// The hook succeeded, the message has been replaced by its
+    /// output.
+    Applied,
+
+    /// The hook failed with the given error, and has been skipped as
+    /// allowed by [`PreSendHookPolicy::Continue`].
+    Skipped(String),
+}
+
+impl PreSendHookResult {
+    /// Return `true` if the hook failed.
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.status, PreSendHookStatus::Skipped(_))
+    }
+}
+
+/// Pipe the given raw message into the pre-send hooks of the given
+/// account.
+///
+/// The legacy [`pre_hook`] runs first, and is skipped on error. The
+/// chain of [`pre_hooks`] runs next, in order. This function returns
+/// an error as soon as a hook using [`PreSendHookPolicy::Abort`]
+/// fails, otherwise it returns the final message alongside the
+/// result of each hook.
+///
+/// [`pre_hook`]: super::config::MessageSendConfig::pre_hook
+/// [`pre_hooks`]: super::config::MessageSendConfig::pre_hooks
+pub async fn run_pre_send_hooks<'a>(
+    config: &AccountConfig,
+    msg: &'a [u8],
+) -> Result<(Cow<'a, [u8]>, Vec<PreSendHookResult>), Error> {
+    let mut msg = Cow::Borrowed(msg);
+    let mut results = Vec::new();
+
+    if let Some(cmd) = config.find_message_pre_send_hook() {
+        let hook = PreSendHook {
+            name: Some(LEGACY_PRE_SEND_HOOK.to_owned()),
+            cmd: Some(cmd.clone()),
+            ..Default::default()
+        };
+
+        results.push(run_pre_send_hook(&hook, LEGACY_PRE_SEND_HOOK, &mut msg).await?);
+    }
+
+    for (i, hook) in config.get_message_pre_send_hooks().iter().enumerate() {
+        let name = match hook.name.as_ref() {
+            Some(name) => name.clone(),
+            None => format!("#{}", i + 1),
+        };
+
+        results.push(run_pre_send_hook(hook, &name, &mut msg).await?);
+    }
+
+    Ok((msg, results))
+}
+
+/// Run the given hook, then replace the given message by its output
+/// if it succeeded.
+async fn run_pre_send_hook(
+    hook: &PreSendHook,
+    name: &str,
+    msg: &mut Cow<'_, [u8]>,
+) -> Result<PreSendHookResult, Error> {
+    let status = match pipe(hook, msg.to_vec()).await {
+        Ok(output) => {
+            debug!("pre-send hook {name} applied");
+            *msg = Cow::Owned(output);
+            PreSendHookStatus::Applied
+        }
+        Err(err) if hook.on_error.unwrap_or_default() == PreSendHookPolicy::Abort => {
+            return Err(Error::RunPreSendHookError(name.to_owned(), err));
+        }
+        Err(err) => {
+            debug!("cannot execute pre-send hook {name}, skipping it: {err}");
+            debug!("{err:?}");
+            PreSendHookStatus::Skipped(err.to_string())
+        }
+    };
+
+    Ok(PreSendHookResult {
+        name: name.to_owned(),
+        status,
+    })
+}
+
+/// Pipe the given raw message into the command then the callback of
+/// the given hook.
+async fn pipe(hook: &PreSendHook, mut msg: Vec<u8>) -> AnyResult<Vec<u8>> {
+    if let Some(cmd) = hook.cmd.as_ref() {
+        let output = cmd
+            .run_with(&msg)
+            .await
+            .map_err(Error::ExecutePreSendHookCommandError)?;
+        msg = output.into();
+    }
+
+    if let Some(callback) = hook.callback.as_ref() {
+        msg = callback(msg).await?;
+    }
+
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_pre_send_hooks, PreSendHookStatus};
+    use crate::{
+        account::config::AccountConfig,
+        email::error::Error,
+        message::{
+            config::MessageConfig,
+            send::config::{MessageSendConfig, PreSendHook, PreSendHookFn, PreSendHookPolicy},
+        },
+        AnyBoxedError,
+    };
+
+    fn append(suffix: &'static str) -> PreSendHookFn {
+        PreSendHookFn::new(move |mut msg: Vec<u8>| async move {
+            msg.extend_from_slice(suffix.as_bytes());
+            Ok(msg)
+        })
+    }
+
+    fn fail() -> PreSendHookFn {
+        PreSendHookFn::new(|_| async { Err(AnyBoxedError::from(Error::ParseEmailEmptyRawError)) })
+    }
+
+    fn config(hooks: Vec<PreSendHook>) -> AccountConfig {
+        AccountConfig {
+            message: Some(MessageConfig {
+                send: Some(MessageSendConfig {
+                    pre_hooks: Some(hooks),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn chained_hooks() {
+        let config = config(vec![
+            PreSendHook {
+                name: Some("lint".into()),
+                callback: Some(append(" linted")),
+                ..Default::default()
+            },
+            PreSendHook {
+                callback: Some(fail()),
+                on_error: Some(PreSendHookPolicy::Continue),
+                ..Default::default()
+            },
+            PreSendHook {
+                callback: Some(append(" signed")),
+                ..Default::default()
+            },
+        ]);
+
+        let (msg, results) = run_pre_send_hooks(&config, b"Hello!").await.unwrap();
+        assert_eq!(msg.as_ref(), b"Hello! linted signed");
+
+        let names: Vec<_> = results.iter().map(|res| res.name.as_str()).collect();
+        assert_eq!(names, ["lint", "#2", "#3"]);
+
+        assert_eq!(results[0].status, PreSendHookStatus::Applied);
+        assert!(results[1].is_skipped());
+        assert_eq!(results[2].status, PreSendHookStatus::Applied);
+    }
+
+    #[tokio::test]
+    async fn aborting_hook() {
+        let config = config(vec![
+            PreSendHook {
+                callback: Some(append(" linted")),
+                ..Default::default()
+            },
+            PreSendHook {
+                name: Some("encrypt".into()),
+                callback: Some(fail()),
+                on_error: Some(PreSendHookPolicy::Abort),
+                ..Default::default()
+            },
+        ]);
+
+        let err = run_pre_send_hooks(&config, b"Hello!").await.unwrap_err();
+        assert!(err.to_string().contains("encrypt"));
+    }
+}
@@ -0,0 +1,32 @@
+use async_trait::async_trait;
+
+use super::{SendMessage, SentMessage};
+use crate::{info, lmtp::LmtpContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct SendLmtpMessage {
+    ctx: LmtpContextSync,
+}
+
+impl SendLmtpMessage {
+    pub fn new(ctx: &LmtpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &LmtpContextSync) -> Box<dyn SendMessage> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &LmtpContextSync) -> Option<Box<dyn SendMessage>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SendMessage for SendLmtpMessage {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
+        info!("sending lmtp message");
+        let sent = self.ctx.send(msg).await?;
+        Ok(sent)
+    }
+}
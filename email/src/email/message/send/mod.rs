@@ -1,32 +1,215 @@
 pub mod config;
+#[cfg(feature = "dkim")]
+pub mod dkim;
+pub mod envelope;
+pub mod hook;
+#[cfg(feature = "lmtp")]
+pub mod lmtp;
+#[cfg(feature = "watch")]
+pub mod outbox;
+pub mod queue;
+pub mod rate_limit;
+pub mod recipients;
+pub mod report;
+pub mod route;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "smtp")]
 pub mod smtp;
 
+use std::fmt;
+
 use async_trait::async_trait;
+use mail_parser::MessageParser;
 
+#[doc(inline)]
+pub use self::report::{SendMessageReport, SendReport, SentMessage};
+use self::{
+    config::SaveCopyKind, envelope::SendEnvelope, recipients::add_auto_recipients,
+    report::SaveCopyDecision,
+};
 use super::add::AddMessage;
-use crate::{account::config::HasAccountConfig, flag::Flag, folder::SENT, AnyResult};
+use crate::{account::config::HasAccountConfig, debug, flag::Flag, folder::SENT, AnyResult};
+
+/// Email domains of providers known to automatically save a copy of
+/// messages sent via SMTP in their Sent folder.
+pub const AUTO_SAVING_SENT_DOMAINS: [&str; 5] = [
+    "gmail.com",
+    "googlemail.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+];
+
+/// Return `true` if the provider of the given email address is known
+/// to automatically save a copy of sent messages.
+pub fn is_sent_message_saved_by_provider(email: &str) -> bool {
+    match email.trim().rsplit_once('@') {
+        Some((_, domain)) => AUTO_SAVING_SENT_DOMAINS
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(domain)),
+        None => false,
+    }
+}
+
+/// The options of the [`SendMessage`] feature.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SendMessageOptions {
+    /// The delivery status notifications to request.
+    ///
+    /// Only honoured by backends supporting them, like SMTP when the
+    /// server advertises the DSN extension.
+    pub dsn: Option<DsnOptions>,
+
+    /// Should add the automatic recipients of the account.
+    ///
+    /// Allows to override the account configuration for a single
+    /// message. Defaults to `true`, see
+    /// [`add_auto_recipients`](recipients::add_auto_recipients).
+    pub auto_recipients: Option<bool>,
+}
+
+/// The delivery status notification (DSN) options.
+///
+/// See [RFC 3461](https://www.rfc-editor.org/rfc/rfc3461).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DsnOptions {
+    /// The conditions under which notifications should be sent
+    /// (`NOTIFY` parameter).
+    ///
+    /// Defaults to the server conditions when empty.
+    pub notify: Vec<DsnNotify>,
+
+    /// The part of the message to return in failure notifications
+    /// (`RET` parameter).
+    ///
+    /// Defaults to the server choice when `None`.
+    pub ret: Option<DsnReturn>,
+}
+
+/// The condition under which a delivery status notification should
+/// be sent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DsnNotify {
+    Never,
+    Success,
+    Failure,
+    Delay,
+}
+
+impl fmt::Display for DsnNotify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Never => write!(f, "NEVER"),
+            Self::Success => write!(f, "SUCCESS"),
+            Self::Failure => write!(f, "FAILURE"),
+            Self::Delay => write!(f, "DELAY"),
+        }
+    }
+}
+
+/// The part of the message returned in failure notifications.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DsnReturn {
+    /// Only return the headers of the message.
+    Headers,
+
+    /// Return the full message.
+    Full,
+}
+
+impl fmt::Display for DsnReturn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Headers => write!(f, "HDRS"),
+            Self::Full => write!(f, "FULL"),
+        }
+    }
+}
 
 #[async_trait]
 pub trait SendMessage: Send + Sync {
-    /// Send the given raw email message.
-    async fn send_message(&self, msg: &[u8]) -> AnyResult<()>;
+    /// Send the given raw email message, then return the reply of
+    /// the server.
+    ///
+    /// Transports that do not expose the reply of the server return
+    /// an empty [`SentMessage`].
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage>;
+
+    /// Send the given raw email message using the given options,
+    /// then return which recipients accepted it.
+    ///
+    /// Backends that do not support options send the message as
+    /// [`SendMessage::send_message`] does. Backends that cannot
+    /// report recipients individually consider all of them as
+    /// accepted.
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        _opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        let sent = self.send_message(msg).await?;
+
+        let msg = MessageParser::new().parse(msg).unwrap_or_default();
+        let SendEnvelope { rcpt_to, .. } = SendEnvelope::from(&msg);
+
+        Ok(SendReport {
+            accepted: rcpt_to.into_iter().collect(),
+            rejected: Vec::new(),
+            pre_hooks: Vec::new(),
+            sent,
+        })
+    }
 }
 
 #[async_trait]
 pub trait SendMessageThenSaveCopy: HasAccountConfig + AddMessage + SendMessage {
     /// Send the given raw email message, then save a copy to the Sent
     /// folder.
-    async fn send_message_then_save_copy(&self, msg: &[u8]) -> AnyResult<()> {
-        self.send_message(msg).await?;
+    ///
+    /// Which recipients accepted the message, and whether a copy has
+    /// been saved or not are surfaced in the returned report.
+    async fn send_message_then_save_copy(&self, msg: &[u8]) -> AnyResult<SendMessageReport> {
+        self.send_message_then_save_copy_with_options(msg, &Default::default())
+            .await
+    }
 
-        if self.account_config().should_save_copy_sent_message() {
-            self.add_message_with_flag(SENT, msg, Flag::Seen).await?;
-        }
+    /// Send the given raw email message using the given options, then
+    /// save a copy to the Sent folder.
+    ///
+    /// The automatic recipients of the account are added before
+    /// sending, so that the saved copy reflects them.
+    async fn send_message_then_save_copy_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendMessageReport> {
+        let config = self.account_config();
+        let msg = add_auto_recipients(config, msg, opts);
+        let msg = msg.as_ref();
+
+        let recipients = self.send_message_with_options(msg, opts).await?;
+
+        let save_copy = match config.get_message_send_save_copy() {
+            _ if config.should_auto_save_copy_sent_message() => {
+                debug!("sender saves sent messages by itself, skipping copy");
+                SaveCopyDecision::SavedBySender
+            }
+            SaveCopyKind::Never => SaveCopyDecision::Disabled,
+            SaveCopyKind::Auto if is_sent_message_saved_by_provider(&config.email) => {
+                debug!("provider saves sent messages by itself, skipping copy");
+                SaveCopyDecision::SavedByProvider
+            }
+            SaveCopyKind::Always | SaveCopyKind::Auto => {
+                let id = self.add_message_with_flag(SENT, msg, Flag::Seen).await?;
+                SaveCopyDecision::Saved(SENT.to_owned(), id)
+            }
+        };
 
-        Ok(())
+        Ok(SendMessageReport {
+            recipients,
+            save_copy,
+        })
     }
 }
 
@@ -0,0 +1,156 @@
+//! # Outbox
+//!
+//! Module dedicated to queue-based sending. Messages appended to the
+//! outbox folder by any client sharing the mailbox are detected by
+//! the watch subsystem, sent, then moved to the sent folder.
+
+use async_trait::async_trait;
+use tokio::sync::{
+    mpsc,
+    oneshot::{Receiver, Sender},
+};
+
+use super::SendMessage;
+use crate::{
+    account::config::HasAccountConfig,
+    debug,
+    email::error::Error,
+    envelope::{
+        watch::{WatchEnvelopes, WatchEvent},
+        Envelope, Id,
+    },
+    flag::Flag,
+    info,
+    message::{peek::PeekMessages, r#move::MoveMessages},
+    AnyResult,
+};
+
+/// Return the envelope of the message ready to be sent from the
+/// outbox, if the given watch event denotes one.
+///
+/// Messages are ready when they are appended without the draft
+/// flag, or when their draft flag is removed.
+pub fn is_ready_to_send(event: &WatchEvent) -> Option<&Envelope> {
+    match event {
+        WatchEvent::Received(envelope) if !envelope.flags.contains(&Flag::Draft) => Some(envelope),
+        WatchEvent::FlagsChanged {
+            prev_flags,
+            envelope,
+        } if prev_flags.contains(&Flag::Draft) && !envelope.flags.contains(&Flag::Draft) => {
+            Some(envelope)
+        }
+        _ => None,
+    }
+}
+
+#[async_trait]
+pub trait SendOutboxMessages:
+    HasAccountConfig + WatchEnvelopes + PeekMessages + SendMessage + MoveMessages
+{
+    /// Send the message matching the given id from the given outbox
+    /// folder, then move it to the sent folder.
+    async fn send_outbox_message(&self, outbox: &str, id: &str) -> AnyResult<()> {
+        let id = Id::single(id);
+        let msgs = self.peek_messages(outbox, &id).await?;
+        let msg = msgs
+            .first()
+            .ok_or_else(|| Error::FindMessageError(id.to_string()))?;
+
+        self.send_message(msg.raw()?).await?;
+
+        let sent = self.account_config().get_sent_folder_alias();
+        self.move_messages(outbox, &sent, &id).await?;
+
+        Ok(())
+    }
+
+    /// Watch the configured outbox folder, and send messages as soon
+    /// as they are ready.
+    ///
+    /// Messages already present in the outbox when the watch starts
+    /// are not sent. Sending errors are logged without stopping the
+    /// watch, so that one invalid message does not block the queue.
+    async fn watch_outbox(
+        &self,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        let outbox = self
+            .account_config()
+            .find_message_send_outbox_folder_alias()
+            .ok_or(Error::OutboxNotConfiguredError)?;
+
+        info!("watching outbox folder {outbox} for queued messages");
+
+        let (events, mut events_rx) = mpsc::unbounded_channel();
+
+        let watch =
+            self.watch_envelope_events(&outbox, events, wait_for_shutdown_request, shutdown);
+
+        let send = async {
+            // the channel closes when the watch stops
+            while let Some(event) = events_rx.recv().await {
+                let Some(envelope) = is_ready_to_send(&event) else {
+                    continue;
+                };
+
+                debug!("sending queued message {} from outbox", envelope.id);
+
+                if let Err(_err) = self.send_outbox_message(&outbox, &envelope.id).await {
+                    debug!("cannot send queued message {}: {_err}", envelope.id);
+                    debug!("{_err:?}");
+                }
+            }
+        };
+
+        let (res, ()) = tokio::join!(watch, send);
+
+        res
+    }
+}
+
+impl<T: HasAccountConfig + WatchEnvelopes + PeekMessages + SendMessage + MoveMessages>
+    SendOutboxMessages for T
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_ready_to_send;
+    use crate::{
+        envelope::{watch::WatchEvent, Envelope},
+        flag::{Flag, Flags},
+    };
+
+    fn envelope(flags: impl IntoIterator<Item = Flag>) -> Envelope {
+        Envelope {
+            id: "1".into(),
+            flags: Flags::from_iter(flags),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ready_to_send() {
+        let event = WatchEvent::Received(envelope([]));
+        assert!(is_ready_to_send(&event).is_some());
+
+        let event = WatchEvent::Received(envelope([Flag::Draft]));
+        assert!(is_ready_to_send(&event).is_none());
+
+        let event = WatchEvent::FlagsChanged {
+            prev_flags: Flags::from_iter([Flag::Draft]),
+            envelope: envelope([Flag::Seen]),
+        };
+        assert!(is_ready_to_send(&event).is_some());
+
+        let event = WatchEvent::FlagsChanged {
+            prev_flags: Flags::default(),
+            envelope: envelope([Flag::Seen]),
+        };
+        assert!(is_ready_to_send(&event).is_none());
+
+        let event = WatchEvent::Expunged(envelope([]));
+        assert!(is_ready_to_send(&event).is_none());
+    }
+}
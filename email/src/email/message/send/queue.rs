@@ -0,0 +1,856 @@
+//! # Offline outbox queue
+//!
+//! Module dedicated to offline sending. When the transport cannot be
+//! reached, messages are stored in an on-disk queue instead of being
+//! lost. A flusher then retries to send them with an exponential
+//! backoff, and emits [`OutboxEvent`]s so that clients can notify
+//! users.
+//!
+//! The same queue is used to send messages later: scheduled messages
+//! are stored until their sending date, either given explicitly or
+//! taken from a `Date` header set in the future, then sent by the
+//! flusher.
+//!
+//! Sending can also be delayed by a grace period, during which it can
+//! be undone using the returned [`SendHandle`].
+//!
+//! Each queued message is stored in the queue directory as a raw
+//! `<id>.eml` file, alongside a `<id>.state` file holding its retry
+//! state.
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use mail_parser::MessageParser;
+use tokio::{fs, sync::oneshot::Receiver};
+
+use super::{config::OutboxQueueConfig, SendMessage};
+use crate::{
+    backend::kit::{classify_error, ErrorClass},
+    date::from_mail_parser_to_chrono_datetime,
+    debug,
+    email::error::{Error, Result},
+    info, runtime, trace, AnyResult,
+};
+
+/// The default delay before the first retry.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// The default maximum delay between two retries.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// The default interval between two flushes of the queue, used when
+/// the queue is empty.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The counter making message identifiers unique within a process.
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// The retry backoff of queued messages.
+///
+/// The delay doubles after each failed attempt, from the base delay
+/// up to the maximum delay.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutboxBackoff {
+    /// The delay before the first retry.
+    pub base: Duration,
+
+    /// The maximum delay between two retries.
+    pub max: Duration,
+
+    /// The maximum number of attempts before giving up.
+    ///
+    /// Messages are retried forever if not defined.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for OutboxBackoff {
+    fn default() -> Self {
+        Self {
+            base: DEFAULT_RETRY_DELAY,
+            max: DEFAULT_MAX_RETRY_DELAY,
+            max_attempts: None,
+        }
+    }
+}
+
+impl OutboxBackoff {
+    /// Return the delay before the next attempt, after the given
+    /// number of failed attempts.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+
+    /// Return `true` if messages should not be retried anymore after
+    /// the given number of failed attempts.
+    pub fn gives_up(&self, attempts: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+    }
+}
+
+impl From<&OutboxQueueConfig> for OutboxBackoff {
+    fn from(config: &OutboxQueueConfig) -> Self {
+        let default = Self::default();
+
+        Self {
+            base: config
+                .retry_delay
+                .map(Duration::from_secs)
+                .unwrap_or(default.base),
+            max: config
+                .max_retry_delay
+                .map(Duration::from_secs)
+                .unwrap_or(default.max),
+            max_attempts: config.max_attempts,
+        }
+    }
+}
+
+/// A message waiting in the queue.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedMessage {
+    /// The identifier of the message in the queue.
+    ///
+    /// Identifiers sort in the order messages were queued.
+    pub id: String,
+
+    /// The number of failed attempts.
+    pub attempts: u32,
+
+    /// The date of the next attempt.
+    pub next_attempt: DateTime<Utc>,
+
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+
+    /// The date the message is scheduled to be sent at, if any.
+    pub send_at: Option<DateTime<Utc>>,
+}
+
+impl QueuedMessage {
+    fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            id: format!("{nanos:032x}-{:x}-{count:x}", std::process::id()),
+            attempts: 0,
+            next_attempt: Utc::now(),
+            last_error: None,
+            send_at: None,
+        }
+    }
+
+    /// Parse the retry state of the message matching the given
+    /// identifier.
+    ///
+    /// Parsing is lenient: invalid lines are ignored.
+    fn parse_state(id: String, input: &str) -> Self {
+        let mut msg = Self {
+            id,
+            attempts: 0,
+            next_attempt: Utc::now(),
+            last_error: None,
+            send_at: None,
+        };
+
+        for line in input.lines() {
+            match line.split_once('=') {
+                Some(("attempts", attempts)) => {
+                    msg.attempts = attempts.trim().parse().unwrap_or_default();
+                }
+                Some(("next-attempt", date)) => {
+                    if let Ok(date) = DateTime::parse_from_rfc3339(date.trim()) {
+                        msg.next_attempt = date.with_timezone(&Utc);
+                    }
+                }
+                Some(("send-at", date)) => {
+                    if let Ok(date) = DateTime::parse_from_rfc3339(date.trim()) {
+                        msg.send_at = Some(date.with_timezone(&Utc));
+                    }
+                }
+                Some(("last-error", err)) => {
+                    msg.last_error = Some(err.to_owned());
+                }
+                _ => (),
+            }
+        }
+
+        msg
+    }
+
+    /// Return `true` if the message is due for a new attempt.
+    pub fn is_due(&self) -> bool {
+        self.next_attempt <= Utc::now()
+    }
+
+    fn state(&self) -> String {
+        let mut state = format!(
+            "attempts={}\nnext-attempt={}\n",
+            self.attempts,
+            self.next_attempt.to_rfc3339(),
+        );
+
+        if let Some(date) = &self.send_at {
+            state.push_str(&format!("send-at={}\n", date.to_rfc3339()));
+        }
+
+        if let Some(err) = &self.last_error {
+            let err = err.lines().collect::<Vec<_>>().join(" ");
+            state.push_str(&format!("last-error={err}\n"));
+        }
+
+        state
+    }
+}
+
+/// The outbox queue async event handler.
+pub type OutboxEventHandler =
+    dyn Fn(OutboxEvent) -> Pin<Box<dyn Future<Output = AnyResult<()>> + Send>> + Send + Sync;
+
+/// The outbox queue event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutboxEvent {
+    /// The transport could not be reached, so the message has been
+    /// queued.
+    Queued(QueuedMessage),
+
+    /// The message has been scheduled to be sent at
+    /// [`QueuedMessage::send_at`].
+    Scheduled(QueuedMessage),
+
+    /// The queued message has been sent, then removed from the
+    /// queue.
+    Sent(QueuedMessage),
+
+    /// The queued message could not be sent, it will be retried at
+    /// [`QueuedMessage::next_attempt`].
+    Failed(QueuedMessage),
+
+    /// The queued message could not be sent and reached the maximum
+    /// number of attempts. It stays in the queue until removed.
+    GaveUp(QueuedMessage),
+}
+
+impl OutboxEvent {
+    pub async fn emit(&self, handler: &Option<Arc<OutboxEventHandler>>) {
+        if let Some(handler) = handler.as_ref() {
+            if let Err(_err) = handler(self.clone()).await {
+                debug!("error while emitting outbox event: {_err}");
+                trace!("{_err:?}");
+            } else {
+                debug!("emitted outbox event {self:?}");
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutboxEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queued(msg) => write!(f, "Queued message {}", msg.id),
+            Self::Scheduled(msg) => match &msg.send_at {
+                Some(date) => write!(f, "Scheduled message {} at {date}", msg.id),
+                None => write!(f, "Scheduled message {}", msg.id),
+            },
+            Self::Sent(msg) => write!(f, "Sent queued message {}", msg.id),
+            Self::Failed(msg) => write!(
+                f,
+                "Cannot send queued message {} (attempt {}), retrying at {}",
+                msg.id, msg.attempts, msg.next_attempt
+            ),
+            Self::GaveUp(msg) => write!(
+                f,
+                "Cannot send queued message {} after {} attempts, giving up",
+                msg.id, msg.attempts
+            ),
+        }
+    }
+}
+
+/// The outcome of [`OutboxQueue::send_or_enqueue`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SendOutcome {
+    /// The message has been sent straight away.
+    Sent,
+
+    /// The transport could not be reached, so the message has been
+    /// queued.
+    Queued(QueuedMessage),
+
+    /// The message has been scheduled to be sent later.
+    Scheduled(QueuedMessage),
+}
+
+/// The handle of a message sent with a grace period.
+///
+/// See [`OutboxQueue::send_with_undo`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SendHandle {
+    queue: OutboxQueue,
+    msg: QueuedMessage,
+}
+
+impl SendHandle {
+    /// The identifier of the message in the queue.
+    pub fn id(&self) -> &str {
+        &self.msg.id
+    }
+
+    /// The date the message will be sent at, once the grace period
+    /// is over.
+    pub fn send_at(&self) -> DateTime<Utc> {
+        self.msg.next_attempt
+    }
+
+    /// Cancel the sending of the message, then remove it from the
+    /// queue.
+    ///
+    /// Returns `false` if it is too late: the message is being sent
+    /// or has already been sent.
+    pub async fn cancel(&self) -> Result<bool> {
+        let cancelled = self.queue.cancel(&self.msg.id).await?;
+
+        if cancelled {
+            info!("cancelled sending of message {}", self.msg.id);
+        }
+
+        Ok(cancelled)
+    }
+}
+
+/// The on-disk outbox queue.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutboxQueue {
+    dir: PathBuf,
+    backoff: OutboxBackoff,
+    undo_delay: Duration,
+}
+
+impl OutboxQueue {
+    /// Create a new queue stored in the given directory, using the
+    /// default backoff and no grace period.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            backoff: Default::default(),
+            undo_delay: Duration::ZERO,
+        }
+    }
+
+    /// Create a new queue from the given configuration.
+    pub fn from_config(config: &OutboxQueueConfig) -> Self {
+        let undo_delay = config.undo_delay.map(Duration::from_secs);
+
+        Self::new(&config.dir)
+            .with_backoff(config.into())
+            .with_undo_delay(undo_delay.unwrap_or_default())
+    }
+
+    pub fn with_backoff(mut self, backoff: OutboxBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the grace period during which sending a message can be
+    /// undone, see [`OutboxQueue::send_with_undo`].
+    pub fn with_undo_delay(mut self, delay: Duration) -> Self {
+        self.undo_delay = delay;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn msg_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.eml"))
+    }
+
+    fn state_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.state"))
+    }
+
+    fn sending_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.sending"))
+    }
+
+    async fn write_state(&self, msg: &QueuedMessage) -> Result<()> {
+        let path = self.state_path(&msg.id);
+        write_atomically(&path, msg.state().as_bytes()).await
+    }
+
+    /// Store the given raw message in the queue.
+    pub async fn enqueue(&self, raw: &[u8]) -> Result<QueuedMessage> {
+        self.store(raw, QueuedMessage::new()).await
+    }
+
+    /// Store the given raw message in the queue, to be sent at the
+    /// given date.
+    pub async fn schedule(&self, raw: &[u8], send_at: DateTime<Utc>) -> Result<QueuedMessage> {
+        let mut msg = QueuedMessage::new();
+        msg.next_attempt = send_at;
+        msg.send_at = Some(send_at);
+        self.store(raw, msg).await
+    }
+
+    async fn store(&self, raw: &[u8], msg: QueuedMessage) -> Result<QueuedMessage> {
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|err| Error::WriteOutboxQueueError(err, self.dir.clone()))?;
+
+        // the state is written first, so that listing the queue never
+        // finds a message without state
+        self.write_state(&msg).await?;
+        write_atomically(&self.msg_path(&msg.id), raw).await?;
+
+        info!("queued message {} in outbox", msg.id);
+
+        Ok(msg)
+    }
+
+    /// List the messages of the queue, in the order they were queued.
+    pub async fn list(&self) -> Result<Vec<QueuedMessage>> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(Error::ReadOutboxQueueError(err, self.dir.clone())),
+        };
+
+        let mut msgs = Vec::new();
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| Error::ReadOutboxQueueError(err, self.dir.clone()))?
+        {
+            let path = entry.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some("eml") {
+                continue;
+            }
+
+            let Some(id) = path.file_stem().and_then(|id| id.to_str()) else {
+                continue;
+            };
+
+            let state = match fs::read_to_string(self.state_path(id)).await {
+                Ok(state) => state,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(err) => return Err(Error::ReadOutboxQueueError(err, self.state_path(id))),
+            };
+
+            msgs.push(QueuedMessage::parse_state(id.to_owned(), &state));
+        }
+
+        msgs.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(msgs)
+    }
+
+    /// Read the raw message matching the given identifier.
+    pub async fn read(&self, id: &str) -> Result<Vec<u8>> {
+        let path = self.msg_path(id);
+        fs::read(&path)
+            .await
+            .map_err(|err| Error::ReadOutboxQueueError(err, path))
+    }
+
+    /// Remove the message matching the given identifier from the
+    /// queue.
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        for path in [self.msg_path(id), self.state_path(id)] {
+            match fs::remove_file(&path).await {
+                Ok(()) => (),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(Error::WriteOutboxQueueError(err, path)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the given raw message, or store it in the queue if the
+    /// transport cannot be reached.
+    ///
+    /// Only transient errors (see
+    /// [`ErrorClass::Transient`](crate::backend::kit::ErrorClass::Transient))
+    /// lead to queuing: other errors would fail the same way when
+    /// retried, so they are returned as is.
+    pub async fn send_or_enqueue(
+        &self,
+        sender: &(impl SendMessage + ?Sized),
+        raw: &[u8],
+        handler: &Option<Arc<OutboxEventHandler>>,
+    ) -> AnyResult<SendOutcome> {
+        match sender.send_message(raw).await {
+            Ok(_) => Ok(SendOutcome::Sent),
+            Err(err) if classify_error(&err) == ErrorClass::Transient => {
+                debug!("cannot reach transport, queuing message: {err}");
+                trace!("{err:?}");
+
+                let mut msg = QueuedMessage::new();
+                msg.last_error = Some(err.to_string());
+                msg.next_attempt = Utc::now() + self.backoff.delay(1);
+                let msg = self.store(raw, msg).await?;
+
+                OutboxEvent::Queued(msg.clone()).emit(handler).await;
+                Ok(SendOutcome::Queued(msg))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Send the given raw message, or store it in the queue if it
+    /// should be sent later.
+    ///
+    /// The message is scheduled at the given date, or at the date of
+    /// its `Date` header when it is in the future. Otherwise it is
+    /// sent as [`OutboxQueue::send_or_enqueue`] does.
+    pub async fn send_or_schedule(
+        &self,
+        sender: &(impl SendMessage + ?Sized),
+        raw: &[u8],
+        send_at: Option<DateTime<Utc>>,
+        handler: &Option<Arc<OutboxEventHandler>>,
+    ) -> AnyResult<SendOutcome> {
+        match send_at.or_else(|| find_send_at(raw)) {
+            Some(send_at) if send_at > Utc::now() => {
+                let msg = self.schedule(raw, send_at).await?;
+                info!("scheduled message {} at {send_at}", msg.id);
+
+                OutboxEvent::Scheduled(msg.clone()).emit(handler).await;
+                Ok(SendOutcome::Scheduled(msg))
+            }
+            _ => self.send_or_enqueue(sender, raw, handler).await,
+        }
+    }
+
+    /// Store the given raw message in the queue, then send it once
+    /// the grace period is over.
+    ///
+    /// Until then, sending can be undone with [`SendHandle::cancel`].
+    /// The message is sent by a background task: if the process
+    /// exits before, the message stays in the queue and is sent by
+    /// the next flush.
+    pub async fn send_with_undo<S>(
+        &self,
+        sender: Arc<S>,
+        raw: &[u8],
+        handler: Option<Arc<OutboxEventHandler>>,
+    ) -> Result<SendHandle>
+    where
+        S: SendMessage + ?Sized + 'static,
+    {
+        let send_at = Utc::now() + self.undo_delay;
+        let mut msg = QueuedMessage::new();
+        msg.next_attempt = send_at;
+        let msg = self.store(raw, msg).await?;
+
+        debug!("sending message {} in {:?}", msg.id, self.undo_delay);
+
+        let queue = self.clone();
+        let delay = self.undo_delay;
+        let pending = msg.clone();
+
+        runtime::spawn(async move {
+            runtime::sleep(delay).await;
+
+            if let Err(_err) = queue.send_one(&*sender, pending, &handler).await {
+                debug!("cannot send message after grace period: {_err}");
+                trace!("{_err:?}");
+            }
+        });
+
+        Ok(SendHandle {
+            queue: self.clone(),
+            msg,
+        })
+    }
+
+    /// Remove the message matching the given identifier from the
+    /// queue, unless it is being sent.
+    ///
+    /// Returns `false` if the message is being sent or has already
+    /// been sent.
+    pub async fn cancel(&self, id: &str) -> Result<bool> {
+        let path = self.msg_path(id);
+
+        match fs::remove_file(&path).await {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(Error::WriteOutboxQueueError(err, path)),
+        }
+
+        self.remove(id).await?;
+        Ok(true)
+    }
+
+    /// Try to send all the due messages of the queue once.
+    ///
+    /// Sent messages are removed from the queue. Failed messages are
+    /// rescheduled using the backoff. Returns the number of sent
+    /// messages.
+    pub async fn flush(
+        &self,
+        sender: &(impl SendMessage + ?Sized),
+        handler: &Option<Arc<OutboxEventHandler>>,
+    ) -> Result<usize> {
+        let mut sent = 0;
+
+        for msg in self.list().await? {
+            if !msg.is_due() || self.backoff.gives_up(msg.attempts) {
+                continue;
+            }
+
+            if self.send_one(sender, msg, handler).await? {
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Send the given queued message.
+    ///
+    /// The message is first claimed by renaming it, so that it
+    /// cannot be sent twice nor cancelled while being sent. Returns
+    /// `false` if the message could not be claimed or sent.
+    async fn send_one(
+        &self,
+        sender: &(impl SendMessage + ?Sized),
+        mut msg: QueuedMessage,
+        handler: &Option<Arc<OutboxEventHandler>>,
+    ) -> Result<bool> {
+        let path = self.msg_path(&msg.id);
+        let sending_path = self.sending_path(&msg.id);
+
+        match fs::rename(&path, &sending_path).await {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                debug!("queued message {} cancelled or already claimed", msg.id);
+                return Ok(false);
+            }
+            Err(err) => return Err(Error::WriteOutboxQueueError(err, path)),
+        }
+
+        debug!("sending queued message {} from outbox", msg.id);
+
+        let raw = fs::read(&sending_path)
+            .await
+            .map_err(|err| Error::ReadOutboxQueueError(err, sending_path.clone()))?;
+
+        match sender.send_message(&raw).await {
+            Ok(_) => {
+                fs::remove_file(&sending_path)
+                    .await
+                    .map_err(|err| Error::WriteOutboxQueueError(err, sending_path))?;
+                self.remove(&msg.id).await?;
+                OutboxEvent::Sent(msg).emit(handler).await;
+                Ok(true)
+            }
+            Err(err) => {
+                debug!("cannot send queued message {}: {err}", msg.id);
+                trace!("{err:?}");
+
+                msg.attempts += 1;
+                msg.last_error = Some(err.to_string());
+                msg.next_attempt = Utc::now() + self.backoff.delay(msg.attempts);
+                self.write_state(&msg).await?;
+
+                fs::rename(&sending_path, &path)
+                    .await
+                    .map_err(|err| Error::WriteOutboxQueueError(err, path))?;
+
+                if self.backoff.gives_up(msg.attempts) {
+                    OutboxEvent::GaveUp(msg).emit(handler).await;
+                } else {
+                    OutboxEvent::Failed(msg).emit(handler).await;
+                }
+
+                Ok(false)
+            }
+        }
+    }
+
+    /// Flush the queue until a shutdown is requested.
+    ///
+    /// The queue is flushed as soon as the next message is due, or
+    /// every [`DEFAULT_FLUSH_INTERVAL`] when no message is waiting,
+    /// so that messages queued by other processes are sent as well.
+    pub async fn run(
+        &self,
+        sender: &(impl SendMessage + ?Sized),
+        handler: &Option<Arc<OutboxEventHandler>>,
+        mut wait_for_shutdown_request: Receiver<()>,
+    ) -> Result<()> {
+        info!("flushing outbox queue at {:?}", self.dir);
+
+        loop {
+            self.flush(sender, handler).await?;
+
+            let delay = self
+                .list()
+                .await?
+                .into_iter()
+                .filter(|msg| !self.backoff.gives_up(msg.attempts))
+                .map(|msg| msg.next_attempt)
+                .min()
+                .map(|next| (next - Utc::now()).to_std().unwrap_or_default())
+                .unwrap_or(DEFAULT_FLUSH_INTERVAL)
+                .min(DEFAULT_FLUSH_INTERVAL);
+
+            tokio::select! {
+                _ = runtime::sleep(delay) => (),
+                _ = &mut wait_for_shutdown_request => {
+                    debug!("shutdown requested, stopping outbox queue flusher");
+                    break Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Return the date of the `Date` header of the given raw message, if
+/// it is in the future.
+pub fn find_send_at(raw: &[u8]) -> Option<DateTime<Utc>> {
+    let msg = MessageParser::new().parse_headers(raw)?;
+    let date = from_mail_parser_to_chrono_datetime(msg.date()?)?.with_timezone(&Utc);
+    (date > Utc::now()).then_some(date)
+}
+
+/// Write the given content to a temporary file, then rename it to the
+/// given path, so that readers never see a partially written file.
+async fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, content)
+        .await
+        .map_err(|err| Error::WriteOutboxQueueError(err, tmp.clone()))?;
+    fs::rename(&tmp, path)
+        .await
+        .map_err(|err| Error::WriteOutboxQueueError(err, path.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{find_send_at, OutboxBackoff, OutboxQueue, QueuedMessage};
+    use crate::{
+        message::send::{SendMessage, SentMessage},
+        AnyResult,
+    };
+
+    #[derive(Default)]
+    struct TestSender(AtomicUsize);
+
+    #[async_trait]
+    impl SendMessage for TestSender {
+        async fn send_message(&self, _msg: &[u8]) -> AnyResult<SentMessage> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(SentMessage::default())
+        }
+    }
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = OutboxBackoff {
+            base: Duration::from_secs(30),
+            max: Duration::from_secs(300),
+            max_attempts: Some(5),
+        };
+
+        assert_eq!(backoff.delay(1), Duration::from_secs(30));
+        assert_eq!(backoff.delay(2), Duration::from_secs(60));
+        assert_eq!(backoff.delay(4), Duration::from_secs(240));
+        assert_eq!(backoff.delay(5), Duration::from_secs(300));
+        assert_eq!(backoff.delay(100), Duration::from_secs(300));
+
+        assert!(!backoff.gives_up(4));
+        assert!(backoff.gives_up(5));
+        assert!(!OutboxBackoff::default().gives_up(u32::MAX));
+    }
+
+    #[test]
+    fn queued_message_state() {
+        let mut msg = QueuedMessage::new();
+        msg.attempts = 2;
+        msg.last_error = Some("connection refused\nby peer".into());
+        msg.send_at = Some(msg.next_attempt);
+
+        let parsed = QueuedMessage::parse_state(msg.id.clone(), &msg.state());
+
+        assert_eq!(parsed.id, msg.id);
+        assert_eq!(parsed.attempts, 2);
+        assert_eq!(parsed.next_attempt, msg.next_attempt);
+        assert_eq!(
+            parsed.last_error.as_deref(),
+            Some("connection refused by peer")
+        );
+        assert_eq!(parsed.send_at, Some(msg.next_attempt));
+    }
+
+    #[test]
+    fn send_at_from_date_header() {
+        let future = b"Date: Fri, 1 Jan 2100 10:00:00 +0000\r\nSubject: later\r\n\r\nHello";
+        let past = b"Date: Thu, 1 Jan 2015 10:00:00 +0000\r\nSubject: now\r\n\r\nHello";
+
+        let send_at = find_send_at(future).unwrap();
+        assert_eq!(send_at.to_rfc3339(), "2100-01-01T10:00:00+00:00");
+        assert_eq!(find_send_at(past), None);
+        assert_eq!(find_send_at(b"Subject: no date\r\n\r\nHello"), None);
+    }
+
+    #[tokio::test]
+    async fn undo_send() {
+        let dir = tempfile::tempdir().unwrap();
+        let sender = Arc::new(TestSender::default());
+
+        let queue = OutboxQueue::new(dir.path()).with_undo_delay(Duration::from_secs(3600));
+        let handle = queue
+            .send_with_undo(sender.clone(), b"Subject: undo\r\n\r\n", None)
+            .await
+            .unwrap();
+
+        assert_eq!(queue.list().await.unwrap().len(), 1);
+        assert!(handle.cancel().await.unwrap());
+        assert!(!handle.cancel().await.unwrap());
+        assert!(queue.list().await.unwrap().is_empty());
+
+        let queue = queue.with_undo_delay(Duration::ZERO);
+        let handle = queue
+            .send_with_undo(sender.clone(), b"Subject: send\r\n\r\n", None)
+            .await
+            .unwrap();
+
+        while sender.0.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(!handle.cancel().await.unwrap());
+    }
+}
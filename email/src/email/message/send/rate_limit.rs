@@ -0,0 +1,288 @@
+//! Module dedicated to outbound rate limiting.
+//!
+//! Providers limit the number of messages an account can send per
+//! minute, and sometimes the number of concurrent connections.
+//! Tripping those limits leads at best to temporary rejections, at
+//! worst to a suspended account, which easily happens when flushing
+//! a large outbox. The [`SendRateLimiter`] paces messages using a
+//! token bucket, and bounds the number of concurrent sends using a
+//! semaphore (see [`SendRateLimitConfig`]).
+//!
+//! Backends built from an account configuration enforce the limits
+//! of the account by themselves. Standalone transports can be
+//! wrapped with a [`RateLimitedSendMessage`].
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+use super::{
+    config::SendRateLimitConfig, SendMessage, SendMessageOptions, SendReport, SentMessage,
+};
+use crate::{account::config::AccountConfig, debug, runtime, AnyResult};
+
+/// The token bucket pacing messages.
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    /// The maximum number of tokens the bucket can hold.
+    capacity: f64,
+
+    /// The number of tokens currently available.
+    tokens: f64,
+
+    /// The number of tokens added to the bucket per second.
+    rate: f64,
+
+    /// The last time tokens were added to the bucket.
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Create a new full bucket.
+    fn new(per_minute: u32, burst: u32, now: Instant) -> Self {
+        let capacity = burst.max(1) as f64;
+
+        Self {
+            capacity,
+            tokens: capacity,
+            rate: per_minute as f64 / 60.,
+            refilled_at: now,
+        }
+    }
+
+    /// Take a token from the bucket at the given instant.
+    ///
+    /// This function returns `None` if a token has been taken,
+    /// otherwise it returns the duration to wait before the next
+    /// token becomes available.
+    fn take(&mut self, now: Instant) -> Option<Duration> {
+        if now > self.refilled_at {
+            let elapsed = (now - self.refilled_at).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.refilled_at = now;
+        }
+
+        if self.tokens >= 1. {
+            self.tokens -= 1.;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1. - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// The outbound rate limiter.
+///
+/// Clones share the same limits, which is why a single limiter
+/// should be created per account.
+#[derive(Clone, Debug, Default)]
+pub struct SendRateLimiter {
+    bucket: Option<Arc<Mutex<TokenBucket>>>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+impl SendRateLimiter {
+    /// Create a new rate limiter from the given configuration.
+    ///
+    /// Limits set to zero are considered unlimited.
+    pub fn new(config: &SendRateLimitConfig) -> Self {
+        let bucket = config.per_minute.filter(|n| *n > 0).map(|per_minute| {
+            let burst = config.burst.unwrap_or(1);
+            let bucket = TokenBucket::new(per_minute, burst, Instant::now());
+            Arc::new(Mutex::new(bucket))
+        });
+
+        let semaphore = config
+            .concurrency
+            .filter(|n| *n > 0)
+            .map(|n| Arc::new(Semaphore::new(n)));
+
+        Self { bucket, semaphore }
+    }
+
+    /// Create a new rate limiter using the limits of the given
+    /// account.
+    ///
+    /// The limiter does not limit anything if the account does not
+    /// define limits.
+    pub fn from_account_config(config: &AccountConfig) -> Self {
+        config
+            .find_message_send_rate_limit()
+            .map(Self::new)
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the limiter does not limit anything.
+    pub fn is_unlimited(&self) -> bool {
+        self.bucket.is_none() && self.semaphore.is_none()
+    }
+
+    /// Wait until a message can be sent.
+    ///
+    /// The returned permit should be held until the message is sent,
+    /// so that it counts towards the concurrency limit.
+    pub async fn acquire(&self) -> SendRatePermit {
+        let permit = match self.semaphore.clone() {
+            Some(semaphore) => semaphore.acquire_owned().await.ok(),
+            None => None,
+        };
+
+        if let Some(bucket) = self.bucket.as_ref() {
+            while let Some(delay) = bucket.lock().await.take(Instant::now()) {
+                debug!("send rate limit reached, waiting {delay:?}");
+                runtime::sleep(delay).await;
+            }
+        }
+
+        SendRatePermit { _permit: permit }
+    }
+}
+
+/// The permit to send a message, released on drop.
+#[derive(Debug)]
+pub struct SendRatePermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// The rate-limited send message feature.
+///
+/// The wrapper is itself a [`SendMessage`] feature, which means that
+/// it can be plugged in a backend the same way a single transport
+/// is.
+pub struct RateLimitedSendMessage {
+    limiter: SendRateLimiter,
+    inner: Box<dyn SendMessage>,
+}
+
+impl RateLimitedSendMessage {
+    /// Wrap the given transport with the given limiter.
+    pub fn new(limiter: SendRateLimiter, inner: Box<dyn SendMessage>) -> Self {
+        Self { limiter, inner }
+    }
+
+    /// Wrap the given transport with the limits of the given
+    /// account.
+    pub fn from_account_config(config: &AccountConfig, inner: Box<dyn SendMessage>) -> Self {
+        Self::new(SendRateLimiter::from_account_config(config), inner)
+    }
+}
+
+#[async_trait]
+impl SendMessage for RateLimitedSendMessage {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.send_message(msg).await
+    }
+
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.send_message_with_options(msg, opts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::time::Instant;
+
+    use super::{RateLimitedSendMessage, SendRateLimiter, TokenBucket};
+    use crate::{
+        message::send::{config::SendRateLimitConfig, SendMessage, SentMessage},
+        runtime, AnyResult,
+    };
+
+    #[test]
+    fn token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(30, 2, now);
+
+        assert_eq!(bucket.take(now), None);
+        assert_eq!(bucket.take(now), None);
+        assert_eq!(bucket.take(now), Some(Duration::from_secs(2)));
+
+        let now = now + Duration::from_secs(1);
+        assert_eq!(bucket.take(now), Some(Duration::from_secs(1)));
+
+        let now = now + Duration::from_secs(1);
+        assert_eq!(bucket.take(now), None);
+        assert_eq!(bucket.take(now), Some(Duration::from_secs(2)));
+
+        // the bucket never holds more than its capacity
+        let now = now + Duration::from_secs(60);
+        assert_eq!(bucket.take(now), None);
+        assert_eq!(bucket.take(now), None);
+        assert!(bucket.take(now).is_some());
+    }
+
+    #[test]
+    fn unlimited() {
+        assert!(SendRateLimiter::default().is_unlimited());
+
+        let config = SendRateLimitConfig {
+            per_minute: Some(0),
+            burst: Some(10),
+            concurrency: Some(0),
+        };
+        assert!(SendRateLimiter::new(&config).is_unlimited());
+    }
+
+    #[derive(Clone, Default)]
+    struct TestSender {
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl SendMessage for TestSender {
+        async fn send_message(&self, _msg: &[u8]) -> AnyResult<SentMessage> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            runtime::sleep(Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(SentMessage::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrency() {
+        let config = SendRateLimitConfig {
+            concurrency: Some(2),
+            ..Default::default()
+        };
+
+        let sender = TestSender::default();
+        let limited = Arc::new(RateLimitedSendMessage::new(
+            SendRateLimiter::new(&config),
+            Box::new(sender.clone()),
+        ));
+
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let limited = limited.clone();
+                runtime::spawn(async move { limited.send_message(b"Hello!").await })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(sender.max_running.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,237 @@
+//! Module dedicated to automatic recipients.
+//!
+//! Accounts can define recipients automatically added to the `Cc` or
+//! `Bcc` headers of messages being sent, for example to keep a copy
+//! of sent messages in a compliance archive. Recipients are added to
+//! the raw message itself, so that the copy saved in the sent folder
+//! reflects them. Recipients already present in the message are not
+//! added twice, which makes the operation idempotent.
+
+use std::{borrow::Cow, collections::HashSet};
+
+use mail_parser::MessageParser;
+
+use super::{envelope::SendEnvelope, SendMessageOptions};
+use crate::{account::config::AccountConfig, debug};
+
+/// Add the automatic recipients of the given account to the given
+/// raw message.
+///
+/// The message is left untouched if the account does not define
+/// automatic recipients, or if they are disabled by the given
+/// options.
+pub fn add_auto_recipients<'a>(
+    config: &AccountConfig,
+    msg: &'a [u8],
+    opts: &SendMessageOptions,
+) -> Cow<'a, [u8]> {
+    if !opts.auto_recipients.unwrap_or(true) {
+        return Cow::Borrowed(msg);
+    }
+
+    let cc = config.get_message_send_auto_cc();
+    let bcc = config.get_message_send_auto_bcc();
+
+    if cc.is_empty() && bcc.is_empty() {
+        return Cow::Borrowed(msg);
+    }
+
+    let mut known: HashSet<String> = match MessageParser::new().parse_headers(msg) {
+        Some(msg) => SendEnvelope::from(&msg)
+            .rcpt_to
+            .into_iter()
+            .map(|email| email.to_lowercase())
+            .collect(),
+        None => {
+            debug!("cannot parse raw message, skipping automatic recipients");
+            return Cow::Borrowed(msg);
+        }
+    };
+
+    let mut missing = |addrs: &[String]| -> Vec<String> {
+        addrs
+            .iter()
+            .filter(|addr| known.insert(find_email(addr).to_lowercase()))
+            .cloned()
+            .collect()
+    };
+
+    let cc = missing(&cc);
+    let bcc = missing(&bcc);
+
+    let mut msg = Cow::Borrowed(msg);
+
+    if !cc.is_empty() {
+        debug!("adding automatic cc recipients {cc:?}");
+        msg = Cow::Owned(append_to_header(&msg, "Cc", &cc));
+    }
+
+    if !bcc.is_empty() {
+        debug!("adding automatic bcc recipients {bcc:?}");
+        msg = Cow::Owned(append_to_header(&msg, "Bcc", &bcc));
+    }
+
+    msg
+}
+
+/// Find the email address of the given mailbox, which can either be
+/// a bare address or a name followed by an address between angle
+/// brackets.
+fn find_email(addr: &str) -> &str {
+    match (addr.rfind('<'), addr.rfind('>')) {
+        (Some(start), Some(end)) if start < end => addr[start + 1..end].trim(),
+        _ => addr.trim(),
+    }
+}
+
+/// Append the given addresses to the first header of the given name,
+/// or add the header at the end of the header section if it does not
+/// exist yet.
+fn append_to_header(msg: &[u8], name: &str, addrs: &[String]) -> Vec<u8> {
+    let eol: &[u8] = if msg.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+
+    // the end of the value of the matching header, and the end of
+    // the header section
+    let mut value_end = None;
+    let mut headers_end = msg.len();
+    let mut in_header = false;
+    let mut pos = 0;
+
+    while pos < msg.len() {
+        let line_end = match msg[pos..].iter().position(|b| *b == b'\n') {
+            Some(i) => pos + i + 1,
+            None => msg.len(),
+        };
+
+        let line = &msg[pos..line_end];
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+
+        if content.is_empty() {
+            headers_end = pos;
+            break;
+        }
+
+        if content.starts_with(b" ") || content.starts_with(b"\t") {
+            if in_header {
+                value_end = Some(pos + content.len());
+            }
+        } else {
+            in_header = value_end.is_none()
+                && content.split(|b| *b == b':').next().is_some_and(|key| {
+                    String::from_utf8_lossy(key)
+                        .trim()
+                        .eq_ignore_ascii_case(name)
+                });
+
+            if in_header {
+                value_end = Some(pos + content.len());
+            }
+        }
+
+        pos = line_end;
+    }
+
+    let addrs = addrs.join(", ");
+    let mut out = Vec::with_capacity(msg.len() + addrs.len() + name.len() + 4);
+
+    match value_end {
+        Some(end) => {
+            out.extend_from_slice(&msg[..end]);
+            out.extend_from_slice(b", ");
+            out.extend_from_slice(addrs.as_bytes());
+            out.extend_from_slice(&msg[end..]);
+        }
+        None => {
+            out.extend_from_slice(&msg[..headers_end]);
+            if !out.is_empty() && !out.ends_with(b"\n") {
+                out.extend_from_slice(eol);
+            }
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(addrs.as_bytes());
+            out.extend_from_slice(eol);
+            out.extend_from_slice(&msg[headers_end..]);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_auto_recipients;
+    use crate::{
+        account::config::AccountConfig,
+        message::{
+            config::MessageConfig,
+            send::{config::MessageSendConfig, SendMessageOptions},
+        },
+    };
+
+    fn config() -> AccountConfig {
+        AccountConfig {
+            email: "alice@localhost".into(),
+            message: Some(MessageConfig {
+                send: Some(MessageSendConfig {
+                    auto_cc: Some(vec!["Team <team@localhost>".into()]),
+                    auto_bcc: Some(vec!["alice@localhost".into(), "archive@localhost".into()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn add_missing_recipients() {
+        let msg = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost\r\n",
+            "Cc: carol@localhost,\r\n",
+            " Archive <ARCHIVE@localhost>\r\n",
+            "Subject: test\r\n",
+            "\r\n",
+            "Hello!\r\n",
+        );
+
+        let msg = add_auto_recipients(&config(), msg.as_bytes(), &Default::default());
+
+        let expected = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost\r\n",
+            "Cc: carol@localhost,\r\n",
+            " Archive <ARCHIVE@localhost>, Team <team@localhost>\r\n",
+            "Subject: test\r\n",
+            "Bcc: alice@localhost\r\n",
+            "\r\n",
+            "Hello!\r\n",
+        );
+
+        assert_eq!(String::from_utf8_lossy(&msg), expected);
+
+        // adding recipients is idempotent
+        let again = add_auto_recipients(&config(), &msg, &Default::default());
+        assert_eq!(String::from_utf8_lossy(&again), expected);
+    }
+
+    #[test]
+    fn skip_recipients() {
+        let msg = "From: alice@localhost\nTo: bob@localhost\n\nHello!\n";
+
+        let opts = SendMessageOptions {
+            auto_recipients: Some(false),
+            ..Default::default()
+        };
+        let skipped = add_auto_recipients(&config(), msg.as_bytes(), &opts);
+        assert_eq!(skipped.as_ref(), msg.as_bytes());
+
+        let skipped = add_auto_recipients(&Default::default(), msg.as_bytes(), &Default::default());
+        assert_eq!(skipped.as_ref(), msg.as_bytes());
+    }
+}
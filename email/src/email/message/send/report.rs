@@ -0,0 +1,171 @@
+//! Module dedicated to message sending reporting.
+//!
+//! The core structures of this module are the [`SentMessage`], which
+//! holds the reply of the server to a sent message, the
+//! [`SendReport`], which tells which recipients accepted a message,
+//! and the [`SendMessageReport`].
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::hook::PreSendHookResult;
+use crate::envelope::SingleId;
+
+/// Regexes matching the queue identifier in replies of common mail
+/// servers, in order of precedence.
+static QUEUE_ID_REGEXES: Lazy<[Regex; 5]> = Lazy::new(|| {
+    [
+        // Postfix: `2.0.0 Ok: queued as 4F2A1C0B3D`
+        Regex::new(r"(?i)\bqueued as\s+<?([^\s<>;,]+)").unwrap(),
+        // Exim: `OK id=1qAbCd-000123-Ef`
+        Regex::new(r"(?i)\bid=<?([^\s<>;,]+)").unwrap(),
+        // Gmail: `2.0.0 OK  1700000000 a1-20020a05sor123wrx.0 - gsmtp`
+        Regex::new(r"(?i)\bok\s+\d+\s+(\S+)\s+-\s+gsmtp").unwrap(),
+        // Sendmail: `2.0.0 3AB1cd123 Message accepted for delivery`
+        Regex::new(r"(?i)(\S+)\s+message accepted").unwrap(),
+        // Outlook and others: `2.0.0 OK <queue-id@host> [Hostname=…]`
+        Regex::new(r"<([^\s<>]+)>").unwrap(),
+    ]
+});
+
+/// The reply of the server to a sent message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SentMessage {
+    /// The identifier given to the message by the server, if it
+    /// could be found in its reply.
+    ///
+    /// Servers usually put the message in a queue before delivering
+    /// it, this identifier is the one to give to the provider when
+    /// investigating a delivery issue.
+    pub queue_id: Option<String>,
+
+    /// The final reply of the server, if the transport exposes it.
+    pub response: Option<String>,
+}
+
+impl SentMessage {
+    /// Create a sent message from the given final reply of the
+    /// server, for example `250 2.0.0 Ok: queued as 4F2A1C0B3D`.
+    pub fn from_response(response: impl ToString) -> Self {
+        let response = response.to_string();
+        let queue_id = parse_queue_id(&response);
+
+        Self {
+            queue_id,
+            response: Some(response),
+        }
+    }
+}
+
+/// Find the queue identifier in the given reply of the server.
+pub fn parse_queue_id(response: &str) -> Option<String> {
+    QUEUE_ID_REGEXES
+        .iter()
+        .find_map(|regex| regex.captures(response))
+        .and_then(|captures| captures.get(1))
+        .map(|id| id.as_str().to_owned())
+}
+
+/// The report of recipients of a sent message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SendReport {
+    /// The recipients that accepted the message.
+    pub accepted: Vec<String>,
+
+    /// The recipients that rejected the message, with the reply of
+    /// the server.
+    pub rejected: Vec<(String, String)>,
+
+    /// The results of the pre-send hooks, in the order they ran.
+    pub pre_hooks: Vec<PreSendHookResult>,
+
+    /// The reply of the server to the sent message.
+    pub sent: SentMessage,
+}
+
+impl SendReport {
+    /// Return `true` if some recipients rejected the message.
+    pub fn is_partial(&self) -> bool {
+        !self.rejected.is_empty()
+    }
+}
+
+/// The message sending report.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SendMessageReport {
+    /// Which recipients accepted the message.
+    pub recipients: SendReport,
+
+    /// What happened to the copy of the sent message.
+    pub save_copy: SaveCopyDecision,
+}
+
+/// The decision taken regarding the copy of a sent message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum SaveCopyDecision {
+    /// A copy has been saved in the given folder, under the given
+    /// identifier.
+    Saved(String, SingleId),
+
+    /// No copy has been saved, as disabled by the configuration.
+    #[default]
+    Disabled,
+
+    /// No copy has been saved, as the provider already saves sent
+    /// messages by itself.
+    SavedByProvider,
+
+    /// A copy has been saved right after sending the message, as
+    /// enabled by the `auto-save-copy` option.
+    SavedBySender,
+}
+
+impl SaveCopyDecision {
+    /// Return `true` if a copy has been saved by the client.
+    pub fn is_saved(&self) -> bool {
+        matches!(self, Self::Saved(..) | Self::SavedBySender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_queue_id, SentMessage};
+
+    #[test]
+    fn queue_id() {
+        let id = |response| parse_queue_id(response);
+
+        assert_eq!(
+            id("2.0.0 Ok: queued as 4F2A1C0B3D").as_deref(),
+            Some("4F2A1C0B3D")
+        );
+        assert_eq!(
+            id("OK id=1qAbCd-000123-Ef").as_deref(),
+            Some("1qAbCd-000123-Ef")
+        );
+        assert_eq!(
+            id("2.0.0 OK  1700000000 a1-20020a05sor123wrx.0 - gsmtp").as_deref(),
+            Some("a1-20020a05sor123wrx.0")
+        );
+        assert_eq!(
+            id("2.0.0 3AB1cd123 Message accepted for delivery").as_deref(),
+            Some("3AB1cd123")
+        );
+        assert_eq!(
+            id("2.0.0 OK <AB12CD@eur.prod.outlook.com> [Hostname=AB12]").as_deref(),
+            Some("AB12CD@eur.prod.outlook.com")
+        );
+        assert_eq!(id("2.0.0 Ok"), None);
+    }
+
+    #[test]
+    fn from_response() {
+        let sent = SentMessage::from_response("250 2.0.0 Ok: queued as 4F2A1C0B3D");
+
+        assert_eq!(sent.queue_id.as_deref(), Some("4F2A1C0B3D"));
+        assert_eq!(
+            sent.response.as_deref(),
+            Some("250 2.0.0 Ok: queued as 4F2A1C0B3D")
+        );
+    }
+}
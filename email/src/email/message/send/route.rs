@@ -0,0 +1,506 @@
+//! Module dedicated to message routing.
+//!
+//! An account can send messages via more than one transport, for
+//! example an internal SMTP server for internal recipients and the
+//! provider SMTP server for everything else. The
+//! [`SendMessageRouter`] evaluates the routes of the account (see
+//! [`SendRouteConfig`]) against each message, then delegates the
+//! sending to the matching transport.
+//!
+//! When the matching transport cannot be reached, the router can
+//! fall back to another transport, for example a local sendmail when
+//! the SMTP server is down. A [`SendFallbackEvent`] is then emitted,
+//! so that clients can let users know.
+//!
+//! ```rust,ignore
+//! let router = SendMessageRouter::from_account_config(&account_config, provider)
+//!     .with_transport("internal", internal)
+//!     .with_transport("sendmail", sendmail)
+//!     .with_fallback("sendmail");
+//!
+//! router.send_message(msg).await?;
+//! ```
+
+use std::{collections::HashMap, fmt, future::Future, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use mail_parser::{HeaderValue, Message, MessageParser};
+
+use super::{
+    config::{SendRouteConfig, SendRouteHeaderConfig},
+    envelope::SendEnvelope,
+    SendMessage, SendMessageOptions, SendReport, SentMessage,
+};
+use crate::{
+    account::config::AccountConfig,
+    backend::kit::{classify_error, ErrorClass},
+    debug,
+    email::error::Error,
+    trace, warn, AnyBoxedError, AnyResult,
+};
+
+impl SendRouteConfig {
+    /// Return `true` if the given message matches the rule.
+    pub fn matches(&self, msg: &Message<'_>) -> bool {
+        if let Some(pattern) = self.recipients.as_deref() {
+            let SendEnvelope { rcpt_to, .. } = SendEnvelope::from(msg);
+
+            if rcpt_to.is_empty() || !rcpt_to.iter().all(|rcpt| glob_match(pattern, rcpt)) {
+                return false;
+            }
+        }
+
+        if let Some(header) = self.header.as_ref() {
+            if !header.matches(msg) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl SendRouteHeaderConfig {
+    /// Return `true` if the given message contains a header matching
+    /// the condition.
+    pub fn matches(&self, msg: &Message<'_>) -> bool {
+        msg.headers()
+            .iter()
+            .filter(|header| header.name.as_str().eq_ignore_ascii_case(&self.name))
+            .any(|header| {
+                let value = match header.value() {
+                    HeaderValue::Text(text) => text.to_string(),
+                    _ => {
+                        let start = header.offset_start;
+                        let end = header.offset_end;
+                        let raw = msg.raw_message().get(start..end).unwrap_or_default();
+                        String::from_utf8_lossy(raw).to_string()
+                    }
+                };
+
+                glob_match(&self.value, value.trim())
+            })
+    }
+}
+
+/// The send fallback async event handler.
+pub type SendFallbackEventHandler =
+    dyn Fn(SendFallbackEvent) -> Pin<Box<dyn Future<Output = AnyResult<()>> + Send>> + Send + Sync;
+
+/// The event emitted when a message is sent using the fallback
+/// transport.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SendFallbackEvent {
+    /// The name of the transport that could not be reached, or
+    /// `None` for the default transport.
+    pub transport: Option<String>,
+
+    /// The name of the fallback transport.
+    pub fallback: String,
+
+    /// The error of the transport that could not be reached.
+    pub reason: String,
+}
+
+impl SendFallbackEvent {
+    pub async fn emit(&self, handler: &Option<Arc<SendFallbackEventHandler>>) {
+        if let Some(handler) = handler.as_ref() {
+            if let Err(_err) = handler(self.clone()).await {
+                debug!("error while emitting send fallback event: {_err}");
+                trace!("{_err:?}");
+            } else {
+                debug!("emitted send fallback event {self:?}");
+            }
+        }
+    }
+}
+
+impl fmt::Display for SendFallbackEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.transport {
+            Some(transport) => write!(f, "Cannot reach transport {transport}")?,
+            None => write!(f, "Cannot reach default transport")?,
+        }
+
+        write!(f, ", message sent using {} instead", self.fallback)
+    }
+}
+
+/// The message router.
+///
+/// The router is itself a [`SendMessage`] feature, which means that
+/// it can be plugged in a backend the same way a single transport
+/// is.
+pub struct SendMessageRouter {
+    routes: Vec<SendRouteConfig>,
+    transports: HashMap<String, Box<dyn SendMessage>>,
+    default: Box<dyn SendMessage>,
+    fallback: Option<String>,
+    fallback_handler: Option<Arc<SendFallbackEventHandler>>,
+}
+
+impl SendMessageRouter {
+    /// Create a new router without routes, sending all messages via
+    /// the given default transport.
+    pub fn new(default: Box<dyn SendMessage>) -> Self {
+        Self {
+            routes: Vec::new(),
+            transports: HashMap::new(),
+            default,
+            fallback: None,
+            fallback_handler: None,
+        }
+    }
+
+    /// Create a new router using the routes and the fallback
+    /// transport of the given account.
+    pub fn from_account_config(config: &AccountConfig, default: Box<dyn SendMessage>) -> Self {
+        let router = Self::new(default).with_routes(config.get_message_send_routes());
+
+        match config.find_message_send_fallback() {
+            Some(fallback) => router.with_fallback(fallback),
+            None => router,
+        }
+    }
+
+    /// Add the given routes, evaluated after the existing ones.
+    pub fn with_routes(mut self, routes: impl IntoIterator<Item = SendRouteConfig>) -> Self {
+        self.routes.extend(routes);
+        self
+    }
+
+    /// Register the given transport under the given name.
+    pub fn with_transport(mut self, name: impl ToString, sender: Box<dyn SendMessage>) -> Self {
+        self.transports.insert(name.to_string(), sender);
+        self
+    }
+
+    /// Use the transport registered under the given name when the
+    /// transport chosen for a message cannot be reached.
+    pub fn with_fallback(mut self, name: impl ToString) -> Self {
+        self.fallback = Some(name.to_string());
+        self
+    }
+
+    /// Call the given handler each time the fallback transport is
+    /// used.
+    pub fn with_fallback_handler(mut self, handler: Arc<SendFallbackEventHandler>) -> Self {
+        self.fallback_handler = Some(handler);
+        self
+    }
+
+    /// Find the transport the given raw message should be sent with.
+    ///
+    /// This function returns an error if the matching route refers to
+    /// a transport that has not been registered.
+    pub fn route(&self, msg: &[u8]) -> AnyResult<&dyn SendMessage> {
+        Ok(self.find_transport(msg)?.1)
+    }
+
+    /// Find the transport the given raw message should be sent with,
+    /// alongside its name (`None` for the default transport).
+    fn find_transport(&self, msg: &[u8]) -> AnyResult<(Option<&str>, &dyn SendMessage)> {
+        let Some(parsed) = MessageParser::new().parse_headers(msg) else {
+            debug!("cannot parse raw message, using default transport");
+            return Ok((None, self.default.as_ref()));
+        };
+
+        let Some(route) = self.routes.iter().find(|route| route.matches(&parsed)) else {
+            debug!("no route matching message, using default transport");
+            return Ok((None, self.default.as_ref()));
+        };
+
+        debug!("message matches route using transport {}", route.transport);
+
+        Ok((
+            Some(&route.transport),
+            self.get_transport(&route.transport)?,
+        ))
+    }
+
+    /// Get the transport registered under the given name.
+    fn get_transport(&self, name: &str) -> AnyResult<&dyn SendMessage> {
+        match self.transports.get(name) {
+            Some(sender) => Ok(sender.as_ref()),
+            None => Err(Error::SendMessageUnknownTransportError(name.to_owned()).into()),
+        }
+    }
+
+    /// Find the fallback transport to use after the given error of
+    /// the given transport, if any.
+    ///
+    /// Only transient errors lead to the fallback transport: other
+    /// errors would most likely fail the same way with any transport.
+    async fn find_fallback(
+        &self,
+        transport: Option<&str>,
+        err: &AnyBoxedError,
+    ) -> AnyResult<Option<&dyn SendMessage>> {
+        let Some(fallback) = self.fallback.as_deref() else {
+            return Ok(None);
+        };
+
+        if transport == Some(fallback) || classify_error(err) != ErrorClass::Transient {
+            return Ok(None);
+        }
+
+        let sender = self.get_transport(fallback)?;
+
+        warn!("cannot reach transport, falling back to {fallback}: {err}");
+        trace!("{err:?}");
+
+        let event = SendFallbackEvent {
+            transport: transport.map(ToOwned::to_owned),
+            fallback: fallback.to_owned(),
+            reason: err.to_string(),
+        };
+
+        event.emit(&self.fallback_handler).await;
+
+        Ok(Some(sender))
+    }
+}
+
+#[async_trait]
+impl SendMessage for SendMessageRouter {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
+        let (transport, sender) = self.find_transport(msg)?;
+
+        match sender.send_message(msg).await {
+            Err(err) => match self.find_fallback(transport, &err).await? {
+                Some(fallback) => fallback.send_message(msg).await,
+                None => Err(err),
+            },
+            sent => sent,
+        }
+    }
+
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        let (transport, sender) = self.find_transport(msg)?;
+
+        match sender.send_message_with_options(msg, opts).await {
+            Err(err) => match self.find_fallback(transport, &err).await? {
+                Some(fallback) => fallback.send_message_with_options(msg, opts).await,
+                None => Err(err),
+            },
+            report => report,
+        }
+    }
+}
+
+/// Return `true` if the given text matches the given glob pattern,
+/// case-insensitively.
+///
+/// `*` matches any sequence of characters (including an empty one),
+/// `?` matches a single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // the position of the last star in the pattern, and the position
+    // in the text it started matching at
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::Any,
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use mail_parser::MessageParser;
+    use thiserror::Error;
+
+    use super::{glob_match, SendFallbackEvent, SendFallbackEventHandler, SendMessageRouter};
+    use crate::{
+        message::send::{
+            config::{SendRouteConfig, SendRouteHeaderConfig},
+            SendMessage, SentMessage,
+        },
+        AnyError, AnyResult,
+    };
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*@internal.corp", "bob@internal.corp"));
+        assert!(glob_match("*@INTERNAL.corp", "Bob@internal.CORP"));
+        assert!(!glob_match("*@internal.corp", "bob@internal.corp.evil"));
+        assert!(glob_match("*@*.corp", "bob@eu.internal.corp"));
+        assert!(glob_match("b?b@*", "bob@localhost"));
+        assert!(!glob_match("b?b@*", "bobby@localhost"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn route_rules() {
+        let internal = SendRouteConfig {
+            transport: "internal".into(),
+            recipients: Some("*@internal.corp".into()),
+            header: None,
+        };
+
+        let msg = "To: bob@internal.corp\r\nCc: carol@internal.corp\r\n\r\n";
+        let msg = MessageParser::new().parse_headers(msg.as_bytes()).unwrap();
+        assert!(internal.matches(&msg));
+
+        let msg = "To: bob@internal.corp\r\nCc: carol@localhost\r\n\r\n";
+        let msg = MessageParser::new().parse_headers(msg.as_bytes()).unwrap();
+        assert!(!internal.matches(&msg));
+
+        let bulk = SendRouteConfig {
+            transport: "bulk".into(),
+            recipients: None,
+            header: Some(SendRouteHeaderConfig {
+                name: "precedence".into(),
+                value: "bulk".into(),
+            }),
+        };
+
+        let msg = "To: bob@localhost\r\nPrecedence: Bulk\r\n\r\n";
+        let msg = MessageParser::new().parse_headers(msg.as_bytes()).unwrap();
+        assert!(bulk.matches(&msg));
+    }
+
+    #[derive(Clone, Default)]
+    struct TestSender(Arc<Mutex<Vec<&'static str>>>, &'static str);
+
+    #[async_trait]
+    impl SendMessage for TestSender {
+        async fn send_message(&self, _msg: &[u8]) -> AnyResult<SentMessage> {
+            self.0.lock().unwrap().push(self.1);
+            Ok(SentMessage::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn router() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+
+        let router = SendMessageRouter::new(Box::new(TestSender(sent.clone(), "provider")))
+            .with_routes([
+                SendRouteConfig {
+                    transport: "internal".into(),
+                    recipients: Some("*@internal.corp".into()),
+                    header: None,
+                },
+                SendRouteConfig {
+                    transport: "unknown".into(),
+                    recipients: Some("*@unknown".into()),
+                    header: None,
+                },
+            ])
+            .with_transport("internal", Box::new(TestSender(sent.clone(), "internal")));
+
+        let msg = b"From: alice@internal.corp\r\nTo: bob@internal.corp\r\n\r\nHello!";
+        router.send_message(msg).await.unwrap();
+
+        let msg = b"From: alice@internal.corp\r\nTo: bob@localhost\r\n\r\nHello!";
+        router.send_message(msg).await.unwrap();
+
+        let msg = b"From: alice@internal.corp\r\nTo: bob@unknown\r\n\r\nHello!";
+        assert!(router.send_message(msg).await.is_err());
+
+        assert_eq!(*sent.lock().unwrap(), ["internal", "provider"]);
+    }
+
+    #[derive(Debug, Error)]
+    #[error("cannot reach test transport")]
+    struct UnreachableError(#[source] io::Error);
+
+    impl AnyError for UnreachableError {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    struct UnreachableSender(io::ErrorKind);
+
+    #[async_trait]
+    impl SendMessage for UnreachableSender {
+        async fn send_message(&self, _msg: &[u8]) -> AnyResult<SentMessage> {
+            Err(Box::new(UnreachableError(io::Error::from(self.0))))
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let handler: Arc<SendFallbackEventHandler> = {
+            let events = events.clone();
+            Arc::new(move |event| {
+                let events = events.clone();
+                Box::pin(async move {
+                    events.lock().unwrap().push(event);
+                    Ok(())
+                })
+            })
+        };
+
+        let router = SendMessageRouter::new(Box::new(UnreachableSender(
+            io::ErrorKind::ConnectionRefused,
+        )))
+        .with_routes([SendRouteConfig {
+            transport: "internal".into(),
+            recipients: Some("*@internal.corp".into()),
+            header: None,
+        }])
+        .with_transport(
+            "internal",
+            Box::new(UnreachableSender(io::ErrorKind::PermissionDenied)),
+        )
+        .with_transport("sendmail", Box::new(TestSender(sent.clone(), "sendmail")))
+        .with_fallback("sendmail")
+        .with_fallback_handler(handler);
+
+        let msg = b"From: alice@internal.corp\r\nTo: bob@localhost\r\n\r\nHello!";
+        router.send_message(msg).await.unwrap();
+
+        // permanent errors do not lead to the fallback transport
+        let msg = b"From: alice@internal.corp\r\nTo: bob@internal.corp\r\n\r\nHello!";
+        assert!(router.send_message(msg).await.is_err());
+
+        assert_eq!(*sent.lock().unwrap(), ["sendmail"]);
+        assert_eq!(
+            *events.lock().unwrap(),
+            [SendFallbackEvent {
+                transport: None,
+                fallback: "sendmail".into(),
+                reason: "cannot reach test transport".into(),
+            }]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use mail_parser::MessageParser;
 
-use super::SendMessage;
+use super::{hook::run_pre_send_hooks, SendMessage, SentMessage};
 use crate::{debug, email::error::Error, info, sendmail::SendmailContextSync, AnyResult};
 
 #[derive(Clone)]
@@ -25,38 +25,30 @@ pub fn some_new_boxed(ctx: &SendmailContextSync) -> Option<Box<dyn SendMessage>>
 
 #[async_trait]
 impl SendMessage for SendSendmailMessage {
-    async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
         info!("sending sendmail message");
 
-        let buffer: Vec<u8>;
-        let mut msg = MessageParser::new().parse(msg).unwrap_or_else(|| {
+        let (msg, _) = run_pre_send_hooks(&self.ctx.account_config, msg).await?;
+        let msg = MessageParser::new().parse(msg.as_ref()).unwrap_or_else(|| {
             debug!("cannot parse raw message");
             Default::default()
         });
 
-        if let Some(cmd) = self.ctx.account_config.find_message_pre_send_hook() {
-            match cmd.run_with(msg.raw_message()).await {
-                Ok(res) => {
-                    buffer = res.into();
-                    msg = MessageParser::new().parse(&buffer).unwrap_or_else(|| {
-                        debug!("cannot parse raw message after pre-send hook");
-                        Default::default()
-                    });
-                }
-                Err(_err) => {
-                    debug!("cannot execute pre-send hook: {_err}");
-                    debug!("{_err:?}");
-                }
-            }
-        };
+        #[cfg(feature = "dkim")]
+        let signed = super::dkim::sign_message(&self.ctx.account_config, msg.raw_message).await?;
+        #[cfg(not(feature = "dkim"))]
+        let signed = msg.raw_message;
 
+        // NOTE: the Bcc header is kept, since sendmail-compatible
+        // commands read recipients from headers then strip it
         self.ctx
             .sendmail_config
             .cmd
-            .run_with(msg.raw_message())
+            .run_with(signed.as_ref())
             .await
             .map_err(Error::RunSendmailCommandError)?;
 
-        Ok(())
+        // NOTE: sendmail does not give any queue identifier back
+        Ok(SentMessage::default())
     }
 }
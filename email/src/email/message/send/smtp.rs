@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use super::SendMessage;
+use super::{SendMessage, SendMessageOptions, SendReport, SentMessage};
 use crate::{info, smtp::SmtpContextSync, AnyResult};
 
 #[derive(Clone)]
@@ -24,12 +24,25 @@ pub fn some_new_boxed(ctx: &SmtpContextSync) -> Option<Box<dyn SendMessage>> {
 
 #[async_trait]
 impl SendMessage for SendSmtpMessage {
-    async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
         info!("sending smtp message");
 
         let mut ctx = self.ctx.lock().await;
-        ctx.send(msg).await?;
+        let sent = ctx.send(msg).await?;
 
-        Ok(())
+        Ok(sent)
+    }
+
+    async fn send_message_with_options(
+        &self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> AnyResult<SendReport> {
+        info!("sending smtp message with options {opts:?}");
+
+        let mut ctx = self.ctx.lock().await;
+        let report = ctx.send_with_options(msg, opts).await?;
+
+        Ok(report)
     }
 }
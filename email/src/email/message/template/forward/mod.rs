@@ -306,6 +306,12 @@ pub async fn build(self) -> Result<Template, Error> {
     }
 }
 
+crate::compat::deprecated_type!(
+    ForwardTplBuilder<'a> => ForwardTemplateBuilder,
+    "0.26.0",
+    "use `ForwardTemplateBuilder` instead"
+);
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -13,10 +13,17 @@
     MessageBuilder,
 };
 use mml::MimeInterpreterBuilder;
+use tokio::io::AsyncRead;
 
 use self::config::NewTemplateSignatureStyle;
 use super::{Template, TemplateBody, TemplateCursor};
-use crate::{account::config::AccountConfig, email::error::Error};
+use crate::{
+    account::config::AccountConfig,
+    email::{
+        error::Error,
+        message::attachment::{AttachmentSource, DEFAULT_MAX_SIZE},
+    },
+};
 
 /// The new template builder.
 ///
@@ -38,6 +45,12 @@ pub struct NewTemplateBuilder {
     /// this one is `None`.
     signature_style: Option<NewTemplateSignatureStyle>,
 
+    /// Attachments to add to the template.
+    attachments: Vec<AttachmentSource>,
+
+    /// The maximum size of each attachment, in bytes.
+    attachment_max_size: usize,
+
     /// Template interpreter instance.
     pub interpreter: MimeInterpreterBuilder,
 }
@@ -54,6 +67,8 @@ pub fn new(config: Arc<AccountConfig>) -> Self {
             headers: Vec::new(),
             body: String::new(),
             signature_style: None,
+            attachments: Vec::new(),
+            attachment_max_size: DEFAULT_MAX_SIZE,
             interpreter,
         }
     }
@@ -125,6 +140,46 @@ pub fn with_signature_style(mut self, style: impl Into<NewTemplateSignatureStyle
         self
     }
 
+    /// Add an attachment following the builder pattern.
+    ///
+    /// Attachment sources are read when the template is built.
+    pub fn with_attachment(mut self, attachment: impl Into<AttachmentSource>) -> Self {
+        self.attachments.push(attachment.into());
+        self
+    }
+
+    /// Add an attachment read from the given async stream, following
+    /// the builder pattern.
+    ///
+    /// The MIME type of the attachment is guessed from its content.
+    pub fn with_attachment_reader(
+        self,
+        filename: impl ToString,
+        reader: impl AsyncRead + Send + Unpin + 'static,
+    ) -> Self {
+        self.with_attachment(AttachmentSource::Reader {
+            filename: Some(filename.to_string()),
+            mime: None,
+            reader: Box::new(reader),
+        })
+    }
+
+    /// Add an attachment downloaded from the given HTTP(S) URL,
+    /// following the builder pattern.
+    #[cfg(feature = "attachment-url")]
+    pub fn with_attachment_url(self, uri: hyper::Uri) -> Self {
+        self.with_attachment(AttachmentSource::Url(uri))
+    }
+
+    /// Set the maximum size of each attachment, in bytes, following
+    /// the builder pattern.
+    ///
+    /// Defaults to [`DEFAULT_MAX_SIZE`].
+    pub fn with_attachment_max_size(mut self, max_size: usize) -> Self {
+        self.attachment_max_size = max_size;
+        self
+    }
+
     /// Set the template interpreter following the builder pattern.
     pub fn with_interpreter(mut self, interpreter: MimeInterpreterBuilder) -> Self {
         self.interpreter = interpreter;
@@ -179,6 +234,12 @@ pub async fn build(self) -> Result<Template, Error> {
             }
         }
 
+        for source in self.attachments {
+            let attachment = source.read(self.attachment_max_size).await?;
+            let filename = attachment.filename.unwrap_or_else(|| "attachment".into());
+            msg = msg.attachment(attachment.mime, filename, attachment.body);
+        }
+
         let content = self
             .interpreter
             .build()
@@ -190,6 +251,12 @@ pub async fn build(self) -> Result<Template, Error> {
     }
 }
 
+crate::compat::deprecated_type!(
+    NewTplBuilder => NewTemplateBuilder,
+    "0.26.0",
+    "use `NewTemplateBuilder` instead"
+);
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
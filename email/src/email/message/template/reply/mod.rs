@@ -426,6 +426,12 @@ pub async fn build(self) -> Result<Template, Error> {
     }
 }
 
+crate::compat::deprecated_type!(
+    ReplyTplBuilder<'a> => ReplyTemplateBuilder,
+    "0.26.0",
+    "use `ReplyTemplateBuilder` instead"
+);
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
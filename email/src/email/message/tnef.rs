@@ -0,0 +1,357 @@
+//! # TNEF
+//!
+//! Module dedicated to Outlook TNEF attachments. Exchange sometimes
+//! sends messages with a single `application/ms-tnef` part (usually
+//! named `winmail.dat`), which hides the real attachments. This
+//! module decodes them, so that they can be listed and extracted
+//! like normal parts.
+//!
+//! https://learn.microsoft.com/en-us/openspecs/exchange_server_protocols/ms-oxtnef
+
+use super::attachment::Attachment;
+use crate::email::error::{Error, Result};
+
+/// The signature every TNEF stream starts with.
+const SIGNATURE: u32 = 0x223E_9F78;
+
+/// The level of attributes related to attachments.
+const LEVEL_ATTACHMENT: u8 = 0x02;
+
+/// The attribute starting a new attachment.
+const ATT_ATTACH_REND_DATA: u16 = 0x9002;
+/// The attribute containing the (short) filename of an attachment.
+const ATT_ATTACH_TITLE: u16 = 0x8010;
+/// The attribute containing the content of an attachment.
+const ATT_ATTACH_DATA: u16 = 0x800F;
+/// The attribute containing the MAPI properties of an attachment.
+const ATT_ATTACHMENT: u16 = 0x9005;
+
+/// The MAPI property containing the long filename of an attachment.
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+/// The MAPI property containing the MIME type of an attachment.
+const PR_ATTACH_MIME_TAG: u16 = 0x370E;
+
+/// The flag of multi-valued MAPI property types.
+const MV_FLAG: u16 = 0x1000;
+
+/// Return `true` if the given attachment MIME type or filename
+/// denotes a TNEF attachment.
+pub fn is_tnef(mime: &str, filename: Option<&str>) -> bool {
+    mime.eq_ignore_ascii_case("application/ms-tnef")
+        || mime.eq_ignore_ascii_case("application/vnd.ms-tnef")
+        || filename.is_some_and(|name| name.eq_ignore_ascii_case("winmail.dat"))
+}
+
+/// Decode the attachments of the given TNEF stream.
+///
+/// The MIME type of attachments is taken from their MAPI properties
+/// when available, otherwise it is guessed from their content.
+pub fn decode(data: &[u8]) -> Result<Vec<Attachment>> {
+    let mut reader = Reader::new(data);
+
+    if reader.u32() != Some(SIGNATURE) {
+        return Err(Error::ParseTnefError("invalid signature"));
+    }
+
+    // legacy key, not used anymore
+    reader.u16().ok_or(Error::ParseTnefError("missing key"))?;
+
+    let mut attachments = Vec::new();
+    let mut current: Option<TnefAttachment> = None;
+
+    while !reader.is_empty() {
+        let level = reader.u8();
+        let id = reader.u32();
+        let len = reader.u32();
+
+        let (Some(level), Some(id), Some(len)) = (level, id, len) else {
+            return Err(Error::ParseTnefError("truncated attribute"));
+        };
+
+        let value = reader
+            .bytes(len as usize)
+            .ok_or(Error::ParseTnefError("truncated attribute value"))?;
+
+        // checksum, not verified
+        reader
+            .u16()
+            .ok_or(Error::ParseTnefError("missing attribute checksum"))?;
+
+        if level != LEVEL_ATTACHMENT {
+            continue;
+        }
+
+        match id as u16 {
+            ATT_ATTACH_REND_DATA => {
+                attachments.extend(current.take().and_then(TnefAttachment::into_attachment));
+                current = Some(TnefAttachment::default());
+            }
+            ATT_ATTACH_TITLE => {
+                let attachment = current.get_or_insert_with(Default::default);
+                attachment.title = Some(decode_string8(value));
+            }
+            ATT_ATTACH_DATA => {
+                let attachment = current.get_or_insert_with(Default::default);
+                attachment.data = Some(value.to_vec());
+            }
+            ATT_ATTACHMENT => {
+                let attachment = current.get_or_insert_with(Default::default);
+                // MAPI properties only give extra information, they
+                // are ignored if they cannot be decoded
+                let _ = attachment.read_props(value);
+            }
+            _ => (),
+        }
+    }
+
+    attachments.extend(current.and_then(TnefAttachment::into_attachment));
+
+    Ok(attachments)
+}
+
+/// An attachment being decoded.
+#[derive(Debug, Default)]
+struct TnefAttachment {
+    title: Option<String>,
+    long_filename: Option<String>,
+    mime: Option<String>,
+    data: Option<Vec<u8>>,
+}
+
+impl TnefAttachment {
+    /// Read the interesting MAPI properties of the attachment.
+    fn read_props(&mut self, props: &[u8]) -> Option<()> {
+        let mut reader = Reader::new(props);
+        let count = reader.u32()?;
+
+        for _ in 0..count {
+            let ty = reader.u16()?;
+            let id = reader.u16()?;
+
+            // named properties are followed by their name
+            if id >= 0x8000 {
+                reader.bytes(16)?;
+                match reader.u32()? {
+                    0 => {
+                        reader.u32()?;
+                    }
+                    _ => {
+                        let len = reader.u32()?;
+                        reader.padded_bytes(len as usize)?;
+                    }
+                }
+            }
+
+            let values = if ty & MV_FLAG != 0 { reader.u32()? } else { 1 };
+
+            for _ in 0..values {
+                let value = reader.prop_value(ty & !MV_FLAG, ty & MV_FLAG != 0)?;
+
+                match id {
+                    PR_ATTACH_LONG_FILENAME => {
+                        self.long_filename = value.map(|value| decode_prop_string(ty, value));
+                    }
+                    PR_ATTACH_MIME_TAG => {
+                        self.mime = value.map(|value| decode_prop_string(ty, value));
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    fn into_attachment(self) -> Option<Attachment> {
+        let body = self.data?;
+
+        let mime = self
+            .mime
+            .filter(|mime| !mime.is_empty())
+            .unwrap_or_else(|| tree_magic_mini::from_u8(&body).to_owned());
+
+        let filename = self
+            .long_filename
+            .or(self.title)
+            .filter(|name| !name.is_empty());
+
+        Some(Attachment {
+            filename,
+            mime,
+            body,
+        })
+    }
+}
+
+/// A little-endian reader over a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+
+        let (bytes, data) = self.data.split_at(len);
+        self.data = data;
+        Some(bytes)
+    }
+
+    /// Read the given number of bytes, then skip the padding up to
+    /// the next 4-byte boundary.
+    fn padded_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes(len)?;
+        self.bytes((4 - len % 4) % 4)?;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    /// Read a MAPI property value of the given type.
+    ///
+    /// Only variable-length values are returned, fixed-length values
+    /// are skipped.
+    fn prop_value(&mut self, ty: u16, multi: bool) -> Option<Option<&'a [u8]>> {
+        let size = match ty {
+            // null, unspecified
+            0x0000 | 0x0001 => 0,
+            // short, long, float, error, boolean
+            0x0002 | 0x0003 | 0x0004 | 0x000A | 0x000B => 4,
+            // double, currency, app time, i8, system time
+            0x0005 | 0x0006 | 0x0007 | 0x0014 | 0x0040 => 8,
+            // clsid
+            0x0048 => 16,
+            // object, string8, unicode, binary
+            0x000D | 0x001E | 0x001F | 0x0102 => {
+                // single-valued variable-length properties are
+                // prefixed by their number of values (always 1)
+                if !multi {
+                    self.u32()?;
+                }
+
+                let len = self.u32()?;
+                return self.padded_bytes(len as usize).map(Some);
+            }
+            _ => return None,
+        };
+
+        self.bytes(size)?;
+        Some(None)
+    }
+}
+
+/// Decode a null-terminated 8-bit string.
+fn decode_string8(value: &[u8]) -> String {
+    let value = value.split(|b| *b == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(value).into_owned()
+}
+
+/// Decode a MAPI string property, either 8-bit or UTF-16.
+fn decode_prop_string(ty: u16, value: &[u8]) -> String {
+    if ty & !MV_FLAG != 0x001F {
+        return decode_string8(value);
+    }
+
+    let value: Vec<u16> = value
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+
+    String::from_utf16_lossy(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, is_tnef, ATT_ATTACHMENT, ATT_ATTACH_DATA, ATT_ATTACH_REND_DATA, ATT_ATTACH_TITLE,
+        LEVEL_ATTACHMENT, PR_ATTACH_LONG_FILENAME, SIGNATURE,
+    };
+
+    fn attr(tnef: &mut Vec<u8>, level: u8, id: u16, value: &[u8]) {
+        tnef.push(level);
+        tnef.extend((id as u32 | 0x0006_0000).to_le_bytes());
+        tnef.extend((value.len() as u32).to_le_bytes());
+        tnef.extend(value);
+        tnef.extend(0u16.to_le_bytes());
+    }
+
+    #[test]
+    fn tnef_detection() {
+        assert!(is_tnef("application/ms-tnef", None));
+        assert!(is_tnef("application/octet-stream", Some("WINMAIL.DAT")));
+        assert!(!is_tnef("application/octet-stream", Some("report.dat")));
+    }
+
+    #[test]
+    fn decode_attachments() {
+        let mut props = Vec::new();
+        props.extend(1u32.to_le_bytes());
+        props.extend(0x001Eu16.to_le_bytes());
+        props.extend(PR_ATTACH_LONG_FILENAME.to_le_bytes());
+        props.extend(1u32.to_le_bytes());
+        props.extend(17u32.to_le_bytes());
+        props.extend(b"long-filename.txt\0\0\0");
+
+        let mut tnef = Vec::new();
+        tnef.extend(SIGNATURE.to_le_bytes());
+        tnef.extend(0u16.to_le_bytes());
+        // message-level attribute, ignored
+        attr(&mut tnef, 0x01, 0x8004, b"Subject\0");
+        attr(&mut tnef, LEVEL_ATTACHMENT, ATT_ATTACH_REND_DATA, &[0; 14]);
+        attr(
+            &mut tnef,
+            LEVEL_ATTACHMENT,
+            ATT_ATTACH_TITLE,
+            b"LONG-F~1.TXT\0",
+        );
+        attr(
+            &mut tnef,
+            LEVEL_ATTACHMENT,
+            ATT_ATTACH_DATA,
+            b"Hello, world!",
+        );
+        attr(&mut tnef, LEVEL_ATTACHMENT, ATT_ATTACHMENT, &props);
+        attr(&mut tnef, LEVEL_ATTACHMENT, ATT_ATTACH_REND_DATA, &[0; 14]);
+        attr(
+            &mut tnef,
+            LEVEL_ATTACHMENT,
+            ATT_ATTACH_TITLE,
+            b"EMPTY.TXT\0",
+        );
+        attr(&mut tnef, LEVEL_ATTACHMENT, ATT_ATTACH_DATA, b"");
+
+        let attachments = decode(&tnef).unwrap();
+
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(
+            attachments[0].filename.as_deref(),
+            Some("long-filename.txt")
+        );
+        assert_eq!(attachments[0].body, b"Hello, world!");
+        assert_eq!(attachments[1].filename.as_deref(), Some("EMPTY.TXT"));
+
+        assert!(decode(b"not a tnef stream").is_err());
+        assert!(decode(&tnef[..tnef.len() - 3]).is_err());
+    }
+}
@@ -17,6 +17,8 @@
 
 pub mod error;
 pub mod filter;
+#[cfg(any(feature = "notmuch", feature = "notmuch-remote"))]
+mod notmuch;
 pub mod parser;
 pub mod sort;
 
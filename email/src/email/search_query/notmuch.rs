@@ -0,0 +1,90 @@
+//! # Notmuch search emails query
+//!
+//! Module dedicated to the conversion of search emails queries into
+//! Notmuch search terms. It is shared by the local and the remote
+//! Notmuch backends.
+
+use chrono::TimeDelta;
+
+use super::{filter::SearchEmailsFilterQuery, SearchEmailsQuery};
+
+impl SearchEmailsQuery {
+    pub fn to_notmuch_search_query(&self) -> String {
+        self.filter
+            .as_ref()
+            .map(|f| f.to_notmuch_search_query())
+            .unwrap_or_default()
+    }
+}
+
+impl SearchEmailsFilterQuery {
+    pub fn to_notmuch_search_query(&self) -> String {
+        let mut query = String::new();
+
+        match self {
+            SearchEmailsFilterQuery::And(left, right) => {
+                query.push_str("(");
+                query.push_str(&left.to_notmuch_search_query());
+                query.push_str(") and (");
+                query.push_str(&right.to_notmuch_search_query());
+                query.push(')');
+            }
+            SearchEmailsFilterQuery::Or(left, right) => {
+                query.push_str("(");
+                query.push_str(&left.to_notmuch_search_query());
+                query.push_str(") or (");
+                query.push_str(&right.to_notmuch_search_query());
+                query.push(')');
+            }
+            SearchEmailsFilterQuery::Not(right) => {
+                query.push_str("not (");
+                query.push_str(&right.to_notmuch_search_query());
+                query.push_str(")");
+            }
+            SearchEmailsFilterQuery::Date(date) => {
+                query.push_str("date:");
+                query.push_str(&date.to_string());
+            }
+            SearchEmailsFilterQuery::BeforeDate(date) => {
+                // notmuch dates are inclusive, so we substract one
+                // day from the before date filter.
+                let date = *date - TimeDelta::try_days(1).unwrap();
+                query.push_str("date:..");
+                query.push_str(&date.to_string());
+            }
+            SearchEmailsFilterQuery::AfterDate(date) => {
+                // notmuch dates are inclusive, so we add one day to
+                // the after date filter.
+                let date = *date + TimeDelta::try_days(1).unwrap();
+                query.push_str("date:");
+                query.push_str(&date.to_string());
+                query.push_str("..");
+            }
+            SearchEmailsFilterQuery::From(pattern) => {
+                query.push_str("from:/");
+                query.push_str(pattern);
+                query.push('/');
+            }
+
+            SearchEmailsFilterQuery::To(pattern) => {
+                query.push_str("to:/");
+                query.push_str(pattern);
+                query.push('/');
+            }
+            SearchEmailsFilterQuery::Subject(pattern) => {
+                query.push_str("subject:");
+                query.push_str(pattern);
+            }
+            SearchEmailsFilterQuery::Body(pattern) => {
+                query.push_str("body:");
+                query.push_str(pattern);
+            }
+            SearchEmailsFilterQuery::Flag(flag) => {
+                query.push_str("tag:");
+                query.push_str(&flag.to_string());
+            }
+        };
+
+        query
+    }
+}
@@ -106,7 +106,7 @@ pub fn folder(&self) -> &str {
 /// The email synchronization cache hunk.
 ///
 /// Similar to the [`EmailSyncHunk`], except that this hunk is
-/// specific to the cache (SQLite).
+/// specific to the cache (Maildir).
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum EmailSyncCacheHunk {
     /// The email matching the given envelope identifier needs to be
@@ -18,17 +18,20 @@
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    backend::context::BackendContextBuilder,
+    backend::{context::BackendContextBuilder, Backend},
     debug,
     envelope::{
         get::GetEnvelope,
         list::{ListEnvelopes, ListEnvelopesOptions},
-        Envelope, Id, SingleId,
+        Envelope, Flags, Id, SingleId,
     },
     flag::{add::AddFlags, set::SetFlags, Flag},
+    folder::list::ListFolders,
+    maildir::MaildirContextSync,
     message::{add::AddMessage, peek::PeekMessages},
+    runtime,
     search_query::SearchEmailsQuery,
-    sync::{pool::SyncPoolContext, SyncDestination, SyncEvent},
+    sync::{pool::SyncPoolContext, SyncDestination, SyncEvent, SyncEventHandler},
     trace, AnyBoxedError,
 };
 
@@ -43,11 +46,21 @@ pub(crate) async fn sync<L, R>(
     R: BackendContextBuilder + 'static,
 {
     let mut report = EmailSyncReport::default();
+
+    for folder in folders {
+        let ctx = &ctx_ref;
+        let (handler, dry_run) = (&ctx.handler, ctx.dry_run);
+        let dest = SyncDestination::Left;
+        check_folder_identity(&ctx.left_cache, &ctx.left, folder, dest, handler, dry_run).await;
+        let dest = SyncDestination::Right;
+        check_folder_identity(&ctx.right_cache, &ctx.right, folder, dest, handler, dry_run).await;
+    }
+
     let patch = FuturesUnordered::from_iter(folders.iter().map(|folder| {
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
 
-        let left_cached_envelopes = tokio::spawn(async move {
+        let left_cached_envelopes = runtime::spawn(async move {
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.left_cache
                     .list_envelopes(
@@ -59,6 +72,8 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            changed_since: None,
+                            after: None,
                         },
                     )
                     .await
@@ -82,7 +97,7 @@ pub(crate) async fn sync<L, R>(
 
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
-        let left_envelopes = tokio::spawn(async move {
+        let left_envelopes = runtime::spawn(async move {
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.left
                     .list_envelopes(
@@ -94,6 +109,8 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            changed_since: None,
+                            after: None,
                         },
                     )
                     .await
@@ -117,7 +134,7 @@ pub(crate) async fn sync<L, R>(
 
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
-        let right_cached_envelopes = tokio::spawn(async move {
+        let right_cached_envelopes = runtime::spawn(async move {
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.right_cache
                     .list_envelopes(
@@ -129,6 +146,8 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            changed_since: None,
+                            after: None,
                         },
                     )
                     .await
@@ -152,7 +171,7 @@ pub(crate) async fn sync<L, R>(
 
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
-        let right_envelopes = tokio::spawn(async move {
+        let right_envelopes = runtime::spawn(async move {
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.right
                     .list_envelopes(
@@ -164,6 +183,8 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            changed_since: None,
+                            after: None,
                         },
                     )
                     .await
@@ -225,15 +246,17 @@ pub(crate) async fn sync<L, R>(
         .emit(&ctx_ref.handler)
         .await;
 
-    report.patch = FuturesUnordered::from_iter(patch.into_values().flatten().map(|hunk| {
+    let hunks = FuturesUnordered::from_iter(patch.into_values().flatten().map(|hunk| {
         let ctx = ctx_ref.clone();
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             let hunk_clone = hunk.clone();
             let handler = ctx.handler.clone();
 
             let task = async move {
+                let mut dropped = DroppedFlags::new(ctx.strict_flags);
+
                 if ctx.dry_run {
-                    return Ok(());
+                    return Ok(dropped.flags);
                 }
 
                 match hunk_clone {
@@ -241,17 +264,21 @@ pub(crate) async fn sync<L, R>(
                         let envelope = ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
                         let flags = envelope.flags.clone();
                         let msg = envelope.to_sync_cache_msg();
-                        ctx.left_cache
+                        let id = ctx
+                            .left_cache
                             .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                             .await?;
+                        dropped.check(&ctx.left_cache, &folder, &id, &flags).await;
                     }
                     EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
                         let envelope = ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
                         let flags = envelope.flags.clone();
                         let msg = envelope.to_sync_cache_msg();
-                        ctx.right_cache
+                        let id = ctx
+                            .right_cache
                             .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                             .await?;
+                        dropped.check(&ctx.right_cache, &folder, &id, &flags).await;
                     }
                     EmailSyncHunk::CopyThenCache(
                         folder,
@@ -266,9 +293,11 @@ pub(crate) async fn sync<L, R>(
                                 if refresh_source_cache {
                                     let flags = envelope.flags.clone();
                                     let msg = envelope.to_sync_cache_msg();
-                                    ctx.left_cache
+                                    let id = ctx
+                                        .left_cache
                                         .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                         .await?;
+                                    dropped.check(&ctx.left_cache, &folder, &id, &flags).await;
                                 };
                                 ctx.left.peek_messages(&folder, &id).await?
                             }
@@ -276,9 +305,11 @@ pub(crate) async fn sync<L, R>(
                                 if refresh_source_cache {
                                     let flags = envelope.flags.clone();
                                     let msg = envelope.to_sync_cache_msg();
-                                    ctx.right_cache
+                                    let id = ctx
+                                        .right_cache
                                         .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                         .await?;
+                                    dropped.check(&ctx.right_cache, &folder, &id, &flags).await;
                                 };
                                 ctx.right.peek_messages(&folder, &id).await?
                             }
@@ -295,26 +326,32 @@ pub(crate) async fn sync<L, R>(
                                     .left
                                     .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
                                     .await?;
-                                let envelope =
+                                let added =
                                     ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.left_cache
+                                dropped.compare(&envelope.flags, &added.flags);
+                                let flags = added.flags.clone();
+                                let msg = added.to_sync_cache_msg();
+                                let id = ctx
+                                    .left_cache
                                     .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                     .await?;
+                                dropped.check(&ctx.left_cache, &folder, &id, &flags).await;
                             }
                             SyncDestination::Right => {
                                 let id = ctx
                                     .right
                                     .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
                                     .await?;
-                                let envelope =
+                                let added =
                                     ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.right_cache
+                                dropped.compare(&envelope.flags, &added.flags);
+                                let flags = added.flags.clone();
+                                let msg = added.to_sync_cache_msg();
+                                let id = ctx
+                                    .right_cache
                                     .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                     .await?;
+                                dropped.check(&ctx.right_cache, &folder, &id, &flags).await;
                             }
                         };
                     }
@@ -342,25 +379,41 @@ pub(crate) async fn sync<L, R>(
                         ctx.left_cache
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
+                        let id = SingleId::from(&envelope.id);
+                        dropped
+                            .check(&ctx.left_cache, &folder, &id, &envelope.flags)
+                            .await;
                     }
                     EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
                         ctx.left
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
+                        let id = SingleId::from(&envelope.id);
+                        dropped
+                            .check(&ctx.left, &folder, &id, &envelope.flags)
+                            .await;
                     }
                     EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
                         ctx.right_cache
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
+                        let id = SingleId::from(&envelope.id);
+                        dropped
+                            .check(&ctx.right_cache, &folder, &id, &envelope.flags)
+                            .await;
                     }
                     EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
                         ctx.right
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
+                        let id = SingleId::from(&envelope.id);
+                        dropped
+                            .check(&ctx.right, &folder, &id, &envelope.flags)
+                            .await;
                     }
                 };
 
-                Ok(())
+                Ok(dropped.flags)
             };
 
             let output = task.await;
@@ -370,8 +423,8 @@ pub(crate) async fn sync<L, R>(
                 .await;
 
             match output {
-                Ok(()) => (hunk, None),
-                Err(err) => (hunk, Some(err)),
+                Ok(dropped) => (hunk, None, dropped),
+                Err(err) => (hunk, Some(err), Flags::default()),
             }
         })
     }))
@@ -388,9 +441,138 @@ pub(crate) async fn sync<L, R>(
     .collect::<Vec<_>>()
     .await;
 
+    for (hunk, err, dropped) in hunks {
+        report.push(hunk, err, dropped);
+    }
+
     SyncEvent::ProcessedAllEmailHunks
         .emit(&ctx_ref.handler)
         .await;
 
     Ok(report)
 }
+
+/// Invalidate the cache of the given folder if the identity of the
+/// source folder changed since the last synchronization, for example
+/// because it has been deleted then re-created outside of the
+/// library.
+///
+/// Errors are logged but not propagated: backends that do not
+/// support folder identities are never invalidated.
+async fn check_folder_identity(
+    cache: &Backend<MaildirContextSync>,
+    source: &impl ListFolders,
+    folder: &str,
+    dest: SyncDestination,
+    handler: &Option<Arc<SyncEventHandler>>,
+    dry_run: bool,
+) {
+    let identity = match source.folder_identity(folder).await {
+        Ok(Some(identity)) => identity,
+        Ok(None) => return,
+        Err(_err) => {
+            debug!("cannot get identity of {dest} folder {folder}: {_err}");
+            trace!("{_err:?}");
+            return;
+        }
+    };
+
+    let ctx = cache.context.lock().await;
+
+    let prev_identity = match ctx.get_folder_source_identity(folder) {
+        Ok(identity) => identity,
+        Err(_err) => {
+            debug!("cannot get cached identity of {dest} folder {folder}: {_err}");
+            trace!("{_err:?}");
+            return;
+        }
+    };
+
+    if prev_identity.as_ref() == Some(&identity) || dry_run {
+        return;
+    }
+
+    if prev_identity.is_some() {
+        debug!("identity of {dest} folder {folder} changed, invalidating its cache");
+
+        let entries = ctx
+            .get_maildir_from_folder_alias(folder)
+            .and_then(|mdir| Ok(mdir.read()?));
+
+        match entries {
+            Ok(entries) => {
+                for entry in entries {
+                    if let Err(_err) = entry.remove() {
+                        debug!("cannot remove cached entry {:?}: {_err}", entry.path());
+                    }
+                }
+            }
+            Err(_err) => {
+                debug!("cannot invalidate cache of {dest} folder {folder}: {_err}");
+                trace!("{_err:?}");
+                return;
+            }
+        }
+
+        SyncEvent::InvalidatedFolderCache(folder.to_owned(), dest)
+            .emit(handler)
+            .await;
+    }
+
+    if let Err(_err) = ctx.set_folder_source_identity(folder, &identity) {
+        debug!("cannot save identity of {dest} folder {folder}: {_err}");
+        trace!("{_err:?}");
+    }
+}
+
+/// The flags dropped by backends while processing a hunk.
+///
+/// Flags are only checked in strict mode, see
+/// [`FlagConfig::strict`](crate::flag::config::FlagConfig::strict).
+struct DroppedFlags {
+    strict: bool,
+    flags: Flags,
+}
+
+impl DroppedFlags {
+    fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            flags: Flags::default(),
+        }
+    }
+
+    /// Collect the expected flags missing from the actual ones.
+    fn compare(&mut self, expected: &Flags, actual: &Flags) {
+        if self.strict {
+            let dropped = expected.difference(actual).cloned();
+            self.flags.extend(dropped);
+        }
+    }
+
+    /// Get the given envelope back from the given backend, then
+    /// collect the expected flags it dropped.
+    ///
+    /// A failing check is not considered as an error: the hunk has
+    /// been processed anyway.
+    async fn check(
+        &mut self,
+        backend: &(impl GetEnvelope + ?Sized),
+        folder: &str,
+        id: &SingleId,
+        expected: &Flags,
+    ) {
+        if !self.strict {
+            return;
+        }
+
+        match backend.get_envelope(folder, id).await {
+            Ok(envelope) => self.compare(expected, &envelope.flags),
+            Err(_err) => {
+                let id = id.as_str();
+                debug!("cannot check flags of envelope {id} from folder {folder}: {_err}");
+                trace!("{_err:?}");
+            }
+        }
+    }
+}
@@ -3,12 +3,50 @@
 //! Module dedicated to email synchronization reporting. The main
 //! structure of this module is [`EmailSyncReport`].
 
-use super::hunk::EmailSyncHunk;
-use crate::AnyBoxedError;
+use super::{hunk::EmailSyncHunk, Error};
+use crate::{envelope::Flags, warn, AnyBoxedError};
 
 /// The email synchronization report.
 #[derive(Debug, Default)]
 pub struct EmailSyncReport {
     /// The list of processed hunks associated with an optional error.
     pub patch: Vec<(EmailSyncHunk, Option<AnyBoxedError>)>,
+
+    /// The list of hunks skipped because their message exceeds the
+    /// size limit of the target backend, associated with the
+    /// [`Error::AddMessageTooLarge`] error.
+    pub skipped: Vec<(EmailSyncHunk, AnyBoxedError)>,
+
+    /// The list of processed hunks whose target backend could not
+    /// represent some flags, associated with the dropped flags.
+    ///
+    /// Only filled in strict mode, see
+    /// [`FlagConfig::strict`](crate::flag::config::FlagConfig::strict).
+    pub dropped_flags: Vec<(EmailSyncHunk, Flags)>,
+}
+
+impl EmailSyncReport {
+    /// Add the given processed hunk to the report, as skipped if its
+    /// message exceeds the size limit of the target backend.
+    ///
+    /// The given flags are the ones dropped by the target backend
+    /// while processing the hunk.
+    pub(crate) fn push(&mut self, hunk: EmailSyncHunk, err: Option<AnyBoxedError>, dropped: Flags) {
+        if !dropped.is_empty() {
+            warn!("flag(s) {dropped} dropped by backend: {hunk}");
+            self.dropped_flags.push((hunk.clone(), dropped));
+        }
+
+        match err {
+            Some(err) if is_message_too_large(&err) => self.skipped.push((hunk, err)),
+            err => self.patch.push((hunk, err)),
+        }
+    }
+}
+
+fn is_message_too_large(err: &AnyBoxedError) -> bool {
+    matches!(
+        err.as_any().downcast_ref(),
+        Some(Error::AddMessageTooLarge { .. })
+    )
 }
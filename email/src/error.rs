@@ -1,6 +1,6 @@
 use std::{any::Any, error, result};
 
-use tokio::task::JoinError;
+use crate::{backend::kit, runtime::JoinError};
 
 /// The global any `Result` alias of the library.
 ///
@@ -24,6 +24,16 @@ fn as_any(&self) -> &dyn Any {
     }
 }
 
+impl dyn AnyError + Send {
+    /// Return an actionable, non-technical message for end users,
+    /// driven by the class of the error.
+    ///
+    /// See [`kit::user_message`].
+    pub fn user_message(&self, account: &str) -> String {
+        kit::user_message(self, account)
+    }
+}
+
 /// The global any boxed `Error` alias of the module.
 pub type AnyBoxedError = Box<dyn AnyError + Send + 'static>;
 
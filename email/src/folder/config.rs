@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use super::list::config::FolderListConfig;
 #[cfg(feature = "sync")]
@@ -15,8 +15,9 @@ pub struct FolderConfig {
     /// Define custom folder aliases.
     ///
     /// Aliases are resolved when calling backend features. There are
-    /// 4 special aliases that map to [`super::FolderKind`]: inbox,
-    /// draft(s), sent and trash. Other aliases map to folder names.
+    /// 6 special aliases that map to [`super::FolderKind`]: inbox,
+    /// draft(s), sent, trash, junk and archive. Other aliases map to
+    /// folder names.
     ///
     /// Note: folder aliases are case-insensitive.
     pub aliases: Option<HashMap<String, String>>,
@@ -24,6 +25,15 @@ pub struct FolderConfig {
     /// The configuration dedicated to folder listing.
     pub list: Option<FolderListConfig>,
 
+    /// The path to the local store of folder metadata (color,
+    /// display order, collapsed state).
+    ///
+    /// The local store is used by backends that cannot store
+    /// metadata server-side, like IMAP servers not supporting the
+    /// METADATA extension. The Maildir backend defaults to a
+    /// `.folder-metadata` file at the root of the Maildir.
+    pub metadata_store: Option<PathBuf>,
+
     #[cfg(feature = "sync")]
     /// The configuration dedicated to folder synchronization.
     pub sync: Option<FolderSyncConfig>,
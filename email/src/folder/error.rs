@@ -1,9 +1,11 @@
 use std::{any::Any, result};
 
 use thiserror::Error;
-use tokio::task::JoinError;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{
+    runtime::{Elapsed, JoinError},
+    AnyBoxedError, AnyError,
+};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -27,6 +29,9 @@ pub enum Error {
     #[error("maildir: cannot list current folder from {1}")]
     ListCurrentFolderMaildirError(#[source] maildirs::Error, std::path::PathBuf),
     #[cfg(feature = "maildir")]
+    #[error("cannot read maildir folder at {1}")]
+    ReadMaildirFolderError(#[source] maildirs::Error, std::path::PathBuf),
+    #[cfg(feature = "maildir")]
     #[error("cannot remove maildir entry at {1}")]
     RemoveMaildirEntryError(#[source] maildirs::Error, std::path::PathBuf),
     #[error("cannot parse folder kind {0}")]
@@ -35,6 +40,14 @@ pub enum Error {
     GetUidMissingImapError(u32),
     #[error("cannot gather folders: {0}")]
     FolderTasksFailed(JoinError),
+    #[error("cannot list folders of account {1}: timed out")]
+    ListAccountFoldersTimedOutError(#[source] Elapsed, String),
+    #[error("cannot read folder metadata store at {1}")]
+    ReadFolderMetadataStoreError(#[source] std::io::Error, std::path::PathBuf),
+    #[error("cannot write folder metadata store at {1}")]
+    WriteFolderMetadataStoreError(#[source] std::io::Error, std::path::PathBuf),
+    #[error("cannot store metadata of folder {0}: no local metadata store configured")]
+    FolderMetadataStoreNotConfiguredError(String),
 
     #[error("cannot sync: cannot list folders from left cache")]
     ListLeftFoldersCachedError(#[source] AnyBoxedError),
@@ -1,8 +1,8 @@
 use async_trait::async_trait;
-use utf7_imap::encode_utf7_imap as encode_utf7;
+use imap_next::imap_types::sequence::{Sequence, SequenceSet};
 
 use super::ExpungeFolder;
-use crate::{debug, imap::ImapContext, info, AnyResult};
+use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult};
 
 #[derive(Debug)]
 pub struct ExpungeImapFolder {
@@ -28,16 +28,39 @@ impl ExpungeFolder for ExpungeImapFolder {
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
         info!("expunging imap folder {folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("expunge_folder").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
         let _count = client.expunge_mailbox(&folder_encoded).await?;
         debug!("expunged {_count} messages from {folder}");
 
         Ok(())
     }
+
+    async fn expunge_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        info!("expunging imap messages {id} from folder {folder}");
+
+        let mut client = self.ctx.client_for("expunge_messages").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let uids: Vec<_> = id
+            .iter()
+            .filter_map(|id| Sequence::try_from(id).ok())
+            .collect();
+
+        let Ok(uids) = SequenceSet::try_from(uids) else {
+            return Ok(());
+        };
+
+        let _count = client.expunge_messages(&folder_encoded, uids).await?;
+        debug!("expunged {_count} messages from {folder}");
+
+        Ok(())
+    }
 }
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 
 use super::ExpungeFolder;
-use crate::{folder::error::Error, info, maildir::MaildirContextSync, AnyResult};
+use crate::{envelope::Id, folder::error::Error, info, maildir::MaildirContextSync, AnyResult};
 
 pub struct ExpungeMaildirFolder {
     ctx: MaildirContextSync,
@@ -43,4 +43,22 @@ async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
 
         Ok(())
     }
+
+    async fn expunge_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        info!("expunging maildir messages {id} from folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+
+        id.iter()
+            .filter_map(|id| mdir.find(id).ok().flatten())
+            .filter(|entry| entry.has_trash_flag())
+            .try_for_each(|entry| {
+                entry
+                    .remove()
+                    .map_err(|err| Error::RemoveMaildirEntryError(err, entry.path().to_owned()))
+            })?;
+
+        Ok(())
+    }
 }
@@ -5,7 +5,7 @@
 
 use async_trait::async_trait;
 
-use crate::AnyResult;
+use crate::{envelope::Id, AnyResult};
 
 #[async_trait]
 pub trait ExpungeFolder: Send + Sync {
@@ -14,4 +14,12 @@ pub trait ExpungeFolder: Send + Sync {
     /// The concept is similar to the IMAP expunge: it definitely
     /// deletes messages with [`Flag::Deleted`](crate::email::Flag).
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()>;
+
+    /// Expunge the given messages from the given folder.
+    ///
+    /// Unlike [`ExpungeFolder::expunge_folder`], only the given
+    /// messages with [`Flag::Deleted`](crate::email::Flag) are
+    /// definitely deleted. Other messages flagged as deleted, for
+    /// example by another client, are left untouched.
+    async fn expunge_messages(&self, folder: &str, id: &Id) -> AnyResult<()>;
 }
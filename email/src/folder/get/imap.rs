@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+
+use super::{find_folder, Folder, GetFolder};
+use crate::{debug, imap::ImapContext, info, AnyResult};
+
+#[derive(Debug, Clone)]
+pub struct GetImapFolder {
+    ctx: ImapContext,
+}
+
+impl GetImapFolder {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolder for GetImapFolder {
+    async fn get_folder(&self, folder: &str) -> AnyResult<Option<Folder>> {
+        info!("getting imap folder {folder}");
+
+        let config = &self.ctx.account_config;
+        let mut client = self.ctx.client_for("get_folder").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        // NOTE: the folder name is used as LIST pattern, which means
+        // that names containing wildcards can match other folders,
+        // hence the exact match below
+        let folders = client.list_mailboxes(config, &folder_encoded).await?;
+
+        let Some(mut folder) = find_folder(folders, &folder) else {
+            debug!("imap folder {folder} not found");
+            return Ok(None);
+        };
+
+        if folder.total.is_none() {
+            let status = client.mailbox_status(folder_encoded).await?;
+            folder.total = status.messages;
+            folder.unseen = status.unseen;
+        }
+
+        Ok(Some(folder))
+    }
+}
@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+
+use super::{Folder, GetFolder};
+use crate::{
+    backend::kit, debug, folder::stats::maildir::count_maildir_entries, info,
+    maildir::MaildirContextSync, AnyResult,
+};
+
+pub struct GetMaildirFolder {
+    ctx: MaildirContextSync,
+}
+
+impl GetMaildirFolder {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn GetFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn GetFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolder for GetMaildirFolder {
+    async fn get_folder(&self, folder: &str) -> AnyResult<Option<Folder>> {
+        info!("getting maildir folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let name = ctx.account_config.get_folder_alias(folder);
+
+        let mdir = match ctx.get_maildir_from_folder_alias(folder) {
+            Ok(mdir) if mdir.path().is_dir() => mdir,
+            Ok(_) => {
+                debug!("maildir folder {name} not found");
+                return Ok(None);
+            }
+            Err(_err) => {
+                debug!("maildir folder {name} not found: {_err}");
+                return Ok(None);
+            }
+        };
+
+        let stats = count_maildir_entries(&mdir, false)?;
+
+        Ok(Some(Folder {
+            kind: kit::find_folder_kind(&ctx.account_config, &name),
+            desc: mdir.path().display().to_string(),
+            name,
+            total: Some(stats.total),
+            unseen: Some(stats.unseen),
+            ..Default::default()
+        }))
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+
+use async_trait::async_trait;
+
+use super::{Folder, Folders};
+use crate::AnyResult;
+
+#[async_trait]
+pub trait GetFolder: Send + Sync {
+    /// Get the folder (alias mailbox) matching the given name,
+    /// without listing all the folders.
+    ///
+    /// The returned folder holds the kind, the backend-specific data
+    /// (like the IMAP hierarchy delimiter, see
+    /// [`crate::backend::extensions`]) and, when the backend can
+    /// compute them cheaply, the total and unseen messages counts.
+    ///
+    /// Returns `None` if the folder does not exist.
+    async fn get_folder(&self, folder: &str) -> AnyResult<Option<Folder>>;
+}
+
+/// Find the folder matching the given name among the given folders.
+///
+/// This is the in-memory fallback used by backends that cannot get a
+/// single folder by themselves. The INBOX is matched
+/// case-insensitively.
+pub fn find_folder(folders: Folders, name: &str) -> Option<Folder> {
+    folders.into_iter().find(|folder| {
+        if folder.is_inbox() || folder.name.eq_ignore_ascii_case("INBOX") {
+            name.eq_ignore_ascii_case("INBOX") || folder.name == name
+        } else {
+            folder.name == name
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_folder;
+    use crate::folder::{Folder, FolderKind, Folders};
+
+    #[test]
+    fn found_folder() {
+        let folders: Folders = [
+            (Some(FolderKind::Inbox), "INBOX"),
+            (Some(FolderKind::Sent), "Sent Items"),
+            (None, "Archive/2024"),
+        ]
+        .into_iter()
+        .map(|(kind, name)| Folder {
+            kind,
+            name: name.into(),
+            ..Default::default()
+        })
+        .collect();
+
+        let found = |name| find_folder(folders.clone(), name).map(|f| f.name);
+
+        assert_eq!(found("inbox").as_deref(), Some("INBOX"));
+        assert_eq!(found("Sent Items").as_deref(), Some("Sent Items"));
+        assert_eq!(found("Archive/2024").as_deref(), Some("Archive/2024"));
+        assert_eq!(found("archive/2024"), None);
+        assert_eq!(found("Archive"), None);
+    }
+}
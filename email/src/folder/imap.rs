@@ -1,13 +1,9 @@
-use imap_next::imap_types::{
-    core::{Atom, QuotedChar},
-    flag::FlagNameAttribute,
-    mailbox::Mailbox,
-};
-use utf7_imap::decode_utf7_imap as decode_utf7;
+use imap_next::imap_types::{core::QuotedChar, flag::FlagNameAttribute, mailbox::Mailbox};
 
 use super::{Error, FolderKind, Result};
 use crate::{
     account::config::AccountConfig,
+    backend::extensions::IMAP_DELIMITER,
     debug,
     folder::{Folder, Folders},
 };
@@ -15,16 +11,26 @@
 pub type ImapMailboxes = Vec<ImapMailbox>;
 
 impl Folders {
-    pub fn from_imap_mailboxes(config: &AccountConfig, mboxes: ImapMailboxes) -> Self {
+    /// Build folders from the given IMAP mailboxes, using the given
+    /// function to decode mailbox names.
+    ///
+    /// See [`ImapClient::decode_folder`](crate::imap::ImapClient::decode_folder).
+    pub fn from_imap_mailboxes(
+        config: &AccountConfig,
+        mboxes: ImapMailboxes,
+        decode: impl Fn(String) -> String,
+    ) -> Self {
         mboxes
             .into_iter()
-            .filter_map(|mbox| match Folder::try_from_imap_mailbox(config, &mbox) {
-                Ok(folder) => Some(folder),
-                Err(_err) => {
-                    debug!("skipping IMAP mailbox {:?}: {_err}", mbox.0.clone());
-                    None
-                }
-            })
+            .filter_map(
+                |mbox| match Folder::try_from_imap_mailbox(config, &mbox, &decode) {
+                    Ok(folder) => Some(folder),
+                    Err(_err) => {
+                        debug!("skipping IMAP mailbox {:?}: {_err}", mbox.0.clone());
+                        None
+                    }
+                },
+            )
             .collect()
     }
 }
@@ -38,7 +44,8 @@ pub fn from_imap_mailboxes(config: &AccountConfig, mboxes: ImapMailboxes) -> Sel
 impl Folder {
     fn try_from_imap_mailbox(
         config: &AccountConfig,
-        (mbox, _delim, attrs): &ImapMailbox,
+        (mbox, delim, attrs): &ImapMailbox,
+        decode: impl Fn(String) -> String,
     ) -> Result<Self> {
         let mbox = match mbox {
             Mailbox::Inbox => String::from("INBOX"),
@@ -51,11 +58,12 @@ fn try_from_imap_mailbox(
             return Err(Error::ParseImapFolderNotSelectableError(mbox.clone()));
         }
 
-        let name = decode_utf7(mbox.into());
+        let name = decode(mbox);
 
-        let kind = config
-            .find_folder_kind_from_alias(&name)
-            .or_else(|| find_folder_kind_from_imap_attrs(attrs.as_ref()))
+        // SPECIAL-USE attributes are set by the server, they take
+        // precedence over the configured aliases
+        let kind = find_folder_kind_from_imap_attrs(attrs.as_ref())
+            .or_else(|| config.find_folder_kind_from_alias(&name))
             .or_else(|| name.parse().ok());
 
         let desc = attrs.iter().fold(String::default(), |mut desc, attr| {
@@ -66,20 +74,72 @@ fn try_from_imap_mailbox(
             desc
         });
 
-        Ok(Folder { kind, name, desc })
+        let mut folder = Folder {
+            kind,
+            name,
+            desc,
+            ..Default::default()
+        };
+
+        if let Some(delim) = delim {
+            folder
+                .extensions
+                .set(IMAP_DELIMITER, delim.inner().to_string());
+        }
+
+        Ok(folder)
     }
 }
 
+/// Find the folder kind matching the given IMAP mailbox attributes.
+///
+/// Both SPECIAL-USE (RFC 6154) and the legacy Gmail XLIST attributes
+/// are supported. Attributes are matched case-insensitively.
 pub fn find_folder_kind_from_imap_attrs(attrs: &[FlagNameAttribute]) -> Option<FolderKind> {
     attrs.iter().find_map(|attr| {
-        if attr == &FlagNameAttribute::from(Atom::try_from("Sent").unwrap()) {
-            Some(FolderKind::Sent)
-        } else if attr == &FlagNameAttribute::from(Atom::try_from("Drafts").unwrap()) {
-            Some(FolderKind::Drafts)
-        } else if attr == &FlagNameAttribute::from(Atom::try_from("Trash").unwrap()) {
-            Some(FolderKind::Trash)
-        } else {
-            None
+        let attr = attr.to_string();
+        let attr = attr.trim_start_matches('\\');
+
+        match attr {
+            attr if attr.eq_ignore_ascii_case("Inbox") => Some(FolderKind::Inbox),
+            attr if attr.eq_ignore_ascii_case("Sent") => Some(FolderKind::Sent),
+            attr if attr.eq_ignore_ascii_case("Drafts") => Some(FolderKind::Drafts),
+            attr if attr.eq_ignore_ascii_case("Trash") => Some(FolderKind::Trash),
+            attr if attr.eq_ignore_ascii_case("Junk") => Some(FolderKind::Junk),
+            // XLIST
+            attr if attr.eq_ignore_ascii_case("Spam") => Some(FolderKind::Junk),
+            attr if attr.eq_ignore_ascii_case("Archive") => Some(FolderKind::Archive),
+            _ => None,
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use imap_next::imap_types::{core::Atom, flag::FlagNameAttribute};
+
+    use super::find_folder_kind_from_imap_attrs;
+    use crate::folder::FolderKind;
+
+    fn attr(name: &'static str) -> FlagNameAttribute<'static> {
+        FlagNameAttribute::from(Atom::try_from(name).unwrap())
+    }
+
+    #[test]
+    fn folder_kind_from_imap_attrs() {
+        let kind = find_folder_kind_from_imap_attrs(&[FlagNameAttribute::Noinferiors]);
+        assert_eq!(kind, None);
+
+        let kind = find_folder_kind_from_imap_attrs(&[attr("HasNoChildren"), attr("Sent")]);
+        assert_eq!(kind, Some(FolderKind::Sent));
+
+        let kind = find_folder_kind_from_imap_attrs(&[attr("junk")]);
+        assert_eq!(kind, Some(FolderKind::Junk));
+
+        let kind = find_folder_kind_from_imap_attrs(&[attr("Spam")]);
+        assert_eq!(kind, Some(FolderKind::Junk));
+
+        let kind = find_folder_kind_from_imap_attrs(&[attr("Archive")]);
+        assert_eq!(kind, Some(FolderKind::Archive));
+    }
+}
@@ -0,0 +1,245 @@
+//! # Folder listing across accounts
+//!
+//! Module dedicated to listing folders of many accounts at once, as
+//! multi-account clients do at startup. Accounts are processed
+//! concurrently, within a global concurrency bound so that slow
+//! servers do not exhaust resources, and each account has its own
+//! timeout so that one unreachable server does not block the others.
+//!
+//! Folders counts (like the IMAP STATUS response) are part of the
+//! result when the backend is able to provide them, see
+//! [`Folder::total`](crate::folder::Folder::total).
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+
+use super::{order::FolderOrder, ListFolders};
+use crate::{
+    debug,
+    folder::{Error, Folders},
+    runtime, trace, AnyBoxedError, AnyResult,
+};
+
+/// The default maximum number of accounts processed at the same time.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// The default timeout of a single account.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The options of [`list_accounts_folders`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListAccountsFoldersOptions {
+    /// The maximum number of accounts processed at the same time.
+    ///
+    /// Defaults to [`DEFAULT_CONCURRENCY`].
+    pub concurrency: usize,
+
+    /// The timeout of a single account.
+    ///
+    /// The timeout starts once the account is processed, which means
+    /// that the time spent waiting for a free slot does not count.
+    /// Defaults to [`DEFAULT_TIMEOUT`], `None` disables it.
+    pub timeout: Option<Duration>,
+
+    /// The custom folder order of the accounts, by account name.
+    ///
+    /// Folders of accounts without custom order keep the order of
+    /// their backend. See [`FolderOrder::from_account_config`].
+    pub orders: HashMap<String, FolderOrder>,
+}
+
+impl Default for ListAccountsFoldersOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: Some(DEFAULT_TIMEOUT),
+            orders: HashMap::new(),
+        }
+    }
+}
+
+/// The folders of many accounts.
+///
+/// A failing account does not prevent other accounts from being
+/// listed: results are partial, and errors are kept alongside.
+#[derive(Debug, Default)]
+pub struct AccountsFolders {
+    /// The folders of the accounts that could be listed, in the
+    /// order accounts were given.
+    pub folders: Vec<(String, Folders)>,
+
+    /// The errors of the accounts that could not be listed, in the
+    /// order accounts were given.
+    pub errors: Vec<(String, AnyBoxedError)>,
+}
+
+impl AccountsFolders {
+    /// Return `true` if all the accounts could be listed.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// List folders of the given accounts concurrently.
+///
+/// Accounts are given as pairs of account name and backend. At most
+/// [`ListAccountsFoldersOptions::concurrency`] accounts are processed
+/// at the same time.
+pub async fn list_accounts_folders<B>(
+    accounts: impl IntoIterator<Item = (String, Arc<B>)>,
+    opts: &ListAccountsFoldersOptions,
+) -> AccountsFolders
+where
+    B: ListFolders + ?Sized + 'static,
+{
+    let permits = Arc::new(Semaphore::new(opts.concurrency.max(1)));
+    let timeout = opts.timeout;
+
+    let tasks: Vec<_> = accounts
+        .into_iter()
+        .map(|(account, backend)| {
+            let permits = permits.clone();
+            let name = account.clone();
+
+            let task = runtime::spawn(async move {
+                // the semaphore is never closed
+                let _permit = permits.acquire_owned().await;
+                debug!("listing folders of account {name}");
+                list_folders(&*backend, &name, timeout).await
+            });
+
+            (account, task)
+        })
+        .collect();
+
+    let mut report = AccountsFolders::default();
+
+    for (account, task) in tasks {
+        let res = task.await.map_err(AnyBoxedError::from).and_then(|res| res);
+
+        match res {
+            Ok(mut folders) => {
+                if let Some(order) = opts.orders.get(&account) {
+                    order.sort(&mut folders);
+                }
+                report.folders.push((account, folders))
+            }
+            Err(err) => {
+                debug!("cannot list folders of account {account}: {err}");
+                trace!("{err:?}");
+                report.errors.push((account, err));
+            }
+        }
+    }
+
+    report
+}
+
+async fn list_folders<B>(
+    backend: &B,
+    account: &str,
+    timeout: Option<Duration>,
+) -> AnyResult<Folders>
+where
+    B: ListFolders + ?Sized,
+{
+    let Some(timeout) = timeout else {
+        return backend.list_folders().await;
+    };
+
+    match runtime::timeout(timeout, backend.list_folders()).await {
+        Ok(res) => res,
+        Err(err) => Err(Error::ListAccountFoldersTimedOutError(err, account.to_owned()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{list_accounts_folders, ListAccountsFoldersOptions};
+    use crate::{
+        backend::kit::{classify_error, ErrorClass},
+        folder::{list::ListFolders, Folder, Folders},
+        runtime, AnyResult,
+    };
+
+    struct TestBackend {
+        delay: Duration,
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+    }
+
+    /// Decrement the running counter on drop, so that timed out
+    /// listings are counted as well.
+    struct Running(Arc<AtomicUsize>);
+
+    impl Drop for Running {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl ListFolders for TestBackend {
+        async fn list_folders(&self) -> AnyResult<Folders> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            let _running = Running(self.running.clone());
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            runtime::sleep(self.delay).await;
+
+            Ok(Folders::from_iter([Folder {
+                name: "INBOX".into(),
+                ..Default::default()
+            }]))
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_results_within_bounds() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let accounts = [10, 10, 500, 10, 10]
+            .into_iter()
+            .enumerate()
+            .map(|(i, ms)| {
+                let backend = TestBackend {
+                    delay: Duration::from_millis(ms),
+                    running: running.clone(),
+                    max_running: max_running.clone(),
+                };
+                (format!("account{i}"), Arc::new(backend))
+            });
+
+        let opts = ListAccountsFoldersOptions {
+            concurrency: 2,
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+
+        let report = list_accounts_folders(accounts, &opts).await;
+
+        let listed: Vec<_> = report
+            .folders
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(listed, ["account0", "account1", "account3", "account4"]);
+
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, "account2");
+        assert_eq!(classify_error(&report.errors[0].1), ErrorClass::Transient);
+
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+    }
+}
@@ -10,4 +10,13 @@ pub struct FolderListConfig {
     /// A page size of 0 disables the pagination and displays all
     /// available folders.
     pub page_size: Option<usize>,
+
+    /// Define a custom order of listed folders.
+    ///
+    /// When defined, listed folders are sorted as follow: the INBOX
+    /// first, then the folders of this list (folder names or aliases)
+    /// in the given order, then the remaining folders alphabetically.
+    /// An empty list only sorts folders alphabetically, after the
+    /// INBOX. Listed folders keep the order of the backend otherwise.
+    pub order: Option<Vec<String>>,
 }
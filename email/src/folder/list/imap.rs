@@ -28,9 +28,10 @@ async fn list_folders(&self) -> AnyResult<Folders> {
         info!("listing imap folders");
 
         let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client_for("list_folders").await;
 
-        let folders = client.list_all_mailboxes(config).await?;
+        let mut folders = client.list_all_mailboxes(config).await?;
+        folders.sort_by_account_config(config);
 
         Ok(folders)
     }
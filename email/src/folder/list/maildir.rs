@@ -27,8 +27,14 @@ async fn list_folders(&self) -> AnyResult<Folders> {
         info!("listing maildir folders");
 
         let ctx = self.ctx.lock().await;
-        let folders = Folders::from_maildir_context(&ctx);
+        let mut folders = Folders::from_maildir_context(&ctx);
+        folders.sort_by_account_config(&ctx.account_config);
 
-        Ok(folders.into())
+        Ok(folders)
+    }
+
+    async fn folder_identity(&self, folder: &str) -> AnyResult<Option<String>> {
+        let ctx = self.ctx.lock().await;
+        Ok(Some(ctx.get_folder_identity(folder)?))
     }
 }
@@ -1,10 +1,14 @@
+pub mod accounts;
 pub mod config;
 #[cfg(feature = "imap")]
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+pub mod order;
 
 use async_trait::async_trait;
 
@@ -15,4 +19,14 @@
 pub trait ListFolders: Send + Sync {
     /// List all available folders (alias mailboxes).
     async fn list_folders(&self) -> AnyResult<Folders>;
+
+    /// Return the identity of the given folder, if the backend
+    /// supports it.
+    ///
+    /// The identity changes when the folder is deleted then
+    /// re-created, which allows the sync engine to invalidate its
+    /// cache.
+    async fn folder_identity(&self, _folder: &str) -> AnyResult<Option<String>> {
+        Ok(None)
+    }
 }
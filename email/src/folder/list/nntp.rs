@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+
+use super::ListFolders;
+use crate::{
+    folder::{Folder, Folders},
+    info,
+    nntp::NntpContextSync,
+    AnyResult,
+};
+
+pub struct ListNntpFolders {
+    ctx: NntpContextSync,
+}
+
+impl ListNntpFolders {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn ListFolders> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn ListFolders>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFolders for ListNntpFolders {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        info!("listing NNTP newsgroups");
+
+        let mut ctx = self.ctx.lock().await;
+        let groups = ctx.client.list_active().await?;
+
+        let mut folders: Folders = groups
+            .into_iter()
+            .map(|group| Folder {
+                kind: None,
+                name: group.name,
+                desc: format!("{}-{} ({})", group.low, group.high, group.status),
+                ..Default::default()
+            })
+            .collect();
+
+        folders.sort_by_account_config(&ctx.account_config);
+
+        Ok(folders)
+    }
+}
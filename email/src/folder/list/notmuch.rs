@@ -27,7 +27,8 @@ async fn list_folders(&self) -> AnyResult<Folders> {
         info!("listing notmuch folders via maildir");
 
         let ctx = self.ctx.lock().await;
-        let folders = Folders::from_maildir_context(&ctx.mdir_ctx);
+        let mut folders = Folders::from_maildir_context(&ctx.mdir_ctx);
+        folders.sort_by_account_config(&ctx.account_config);
 
         Ok(folders)
     }
@@ -0,0 +1,160 @@
+//! # Folder order
+//!
+//! Module dedicated to the custom order of listed folders. Backends
+//! list folders in their own order (IMAP servers in the order of the
+//! LIST response, Maildir in the order of the file system), which is
+//! rarely the order users expect. When an account defines a folder
+//! order (see [`FolderListConfig::order`]), listed folders are sorted
+//! as follow: the INBOX first, then the pinned folders in the order
+//! they were defined, then the remaining folders alphabetically.
+//!
+//! [`FolderListConfig::order`]: super::config::FolderListConfig::order
+
+use crate::{
+    account::config::AccountConfig,
+    folder::{Folder, FolderKind, Folders},
+};
+
+/// The folder order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FolderOrder {
+    /// The pinned folders, as pairs of optional folder kind and
+    /// folder name.
+    pinned: Vec<(Option<FolderKind>, String)>,
+}
+
+impl FolderOrder {
+    /// Create a new folder order from the given pinned folder names.
+    pub fn new(pinned: impl IntoIterator<Item = impl ToString>) -> Self {
+        let pinned = pinned
+            .into_iter()
+            .map(|name| {
+                let name = name.to_string();
+                (find_kind(&name), name)
+            })
+            .collect();
+
+        Self { pinned }
+    }
+
+    /// Create a new folder order from the given account
+    /// configuration, if the account defines one.
+    ///
+    /// Pinned folders are resolved using the folder aliases of the
+    /// account.
+    pub fn from_account_config(config: &AccountConfig) -> Option<Self> {
+        let pinned = config.get_folder_list_order()?;
+
+        let pinned = pinned
+            .iter()
+            .map(|name| (find_kind(name), config.get_folder_alias(name)))
+            .collect();
+
+        Some(Self { pinned })
+    }
+
+    /// Return the position of the given folder among the pinned
+    /// folders, if pinned.
+    fn find_pinned(&self, folder: &Folder) -> Option<usize> {
+        self.pinned.iter().position(|(kind, name)| {
+            let same_kind = kind.is_some() && *kind == folder.kind;
+            same_kind || *name == folder.name
+        })
+    }
+
+    /// Sort the given folders.
+    ///
+    /// Folders that are not pinned are sorted case-insensitively by
+    /// name. The sort is stable.
+    pub fn sort(&self, folders: &mut Folders) {
+        folders.sort_by_cached_key(|folder| {
+            if folder.is_inbox() || folder.name.eq_ignore_ascii_case(FolderKind::Inbox.as_str()) {
+                (0, 0, String::new())
+            } else if let Some(pos) = self.find_pinned(folder) {
+                (1, pos, String::new())
+            } else {
+                (2, 0, folder.name.to_lowercase())
+            }
+        })
+    }
+}
+
+impl Folders {
+    /// Sort folders using the folder order of the given account, if
+    /// the account defines one.
+    pub fn sort_by_account_config(&mut self, config: &AccountConfig) {
+        if let Some(order) = FolderOrder::from_account_config(config) {
+            order.sort(self)
+        }
+    }
+}
+
+/// Find the special folder kind matching the given name, if any.
+fn find_kind(name: &str) -> Option<FolderKind> {
+    Some(FolderKind::from(name)).filter(|kind| !kind.is_user_defined())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::FolderOrder;
+    use crate::{
+        account::config::AccountConfig,
+        folder::{
+            config::FolderConfig, list::config::FolderListConfig, Folder, FolderKind, Folders,
+        },
+    };
+
+    fn folders() -> Folders {
+        [
+            "Archives", "drafts", "Sent", "projects", "INBOX", "Zoo", "Work",
+        ]
+        .into_iter()
+        .map(|name| Folder {
+            kind: Some(FolderKind::from(name)).filter(|kind| !kind.is_user_defined()),
+            name: name.into(),
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    fn names(folders: &Folders) -> Vec<&str> {
+        folders.iter().map(|folder| folder.name.as_str()).collect()
+    }
+
+    #[test]
+    fn inbox_then_pinned_then_alphabetical() {
+        let mut folders = folders();
+        FolderOrder::new(["Work", "sent"]).sort(&mut folders);
+
+        assert_eq!(
+            names(&folders),
+            ["INBOX", "Work", "Sent", "Archives", "drafts", "projects", "Zoo"]
+        );
+    }
+
+    #[test]
+    fn account_config_order() {
+        let mut config = AccountConfig::default();
+
+        let mut folders = folders();
+        folders.sort_by_account_config(&config);
+        assert_eq!(names(&folders), names(&self::folders()));
+
+        config.folder = Some(FolderConfig {
+            aliases: Some(HashMap::from_iter([("job".into(), "Work".into())])),
+            list: Some(FolderListConfig {
+                order: Some(vec!["job".into(), "Unknown".into(), "Archives".into()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        folders.sort_by_account_config(&config);
+        assert_eq!(
+            names(&folders),
+            ["INBOX", "Work", "Archives", "drafts", "projects", "Sent", "Zoo"]
+        );
+    }
+}
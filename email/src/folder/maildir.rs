@@ -7,6 +7,7 @@
 
 use crate::{
     account::config::AccountConfig,
+    backend::kit,
     folder::{Folder, Folders},
     maildir::MaildirContext,
 };
@@ -19,15 +20,11 @@ impl Folders {
     /// Folders are parsed in parallel, using [`rayon`]. Only parses
     /// direct submaildirs (no recursion).
     pub fn from_maildir_context(ctx: &MaildirContext) -> Self {
-        Folders::from_iter(ctx.root.iter().map(|entry| {
-            Folder {
-                kind: ctx
-                    .account_config
-                    .find_folder_kind_from_alias(&entry.name)
-                    .or_else(|| entry.name.parse().ok()),
-                name: entry.name,
-                desc: entry.maildir.path().display().to_string(),
-            }
+        Folders::from_iter(ctx.root.iter().map(|entry| Folder {
+            kind: kit::find_folder_kind(&ctx.account_config, &entry.name),
+            name: entry.name,
+            desc: entry.maildir.path().display().to_string(),
+            ..Default::default()
         }))
     }
 }
@@ -40,11 +37,14 @@ impl Folder {
     /// be treated as a maildir folder).
     pub fn try_from_maildir(config: &AccountConfig, mdir: Maildir) -> Result<Self> {
         let name = mdir.name()?.to_owned();
-        let kind = config
-            .find_folder_kind_from_alias(&name)
-            .or_else(|| name.parse().ok());
+        let kind = kit::find_folder_kind(config, &name);
         let desc = mdir.path().display().to_string();
 
-        Ok(Folder { kind, name, desc })
+        Ok(Folder {
+            kind,
+            name,
+            desc,
+            ..Default::default()
+        })
     }
 }
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{store::FolderMetadataStore, FolderMetadata, GetFolderMetadata, SetFolderMetadata};
+use crate::{debug, folder::error::Error, imap::ImapContext, info, AnyResult};
+
+/// The prefix of the IMAP metadata entries.
+///
+/// Entries are private, which means that they are shared by the
+/// clients of the same user only.
+const ENTRY_PREFIX: &str = "/private/vendor/pimalaya/";
+
+/// Return the path to the local store used when the IMAP server does
+/// not support the METADATA extension.
+fn local_store_path(ctx: &ImapContext, folder: &str) -> Result<PathBuf, Error> {
+    ctx.account_config
+        .get_folder_metadata_store_path()
+        .ok_or_else(|| Error::FolderMetadataStoreNotConfiguredError(folder.to_owned()))
+}
+
+#[derive(Debug)]
+pub struct GetImapFolderMetadata {
+    ctx: ImapContext,
+}
+
+impl GetImapFolderMetadata {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetFolderMetadata> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetFolderMetadata>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolderMetadata for GetImapFolderMetadata {
+    async fn get_folder_metadata(&self, folder: &str) -> AnyResult<FolderMetadata> {
+        info!("getting metadata of imap folder {folder}");
+
+        let mut client = self.ctx.client_for("get_folder_metadata").await;
+        let folder = client.get_folder_alias(folder);
+
+        if !client.ext_metadata_supported() {
+            debug!("IMAP server does not support METADATA, using local store");
+            drop(client);
+
+            let path = local_store_path(&self.ctx, &folder)?;
+            let store = FolderMetadataStore::load(&path).await?;
+            return Ok(store.get(&folder));
+        }
+
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let entries: Vec<_> = FolderMetadata::KEYS
+            .iter()
+            .map(|key| format!("{ENTRY_PREFIX}{key}"))
+            .collect();
+        let entries: Vec<_> = entries.iter().map(String::as_str).collect();
+
+        let values = client.get_metadata(&folder_encoded, &entries).await?;
+        let mut metadata = FolderMetadata::default();
+
+        for (entry, value) in values {
+            if let Some(key) = entry.strip_prefix(ENTRY_PREFIX) {
+                metadata.set_entry(key, value.as_deref());
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[derive(Debug)]
+pub struct SetImapFolderMetadata {
+    ctx: ImapContext,
+}
+
+impl SetImapFolderMetadata {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn SetFolderMetadata> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn SetFolderMetadata>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SetFolderMetadata for SetImapFolderMetadata {
+    async fn set_folder_metadata(&self, folder: &str, metadata: &FolderMetadata) -> AnyResult<()> {
+        info!("setting metadata of imap folder {folder}");
+
+        let mut client = self.ctx.client_for("set_folder_metadata").await;
+        let folder = client.get_folder_alias(folder);
+
+        if !client.ext_metadata_supported() {
+            debug!("IMAP server does not support METADATA, using local store");
+            drop(client);
+
+            let path = local_store_path(&self.ctx, &folder)?;
+            FolderMetadataStore::update(&path, &folder, metadata.clone()).await?;
+            return Ok(());
+        }
+
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let entries: Vec<_> = metadata
+            .entries()
+            .into_iter()
+            .map(|(key, value)| (format!("{ENTRY_PREFIX}{key}"), value))
+            .collect();
+        let entries = entries
+            .iter()
+            .map(|(entry, value)| (entry.as_str(), value.clone()))
+            .collect();
+
+        client.set_metadata(&folder_encoded, entries).await?;
+
+        Ok(())
+    }
+}
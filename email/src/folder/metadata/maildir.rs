@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{store::FolderMetadataStore, FolderMetadata, GetFolderMetadata, SetFolderMetadata};
+use crate::{info, maildir::MaildirContextSync, AnyResult};
+
+/// The name of the default local store, at the root of the Maildir.
+const STORE_NAME: &str = ".folder-metadata";
+
+/// Return the path to the local store, from the account
+/// configuration or at the root of the Maildir.
+async fn store_path(ctx: &MaildirContextSync) -> PathBuf {
+    match ctx.account_config.get_folder_metadata_store_path() {
+        Some(path) => path,
+        None => ctx.lock().await.root.path().join(STORE_NAME),
+    }
+}
+
+#[derive(Clone)]
+pub struct GetMaildirFolderMetadata {
+    ctx: MaildirContextSync,
+}
+
+impl GetMaildirFolderMetadata {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn GetFolderMetadata> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn GetFolderMetadata>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolderMetadata for GetMaildirFolderMetadata {
+    async fn get_folder_metadata(&self, folder: &str) -> AnyResult<FolderMetadata> {
+        info!("getting metadata of maildir folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let path = store_path(&self.ctx).await;
+        let store = FolderMetadataStore::load(&path).await?;
+
+        Ok(store.get(&folder))
+    }
+}
+
+#[derive(Clone)]
+pub struct SetMaildirFolderMetadata {
+    ctx: MaildirContextSync,
+}
+
+impl SetMaildirFolderMetadata {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn SetFolderMetadata> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn SetFolderMetadata>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SetFolderMetadata for SetMaildirFolderMetadata {
+    async fn set_folder_metadata(&self, folder: &str, metadata: &FolderMetadata) -> AnyResult<()> {
+        info!("setting metadata of maildir folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let path = store_path(&self.ctx).await;
+        FolderMetadataStore::update(&path, &folder, metadata.clone()).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+//! # Folder metadata
+//!
+//! Module dedicated to the folder metadata used by clients to present
+//! folders (color, display order, collapsed state). Metadata is
+//! stored server-side when the backend supports it (IMAP METADATA
+//! extension), or in a local [`store::FolderMetadataStore`]
+//! otherwise, so that different clients of the same account share
+//! the same folder presentation.
+
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+pub mod store;
+
+use async_trait::async_trait;
+
+use crate::AnyResult;
+
+/// The folder metadata.
+///
+/// Undefined fields are left to the appreciation of clients.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderMetadata {
+    /// The color of the folder, for example `#d73a4a`.
+    pub color: Option<String>,
+
+    /// The display order of the folder, lowest first.
+    pub order: Option<u32>,
+
+    /// Whether the sub-folders of the folder are collapsed.
+    pub collapsed: Option<bool>,
+}
+
+impl FolderMetadata {
+    /// The keys of the metadata entries.
+    pub const KEYS: [&'static str; 3] = ["color", "order", "collapsed"];
+
+    /// Return `true` if none of the fields is defined.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Return the metadata entries, undefined ones included.
+    pub fn entries(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("color", self.color.clone()),
+            ("order", self.order.map(|order| order.to_string())),
+            (
+                "collapsed",
+                self.collapsed.map(|collapsed| collapsed.to_string()),
+            ),
+        ]
+    }
+
+    /// Set the entry matching the given key.
+    ///
+    /// Unknown keys and invalid values are ignored, since entries
+    /// can be written by other clients.
+    pub fn set_entry(&mut self, key: &str, value: Option<&str>) {
+        match key {
+            "color" => self.color = value.map(ToOwned::to_owned),
+            "order" => self.order = value.and_then(|order| order.parse().ok()),
+            "collapsed" => self.collapsed = value.and_then(|collapsed| collapsed.parse().ok()),
+            _ => (),
+        }
+    }
+}
+
+#[async_trait]
+pub trait GetFolderMetadata: Send + Sync {
+    /// Get the metadata of the given folder.
+    async fn get_folder_metadata(&self, folder: &str) -> AnyResult<FolderMetadata>;
+}
+
+#[async_trait]
+pub trait SetFolderMetadata: Send + Sync {
+    /// Set the metadata of the given folder.
+    ///
+    /// Undefined fields are removed from the folder metadata.
+    async fn set_folder_metadata(&self, folder: &str, metadata: &FolderMetadata) -> AnyResult<()>;
+}
@@ -0,0 +1,173 @@
+//! Module dedicated to the local folder metadata store.
+//!
+//! Backends that cannot store metadata server-side store it in a
+//! local file instead. Each line contains a folder name followed by
+//! its metadata entries, separated by tabulations. Names and values
+//! are percent-encoded:
+//!
+//! ```text
+//! Archives%2F2024	color=%23d73a4a	order=3	collapsed=true
+//! ```
+
+use std::{collections::BTreeMap, fmt, io, path::Path};
+
+use tokio::fs;
+
+use super::FolderMetadata;
+use crate::folder::{Error, Result};
+
+/// The local folder metadata store.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FolderMetadataStore {
+    folders: BTreeMap<String, FolderMetadata>,
+}
+
+impl FolderMetadataStore {
+    /// Parse the store from the given content.
+    ///
+    /// Parsing is lenient: invalid lines and entries are ignored.
+    pub fn parse(input: &str) -> Self {
+        let mut folders = BTreeMap::new();
+
+        for line in input.lines() {
+            let mut parts = line.split('\t');
+
+            let Some(Ok(folder)) = parts.next().map(urlencoding::decode) else {
+                continue;
+            };
+
+            if folder.is_empty() {
+                continue;
+            }
+
+            let mut metadata = FolderMetadata::default();
+
+            for entry in parts {
+                let Some((key, value)) = entry.split_once('=') else {
+                    continue;
+                };
+
+                if let Ok(value) = urlencoding::decode(value) {
+                    metadata.set_entry(key, Some(&value));
+                }
+            }
+
+            if !metadata.is_empty() {
+                folders.insert(folder.into_owned(), metadata);
+            }
+        }
+
+        Self { folders }
+    }
+
+    /// Load the store from the given file.
+    ///
+    /// A missing file is considered as an empty store.
+    pub async fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path).await {
+            Ok(content) => Ok(Self::parse(&content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::ReadFolderMetadataStoreError(err, path.to_owned())),
+        }
+    }
+
+    /// Save the store to the given file.
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .await
+                .map_err(|err| Error::WriteFolderMetadataStoreError(err, path.to_owned()))?;
+        }
+
+        fs::write(path, self.to_string())
+            .await
+            .map_err(|err| Error::WriteFolderMetadataStoreError(err, path.to_owned()))
+    }
+
+    /// Get the metadata of the given folder.
+    pub fn get(&self, folder: &str) -> FolderMetadata {
+        self.folders.get(folder).cloned().unwrap_or_default()
+    }
+
+    /// Set the metadata of the given folder.
+    pub fn set(&mut self, folder: &str, metadata: FolderMetadata) {
+        if metadata.is_empty() {
+            self.folders.remove(folder);
+        } else {
+            self.folders.insert(folder.to_owned(), metadata);
+        }
+    }
+
+    /// Load the store from the given file, set the metadata of the
+    /// given folder then save the store back.
+    pub async fn update(path: &Path, folder: &str, metadata: FolderMetadata) -> Result<()> {
+        let mut store = Self::load(path).await?;
+        store.set(folder, metadata);
+        store.save(path).await
+    }
+}
+
+impl fmt::Display for FolderMetadataStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (folder, metadata) in &self.folders {
+            write!(f, "{}", urlencoding::encode(folder))?;
+
+            for (key, value) in metadata.entries() {
+                if let Some(value) = value {
+                    write!(f, "\t{key}={}", urlencoding::encode(&value))?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FolderMetadataStore;
+    use crate::folder::metadata::FolderMetadata;
+
+    #[test]
+    fn parse_and_display() {
+        let store = FolderMetadataStore::parse(concat!(
+            "Archives%2F2024\tcolor=%23d73a4a\torder=3\tcollapsed=true\n",
+            "INBOX\torder=invalid\tunknown=value\n",
+            "Sent\torder=1\n",
+            "\tcolor=red\n",
+        ));
+
+        let expected = FolderMetadata {
+            color: Some("#d73a4a".into()),
+            order: Some(3),
+            collapsed: Some(true),
+        };
+
+        assert_eq!(store.get("Archives/2024"), expected);
+        assert_eq!(store.get("INBOX"), FolderMetadata::default());
+        assert_eq!(store.get("Sent").order, Some(1));
+
+        assert_eq!(
+            store.to_string(),
+            "Archives%2F2024\tcolor=%23d73a4a\torder=3\tcollapsed=true\nSent\torder=1\n"
+        );
+    }
+
+    #[test]
+    fn set_metadata() {
+        let mut store = FolderMetadataStore::default();
+
+        let metadata = FolderMetadata {
+            collapsed: Some(false),
+            ..Default::default()
+        };
+
+        store.set("Drafts", metadata.clone());
+        assert_eq!(store.get("Drafts"), metadata);
+
+        store.set("Drafts", FolderMetadata::default());
+        assert!(store.to_string().is_empty());
+    }
+}
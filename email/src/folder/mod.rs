@@ -8,7 +8,8 @@
 //! the account configuration.
 //!
 //! Backend features reside in their own module as well: [`add`],
-//! [`list`], [`expunge`], [`purge`], [`delete`].
+//! [`list`], [`get`], [`search`], [`expunge`], [`purge`], [`delete`],
+//! [`stats`], [`quota`], [`metadata`].
 //!
 //! Finally, the [`sync`] module contains everything needed to
 //! synchronize a remote folder with a local one.
@@ -17,12 +18,17 @@
 pub mod delete;
 mod error;
 pub mod expunge;
+pub mod get;
 #[cfg(feature = "imap")]
 pub mod imap;
 pub mod list;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod metadata;
 pub mod purge;
+pub mod quota;
+pub mod search;
+pub mod stats;
 #[cfg(feature = "sync")]
 pub mod sync;
 
@@ -38,12 +44,15 @@
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
+use crate::backend::extensions::Extensions;
 
 pub const INBOX: &str = "INBOX";
 pub const SENT: &str = "Sent";
 pub const DRAFT: &str = "Drafts";
 pub const DRAFTS: &str = "Drafts";
 pub const TRASH: &str = "Trash";
+pub const JUNK: &str = "Junk";
+pub const ARCHIVE: &str = "Archive";
 
 /// The folder kind enumeration.
 ///
@@ -80,6 +89,18 @@ pub enum FolderKind {
     /// in this folder are supposed to be deleted.
     Trash,
 
+    /// The kind of folder that contains junk emails.
+    ///
+    /// This kind of folder is used to store spam, or more generally
+    /// emails considered as unwanted.
+    Junk,
+
+    /// The kind of folder that contains archived emails.
+    ///
+    /// This kind of folder is used to store emails that are kept
+    /// out of the inbox.
+    Archive,
+
     /// The user-defined kind of folder.
     ///
     /// This kind of folder represents the alias as defined by the
@@ -112,6 +133,18 @@ pub fn is_trash(&self) -> bool {
         matches!(self, FolderKind::Trash)
     }
 
+    /// Return `true` if the current folder kind matches the Junk
+    /// variant.
+    pub fn is_junk(&self) -> bool {
+        matches!(self, FolderKind::Junk)
+    }
+
+    /// Return `true` if the current folder kind matches the Archive
+    /// variant.
+    pub fn is_archive(&self) -> bool {
+        matches!(self, FolderKind::Archive)
+    }
+
     /// Return `true` if the current folder kind matches the
     /// UserDefined variant.
     pub fn is_user_defined(&self) -> bool {
@@ -154,6 +187,25 @@ pub fn matches_trash(folder: impl AsRef<str>) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return `true` if the given string matches the Junk variant.
+    pub fn matches_junk(folder: impl AsRef<str>) -> bool {
+        folder
+            .as_ref()
+            .parse::<FolderKind>()
+            .map(|kind| kind.is_junk())
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the given string matches the Archive
+    /// variant.
+    pub fn matches_archive(folder: impl AsRef<str>) -> bool {
+        folder
+            .as_ref()
+            .parse::<FolderKind>()
+            .map(|kind| kind.is_archive())
+            .unwrap_or_default()
+    }
+
     /// Return the folder kind as string slice.
     pub fn as_str(&self) -> &str {
         match self {
@@ -161,6 +213,8 @@ pub fn as_str(&self) -> &str {
             Self::Sent => SENT,
             Self::Drafts => DRAFTS,
             Self::Trash => TRASH,
+            Self::Junk => JUNK,
+            Self::Archive => ARCHIVE,
             Self::UserDefined(alias) => alias.as_str(),
         }
     }
@@ -176,6 +230,8 @@ fn from_str(kind: &str) -> Result<Self> {
             kind if kind.eq_ignore_ascii_case(DRAFT) => Ok(Self::Drafts),
             kind if kind.eq_ignore_ascii_case(DRAFTS) => Ok(Self::Drafts),
             kind if kind.eq_ignore_ascii_case(TRASH) => Ok(Self::Trash),
+            kind if kind.eq_ignore_ascii_case(JUNK) => Ok(Self::Junk),
+            kind if kind.eq_ignore_ascii_case(ARCHIVE) => Ok(Self::Archive),
             kind => Err(Error::ParseFolderKindError(kind.to_owned())),
         }
     }
@@ -214,6 +270,23 @@ pub struct Folder {
     /// The description depends on the backend used: it can be IMAP
     /// attributes or Maildir path.
     pub desc: String,
+
+    /// The total number of messages contained in the folder.
+    ///
+    /// Only set by backends able to count messages while listing
+    /// folders, `None` otherwise.
+    pub total: Option<usize>,
+
+    /// The number of unseen messages contained in the folder.
+    ///
+    /// Only set by backends able to count messages while listing
+    /// folders, `None` otherwise.
+    pub unseen: Option<usize>,
+
+    /// The backend-specific data of the folder.
+    ///
+    /// See [`crate::backend::extensions`].
+    pub extensions: Extensions,
 }
 
 impl Folder {
@@ -249,6 +322,22 @@ pub fn is_trash(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return `true` if the folder kind matches the Junk variant.
+    pub fn is_junk(&self) -> bool {
+        self.kind
+            .as_ref()
+            .map(|kind| kind.is_junk())
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the folder kind matches the Archive variant.
+    pub fn is_archive(&self) -> bool {
+        self.kind
+            .as_ref()
+            .map(|kind| kind.is_archive())
+            .unwrap_or_default()
+    }
+
     /// Return the folder kind as string slice if existing, otherwise
     /// return the folder name as string slice.
     pub fn get_kind_or_name(&self) -> &str {
@@ -341,6 +430,7 @@ fn folder_inbox_foo() -> Folder {
             kind: Some(FolderKind::Inbox),
             name: "foo".to_owned(),
             desc: "1".to_owned(),
+            ..Default::default()
         }
     }
     fn folder_none_foo() -> Folder {
@@ -348,6 +438,7 @@ fn folder_none_foo() -> Folder {
             kind: None,
             name: "foo".to_owned(),
             desc: "2".to_owned(),
+            ..Default::default()
         }
     }
     fn folder_none_bar() -> Folder {
@@ -355,6 +446,7 @@ fn folder_none_bar() -> Folder {
             kind: None,
             name: "bar".to_owned(),
             desc: "3".to_owned(),
+            ..Default::default()
         }
     }
     fn folder_inbox_bar() -> Folder {
@@ -362,6 +454,7 @@ fn folder_inbox_bar() -> Folder {
             kind: Some(FolderKind::Inbox),
             name: "bar".to_owned(),
             desc: "4".to_owned(),
+            ..Default::default()
         }
     }
 
@@ -1,5 +1,4 @@
 use async_trait::async_trait;
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::PurgeFolder;
 use crate::{debug, imap::ImapContext, info, AnyResult};
@@ -28,12 +27,11 @@ impl PurgeFolder for PurgeImapFolder {
     async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
         info!("purging imap folder {folder}");
 
-        let mut client = self.ctx.client().await;
-        let config = &client.account_config;
+        let mut client = self.ctx.client_for("purge_folder").await;
 
-        let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
-        debug!("utf7 encoded folder: {folder_encoded}");
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
 
         client.purge_mailbox(&folder_encoded).await?;
 
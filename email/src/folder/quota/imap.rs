@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+
+use super::{GetQuota, Quota};
+use crate::{debug, imap::ImapContext, info, AnyResult};
+
+#[derive(Debug)]
+pub struct GetImapQuota {
+    ctx: ImapContext,
+}
+
+impl GetImapQuota {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetQuota> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetQuota>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetQuota for GetImapQuota {
+    async fn get_quota(&self, folder: &str) -> AnyResult<Vec<Quota>> {
+        info!("getting quota of imap folder {folder}");
+
+        let mut client = self.ctx.client_for("get_quota").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let quotas = client.get_quota_root(&folder_encoded).await?;
+        debug!("found {} quota roots for {folder}", quotas.len());
+
+        Ok(quotas)
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+
+use async_trait::async_trait;
+
+use crate::AnyResult;
+
+/// The usage and the limit of a quota resource.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct QuotaUsage {
+    /// The current usage of the resource.
+    pub usage: u64,
+
+    /// The maximum usage of the resource.
+    pub limit: u64,
+}
+
+impl QuotaUsage {
+    /// Return the usage ratio of the resource, between 0 and 1.
+    ///
+    /// A limit of 0 is considered as fully used.
+    pub fn ratio(&self) -> f64 {
+        if self.limit == 0 {
+            return 1.0;
+        }
+
+        self.usage as f64 / self.limit as f64
+    }
+
+    /// Return `true` if the usage reached the given ratio of the
+    /// limit, for example `0.9` for 90%.
+    pub fn exceeds(&self, ratio: f64) -> bool {
+        self.ratio() >= ratio
+    }
+}
+
+/// The quota applying to a folder.
+///
+/// A quota root can be shared by multiple folders, for example all
+/// the folders of an account.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct Quota {
+    /// The name of the quota root.
+    pub root: String,
+
+    /// The storage usage and limit, in kibibytes.
+    pub storage: Option<QuotaUsage>,
+
+    /// The number of messages and its limit.
+    pub messages: Option<QuotaUsage>,
+}
+
+#[async_trait]
+pub trait GetQuota: Send + Sync {
+    /// Get the quotas applying to the given folder.
+    ///
+    /// An empty list means that the folder is not subject to any
+    /// quota.
+    async fn get_quota(&self, folder: &str) -> AnyResult<Vec<Quota>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuotaUsage;
+
+    #[test]
+    fn quota_usage_ratio() {
+        let usage = QuotaUsage {
+            usage: 900,
+            limit: 1000,
+        };
+        assert_eq!(usage.ratio(), 0.9);
+        assert!(usage.exceeds(0.9));
+        assert!(!usage.exceeds(0.95));
+
+        let usage = QuotaUsage { usage: 0, limit: 0 };
+        assert!(usage.exceeds(1.0));
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+
+use super::{Folders, SearchFolders};
+use crate::{debug, imap::ImapContext, info, AnyResult};
+
+#[derive(Debug, Clone)]
+pub struct SearchImapFolders {
+    ctx: ImapContext,
+}
+
+impl SearchImapFolders {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn SearchFolders> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn SearchFolders>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SearchFolders for SearchImapFolders {
+    async fn search_folders(&self, patterns: &[String]) -> AnyResult<Folders> {
+        info!("searching imap folders matching {patterns:?}");
+
+        let config = &self.ctx.account_config;
+        let mut client = self.ctx.client_for("search_folders").await;
+
+        let mut names = HashSet::new();
+        let mut folders = Folders::default();
+
+        // NOTE: multiple patterns per LIST command require the
+        // LIST-EXTENDED extension, so one command is sent per pattern
+        for pattern in patterns {
+            let pattern = client.encode_folder(pattern);
+            debug!("encoded pattern: {pattern}");
+
+            let matching = client.list_mailboxes(config, &pattern).await?;
+            folders.extend(
+                matching
+                    .into_iter()
+                    .filter(|folder| names.insert(folder.name.clone())),
+            );
+        }
+
+        folders.sort_by_account_config(config);
+
+        Ok(folders)
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+
+use async_trait::async_trait;
+
+use super::Folders;
+use crate::AnyResult;
+
+#[async_trait]
+pub trait SearchFolders: Send + Sync {
+    /// List folders (alias mailboxes) whose name matches at least one
+    /// of the given patterns.
+    ///
+    /// Patterns follow the IMAP LIST syntax: `*` matches any sequence
+    /// of characters, `%` matches any sequence of characters except
+    /// the hierarchy delimiter. See [`matches_pattern`].
+    async fn search_folders(&self, patterns: &[String]) -> AnyResult<Folders>;
+}
+
+/// Keep only the folders matching at least one of the given
+/// patterns.
+///
+/// This is the in-memory fallback used by backends that cannot search
+/// folders by themselves.
+pub fn filter_folders(folders: Folders, patterns: &[String]) -> Folders {
+    folders
+        .into_iter()
+        .filter(|folder| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &folder.name))
+        })
+        .collect()
+}
+
+/// Return `true` if the given folder name matches the given IMAP LIST
+/// pattern.
+///
+/// `*` matches any sequence of characters, `%` matches any sequence
+/// of characters except the `/` hierarchy delimiter. Names are
+/// compared case-sensitively, except for the INBOX.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    if pattern.eq_ignore_ascii_case("INBOX") {
+        return name.eq_ignore_ascii_case("INBOX");
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    matches_chars(&pattern, &name)
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| matches_chars(rest, &name[i..])),
+        Some(('%', rest)) => {
+            let max = name.iter().position(|c| *c == '/').unwrap_or(name.len());
+            (0..=max).any(|i| matches_chars(rest, &name[i..]))
+        }
+        Some((c, rest)) => name.first() == Some(c) && matches_chars(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_folders, matches_pattern};
+    use crate::folder::{Folder, Folders};
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("*", "Archives/2024"));
+        assert!(matches_pattern("Archives/*", "Archives/2024/01"));
+        assert!(matches_pattern("Archives/%", "Archives/2024"));
+        assert!(!matches_pattern("Archives/%", "Archives/2024/01"));
+        assert!(matches_pattern("%/2024", "Archives/2024"));
+        assert!(!matches_pattern("archives/*", "Archives/2024"));
+        assert!(matches_pattern("inbox", "INBOX"));
+        assert!(!matches_pattern("Sent", "Sent Items"));
+    }
+
+    #[test]
+    fn filtered_folders() {
+        let folders: Folders = ["INBOX", "Sent", "Archives/2023", "Archives/2024"]
+            .into_iter()
+            .map(|name| Folder {
+                name: name.into(),
+                ..Default::default()
+            })
+            .collect();
+
+        let patterns = ["INBOX".into(), "Archives/*".into()];
+        let names: Vec<_> = filter_folders(folders, &patterns)
+            .iter()
+            .map(|folder| folder.name.clone())
+            .collect();
+
+        assert_eq!(names, ["INBOX", "Archives/2023", "Archives/2024"]);
+    }
+}
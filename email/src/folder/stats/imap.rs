@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+
+use super::{FolderStats, GetFolderStats};
+use crate::{debug, imap::ImapContext, info, AnyResult};
+
+#[derive(Debug, Clone)]
+pub struct GetImapFolderStats {
+    ctx: ImapContext,
+}
+
+impl GetImapFolderStats {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetFolderStats> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetFolderStats>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolderStats for GetImapFolderStats {
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats> {
+        info!("getting stats of imap folder {folder}");
+
+        let mut client = self.ctx.client_for("get_folder_stats").await;
+
+        let folder = client.get_folder_alias(folder);
+        let folder_encoded = client.encode_folder(&folder);
+        debug!("encoded folder: {folder_encoded}");
+
+        let status = client.mailbox_status(folder_encoded).await?;
+        debug!("imap folder {folder} status: {status:?}");
+
+        // NOTE: the size requires the STATUS=SIZE extension (RFC
+        // 8438), which is not supported by the IMAP codec yet
+        Ok(FolderStats {
+            total: status.messages.unwrap_or_default(),
+            unseen: status.unseen.unwrap_or_default(),
+            size: None,
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+
+use async_trait::async_trait;
+use maildirs::{Flag, Maildir};
+
+use super::{FolderStats, GetFolderStats};
+use crate::{
+    debug,
+    folder::{Error, Result},
+    info,
+    maildir::MaildirContextSync,
+    AnyResult,
+};
+
+pub struct GetMaildirFolderStats {
+    ctx: MaildirContextSync,
+}
+
+impl GetMaildirFolderStats {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn GetFolderStats> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn GetFolderStats>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolderStats for GetMaildirFolderStats {
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats> {
+        info!("getting stats of maildir folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let stats = count_maildir_entries(&mdir, true)?;
+        debug!("maildir folder {folder} stats: {stats:?}");
+
+        Ok(stats)
+    }
+}
+
+/// Count the entries of the given Maildir, and optionally sum their
+/// sizes.
+///
+/// Only entries file names and metadata are read, messages are not
+/// parsed.
+pub(crate) fn count_maildir_entries(mdir: &Maildir, with_size: bool) -> Result<FolderStats> {
+    let entries = mdir
+        .read()
+        .map_err(|err| Error::ReadMaildirFolderError(err, mdir.path().to_owned()))?;
+
+    let mut stats = FolderStats {
+        size: with_size.then_some(0),
+        ..Default::default()
+    };
+
+    for entry in entries {
+        stats.total += 1;
+
+        let seen = entry
+            .flags()
+            .map(|flags| flags.contains(&Flag::Seen))
+            .unwrap_or_default();
+
+        if !seen {
+            stats.unseen += 1;
+        }
+
+        if let Some(size) = stats.size.as_mut() {
+            // entries can be moved or removed concurrently, which is
+            // why the size is only approximate
+            *size += fs::metadata(entry.path())
+                .map(|meta| meta.len())
+                .unwrap_or_default();
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,37 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+#[cfg(feature = "notmuch")]
+pub mod notmuch;
+
+use async_trait::async_trait;
+
+use crate::AnyResult;
+
+/// The statistics of a folder.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderStats {
+    /// The total number of messages.
+    pub total: usize,
+
+    /// The number of unseen messages.
+    pub unseen: usize,
+
+    /// The approximate size of the messages, in bytes.
+    ///
+    /// `None` when the backend cannot compute it cheaply.
+    pub size: Option<u64>,
+}
+
+#[async_trait]
+pub trait GetFolderStats: Send + Sync {
+    /// Get the statistics of the given folder, without fetching its
+    /// envelopes.
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats>;
+}
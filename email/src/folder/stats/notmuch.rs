@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+
+use super::{FolderStats, GetFolderStats};
+use crate::{
+    debug,
+    folder::FolderKind,
+    info,
+    notmuch::{Error, NotmuchContextSync},
+    AnyResult,
+};
+
+pub struct GetNotmuchFolderStats {
+    ctx: NotmuchContextSync,
+}
+
+impl GetNotmuchFolderStats {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn GetFolderStats> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn GetFolderStats>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetFolderStats for GetNotmuchFolderStats {
+    async fn get_folder_stats(&self, folder: &str) -> AnyResult<FolderStats> {
+        info!("getting stats of notmuch folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let config = &ctx.account_config;
+        let db = ctx.open_db()?;
+
+        let folder = config.get_folder_alias(folder);
+        let mut query = if ctx.maildirpp() && FolderKind::matches_inbox(&folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        };
+        ctx.exclude_tags_from_query(&mut query);
+
+        let count = |query: &str| {
+            db.create_query(query)
+                .map_err(Error::CreateQueryError)?
+                .count_messages()
+                .map_err(Error::ExecuteQueryError)
+        };
+
+        let total = count(&query)?;
+        let unseen = count(&format!("{query} and tag:unread"))?;
+        debug!("notmuch folder {folder} has {total} messages, {unseen} unseen");
+
+        db.close().map_err(Error::CloseDatabaseError)?;
+
+        // NOTE: notmuch does not index message sizes
+        Ok(FolderStats {
+            total: total as usize,
+            unseen: unseen as usize,
+            size: None,
+        })
+    }
+}
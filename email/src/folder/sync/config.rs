@@ -2,6 +2,8 @@
 
 use std::collections::BTreeSet;
 
+use crate::{account::config::AccountConfig, folder::FolderKind};
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
@@ -14,6 +16,13 @@ pub struct FolderSyncConfig {
 
     #[cfg_attr(feature = "derive", serde(default))]
     pub permissions: FolderSyncPermissions,
+
+    /// The maximum number of messages a folder can contain to be
+    /// deleted by the synchronization without confirmation.
+    ///
+    /// See [`super::deletion::FolderDeletionPolicy`].
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub delete_threshold: Option<usize>,
 }
 
 /// The folder synchronization strategy.
@@ -44,6 +53,37 @@ pub fn matches(&self, folder: &str) -> bool {
             FolderSyncStrategy::Exclude(folders) => !folders.contains(folder),
         }
     }
+
+    /// Return the folder search patterns the strategy can be pushed
+    /// down to, using the folder aliases of the given account.
+    ///
+    /// Only inclusive strategies can be pushed down. Special folders
+    /// without alias cannot be searched by name, since their name is
+    /// only known after listing (for example via IMAP special-use
+    /// attributes): `None` is returned in this case, as for other
+    /// strategies, meaning that all folders should be listed.
+    pub fn search_patterns(&self, config: &AccountConfig) -> Option<Vec<String>> {
+        let FolderSyncStrategy::Include(folders) = self else {
+            return None;
+        };
+
+        folders
+            .iter()
+            .map(|folder| {
+                if folder.contains(|c| c == '*' || c == '%') {
+                    return None;
+                }
+
+                let kind = FolderKind::from(folder.as_str());
+
+                if kind.is_inbox() || kind.is_user_defined() {
+                    return Some(config.get_folder_alias(folder));
+                }
+
+                config.find_folder_alias(folder)
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
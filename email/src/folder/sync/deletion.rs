@@ -0,0 +1,188 @@
+//! # Folder deletion
+//!
+//! Module dedicated to the confirmation of folder deletions during
+//! synchronization. A transient listing failure on one side can look
+//! like a folder deletion, which would then be propagated to the
+//! other side. The [`FolderDeletionPolicy`] decides whether a folder
+//! can be deleted, based on the number of messages it contains and
+//! on an optional confirmation handler.
+
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+use super::hunk::FolderName;
+use crate::sync::SyncDestination;
+
+/// The folder deletion confirmation async handler.
+///
+/// The handler receives the deletion to confirm, and returns `true`
+/// if the folder can be deleted.
+pub type FolderDeletionConfirmationHandler =
+    dyn Fn(FolderDeletion) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync;
+
+/// The folder deletion, as submitted to the policy.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FolderDeletion {
+    /// The name of the folder to delete.
+    pub folder: FolderName,
+
+    /// The side the folder is deleted from.
+    pub destination: SyncDestination,
+
+    /// The number of messages contained in the folder, if it has
+    /// been counted.
+    pub messages: Option<usize>,
+}
+
+impl fmt::Display for FolderDeletion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            folder,
+            destination,
+            messages,
+        } = self;
+
+        match messages {
+            Some(n) => write!(f, "{destination} folder {folder} containing {n} messages"),
+            None => write!(f, "{destination} folder {folder}"),
+        }
+    }
+}
+
+/// The decision taken for a folder deletion.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum FolderDeletionDecision {
+    /// The deletion is allowed by the policy without confirmation.
+    Allowed,
+
+    /// The deletion has been confirmed by the confirmation handler.
+    Confirmed,
+
+    /// The deletion has been refused, either by the confirmation
+    /// handler or because it could not be confirmed. All the hunks
+    /// of the folder are then discarded.
+    Refused,
+}
+
+impl FolderDeletionDecision {
+    /// Return `true` if the folder can be deleted.
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, Self::Refused)
+    }
+}
+
+impl fmt::Display for FolderDeletionDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allowed => write!(f, "allowed"),
+            Self::Confirmed => write!(f, "confirmed"),
+            Self::Refused => write!(f, "refused"),
+        }
+    }
+}
+
+/// The folder deletion policy.
+///
+/// Without threshold nor confirmation handler, all deletions are
+/// allowed. With a threshold, deletions of folders containing more
+/// messages than the threshold need to be confirmed. Without
+/// threshold, the confirmation handler is consulted for every
+/// deletion. Deletions needing a confirmation are refused when no
+/// confirmation handler is defined.
+#[derive(Clone, Default)]
+pub struct FolderDeletionPolicy {
+    /// The maximum number of messages a folder can contain to be
+    /// deleted without confirmation.
+    pub threshold: Option<usize>,
+
+    /// The confirmation handler.
+    pub confirmation: Option<Arc<FolderDeletionConfirmationHandler>>,
+}
+
+impl FolderDeletionPolicy {
+    /// Return `true` if the messages of folders to delete need to be
+    /// counted.
+    pub fn needs_count(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    /// Decide whether the given folder deletion is accepted.
+    ///
+    /// The messages of the deletion are expected to be counted when
+    /// [`FolderDeletionPolicy::needs_count`] returns `true`. A
+    /// deletion with uncounted messages then needs a confirmation.
+    pub async fn decide(&self, deletion: FolderDeletion) -> FolderDeletionDecision {
+        let needs_confirmation = match (self.threshold, deletion.messages) {
+            (None, _) => self.confirmation.is_some(),
+            (Some(threshold), Some(n)) => n > threshold,
+            (Some(_), None) => true,
+        };
+
+        if !needs_confirmation {
+            return FolderDeletionDecision::Allowed;
+        }
+
+        match self.confirmation.as_ref() {
+            Some(confirm) if confirm(deletion).await => FolderDeletionDecision::Confirmed,
+            _ => FolderDeletionDecision::Refused,
+        }
+    }
+}
+
+impl fmt::Debug for FolderDeletionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FolderDeletionPolicy")
+            .field("threshold", &self.threshold)
+            .field("confirmation", &self.confirmation.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    use super::{FolderDeletion, FolderDeletionDecision, FolderDeletionPolicy};
+    use crate::sync::SyncDestination;
+
+    fn deletion(messages: Option<usize>) -> FolderDeletion {
+        FolderDeletion {
+            folder: "folder".into(),
+            destination: SyncDestination::Right,
+            messages,
+        }
+    }
+
+    #[tokio::test]
+    async fn decide() {
+        let policy = FolderDeletionPolicy::default();
+        assert!(!policy.needs_count());
+        let decision = policy.decide(deletion(None)).await;
+        assert_eq!(decision, FolderDeletionDecision::Allowed);
+
+        let policy = FolderDeletionPolicy {
+            threshold: Some(10),
+            confirmation: None,
+        };
+        assert!(policy.needs_count());
+        let decision = policy.decide(deletion(Some(10))).await;
+        assert_eq!(decision, FolderDeletionDecision::Allowed);
+        let decision = policy.decide(deletion(Some(11))).await;
+        assert_eq!(decision, FolderDeletionDecision::Refused);
+        let decision = policy.decide(deletion(None)).await;
+        assert_eq!(decision, FolderDeletionDecision::Refused);
+
+        let policy = FolderDeletionPolicy {
+            threshold: Some(10),
+            confirmation: Some(Arc::new(
+                |deletion: FolderDeletion| -> Pin<Box<dyn Future<Output = bool> + Send>> {
+                    Box::pin(async move { deletion.messages < Some(100) })
+                },
+            )),
+        };
+        let decision = policy.decide(deletion(Some(11))).await;
+        assert_eq!(decision, FolderDeletionDecision::Confirmed);
+        let decision = policy.decide(deletion(Some(100))).await;
+        assert_eq!(decision, FolderDeletionDecision::Refused);
+        assert!(!decision.is_accepted());
+    }
+}
@@ -83,7 +83,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 /// The folder synchronization cache hunk.
 ///
 /// Similar to the [`FolderSyncHunk`], except that this hunk is
-/// specific to the cache (SQLite).
+/// specific to the cache (Maildir).
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum FolderSyncCacheHunk {
     /// The given folder name needs to be added to the cache for the
@@ -4,6 +4,7 @@
 //! folders with local ones.
 
 pub mod config;
+pub mod deletion;
 pub mod hunk;
 pub mod patch;
 pub mod report;
@@ -12,17 +13,29 @@
 
 use futures::{stream::FuturesUnordered, StreamExt};
 
-use self::{hunk::FolderSyncHunk, report::FolderSyncReport};
+use self::{
+    config::FolderSyncStrategy,
+    deletion::{FolderDeletion, FolderDeletionDecision},
+    hunk::FolderSyncHunk,
+    patch::FolderSyncPatches,
+    report::FolderSyncReport,
+};
 use super::{
-    add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders, Folder,
+    add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+    search::SearchFolders, Folder,
 };
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    backend::context::BackendContextBuilder,
+    backend::{
+        context::{BackendContext, BackendContextBuilder},
+        Backend,
+    },
     debug,
+    envelope::list::{ListEnvelopes, ListEnvelopesOptions},
+    runtime,
     sync::{pool::SyncPoolContext, SyncDestination, SyncEvent},
-    trace,
+    trace, AnyResult,
 };
 
 pub(crate) async fn sync<L, R>(
@@ -35,29 +48,10 @@ pub(crate) async fn sync<L, R>(
     let mut report = FolderSyncReport::default();
 
     let ctx = ctx_ref.clone();
-    let left_cached_folders = tokio::spawn(async move {
-        let folders = ctx
-            .left_cache
-            .list_folders()
+    let left_cached_folders = runtime::spawn(async move {
+        let names = list_folder_names(&ctx.left_cache, &ctx.folder_filters)
             .await
             .map_err(Error::ListLeftFoldersCachedError)?;
-        let names = HashSet::<String>::from_iter(
-            folders
-                .iter()
-                .map(Folder::get_kind_or_name)
-                // TODO: instead of fetching all the folders then
-                // filtering them here, it could be better to filter
-                // them at the source directly, which implies to add a
-                // new backend fn called `search_folders` and to set
-                // up a common search API across backends.
-                .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
-                        Some(folder.to_owned())
-                    } else {
-                        None
-                    }
-                }),
-        );
 
         SyncEvent::ListedLeftCachedFolders(names.len())
             .emit(&ctx.handler)
@@ -67,29 +61,10 @@ pub(crate) async fn sync<L, R>(
     });
 
     let ctx = ctx_ref.clone();
-    let left_folders = tokio::spawn(async move {
-        let folders = ctx
-            .left
-            .list_folders()
+    let left_folders = runtime::spawn(async move {
+        let names = list_folder_names(&ctx.left, &ctx.folder_filters)
             .await
             .map_err(Error::ListLeftFoldersError)?;
-        let names = HashSet::<String>::from_iter(
-            folders
-                .iter()
-                .map(Folder::get_kind_or_name)
-                // TODO: instead of fetching all the folders then
-                // filtering them here, it could be better to filter
-                // them at the source directly, which implies to add a
-                // new backend fn called `search_folders` and to set
-                // up a common search API across backends.
-                .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
-                        Some(folder.to_owned())
-                    } else {
-                        None
-                    }
-                }),
-        );
 
         SyncEvent::ListedLeftFolders(names.len())
             .emit(&ctx.handler)
@@ -99,29 +74,10 @@ pub(crate) async fn sync<L, R>(
     });
 
     let ctx = ctx_ref.clone();
-    let right_cached_folders = tokio::spawn(async move {
-        let folders = ctx
-            .right_cache
-            .list_folders()
+    let right_cached_folders = runtime::spawn(async move {
+        let names = list_folder_names(&ctx.right_cache, &ctx.folder_filters)
             .await
             .map_err(Error::ListRightFoldersCachedError)?;
-        let names = HashSet::<String>::from_iter(
-            folders
-                .iter()
-                .map(Folder::get_kind_or_name)
-                // TODO: instead of fetching all the folders then
-                // filtering them here, it could be better to filter
-                // them at the source directly, which implies to add a
-                // new backend fn called `search_folders` and to set
-                // up a common search API across backends.
-                .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
-                        Some(folder.to_owned())
-                    } else {
-                        None
-                    }
-                }),
-        );
 
         SyncEvent::ListedRightCachedFolders(names.len())
             .emit(&ctx.handler)
@@ -131,29 +87,10 @@ pub(crate) async fn sync<L, R>(
     });
 
     let ctx = ctx_ref.clone();
-    let right_folders = tokio::spawn(async move {
-        let folders = ctx
-            .right
-            .list_folders()
+    let right_folders = runtime::spawn(async move {
+        let names = list_folder_names(&ctx.right, &ctx.folder_filters)
             .await
             .map_err(Error::ListRightFoldersError)?;
-        let names: HashSet<String> = HashSet::from_iter(
-            folders
-                .iter()
-                .map(Folder::get_kind_or_name)
-                // TODO: instead of fetching all the folders then
-                // filtering them here, it could be better to filter
-                // them at the source directly, which implies to add a
-                // new backend fn called `search_folders` and to set
-                // up a common search API across backends.
-                .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
-                        Some(folder.to_owned())
-                    } else {
-                        None
-                    }
-                }),
-        );
 
         SyncEvent::ListedRightFolders(names.len())
             .emit(&ctx.handler)
@@ -181,6 +118,7 @@ pub(crate) async fn sync<L, R>(
     );
 
     ctx_ref.apply_folder_permissions(&mut patch);
+    report.deletions = confirm_deletions(&ctx_ref, &mut patch).await;
 
     SyncEvent::GeneratedFolderPatch(patch.clone())
         .emit(&ctx_ref.handler)
@@ -198,7 +136,7 @@ pub(crate) async fn sync<L, R>(
     report.names = folders;
     report.patch = FuturesUnordered::from_iter(patch.into_iter().map(|hunk| {
         let ctx = ctx_ref.clone();
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             let hunk_clone = hunk.clone();
             let handler = ctx.handler.clone();
             let task = async move {
@@ -268,6 +206,119 @@ pub(crate) async fn sync<L, R>(
     Ok(report)
 }
 
+/// List the names (or kinds) of the folders of the given backend
+/// matching the given filters.
+///
+/// Filters are pushed down to backends able to search folders (see
+/// [`FolderSyncStrategy::search_patterns`]), which prevents listing
+/// all folders when only a few of them are synchronized. They are
+/// applied again on the listed folders anyway, since searched folders
+/// can be identified by their kind.
+async fn list_folder_names<C: BackendContext>(
+    backend: &Backend<C>,
+    filters: &FolderSyncStrategy,
+) -> AnyResult<HashSet<String>> {
+    let patterns = match backend.search_folders {
+        Some(_) => filters.search_patterns(&backend.account_config),
+        None => None,
+    };
+
+    let folders = match patterns {
+        Some(patterns) => backend.search_folders(&patterns).await?,
+        None => backend.list_folders().await?,
+    };
+
+    let names = folders
+        .iter()
+        .map(Folder::get_kind_or_name)
+        .filter(|folder| filters.matches(folder))
+        .map(ToOwned::to_owned)
+        .collect();
+
+    Ok(names)
+}
+
+/// Submit the folder deletions of the given patch to the deletion
+/// policy.
+///
+/// Folders whose deletion is refused are removed from the patch:
+/// none of their hunks are applied, and their emails are not
+/// synchronized.
+async fn confirm_deletions<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    patch: &mut FolderSyncPatches,
+) -> Vec<(FolderDeletion, FolderDeletionDecision)>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let deletions: Vec<_> = patch
+        .values()
+        .flatten()
+        .filter_map(|hunk| match hunk {
+            FolderSyncHunk::Delete(folder, target) => Some((folder.clone(), target.clone())),
+            _ => None,
+        })
+        .collect();
+
+    let mut decisions = Vec::with_capacity(deletions.len());
+
+    for (folder, destination) in deletions {
+        let messages = if ctx.folder_deletion_policy.needs_count() {
+            count_messages(ctx, &folder, &destination).await
+        } else {
+            None
+        };
+
+        let deletion = FolderDeletion {
+            folder,
+            destination,
+            messages,
+        };
+
+        let decision = ctx.folder_deletion_policy.decide(deletion.clone()).await;
+        debug!("deletion of {deletion}: {decision}");
+
+        if !decision.is_accepted() {
+            patch.remove(&deletion.folder);
+        }
+
+        decisions.push((deletion, decision));
+    }
+
+    decisions
+}
+
+/// Count the messages of the given folder.
+///
+/// Counting errors are not fatal: the deletion then needs to be
+/// confirmed.
+async fn count_messages<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    destination: &SyncDestination,
+) -> Option<usize>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let opts = ListEnvelopesOptions::default();
+
+    let envelopes = match destination {
+        SyncDestination::Left => ctx.left.list_envelopes(folder, opts).await,
+        SyncDestination::Right => ctx.right.list_envelopes(folder, opts).await,
+    };
+
+    match envelopes {
+        Ok(envelopes) => Some(envelopes.len()),
+        Err(err) => {
+            debug!("cannot count messages of {destination} folder {folder}: {err}");
+            trace!("{err:?}");
+            None
+        }
+    }
+}
+
 pub(crate) async fn expunge<L, R>(
     ctx_ref: Arc<SyncPoolContext<L::Context, R::Context>>,
     folders: &HashSet<String>,
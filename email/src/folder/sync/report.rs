@@ -2,7 +2,10 @@
 //!
 //! The core structure of this module is the [`FolderSyncReport`].
 
-use super::hunk::{FolderSyncHunk, FoldersName};
+use super::{
+    deletion::{FolderDeletion, FolderDeletionDecision},
+    hunk::{FolderSyncHunk, FoldersName},
+};
 use crate::AnyBoxedError;
 
 /// The folder synchronization report.
@@ -14,4 +17,8 @@ pub struct FolderSyncReport {
     /// The list of processed hunks associated with an optional
     /// error. Hunks that could not be processed are ignored.
     pub patch: Vec<(FolderSyncHunk, Option<AnyBoxedError>)>,
+
+    /// The list of folder deletions submitted to the deletion
+    /// policy, associated with their decision.
+    pub deletions: Vec<(FolderDeletion, FolderDeletionDecision)>,
 }
@@ -0,0 +1,260 @@
+//! # IMAP alerts
+//!
+//! Module dedicated to out-of-band IMAP server messages. Servers can
+//! send messages meant to be shown to users, like `[ALERT]` response
+//! codes (mailbox over quota, password about to expire etc) or
+//! untagged NO responses (warnings). Instead of dropping them,
+//! clients report them to an optional [`ImapAlertSink`].
+//!
+//! Alerts are surfaced from the commands resolved by this crate
+//! (NOOP, STATUS, GETQUOTAROOT, GETMETADATA, SETMETADATA, UID EXPUNGE
+//! and APPEND). Responses handled internally by the underlying IMAP
+//! client (like the ones received during authentication) are not
+//! surfaced yet.
+//!
+//! https://www.rfc-editor.org/rfc/rfc3501#section-7.1
+
+use std::{fmt, sync::Arc};
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::CommandBody,
+    response::{Code, Data, StatusBody, StatusKind},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The kind of IMAP alert.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ImapAlertKind {
+    /// The server asked for the message to be shown to the user
+    /// (`[ALERT]` response code).
+    Alert,
+
+    /// The server sent an untagged NO or BAD response, which is a
+    /// warning not related to any command.
+    Warning,
+}
+
+/// An out-of-band message sent by the IMAP server.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ImapAlert {
+    /// The kind of alert.
+    pub kind: ImapAlertKind,
+
+    /// The human-readable message of the server.
+    pub text: String,
+}
+
+impl ImapAlert {
+    /// Build an alert from the given status body, if it contains
+    /// one.
+    ///
+    /// Tagged status bodies only contain alerts when they have the
+    /// `[ALERT]` response code, since NO and BAD tagged responses
+    /// are command failures rather than warnings.
+    pub fn from_status_body(status_body: &StatusBody<'_>, tagged: bool) -> Option<Self> {
+        let kind = match (&status_body.code, &status_body.kind) {
+            (Some(Code::Alert), _) => ImapAlertKind::Alert,
+            (_, StatusKind::No | StatusKind::Bad) if !tagged => ImapAlertKind::Warning,
+            _ => return None,
+        };
+
+        let text = status_body.text.as_ref().to_owned();
+
+        Some(Self { kind, text })
+    }
+}
+
+impl fmt::Display for ImapAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// The IMAP alert sink.
+///
+/// The sink receives the alerts of all the clients of the pool, as
+/// soon as they are received.
+pub trait ImapAlertSink: Send + Sync {
+    /// Report the given alert.
+    fn alert(&self, alert: &ImapAlert);
+}
+
+/// Channel-based IMAP alert sink, which turns alerts into a stream
+/// of events.
+impl ImapAlertSink for UnboundedSender<ImapAlert> {
+    fn alert(&self, alert: &ImapAlert) {
+        // the receiver may have been dropped, in which case nobody
+        // is interested in alerts anymore
+        let _ = self.send(alert.clone());
+    }
+}
+
+/// Shared IMAP alert sink.
+///
+/// This wrapper allows contexts and their builders to hold an alert
+/// sink while staying cloneable and comparable. Two shared sinks are
+/// equal if they point to the same sink.
+#[derive(Clone)]
+pub struct SharedImapAlertSink(Arc<dyn ImapAlertSink>);
+
+impl SharedImapAlertSink {
+    /// Create a new shared sink from the given sink.
+    pub fn new(sink: impl ImapAlertSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    /// Report the given alert.
+    pub fn alert(&self, alert: &ImapAlert) {
+        self.0.alert(alert)
+    }
+}
+
+impl<T: ImapAlertSink + 'static> From<Arc<T>> for SharedImapAlertSink {
+    fn from(sink: Arc<T>) -> Self {
+        Self(sink)
+    }
+}
+
+impl From<UnboundedSender<ImapAlert>> for SharedImapAlertSink {
+    fn from(sender: UnboundedSender<ImapAlert>) -> Self {
+        Self::new(sender)
+    }
+}
+
+impl fmt::Debug for SharedImapAlertSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedImapAlertSink")
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for SharedImapAlertSink {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedImapAlertSink {}
+
+/// The task wrapping another task in order to collect the alerts
+/// received while resolving it.
+///
+/// Only data, untagged status and tagged status responses are
+/// forwarded to the wrapped task, which means that it should not
+/// rely on continuation requests.
+#[derive(Clone, Debug)]
+pub struct AlertTask<T> {
+    task: T,
+    alerts: Vec<ImapAlert>,
+}
+
+impl<T: Task> AlertTask<T> {
+    pub fn new(task: T) -> Self {
+        Self {
+            task,
+            alerts: Vec::new(),
+        }
+    }
+}
+
+impl<T: Task> Task for AlertTask<T> {
+    type Output = (T::Output, Vec<ImapAlert>);
+
+    fn command_body(&self) -> CommandBody<'static> {
+        self.task.command_body()
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        self.task.process_data(data)
+    }
+
+    fn process_untagged(
+        &mut self,
+        status_body: StatusBody<'static>,
+    ) -> Option<StatusBody<'static>> {
+        self.alerts
+            .extend(ImapAlert::from_status_body(&status_body, false));
+        self.task.process_untagged(status_body)
+    }
+
+    fn process_tagged(mut self, status_body: StatusBody<'static>) -> Self::Output {
+        self.alerts
+            .extend(ImapAlert::from_status_body(&status_body, true));
+        (self.task.process_tagged(status_body), self.alerts)
+    }
+}
+
+/// The task resolving the NOOP command.
+///
+/// Unlike the NOOP of the underlying IMAP client, this task can be
+/// wrapped into an [`AlertTask`], which surfaces the warnings sent
+/// by servers while clients are polled.
+#[derive(Clone, Debug, Default)]
+pub struct NoOpTask;
+
+impl Task for NoOpTask {
+    type Output = Result<(), TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::Noop
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(()),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_next::imap_types::{
+        core::Text,
+        response::{Code, StatusBody, StatusKind},
+    };
+    use tokio::sync::mpsc;
+
+    use super::{ImapAlert, ImapAlertKind, SharedImapAlertSink};
+
+    fn status_body(kind: StatusKind, code: Option<Code<'static>>) -> StatusBody<'static> {
+        StatusBody {
+            kind,
+            code,
+            text: Text::try_from("mailbox is over quota").unwrap(),
+        }
+    }
+
+    #[test]
+    fn alerts_from_status_bodies() {
+        let alert =
+            ImapAlert::from_status_body(&status_body(StatusKind::Ok, Some(Code::Alert)), true);
+        let expected = ImapAlert {
+            kind: ImapAlertKind::Alert,
+            text: "mailbox is over quota".into(),
+        };
+        assert_eq!(alert, Some(expected));
+
+        let alert = ImapAlert::from_status_body(&status_body(StatusKind::No, None), false);
+        assert_eq!(alert.unwrap().kind, ImapAlertKind::Warning);
+
+        assert!(ImapAlert::from_status_body(&status_body(StatusKind::No, None), true).is_none());
+        assert!(ImapAlert::from_status_body(&status_body(StatusKind::Ok, None), false).is_none());
+    }
+
+    #[test]
+    fn channel_sink() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sink = SharedImapAlertSink::from(tx);
+
+        let alert = ImapAlert {
+            kind: ImapAlertKind::Warning,
+            text: "server going down soon".into(),
+        };
+
+        sink.alert(&alert);
+        assert_eq!(rx.try_recv().unwrap(), alert);
+    }
+}
@@ -0,0 +1,168 @@
+//! # IMAP fetch batching
+//!
+//! Module dedicated to the sizing of FETCH batches. Fetching too few
+//! messages per command wastes round trips on fast links, whereas
+//! fetching too many makes each command slow on slow links. The
+//! [`FetchBatchSizer`] adapts the batch size to the observed latency:
+//! it starts small, grows while commands complete quickly, and
+//! shrinks as soon as they become slow.
+
+use std::time::Duration;
+
+use super::config::ImapFetchBatchConfig;
+use crate::debug;
+
+/// The default minimum number of messages fetched per command.
+pub const DEFAULT_MIN_BATCH_SIZE: usize = 32;
+
+/// The default maximum number of messages fetched per command.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 255;
+
+/// The default latency targeted by a single FETCH command.
+pub const DEFAULT_TARGET_LATENCY: Duration = Duration::from_secs(1);
+
+/// The adaptive FETCH batch sizer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FetchBatchSizer {
+    min: usize,
+    max: usize,
+    target_latency: Duration,
+    current: usize,
+
+    /// The sizes of the batches chosen so far.
+    sizes: Vec<usize>,
+}
+
+impl Default for FetchBatchSizer {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_MIN_BATCH_SIZE,
+            DEFAULT_MAX_BATCH_SIZE,
+            DEFAULT_TARGET_LATENCY,
+        )
+    }
+}
+
+impl FetchBatchSizer {
+    /// Create a new batch sizer.
+    ///
+    /// Batches start at the minimum size. Using the same minimum and
+    /// maximum sizes disables the adaptation.
+    pub fn new(min: usize, max: usize, target_latency: Duration) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+
+        Self {
+            min,
+            max,
+            target_latency,
+            current: min,
+            sizes: Vec::new(),
+        }
+    }
+
+    /// Return the size of the next batch, bounded by the given number
+    /// of remaining messages.
+    pub fn next_size(&mut self, remaining: usize) -> usize {
+        let size = self.current.min(remaining);
+        self.sizes.push(size);
+        size
+    }
+
+    /// Record the latency of a batch of the given size.
+    ///
+    /// The batch size doubles when the batch completed in less than
+    /// half the target latency, and halves when it took more than the
+    /// target latency. Partial batches do not make the size grow.
+    pub fn record(&mut self, size: usize, latency: Duration) {
+        let previous = self.current;
+
+        if latency > self.target_latency {
+            self.current = (self.current / 2).max(self.min);
+        } else if latency < self.target_latency / 2 && size >= self.current {
+            self.current = self.current.saturating_mul(2).min(self.max);
+        }
+
+        if previous != self.current {
+            debug!(
+                "fetch batch of {size} took {latency:?}, resizing to {}",
+                self.current
+            );
+        }
+    }
+
+    /// Return the sizes of the batches chosen so far.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+}
+
+impl From<Option<&ImapFetchBatchConfig>> for FetchBatchSizer {
+    fn from(config: Option<&ImapFetchBatchConfig>) -> Self {
+        let Some(config) = config else {
+            return Self::default();
+        };
+
+        Self::new(
+            config.min_size.unwrap_or(DEFAULT_MIN_BATCH_SIZE),
+            config.max_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+            config
+                .target_latency
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_TARGET_LATENCY),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::FetchBatchSizer;
+
+    #[test]
+    fn adaptive_sizes() {
+        let fast = Duration::from_millis(100);
+        let slow = Duration::from_secs(2);
+        let mut sizer = FetchBatchSizer::new(10, 50, Duration::from_secs(1));
+
+        let size = sizer.next_size(1000);
+        assert_eq!(size, 10);
+        sizer.record(size, fast);
+
+        let size = sizer.next_size(1000);
+        assert_eq!(size, 20);
+        sizer.record(size, fast);
+
+        let size = sizer.next_size(1000);
+        assert_eq!(size, 40);
+        sizer.record(size, fast);
+
+        let size = sizer.next_size(1000);
+        assert_eq!(size, 50);
+        sizer.record(size, slow);
+
+        let size = sizer.next_size(1000);
+        assert_eq!(size, 25);
+        sizer.record(size, Duration::from_millis(700));
+
+        // partial batches do not make the size grow
+        let size = sizer.next_size(5);
+        assert_eq!(size, 5);
+        sizer.record(size, fast);
+
+        assert_eq!(sizer.sizes(), [10, 20, 40, 50, 25, 5]);
+    }
+
+    #[test]
+    fn fixed_sizes() {
+        let mut sizer = FetchBatchSizer::new(100, 100, Duration::from_secs(1));
+
+        for _ in 0..3 {
+            let size = sizer.next_size(1000);
+            sizer.record(size, Duration::ZERO);
+        }
+
+        assert_eq!(sizer.sizes(), [100, 100, 100]);
+    }
+}
@@ -0,0 +1,154 @@
+//! # IMAP folder name codec
+//!
+//! Module dedicated to the conversion of folder names between their
+//! decoded form, as manipulated by features, and their encoded form,
+//! as exchanged with the server. Folder names are encoded using
+//! modified UTF-7, unless the server advertises the [UTF8=ACCEPT]
+//! extension, in which case they are exchanged in UTF-8 directly.
+//!
+//! [UTF8=ACCEPT]: https://www.rfc-editor.org/rfc/rfc6855.html
+
+use imap_next::imap_types::response::Capability;
+use utf7_imap::{decode_utf7_imap as decode_utf7, encode_utf7_imap as encode_utf7};
+
+use super::namespace::ImapNamespace;
+
+/// The UTF8=ACCEPT capability.
+pub const UTF8_ACCEPT: &str = "UTF8=ACCEPT";
+
+/// Return `true` if the given server capabilities contain the
+/// UTF8=ACCEPT capability.
+pub fn utf8_accept_supported<'a>(
+    capabilities: impl IntoIterator<Item = &'a Capability<'a>>,
+) -> bool {
+    capabilities
+        .into_iter()
+        .any(|capability| capability.to_string().eq_ignore_ascii_case(UTF8_ACCEPT))
+}
+
+/// The IMAP folder name codec.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FolderNameCodec<'a> {
+    /// Whether UTF8=ACCEPT is enabled.
+    pub utf8_accept: bool,
+
+    /// The personal namespace, if discovered.
+    pub namespace: Option<&'a ImapNamespace>,
+}
+
+impl<'a> FolderNameCodec<'a> {
+    /// Create a new codec.
+    pub fn new(utf8_accept: bool, namespace: Option<&'a ImapNamespace>) -> Self {
+        Self {
+            utf8_accept,
+            namespace,
+        }
+    }
+
+    /// Encode the given folder name, so that it can be sent to the
+    /// server.
+    ///
+    /// The name is encoded using modified UTF-7 (unless UTF8=ACCEPT
+    /// is enabled), then prefixed by the personal namespace.
+    pub fn encode(&self, folder: impl ToString) -> String {
+        let folder = folder.to_string();
+
+        let folder = if self.utf8_accept {
+            folder
+        } else {
+            encode_utf7(folder)
+        };
+
+        match self.namespace {
+            Some(namespace) => namespace.apply(&folder),
+            None => folder,
+        }
+    }
+
+    /// Decode the given folder name received from the server.
+    ///
+    /// This is the reverse of [`FolderNameCodec::encode`]: the
+    /// personal namespace prefix is removed, then the name is decoded
+    /// from modified UTF-7 (unless UTF8=ACCEPT is enabled).
+    pub fn decode(&self, folder: impl ToString) -> String {
+        let folder = folder.to_string();
+
+        let folder = match self.namespace {
+            Some(namespace) => namespace.strip(&folder),
+            None => folder,
+        };
+
+        if self.utf8_accept {
+            folder
+        } else {
+            decode_utf7(folder)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_next::imap_types::response::Capability;
+
+    use super::{utf8_accept_supported, FolderNameCodec};
+    use crate::imap::namespace::ImapNamespace;
+
+    /// Pairs of decoded folder names and their modified UTF-7
+    /// encoding.
+    const FIXTURES: [(&str, &str); 7] = [
+        ("INBOX", "INBOX"),
+        ("A&B", "A&-B"),
+        ("Entwürfe", "Entw&APw-rfe"),
+        ("Éléments envoyés", "&AMk-l&AOk-ments envoy&AOk-s"),
+        ("Отправленные", "&BB4EQgQ,BEAEMAQyBDsENQQ9BD0ESwQ1-"),
+        ("~peter/mail/台北/日本語", "~peter/mail/&U,BTFw-/&ZeVnLIqe-"),
+        ("Archives/2024", "Archives/2024"),
+    ];
+
+    #[test]
+    fn utf8_accept_capability() {
+        let utf8 = Capability::try_from("UTF8=ACCEPT").unwrap();
+        let utf8_only = Capability::try_from("UTF8=ONLY").unwrap();
+
+        assert!(!utf8_accept_supported([&Capability::Imap4Rev1]));
+        assert!(!utf8_accept_supported([&utf8_only]));
+        assert!(utf8_accept_supported([&Capability::Imap4Rev1, &utf8]));
+    }
+
+    #[test]
+    fn utf7_codec() {
+        let codec = FolderNameCodec::new(false, None);
+
+        for (decoded, encoded) in FIXTURES {
+            assert_eq!(codec.encode(decoded), encoded);
+            assert_eq!(codec.decode(encoded), decoded);
+        }
+    }
+
+    #[test]
+    fn utf8_codec() {
+        let codec = FolderNameCodec::new(true, None);
+
+        for (decoded, _) in FIXTURES {
+            assert_eq!(codec.encode(decoded), decoded);
+            assert_eq!(codec.decode(decoded), decoded);
+        }
+    }
+
+    #[test]
+    fn namespaced_codec() {
+        let namespace = ImapNamespace {
+            prefix: "INBOX.".into(),
+            delimiter: Some('.'),
+        };
+
+        let codec = FolderNameCodec::new(false, Some(&namespace));
+        assert_eq!(codec.encode("Entwürfe"), "INBOX.Entw&APw-rfe");
+        assert_eq!(codec.decode("INBOX.Entw&APw-rfe"), "Entwürfe");
+        assert_eq!(codec.encode("INBOX"), "INBOX");
+
+        let codec = FolderNameCodec::new(true, Some(&namespace));
+        assert_eq!(codec.encode("Entwürfe"), "INBOX.Entwürfe");
+        assert_eq!(codec.decode("INBOX.Entwürfe"), "Entwürfe");
+    }
+}
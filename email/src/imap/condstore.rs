@@ -0,0 +1,151 @@
+//! # IMAP conditional fetch
+//!
+//! Module dedicated to the `CHANGEDSINCE` fetch modifier of the
+//! CONDSTORE extension, which restricts a FETCH command to the
+//! messages whose modification sequence is greater than the given
+//! one. It prevents scanning the whole mailbox when looking for
+//! changes.
+//!
+//! https://www.rfc-editor.org/rfc/rfc7162#section-3.1.4.1
+
+use std::{
+    collections::HashMap,
+    num::{NonZeroU32, NonZeroU64},
+};
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::{CommandBody, FetchModifier},
+    fetch::MessageDataItem,
+    response::{Data, StatusBody, StatusKind},
+    sequence::SequenceSet,
+};
+
+use crate::envelope::imap::FETCH_MOD_SEQS;
+
+/// The task resolving the `UID FETCH 1:* (UID FLAGS MODSEQ)
+/// (CHANGEDSINCE <mod-seq>)` command.
+///
+/// The output contains the modification sequence of the messages
+/// changed since the given modification sequence, indexed by UID. A
+/// modification sequence of 0 matches all the messages of the
+/// mailbox.
+#[derive(Clone, Debug)]
+pub struct ChangedSinceTask {
+    since: Option<NonZeroU64>,
+    mod_seqs: HashMap<NonZeroU32, u64>,
+}
+
+impl ChangedSinceTask {
+    pub fn new(since: u64) -> Self {
+        Self {
+            since: NonZeroU64::new(since),
+            mod_seqs: HashMap::new(),
+        }
+    }
+}
+
+impl Task for ChangedSinceTask {
+    type Output = Result<HashMap<NonZeroU32, u64>, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::Fetch {
+            sequence_set: SequenceSet::try_from("1:*").unwrap(),
+            macro_or_item_names: FETCH_MOD_SEQS.clone(),
+            modifiers: self
+                .since
+                .map(FetchModifier::ChangedSince)
+                .into_iter()
+                .collect(),
+            uid: true,
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::Fetch { seq, items } => {
+                let mut uid = None;
+                let mut mod_seq = None;
+
+                for item in items.as_ref() {
+                    match item {
+                        MessageDataItem::Uid(id) => uid = Some(*id),
+                        MessageDataItem::ModSeq(seq) => mod_seq = Some(seq.get()),
+                        _ => (),
+                    }
+                }
+
+                match (uid, mod_seq) {
+                    (Some(uid), Some(mod_seq)) => {
+                        self.mod_seqs.insert(uid, mod_seq);
+                        None
+                    }
+                    // unsolicited fetch responses (flag changes made
+                    // by other clients) are left to the client
+                    _ => Some(Data::Fetch { seq, items }),
+                }
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.mod_seqs),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::{NonZeroU32, NonZeroU64};
+
+    use imap_client::tasks::Task;
+    use imap_next::imap_types::{
+        command::{CommandBody, FetchModifier},
+        core::Vec1,
+        fetch::MessageDataItem,
+        response::Data,
+    };
+
+    use super::ChangedSinceTask;
+
+    #[test]
+    fn changed_since() {
+        let CommandBody::Fetch { modifiers, uid, .. } = ChangedSinceTask::new(0).command_body()
+        else {
+            panic!("expected a fetch command");
+        };
+        assert!(uid);
+        assert!(modifiers.is_empty());
+
+        let mut task = ChangedSinceTask::new(5);
+        let CommandBody::Fetch { modifiers, .. } = task.command_body() else {
+            panic!("expected a fetch command");
+        };
+        assert_eq!(
+            modifiers,
+            [FetchModifier::ChangedSince(NonZeroU64::new(5).unwrap())]
+        );
+
+        let seq = NonZeroU32::new(1).unwrap();
+        let uid = NonZeroU32::new(42).unwrap();
+
+        let items = Vec1::try_from(vec![
+            MessageDataItem::Uid(uid),
+            MessageDataItem::ModSeq(NonZeroU64::new(7).unwrap()),
+        ])
+        .unwrap();
+        assert!(task.process_data(Data::Fetch { seq, items }).is_none());
+
+        // fetch responses without modification sequence are not
+        // part of the output
+        let items = Vec1::try_from(vec![MessageDataItem::Flags(vec![])]).unwrap();
+        assert!(task.process_data(Data::Fetch { seq, items }).is_some());
+
+        assert_eq!(task.mod_seqs.len(), 1);
+        assert_eq!(task.mod_seqs[&uid], 7);
+    }
+}
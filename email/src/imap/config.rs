@@ -3,15 +3,21 @@
 //! This module contains the implementation of the IMAP backend and
 //! all associated structures related to it.
 
-use std::fmt;
+use std::{fmt, path::PathBuf, time::Duration};
 #[cfg(feature = "derive")]
 use std::{marker::PhantomData, result};
 
+use secret::Secret;
+
 #[doc(inline)]
 use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::OAuth2Config;
 use crate::account::config::passwd::PasswdConfig;
+use crate::{
+    network::{config::ClientCertificateConfig, tls::TlsOptions},
+    retry::DEFAULT_TIMEOUT,
+};
 
 /// Errors related to the IMAP backend configuration.
 
@@ -38,6 +44,31 @@ pub struct ImapConfig {
     )]
     pub encryption: Option<ImapEncryptionKind>,
 
+    /// The path to a custom CA bundle.
+    ///
+    /// When defined, the IMAP server certificate chain is verified
+    /// against the PEM-encoded certificates of this bundle instead
+    /// of the system root certificates, which is useful for
+    /// self-hosted servers using a private certificate authority.
+    /// The path is shell expanded.
+    pub ca_bundle: Option<PathBuf>,
+
+    /// The IMAP server certificate pins.
+    ///
+    /// When defined, the server certificate chain must contain at
+    /// least one certificate whose Subject Public Key Info (SPKI)
+    /// SHA-256 hash matches one of the pins, otherwise the
+    /// connection fails even if the chain is valid. Pins are
+    /// base64-encoded hashes, optionally prefixed by `sha256/`.
+    pub certificate_pins: Option<Vec<String>>,
+
+    /// The TLS client certificate.
+    ///
+    /// When defined, the client authenticates to the IMAP server
+    /// using this certificate, as required by servers enforcing
+    /// mutual TLS (mTLS).
+    pub client_certificate: Option<ClientCertificateConfig>,
+
     /// The IMAP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -64,6 +95,23 @@ pub struct ImapConfig {
     /// Defines the number of clients that are created and managed
     /// simultaneously by the IMAP context. Defaults to 1.
     pub clients_pool_size: Option<u8>,
+
+    /// The IMAP operation timeouts configuration.
+    ///
+    /// See [ImapTimeoutsConfig].
+    pub timeouts: Option<ImapTimeoutsConfig>,
+
+    /// The FETCH batching configuration.
+    ///
+    /// See [ImapFetchBatchConfig].
+    pub fetch_batch: Option<ImapFetchBatchConfig>,
+
+    /// The ManageSieve configuration.
+    ///
+    /// The ManageSieve client shares the IMAP host, login and
+    /// authentication configuration.
+    #[cfg(feature = "sieve")]
+    pub sieve: Option<crate::sieve::config::SieveConfig>,
 }
 
 impl ImapConfig {
@@ -79,6 +127,71 @@ pub fn send_id_after_auth(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return the client name sent with the ID command, if any.
+    pub fn id_name(&self) -> Option<&str> {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.id.as_ref())
+            .and_then(|id| id.name.as_deref())
+    }
+
+    /// Return the client version sent with the ID command, if any.
+    pub fn id_version(&self) -> Option<&str> {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.id.as_ref())
+            .and_then(|id| id.version.as_deref())
+    }
+
+    /// Return `true` if non-synchronizing literals can be used when
+    /// advertised by the server.
+    pub fn non_sync_literals_enabled(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.literal.as_ref())
+            .and_then(|literal| literal.non_sync)
+            .unwrap_or(true)
+    }
+
+    /// Return `true` if folders should be listed with their total
+    /// and unseen messages.
+    pub fn list_status_enabled(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.list_status.as_ref())
+            .and_then(|list_status| list_status.enable)
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the personal namespace should be discovered
+    /// and applied to folder names.
+    pub fn namespace_enabled(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.namespace.as_ref())
+            .and_then(|namespace| namespace.enable)
+            .unwrap_or(true)
+    }
+
+    /// Return `true` if UTF8=ACCEPT should be enabled when advertised
+    /// by the server.
+    pub fn utf8_accept_enabled(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.utf8.as_ref())
+            .and_then(|utf8| utf8.accept)
+            .unwrap_or(true)
+    }
+
+    /// Return the TLS options of the connection.
+    pub fn tls_options(&self) -> TlsOptions<'_> {
+        TlsOptions {
+            ca_bundle: self.ca_bundle.as_deref(),
+            pins: self.certificate_pins.as_deref(),
+            client_certificate: self.client_certificate.as_ref(),
+        }
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -109,6 +222,47 @@ pub async fn build_credentials(&self) -> Result<String> {
     pub fn find_watch_timeout(&self) -> Option<u64> {
         self.watch.as_ref().and_then(|c| c.find_timeout())
     }
+
+    /// Return the timeout of the connection to the IMAP server.
+    pub fn connect_timeout(&self) -> Duration {
+        self.timeouts
+            .as_ref()
+            .and_then(|timeouts| timeouts.connect)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Return the timeout of the authentication to the IMAP server.
+    pub fn login_timeout(&self) -> Duration {
+        self.timeouts
+            .as_ref()
+            .and_then(|timeouts| timeouts.login)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Return the timeout of FETCH commands.
+    pub fn fetch_timeout(&self) -> Duration {
+        self.timeouts
+            .as_ref()
+            .and_then(|timeouts| timeouts.fetch)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Return the timeout of IDLE commands.
+    ///
+    /// Defaults to one minute more than the watch timeout, so that
+    /// IDLE commands are refreshed before timing out.
+    pub fn idle_timeout(&self) -> Duration {
+        let timeout = self
+            .timeouts
+            .as_ref()
+            .and_then(|timeouts| timeouts.idle)
+            .unwrap_or_else(|| self.find_watch_timeout().unwrap_or(29 * 60) + 60);
+
+        Duration::from_secs(timeout)
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -181,6 +335,15 @@ fn default() -> Self {
 }
 
 impl ImapAuthConfig {
+    /// Get IMAP secrets (password or OAuth 2.0 secrets).
+    pub fn secrets(&self) -> Vec<&Secret> {
+        match self {
+            ImapAuthConfig::Passwd(passwd) => vec![&passwd.0],
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(config) => config.secrets().to_vec(),
+        }
+    }
+
     /// Reset IMAP secrets (password or OAuth 2.0 tokens).
     pub async fn reset(&self) -> Result<()> {
         match self {
@@ -247,6 +410,67 @@ pub fn replace_undefined_keyring_entries(&mut self, name: impl AsRef<str>) -> Re
     }
 }
 
+/// The IMAP operation timeouts configuration.
+///
+/// Timeouts are expressed in seconds. When an operation takes longer
+/// than its timeout, it fails with a dedicated timeout error instead
+/// of blocking forever on a hung server.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapTimeoutsConfig {
+    /// The timeout of the connection to the IMAP server, including
+    /// the TLS negotiation. Defaults to 30 seconds.
+    pub connect: Option<u64>,
+
+    /// The timeout of the authentication to the IMAP server.
+    /// Defaults to 30 seconds.
+    pub login: Option<u64>,
+
+    /// The timeout of FETCH commands. Defaults to 30 seconds.
+    ///
+    /// Fetching big messages over slow connections may require a
+    /// higher value.
+    pub fetch: Option<u64>,
+
+    /// The timeout of IDLE commands. Defaults to one minute more
+    /// than the watch timeout.
+    ///
+    /// This timeout should be greater than the watch timeout,
+    /// otherwise IDLE commands time out before being refreshed.
+    pub idle: Option<u64>,
+}
+
+/// The IMAP FETCH batching configuration.
+///
+/// Envelopes are fetched in batches whose size adapts to the latency
+/// of the server: batches start at the minimum size, double while
+/// FETCH commands complete in less than half the target latency, and
+/// halve when they take longer than the target latency. Using the
+/// same minimum and maximum sizes disables the adaptation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapFetchBatchConfig {
+    /// The minimum number of messages fetched per command. Defaults
+    /// to 32.
+    pub min_size: Option<usize>,
+
+    /// The maximum number of messages fetched per command. Defaults
+    /// to 255.
+    pub max_size: Option<usize>,
+
+    /// The latency targeted by a single FETCH command, in
+    /// milliseconds. Defaults to 1000.
+    pub target_latency: Option<u64>,
+}
+
 /// The IMAP watch options (IDLE).
 ///
 /// Options dedicated to the IMAP IDLE mode, which is used to watch
@@ -334,6 +558,10 @@ fn visit_str<E>(self, v: &str) -> result::Result<Self::Value, E>
 )]
 pub struct ImapExtensionsConfig {
     id: Option<ImapIdExtensionConfig>,
+    literal: Option<ImapLiteralExtensionConfig>,
+    list_status: Option<ImapListStatusExtensionConfig>,
+    namespace: Option<ImapNamespaceExtensionConfig>,
+    utf8: Option<ImapUtf8ExtensionConfig>,
 }
 
 /// The IMAP configuration dedicated to the ID extension.
@@ -347,6 +575,79 @@ pub struct ImapExtensionsConfig {
 )]
 pub struct ImapIdExtensionConfig {
     /// Automatically sends the ID command straight after
-    /// authentication.
+    /// authentication. Some providers (like Netease) refuse
+    /// operations from unidentified clients.
     send_after_auth: Option<bool>,
+
+    /// The client name sent with the ID command. Defaults to the
+    /// name of the program.
+    name: Option<String>,
+
+    /// The client version sent with the ID command. Defaults to the
+    /// version of the program.
+    version: Option<String>,
+}
+
+/// The IMAP configuration dedicated to the LITERAL+ and LITERAL-
+/// extensions.
+///
+/// https://www.rfc-editor.org/rfc/rfc7888.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapLiteralExtensionConfig {
+    /// Sends non-synchronizing literals when the server advertises
+    /// them. Defaults to `true`, disable it for servers wrongly
+    /// advertising the extensions.
+    non_sync: Option<bool>,
+}
+
+/// The IMAP configuration dedicated to the LIST-STATUS extension.
+///
+/// https://www.rfc-editor.org/rfc/rfc5819.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapListStatusExtensionConfig {
+    /// Counts the total and unseen messages of folders while
+    /// listing them. Defaults to `false`, since it costs one STATUS
+    /// command per folder.
+    enable: Option<bool>,
+}
+
+/// The IMAP configuration dedicated to the NAMESPACE extension.
+///
+/// https://www.rfc-editor.org/rfc/rfc2342.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapNamespaceExtensionConfig {
+    /// Discovers the personal namespace on connect, and applies its
+    /// prefix to folder names. Defaults to `true`.
+    enable: Option<bool>,
+}
+
+/// The IMAP configuration dedicated to the UTF8=ACCEPT extension.
+///
+/// https://www.rfc-editor.org/rfc/rfc6855.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapUtf8ExtensionConfig {
+    /// Enables UTF8=ACCEPT when the server advertises it, so that
+    /// folder names are exchanged in UTF-8 instead of modified
+    /// UTF-7. Defaults to `true`.
+    accept: Option<bool>,
 }
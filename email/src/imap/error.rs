@@ -1,4 +1,4 @@
-use std::{any::Any, collections::HashSet, result};
+use std::{any::Any, collections::HashSet, io, result};
 
 use imap_client::ClientError;
 use imap_next::{
@@ -9,7 +9,7 @@
 use thiserror::Error;
 use tokio::task::JoinError;
 
-use crate::{account, AnyBoxedError, AnyError};
+use crate::{account, network, AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -27,6 +27,18 @@ pub enum Error {
     BuildStartTlsClientError(#[source] ClientError, String, u16),
     #[error("cannot connect to IMAP server {1}:{2} using SSL/TLS")]
     BuildTlsClientError(#[source] ClientError, String, u16),
+    #[error("cannot connect to IMAP server {0}:{1}: request timed out")]
+    ConnectTimedOutError(String, u16),
+    #[error("cannot connect to IMAP server {1}:{2}")]
+    ConnectNetworkError(#[source] network::Error, String, u16),
+    #[error("cannot negotiate SSL/TLS with IMAP server {1}:{2}")]
+    ConnectNetworkTlsError(#[source] network::Error, String, u16),
+    #[error("certificate of IMAP server {0}:{1} does not match any pin")]
+    UnpinnedCertificateError(String, u16),
+    #[error("cannot build IMAP client for server {1}:{2}")]
+    BuildNetworkClientError(#[source] ClientError, String, u16),
+    #[error("cannot use STARTTLS with IMAP network configuration, TLS options or custom streams")]
+    NetworkStartTlsNotSupportedError,
 
     #[error("cannot get imap password from global keyring")]
     GetPasswdImapError(#[source] secret::Error),
@@ -70,6 +82,8 @@ pub enum Error {
     AuthenticateError(#[source] ClientError),
     #[error("cannot authenticate to IMAP server using LOGIN mechanism")]
     LoginError(#[source] ClientError),
+    #[error("cannot authenticate to IMAP server as {0}: request timed out")]
+    LoginTimedOutError(String),
     #[error("cannot authenticate to IMAP server using SASL PLAIN mechanism")]
     AuthenticatePlainError(#[source] ClientError),
     #[error("cannot authenticate to IMAP server using SASL XOAUTH2 mechanism")]
@@ -96,6 +110,32 @@ pub enum Error {
     ListMailboxesError(#[source] ClientError),
     #[error("cannot list IMAP mailboxes: request timed out")]
     ListMailboxesTimedOutError,
+    #[error("cannot get status of IMAP mailbox")]
+    StatusMailboxError(#[source] ClientError),
+    #[error("cannot get status of IMAP mailbox: request timed out")]
+    StatusMailboxTimedOutError,
+    #[error("cannot get IMAP quota: QUOTA extension not supported")]
+    QuotaNotSupportedError,
+    #[error("cannot get IMAP quota root")]
+    GetQuotaRootError(#[source] ClientError),
+    #[error("cannot get IMAP quota root: request timed out")]
+    GetQuotaRootTimedOutError,
+    #[error("cannot get or set IMAP metadata: METADATA extension not supported")]
+    MetadataNotSupportedError,
+    #[error("cannot parse IMAP metadata entry {0}")]
+    ParseMetadataEntryError(String),
+    #[error("cannot get IMAP metadata")]
+    GetMetadataError(#[source] ClientError),
+    #[error("cannot get IMAP metadata: request timed out")]
+    GetMetadataTimedOutError,
+    #[error("cannot set IMAP metadata")]
+    SetMetadataError(#[source] ClientError),
+    #[error("cannot set IMAP metadata: request timed out")]
+    SetMetadataTimedOutError,
+    #[error("cannot thread IMAP messages: THREAD=REFERENCES extension not supported")]
+    ThreadReferencesNotSupportedError,
+    #[error("cannot discover IMAP namespace")]
+    DiscoverNamespaceError(#[source] ClientError),
 
     #[error("cannot expunge selected IMAP mailbox")]
     ExpungeMailboxError(#[source] ClientError),
@@ -152,8 +192,18 @@ pub enum Error {
     StartIdleError(#[source] StreamError<ClientFlowError>),
     #[error("cannot stop IMAP IDLE mode")]
     StopIdleError(#[source] StreamError<ClientFlowError>),
+    #[error("cannot wait for IMAP IDLE mode: request timed out")]
+    IdleTimedOutError,
     #[error("IMAP IDLE mode interrupted")]
     IdleInterruptedError,
+    #[error("cannot watch IMAP folders using NOTIFY: {0}")]
+    NotifyNotSupportedError(&'static str),
+    #[error("cannot exchange data with the IMAP NOTIFY connection")]
+    NotifyIoError(#[source] io::Error),
+    #[error("cannot execute IMAP command {0} on the NOTIFY connection: {1}")]
+    NotifyCommandError(&'static str, String),
+    #[error("IMAP NOTIFY connection closed by the server")]
+    NotifyClosedError,
     #[error("cannot append IMAP message")]
     AppendMessageError(#[source] ClientError),
     #[error("cannot execute IMAP no-op after append")]
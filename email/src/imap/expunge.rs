@@ -0,0 +1,64 @@
+//! # IMAP targeted expunge
+//!
+//! Module dedicated to the IMAP UID EXPUNGE command, brought by the
+//! UIDPLUS extension. Unlike EXPUNGE, only messages matching the
+//! given UIDs are permanently removed, which prevents destroying
+//! messages another client flagged as deleted concurrently.
+//!
+//! https://www.rfc-editor.org/rfc/rfc4315.html#section-2.1
+
+use std::num::NonZeroU32;
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::CommandBody,
+    response::{Data, StatusBody, StatusKind},
+    sequence::SequenceSet,
+};
+
+/// The task resolving the UID EXPUNGE command.
+///
+/// The output contains the message sequence numbers of expunged
+/// messages.
+#[derive(Clone, Debug)]
+pub struct UidExpungeTask {
+    sequence_set: SequenceSet,
+    expunged: Vec<NonZeroU32>,
+}
+
+impl UidExpungeTask {
+    pub fn new(sequence_set: SequenceSet) -> Self {
+        Self {
+            sequence_set,
+            expunged: Vec::new(),
+        }
+    }
+}
+
+impl Task for UidExpungeTask {
+    type Output = Result<Vec<NonZeroU32>, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::ExpungeUid {
+            sequence_set: self.sequence_set.clone(),
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::Expunge(seq) => {
+                self.expunged.push(seq);
+                None
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.expunged),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
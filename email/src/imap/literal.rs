@@ -0,0 +1,211 @@
+//! # IMAP literals
+//!
+//! Module dedicated to IMAP literals. By default, literals are
+//! synchronizing: the client waits for the server to accept the
+//! literal before sending its data, which costs one round trip per
+//! literal. Servers advertising the [LITERAL+ or LITERAL-] extension
+//! accept non-synchronizing literals, which are sent straight away.
+//!
+//! [LITERAL+ or LITERAL-]: https://www.rfc-editor.org/rfc/rfc7888.html
+
+use imap_next::imap_types::{
+    core::{AString, IString, Literal, LiteralMode},
+    extensions::literal::LiteralCapability,
+    response::Capability,
+    search::SearchKey,
+};
+
+/// The maximum size of non-synchronizing literals accepted by servers
+/// advertising LITERAL-.
+pub const LITERAL_MINUS_MAX_SIZE: usize = 4096;
+
+/// The support of non-synchronizing literals by the server.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NonSyncLiterals {
+    /// Non-synchronizing literals are not supported.
+    #[default]
+    Unsupported,
+
+    /// Non-synchronizing literals are supported up to
+    /// [`LITERAL_MINUS_MAX_SIZE`] bytes (LITERAL-).
+    Limited,
+
+    /// Non-synchronizing literals are supported whatever their size
+    /// (LITERAL+).
+    Unlimited,
+}
+
+impl NonSyncLiterals {
+    /// Find the support of non-synchronizing literals from the given
+    /// server capabilities.
+    pub fn from_capabilities<'a>(
+        capabilities: impl IntoIterator<Item = &'a Capability<'a>>,
+    ) -> Self {
+        let mut support = Self::Unsupported;
+
+        for capability in capabilities {
+            match capability {
+                Capability::Literal(LiteralCapability::Plus) => return Self::Unlimited,
+                Capability::Literal(LiteralCapability::Minus) => support = Self::Limited,
+                _ => (),
+            }
+        }
+
+        support
+    }
+
+    /// Return `true` if a literal of the given size can be sent
+    /// without synchronization.
+    pub fn accept(&self, size: usize) -> bool {
+        match self {
+            Self::Unsupported => false,
+            Self::Limited => size <= LITERAL_MINUS_MAX_SIZE,
+            Self::Unlimited => true,
+        }
+    }
+}
+
+/// Literals statistics of an IMAP client.
+///
+/// Each synchronizing literal costs one round trip, so the number of
+/// non-synchronizing literals is the number of round trips saved.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LiteralsStats {
+    /// The number of synchronizing literals sent.
+    pub sync: usize,
+
+    /// The number of non-synchronizing literals sent.
+    pub non_sync: usize,
+}
+
+impl LiteralsStats {
+    /// Return the number of round trips saved thanks to
+    /// non-synchronizing literals.
+    pub fn saved_round_trips(&self) -> usize {
+        self.non_sync
+    }
+
+    /// Make the given literal non-synchronizing if supported, and
+    /// count it.
+    pub(crate) fn prepare<'a>(
+        &mut self,
+        literal: Literal<'a>,
+        support: NonSyncLiterals,
+    ) -> Literal<'a> {
+        if support.accept(literal.data().len()) {
+            self.non_sync += 1;
+            literal.into_non_sync()
+        } else {
+            self.sync += 1;
+            literal
+        }
+    }
+
+    /// Make literals of the given search key non-synchronizing if
+    /// supported, and count them.
+    ///
+    /// Only the search keys built by the library are inspected,
+    /// others are returned unchanged.
+    pub(crate) fn prepare_search_key(
+        &mut self,
+        key: SearchKey<'static>,
+        support: NonSyncLiterals,
+    ) -> SearchKey<'static> {
+        match key {
+            SearchKey::And(keys) => {
+                let keys = keys
+                    .into_iter()
+                    .map(|key| self.prepare_search_key(key, support))
+                    .collect::<Vec<_>>();
+                // the number of keys does not change
+                SearchKey::And(keys.try_into().unwrap())
+            }
+            SearchKey::Or(left, right) => {
+                let left = self.prepare_search_key(*left, support);
+                let right = self.prepare_search_key(*right, support);
+                SearchKey::Or(Box::new(left), Box::new(right))
+            }
+            SearchKey::Not(key) => SearchKey::Not(Box::new(self.prepare_search_key(*key, support))),
+            SearchKey::From(s) => SearchKey::From(self.prepare_astring(s, support)),
+            SearchKey::To(s) => SearchKey::To(self.prepare_astring(s, support)),
+            SearchKey::Subject(s) => SearchKey::Subject(self.prepare_astring(s, support)),
+            SearchKey::Body(s) => SearchKey::Body(self.prepare_astring(s, support)),
+            key => key,
+        }
+    }
+
+    fn prepare_astring(
+        &mut self,
+        s: AString<'static>,
+        support: NonSyncLiterals,
+    ) -> AString<'static> {
+        match s {
+            AString::String(IString::Literal(literal)) if literal.mode() == LiteralMode::Sync => {
+                AString::String(IString::Literal(self.prepare(literal, support)))
+            }
+            s => s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_next::imap_types::{
+        core::{AString, IString, Literal, LiteralMode},
+        extensions::literal::LiteralCapability,
+        response::Capability,
+        search::SearchKey,
+    };
+
+    use super::{LiteralsStats, NonSyncLiterals, LITERAL_MINUS_MAX_SIZE};
+
+    #[test]
+    fn non_sync_literals_support() {
+        let plus = Capability::Literal(LiteralCapability::Plus);
+        let minus = Capability::Literal(LiteralCapability::Minus);
+
+        let support = NonSyncLiterals::from_capabilities([&Capability::Imap4Rev1]);
+        assert_eq!(support, NonSyncLiterals::Unsupported);
+        assert!(!support.accept(1));
+
+        let support = NonSyncLiterals::from_capabilities([&minus]);
+        assert_eq!(support, NonSyncLiterals::Limited);
+        assert!(support.accept(LITERAL_MINUS_MAX_SIZE));
+        assert!(!support.accept(LITERAL_MINUS_MAX_SIZE + 1));
+
+        let support = NonSyncLiterals::from_capabilities([&minus, &plus]);
+        assert_eq!(support, NonSyncLiterals::Unlimited);
+        assert!(support.accept(LITERAL_MINUS_MAX_SIZE + 1));
+    }
+
+    #[test]
+    fn prepare_search_key() {
+        let literal = || {
+            let literal = Literal::try_from("multi\r\nline").unwrap();
+            AString::String(IString::Literal(literal))
+        };
+
+        let key = SearchKey::Or(
+            Box::new(SearchKey::From(literal())),
+            Box::new(SearchKey::Not(Box::new(SearchKey::Subject(literal())))),
+        );
+
+        let mut stats = LiteralsStats::default();
+        let key = stats.prepare_search_key(key, NonSyncLiterals::Unlimited);
+        assert_eq!(stats.non_sync, 2);
+        assert_eq!(stats.saved_round_trips(), 2);
+
+        let SearchKey::Or(left, _) = key else {
+            panic!("expected OR search key");
+        };
+        let SearchKey::From(AString::String(IString::Literal(literal))) = *left else {
+            panic!("expected FROM search key with literal");
+        };
+        assert_eq!(literal.mode(), LiteralMode::NonSync);
+
+        let mut stats = LiteralsStats::default();
+        stats.prepare_search_key(SearchKey::Body(literal()), NonSyncLiterals::Unsupported);
+        assert_eq!(stats.sync, 1);
+        assert_eq!(stats.non_sync, 0);
+    }
+}
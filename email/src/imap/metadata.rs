@@ -0,0 +1,147 @@
+//! # IMAP metadata
+//!
+//! Module dedicated to the IMAP METADATA extension, which allows
+//! clients to attach arbitrary entries to mailboxes. Entries are
+//! stored server-side, so they are shared by all the clients of the
+//! account.
+//!
+//! https://www.rfc-editor.org/rfc/rfc5464.html
+
+use imap_client::tasks::{tasks::TaskError, Task};
+#[doc(inline)]
+pub use imap_next::imap_types::extensions::metadata::{Entry, EntryValue};
+use imap_next::imap_types::{
+    command::CommandBody,
+    core::{AString, IString, NString, NString8, Vec1},
+    extensions::metadata::MetadataResponse,
+    mailbox::Mailbox,
+    response::{Data, StatusBody, StatusKind},
+};
+
+/// Build a metadata entry from the given entry name.
+///
+/// Returns `None` if the name is not a valid entry, for example if
+/// it does not start with `/private` or `/shared`.
+pub fn entry(name: &str) -> Option<Entry<'static>> {
+    let name = AString::try_from(name.to_owned()).ok()?;
+    Entry::try_from(name).ok()
+}
+
+/// Build a metadata entry value from the given value.
+///
+/// A `None` value removes the entry. Returns `None` if the value
+/// cannot be represented by an IMAP string.
+pub fn entry_value(name: &str, value: Option<String>) -> Option<EntryValue<'static>> {
+    let value = match value {
+        Some(value) => NString(Some(IString::try_from(value).ok()?)),
+        None => NString(None),
+    };
+
+    Some(EntryValue {
+        entry: entry(name)?,
+        value: NString8::NString(value),
+    })
+}
+
+/// The task resolving the GETMETADATA command of a mailbox.
+#[derive(Clone, Debug)]
+pub struct GetMetadataTask {
+    mailbox: Mailbox<'static>,
+    entries: Vec1<Entry<'static>>,
+    values: Vec<(String, Option<String>)>,
+}
+
+impl GetMetadataTask {
+    pub fn new(mailbox: Mailbox<'static>, entries: Vec1<Entry<'static>>) -> Self {
+        Self {
+            mailbox,
+            entries,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl Task for GetMetadataTask {
+    type Output = Result<Vec<(String, Option<String>)>, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::GetMetadata {
+            options: Vec::new(),
+            mailbox: self.mailbox.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::Metadata {
+                items: MetadataResponse::WithValues(values),
+                ..
+            } => {
+                for EntryValue { entry, value } in values.into_iter() {
+                    let entry = String::from_utf8_lossy(entry.as_ref()).to_string();
+                    let value = match value {
+                        NString8::NString(NString(None)) => None,
+                        NString8::NString(NString(Some(value))) => {
+                            Some(String::from_utf8_lossy(value.as_ref()).to_string())
+                        }
+                        NString8::Literal8(value) => {
+                            Some(String::from_utf8_lossy(value.data.as_ref()).to_string())
+                        }
+                    };
+
+                    self.values.push((entry, value));
+                }
+
+                None
+            }
+            // entries without values are only sent unsolicited, to
+            // notify changes
+            Data::Metadata { .. } => None,
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.values),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+/// The task resolving the SETMETADATA command of a mailbox.
+#[derive(Clone, Debug)]
+pub struct SetMetadataTask {
+    mailbox: Mailbox<'static>,
+    entry_values: Vec1<EntryValue<'static>>,
+}
+
+impl SetMetadataTask {
+    pub fn new(mailbox: Mailbox<'static>, entry_values: Vec1<EntryValue<'static>>) -> Self {
+        Self {
+            mailbox,
+            entry_values,
+        }
+    }
+}
+
+impl Task for SetMetadataTask {
+    type Output = Result<(), TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::SetMetadata {
+            mailbox: self.mailbox.clone(),
+            entry_values: self.entry_values.clone(),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(()),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
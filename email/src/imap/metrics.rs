@@ -0,0 +1,310 @@
+//! # IMAP metrics
+//!
+//! Module dedicated to IMAP metrics. IMAP clients count the commands
+//! they issue, and report them to an optional [`ImapMetricsSink`]
+//! once given back to the pool, labelled with the high-level
+//! operation (backend feature) that used them. This helps detecting
+//! N+1 patterns, like one FETCH per message instead of one FETCH
+//! per set of messages.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// The IMAP command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum ImapCommand {
+    Append,
+    Copy,
+    Create,
+    Delete,
+    Examine,
+    Expunge,
+    Fetch,
+    GetMetadata,
+    GetQuotaRoot,
+    Idle,
+    List,
+    Move,
+    Noop,
+    Search,
+    Select,
+    SetMetadata,
+    Sort,
+    Status,
+    Store,
+    Thread,
+}
+
+impl fmt::Display for ImapCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cmd = match self {
+            Self::Append => "APPEND",
+            Self::Copy => "COPY",
+            Self::Create => "CREATE",
+            Self::Delete => "DELETE",
+            Self::Examine => "EXAMINE",
+            Self::Expunge => "EXPUNGE",
+            Self::Fetch => "FETCH",
+            Self::GetMetadata => "GETMETADATA",
+            Self::GetQuotaRoot => "GETQUOTAROOT",
+            Self::Idle => "IDLE",
+            Self::List => "LIST",
+            Self::Move => "MOVE",
+            Self::Noop => "NOOP",
+            Self::Search => "SEARCH",
+            Self::Select => "SELECT",
+            Self::SetMetadata => "SETMETADATA",
+            Self::Sort => "SORT",
+            Self::Status => "STATUS",
+            Self::Store => "STORE",
+            Self::Thread => "THREAD",
+        };
+
+        write!(f, "{cmd}")
+    }
+}
+
+/// The number of issued IMAP commands, by command.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImapCommandCounts(BTreeMap<ImapCommand, usize>);
+
+impl ImapCommandCounts {
+    /// Count one more issued command.
+    pub fn record(&mut self, cmd: ImapCommand) {
+        *self.0.entry(cmd).or_default() += 1;
+    }
+
+    /// Return the number of issued commands of the given kind.
+    pub fn get(&self, cmd: ImapCommand) -> usize {
+        self.0.get(&cmd).copied().unwrap_or_default()
+    }
+
+    /// Return the total number of issued commands.
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// Return `true` if no command has been issued.
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Iterate over the issued commands and their count.
+    pub fn iter(&self) -> impl Iterator<Item = (ImapCommand, usize)> + '_ {
+        self.0
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(cmd, n)| (*cmd, *n))
+    }
+
+    /// Return the commands issued since the given previous counts.
+    pub fn since(&self, previous: &Self) -> Self {
+        let counts = self
+            .0
+            .iter()
+            .map(|(cmd, n)| (*cmd, n.saturating_sub(previous.get(*cmd))))
+            .filter(|(_, n)| *n > 0);
+
+        Self(BTreeMap::from_iter(counts))
+    }
+
+    /// Add the given counts to the current ones.
+    pub fn extend(&mut self, other: &Self) {
+        for (cmd, n) in other.iter() {
+            *self.0.entry(cmd).or_default() += n;
+        }
+    }
+}
+
+impl fmt::Display for ImapCommandCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut glue = "";
+
+        for (cmd, n) in self.iter() {
+            write!(f, "{glue}{n} {cmd}")?;
+            glue = " + ";
+        }
+
+        if glue.is_empty() {
+            write!(f, "no command")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The IMAP metrics sink.
+///
+/// The sink receives the commands issued by a client each time it is
+/// given back to the pool. A single operation can take more than one
+/// client, in which case the sink is called more than once for the
+/// same operation.
+pub trait ImapMetricsSink: Send + Sync {
+    /// Record the commands issued for the given operation.
+    fn record(&self, operation: &str, commands: &ImapCommandCounts);
+
+    /// Record the sizes of the FETCH batches chosen for the given
+    /// operation.
+    ///
+    /// Does nothing by default.
+    fn record_fetch_batches(&self, _operation: &str, _sizes: &[usize]) {
+        //
+    }
+}
+
+/// Shared IMAP metrics sink.
+///
+/// This wrapper allows contexts and their builders to hold a metrics
+/// sink while staying cloneable and comparable. Two shared sinks are
+/// equal if they point to the same sink.
+#[derive(Clone)]
+pub struct SharedImapMetricsSink(Arc<dyn ImapMetricsSink>);
+
+impl SharedImapMetricsSink {
+    /// Create a new shared sink from the given sink.
+    pub fn new(sink: impl ImapMetricsSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    /// Record the commands issued for the given operation.
+    pub fn record(&self, operation: &str, commands: &ImapCommandCounts) {
+        self.0.record(operation, commands)
+    }
+
+    /// Record the sizes of the FETCH batches chosen for the given
+    /// operation.
+    pub fn record_fetch_batches(&self, operation: &str, sizes: &[usize]) {
+        self.0.record_fetch_batches(operation, sizes)
+    }
+}
+
+impl<T: ImapMetricsSink + 'static> From<Arc<T>> for SharedImapMetricsSink {
+    fn from(sink: Arc<T>) -> Self {
+        Self(sink)
+    }
+}
+
+impl fmt::Debug for SharedImapMetricsSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedImapMetricsSink")
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for SharedImapMetricsSink {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedImapMetricsSink {}
+
+/// In-memory IMAP metrics sink.
+///
+/// Commands are accumulated by operation, until they are taken. This
+/// sink is mostly useful for asserting command budgets in tests.
+#[derive(Debug, Default)]
+pub struct MemoryImapMetricsSink {
+    operations: Mutex<HashMap<String, ImapCommandCounts>>,
+    fetch_batches: Mutex<HashMap<String, Vec<usize>>>,
+}
+
+impl MemoryImapMetricsSink {
+    /// Take the commands accumulated for the given operation.
+    pub fn take(&self, operation: &str) -> ImapCommandCounts {
+        self.operations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(operation)
+            .unwrap_or_default()
+    }
+
+    /// Take the FETCH batch sizes accumulated for the given
+    /// operation.
+    pub fn take_fetch_batches(&self, operation: &str) -> Vec<usize> {
+        self.fetch_batches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(operation)
+            .unwrap_or_default()
+    }
+}
+
+impl ImapMetricsSink for MemoryImapMetricsSink {
+    fn record(&self, operation: &str, commands: &ImapCommandCounts) {
+        self.operations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(operation.to_owned())
+            .or_default()
+            .extend(commands)
+    }
+
+    fn record_fetch_batches(&self, operation: &str, sizes: &[usize]) {
+        self.fetch_batches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(operation.to_owned())
+            .or_default()
+            .extend_from_slice(sizes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        ImapCommand, ImapCommandCounts, ImapMetricsSink, MemoryImapMetricsSink,
+        SharedImapMetricsSink,
+    };
+
+    #[test]
+    fn command_counts() {
+        let mut counts = ImapCommandCounts::default();
+        assert!(counts.is_empty());
+        assert_eq!(counts.to_string(), "no command");
+
+        counts.record(ImapCommand::Select);
+        let previous = counts.clone();
+
+        counts.record(ImapCommand::Select);
+        counts.record(ImapCommand::Fetch);
+        counts.record(ImapCommand::Fetch);
+        counts.record(ImapCommand::Fetch);
+        assert_eq!(counts.total(), 5);
+        assert_eq!(counts.get(ImapCommand::Store), 0);
+
+        let since = counts.since(&previous);
+        assert_eq!(since.get(ImapCommand::Select), 1);
+        assert_eq!(since.get(ImapCommand::Fetch), 3);
+        assert_eq!(since.to_string(), "3 FETCH + 1 SELECT");
+    }
+
+    #[test]
+    fn memory_sink() {
+        let sink = Arc::new(MemoryImapMetricsSink::default());
+        let shared = SharedImapMetricsSink::from(sink.clone());
+        assert_eq!(shared.clone(), shared);
+
+        let mut counts = ImapCommandCounts::default();
+        counts.record(ImapCommand::Select);
+        counts.record(ImapCommand::Fetch);
+
+        shared.record("list_envelopes", &counts);
+        sink.record("list_envelopes", &counts);
+
+        let counts = sink.take("list_envelopes");
+        assert_eq!(counts.get(ImapCommand::Select), 2);
+        assert_eq!(counts.get(ImapCommand::Fetch), 2);
+        assert!(sink.take("list_envelopes").is_empty());
+
+        shared.record_fetch_batches("list_envelopes", &[32, 64]);
+        shared.record_fetch_batches("list_envelopes", &[128]);
+        assert_eq!(sink.take_fetch_batches("list_envelopes"), [32, 64, 128]);
+        assert!(sink.take_fetch_batches("list_envelopes").is_empty());
+    }
+}
@@ -1,51 +1,87 @@
+pub mod alert;
+pub mod batch;
+pub mod codec;
+pub mod condstore;
 pub mod config;
 mod error;
-
-use std::{collections::HashMap, env, fmt, num::NonZeroU32, sync::Arc, time::Duration};
+pub mod expunge;
+pub mod literal;
+pub mod metadata;
+pub mod metrics;
+pub mod namespace;
+pub mod notify;
+pub mod quota;
+pub mod status;
+
+use std::{
+    collections::HashMap,
+    env, fmt,
+    num::NonZeroU32,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use futures::{stream::FuturesUnordered, StreamExt};
 use imap_client::{
-    tasks::{tasks::select::SelectDataUnvalidated, SchedulerError},
+    tasks::{
+        tasks::{appenduid::AppendUidTask, select::SelectDataUnvalidated},
+        SchedulerError, Task,
+    },
     Client, ClientError,
 };
 use imap_next::{
     imap_types::{
         auth::AuthMechanism,
-        core::{IString, NString, Vec1},
+        core::{IString, Literal, LiteralOrLiteral8, NString, Vec1},
         extensions::{
+            enable::{CapabilityEnable, Utf8Kind},
             sort::SortCriterion,
             thread::{Thread, ThreadingAlgorithm},
         },
         fetch::MessageDataItem,
         flag::{Flag, StoreType},
+        mailbox::Mailbox,
+        response::Capability,
         search::SearchKey,
         sequence::SequenceSet,
     },
-    stream::Error as StreamError,
+    stream::{Error as StreamError, Stream},
 };
-use once_cell::sync::Lazy;
 use paste::paste;
 use tokio::{
     select,
-    sync::{oneshot, Mutex, MutexGuard},
-    time::sleep,
+    sync::{oneshot, Mutex, MutexGuard, Semaphore, SemaphorePermit},
 };
 
-use self::config::{ImapAuthConfig, ImapConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
+use self::{
+    alert::{AlertTask, NoOpTask, SharedImapAlertSink},
+    codec::{utf8_accept_supported, FolderNameCodec},
+    condstore::ChangedSinceTask,
+    config::{ImapAuthConfig, ImapConfig},
+    expunge::UidExpungeTask,
+    literal::{LiteralsStats, NonSyncLiterals},
+    metadata::{GetMetadataTask, SetMetadataTask},
+    metrics::{ImapCommand, ImapCommandCounts, SharedImapMetricsSink},
+    namespace::{ImapNamespace, NamespaceTask},
+    quota::GetQuotaRootTask,
+    status::{MailboxStatus, StatusTask},
+};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::OAuth2Method;
+#[cfg(feature = "stream")]
+use crate::envelope::stream::{imap::StreamImapEnvelopes, StreamEnvelopes};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{imap::ThreadImapEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
 use crate::envelope::watch::{imap::WatchImapEnvelopes, WatchEnvelopes};
-#[cfg(feature = "oauth2")]
-use crate::warn;
 use crate::{
     account::config::AccountConfig,
     backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
@@ -58,6 +94,7 @@
     },
     flag::{
         add::{imap::AddImapFlags, AddFlags},
+        list::{imap::ListImapFlags, ListFlags},
         remove::{imap::RemoveImapFlags, RemoveFlags},
         set::{imap::SetImapFlags, SetFlags},
     },
@@ -65,9 +102,17 @@
         add::{imap::AddImapFolder, AddFolder},
         delete::{imap::DeleteImapFolder, DeleteFolder},
         expunge::{imap::ExpungeImapFolder, ExpungeFolder},
+        get::{imap::GetImapFolder, GetFolder},
         list::{imap::ListImapFolders, ListFolders},
+        metadata::{
+            imap::{GetImapFolderMetadata, SetImapFolderMetadata},
+            GetFolderMetadata, SetFolderMetadata,
+        },
         purge::{imap::PurgeImapFolder, PurgeFolder},
-        Folders,
+        quota::{imap::GetImapQuota, GetQuota, Quota},
+        search::{imap::SearchImapFolders, SearchFolders},
+        stats::{imap::GetImapFolderStats, GetFolderStats},
+        Folders, INBOX,
     },
     imap::config::ImapEncryptionKind,
     message::{
@@ -75,22 +120,28 @@
         copy::{imap::CopyImapMessages, CopyMessages},
         delete::{imap::DeleteImapMessages, DeleteMessages},
         get::{imap::GetImapMessages, GetMessages},
-        imap::{FETCH_MESSAGES, PEEK_MESSAGES},
+        imap::{FETCH_MESSAGES, FETCH_MESSAGES_SIZES, PEEK_MESSAGES},
         peek::{imap::PeekImapMessages, PeekMessages},
         r#move::{imap::MoveImapMessages, MoveMessages},
         remove::{imap::RemoveImapMessages, RemoveMessages},
         Messages,
     },
-    retry::{Retry, RetryState},
-    AnyResult,
+    network::{self, config::NetworkConfig, stream::SharedStreamConnector},
+    retry::{Retry, RetryState, DEFAULT_TIMEOUT},
+    runtime, warn, AnyResult,
 };
 
 macro_rules! retry {
-    ($self:ident, $task:expr, $err:ident) => {
+    ($self:ident, $task:expr, $err:ident, [$($cmd:ident),+]) => {
         paste! {{
-            let mut retry = Retry::default();
+            let mut retry = Retry::new($self.command_timeout(&[$(ImapCommand::$cmd),+]));
+            let mut reconnected = false;
+
+            $self.command_pending = true;
+
+            let res = loop {
+                $($self.commands.record(ImapCommand::$cmd);)+
 
-            loop {
                 match retry.next(retry.timeout($task).await) {
                     RetryState::Retry => {
                         debug!(attempt = retry.attempts, "request timed out");
@@ -102,72 +153,112 @@ macro_rules! retry {
                     RetryState::Ok(Ok(res)) => {
                         break Ok(res);
                     }
-                    RetryState::Ok(Err(ClientError::Stream(StreamError::State(SchedulerError::UnexpectedByeResponse(bye))))) => {
+                    RetryState::Ok(Err(err)) if !reconnected && is_connection_lost(&err) => {
                         #[cfg(feature = "tracing")]
-                        tracing::debug!(reason = bye.text.to_string(), "connection closed");
+                        tracing::debug!(?err, "connection lost");
 
-                        #[cfg(feature = "tracing")]
-			tracing::debug!("re-connecting…");
-
-			$self.inner = $self.client_builder.build().await?;
+                        $self.reconnect().await?;
 
-			if let Some(mbox) = &$self.mailbox {
-			    $self.inner.select(mbox.clone()).await.map_err(Error::SelectMailboxError)?;
-			}
+                        if !is_replayable(&[$(ImapCommand::$cmd),+]) {
+                            break Err(Error::[<$err Error>](err));
+                        }
 
-			retry.attempts = 0;
-			continue;
+                        reconnected = true;
+                        retry.attempts = 0;
+                        continue;
                     }
                     RetryState::Ok(Err(err)) => {
-			break Err(Error::[<$err Error>](err));
+                        break Err(Error::[<$err Error>](err));
                     }
-		}
-            }
+                }
+            };
+
+            // a timed out command may still be running on the server
+            $self.command_pending = matches!(res, Err(Error::[<$err TimedOutError>]));
+
+            res
         }}
     };
 }
 
-static ID_PARAMS: Lazy<Vec<(IString<'static>, NString<'static>)>> = Lazy::new(|| {
-    vec![
-        (
-            "name".try_into().unwrap(),
-            NString(
-                env::var("CARGO_PKG_NAME")
-                    .ok()
-                    .map(|e| e.try_into().unwrap()),
-            ),
-        ),
-        (
-            "vendor".try_into().unwrap(),
-            NString(
-                env::var("CARGO_PKG_NAME")
-                    .ok()
-                    .map(|e| e.try_into().unwrap()),
-            ),
-        ),
-        (
-            "version".try_into().unwrap(),
-            NString(
-                env::var("CARGO_PKG_VERSION")
-                    .ok()
-                    .map(|e| e.try_into().unwrap()),
-            ),
-        ),
-        (
-            "support-url".try_into().unwrap(),
-            NString(Some(
-                "https://github.com/orgs/pimalaya/discussions/new?category=q-a"
-                    .try_into()
-                    .unwrap(),
-            )),
-        ),
+/// The maximum number of connection attempts made when re-connecting
+/// a client after a connection loss.
+const RECONNECT_MAX_ATTEMPTS: u32 = 4;
+
+/// The delay before the second connection attempt. This delay
+/// doubles after each failed attempt.
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Return `true` if the given client error means that the connection
+/// to the server has been lost (BYE response, closed stream or I/O
+/// error like a broken pipe).
+fn is_connection_lost(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::Stream(
+            StreamError::Closed
+                | StreamError::Io(_)
+                | StreamError::State(SchedulerError::UnexpectedByeResponse(_))
+        )
+    )
+}
+
+/// The commands that must not be replayed after a connection loss.
+///
+/// The server may have processed them before the connection got
+/// lost, in which case replaying them would add, copy or move
+/// messages twice, or fail to create a mailbox that now exists.
+/// Commands targeting messages by UID are not replayed either, since
+/// the UIDVALIDITY of the mailbox is not validated again after
+/// re-connecting.
+const NON_REPLAYABLE_COMMANDS: [ImapCommand; 6] = [
+    ImapCommand::Append,
+    ImapCommand::Copy,
+    ImapCommand::Create,
+    ImapCommand::Expunge,
+    ImapCommand::Move,
+    ImapCommand::Store,
+];
+
+/// Return `true` if the given commands can safely be replayed after
+/// a connection loss.
+fn is_replayable(cmds: &[ImapCommand]) -> bool {
+    !cmds.iter().any(|cmd| NON_REPLAYABLE_COMMANDS.contains(cmd))
+}
+
+/// Build the parameters of the ID command from the given
+/// configuration.
+///
+/// The client name and version default to the ones of the program,
+/// when available. Values that cannot be sent as IMAP strings are
+/// left empty.
+fn id_params(config: &ImapConfig) -> Vec<(IString<'static>, NString<'static>)> {
+    let pkg_name = env::var("CARGO_PKG_NAME").ok();
+    let pkg_version = env::var("CARGO_PKG_VERSION").ok();
+
+    let name = config.id_name().map(ToOwned::to_owned).or(pkg_name.clone());
+    let version = config.id_version().map(ToOwned::to_owned).or(pkg_version);
+    let support_url = "https://github.com/orgs/pimalaya/discussions/new?category=q-a";
+
+    [
+        ("name", name),
+        ("vendor", pkg_name),
+        ("version", version),
+        ("support-url", Some(support_url.to_owned())),
     ]
-});
+    .into_iter()
+    .map(|(key, val)| {
+        let key = key.try_into().unwrap();
+        let val = NString(val.and_then(|val| val.try_into().ok()));
+        (key, val)
+    })
+    .collect()
+}
 
 /// The IMAP backend context.
 ///
 /// This context is unsync, which means it cannot be shared between
-/// threads. For the sync version, see [`ImapContextSync`].
+/// threads. For the sync version, see [`ImapContext`].
 pub struct ImapClient {
     pub id: u8,
 
@@ -185,61 +276,566 @@ pub struct ImapClient {
 
     /// The selected mailbox.
     mailbox: Option<String>,
+
+    /// The literals statistics.
+    literals_stats: LiteralsStats,
+
+    /// The number of issued commands.
+    commands: ImapCommandCounts,
+
+    /// The personal namespace, if discovered.
+    namespace: Option<ImapNamespace>,
+
+    /// The alert sink.
+    alert_sink: Option<SharedImapAlertSink>,
+
+    /// Whether a command is being exchanged with the server.
+    command_pending: bool,
+
+    /// Whether the session is out of sync with the server, because
+    /// the client was given back to the pool in the middle of a
+    /// command (timed out or cancelled call).
+    poisoned: bool,
 }
 
 impl ImapClient {
+    /// Return the timeout of requests issuing the given commands.
+    fn command_timeout(&self, cmds: &[ImapCommand]) -> Duration {
+        if cmds.contains(&ImapCommand::Fetch) {
+            self.imap_config.fetch_timeout()
+        } else {
+            DEFAULT_TIMEOUT
+        }
+    }
+
+    /// Re-connect the client after a connection loss, then select
+    /// back the previously selected mailbox.
+    ///
+    /// Connection attempts are retried with an exponential backoff.
+    async fn reconnect(&mut self) -> Result<()> {
+        let mut delay = RECONNECT_BASE_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            debug!(attempt, "re-connecting…");
+
+            match self.client_builder.build().await {
+                Ok(client) => {
+                    self.inner = client;
+                    break;
+                }
+                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                Err(err) if attempt < RECONNECT_MAX_ATTEMPTS => {
+                    debug!(?err, "cannot re-connect, retrying in {delay:?}");
+                    runtime::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        if let Some(mbox) = &self.mailbox {
+            self.commands.record(ImapCommand::Select);
+            self.inner
+                .select(mbox.clone())
+                .await
+                .map_err(Error::SelectMailboxError)?;
+        }
+
+        Ok(())
+    }
+
     pub fn ext_sort_supported(&self) -> bool {
         self.inner.ext_sort_supported()
     }
 
+    /// Return `true` if the server supports threading messages with
+    /// the REFERENCES algorithm.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc5256.html
+    pub fn ext_thread_references_supported(&self) -> bool {
+        self.inner.capabilities_iter().any(|capability| {
+            capability
+                .to_string()
+                .eq_ignore_ascii_case("THREAD=REFERENCES")
+        })
+    }
+
+    /// Return the support of the UIDPLUS extension.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc4315.html
+    pub fn ext_uidplus_supported(&self) -> bool {
+        self.inner
+            .capabilities_iter()
+            .any(|capability| matches!(capability, Capability::UidPlus))
+    }
+
+    /// Return the support of the QUOTA extension.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc9208.html
+    pub fn ext_quota_supported(&self) -> bool {
+        self.inner.capabilities_iter().any(|capability| {
+            let capability = capability.to_string().to_ascii_uppercase();
+            capability == "QUOTA" || capability.starts_with("QUOTA=RES-")
+        })
+    }
+
+    /// Return the support of the METADATA extension, which allows
+    /// clients to attach entries to mailboxes.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc5464.html
+    pub fn ext_metadata_supported(&self) -> bool {
+        self.inner
+            .capabilities_iter()
+            .any(|capability| capability.to_string().eq_ignore_ascii_case("METADATA"))
+    }
+
+    /// Return the support of the NOTIFY extension, which allows a
+    /// single connection to monitor multiple mailboxes.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc5465.html
+    pub fn ext_notify_supported(&self) -> bool {
+        self.inner
+            .capabilities_iter()
+            .any(|capability| capability.to_string().eq_ignore_ascii_case("NOTIFY"))
+    }
+
+    /// Return the support of non-synchronizing literals, taking into
+    /// account the configuration override.
+    pub fn non_sync_literals(&self) -> NonSyncLiterals {
+        if !self.imap_config.non_sync_literals_enabled() {
+            return NonSyncLiterals::Unsupported;
+        }
+
+        NonSyncLiterals::from_capabilities(self.inner.capabilities_iter())
+    }
+
+    /// Return the statistics of literals sent by this client.
+    ///
+    /// This can be used to measure the round trips saved by
+    /// non-synchronizing literals.
+    pub fn literals_stats(&self) -> LiteralsStats {
+        self.literals_stats
+    }
+
+    /// Return the maximum size of appended messages, in bytes, as
+    /// advertised by the APPENDLIMIT capability.
+    ///
+    /// Returns `None` when the server does not advertise a global
+    /// limit. Per-mailbox limits are not supported.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7889.html
+    pub fn append_limit(&self) -> Option<u64> {
+        self.inner.capabilities_iter().find_map(|capability| {
+            let capability = capability.to_string();
+            let (name, limit) = capability.split_once('=')?;
+
+            if name.eq_ignore_ascii_case("APPENDLIMIT") {
+                limit.parse().ok()
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Return the personal namespace discovered on connect.
+    pub fn namespace(&self) -> Option<&ImapNamespace> {
+        self.namespace.as_ref()
+    }
+
+    /// Find the alias of the given folder.
+    ///
+    /// The returned name is not encoded yet, see
+    /// [`ImapClient::encode_folder`].
+    ///
+    /// See [`AccountConfig::get_folder_alias`].
+    pub fn get_folder_alias(&self, folder: &str) -> String {
+        self.account_config.get_folder_alias(folder)
+    }
+
+    /// Return `true` if UTF8=ACCEPT is enabled, in which case folder
+    /// names are exchanged in UTF-8 instead of modified UTF-7.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc6855.html
+    pub fn utf8_accept_enabled(&self) -> bool {
+        self.imap_config.utf8_accept_enabled() && ext_utf8_accept_supported(&self.inner)
+    }
+
+    /// Return the folder name codec of this client.
+    pub fn folder_codec(&self) -> FolderNameCodec<'_> {
+        FolderNameCodec::new(self.utf8_accept_enabled(), self.namespace.as_ref())
+    }
+
+    /// Encode the given folder name, so that it can be sent to the
+    /// server.
+    ///
+    /// This is the only place where folder names are encoded:
+    /// features manipulate decoded names, and only send encoded
+    /// ones. See [`FolderNameCodec::encode`].
+    pub fn encode_folder(&self, folder: impl ToString) -> String {
+        self.folder_codec().encode(folder)
+    }
+
+    /// Decode the given folder name received from the server.
+    ///
+    /// See [`FolderNameCodec::decode`].
+    pub fn decode_folder(&self, folder: impl ToString) -> String {
+        self.folder_codec().decode(folder)
+    }
+
+    /// Return the number of commands issued by this client, by
+    /// command.
+    pub fn command_counts(&self) -> &ImapCommandCounts {
+        &self.commands
+    }
+
+    /// Make literals of the given search criteria non-synchronizing
+    /// when supported.
+    fn prepare_search_criteria(
+        &mut self,
+        search_criteria: impl IntoIterator<Item = SearchKey<'static>>,
+    ) -> Vec<SearchKey<'static>> {
+        let support = self.non_sync_literals();
+
+        search_criteria
+            .into_iter()
+            .map(|key| self.literals_stats.prepare_search_key(key, support))
+            .collect()
+    }
+
+    /// Return `true` if the server supports the CONDSTORE extension,
+    /// which is implied by the QRESYNC extension.
+    pub fn ext_condstore_supported(&self) -> bool {
+        self.inner
+            .capabilities_iter()
+            .any(|capability| matches!(capability, Capability::CondStore | Capability::QResync))
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn noop(&mut self) -> Result<()> {
-        retry!(self, self.inner.noop(), NoOp)
+        retry!(self, self.resolve_noop(), NoOp, [Noop])
+    }
+
+    /// Resolve the NOOP command.
+    async fn resolve_noop(&mut self) -> std::result::Result<(), ClientError> {
+        self.resolve(NoOpTask).await??;
+        Ok(())
+    }
+
+    /// Resolve the given task, then report the alerts received in
+    /// the meantime to the alert sink, if any.
+    async fn resolve<T: Task>(&mut self, task: T) -> std::result::Result<T::Output, ClientError> {
+        let (output, alerts) = self.inner.resolve(AlertTask::new(task)).await?;
+
+        for alert in alerts {
+            warn!("received IMAP alert: {alert}");
+
+            if let Some(sink) = self.alert_sink.as_ref() {
+                sink.alert(&alert);
+            }
+        }
+
+        Ok(output)
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn select_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDataUnvalidated> {
-        let data = retry!(self, self.inner.select(mbox.to_string()), SelectMailbox)?;
+        let data = retry!(
+            self,
+            self.inner.select(mbox.to_string()),
+            SelectMailbox,
+            [Select]
+        )?;
         self.mailbox = Some(mbox.to_string());
         Ok(data)
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn examine_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDataUnvalidated> {
-        retry!(self, self.inner.examine(mbox.to_string()), ExamineMailbox)
+        retry!(
+            self,
+            self.inner.examine(mbox.to_string()),
+            ExamineMailbox,
+            [Examine]
+        )
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn create_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
-        retry!(self, self.inner.create(mbox.to_string()), CreateMailbox)
+        retry!(
+            self,
+            self.inner.create(mbox.to_string()),
+            CreateMailbox,
+            [Create]
+        )
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Folders> {
-        let mboxes = retry!(self, self.inner.list("", "*"), ListMailboxes)?;
-        let folders = Folders::from_imap_mailboxes(config, mboxes);
+        self.list_mailboxes(config, "*").await
+    }
+
+    /// List the mailboxes matching the given LIST pattern.
+    ///
+    /// The pattern should already be encoded, see
+    /// [`ImapClient::encode_folder`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn list_mailboxes(
+        &mut self,
+        config: &AccountConfig,
+        pattern: &str,
+    ) -> Result<Folders> {
+        let mboxes = retry!(self, self.inner.list("", pattern), ListMailboxes, [List])?;
+        let mut folders =
+            Folders::from_imap_mailboxes(config, mboxes, |name| self.decode_folder(name));
+
+        // NOTE: the LIST command cannot carry the STATUS return
+        // option yet, so counts are fetched with one STATUS command
+        // per listed folder
+        if self.imap_config.list_status_enabled() {
+            for folder in folders.iter_mut() {
+                let status = self
+                    .mailbox_status(self.encode_folder(&folder.name))
+                    .await?;
+                folder.total = status.messages;
+                folder.unseen = status.unseen;
+            }
+        }
+
         Ok(folders)
     }
 
+    /// Return the total and unseen messages of the given mailbox,
+    /// without selecting it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn mailbox_status(&mut self, mbox: impl ToString) -> Result<MailboxStatus> {
+        let mbox = mbox.to_string();
+        let mailbox =
+            Mailbox::try_from(mbox.clone()).map_err(|err| Error::ParseMailboxError(err, mbox))?;
+
+        retry!(self, self.status(mailbox.clone()), StatusMailbox, [Status])
+    }
+
+    /// Resolve the STATUS command of the given mailbox.
+    async fn status(
+        &mut self,
+        mailbox: Mailbox<'static>,
+    ) -> std::result::Result<MailboxStatus, ClientError> {
+        let status = self.resolve(StatusTask::new(mailbox)).await??;
+        Ok(status)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.select_mailbox(mbox).await?;
-        let expunged = retry!(self, self.inner.expunge(), ExpungeMailbox)?;
+        let expunged = retry!(self, self.inner.expunge(), ExpungeMailbox, [Expunge])?;
         Ok(expunged.len())
     }
 
+    /// Get the quotas applying to the given mailbox.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn get_quota_root(&mut self, mbox: impl ToString) -> Result<Vec<Quota>> {
+        if !self.ext_quota_supported() {
+            return Err(Error::QuotaNotSupportedError);
+        }
+
+        let mbox = mbox.to_string();
+        let mailbox =
+            Mailbox::try_from(mbox.clone()).map_err(|err| Error::ParseMailboxError(err, mbox))?;
+
+        retry!(
+            self,
+            self.quota_root(mailbox.clone()),
+            GetQuotaRoot,
+            [GetQuotaRoot]
+        )
+    }
+
+    /// Resolve the GETQUOTAROOT command of the given mailbox.
+    async fn quota_root(
+        &mut self,
+        mailbox: Mailbox<'static>,
+    ) -> std::result::Result<Vec<Quota>, ClientError> {
+        let quotas = self.resolve(GetQuotaRootTask::new(mailbox)).await??;
+        Ok(quotas)
+    }
+
+    /// Get the given metadata entries of the given mailbox.
+    ///
+    /// Entries not defined on the mailbox are either returned with a
+    /// `None` value or not returned at all, depending on servers.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn get_metadata(
+        &mut self,
+        mbox: impl ToString,
+        entries: &[&str],
+    ) -> Result<Vec<(String, Option<String>)>> {
+        if !self.ext_metadata_supported() {
+            return Err(Error::MetadataNotSupportedError);
+        }
+
+        let mbox = mbox.to_string();
+        let mailbox =
+            Mailbox::try_from(mbox.clone()).map_err(|err| Error::ParseMailboxError(err, mbox))?;
+
+        let entries = entries
+            .iter()
+            .map(|name| {
+                metadata::entry(name)
+                    .ok_or_else(|| Error::ParseMetadataEntryError(name.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let Ok(entries) = Vec1::try_from(entries) else {
+            return Ok(Vec::new());
+        };
+
+        retry!(
+            self,
+            self.metadata(mailbox.clone(), entries.clone()),
+            GetMetadata,
+            [GetMetadata]
+        )
+    }
+
+    /// Resolve the GETMETADATA command of the given mailbox.
+    async fn metadata(
+        &mut self,
+        mailbox: Mailbox<'static>,
+        entries: Vec1<metadata::Entry<'static>>,
+    ) -> std::result::Result<Vec<(String, Option<String>)>, ClientError> {
+        let values = self
+            .resolve(GetMetadataTask::new(mailbox, entries))
+            .await??;
+        Ok(values)
+    }
+
+    /// Set the given metadata entries of the given mailbox.
+    ///
+    /// Entries with a `None` value are removed from the mailbox.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn set_metadata(
+        &mut self,
+        mbox: impl ToString,
+        entries: Vec<(&str, Option<String>)>,
+    ) -> Result<()> {
+        if !self.ext_metadata_supported() {
+            return Err(Error::MetadataNotSupportedError);
+        }
+
+        let mbox = mbox.to_string();
+        let mailbox =
+            Mailbox::try_from(mbox.clone()).map_err(|err| Error::ParseMailboxError(err, mbox))?;
+
+        let entry_values = entries
+            .into_iter()
+            .map(|(name, value)| {
+                metadata::entry_value(name, value)
+                    .ok_or_else(|| Error::ParseMetadataEntryError(name.to_owned()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let Ok(entry_values) = Vec1::try_from(entry_values) else {
+            return Ok(());
+        };
+
+        retry!(
+            self,
+            self.set_metadata_values(mailbox.clone(), entry_values.clone()),
+            SetMetadata,
+            [SetMetadata]
+        )
+    }
+
+    /// Resolve the SETMETADATA command of the given mailbox.
+    async fn set_metadata_values(
+        &mut self,
+        mailbox: Mailbox<'static>,
+        entry_values: Vec1<metadata::EntryValue<'static>>,
+    ) -> std::result::Result<(), ClientError> {
+        self.resolve(SetMetadataTask::new(mailbox, entry_values))
+            .await??;
+        Ok(())
+    }
+
+    /// Permanently remove the given messages from the given mailbox.
+    ///
+    /// Messages need to be flagged as deleted first. UID EXPUNGE is
+    /// used when the UIDPLUS extension is supported. Otherwise, other
+    /// messages flagged as deleted are temporarily unflagged, so that
+    /// a plain EXPUNGE only removes the given messages.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn expunge_messages(
+        &mut self,
+        mbox: impl ToString,
+        uids: SequenceSet,
+    ) -> Result<usize> {
+        self.select_mailbox(mbox).await?;
+
+        if self.ext_uidplus_supported() {
+            let expunged = retry!(
+                self,
+                self.uid_expunge(uids.clone()),
+                ExpungeMailbox,
+                [Expunge]
+            )?;
+
+            return Ok(expunged.len());
+        }
+
+        let others = self
+            .search_uids([
+                SearchKey::Deleted,
+                SearchKey::Not(Box::new(SearchKey::Uid(uids.clone()))),
+            ])
+            .await?;
+
+        let others = SequenceSet::try_from(others).ok();
+
+        if let Some(others) = others.clone() {
+            self.remove_flags_silently(others, Some(Flag::Deleted))
+                .await?;
+        }
+
+        let expunged = retry!(self, self.inner.expunge(), ExpungeMailbox, [Expunge]);
+
+        // flags need to be restored even if the expunge failed
+        if let Some(others) = others {
+            self.add_deleted_flag_silently(others).await?;
+        }
+
+        Ok(expunged?.len())
+    }
+
+    /// Resolve the UID EXPUNGE command of the given messages.
+    async fn uid_expunge(
+        &mut self,
+        uids: SequenceSet,
+    ) -> std::result::Result<Vec<NonZeroU32>, ClientError> {
+        let expunged = self.resolve(UidExpungeTask::new(uids)).await??;
+        Ok(expunged)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn purge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.select_mailbox(mbox).await?;
         self.add_deleted_flag_silently("1:*".try_into().unwrap())
             .await?;
-        let expunged = retry!(self, self.inner.expunge(), ExpungeMailbox)?;
+        let expunged = retry!(self, self.inner.expunge(), ExpungeMailbox, [Expunge])?;
         Ok(expunged.len())
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn delete_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
-        retry!(self, self.inner.delete(mbox.to_string()), DeleteMailbox)
+        retry!(
+            self,
+            self.inner.delete(mbox.to_string()),
+            DeleteMailbox,
+            [Delete]
+        )
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
@@ -247,7 +843,8 @@ pub async fn fetch_envelopes(&mut self, uids: SequenceSet) -> Result<Envelopes>
         let fetches = retry!(
             self,
             self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         Ok(Envelopes::from_imap_data_items(fetches))
@@ -261,7 +858,8 @@ pub async fn fetch_envelopes_map(
         let fetches = retry!(
             self,
             self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         let map = fetches
@@ -281,7 +879,8 @@ pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
             self,
             self.inner
                 .uid_fetch_first(uid.try_into().unwrap(), FETCH_ENVELOPES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         Ok(Envelope::from_imap_data_items(items.as_ref()))
@@ -292,12 +891,37 @@ pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<
         let fetches = retry!(
             self,
             self.inner.fetch(seq.clone(), FETCH_ENVELOPES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         Ok(Envelopes::from_imap_data_items(fetches))
     }
 
+    /// Fetch the modification sequence of the messages of the
+    /// selected mailbox changed since the given modification
+    /// sequence, indexed by UID.
+    ///
+    /// Fetching modification sequences enables the CONDSTORE
+    /// extension for the current session.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn fetch_mod_seqs_changed_since(
+        &mut self,
+        since: u64,
+    ) -> Result<HashMap<NonZeroU32, u64>> {
+        retry!(self, self.changed_since(since), FetchMessages, [Fetch])
+    }
+
+    /// Resolve the UID FETCH command of the modification sequences
+    /// changed since the given one.
+    async fn changed_since(
+        &mut self,
+        since: u64,
+    ) -> std::result::Result<HashMap<NonZeroU32, u64>, ClientError> {
+        let mod_seqs = self.resolve(ChangedSinceTask::new(since)).await??;
+        Ok(mod_seqs)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn fetch_all_envelopes(&mut self) -> Result<Envelopes> {
         self.fetch_envelopes_by_sequence("1:*".try_into().unwrap())
@@ -310,11 +934,14 @@ pub async fn sort_uids(
         sort_criteria: impl IntoIterator<Item = SortCriterion> + Clone,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<NonZeroU32>> {
+        let search_criteria = self.prepare_search_criteria(search_criteria);
+
         retry!(
             self,
             self.inner
                 .uid_sort(sort_criteria.clone(), search_criteria.clone()),
-            SortUids
+            SortUids,
+            [Sort]
         )
     }
 
@@ -323,10 +950,13 @@ pub async fn search_uids(
         &mut self,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<NonZeroU32>> {
+        let search_criteria = self.prepare_search_criteria(search_criteria);
+
         retry!(
             self,
             self.inner.uid_search(search_criteria.clone()),
-            SearchUids
+            SearchUids,
+            [Search]
         )
     }
 
@@ -336,6 +966,8 @@ pub async fn sort_envelopes(
         sort_criteria: impl IntoIterator<Item = SortCriterion> + Clone,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Envelopes> {
+        let search_criteria = self.prepare_search_criteria(search_criteria);
+
         let fetches = retry!(
             self,
             self.inner.uid_sort_or_fallback(
@@ -343,7 +975,8 @@ pub async fn sort_envelopes(
                 search_criteria.clone(),
                 FETCH_ENVELOPES.clone(),
             ),
-            FetchMessages
+            FetchMessages,
+            [Sort, Fetch]
         )?;
 
         Ok(Envelopes::from(fetches))
@@ -354,11 +987,14 @@ pub async fn thread_envelopes(
         &mut self,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<Thread>> {
+        let search_criteria = self.prepare_search_criteria(search_criteria);
+
         retry!(
             self,
             self.inner
                 .uid_thread(ThreadingAlgorithm::References, search_criteria.clone(),),
-            ThreadMessages
+            ThreadMessages,
+            [Thread]
         )
     }
 
@@ -367,16 +1003,26 @@ pub async fn idle(
         &mut self,
         wait_for_shutdown_request: &mut oneshot::Receiver<()>,
     ) -> Result<()> {
+        self.commands.record(ImapCommand::Idle);
         let tag = self.inner.enqueue_idle();
+        let timeout = self.imap_config.idle_timeout();
+
+        // the command stays pending on failure, since the server may
+        // still be idling
+        self.command_pending = true;
 
         select! {
-            output = self.inner.idle(tag.clone()) => {
-                output.map_err(Error::StartIdleError)?;
+            output = runtime::timeout(timeout, self.inner.idle(tag.clone())) => {
+                output
+                    .map_err(|_| Error::IdleTimedOutError)?
+                    .map_err(Error::StartIdleError)?;
+                self.command_pending = false;
                 Ok(())
             },
             _ = wait_for_shutdown_request => {
                 debug!("shutdown requested, sending done command…");
                 self.inner.idle_done(tag.clone()).await.map_err(Error::StopIdleError)?;
+                self.command_pending = false;
                 Err(Error::IdleInterruptedError)
             }
         }
@@ -392,7 +1038,8 @@ pub async fn add_flags(
             self,
             self.inner
                 .uid_store(uids.clone(), StoreType::Add, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -405,7 +1052,8 @@ pub async fn add_deleted_flag(
             self,
             self.inner
                 .uid_store(uids.clone(), StoreType::Add, Some(Flag::Deleted)),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -415,7 +1063,8 @@ pub async fn add_deleted_flag_silently(&mut self, uids: SequenceSet) -> Result<(
             self,
             self.inner
                 .uid_silent_store(uids.clone(), StoreType::Add, Some(Flag::Deleted)),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -429,7 +1078,8 @@ pub async fn add_flags_silently(
             self,
             self.inner
                 .uid_silent_store(uids.clone(), StoreType::Add, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -443,7 +1093,8 @@ pub async fn set_flags(
             self,
             self.inner
                 .uid_store(uids.clone(), StoreType::Replace, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -457,7 +1108,8 @@ pub async fn set_flags_silently(
             self,
             self.inner
                 .uid_silent_store(uids.clone(), StoreType::Replace, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -471,7 +1123,8 @@ pub async fn remove_flags(
             self,
             self.inner
                 .uid_store(uids.clone(), StoreType::Remove, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -485,7 +1138,8 @@ pub async fn remove_flags_silently(
             self,
             self.inner
                 .uid_silent_store(uids.clone(), StoreType::Remove, flags.clone()),
-            StoreFlags
+            StoreFlags,
+            [Store]
         )
     }
 
@@ -496,22 +1150,68 @@ pub async fn add_message(
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
         msg: impl AsRef<[u8]> + Clone,
     ) -> Result<NonZeroU32> {
-        let id = retry!(
-            self,
-            self.inner
-                .appenduid_or_fallback(mbox.to_string(), flags.clone(), msg.clone()),
-            StoreFlags
-        )?;
+        let size = msg.as_ref().len();
+        let support = self.non_sync_literals();
+        let now = Instant::now();
+
+        // the fallback for servers without UIDPLUS relies on
+        // synchronizing literals
+        let uidplus = self.ext_uidplus_supported();
+
+        let id = match Literal::try_from(msg.as_ref().to_vec()) {
+            Ok(literal) if uidplus && support.accept(size) => {
+                let mbox = mbox.to_string();
+                let mailbox = Mailbox::try_from(mbox.clone())
+                    .map_err(|err| Error::ParseMailboxError(err, mbox))?;
+                let flags: Vec<_> = flags.into_iter().collect();
+                let literal = self.literals_stats.prepare(literal, support);
+
+                retry!(
+                    self,
+                    self.append_uid(mailbox.clone(), flags.clone(), literal.clone()),
+                    StoreFlags,
+                    [Append]
+                )?
+            }
+            _ => {
+                self.literals_stats.sync += 1;
+
+                retry!(
+                    self,
+                    self.inner
+                        .appenduid_or_fallback(mbox.to_string(), flags.clone(), msg.clone()),
+                    StoreFlags,
+                    [Append]
+                )?
+            }
+        };
+
+        debug!("appended message of {size} bytes in {:?}", now.elapsed());
 
         id.ok_or(Error::FindAppendedMessageUidError)
     }
 
+    /// Append the given literal to the given mailbox, then return
+    /// the UID of the appended message.
+    async fn append_uid(
+        &mut self,
+        mailbox: Mailbox<'static>,
+        flags: Vec<Flag<'static>>,
+        literal: Literal<'static>,
+    ) -> std::result::Result<Option<NonZeroU32>, ClientError> {
+        let task =
+            AppendUidTask::new(mailbox, LiteralOrLiteral8::Literal(literal)).with_flags(flags);
+        let id = self.resolve(task).await??;
+        Ok(id.map(|(_, uid)| uid))
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         let mut fetches = retry!(
             self,
             self.inner.uid_fetch(uids.clone(), FETCH_MESSAGES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         let fetches: Vec<_> = uids
@@ -522,12 +1222,42 @@ pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         Ok(Messages::from(fetches))
     }
 
+    /// Fetch the size of the messages matching the given UIDs,
+    /// without downloading them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn fetch_messages_sizes(
+        &mut self,
+        uids: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, u32>> {
+        let fetches = retry!(
+            self,
+            self.inner
+                .uid_fetch(uids.clone(), FETCH_MESSAGES_SIZES.clone()),
+            FetchMessages,
+            [Fetch]
+        )?;
+
+        let sizes = fetches
+            .into_iter()
+            .filter_map(|(uid, items)| {
+                let size = items.as_ref().iter().find_map(|item| match item {
+                    MessageDataItem::Rfc822Size(size) => Some(*size),
+                    _ => None,
+                })?;
+                Some((uid, size))
+            })
+            .collect();
+
+        Ok(sizes)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn peek_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         let mut fetches = retry!(
             self,
             self.inner.uid_fetch(uids.clone(), PEEK_MESSAGES.clone()),
-            FetchMessages
+            FetchMessages,
+            [Fetch]
         )?;
 
         let fetches: Vec<_> = uids
@@ -543,7 +1273,8 @@ pub async fn copy_messages(&mut self, uids: SequenceSet, mbox: impl ToString) ->
         retry!(
             self,
             self.inner.uid_copy(uids.clone(), mbox.to_string()),
-            CopyMessages
+            CopyMessages,
+            [Copy]
         )
     }
 
@@ -553,7 +1284,8 @@ pub async fn move_messages(&mut self, uids: SequenceSet, mbox: impl ToString) ->
             self,
             self.inner
                 .uid_move_or_fallback(uids.clone(), mbox.to_string()),
-            MoveMessages
+            MoveMessages,
+            [Move]
         )
     }
 }
@@ -570,8 +1302,11 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
 /// The sync version of the IMAP backend context.
 ///
-/// This is just an IMAP session wrapped into a mutex, so the same
-/// IMAP session can be shared and updated across multiple threads.
+/// The context holds a pool of IMAP clients, each one owning its own
+/// IMAP session. Features called in parallel run concurrently on
+/// separate sessions, up to the pool size (see
+/// [`ImapConfig::clients_pool_size`]). Once all clients are busy,
+/// callers wait for the first one to be released.
 #[derive(Debug, Clone)]
 pub struct ImapContext {
     /// The account configuration.
@@ -581,31 +1316,142 @@ pub struct ImapContext {
     pub imap_config: Arc<ImapConfig>,
 
     clients: Vec<Arc<Mutex<ImapClient>>>,
+
+    /// The number of free clients.
+    free_clients: Arc<Semaphore>,
+
+    /// The metrics sink.
+    metrics_sink: Option<SharedImapMetricsSink>,
 }
 
 impl ImapContext {
-    pub async fn client(&self) -> MutexGuard<'_, ImapClient> {
-        loop {
-            let lock = self
-                .clients
-                .iter()
-                .find_map(|client| client.try_lock().ok());
+    /// Take a free client from the pool, waiting for one to be
+    /// released if all clients are busy.
+    ///
+    /// The client is given back to the pool when the returned guard
+    /// is dropped.
+    pub async fn client(&self) -> ImapClientGuard<'_> {
+        // the semaphore is never closed
+        let permit = self.free_clients.acquire().await.unwrap();
+
+        // a permit is held for every locked client, so at least one
+        // client is free once a permit is acquired
+        let mut client = self
+            .clients
+            .iter()
+            .find_map(|client| client.try_lock().ok())
+            .unwrap();
 
-            if let Some(ctx) = lock {
-                #[cfg(feature = "tracing")]
-                {
-                    let total = self.clients.len();
-                    let id = ctx.id;
-                    tracing::debug!("client {id}/{total} is free, locking it");
-                }
+        #[cfg(feature = "tracing")]
+        {
+            let total = self.clients.len();
+            let id = client.id;
+            tracing::debug!("client {id}/{total} is free, locking it");
+        }
 
-                break ctx;
-            } else {
-                #[cfg(feature = "tracing")]
-                tracing::trace!("no free client, sleeping for 1s");
-                sleep(Duration::from_secs(1)).await;
+        // responses of the interrupted command would be taken for
+        // the responses of the next ones
+        if client.poisoned {
+            debug!("client {} is out of sync, re-connecting…", client.id);
+
+            match client.reconnect().await {
+                Ok(()) => {
+                    client.command_pending = false;
+                    client.poisoned = false;
+                }
+                Err(_err) => {
+                    warn!("cannot re-connect out of sync client {}: {_err}", client.id);
+                }
             }
         }
+
+        ImapClientGuard {
+            client,
+            metrics: None,
+            _permit: permit,
+        }
+    }
+
+    /// Take a free client from the pool for the given operation.
+    ///
+    /// Same as [`ImapContext::client`], except that commands issued
+    /// by the client are reported to the metrics sink, if any, once
+    /// the client is given back to the pool.
+    pub async fn client_for(&self, operation: &'static str) -> ImapClientGuard<'_> {
+        let mut guard = self.client().await;
+
+        if let Some(sink) = self.metrics_sink.as_ref() {
+            let commands = guard.commands.clone();
+            guard.metrics = Some((operation, sink, commands));
+        }
+
+        guard
+    }
+
+    /// Return the number of clients of the pool.
+    pub fn pool_size(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Report the sizes of the FETCH batches chosen for the given
+    /// operation to the metrics sink, if any.
+    pub fn record_fetch_batches(&self, operation: &str, sizes: &[usize]) {
+        debug!("IMAP operation {operation} fetched using batches of {sizes:?}");
+
+        if let Some(sink) = self.metrics_sink.as_ref() {
+            sink.record_fetch_batches(operation, sizes);
+        }
+    }
+}
+
+/// The guard of an IMAP client taken from the pool.
+///
+/// The client is given back to the pool when the guard is dropped.
+/// If a command was still running (for example when the call timed
+/// out or got cancelled), the client is poisoned: its session is
+/// re-established next time it is taken from the pool.
+pub struct ImapClientGuard<'a> {
+    // the client needs to be unlocked before the permit is released,
+    // fields are dropped in declaration order
+    client: MutexGuard<'a, ImapClient>,
+    _permit: SemaphorePermit<'a>,
+
+    /// The operation, the metrics sink and the commands issued by
+    /// the client before the operation.
+    metrics: Option<(&'static str, &'a SharedImapMetricsSink, ImapCommandCounts)>,
+}
+
+impl Drop for ImapClientGuard<'_> {
+    fn drop(&mut self) {
+        // the future using the client has been dropped in the middle
+        // of a command
+        if self.client.command_pending {
+            debug!(
+                "client {} released during a command, poisoning it",
+                self.client.id
+            );
+            self.client.poisoned = true;
+        }
+
+        if let Some((operation, sink, previous)) = self.metrics.take() {
+            let commands = self.client.commands.since(&previous);
+            debug!("IMAP operation {operation} issued {commands}");
+            sink.record(operation, &commands);
+        }
+    }
+}
+
+impl Deref for ImapClientGuard<'_> {
+    type Target = ImapClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for ImapClientGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
     }
 }
 
@@ -624,6 +1470,15 @@ pub struct ImapContextBuilder {
     prebuilt_credentials: Option<String>,
 
     pool_size: u8,
+
+    /// The custom stream connector.
+    stream_connector: Option<SharedStreamConnector>,
+
+    /// The metrics sink.
+    metrics_sink: Option<SharedImapMetricsSink>,
+
+    /// The alert sink.
+    alert_sink: Option<SharedImapAlertSink>,
 }
 
 impl ImapContextBuilder {
@@ -635,6 +1490,9 @@ pub fn new(account_config: Arc<AccountConfig>, imap_config: Arc<ImapConfig>) ->
             imap_config,
             prebuilt_credentials: None,
             pool_size,
+            stream_connector: None,
+            metrics_sink: None,
+            alert_sink: None,
         }
     }
 
@@ -652,6 +1510,32 @@ pub fn with_pool_size(mut self, pool_size: u8) -> Self {
         self.pool_size = pool_size;
         self
     }
+
+    /// Connect to the IMAP server using the given custom stream
+    /// connector instead of TCP streams.
+    ///
+    /// The network configuration of the account is ignored.
+    pub fn with_stream_connector(mut self, connector: impl Into<SharedStreamConnector>) -> Self {
+        self.stream_connector = Some(connector.into());
+        self
+    }
+
+    /// Report the commands issued by each operation to the given
+    /// metrics sink.
+    pub fn with_metrics_sink(mut self, sink: impl Into<SharedImapMetricsSink>) -> Self {
+        self.metrics_sink = Some(sink.into());
+        self
+    }
+
+    /// Report the alerts and warnings sent by the server to the
+    /// given alert sink.
+    ///
+    /// An unbounded channel sender can be used as a sink, in which
+    /// case alerts can be consumed as a stream of events.
+    pub fn with_alert_sink(mut self, sink: impl Into<SharedImapAlertSink>) -> Self {
+        self.alert_sink = Some(sink.into());
+        self
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -665,6 +1549,27 @@ fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
 impl BackendContextBuilder for ImapContextBuilder {
     type Context = ImapContext;
 
+    async fn probe_connection(&self, report: &mut CheckUpReport) -> bool {
+        let config = &self.imap_config;
+
+        // STARTTLS is negotiated by the IMAP client itself, so it is
+        // covered by the authentication probe
+        let tls = match &config.encryption {
+            Some(ImapEncryptionKind::Tls) => Some(config.tls_options()),
+            _ => None,
+        };
+
+        report
+            .probe_connection(
+                self.account_config.network.as_ref(),
+                self.stream_connector.as_ref(),
+                &config.host,
+                config.port,
+                tls,
+            )
+            .await
+    }
+
     fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
         Some(Arc::new(CheckUpImap::some_new_boxed))
     }
@@ -689,6 +1594,30 @@ fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder
         Some(Arc::new(DeleteImapFolder::some_new_boxed))
     }
 
+    fn get_quota(&self) -> Option<BackendFeature<Self::Context, dyn GetQuota>> {
+        Some(Arc::new(GetImapQuota::some_new_boxed))
+    }
+
+    fn search_folders(&self) -> Option<BackendFeature<Self::Context, dyn SearchFolders>> {
+        Some(Arc::new(SearchImapFolders::some_new_boxed))
+    }
+
+    fn get_folder(&self) -> Option<BackendFeature<Self::Context, dyn GetFolder>> {
+        Some(Arc::new(GetImapFolder::some_new_boxed))
+    }
+
+    fn get_folder_stats(&self) -> Option<BackendFeature<Self::Context, dyn GetFolderStats>> {
+        Some(Arc::new(GetImapFolderStats::some_new_boxed))
+    }
+
+    fn get_folder_metadata(&self) -> Option<BackendFeature<Self::Context, dyn GetFolderMetadata>> {
+        Some(Arc::new(GetImapFolderMetadata::some_new_boxed))
+    }
+
+    fn set_folder_metadata(&self) -> Option<BackendFeature<Self::Context, dyn SetFolderMetadata>> {
+        Some(Arc::new(SetImapFolderMetadata::some_new_boxed))
+    }
+
     fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
         Some(Arc::new(GetImapEnvelope::some_new_boxed))
     }
@@ -702,6 +1631,11 @@ fn thread_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ThreadEnv
         Some(Arc::new(ThreadImapEnvelopes::some_new_boxed))
     }
 
+    #[cfg(feature = "stream")]
+    fn stream_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn StreamEnvelopes>> {
+        Some(Arc::new(StreamImapEnvelopes::some_new_boxed))
+    }
+
     #[cfg(feature = "watch")]
     fn watch_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn WatchEnvelopes>> {
         Some(Arc::new(WatchImapEnvelopes::some_new_boxed))
@@ -719,6 +1653,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveImapFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListImapFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddImapMessage::some_new_boxed))
     }
@@ -749,29 +1687,43 @@ fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMess
 
     async fn build(self) -> AnyResult<Self::Context> {
         let client_builder =
-            ImapClientBuilder::new(self.imap_config.clone(), self.prebuilt_credentials);
+            ImapClientBuilder::new(self.imap_config.clone(), self.prebuilt_credentials)
+                .with_network(self.account_config.network.clone())
+                .with_stream_connector(self.stream_connector.clone());
+
+        // an empty pool would make callers wait forever
+        let pool_size = self.pool_size.max(1);
 
         #[cfg(feature = "tracing")]
-        tracing::debug!("building {} IMAP clients", self.pool_size);
+        tracing::debug!("building {pool_size} IMAP clients");
 
-        let clients = FuturesUnordered::from_iter((0..self.pool_size).map(move |i| {
+        let clients = FuturesUnordered::from_iter((0..pool_size).map(move |i| {
             let mut client_builder = client_builder.clone();
-            tokio::spawn(async move {
-                let client = client_builder.build().await?;
-                Ok((i + 1, client_builder, client))
+            runtime::spawn(async move {
+                let mut client = client_builder.build().await?;
+                let namespace = discover_namespace(&mut client, &client_builder.config).await?;
+                Ok((i + 1, client_builder, client, namespace))
             })
         }))
         .map(|res| match res {
             Err(err) => Err(Error::JoinClientError(err)),
             Ok(Err(err)) => Err(Error::BuildClientError(Box::new(err))),
-            Ok(Ok((id, client_builder, inner))) => Ok(Arc::new(Mutex::new(ImapClient {
-                id,
-                account_config: self.account_config.clone(),
-                imap_config: self.imap_config.clone(),
-                client_builder,
-                inner,
-                mailbox: None,
-            }))),
+            Ok(Ok((id, client_builder, inner, namespace))) => {
+                Ok(Arc::new(Mutex::new(ImapClient {
+                    id,
+                    account_config: self.account_config.clone(),
+                    imap_config: self.imap_config.clone(),
+                    client_builder,
+                    inner,
+                    mailbox: None,
+                    literals_stats: Default::default(),
+                    commands: Default::default(),
+                    namespace,
+                    alert_sink: self.alert_sink.clone(),
+                    command_pending: false,
+                    poisoned: false,
+                })))
+            }
         })
         .collect::<Vec<_>>()
         .await
@@ -782,10 +1734,49 @@ async fn build(self) -> AnyResult<Self::Context> {
             account_config: self.account_config,
             imap_config: self.imap_config,
             clients,
+            free_clients: Arc::new(Semaphore::new(pool_size as usize)),
+            metrics_sink: self.metrics_sink,
         })
     }
 }
 
+/// Return `true` if the server of the given client supports the
+/// UTF8=ACCEPT extension.
+///
+/// https://www.rfc-editor.org/rfc/rfc6855.html
+fn ext_utf8_accept_supported(client: &Client) -> bool {
+    utf8_accept_supported(client.capabilities_iter())
+}
+
+/// Discover the personal namespace of the given client, if enabled
+/// and advertised by the server.
+async fn discover_namespace(
+    client: &mut Client,
+    config: &ImapConfig,
+) -> Result<Option<ImapNamespace>> {
+    if !config.namespace_enabled() {
+        return Ok(None);
+    }
+
+    let supported = client
+        .capabilities_iter()
+        .any(|capability| capability.to_string().eq_ignore_ascii_case("NAMESPACE"));
+
+    if !supported {
+        return Ok(None);
+    }
+
+    let namespace = client
+        .resolve(NamespaceTask::new())
+        .await
+        .map_err(Error::DiscoverNamespaceError)?
+        .map_err(|err| Error::DiscoverNamespaceError(err.into()))?;
+
+    debug!(?namespace, "discovered personal namespace");
+
+    Ok(namespace)
+}
+
 #[derive(Clone, Debug)]
 pub struct CheckUpImap {
     ctx: ImapContext,
@@ -808,9 +1799,40 @@ pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn CheckUp>> {
 #[async_trait]
 impl CheckUp for CheckUpImap {
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
-    async fn check_up(&self) -> AnyResult<()> {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
         debug!("executing check up backend feature");
-        Ok(self.ctx.client().await.noop().await?)
+
+        let mut report = CheckUpReport::new();
+        let mut client = self.ctx.client_for("check_up").await;
+
+        let capabilities = async {
+            let supported = client.inner.capabilities_iter().any(|capability| {
+                let capability = capability.to_string();
+                capability.eq_ignore_ascii_case("IMAP4REV1")
+                    || capability.eq_ignore_ascii_case("IMAP4REV2")
+            });
+
+            if supported {
+                Ok(())
+            } else {
+                Err("IMAP4rev1 capability not advertised by the server")
+            }
+        };
+
+        report
+            .probe(CheckUpProbeKind::Capabilities, capabilities)
+            .await;
+
+        let inbox = client.get_folder_alias(INBOX);
+        let inbox = client.encode_folder(inbox);
+        report
+            .probe(
+                CheckUpProbeKind::FolderAccess,
+                client.examine_mailbox(inbox),
+            )
+            .await;
+
+        Ok(report)
     }
 }
 
@@ -818,6 +1840,8 @@ async fn check_up(&self) -> AnyResult<()> {
 pub struct ImapClientBuilder {
     pub config: Arc<ImapConfig>,
     pub credentials: Option<String>,
+    pub network: Option<NetworkConfig>,
+    pub stream_connector: Option<SharedStreamConnector>,
 }
 
 impl ImapClientBuilder {
@@ -825,9 +1849,26 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
         Self {
             config,
             credentials,
+            network: None,
+            stream_connector: None,
         }
     }
 
+    /// Connect to the IMAP server using the given network
+    /// configuration.
+    pub fn with_network(mut self, network: Option<NetworkConfig>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Connect to the IMAP server using the given custom stream
+    /// connector, which takes precedence over the network
+    /// configuration.
+    pub fn with_stream_connector(mut self, connector: Option<SharedStreamConnector>) -> Self {
+        self.stream_connector = connector;
+        self
+    }
+
     /// Creates a new session from an IMAP configuration and optional
     /// pre-built credentials.
     ///
@@ -840,36 +1881,60 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
         tracing::instrument(name = "client::build", skip(self))
     )]
     pub async fn build(&mut self) -> Result<Client> {
-        let mut client = match &self.config.encryption {
-            Some(ImapEncryptionKind::None) | None => {
-                Client::insecure(&self.config.host, self.config.port)
-                    .await
-                    .map_err(|err| {
-                        let host = self.config.host.clone();
-                        let port = self.config.port.clone();
-                        Error::BuildInsecureClientError(err, host, port)
-                    })?
-            }
-            Some(ImapEncryptionKind::StartTls) => {
-                Client::starttls(&self.config.host, self.config.port)
-                    .await
-                    .map_err(|err| {
-                        let host = self.config.host.clone();
-                        let port = self.config.port.clone();
-                        Error::BuildStartTlsClientError(err, host, port)
-                    })?
+        let custom = self.stream_connector.is_some()
+            || self.network.is_some()
+            || !self.config.tls_options().is_empty();
+
+        let connect = async {
+            if custom {
+                self.build_network_client().await
+            } else {
+                self.build_client().await
             }
-            Some(ImapEncryptionKind::Tls) => Client::tls(&self.config.host, self.config.port)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildTlsClientError(err, host, port)
-                })?,
         };
 
+        let mut client = runtime::timeout(self.config.connect_timeout(), connect)
+            .await
+            .map_err(|_| {
+                let host = self.config.host.clone();
+                let port = self.config.port;
+                Error::ConnectTimedOutError(host, port)
+            })??;
+
         client.set_some_idle_timeout(self.config.find_watch_timeout().map(Duration::from_secs));
 
+        runtime::timeout(self.config.login_timeout(), self.authenticate(&mut client))
+            .await
+            .map_err(|_| Error::LoginTimedOutError(self.config.login.clone()))??;
+
+        if self.config.send_id_after_auth() {
+            let params = id_params(&self.config);
+            debug!(?params, "client identity");
+
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let params = client
+                .id(Some(params))
+                .await
+                .map_err(Error::ExchangeIdsError)?;
+
+            debug!(?params, "server identity");
+        }
+
+        if self.config.utf8_accept_enabled() && ext_utf8_accept_supported(&client) {
+            debug!("enabling UTF8=ACCEPT capability…");
+
+            client
+                .enable(Some(CapabilityEnable::Utf8(Utf8Kind::Accept)))
+                .await
+                .map_err(Error::EnableCapabilityError)?;
+        }
+
+        Ok(client)
+    }
+
+    /// Authenticates the given client using the authentication
+    /// configuration.
+    async fn authenticate(&mut self, client: &mut Client) -> Result<()> {
         match &self.config.auth {
             ImapAuthConfig::Passwd(passwd) => {
                 #[cfg(feature = "tracing")]
@@ -949,8 +2014,13 @@ pub async fn build(&mut self) -> Result<Client> {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("using OAuth 2.0 authentication");
 
-                match oauth2.method {
-                    OAuth2Method::XOAuth2 => {
+                let method = oauth2.method.resolve(
+                    client.supports_auth_mechanism(AuthMechanism::XOAuth2),
+                    client.supports_auth_mechanism("OAUTHBEARER".try_into().unwrap()),
+                );
+
+                match method {
+                    OAuth2Method::Auto | OAuth2Method::XOAuth2 => {
                         if !client.supports_auth_mechanism(AuthMechanism::XOAuth2) {
                             let auth = client.supported_auth_mechanisms().cloned().collect();
                             return Err(Error::AuthenticateXOAuth2NotSupportedError(auth));
@@ -974,7 +2044,7 @@ pub async fn build(&mut self) -> Result<Client> {
                             warn!("authentication failed, refreshing access token and retrying…");
 
                             let access_token = oauth2
-                                .refresh_access_token()
+                                .refresh_access_token_with_network(self.network.as_ref())
                                 .await
                                 .map_err(Error::RefreshAccessTokenError)?;
 
@@ -1018,7 +2088,7 @@ pub async fn build(&mut self) -> Result<Client> {
                             warn!("authentication failed, refreshing access token and retrying");
 
                             let access_token = oauth2
-                                .refresh_access_token()
+                                .refresh_access_token_with_network(self.network.as_ref())
                                 .await
                                 .map_err(Error::RefreshAccessTokenError)?;
 
@@ -1037,34 +2107,81 @@ pub async fn build(&mut self) -> Result<Client> {
                     }
                 }
             }
-        };
+        }
 
-        if self.config.send_id_after_auth() {
-            #[cfg(feature = "tracing")]
-            {
-                let params = ID_PARAMS.clone();
-                tracing::debug!(?params, "client identity");
-            }
+        Ok(())
+    }
 
-            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-            let params = client
-                .id(Some(ID_PARAMS.clone()))
+    /// Creates a new client connected directly to the IMAP server.
+    async fn build_client(&self) -> Result<Client> {
+        let client = match &self.config.encryption {
+            Some(ImapEncryptionKind::None) | None => {
+                Client::insecure(&self.config.host, self.config.port)
+                    .await
+                    .map_err(|err| {
+                        let host = self.config.host.clone();
+                        let port = self.config.port.clone();
+                        Error::BuildInsecureClientError(err, host, port)
+                    })?
+            }
+            Some(ImapEncryptionKind::StartTls) => {
+                Client::starttls(&self.config.host, self.config.port)
+                    .await
+                    .map_err(|err| {
+                        let host = self.config.host.clone();
+                        let port = self.config.port.clone();
+                        Error::BuildStartTlsClientError(err, host, port)
+                    })?
+            }
+            Some(ImapEncryptionKind::Tls) => Client::tls(&self.config.host, self.config.port)
                 .await
-                .map_err(Error::ExchangeIdsError)?;
+                .map_err(|err| {
+                    let host = self.config.host.clone();
+                    let port = self.config.port.clone();
+                    Error::BuildTlsClientError(err, host, port)
+                })?,
+        };
 
-            debug!(?params, "server identity");
-        }
+        Ok(client)
+    }
 
-        // TODO: make it customizable
-        //
-        // #[cfg(feature = "tracing")]
-        // tracing::debug!("enabling UTF8 capability…");
-        //
-        // client
-        //     .enable(Some(CapabilityEnable::Utf8(Utf8Kind::Accept)))
-        //     .await
-        //     .map_err(Error::EnableCapabilityError)?;
+    /// Creates a new client connected to the IMAP server using the
+    /// custom stream connector, the network configuration and the
+    /// TLS options.
+    ///
+    /// STARTTLS is not supported by this kind of client.
+    async fn build_network_client(&self) -> Result<Client> {
+        let host = self.config.host.as_str();
+        let port = self.config.port;
+
+        let tcp = match &self.stream_connector {
+            Some(connector) => connector.connect(host, port).await,
+            None => network::connect(self.network.as_ref(), host, port).await,
+        };
 
-        Ok(client)
+        let tcp = tcp.map_err(|err| Error::ConnectNetworkError(err, host.to_owned(), port))?;
+
+        let stream = match &self.config.encryption {
+            Some(ImapEncryptionKind::None) | None => Stream::insecure(tcp),
+            Some(ImapEncryptionKind::StartTls) => {
+                return Err(Error::NetworkStartTlsNotSupportedError);
+            }
+            Some(ImapEncryptionKind::Tls) => {
+                let opts = self.config.tls_options();
+                let tls = network::tls::connect(tcp, host, port, opts)
+                    .await
+                    .map_err(|err| match err {
+                        network::Error::UnpinnedCertificateError(host, port) => {
+                            Error::UnpinnedCertificateError(host, port)
+                        }
+                        err => Error::ConnectNetworkTlsError(err, host.to_owned(), port),
+                    })?;
+                Stream::tls(tls)
+            }
+        };
+
+        Client::new(stream)
+            .await
+            .map_err(|err| Error::BuildNetworkClientError(err, host.to_owned(), port))
     }
 }
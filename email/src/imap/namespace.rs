@@ -0,0 +1,144 @@
+//! # IMAP namespace
+//!
+//! Module dedicated to the IMAP NAMESPACE extension. Some servers
+//! (like Courier) store personal mailboxes under a prefix, for
+//! example `INBOX.Sent`. The personal namespace is discovered on
+//! connect, so that folder names can be used without their prefix.
+//!
+//! https://www.rfc-editor.org/rfc/rfc2342.html
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::CommandBody,
+    extensions::namespace::Namespace,
+    response::{Data, StatusBody, StatusKind},
+};
+
+use crate::folder::INBOX;
+
+/// The IMAP personal namespace.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImapNamespace {
+    /// The prefix of personal mailboxes, for example `INBOX.`.
+    pub prefix: String,
+
+    /// The hierarchy delimiter, for example `.`.
+    pub delimiter: Option<char>,
+}
+
+impl ImapNamespace {
+    /// Return the prefix of personal mailboxes, ending with the
+    /// hierarchy delimiter.
+    fn full_prefix(&self) -> String {
+        let mut prefix = self.prefix.clone();
+
+        if let Some(delim) = self.delimiter {
+            if !prefix.is_empty() && !prefix.ends_with(delim) {
+                prefix.push(delim);
+            }
+        }
+
+        prefix
+    }
+
+    /// Prefix the given folder name with the namespace prefix.
+    ///
+    /// The inbox and already prefixed folders are left untouched.
+    pub fn apply(&self, folder: &str) -> String {
+        let prefix = self.full_prefix();
+
+        if prefix.is_empty() || folder.eq_ignore_ascii_case(INBOX) || folder.starts_with(&prefix) {
+            return folder.to_owned();
+        }
+
+        format!("{prefix}{folder}")
+    }
+
+    /// Remove the namespace prefix from the given folder name.
+    ///
+    /// This is the reverse of [`ImapNamespace::apply`].
+    pub fn strip(&self, folder: &str) -> String {
+        let prefix = self.full_prefix();
+
+        match folder.strip_prefix(&prefix) {
+            Some(folder) if !prefix.is_empty() && !folder.is_empty() => folder.to_owned(),
+            _ => folder.to_owned(),
+        }
+    }
+}
+
+impl From<&Namespace<'_>> for ImapNamespace {
+    fn from(namespace: &Namespace<'_>) -> Self {
+        Self {
+            prefix: String::from_utf8_lossy(namespace.prefix.as_ref()).to_string(),
+            delimiter: namespace.delimiter.as_ref().map(|delim| delim.inner()),
+        }
+    }
+}
+
+/// The task resolving the first personal namespace of the server.
+#[derive(Clone, Debug, Default)]
+pub struct NamespaceTask {
+    personal: Option<ImapNamespace>,
+}
+
+impl NamespaceTask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Task for NamespaceTask {
+    type Output = Result<Option<ImapNamespace>, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::Namespace
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::Namespace { personal, .. } => {
+                self.personal = personal.first().map(ImapNamespace::from);
+                None
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.personal),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImapNamespace;
+
+    #[test]
+    fn apply_and_strip() {
+        let ns = ImapNamespace::default();
+        assert_eq!(ns.apply("Sent"), "Sent");
+        assert_eq!(ns.strip("Sent"), "Sent");
+
+        let ns = ImapNamespace {
+            prefix: "INBOX.".into(),
+            delimiter: Some('.'),
+        };
+        assert_eq!(ns.apply("Sent"), "INBOX.Sent");
+        assert_eq!(ns.apply("INBOX.Sent"), "INBOX.Sent");
+        assert_eq!(ns.apply("inbox"), "inbox");
+        assert_eq!(ns.strip("INBOX.Sent"), "Sent");
+        assert_eq!(ns.strip("INBOX"), "INBOX");
+
+        let ns = ImapNamespace {
+            prefix: "INBOX".into(),
+            delimiter: Some('.'),
+        };
+        assert_eq!(ns.apply("Sent"), "INBOX.Sent");
+        assert_eq!(ns.strip("INBOX.Sent"), "Sent");
+    }
+}
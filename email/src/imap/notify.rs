@@ -0,0 +1,533 @@
+//! # IMAP notifications
+//!
+//! Module dedicated to the IMAP NOTIFY extension, which allows a
+//! single connection to be notified about changes of several
+//! mailboxes at once, instead of one IDLE connection per mailbox.
+//!
+//! The NOTIFY command cannot be expressed by the underlying IMAP
+//! client, so notifications are received on a dedicated connection
+//! which is not part of the clients pool. The few commands it needs
+//! (LOGIN, EXAMINE, NOTIFY, NOOP and LOGOUT) are exchanged directly,
+//! which is why only password authentication over plain TCP or
+//! SSL/TLS is supported.
+//!
+//! https://www.rfc-editor.org/rfc/rfc5465
+
+use std::collections::VecDeque;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::{
+    config::{ImapAuthConfig, ImapEncryptionKind},
+    Error, ImapClientBuilder, Result,
+};
+use crate::{
+    debug,
+    network::{self, stream::BoxedStream},
+    runtime,
+};
+
+/// The events notified for every watched mailbox.
+///
+/// FlagChange requires both MessageNew and MessageExpunge.
+const EVENTS: &str = "(MessageNew MessageExpunge FlagChange)";
+
+/// A change notified by the IMAP server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImapNotification {
+    /// The selected mailbox changed.
+    Selected,
+
+    /// The given mailbox, which is not the selected one, changed.
+    ///
+    /// The mailbox name is the one sent by the server, which means
+    /// it is encoded.
+    Mailbox(String),
+
+    /// The server could not keep up with notifications, so any
+    /// mailbox may have changed (`[NOTIFICATIONOVERFLOW]` response
+    /// code).
+    Overflow,
+}
+
+/// The IMAP client dedicated to notifications.
+pub struct ImapNotifyClient {
+    stream: BoxedStream,
+
+    /// The bytes received but not processed yet.
+    buf: Vec<u8>,
+
+    /// The notifications received while waiting for a tagged
+    /// response.
+    notifications: VecDeque<ImapNotification>,
+
+    /// The last generated tag.
+    tag: usize,
+}
+
+impl ImapNotifyClient {
+    /// Connect to the IMAP server then authenticate, using the
+    /// configuration of the given client builder.
+    pub async fn connect(builder: &ImapClientBuilder) -> Result<Self> {
+        let config = &builder.config;
+        let host = config.host.as_str();
+        let port = config.port;
+
+        let passwd = match &config.auth {
+            ImapAuthConfig::Passwd(passwd) => passwd,
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(_) => {
+                return Err(Error::NotifyNotSupportedError(
+                    "only password authentication is supported",
+                ));
+            }
+        };
+
+        let passwd = match builder.credentials.as_ref() {
+            Some(passwd) => passwd.to_string(),
+            None => passwd
+                .get()
+                .await
+                .map_err(Error::GetPasswdImapError)?
+                .lines()
+                .next()
+                .ok_or(Error::GetPasswdEmptyImapError)?
+                .to_owned(),
+        };
+
+        let connect = async {
+            let tcp = match &builder.stream_connector {
+                Some(connector) => connector.connect(host, port).await,
+                None => network::connect(builder.network.as_ref(), host, port).await,
+            };
+
+            let tcp = tcp.map_err(|err| Error::ConnectNetworkError(err, host.to_owned(), port))?;
+
+            let stream: BoxedStream = match &config.encryption {
+                Some(ImapEncryptionKind::None) | None => Box::new(tcp),
+                Some(ImapEncryptionKind::StartTls) => {
+                    return Err(Error::NotifyNotSupportedError("STARTTLS is not supported"));
+                }
+                Some(ImapEncryptionKind::Tls) => {
+                    let opts = config.tls_options();
+                    let tls = network::tls::connect(tcp, host, port, opts).await.map_err(
+                        |err| match err {
+                            network::Error::UnpinnedCertificateError(host, port) => {
+                                Error::UnpinnedCertificateError(host, port)
+                            }
+                            err => Error::ConnectNetworkTlsError(err, host.to_owned(), port),
+                        },
+                    )?;
+                    Box::new(tls)
+                }
+            };
+
+            Ok(stream)
+        };
+
+        let stream = runtime::timeout(config.connect_timeout(), connect)
+            .await
+            .map_err(|_| Error::ConnectTimedOutError(host.to_owned(), port))??;
+
+        let mut client = Self::new(stream);
+
+        let login = client.login(&config.login, &passwd);
+        runtime::timeout(config.login_timeout(), login)
+            .await
+            .map_err(|_| Error::LoginTimedOutError(config.login.clone()))??;
+
+        Ok(client)
+    }
+
+    fn new(stream: BoxedStream) -> Self {
+        Self {
+            stream,
+            buf: Vec::new(),
+            notifications: VecDeque::new(),
+            tag: 0,
+        }
+    }
+
+    /// Wait for the server greeting, then log in unless the
+    /// connection is already authenticated.
+    async fn login(&mut self, login: &str, passwd: &str) -> Result<()> {
+        let greeting = self.read_line().await?;
+
+        if starts_with_ignore_case(&greeting, "* PREAUTH") {
+            return Ok(());
+        }
+
+        if !starts_with_ignore_case(&greeting, "* OK") {
+            return Err(Error::NotifyCommandError("LOGIN", greeting));
+        }
+
+        if [login, passwd].iter().any(|s| s.contains(['\r', '\n'])) {
+            let reason = "login and password cannot contain line breaks";
+            return Err(Error::NotifyNotSupportedError(reason));
+        }
+
+        let args = format!("{} {}", quote(login), quote(passwd));
+        self.command("LOGIN", &args).await
+    }
+
+    /// Ask the server to notify changes of the given encoded
+    /// mailboxes.
+    ///
+    /// The first mailbox is examined, so that its changes are
+    /// notified using regular untagged responses. Changes of the
+    /// other ones are notified using STATUS responses.
+    pub async fn notify(&mut self, mailboxes: &[String]) -> Result<()> {
+        let Some((selected, others)) = mailboxes.split_first() else {
+            return self.command("NOTIFY", "NONE").await;
+        };
+
+        self.command("EXAMINE", &quote(selected)).await?;
+        self.notifications.clear();
+
+        let mut args = format!("SET (selected {EVENTS})");
+
+        if !others.is_empty() {
+            let others: Vec<_> = others.iter().map(|mbox| quote(mbox)).collect();
+            args.push_str(&format!(" (mailboxes ({}) {EVENTS})", others.join(" ")));
+        }
+
+        self.command("NOTIFY", &args).await
+    }
+
+    /// Wait for the next change notified by the server.
+    ///
+    /// This function is cancel safe: bytes already received are kept
+    /// until the next call.
+    pub async fn next(&mut self) -> Result<ImapNotification> {
+        loop {
+            if let Some(notification) = self.notifications.pop_front() {
+                return Ok(notification);
+            }
+
+            let line = self.read_line().await?;
+
+            if starts_with_ignore_case(&line, "* BYE") {
+                return Err(Error::NotifyClosedError);
+            }
+
+            // tagged responses of keep-alive NOOP commands do not
+            // carry any change
+            if let Some(notification) = parse_notification(&line) {
+                self.notifications.push_back(notification);
+            }
+        }
+    }
+
+    /// Send a NOOP command, which prevents the server from closing
+    /// the connection for inactivity.
+    ///
+    /// The tagged response is consumed by [`ImapNotifyClient::next`].
+    pub async fn noop(&mut self) -> Result<()> {
+        let tag = self.next_tag();
+        self.write(&format!("{tag} NOOP\r\n")).await
+    }
+
+    /// Close the connection.
+    pub async fn logout(&mut self) -> Result<()> {
+        let tag = self.next_tag();
+        self.write(&format!("{tag} LOGOUT\r\n")).await?;
+        self.stream.shutdown().await.map_err(Error::NotifyIoError)
+    }
+
+    /// Send the given command, then wait for its tagged response.
+    ///
+    /// Notifications received in the meantime are kept for
+    /// [`ImapNotifyClient::next`].
+    async fn command(&mut self, name: &'static str, args: &str) -> Result<()> {
+        let tag = self.next_tag();
+
+        if name != "LOGIN" {
+            debug!("sending {name} {args} to the NOTIFY connection");
+        }
+
+        self.write(&format!("{tag} {name} {args}\r\n")).await?;
+
+        loop {
+            let line = self.read_line().await?;
+
+            if let Some(status) = line.strip_prefix(&format!("{tag} ")) {
+                if starts_with_ignore_case(status, "OK") {
+                    return Ok(());
+                }
+
+                return Err(Error::NotifyCommandError(name, status.to_owned()));
+            }
+
+            if starts_with_ignore_case(&line, "* BYE") {
+                return Err(Error::NotifyClosedError);
+            }
+
+            if let Some(notification) = parse_notification(&line) {
+                self.notifications.push_back(notification);
+            }
+        }
+    }
+
+    fn next_tag(&mut self) -> String {
+        self.tag += 1;
+        format!("N{}", self.tag)
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        self.stream
+            .write_all(data.as_bytes())
+            .await
+            .map_err(Error::NotifyIoError)?;
+        self.stream.flush().await.map_err(Error::NotifyIoError)
+    }
+
+    /// Read the next response line, literals included.
+    async fn read_line(&mut self) -> Result<String> {
+        loop {
+            if let Some(line) = take_line(&mut self.buf) {
+                return Ok(line);
+            }
+
+            let n = self
+                .stream
+                .read_buf(&mut self.buf)
+                .await
+                .map_err(Error::NotifyIoError)?;
+
+            if n == 0 {
+                return Err(Error::NotifyClosedError);
+            }
+        }
+    }
+}
+
+/// Take the first complete response line out of the given buffer.
+///
+/// Literals are turned into quoted strings, so that the line can be
+/// parsed without caring about them.
+fn take_line(buf: &mut Vec<u8>) -> Option<String> {
+    let mut line = String::new();
+    let mut pos = 0;
+
+    loop {
+        let end = pos + buf[pos..].iter().position(|b| *b == b'\n')?;
+        let chunk = String::from_utf8_lossy(&buf[pos..end]);
+        let chunk = chunk.trim_end_matches('\r');
+
+        let Some((chunk, size)) = literal_size(chunk) else {
+            line.push_str(chunk);
+            buf.drain(..=end);
+            return Some(line);
+        };
+
+        let literal = buf.get(end + 1..end + 1 + size)?;
+        line.push_str(chunk);
+        line.push_str(&quote(&String::from_utf8_lossy(literal)));
+        pos = end + 1 + size;
+    }
+}
+
+/// Split the given line chunk ending with a literal announcement
+/// (`{size}` or `{size+}`) into the chunk without it and the size
+/// of the literal.
+fn literal_size(chunk: &str) -> Option<(&str, usize)> {
+    let chunk = chunk.strip_suffix('}')?;
+    let (chunk, size) = chunk.rsplit_once('{')?;
+    let size = size.strip_suffix('+').unwrap_or(size).parse().ok()?;
+    Some((chunk, size))
+}
+
+/// Parse the change notified by the given untagged response, if
+/// any.
+fn parse_notification(line: &str) -> Option<ImapNotification> {
+    let line = line.strip_prefix("* ")?;
+    let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+    if name.eq_ignore_ascii_case("STATUS") {
+        return parse_astring(rest).map(ImapNotification::Mailbox);
+    }
+
+    if name.eq_ignore_ascii_case("VANISHED") {
+        return Some(ImapNotification::Selected);
+    }
+
+    if name.eq_ignore_ascii_case("OK") {
+        return starts_with_ignore_case(rest, "[NOTIFICATIONOVERFLOW]")
+            .then_some(ImapNotification::Overflow);
+    }
+
+    if name.bytes().all(|b| b.is_ascii_digit()) {
+        let kind = rest.split(' ').next().unwrap_or_default();
+
+        return ["EXISTS", "EXPUNGE", "FETCH"]
+            .iter()
+            .any(|k| kind.eq_ignore_ascii_case(k))
+            .then_some(ImapNotification::Selected);
+    }
+
+    None
+}
+
+/// Parse the quoted string or the atom the given string starts
+/// with.
+fn parse_astring(s: &str) -> Option<String> {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let atom = s.split([' ', '(']).next()?;
+        return (!atom.is_empty()).then(|| atom.to_owned());
+    };
+
+    let mut string = String::new();
+    let mut chars = quoted.chars();
+
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(chars.next()?),
+            c => string.push(c),
+        }
+    }
+}
+
+/// Quote the given string.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len()
+        && s.is_char_boundary(prefix.len())
+        && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, split, AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use super::{parse_notification, take_line, ImapNotification, ImapNotifyClient};
+
+    #[test]
+    fn take_lines() {
+        let mut buf = b"* 3 EXISTS\r\n* STATUS {5}\r\nA \"B\" (MESSAGES 1)\r\n* 4".to_vec();
+
+        assert_eq!(take_line(&mut buf).unwrap(), "* 3 EXISTS");
+        assert_eq!(
+            take_line(&mut buf).unwrap(),
+            "* STATUS \"A \\\"B\" (MESSAGES 1)"
+        );
+        assert_eq!(take_line(&mut buf), None);
+        assert_eq!(buf, b"* 4");
+
+        // incomplete literals are kept in the buffer
+        let mut buf = b"* STATUS {5}\r\nA".to_vec();
+        assert_eq!(take_line(&mut buf), None);
+        assert_eq!(buf, b"* STATUS {5}\r\nA");
+    }
+
+    #[test]
+    fn parse_notifications() {
+        let mailbox = |mbox: &str| Some(ImapNotification::Mailbox(mbox.into()));
+
+        assert_eq!(
+            parse_notification("* 3 EXISTS"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            parse_notification("* 1 expunge"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            parse_notification("* 2 FETCH (UID 7 FLAGS (\\Seen))"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            parse_notification("* VANISHED 4"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            parse_notification("* STATUS Archives (MESSAGES 2)"),
+            mailbox("Archives")
+        );
+        assert_eq!(
+            parse_notification("* STATUS \"Sent \\\"old\\\"\" (MESSAGES 2)"),
+            mailbox("Sent \"old\"")
+        );
+        assert_eq!(
+            parse_notification("* OK [NOTIFICATIONOVERFLOW] overflow"),
+            Some(ImapNotification::Overflow)
+        );
+        assert_eq!(parse_notification("* OK still here"), None);
+        assert_eq!(parse_notification("* 2 RECENT"), None);
+        assert_eq!(parse_notification("N2 OK NOOP completed"), None);
+    }
+
+    #[tokio::test]
+    async fn notify() {
+        let (client, server) = duplex(1024);
+        let mut client = ImapNotifyClient::new(Box::new(client));
+        let (server, mut responses) = split(server);
+        let mut commands = BufReader::new(server).lines();
+
+        let server = tokio::spawn(async move {
+            let mut received = Vec::new();
+
+            responses.write_all(b"* OK ready\r\n").await.unwrap();
+
+            while let Some(command) = commands.next_line().await.unwrap() {
+                let (tag, _) = command.split_once(' ').unwrap();
+                let notify = command.contains("NOTIFY");
+
+                if command.contains("EXAMINE") {
+                    responses.write_all(b"* 2 EXISTS\r\n").await.unwrap();
+                }
+
+                if notify {
+                    responses
+                        .write_all(b"* STATUS Sent (MESSAGES 1)\r\n")
+                        .await
+                        .unwrap();
+                }
+
+                let reply = format!("{tag} OK done\r\n");
+                responses.write_all(reply.as_bytes()).await.unwrap();
+
+                if notify {
+                    responses
+                        .write_all(b"* 3 EXPUNGE\r\n* BYE\r\n")
+                        .await
+                        .unwrap();
+                }
+
+                received.push(command);
+            }
+
+            received
+        });
+
+        client.login("bob", "pass\"word").await.unwrap();
+        client
+            .notify(&["INBOX".into(), "Sent".into(), "Archives".into()])
+            .await
+            .unwrap();
+
+        // responses to EXAMINE are not notifications, but the ones
+        // received before the NOTIFY tagged response are kept
+        assert_eq!(
+            client.next().await.unwrap(),
+            ImapNotification::Mailbox("Sent".into())
+        );
+        assert_eq!(client.next().await.unwrap(), ImapNotification::Selected);
+        assert!(client.next().await.is_err());
+
+        drop(client);
+
+        assert_eq!(
+            server.await.unwrap(),
+            [
+                "N1 LOGIN \"bob\" \"pass\\\"word\"",
+                "N2 EXAMINE \"INBOX\"",
+                "N3 NOTIFY SET (selected (MessageNew MessageExpunge FlagChange)) \
+                 (mailboxes (\"Sent\" \"Archives\") (MessageNew MessageExpunge FlagChange))",
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! # IMAP quota
+//!
+//! Module dedicated to the IMAP QUOTA extension, which exposes the
+//! storage usage and limits of mailboxes.
+//!
+//! https://www.rfc-editor.org/rfc/rfc9208.html
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::CommandBody,
+    extensions::quota::{QuotaGet, Resource},
+    mailbox::Mailbox,
+    response::{Data, StatusBody, StatusKind},
+};
+
+use crate::folder::quota::{Quota, QuotaUsage};
+
+/// The task resolving the GETQUOTAROOT command of a mailbox.
+#[derive(Clone, Debug)]
+pub struct GetQuotaRootTask {
+    mailbox: Mailbox<'static>,
+    quotas: Vec<Quota>,
+}
+
+impl GetQuotaRootTask {
+    pub fn new(mailbox: Mailbox<'static>) -> Self {
+        Self {
+            mailbox,
+            quotas: Vec::new(),
+        }
+    }
+}
+
+impl Task for GetQuotaRootTask {
+    type Output = Result<Vec<Quota>, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::GetQuotaRoot {
+            mailbox: self.mailbox.clone(),
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            // roots are also given by QUOTA responses
+            Data::QuotaRoot { .. } => None,
+            Data::Quota { root, quotas } => {
+                let mut quota = Quota {
+                    root: String::from_utf8_lossy(root.as_ref()).to_string(),
+                    ..Default::default()
+                };
+
+                for QuotaGet {
+                    resource,
+                    usage,
+                    limit,
+                } in quotas.into_iter()
+                {
+                    let usage = Some(QuotaUsage { usage, limit });
+
+                    match resource {
+                        Resource::Storage => quota.storage = usage,
+                        Resource::Message => quota.messages = usage,
+                        _ => (),
+                    }
+                }
+
+                self.quotas.push(quota);
+                None
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.quotas),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
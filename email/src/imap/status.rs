@@ -0,0 +1,76 @@
+//! # IMAP mailbox status
+//!
+//! Module dedicated to the IMAP STATUS command, which retrieves the
+//! number of messages of a mailbox without selecting it.
+
+use std::borrow::Cow;
+
+use imap_client::tasks::{tasks::TaskError, Task};
+use imap_next::imap_types::{
+    command::CommandBody,
+    mailbox::Mailbox,
+    response::{Data, StatusBody, StatusKind},
+    status::{StatusDataItem, StatusDataItemName},
+};
+
+/// The status of a mailbox.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MailboxStatus {
+    /// The total number of messages.
+    pub messages: Option<usize>,
+
+    /// The number of unseen messages.
+    pub unseen: Option<usize>,
+}
+
+/// The task resolving the MESSAGES and UNSEEN status of a mailbox.
+#[derive(Clone, Debug)]
+pub struct StatusTask {
+    mailbox: Mailbox<'static>,
+    status: MailboxStatus,
+}
+
+impl StatusTask {
+    pub fn new(mailbox: Mailbox<'static>) -> Self {
+        Self {
+            mailbox,
+            status: MailboxStatus::default(),
+        }
+    }
+}
+
+impl Task for StatusTask {
+    type Output = Result<MailboxStatus, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::Status {
+            mailbox: self.mailbox.clone(),
+            item_names: Cow::Borrowed(&[StatusDataItemName::Messages, StatusDataItemName::Unseen]),
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::Status { mailbox, items } if mailbox == self.mailbox => {
+                for item in items.iter() {
+                    match item {
+                        StatusDataItem::Messages(n) => self.status.messages = Some(*n as usize),
+                        StatusDataItem::Unseen(n) => self.status.unseen = Some(*n as usize),
+                        _ => (),
+                    }
+                }
+
+                None
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.status),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
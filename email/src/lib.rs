@@ -16,6 +16,10 @@
 //!
 //! See examples in the `/tests` folder.
 //!
+//! Programs only composing and sending messages can use the
+//! `send-only` cargo feature with `default-features = false`, which
+//! only compiles the SMTP and Sendmail backends.
+//!
 //! ## Backend features
 //!
 //! ### Folder
@@ -25,6 +29,12 @@
 //! - [`ExpungeFolder`](crate::folder::expunge::ExpungeFolder)
 //! - [`PurgeFolder`](crate::folder::purge::PurgeFolder)
 //! - [`DeleteFolder`](crate::folder::delete::DeleteFolder)
+//! - [`GetQuota`](crate::folder::quota::GetQuota)
+//! - [`SearchFolders`](crate::folder::search::SearchFolders)
+//! - [`GetFolder`](crate::folder::get::GetFolder)
+//! - [`GetFolderStats`](crate::folder::stats::GetFolderStats)
+//! - [`GetFolderMetadata`](crate::folder::metadata::GetFolderMetadata)
+//! - [`SetFolderMetadata`](crate::folder::metadata::SetFolderMetadata)
 //!
 //! ### Envelope
 //!
@@ -36,6 +46,7 @@
 //! - [`AddFlags`](crate::flag::add::AddFlags)
 //! - [`SetFlags`](crate::flag::set::SetFlags)
 //! - [`RemoveFlags`](crate::flag::remove::RemoveFlags)
+//! - [`ListFlags`](crate::flag::list::ListFlags)
 //!
 //! ### Message
 //!
@@ -52,22 +63,50 @@
 #[cfg(feature = "autoconfig")]
 pub mod autoconfig;
 pub mod backend;
+mod compat;
 pub mod config;
 pub mod email;
 mod error;
 pub mod folder;
 #[cfg(feature = "imap")]
 pub mod imap;
+#[cfg(feature = "lmtp")]
+pub mod lmtp;
 pub mod log;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "network")]
+pub mod network;
+#[cfg(feature = "nntp")]
+pub mod nntp;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+#[cfg(feature = "notmuch-remote")]
+pub mod notmuch_remote;
 pub mod retry;
+pub mod runtime;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
+#[cfg(any(feature = "smtp", feature = "sendmail"))]
+#[doc(hidden)]
+#[deprecated(
+    since = "0.26.0",
+    note = "use `email::smtp` or `email::sendmail` instead"
+)]
+pub mod sender {
+    #[cfg(feature = "sendmail")]
+    crate::compat::deprecated_module!(
+        sendmail => crate::sendmail,
+        "0.26.0",
+        "use `email::sendmail` instead"
+    );
+    #[cfg(feature = "smtp")]
+    crate::compat::deprecated_module!(smtp => crate::smtp, "0.26.0", "use `email::smtp` instead");
+}
 #[cfg(feature = "derive")]
 pub(crate) mod serde;
+#[cfg(feature = "sieve")]
+pub mod sieve;
 #[cfg(feature = "smtp")]
 pub mod smtp;
 #[cfg(feature = "sync")]
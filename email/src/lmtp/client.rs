@@ -0,0 +1,334 @@
+//! Module dedicated to the LMTP client.
+//!
+//! The core structure of this module is the [`LmtpClient`], which
+//! implements the subset of the [RFC
+//! 2033](https://www.rfc-editor.org/rfc/rfc2033) needed to deliver
+//! messages.
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+
+use super::{config::LmtpSocket, Error, Result};
+use crate::{
+    debug,
+    network::{self, config::NetworkConfig},
+    warn,
+};
+
+/// The LMTP stream.
+///
+/// The stream can be either a unix socket or a TCP socket.
+pub trait LmtpStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> LmtpStream for T {}
+
+/// An LMTP reply.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LmtpReply {
+    /// The three-digit reply code.
+    pub code: u16,
+
+    /// The text lines of the reply, without the code.
+    pub lines: Vec<String>,
+}
+
+impl LmtpReply {
+    /// Return `true` if the reply code is a positive completion
+    /// (2xx).
+    pub fn is_positive_completion(&self) -> bool {
+        (200..300).contains(&self.code)
+    }
+
+    /// Return `true` if the reply code is a positive intermediate
+    /// (3xx).
+    pub fn is_positive_intermediate(&self) -> bool {
+        (300..400).contains(&self.code)
+    }
+
+    /// Get the text of the reply, lines joined with spaces.
+    pub fn text(&self) -> String {
+        self.lines.join(" ")
+    }
+}
+
+/// The LMTP client.
+pub struct LmtpClient {
+    stream: BufStream<Box<dyn LmtpStream>>,
+}
+
+impl LmtpClient {
+    /// Connect to the LMTP server listening on the given socket, then
+    /// read the greeting.
+    ///
+    /// The network configuration only applies to TCP sockets.
+    pub async fn connect(socket: &LmtpSocket, network: Option<&NetworkConfig>) -> Result<Self> {
+        debug!("connecting to LMTP server {socket}");
+
+        let stream: Box<dyn LmtpStream> = match socket {
+            #[cfg(unix)]
+            LmtpSocket::Unix { path } => {
+                let stream = tokio::net::UnixStream::connect(path)
+                    .await
+                    .map_err(|err| Error::ConnectUnixError(err, path.clone()))?;
+                Box::new(stream)
+            }
+            #[cfg(not(unix))]
+            LmtpSocket::Unix { .. } => {
+                return Err(Error::UnixSocketNotSupportedError);
+            }
+            LmtpSocket::Tcp { host, port } => {
+                let stream = network::connect(network, host, *port)
+                    .await
+                    .map_err(|err| Error::ConnectTcpError(err, host.clone(), *port))?;
+                Box::new(stream)
+            }
+        };
+
+        let mut client = Self {
+            stream: BufStream::new(stream),
+        };
+
+        client.expect("greeting", |reply| reply.code == 220).await?;
+
+        Ok(client)
+    }
+
+    /// Greet the server using the given host name.
+    pub async fn lhlo(&mut self, hostname: &str) -> Result<LmtpReply> {
+        self.write(format!("LHLO {hostname}\r\n")).await?;
+        self.expect("LHLO", LmtpReply::is_positive_completion).await
+    }
+
+    /// Deliver the given message to the given recipients.
+    ///
+    /// Contrary to SMTP, the server sends one reply per accepted
+    /// recipient after the message data. The delivery fails if at
+    /// least one of the recipients is rejected, otherwise the reply
+    /// for the first recipient is returned.
+    pub async fn deliver(
+        &mut self,
+        mail_from: &str,
+        rcpt_to: &[String],
+        msg: &[u8],
+    ) -> Result<LmtpReply> {
+        self.write(format!("MAIL FROM:<{mail_from}>\r\n")).await?;
+        self.expect("MAIL FROM", LmtpReply::is_positive_completion)
+            .await?;
+
+        let mut accepted = Vec::with_capacity(rcpt_to.len());
+        let mut rejected = Vec::new();
+
+        for rcpt in rcpt_to {
+            self.write(format!("RCPT TO:<{rcpt}>\r\n")).await?;
+            let reply = self.read().await?;
+
+            if reply.is_positive_completion() {
+                accepted.push(rcpt);
+            } else {
+                warn!("recipient {rcpt} rejected: {} {}", reply.code, reply.text());
+                rejected.push(format!("{rcpt} ({} {})", reply.code, reply.text()));
+            }
+        }
+
+        if accepted.is_empty() {
+            self.write("RSET\r\n").await?;
+            self.read().await?;
+            return Err(Error::AllRecipientsRejectedError(rejected));
+        }
+
+        self.write("DATA\r\n").await?;
+        self.expect("DATA", LmtpReply::is_positive_intermediate)
+            .await?;
+
+        self.write(dot_stuff(msg)).await?;
+
+        let mut delivered = None;
+
+        for rcpt in accepted {
+            let reply = self.read().await?;
+
+            if !reply.is_positive_completion() {
+                warn!("cannot deliver to {rcpt}: {} {}", reply.code, reply.text());
+                rejected.push(format!("{rcpt} ({} {})", reply.code, reply.text()));
+            } else if delivered.is_none() {
+                delivered = Some(reply);
+            }
+        }
+
+        match delivered {
+            Some(reply) if rejected.is_empty() => Ok(reply),
+            _ => Err(Error::DeliverMessageError(rejected)),
+        }
+    }
+
+    /// Execute the no-operation command.
+    pub async fn noop(&mut self) -> Result<()> {
+        self.write("NOOP\r\n").await?;
+        self.expect("NOOP", LmtpReply::is_positive_completion)
+            .await?;
+        Ok(())
+    }
+
+    /// Close the session.
+    pub async fn quit(&mut self) -> Result<()> {
+        self.write("QUIT\r\n").await?;
+        self.expect("QUIT", |reply| reply.code == 221).await?;
+        Ok(())
+    }
+
+    async fn write(&mut self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        self.stream
+            .write_all(bytes.as_ref())
+            .await
+            .map_err(Error::WriteCommandError)?;
+        self.stream.flush().await.map_err(Error::WriteCommandError)
+    }
+
+    async fn read(&mut self) -> Result<LmtpReply> {
+        read_reply(&mut self.stream).await
+    }
+
+    /// Read a reply, then ensure it matches the given predicate.
+    async fn expect(&mut self, cmd: &str, f: impl Fn(&LmtpReply) -> bool) -> Result<LmtpReply> {
+        let reply = self.read().await?;
+
+        if f(&reply) {
+            Ok(reply)
+        } else {
+            let text = reply.text();
+            Err(Error::UnexpectedReplyError(
+                cmd.to_owned(),
+                reply.code,
+                text,
+            ))
+        }
+    }
+}
+
+/// Read a full LMTP reply from the given reader.
+///
+/// A reply is composed of one or more lines starting with the same
+/// three-digit code. All lines but the last one have a dash after the
+/// code.
+pub async fn read_reply<R>(reader: &mut R) -> Result<LmtpReply>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut lines = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        let n = reader
+            .read_line(&mut line)
+            .await
+            .map_err(Error::ReadReplyError)?;
+
+        if n == 0 {
+            return Err(Error::ConnectionClosedError);
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| Error::ParseReplyError(line.to_owned()))?;
+
+        let (last, text) = match line.get(3..4) {
+            Some("-") => (false, &line[4..]),
+            Some(" ") => (true, &line[4..]),
+            None => (true, ""),
+            Some(_) => return Err(Error::ParseReplyError(line.to_owned())),
+        };
+
+        lines.push(text.to_owned());
+
+        if last {
+            break Ok(LmtpReply { code, lines });
+        }
+    }
+}
+
+/// Transform the given message into LMTP data.
+///
+/// Lines starting with a dot get an extra dot, and the data is
+/// terminated by a line containing a single dot.
+pub fn dot_stuff(msg: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(msg.len() + 5);
+    let mut line_start = true;
+
+    for byte in msg {
+        if line_start && *byte == b'.' {
+            data.push(b'.');
+        }
+
+        data.push(*byte);
+        line_start = *byte == b'\n';
+    }
+
+    if !line_start {
+        data.extend_from_slice(b"\r\n");
+    }
+
+    data.extend_from_slice(b".\r\n");
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dot_stuff, read_reply, LmtpReply};
+
+    #[tokio::test]
+    async fn read_single_line_reply() {
+        let mut reader: &[u8] = b"220 localhost Dovecot ready.\r\n";
+
+        let reply = read_reply(&mut reader).await.unwrap();
+
+        assert_eq!(
+            reply,
+            LmtpReply {
+                code: 220,
+                lines: vec!["localhost Dovecot ready.".into()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn read_multi_line_reply() {
+        let mut reader: &[u8] = concat!(
+            "250-localhost\r\n",
+            "250-8BITMIME\r\n",
+            "250 PIPELINING\r\n",
+            "250 2.1.0 OK\r\n",
+        )
+        .as_bytes();
+
+        let reply = read_reply(&mut reader).await.unwrap();
+        assert!(reply.is_positive_completion());
+        assert_eq!(reply.text(), "localhost 8BITMIME PIPELINING");
+
+        let reply = read_reply(&mut reader).await.unwrap();
+        assert_eq!(reply.text(), "2.1.0 OK");
+    }
+
+    #[tokio::test]
+    async fn read_invalid_reply() {
+        let mut reader: &[u8] = b"hello\r\n";
+        assert!(read_reply(&mut reader).await.is_err());
+
+        let mut reader: &[u8] = b"250-localhost\r\n";
+        assert!(read_reply(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn dot_stuff_message() {
+        assert_eq!(
+            dot_stuff(b"Subject: a\r\n\r\nb\r\n"),
+            b"Subject: a\r\n\r\nb\r\n.\r\n"
+        );
+        assert_eq!(
+            dot_stuff(b".a\r\n..b\r\nc.d"),
+            b"..a\r\n...b\r\nc.d\r\n.\r\n"
+        );
+        assert_eq!(dot_stuff(b""), b".\r\n");
+    }
+}
@@ -0,0 +1,76 @@
+//! Module dedicated to the LMTP sender configuration.
+//!
+//! This module contains the configuration specific to the LMTP
+//! sender.
+
+use std::{fmt, path::PathBuf};
+
+/// The default LMTP unix socket path, as used by Dovecot.
+pub const DEFAULT_LMTP_SOCKET_PATH: &str = "/var/run/dovecot/lmtp";
+
+/// The default host name sent with the `LHLO` command.
+pub const DEFAULT_LMTP_LHLO_HOSTNAME: &str = "localhost";
+
+/// The LMTP sender configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct LmtpConfig {
+    /// The LMTP server socket.
+    ///
+    /// Defaults to the Dovecot unix socket `/var/run/dovecot/lmtp`.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub socket: LmtpSocket,
+
+    /// The host name sent with the `LHLO` command.
+    ///
+    /// Defaults to `localhost`.
+    pub lhlo_hostname: Option<String>,
+}
+
+impl LmtpConfig {
+    /// Get the `LHLO` host name, or return the default one.
+    pub fn lhlo_hostname(&self) -> &str {
+        self.lhlo_hostname
+            .as_deref()
+            .unwrap_or(DEFAULT_LMTP_LHLO_HOSTNAME)
+    }
+}
+
+/// The LMTP server socket.
+///
+/// LMTP servers usually listen on a local unix socket, but TCP is
+/// also supported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase", tag = "type")
+)]
+pub enum LmtpSocket {
+    /// The unix socket located at the given path.
+    Unix { path: PathBuf },
+
+    /// The TCP socket bound to the given host and port.
+    Tcp { host: String, port: u16 },
+}
+
+impl Default for LmtpSocket {
+    fn default() -> Self {
+        Self::Unix {
+            path: DEFAULT_LMTP_SOCKET_PATH.into(),
+        }
+    }
+}
+
+impl fmt::Display for LmtpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unix { path } => write!(f, "unix:{}", path.display()),
+            Self::Tcp { host, port } => write!(f, "tcp:{host}:{port}"),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+
+use crate::{network, AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot connect to LMTP server using unix socket {1}")]
+    ConnectUnixError(#[source] io::Error, PathBuf),
+    #[error("cannot connect to LMTP server using unix socket: not supported on this platform")]
+    UnixSocketNotSupportedError,
+    #[error("cannot connect to LMTP server {1}:{2} using TCP")]
+    ConnectTcpError(#[source] network::Error, String, u16),
+
+    #[error("cannot write LMTP command")]
+    WriteCommandError(#[source] io::Error),
+    #[error("cannot read LMTP reply")]
+    ReadReplyError(#[source] io::Error),
+    #[error("cannot read LMTP reply: connection closed")]
+    ConnectionClosedError,
+    #[error("cannot parse LMTP reply: {0}")]
+    ParseReplyError(String),
+    #[error("LMTP server replied to {0} with code {1}: {2}")]
+    UnexpectedReplyError(String, u16, String),
+
+    #[error("cannot prepare message before sending it")]
+    PrepareMessageError(#[source] crate::email::Error),
+    #[error("cannot send message without a sender")]
+    SendMessageMissingSenderError,
+    #[error("cannot send message without a recipient")]
+    SendMessageMissingRecipientError,
+    #[error("cannot send message: all recipients have been rejected: {0:?}")]
+    AllRecipientsRejectedError(Vec<String>),
+    #[error("cannot deliver message to some recipients: {0:?}")]
+    DeliverMessageError(Vec<String>),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
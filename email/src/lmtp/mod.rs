@@ -0,0 +1,188 @@
+//! Module dedicated to the LMTP sender.
+//!
+//! This module contains the LMTP backend context, which delivers
+//! messages to a local mail server (like Dovecot) using the Local
+//! Mail Transfer Protocol, over a unix socket or TCP.
+
+pub mod client;
+pub mod config;
+mod error;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use self::{client::LmtpClient, config::LmtpConfig};
+use crate::{
+    account::config::AccountConfig,
+    backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
+        context::{BackendContext, BackendContextBuilder},
+        feature::{BackendFeature, CheckUp},
+    },
+    debug, info,
+    message::send::{
+        envelope::{prepare_message, SendEnvelope},
+        lmtp::SendLmtpMessage,
+        SendMessage, SentMessage,
+    },
+    AnyResult,
+};
+
+/// The LMTP backend context.
+///
+/// A new LMTP session is opened for every message, since local
+/// deliveries are cheap and LMTP servers tend to close idle sessions.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LmtpContext {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The LMTP configuration.
+    pub lmtp_config: Arc<LmtpConfig>,
+}
+
+impl LmtpContext {
+    pub fn new(account_config: Arc<AccountConfig>, lmtp_config: Arc<LmtpConfig>) -> Self {
+        Self {
+            account_config,
+            lmtp_config,
+        }
+    }
+
+    /// Open a new LMTP session.
+    pub async fn connect(&self) -> Result<LmtpClient> {
+        let network = self.account_config.network.as_ref();
+        let mut client = LmtpClient::connect(&self.lmtp_config.socket, network).await?;
+        client.lhlo(self.lmtp_config.lhlo_hostname()).await?;
+        Ok(client)
+    }
+
+    /// Deliver the given raw message.
+    ///
+    /// The sender and the recipients are extracted from the message
+    /// headers, the same way the SMTP context does.
+    pub async fn send(&self, msg: &[u8]) -> Result<SentMessage> {
+        let (body, envelope, _) = prepare_message(&self.account_config, msg)
+            .await
+            .map_err(Error::PrepareMessageError)?;
+
+        let SendEnvelope { mail_from, rcpt_to } = envelope;
+
+        if rcpt_to.is_empty() {
+            return Err(Error::SendMessageMissingRecipientError);
+        }
+
+        let mail_from = mail_from.ok_or(Error::SendMessageMissingSenderError)?;
+        let rcpt_to: Vec<_> = rcpt_to.into_iter().collect();
+
+        let mut client = self.connect().await?;
+        let reply = client.deliver(&mail_from, &rcpt_to, &body).await?;
+
+        if let Err(_err) = client.quit().await {
+            debug!("cannot close LMTP session: {_err}");
+            debug!("{_err:?}");
+        }
+
+        Ok(SentMessage::from_response(format!(
+            "{} {}",
+            reply.code,
+            reply.text()
+        )))
+    }
+}
+
+/// The sync version of the LMTP backend context.
+///
+/// The context only holds configurations, so it can be shared as it
+/// is across multiple threads.
+pub type LmtpContextSync = LmtpContext;
+
+impl BackendContext for LmtpContextSync {}
+
+/// The LMTP context builder.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LmtpContextBuilder {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The LMTP configuration.
+    pub lmtp_config: Arc<LmtpConfig>,
+}
+
+impl LmtpContextBuilder {
+    pub fn new(account_config: Arc<AccountConfig>, lmtp_config: Arc<LmtpConfig>) -> Self {
+        Self {
+            account_config,
+            lmtp_config,
+        }
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for LmtpContextBuilder {
+    type Context = LmtpContextSync;
+
+    fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
+        Some(Arc::new(CheckUpLmtp::some_new_boxed))
+    }
+
+    fn send_message(&self) -> Option<BackendFeature<Self::Context, dyn SendMessage>> {
+        Some(Arc::new(SendLmtpMessage::some_new_boxed))
+    }
+
+    /// Build an LMTP sync context.
+    ///
+    /// No session is opened at this moment, see
+    /// [`LmtpContext::connect`].
+    async fn build(self) -> AnyResult<Self::Context> {
+        info!("building new LMTP context");
+
+        Ok(LmtpContextSync::new(self.account_config, self.lmtp_config))
+    }
+}
+
+#[derive(Clone)]
+pub struct CheckUpLmtp {
+    pub ctx: LmtpContextSync,
+}
+
+impl CheckUpLmtp {
+    pub fn new(ctx: &LmtpContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &LmtpContext) -> Box<dyn CheckUp> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &LmtpContext) -> Option<Box<dyn CheckUp>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl CheckUp for CheckUpLmtp {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+
+        let Some(mut client) = report
+            .probe(CheckUpProbeKind::Tcp, self.ctx.connect())
+            .await
+        else {
+            report.skip(CheckUpProbeKind::Session, "connection failed");
+            return Ok(report);
+        };
+
+        let session = async {
+            client.noop().await?;
+            client.quit().await
+        };
+
+        report.probe(CheckUpProbeKind::Session, session).await;
+
+        Ok(report)
+    }
+}
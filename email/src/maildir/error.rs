@@ -1,4 +1,4 @@
-use std::{any::Any, path::PathBuf, result};
+use std::{any::Any, io, path::PathBuf, result};
 
 use thiserror::Error;
 
@@ -14,8 +14,16 @@ pub enum Error {
     CheckConfigurationInvalidPathError(#[source] shellexpand_utils::Error),
     #[error("error while checking up current maildir directory")]
     CheckUpCurrentDirectoryError(#[source] maildirs::Error),
+    #[error("cannot read maildir root directory at {1}")]
+    CheckUpReadRootDirectoryError(#[source] io::Error, PathBuf),
+    #[error("cannot write into maildir root directory at {1}")]
+    CheckUpWriteRootDirectoryError(#[source] io::Error, PathBuf),
     #[error("cannot create maildir folder structure at {0}")]
     CreateFolderStructureError(#[source] maildirs::Error, PathBuf),
+    #[error("cannot read maildir folder identity at {1}")]
+    ReadFolderIdentityError(#[source] io::Error, PathBuf),
+    #[error("cannot write maildir folder identity at {1}")]
+    WriteFolderIdentityError(#[source] io::Error, PathBuf),
 
     #[error(transparent)]
     ExpandPathError(#[from] shellexpand_utils::Error),
@@ -0,0 +1,77 @@
+//! # Maildir folder identity
+//!
+//! Module dedicated to Maildir folder identities. Maildir has no
+//! equivalent of the IMAP UIDVALIDITY: a folder deleted then
+//! re-created outside of the library cannot be distinguished from
+//! the original one. A hidden file containing a generation id is
+//! then written in every folder the first time its identity is
+//! requested, so that the sync engine can detect such changes and
+//! invalidate its cache.
+
+use std::{
+    fs, io,
+    path::Path,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{Error, MaildirContext, Result};
+
+/// The name of the hidden file containing the identity of a folder.
+pub const IDENTITY_FILE: &str = ".identity";
+
+/// The name of the hidden file containing the identity of the folder
+/// a cache folder was last synchronized from.
+pub const SOURCE_IDENTITY_FILE: &str = ".source-identity";
+
+impl MaildirContext {
+    /// Return the identity of the given folder, generating a new one
+    /// if the folder does not have any yet.
+    pub fn get_folder_identity(&self, folder: &str) -> Result<String> {
+        let mdir = self.get_maildir_from_folder_alias(folder)?;
+        let path = mdir.path().join(IDENTITY_FILE);
+
+        if let Some(identity) = read_identity(&path)? {
+            return Ok(identity);
+        }
+
+        let identity = generate_identity();
+        fs::write(&path, &identity)
+            .map_err(|err| Error::WriteFolderIdentityError(err, path.clone()))?;
+
+        Ok(identity)
+    }
+
+    /// Return the identity of the folder the given folder was last
+    /// synchronized from, if any.
+    pub fn get_folder_source_identity(&self, folder: &str) -> Result<Option<String>> {
+        let mdir = self.get_maildir_from_folder_alias(folder)?;
+        read_identity(&mdir.path().join(SOURCE_IDENTITY_FILE))
+    }
+
+    /// Save the identity of the folder the given folder is
+    /// synchronized from.
+    pub fn set_folder_source_identity(&self, folder: &str, identity: &str) -> Result<()> {
+        let mdir = self.get_maildir_from_folder_alias(folder)?;
+        let path = mdir.path().join(SOURCE_IDENTITY_FILE);
+        fs::write(&path, identity).map_err(|err| Error::WriteFolderIdentityError(err, path))
+    }
+}
+
+fn read_identity(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(identity) if !identity.trim().is_empty() => Ok(Some(identity.trim().to_owned())),
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::ReadFolderIdentityError(err, path.to_owned())),
+    }
+}
+
+fn generate_identity() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+
+    format!("{nanos:x}-{:x}", process::id())
+}
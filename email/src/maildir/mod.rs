@@ -1,7 +1,14 @@
 pub mod config;
 mod error;
-
-use std::{ops::Deref, path::PathBuf, sync::Arc};
+pub mod identity;
+
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use maildirs::{Maildir, Maildirs};
@@ -11,6 +18,8 @@
 use self::config::MaildirConfig;
 #[doc(inline)]
 pub use self::error::{Error, Result};
+#[cfg(feature = "stream")]
+use crate::envelope::stream::{maildir::StreamMaildirEnvelopes, StreamEnvelopes};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{maildir::ThreadMaildirEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
@@ -18,6 +27,7 @@
 use crate::{
     account::config::AccountConfig,
     backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
@@ -27,6 +37,7 @@
     },
     flag::{
         add::{maildir::AddMaildirFlags, AddFlags},
+        list::{maildir::ListMaildirFlags, ListFlags},
         remove::{maildir::RemoveMaildirFlags, RemoveFlags},
         set::{maildir::SetMaildirFlags, SetFlags},
     },
@@ -34,7 +45,13 @@
         add::{maildir::AddMaildirFolder, AddFolder},
         delete::{maildir::DeleteMaildirFolder, DeleteFolder},
         expunge::{maildir::ExpungeMaildirFolder, ExpungeFolder},
+        get::{maildir::GetMaildirFolder, GetFolder},
         list::{maildir::ListMaildirFolders, ListFolders},
+        metadata::{
+            maildir::{GetMaildirFolderMetadata, SetMaildirFolderMetadata},
+            GetFolderMetadata, SetFolderMetadata,
+        },
+        stats::{maildir::GetMaildirFolderStats, GetFolderStats},
         FolderKind,
     },
     info,
@@ -50,6 +67,11 @@
     AnyResult,
 };
 
+/// The name of the local flag store, at the root of the Maildir.
+///
+/// See [`FlagStore`](crate::flag::store::FlagStore).
+pub const FLAG_STORE_NAME: &str = ".flags";
+
 /// The Maildir backend context.
 ///
 /// This context is unsync, which means it cannot be shared between
@@ -79,6 +101,14 @@ pub fn get_maildir_from_folder_alias(&self, folder: &str) -> Result<Maildir> {
         let mdir = self.root.get(folder)?;
         Ok(mdir)
     }
+
+    /// Get the path to the local flag store, if flags unsupported by
+    /// Maildir should be preserved.
+    pub fn get_flag_store_path(&self) -> Option<PathBuf> {
+        self.account_config
+            .should_preserve_unsupported_flags()
+            .then(|| self.root.path().join(FLAG_STORE_NAME))
+    }
 }
 
 /// The sync version of the Maildir backend context.
@@ -175,6 +205,14 @@ fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>>
         Some(Arc::new(ListMaildirFolders::some_new_boxed))
     }
 
+    fn get_folder(&self) -> Option<BackendFeature<Self::Context, dyn GetFolder>> {
+        Some(Arc::new(GetMaildirFolder::some_new_boxed))
+    }
+
+    fn get_folder_stats(&self) -> Option<BackendFeature<Self::Context, dyn GetFolderStats>> {
+        Some(Arc::new(GetMaildirFolderStats::some_new_boxed))
+    }
+
     fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
         Some(Arc::new(ExpungeMaildirFolder::some_new_boxed))
     }
@@ -188,6 +226,14 @@ fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder
         Some(Arc::new(DeleteMaildirFolder::some_new_boxed))
     }
 
+    fn get_folder_metadata(&self) -> Option<BackendFeature<Self::Context, dyn GetFolderMetadata>> {
+        Some(Arc::new(GetMaildirFolderMetadata::some_new_boxed))
+    }
+
+    fn set_folder_metadata(&self) -> Option<BackendFeature<Self::Context, dyn SetFolderMetadata>> {
+        Some(Arc::new(SetMaildirFolderMetadata::some_new_boxed))
+    }
+
     fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
         Some(Arc::new(GetMaildirEnvelope::some_new_boxed))
     }
@@ -201,6 +247,11 @@ fn thread_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ThreadEnv
         Some(Arc::new(ThreadMaildirEnvelopes::some_new_boxed))
     }
 
+    #[cfg(feature = "stream")]
+    fn stream_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn StreamEnvelopes>> {
+        Some(Arc::new(StreamMaildirEnvelopes::some_new_boxed))
+    }
+
     #[cfg(feature = "watch")]
     fn watch_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn WatchEnvelopes>> {
         Some(Arc::new(WatchMaildirEnvelopes::some_new_boxed))
@@ -218,6 +269,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveMaildirFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListMaildirFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddMaildirMessage::some_new_boxed))
     }
@@ -284,20 +339,29 @@ pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn CheckUp>> {
 
 #[async_trait]
 impl CheckUp for CheckUpMaildir {
-    async fn check_up(&self) -> AnyResult<()> {
-        // FIXME
-        //
-        // let ctx = self.ctx.lock().await;
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+        let ctx = self.ctx.lock().await;
 
-        // ctx.root
-        //     .list_cur()
-        //     .try_for_each(|e| e.map(|_| ()))
-        //     .map_err(Error::CheckUpCurrentDirectoryError)?;
+        let access = async { check_up_root_dir(ctx.root.path()) };
+        report.probe(CheckUpProbeKind::FolderAccess, access).await;
 
-        Ok(())
+        Ok(report)
     }
 }
 
+/// Check that the given Maildir root directory exists, and that it is
+/// readable and writable.
+fn check_up_root_dir(root: &Path) -> Result<()> {
+    fs::read_dir(root).map_err(|err| Error::CheckUpReadRootDirectoryError(err, root.to_owned()))?;
+
+    // writing a temporary file is the only reliable way to check
+    // that the directory is writable
+    let path = root.join(format!(".check-up.{}", process::id()));
+    let write = fs::write(&path, b"").and_then(|()| fs::remove_file(&path));
+    write.map_err(|err| Error::CheckUpWriteRootDirectoryError(err, root.to_owned()))
+}
+
 /// URL-encode the given folder.
 pub fn encode_folder(folder: impl AsRef<str>) -> String {
     urlencoding::encode(folder.as_ref()).to_string()
@@ -309,3 +373,20 @@ pub fn decode_folder(folder: impl AsRef<str> + ToString) -> String {
         .map(|folder| folder.to_string())
         .unwrap_or_else(|_| folder.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::check_up_root_dir;
+
+    #[test]
+    fn check_up_root() {
+        let dir = tempfile::tempdir().unwrap();
+        check_up_root_dir(dir.path()).unwrap();
+        assert_eq!(dir.path().read_dir().unwrap().count(), 0);
+
+        let err = check_up_root_dir(&dir.path().join("missing")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("cannot read maildir root directory"));
+    }
+}
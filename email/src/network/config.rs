@@ -0,0 +1,137 @@
+//! Module dedicated to the network configuration.
+//!
+//! This module contains the configuration shared by all network
+//! contexts in order to establish TCP connections: proxy, bind
+//! address and DNS overrides. It also contains the TLS client
+//! certificate configuration, used by IMAP and SMTP contexts.
+
+use std::{collections::HashMap, fmt, net::IpAddr, path::PathBuf};
+
+use crate::account::config::passwd::PasswdConfig;
+
+/// The network configuration.
+///
+/// This configuration is defined at account level, and is honored
+/// by every context establishing TCP connections (IMAP, SMTP,
+/// ManageSieve, NNTP, LMTP and OAuth 2.0 token requests).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct NetworkConfig {
+    /// The proxy configuration.
+    ///
+    /// When defined, connections are tunneled through the given
+    /// SOCKS5 or HTTP CONNECT proxy.
+    pub proxy: Option<ProxyConfig>,
+
+    /// The local address outgoing connections are bound to.
+    ///
+    /// Useful on multi-homed hosts, or to force the use of a
+    /// specific interface (like a VPN one).
+    pub bind_address: Option<IpAddr>,
+
+    /// The DNS overrides.
+    ///
+    /// Maps host names to IP addresses, bypassing the system
+    /// resolver, the same way `/etc/hosts` does. Host names are
+    /// matched case-insensitively.
+    pub dns_overrides: Option<HashMap<String, IpAddr>>,
+}
+
+impl NetworkConfig {
+    /// Find the IP address overriding the given host name.
+    pub fn find_dns_override(&self, host: &str) -> Option<IpAddr> {
+        self.dns_overrides
+            .as_ref()?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            .map(|(_, addr)| *addr)
+    }
+}
+
+/// The proxy configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ProxyConfig {
+    /// The proxy protocol.
+    ///
+    /// Supported protocols: SOCKS5 or HTTP CONNECT. Defaults to
+    /// SOCKS5.
+    #[cfg_attr(feature = "derive", serde(default, rename = "type"))]
+    pub kind: ProxyKind,
+
+    /// The proxy host name.
+    pub host: String,
+
+    /// The proxy port.
+    pub port: u16,
+
+    /// The proxy login, if the proxy requires authentication.
+    pub login: Option<String>,
+
+    /// The proxy password.
+    ///
+    /// Only used when a login is defined.
+    pub passwd: Option<PasswdConfig>,
+}
+
+/// The proxy protocol.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ProxyKind {
+    /// The SOCKS5 protocol.
+    ///
+    /// Host names are resolved by the proxy, which makes it suitable
+    /// for Tor.
+    #[default]
+    Socks5,
+
+    /// The HTTP CONNECT method.
+    #[cfg_attr(feature = "derive", serde(alias = "https"))]
+    Http,
+}
+
+impl fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Socks5 => write!(f, "SOCKS5"),
+            Self::Http => write!(f, "HTTP CONNECT"),
+        }
+    }
+}
+
+/// The TLS client certificate configuration.
+///
+/// Used to authenticate the client to servers requiring mutual TLS
+/// (mTLS).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ClientCertificateConfig {
+    /// The path to the PEM-encoded client certificate.
+    ///
+    /// The file may contain the intermediate certificates as well,
+    /// after the client certificate. The path is shell expanded.
+    pub cert: PathBuf,
+
+    /// The path to the PEM-encoded private key of the client
+    /// certificate.
+    ///
+    /// PKCS#1, PKCS#8 and SEC1 keys are supported. The path is
+    /// shell expanded.
+    pub key: PathBuf,
+}
@@ -0,0 +1,97 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+use tokio_rustls::rustls::{self, client::VerifierBuilderError, pki_types::InvalidDnsNameError};
+
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot resolve host {1}")]
+    ResolveHostError(#[source] io::Error, String),
+    #[error("cannot connect to {1}:{2}")]
+    ConnectError(#[source] io::Error, String, u16),
+    #[error("cannot open custom stream to {1}:{2}")]
+    ConnectStreamError(#[source] io::Error, String, u16),
+    #[error("cannot bridge custom stream to loopback TCP stream")]
+    BridgeStreamError(#[source] io::Error),
+    #[error("cannot connect to proxy {1}:{2}")]
+    ConnectProxyError(#[source] Box<Error>, String, u16),
+    #[error("cannot write to proxy")]
+    WriteProxyError(#[source] io::Error),
+    #[error("cannot read from proxy")]
+    ReadProxyError(#[source] io::Error),
+    #[error("cannot get proxy password")]
+    GetPasswdError(#[source] secret::Error),
+    #[error("cannot get proxy password: password is empty")]
+    GetPasswdEmptyError,
+
+    #[error("cannot use host name {0} with SOCKS5 proxy: name too long")]
+    Socks5HostTooLongError(String),
+    #[error("cannot use login with SOCKS5 proxy: login or password too long")]
+    Socks5CredentialsTooLongError,
+    #[error("cannot negotiate with SOCKS5 proxy: invalid version {0}")]
+    Socks5InvalidVersionError(u8),
+    #[error("cannot negotiate with SOCKS5 proxy: no acceptable authentication method")]
+    Socks5NoAcceptableMethodError,
+    #[error("cannot authenticate to SOCKS5 proxy: invalid credentials")]
+    Socks5AuthenticationError,
+    #[error("cannot connect to {1}:{2} through SOCKS5 proxy: {0}")]
+    Socks5ConnectError(&'static str, String, u16),
+    #[error("cannot negotiate with SOCKS5 proxy: invalid address type {0}")]
+    Socks5InvalidAddressTypeError(u8),
+
+    #[error("cannot connect to {1}:{2} through HTTP proxy: {0}")]
+    HttpConnectError(String, String, u16),
+    #[error("cannot read HTTP proxy response: headers too large")]
+    HttpResponseTooLargeError,
+
+    #[error("cannot build TLS configuration")]
+    BuildTlsConfigError(#[source] rustls::Error),
+    #[error("cannot build TLS certificate verifier")]
+    BuildTlsVerifierError(#[source] VerifierBuilderError),
+    #[error("cannot parse certificate pin {0}: expected base64-encoded SPKI SHA-256 hash")]
+    InvalidCertificatePinError(String),
+    #[error("cannot use {1} as TLS server name")]
+    InvalidServerNameError(#[source] InvalidDnsNameError, String),
+    #[error("cannot negotiate SSL/TLS with {1}:{2}")]
+    ConnectTlsError(#[source] io::Error, String, u16),
+    #[error("cannot negotiate SSL/TLS with {0}:{1}: server certificate is not pinned")]
+    UnpinnedCertificateError(String, u16),
+    #[error("cannot read CA bundle at {1}")]
+    ReadCaBundleError(#[source] io::Error, PathBuf),
+    #[error("cannot find certificate in CA bundle {0}")]
+    CaCertificateNotFoundError(PathBuf),
+    #[error("cannot use certificate from CA bundle {1}")]
+    InvalidCaCertificateError(#[source] rustls::Error, PathBuf),
+    #[error("cannot read TLS client certificate at {1}")]
+    ReadClientCertificateError(#[source] io::Error, PathBuf),
+    #[error("cannot find TLS client certificate in {0}")]
+    ClientCertificateNotFoundError(PathBuf),
+    #[error("cannot read TLS client private key at {1}")]
+    ReadClientKeyError(#[source] io::Error, PathBuf),
+    #[error("cannot find TLS client private key in {0}")]
+    ClientKeyNotFoundError(PathBuf),
+    #[error("cannot use TLS client certificate")]
+    BuildTlsClientAuthError(#[source] rustls::Error),
+
+    #[cfg(feature = "oauth2")]
+    #[error("cannot build HTTP client")]
+    BuildHttpClientError(#[source] oauth::reqwest::Error),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,245 @@
+//! # Network
+//!
+//! Module dedicated to network connections. The main function of
+//! this module is [`connect`], which establishes TCP connections to
+//! remote servers while honoring the account [`NetworkConfig`]:
+//! connections can be tunneled through a SOCKS5 or an HTTP CONNECT
+//! proxy, bound to a local address, and host names can be resolved
+//! using DNS overrides. Connections can also be opened over custom
+//! streams, see [`stream`].
+
+pub mod config;
+mod error;
+mod proxy;
+pub mod stream;
+pub mod tls;
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+
+use self::config::NetworkConfig;
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use crate::debug;
+
+/// Connect to the given server, using the given network
+/// configuration if any.
+///
+/// Without configuration, this function behaves like a plain
+/// [`TcpStream::connect`].
+pub async fn connect(config: Option<&NetworkConfig>, host: &str, port: u16) -> Result<TcpStream> {
+    match config {
+        Some(config) => config.connect(host, port).await,
+        None => TcpStream::connect((host, port))
+            .await
+            .map_err(|err| Error::ConnectError(err, host.to_owned(), port)),
+    }
+}
+
+/// Resolve the given server host name, using the given network
+/// configuration if any.
+///
+/// See [`NetworkConfig::resolve`].
+pub async fn resolve(
+    config: Option<&NetworkConfig>,
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>> {
+    match config {
+        Some(config) => config.resolve(host, port).await,
+        None => NetworkConfig::default().resolve(host, port).await,
+    }
+}
+
+impl NetworkConfig {
+    /// Connect to the given server.
+    ///
+    /// When a proxy is defined, the connection to the proxy honors
+    /// the bind address and the DNS overrides. The target host name
+    /// is resolved by the proxy, unless it is overridden.
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let Some(proxy) = self.proxy.as_ref() else {
+            return self.connect_direct(host, port).await;
+        };
+
+        let mut stream = self
+            .connect_direct(&proxy.host, proxy.port)
+            .await
+            .map_err(|err| {
+                Error::ConnectProxyError(Box::new(err), proxy.host.clone(), proxy.port)
+            })?;
+
+        match self.find_dns_override(host) {
+            Some(addr) => proxy.tunnel(&mut stream, &addr.to_string(), port).await?,
+            None => proxy.tunnel(&mut stream, host, port).await?,
+        }
+
+        Ok(stream)
+    }
+
+    /// Connect to the given server without proxy.
+    ///
+    /// Resolved addresses are tried in order, until one of them
+    /// accepts the connection.
+    async fn connect_direct(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let addrs = self.resolve(host, port).await?;
+        let mut last_err = None;
+
+        for addr in addrs {
+            debug!("connecting to {host}:{port} using address {addr}");
+
+            match connect_addr(addr, self.bind_address).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => {
+                    debug!("cannot connect to {addr}: {err}");
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        let err = last_err.unwrap_or_else(|| {
+            let reason = format!("no address found for {host}");
+            io::Error::new(io::ErrorKind::NotFound, reason)
+        });
+
+        Err(Error::ConnectError(err, host.to_owned(), port))
+    }
+
+    /// Resolve the given server host name, honoring the DNS
+    /// overrides.
+    ///
+    /// When a bind address is defined, only addresses of the same
+    /// family are returned.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        if let Some(addr) = self.find_dns_override(host) {
+            debug!("resolving {host} to {addr} using DNS overrides");
+            return Ok(vec![SocketAddr::new(addr, port)]);
+        }
+
+        let addrs = lookup_host((host, port))
+            .await
+            .map_err(|err| Error::ResolveHostError(err, host.to_owned()))?
+            .filter(|addr| match self.bind_address {
+                Some(bind) => bind.is_ipv4() == addr.is_ipv4(),
+                None => true,
+            })
+            .collect();
+
+        Ok(addrs)
+    }
+
+    /// Build an HTTP client honoring the network configuration.
+    ///
+    /// Redirections are disabled, as required by OAuth 2.0 token
+    /// requests.
+    #[cfg(feature = "oauth2")]
+    pub async fn build_http_client(&self) -> Result<oauth::reqwest::Client> {
+        use oauth::reqwest::{redirect::Policy, Client, Proxy};
+
+        use self::config::ProxyKind;
+
+        let mut builder = Client::builder().redirect(Policy::none());
+
+        if let Some(config) = self.proxy.as_ref() {
+            let scheme = match config.kind {
+                ProxyKind::Socks5 => "socks5h",
+                ProxyKind::Http => "http",
+            };
+
+            let url = format!("{scheme}://{}:{}", config.host, config.port);
+            let mut proxy = Proxy::all(url).map_err(Error::BuildHttpClientError)?;
+
+            if let Some((login, passwd)) = config.credentials().await? {
+                proxy = proxy.basic_auth(&login, &passwd);
+            }
+
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(addr) = self.bind_address {
+            builder = builder.local_address(addr);
+        }
+
+        for (host, addr) in self.dns_overrides.iter().flatten() {
+            // the port is ignored, the one from the URL is used
+            builder = builder.resolve(host, SocketAddr::new(*addr, 0));
+        }
+
+        builder.build().map_err(Error::BuildHttpClientError)
+    }
+}
+
+/// Connect to the given socket address, binding the socket to the
+/// given local address if any.
+async fn connect_addr(addr: SocketAddr, bind: Option<IpAddr>) -> io::Result<TcpStream> {
+    let Some(bind) = bind else {
+        return TcpStream::connect(addr).await;
+    };
+
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    socket.bind(SocketAddr::new(bind, 0))?;
+    socket.connect(addr).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use tokio::net::TcpListener;
+
+    use super::config::NetworkConfig;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn find_dns_override() {
+        let config = NetworkConfig {
+            dns_overrides: Some(HashMap::from_iter([(
+                String::from("Mail.Example.Org"),
+                LOCALHOST,
+            )])),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.find_dns_override("mail.example.org"),
+            Some(LOCALHOST)
+        );
+        assert_eq!(config.find_dns_override("example.org"), None);
+        assert_eq!(
+            NetworkConfig::default().find_dns_override("localhost"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_using_overrides() {
+        let listener = TcpListener::bind((LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let config = NetworkConfig {
+            bind_address: Some(LOCALHOST),
+            dns_overrides: Some(HashMap::from_iter([(
+                String::from("mail.example.invalid"),
+                LOCALHOST,
+            )])),
+            ..Default::default()
+        };
+
+        let stream = config.connect("mail.example.invalid", port).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+
+        assert_eq!(stream.local_addr().unwrap(), peer);
+    }
+}
@@ -0,0 +1,378 @@
+//! # Proxy
+//!
+//! Module dedicated to proxies. It negotiates TCP tunnels to remote
+//! servers through SOCKS5 or HTTP CONNECT proxies. Once established,
+//! a tunnel can be used like a direct TCP connection, which means
+//! that TLS can be negotiated on top of it.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::{
+    config::{ProxyConfig, ProxyKind},
+    Error, Result,
+};
+use crate::debug;
+
+/// The maximum size of the HTTP proxy response headers.
+const HTTP_MAX_HEADERS_LEN: usize = 8 * 1024;
+
+impl ProxyConfig {
+    /// Negotiate a tunnel to the given server, using the given
+    /// stream connected to the proxy.
+    pub(crate) async fn tunnel(&self, stream: &mut TcpStream, host: &str, port: u16) -> Result<()> {
+        debug!(
+            "connecting to {host}:{port} through {} proxy {}:{}",
+            self.kind, self.host, self.port
+        );
+
+        let credentials = self.credentials().await?;
+        let credentials = credentials.as_ref().map(|(l, p)| (l.as_str(), p.as_str()));
+
+        match self.kind {
+            ProxyKind::Socks5 => socks5_connect(stream, host, port, credentials).await,
+            ProxyKind::Http => http_connect(stream, host, port, credentials).await,
+        }
+    }
+
+    /// Get the proxy login and password, if a login is defined.
+    pub(crate) async fn credentials(&self) -> Result<Option<(String, String)>> {
+        let Some(login) = self.login.as_ref() else {
+            return Ok(None);
+        };
+
+        let passwd = match self.passwd.as_ref() {
+            Some(passwd) => {
+                let passwd = passwd.get().await.map_err(Error::GetPasswdError)?;
+                let passwd = passwd.lines().next().ok_or(Error::GetPasswdEmptyError)?;
+                passwd.to_owned()
+            }
+            None => String::new(),
+        };
+
+        Ok(Some((login.clone(), passwd)))
+    }
+}
+
+/// Negotiate a tunnel to the given server using the SOCKS5 protocol.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc1928>.
+async fn socks5_connect<S>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    const VERSION: u8 = 5;
+    const NO_AUTH: u8 = 0;
+    const USER_PASS_AUTH: u8 = 2;
+
+    // greeting
+    let greeting: &[u8] = match credentials {
+        Some(_) => &[VERSION, 2, NO_AUTH, USER_PASS_AUTH],
+        None => &[VERSION, 1, NO_AUTH],
+    };
+
+    write_all(stream, greeting).await?;
+
+    let mut reply = [0; 2];
+    read_exact(stream, &mut reply).await?;
+
+    if reply[0] != VERSION {
+        return Err(Error::Socks5InvalidVersionError(reply[0]));
+    }
+
+    match (reply[1], credentials) {
+        (NO_AUTH, _) => (),
+        (USER_PASS_AUTH, Some((login, passwd))) => {
+            // see https://datatracker.ietf.org/doc/html/rfc1929
+            let login_len = u8::try_from(login.len());
+            let passwd_len = u8::try_from(passwd.len());
+            let (Ok(login_len), Ok(passwd_len)) = (login_len, passwd_len) else {
+                return Err(Error::Socks5CredentialsTooLongError);
+            };
+
+            let mut auth = vec![1, login_len];
+            auth.extend_from_slice(login.as_bytes());
+            auth.push(passwd_len);
+            auth.extend_from_slice(passwd.as_bytes());
+            write_all(stream, &auth).await?;
+
+            let mut reply = [0; 2];
+            read_exact(stream, &mut reply).await?;
+
+            if reply[1] != 0 {
+                return Err(Error::Socks5AuthenticationError);
+            }
+        }
+        _ => {
+            return Err(Error::Socks5NoAcceptableMethodError);
+        }
+    }
+
+    // connection request, using the domain name address type so
+    // that the host name is resolved by the proxy
+    let Ok(host_len) = u8::try_from(host.len()) else {
+        return Err(Error::Socks5HostTooLongError(host.to_owned()));
+    };
+
+    let mut request = vec![VERSION, 1, 0, 3, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    write_all(stream, &request).await?;
+
+    let mut reply = [0; 4];
+    read_exact(stream, &mut reply).await?;
+
+    if reply[0] != VERSION {
+        return Err(Error::Socks5InvalidVersionError(reply[0]));
+    }
+
+    if reply[1] != 0 {
+        let reason = match reply[1] {
+            1 => "general SOCKS server failure",
+            2 => "connection not allowed by ruleset",
+            3 => "network unreachable",
+            4 => "host unreachable",
+            5 => "connection refused",
+            6 => "TTL expired",
+            7 => "command not supported",
+            8 => "address type not supported",
+            _ => "unknown error",
+        };
+        return Err(Error::Socks5ConnectError(reason, host.to_owned(), port));
+    }
+
+    // consume the bound address and port
+    let addr_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0; 1];
+            read_exact(stream, &mut len).await?;
+            len[0] as usize
+        }
+        atyp => return Err(Error::Socks5InvalidAddressTypeError(atyp)),
+    };
+
+    let mut bound = vec![0; addr_len + 2];
+    read_exact(stream, &mut bound).await?;
+
+    Ok(())
+}
+
+/// Negotiate a tunnel to the given server using the HTTP CONNECT
+/// method.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9110#name-connect>.
+async fn http_connect<S>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+
+    if let Some((login, passwd)) = credentials {
+        let token = STANDARD.encode(format!("{login}:{passwd}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+    }
+
+    request.push_str("\r\n");
+    write_all(stream, request.as_bytes()).await?;
+
+    // the response is read byte by byte, in order not to consume
+    // bytes sent by the server through the tunnel
+    let mut response = Vec::new();
+    let mut byte = [0; 1];
+
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= HTTP_MAX_HEADERS_LEN {
+            return Err(Error::HttpResponseTooLargeError);
+        }
+
+        read_exact(stream, &mut byte).await?;
+        response.push(byte[0]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+
+    if !status.starts_with('2') {
+        let status_line = status_line.to_owned();
+        return Err(Error::HttpConnectError(status_line, host.to_owned(), port));
+    }
+
+    Ok(())
+}
+
+async fn write_all<S: AsyncWrite + Unpin>(stream: &mut S, bytes: &[u8]) -> Result<()> {
+    stream
+        .write_all(bytes)
+        .await
+        .map_err(Error::WriteProxyError)?;
+    stream.flush().await.map_err(Error::WriteProxyError)
+}
+
+async fn read_exact<S: AsyncRead + Unpin>(stream: &mut S, buf: &mut [u8]) -> Result<()> {
+    stream
+        .read_exact(buf)
+        .await
+        .map_err(Error::ReadProxyError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::{http_connect, socks5_connect, Error};
+
+    #[tokio::test]
+    async fn socks5_no_auth() {
+        let (mut client, mut proxy) = duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let mut greeting = [0; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, 0]);
+            proxy.write_all(&[5, 0]).await.unwrap();
+
+            let mut request = [0; 5 + 9 + 2];
+            proxy.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..5], &[5, 1, 0, 3, 9]);
+            assert_eq!(&request[5..14], b"localhost");
+            assert_eq!(&request[14..], &993u16.to_be_bytes());
+
+            proxy
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0, b'*'])
+                .await
+                .unwrap();
+        });
+
+        socks5_connect(&mut client, "localhost", 993, None)
+            .await
+            .unwrap();
+
+        // the tunnel starts right after the reply
+        let mut byte = [0; 1];
+        client.read_exact(&mut byte).await.unwrap();
+        assert_eq!(&byte, b"*");
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn socks5_auth() {
+        let (mut client, mut proxy) = duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let mut greeting = [0; 4];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 2, 0, 2]);
+            proxy.write_all(&[5, 2]).await.unwrap();
+
+            let mut auth = [0; 1 + 1 + 3 + 1 + 6];
+            proxy.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x03bob\x06passwd");
+            proxy.write_all(&[1, 1]).await.unwrap();
+        });
+
+        let err = socks5_connect(&mut client, "localhost", 993, Some(("bob", "passwd")))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Socks5AuthenticationError));
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn socks5_connect_error() {
+        let (mut client, mut proxy) = duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let mut greeting = [0; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            proxy.write_all(&[5, 0]).await.unwrap();
+
+            let mut request = [0; 5 + 9 + 2];
+            proxy.read_exact(&mut request).await.unwrap();
+            proxy.write_all(&[5, 5, 0, 1]).await.unwrap();
+        });
+
+        let err = socks5_connect(&mut client, "localhost", 993, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Socks5ConnectError("connection refused", _, 993)
+        ));
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn http() {
+        let (mut client, mut proxy) = duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let expected = concat!(
+                "CONNECT localhost:993 HTTP/1.1\r\n",
+                "Host: localhost:993\r\n",
+                "Proxy-Authorization: Basic Ym9iOnBhc3N3ZA==\r\n",
+                "\r\n",
+            );
+
+            let mut request = vec![0; expected.len()];
+            proxy.read_exact(&mut request).await.unwrap();
+            assert_eq!(String::from_utf8(request).unwrap(), expected);
+
+            proxy
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n* OK")
+                .await
+                .unwrap();
+        });
+
+        http_connect(&mut client, "localhost", 993, Some(("bob", "passwd")))
+            .await
+            .unwrap();
+
+        let mut greeting = [0; 4];
+        client.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"* OK");
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn http_error() {
+        let (mut client, mut proxy) = duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let mut request = vec![0; 55];
+            proxy.read_exact(&mut request).await.unwrap();
+            proxy
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let err = http_connect(&mut client, "localhost", 993, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::HttpConnectError(ref status, _, 993)
+            if status == "HTTP/1.1 407 Proxy Authentication Required"));
+
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! # Stream
+//!
+//! Module dedicated to custom streams. A [`StreamConnector`] opens
+//! connections using arbitrary [`AsyncRead`] + [`AsyncWrite`] streams
+//! (unix sockets, SSH tunnels, test harnesses…) instead of plain TCP
+//! streams.
+//!
+//! Network clients expect TCP streams, so custom streams are bridged
+//! through a loopback TCP connection: bytes are copied back and forth
+//! between the custom stream and the loopback connection by a
+//! background task.
+
+use std::{
+    fmt, io,
+    net::Ipv4Addr,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::{
+    io::{copy_bidirectional, AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{Error, Result};
+use crate::{debug, runtime};
+
+/// Stream usable by network clients.
+///
+/// This trait is automatically implemented for every type
+/// implementing [`AsyncRead`] and [`AsyncWrite`].
+pub trait AsyncStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> AsyncStream for T {}
+
+/// The boxed version of [`AsyncStream`].
+pub type BoxedStream = Box<dyn AsyncStream>;
+
+/// Connector opening custom streams.
+///
+/// Contexts may need to open more than one connection (clients pool,
+/// re-connection), which is why a connector is required rather than
+/// a single stream. See [`OnceStreamConnector`] for using a single
+/// pre-established stream.
+#[async_trait]
+pub trait StreamConnector: Send + Sync {
+    /// Open a new stream to the given server.
+    async fn connect(&self, host: &str, port: u16) -> io::Result<BoxedStream>;
+}
+
+/// Connector opening unix socket streams.
+///
+/// The host and port of the server are ignored, the socket located at
+/// the given path is always used.
+#[cfg(unix)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnixSocketConnector {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocketConnector {
+    /// Create a new connector from the given unix socket path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl StreamConnector for UnixSocketConnector {
+    async fn connect(&self, _host: &str, _port: u16) -> io::Result<BoxedStream> {
+        let stream = tokio::net::UnixStream::connect(&self.path).await?;
+        Ok(Box::new(stream))
+    }
+}
+
+/// Connector using a single pre-established stream.
+///
+/// The stream is given to the first connection only, next
+/// connections fail. It should not be used with contexts opening more
+/// than one connection, like IMAP contexts with a pool size greater
+/// than 1.
+pub struct OnceStreamConnector {
+    stream: Mutex<Option<BoxedStream>>,
+}
+
+impl OnceStreamConnector {
+    /// Create a new connector from the given pre-established stream.
+    pub fn new(stream: impl AsyncStream + 'static) -> Self {
+        Self {
+            stream: Mutex::new(Some(Box::new(stream))),
+        }
+    }
+}
+
+impl fmt::Debug for OnceStreamConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceStreamConnector")
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl StreamConnector for OnceStreamConnector {
+    async fn connect(&self, _host: &str, _port: u16) -> io::Result<BoxedStream> {
+        let stream = match self.stream.lock() {
+            Ok(mut stream) => stream.take(),
+            Err(mut poisoned) => poisoned.get_mut().take(),
+        };
+
+        stream.ok_or_else(|| {
+            let reason = "pre-established stream already used";
+            io::Error::new(io::ErrorKind::NotConnected, reason)
+        })
+    }
+}
+
+/// Shared stream connector.
+///
+/// This wrapper allows contexts and their builders to hold a stream
+/// connector while staying cloneable and comparable. Two shared
+/// connectors are equal if they point to the same connector.
+#[derive(Clone)]
+pub struct SharedStreamConnector(Arc<dyn StreamConnector>);
+
+impl SharedStreamConnector {
+    /// Create a new shared connector from the given connector.
+    pub fn new(connector: impl StreamConnector + 'static) -> Self {
+        Self(Arc::new(connector))
+    }
+
+    /// Open a new stream to the given server, then bridge it to a
+    /// loopback TCP stream.
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let stream = self
+            .0
+            .connect(host, port)
+            .await
+            .map_err(|err| Error::ConnectStreamError(err, host.to_owned(), port))?;
+
+        bridge(stream).await.map_err(Error::BridgeStreamError)
+    }
+}
+
+impl From<Arc<dyn StreamConnector>> for SharedStreamConnector {
+    fn from(connector: Arc<dyn StreamConnector>) -> Self {
+        Self(connector)
+    }
+}
+
+impl fmt::Debug for SharedStreamConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedStreamConnector")
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for SharedStreamConnector {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedStreamConnector {}
+
+/// Bridge the given custom stream to a loopback TCP stream.
+async fn bridge(mut stream: BoxedStream) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let client = TcpStream::connect(listener.local_addr()?).await?;
+    let client_addr = client.local_addr()?;
+
+    let mut server = loop {
+        let (server, peer) = listener.accept().await?;
+
+        // any local process can connect to the listener, only the
+        // connection opened above should be bridged
+        if peer == client_addr {
+            break server;
+        }
+
+        debug!("rejecting unexpected stream bridge connection from {peer}");
+    };
+
+    runtime::spawn(async move {
+        if let Err(err) = copy_bidirectional(&mut server, &mut stream).await {
+            debug!("stream bridge closed: {err}");
+        }
+    });
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::{OnceStreamConnector, SharedStreamConnector};
+
+    #[tokio::test]
+    async fn once_stream_connector() {
+        let (stream, mut remote) = duplex(64);
+        let connector = SharedStreamConnector::new(OnceStreamConnector::new(stream));
+
+        let mut tcp = connector.connect("localhost", 143).await.unwrap();
+
+        tcp.write_all(b"A1 NOOP\r\n").await.unwrap();
+        let mut buf = [0; 9];
+        remote.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"A1 NOOP\r\n");
+
+        remote.write_all(b"A1 OK\r\n").await.unwrap();
+        let mut buf = [0; 7];
+        tcp.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"A1 OK\r\n");
+
+        assert!(connector.connect("localhost", 143).await.is_err());
+        assert_eq!(connector.clone(), connector);
+    }
+}
@@ -0,0 +1,442 @@
+//! Module dedicated to the NNTP client.
+//!
+//! The core structure of this module is the [`NntpClient`], which
+//! implements the read-only subset of the [RFC
+//! 3977](https://www.rfc-editor.org/rfc/rfc3977) needed to browse
+//! newsgroups.
+
+use std::{io, sync::Arc};
+
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+use super::{config::NntpEncryptionKind, Error, Result};
+use crate::{
+    debug,
+    network::{self, config::NetworkConfig},
+};
+
+/// The NNTP stream.
+///
+/// The stream can be either plain TCP or encrypted using TLS.
+pub trait NntpStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> NntpStream for T {}
+
+/// An NNTP status line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NntpStatus {
+    /// The three-digit status code.
+    pub code: u16,
+
+    /// The text following the code.
+    pub text: String,
+}
+
+/// A newsgroup, as returned by the `LIST ACTIVE` command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NntpActiveGroup {
+    /// The name of the newsgroup.
+    pub name: String,
+
+    /// The reported high water mark.
+    pub high: u64,
+
+    /// The reported low water mark.
+    pub low: u64,
+
+    /// The status of the newsgroup (`y` when posting is permitted,
+    /// `n` when not, `m` when moderated).
+    pub status: String,
+}
+
+/// A selected newsgroup, as returned by the `GROUP` command.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NntpGroup {
+    /// The name of the newsgroup.
+    pub name: String,
+
+    /// The estimated number of articles.
+    pub count: u64,
+
+    /// The reported low water mark.
+    pub low: u64,
+
+    /// The reported high water mark.
+    pub high: u64,
+}
+
+/// An article overview, as returned by the `OVER` command.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NntpOverview {
+    /// The article number.
+    pub number: u64,
+    pub subject: String,
+    pub from: String,
+    pub date: String,
+    pub message_id: String,
+    pub references: String,
+    /// The article size in bytes, if given by the server.
+    pub bytes: Option<u64>,
+}
+
+/// The NNTP client.
+pub struct NntpClient {
+    stream: BufStream<Box<dyn NntpStream>>,
+    group: Option<NntpGroup>,
+}
+
+impl NntpClient {
+    /// Connect to the given NNTP server, then read the greeting.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        encryption: &NntpEncryptionKind,
+        network: Option<&NetworkConfig>,
+    ) -> Result<Self> {
+        debug!("connecting to NNTP server {host}:{port} using {encryption}");
+
+        let tcp = network::connect(network, host, port)
+            .await
+            .map_err(|err| Error::ConnectTcpError(err, host.to_owned(), port))?;
+
+        let stream: Box<dyn NntpStream> = match encryption {
+            NntpEncryptionKind::Tls => Box::new(upgrade_tls(tcp, host, port).await?),
+            NntpEncryptionKind::None => Box::new(tcp),
+        };
+
+        let mut client = Self {
+            stream: BufStream::new(stream),
+            group: None,
+        };
+
+        client.expect("greeting", &[200, 201]).await?;
+
+        Ok(client)
+    }
+
+    /// Authenticate using the `AUTHINFO USER/PASS` commands.
+    pub async fn authenticate(&mut self, login: &str, passwd: &str) -> Result<()> {
+        self.write(format!("AUTHINFO USER {login}\r\n")).await?;
+        let status = self.expect("AUTHINFO USER", &[281, 381]).await?;
+
+        if status.code == 381 {
+            self.write(format!("AUTHINFO PASS {passwd}\r\n")).await?;
+            self.expect("AUTHINFO PASS", &[281]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// List newsgroups available on the server.
+    pub async fn list_active(&mut self) -> Result<Vec<NntpActiveGroup>> {
+        self.write("LIST ACTIVE\r\n").await?;
+        self.expect("LIST ACTIVE", &[215]).await?;
+
+        let groups = read_block(&mut self.stream)
+            .await?
+            .iter()
+            .filter_map(|line| {
+                let line = String::from_utf8_lossy(line);
+                let mut fields = line.split_whitespace();
+                let name = fields.next()?.to_owned();
+                let high = fields.next()?.parse().ok()?;
+                let low = fields.next()?.parse().ok()?;
+                let status = fields.next().unwrap_or_default().to_owned();
+                Some(NntpActiveGroup {
+                    name,
+                    high,
+                    low,
+                    status,
+                })
+            })
+            .collect();
+
+        Ok(groups)
+    }
+
+    /// Select the given newsgroup.
+    ///
+    /// The command is not sent again if the newsgroup is already
+    /// selected.
+    pub async fn group(&mut self, name: &str) -> Result<NntpGroup> {
+        if let Some(group) = self.group.as_ref().filter(|g| g.name == name) {
+            return Ok(group.clone());
+        }
+
+        self.write(format!("GROUP {name}\r\n")).await?;
+        let status = self.expect("GROUP", &[211]).await?;
+
+        let mut fields = status.text.split_whitespace();
+        let mut next_number = || -> Result<u64> {
+            fields
+                .next()
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| Error::ParseResponseError(status.text.clone()))
+        };
+
+        let group = NntpGroup {
+            name: name.to_owned(),
+            count: next_number()?,
+            low: next_number()?,
+            high: next_number()?,
+        };
+
+        self.group = Some(group.clone());
+
+        Ok(group)
+    }
+
+    /// Get overviews of the articles of the selected newsgroup, in
+    /// the given inclusive range.
+    pub async fn over(&mut self, low: u64, high: u64) -> Result<Vec<NntpOverview>> {
+        self.write(format!("OVER {low}-{high}\r\n")).await?;
+        let status = self.expect("OVER", &[224, 423]).await?;
+
+        // 423: no articles in that range
+        if status.code == 423 {
+            return Ok(Vec::new());
+        }
+
+        let overviews = read_block(&mut self.stream)
+            .await?
+            .iter()
+            .filter_map(|line| parse_overview(&String::from_utf8_lossy(line)))
+            .collect();
+
+        Ok(overviews)
+    }
+
+    /// Get the full raw article matching the given number, from the
+    /// selected newsgroup.
+    pub async fn article(&mut self, number: u64) -> Result<Vec<u8>> {
+        self.write(format!("ARTICLE {number}\r\n")).await?;
+        self.expect("ARTICLE", &[220]).await?;
+
+        let lines = read_block(&mut self.stream).await?;
+        let mut article = Vec::with_capacity(lines.iter().map(|l| l.len() + 2).sum());
+
+        for line in lines {
+            article.extend_from_slice(&line);
+            article.extend_from_slice(b"\r\n");
+        }
+
+        Ok(article)
+    }
+
+    /// Get the server date, mostly used to check the connection.
+    pub async fn date(&mut self) -> Result<String> {
+        self.write("DATE\r\n").await?;
+        let status = self.expect("DATE", &[111]).await?;
+        Ok(status.text)
+    }
+
+    /// Close the session.
+    pub async fn quit(&mut self) -> Result<()> {
+        self.write("QUIT\r\n").await?;
+        self.expect("QUIT", &[205]).await?;
+        Ok(())
+    }
+
+    async fn write(&mut self, cmd: impl AsRef<[u8]>) -> Result<()> {
+        self.stream
+            .write_all(cmd.as_ref())
+            .await
+            .map_err(Error::WriteCommandError)?;
+        self.stream.flush().await.map_err(Error::WriteCommandError)
+    }
+
+    /// Read a status line, then ensure its code is one of the
+    /// expected ones.
+    async fn expect(&mut self, cmd: &str, codes: &[u16]) -> Result<NntpStatus> {
+        let status = read_status(&mut self.stream).await?;
+
+        if codes.contains(&status.code) {
+            Ok(status)
+        } else {
+            let NntpStatus { code, text } = status;
+            Err(Error::UnexpectedResponseError(cmd.to_owned(), code, text))
+        }
+    }
+}
+
+/// Read a single status line from the given reader.
+pub async fn read_status<R>(reader: &mut R) -> Result<NntpStatus>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let line = read_line(reader).await?;
+    let line = String::from_utf8_lossy(&line);
+
+    let code = line
+        .get(..3)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| Error::ParseResponseError(line.to_string()))?;
+
+    let text = line.get(3..).unwrap_or_default().trim().to_owned();
+
+    Ok(NntpStatus { code, text })
+}
+
+/// Read a multi-line data block from the given reader.
+///
+/// The block is terminated by a line containing a single dot. Lines
+/// starting with a dot have their leading dot removed.
+pub async fn read_block<R>(reader: &mut R) -> Result<Vec<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut lines = Vec::new();
+
+    loop {
+        let mut line = read_line(reader).await?;
+
+        if line == b"." {
+            break Ok(lines);
+        }
+
+        if line.starts_with(b"..") {
+            line.remove(0);
+        }
+
+        lines.push(line);
+    }
+}
+
+/// Read a single line, without its line ending.
+async fn read_line<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let n = reader
+        .read_until(b'\n', &mut line)
+        .await
+        .map_err(Error::ReadResponseError)?;
+
+    if n == 0 {
+        return Err(Error::ConnectionClosedError);
+    }
+
+    while let Some(b'\r' | b'\n') = line.last() {
+        line.pop();
+    }
+
+    Ok(line)
+}
+
+/// Parse a single line of an `OVER` response.
+///
+/// Fields are separated by tabs, in the following order: number,
+/// subject, from, date, message-id, references, bytes and lines.
+fn parse_overview(line: &str) -> Option<NntpOverview> {
+    let mut fields = line.split('\t');
+
+    Some(NntpOverview {
+        number: fields.next()?.parse().ok()?,
+        subject: fields.next()?.to_owned(),
+        from: fields.next()?.to_owned(),
+        date: fields.next()?.to_owned(),
+        message_id: fields.next()?.to_owned(),
+        references: fields.next().unwrap_or_default().to_owned(),
+        bytes: fields.next().and_then(|bytes| bytes.parse().ok()),
+    })
+}
+
+async fn upgrade_tls(tcp: TcpStream, host: &str, port: u16) -> Result<impl NntpStream> {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| {
+            let err = io::Error::new(io::ErrorKind::Other, err);
+            Error::ConnectTlsError(err, host.to_owned(), port)
+        })?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let server_name = ServerName::try_from(host.to_owned())
+        .map_err(|err| Error::InvalidServerNameError(err, host.to_owned()))?;
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|err| Error::ConnectTlsError(err, host.to_owned(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_overview, read_block, read_status, NntpOverview, NntpStatus};
+
+    #[tokio::test]
+    async fn read_status_line() {
+        let mut reader: &[u8] = b"211 1234 3000234 3002322 misc.test\r\n";
+
+        assert_eq!(
+            read_status(&mut reader).await.unwrap(),
+            NntpStatus {
+                code: 211,
+                text: "1234 3000234 3002322 misc.test".into(),
+            }
+        );
+
+        let mut reader: &[u8] = b"hello\r\n";
+        assert!(read_status(&mut reader).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn read_data_block() {
+        let mut reader: &[u8] = concat!(
+            "Subject: test\r\n",
+            "\r\n",
+            "..leading dot\r\n",
+            ".\r\n",
+            "205 bye\r\n",
+        )
+        .as_bytes();
+
+        let lines = read_block(&mut reader).await.unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                b"Subject: test".to_vec(),
+                b"".to_vec(),
+                b".leading dot".to_vec(),
+            ]
+        );
+
+        let mut reader: &[u8] = b"Subject: test\r\n";
+        assert!(read_block(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn parse_overview_line() {
+        let line = concat!(
+            "3000234\tI am just a test article\t\"Demo User\" <nobody@example.com>\t",
+            "6 Oct 1998 04:38:40 -0500\t<45223423@example.com>\t<45454@example.net>\t1234\t17",
+        );
+
+        assert_eq!(
+            parse_overview(line),
+            Some(NntpOverview {
+                number: 3000234,
+                subject: "I am just a test article".into(),
+                from: "\"Demo User\" <nobody@example.com>".into(),
+                date: "6 Oct 1998 04:38:40 -0500".into(),
+                message_id: "<45223423@example.com>".into(),
+                references: "<45454@example.net>".into(),
+                bytes: Some(1234),
+            })
+        );
+
+        assert_eq!(parse_overview("invalid"), None);
+    }
+}
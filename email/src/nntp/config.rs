@@ -0,0 +1,92 @@
+//! Module dedicated to the NNTP backend configuration.
+//!
+//! This module contains the configuration specific to the NNTP
+//! backend.
+
+use std::{fmt, path::PathBuf};
+
+use crate::account::config::passwd::PasswdConfig;
+
+/// The default NNTP port over SSL/TLS.
+pub const DEFAULT_NNTPS_PORT: u16 = 563;
+
+/// The default NNTP port without encryption.
+pub const DEFAULT_NNTP_PORT: u16 = 119;
+
+/// The NNTP backend configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct NntpConfig {
+    /// The NNTP server host name.
+    pub host: String,
+
+    /// The NNTP server host port.
+    ///
+    /// Defaults to 563 when using SSL/TLS, otherwise to 119.
+    pub port: Option<u16>,
+
+    /// The NNTP encryption protocol to use.
+    ///
+    /// Supported encryption: SSL/TLS or none. Defaults to SSL/TLS.
+    pub encryption: Option<NntpEncryptionKind>,
+
+    /// The NNTP server login.
+    ///
+    /// Most public servers (like gmane or lore mirrors) do not
+    /// require authentication.
+    pub login: Option<String>,
+
+    /// The NNTP server password.
+    ///
+    /// Only used when a login is defined.
+    pub passwd: Option<PasswdConfig>,
+
+    /// The path to the newsrc file storing the read state of
+    /// articles.
+    ///
+    /// Defaults to keeping the read state in memory only, which
+    /// means that it is lost when the backend is dropped.
+    pub newsrc: Option<PathBuf>,
+}
+
+impl NntpConfig {
+    /// Get the NNTP encryption, or return the default one.
+    pub fn encryption(&self) -> NntpEncryptionKind {
+        self.encryption.clone().unwrap_or_default()
+    }
+
+    /// Get the NNTP server port, or return the default one matching
+    /// the encryption.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.encryption() {
+            NntpEncryptionKind::Tls => DEFAULT_NNTPS_PORT,
+            NntpEncryptionKind::None => DEFAULT_NNTP_PORT,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum NntpEncryptionKind {
+    #[default]
+    #[cfg_attr(feature = "derive", serde(alias = "ssl"))]
+    Tls,
+    None,
+}
+
+impl fmt::Display for NntpEncryptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tls => write!(f, "SSL/TLS"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+use tokio_rustls::rustls::pki_types::InvalidDnsNameError;
+
+use crate::{network, AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot connect to NNTP server {1}:{2} using TCP")]
+    ConnectTcpError(#[source] network::Error, String, u16),
+    #[error("cannot connect to NNTP server {1}:{2} using SSL/TLS")]
+    ConnectTlsError(#[source] io::Error, String, u16),
+    #[error("cannot use {1} as NNTP server name")]
+    InvalidServerNameError(#[source] InvalidDnsNameError, String),
+
+    #[error("cannot write NNTP command")]
+    WriteCommandError(#[source] io::Error),
+    #[error("cannot read NNTP response")]
+    ReadResponseError(#[source] io::Error),
+    #[error("cannot read NNTP response: connection closed")]
+    ConnectionClosedError,
+    #[error("cannot parse NNTP response: {0}")]
+    ParseResponseError(String),
+    #[error("NNTP server replied to {0} with code {1}: {2}")]
+    UnexpectedResponseError(String, u16, String),
+
+    #[error("cannot get NNTP password")]
+    GetPasswdError(#[source] secret::Error),
+    #[error("cannot get NNTP password: password is empty")]
+    GetPasswdEmptyError,
+    #[error("cannot parse NNTP article number {0}")]
+    ParseArticleNumberError(String),
+
+    #[error("cannot read NNTP newsrc file {1}")]
+    ReadNewsrcError(#[source] io::Error, PathBuf),
+    #[error("cannot write NNTP newsrc file {1}")]
+    WriteNewsrcError(#[source] io::Error, PathBuf),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
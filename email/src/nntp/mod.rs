@@ -0,0 +1,223 @@
+//! # NNTP backend
+//!
+//! Module dedicated to the read-mostly NNTP backend. Newsgroups are
+//! exposed as folders, and articles as messages, so that newsgroup
+//! gateways of mailing lists (like gmane or lore) can be browsed
+//! through the same [`Backend`](crate::backend::Backend) API.
+//!
+//! Newsgroups have no notion of flags: the read state of articles is
+//! stored locally, see [`newsrc`].
+
+pub mod client;
+pub mod config;
+mod error;
+pub mod newsrc;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use self::{client::NntpClient, config::NntpConfig, newsrc::Newsrc};
+use crate::{
+    account::config::AccountConfig,
+    backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
+        context::{BackendContext, BackendContextBuilder},
+        feature::{BackendFeature, CheckUp},
+    },
+    envelope::{
+        list::{nntp::ListNntpEnvelopes, ListEnvelopes},
+        Id,
+    },
+    flag::{
+        add::{nntp::AddNntpFlags, AddFlags},
+        remove::{nntp::RemoveNntpFlags, RemoveFlags},
+        set::{nntp::SetNntpFlags, SetFlags},
+    },
+    folder::list::{nntp::ListNntpFolders, ListFolders},
+    info,
+    message::{
+        get::{nntp::GetNntpMessages, GetMessages},
+        peek::{nntp::PeekNntpMessages, PeekMessages},
+    },
+    AnyResult,
+};
+
+/// The NNTP backend context.
+///
+/// This context is unsync, which means it cannot be shared between
+/// threads. For the sync version, see [`NntpContextSync`].
+pub struct NntpContext {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The NNTP configuration.
+    pub nntp_config: Arc<NntpConfig>,
+
+    /// The NNTP client.
+    pub client: NntpClient,
+
+    /// The read state of articles.
+    pub newsrc: Newsrc,
+}
+
+impl NntpContext {
+    /// Mark the given articles of the given newsgroup as read or
+    /// unread, then save the read state if a newsrc file is defined.
+    pub async fn mark_articles(&mut self, group: &str, id: &Id, read: bool) -> Result<()> {
+        for id in id.iter() {
+            let number = id
+                .parse()
+                .map_err(|_| Error::ParseArticleNumberError(id.to_owned()))?;
+
+            if read {
+                self.newsrc.mark_read(group, number);
+            } else {
+                self.newsrc.mark_unread(group, number);
+            }
+        }
+
+        if let Some(path) = self.nntp_config.newsrc.as_ref() {
+            self.newsrc.save(path).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The sync version of the NNTP backend context.
+///
+/// This is just an NNTP client wrapped into a mutex, so the same
+/// NNTP client can be shared and updated across multiple threads.
+pub type NntpContextSync = Arc<Mutex<NntpContext>>;
+
+impl BackendContext for NntpContextSync {}
+
+/// The NNTP context builder.
+#[derive(Clone)]
+pub struct NntpContextBuilder {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The NNTP configuration.
+    nntp_config: Arc<NntpConfig>,
+}
+
+impl NntpContextBuilder {
+    pub fn new(account_config: Arc<AccountConfig>, nntp_config: Arc<NntpConfig>) -> Self {
+        Self {
+            account_config,
+            nntp_config,
+        }
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for NntpContextBuilder {
+    type Context = NntpContextSync;
+
+    fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
+        Some(Arc::new(CheckUpNntp::some_new_boxed))
+    }
+
+    fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>> {
+        Some(Arc::new(ListNntpFolders::some_new_boxed))
+    }
+
+    fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelopes>> {
+        Some(Arc::new(ListNntpEnvelopes::some_new_boxed))
+    }
+
+    fn peek_messages(&self) -> Option<BackendFeature<Self::Context, dyn PeekMessages>> {
+        Some(Arc::new(PeekNntpMessages::some_new_boxed))
+    }
+
+    fn get_messages(&self) -> Option<BackendFeature<Self::Context, dyn GetMessages>> {
+        Some(Arc::new(GetNntpMessages::some_new_boxed))
+    }
+
+    fn add_flags(&self) -> Option<BackendFeature<Self::Context, dyn AddFlags>> {
+        Some(Arc::new(AddNntpFlags::some_new_boxed))
+    }
+
+    fn set_flags(&self) -> Option<BackendFeature<Self::Context, dyn SetFlags>> {
+        Some(Arc::new(SetNntpFlags::some_new_boxed))
+    }
+
+    fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>> {
+        Some(Arc::new(RemoveNntpFlags::some_new_boxed))
+    }
+
+    /// Build an NNTP sync client.
+    ///
+    /// The NNTP client is created at this moment. Authentication is
+    /// performed only if a login is defined.
+    async fn build(self) -> AnyResult<Self::Context> {
+        info!("building new NNTP context");
+
+        let config = &self.nntp_config;
+        let network = self.account_config.network.as_ref();
+        let encryption = config.encryption();
+        let mut client =
+            NntpClient::connect(&config.host, config.port(), &encryption, network).await?;
+
+        if let Some(login) = config.login.as_ref() {
+            let passwd = match config.passwd.as_ref() {
+                Some(passwd) => passwd.get().await.map_err(Error::GetPasswdError)?,
+                None => String::new(),
+            };
+            let passwd = passwd.lines().next().ok_or(Error::GetPasswdEmptyError)?;
+            client.authenticate(login, passwd).await?;
+        }
+
+        let newsrc = match config.newsrc.as_ref() {
+            Some(path) => Newsrc::load(path).await?,
+            None => Newsrc::default(),
+        };
+
+        let ctx = NntpContext {
+            account_config: self.account_config,
+            nntp_config: self.nntp_config,
+            client,
+            newsrc,
+        };
+
+        Ok(Arc::new(Mutex::new(ctx)))
+    }
+}
+
+#[derive(Clone)]
+pub struct CheckUpNntp {
+    ctx: NntpContextSync,
+}
+
+impl CheckUpNntp {
+    pub fn new(ctx: &NntpContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NntpContextSync) -> Box<dyn CheckUp> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NntpContextSync) -> Option<Box<dyn CheckUp>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl CheckUp for CheckUpNntp {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+        let mut ctx = self.ctx.lock().await;
+
+        report
+            .probe(CheckUpProbeKind::Session, ctx.client.date())
+            .await;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,217 @@
+//! Module dedicated to the NNTP read state.
+//!
+//! Newsgroups have no notion of flags, so the read state of articles
+//! is stored locally using the newsrc format shared by most
+//! newsreaders. Each line contains a newsgroup name, followed by `:`
+//! when subscribed (`!` otherwise) and the ranges of read articles:
+//!
+//! ```text
+//! comp.lang.rust: 1-120,124,130-131
+//! ```
+
+use std::{collections::BTreeMap, fmt, io, path::Path};
+
+use tokio::fs;
+
+use super::{Error, Result};
+
+/// The read state of newsgroup articles.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Newsrc {
+    groups: BTreeMap<String, NewsrcGroup>,
+}
+
+impl Newsrc {
+    /// Parse the read state from the given newsrc content.
+    ///
+    /// Parsing is lenient: invalid lines and ranges are ignored.
+    pub fn parse(input: &str) -> Self {
+        let mut groups = BTreeMap::new();
+
+        for line in input.lines() {
+            let Some(pos) = line.find([':', '!']) else {
+                continue;
+            };
+
+            let name = line[..pos].trim();
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let mut group = NewsrcGroup {
+                unsubscribed: line[pos..].starts_with('!'),
+                read: Vec::new(),
+            };
+
+            for range in line[pos + 1..].split(',') {
+                let range = range.trim();
+                let range = match range.split_once('-') {
+                    Some((low, high)) => low.trim().parse().ok().zip(high.trim().parse().ok()),
+                    None => range.parse().ok().map(|number| (number, number)),
+                };
+
+                if let Some((low, high)) = range {
+                    group.insert(low, high);
+                }
+            }
+
+            groups.insert(name.to_owned(), group);
+        }
+
+        Self { groups }
+    }
+
+    /// Load the read state from the given newsrc file.
+    ///
+    /// A missing file is considered as an empty read state.
+    pub async fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path).await {
+            Ok(content) => Ok(Self::parse(&content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::ReadNewsrcError(err, path.to_owned())),
+        }
+    }
+
+    /// Save the read state to the given newsrc file.
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())
+            .await
+            .map_err(|err| Error::WriteNewsrcError(err, path.to_owned()))
+    }
+
+    /// Return `true` if the given article of the given newsgroup has
+    /// been read.
+    pub fn is_read(&self, group: &str, number: u64) -> bool {
+        self.groups
+            .get(group)
+            .is_some_and(|group| group.contains(number))
+    }
+
+    /// Mark the given article of the given newsgroup as read.
+    pub fn mark_read(&mut self, group: &str, number: u64) {
+        self.groups
+            .entry(group.to_owned())
+            .or_default()
+            .insert(number, number)
+    }
+
+    /// Mark the given article of the given newsgroup as unread.
+    pub fn mark_unread(&mut self, group: &str, number: u64) {
+        if let Some(group) = self.groups.get_mut(group) {
+            group.remove(number)
+        }
+    }
+}
+
+impl fmt::Display for Newsrc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, group) in &self.groups {
+            let sep = if group.unsubscribed { '!' } else { ':' };
+            write!(f, "{name}{sep}")?;
+
+            for (i, (low, high)) in group.read.iter().enumerate() {
+                let sep = if i == 0 { " " } else { "," };
+
+                if low == high {
+                    write!(f, "{sep}{low}")?;
+                } else {
+                    write!(f, "{sep}{low}-{high}")?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The read state of a newsgroup.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct NewsrcGroup {
+    unsubscribed: bool,
+
+    /// The sorted, non-overlapping ranges of read articles.
+    read: Vec<(u64, u64)>,
+}
+
+impl NewsrcGroup {
+    fn contains(&self, number: u64) -> bool {
+        self.read
+            .iter()
+            .any(|(low, high)| (*low..=*high).contains(&number))
+    }
+
+    fn insert(&mut self, low: u64, high: u64) {
+        if low > high {
+            return;
+        }
+
+        self.read.push((low, high));
+        self.read.sort_unstable();
+
+        let mut read: Vec<(u64, u64)> = Vec::with_capacity(self.read.len());
+
+        for (low, high) in self.read.drain(..) {
+            match read.last_mut() {
+                Some((_, last)) if low <= last.saturating_add(1) => *last = high.max(*last),
+                _ => read.push((low, high)),
+            }
+        }
+
+        self.read = read;
+    }
+
+    fn remove(&mut self, number: u64) {
+        let mut read = Vec::with_capacity(self.read.len() + 1);
+
+        for (low, high) in self.read.drain(..) {
+            if !(low..=high).contains(&number) {
+                read.push((low, high));
+                continue;
+            }
+
+            if low < number {
+                read.push((low, number - 1));
+            }
+
+            if number < high {
+                read.push((number + 1, high));
+            }
+        }
+
+        self.read = read;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Newsrc;
+
+    #[test]
+    fn newsrc_read_state() {
+        let mut newsrc = Newsrc::parse(concat!(
+            "comp.lang.rust: 1-5,7, 6 ,10-12\n",
+            "alt.test! 3\n",
+            "invalid line\n",
+        ));
+
+        assert!(newsrc.is_read("comp.lang.rust", 6));
+        assert!(!newsrc.is_read("comp.lang.rust", 8));
+        assert!(!newsrc.is_read("comp.lang.unknown", 1));
+
+        newsrc.mark_read("comp.lang.rust", 8);
+        newsrc.mark_unread("comp.lang.rust", 11);
+        newsrc.mark_read("gmane.test", 1);
+
+        let expected = concat!(
+            "alt.test! 3\n",
+            "comp.lang.rust: 1-8,10,12\n",
+            "gmane.test: 1\n",
+        );
+
+        assert_eq!(newsrc.to_string(), expected);
+        assert_eq!(Newsrc::parse(expected), newsrc);
+    }
+}
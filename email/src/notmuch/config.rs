@@ -3,7 +3,10 @@
 //! This module contains the configuration specific to the Notmuch
 //! backend.
 
-use std::path::{Path, PathBuf};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
 
 use notmuch::{Database, DatabaseMode};
 use shellexpand_utils::shellexpand_path;
@@ -42,8 +45,18 @@ pub struct NotmuchConfig {
     pub config_path: Option<PathBuf>,
 
     /// Override the default Notmuch profile name.
+    ///
+    /// Defaults to the `NOTMUCH_PROFILE` environment variable, like
+    /// other Notmuch front-ends.
     pub profile: Option<String>,
 
+    /// Override the tags excluded from envelope listings.
+    ///
+    /// Defaults to the `search.exclude_tags` option of the Notmuch
+    /// configuration file. Envelopes with an excluded tag are still
+    /// listed when the search query explicitly mentions the tag.
+    pub exclude_tags: Option<Vec<String>>,
+
     #[cfg_attr(feature = "derive", serde(default))]
     pub maildirpp: bool,
 }
@@ -63,21 +76,48 @@ pub fn get_default_database_path() -> Result<PathBuf> {
     }
 
     /// Try to get the reference to the Notmuch database path.
+    ///
+    /// Tries `database_path` first, then the database path of the
+    /// discovered Notmuch configuration file, otherwise falls back to
+    /// the default path of the Notmuch library.
     pub fn try_get_database_path(&self) -> Result<PathBuf> {
-        match self.database_path.as_ref() {
-            Some(path) => Ok(shellexpand_path(path)),
+        if let Some(path) = self.database_path.as_ref() {
+            return Ok(shellexpand_path(path));
+        }
+
+        match self.discover_profile_config()?.database_path {
+            Some(path) => Ok(path),
             None => Self::get_default_database_path(),
         }
     }
 
     /// Try to get the reference to the Maildir path.
     ///
-    /// Tries `maildir_path` first, otherwise falls back to
-    /// `database_path`.
+    /// Tries `maildir_path` first, then the mail root of the
+    /// discovered Notmuch configuration file, otherwise falls back to
+    /// the database path.
     pub fn try_get_maildir_path(&self) -> Result<PathBuf> {
-        match self.maildir_path.as_ref() {
-            Some(path) => Ok(shellexpand_path(path)),
-            None => self.try_get_database_path(),
+        if let Some(path) = self.maildir_path.as_ref() {
+            return Ok(shellexpand_path(path));
+        }
+
+        if self.database_path.is_none() {
+            if let Some(path) = self.discover_profile_config()?.mail_root {
+                return Ok(path);
+            }
+        }
+
+        self.try_get_database_path()
+    }
+
+    /// Try to get the tags excluded from envelope listings.
+    ///
+    /// Tries `exclude_tags` first, otherwise falls back to the
+    /// excluded tags of the discovered Notmuch configuration file.
+    pub fn try_get_exclude_tags(&self) -> Result<Vec<String>> {
+        match self.exclude_tags.as_ref() {
+            Some(tags) => Ok(tags.clone()),
+            None => Ok(self.discover_profile_config()?.exclude_tags),
         }
     }
 
@@ -90,4 +130,183 @@ pub fn find_config_path(&self) -> Option<&Path> {
     pub fn find_profile(&self) -> Option<&str> {
         self.profile.as_deref()
     }
+
+    /// Find the Notmuch profile name, from the configuration first,
+    /// then from the `NOTMUCH_PROFILE` environment variable.
+    pub fn find_profile_name(&self) -> Option<String> {
+        match self.profile.as_ref() {
+            Some(profile) => Some(profile.clone()),
+            None => env::var("NOTMUCH_PROFILE")
+                .ok()
+                .filter(|profile| !profile.is_empty()),
+        }
+    }
+
+    /// Discover the path of the Notmuch configuration file, the same
+    /// way the Notmuch library does.
+    ///
+    /// Tries `config_path` first, then the `NOTMUCH_CONFIG`
+    /// environment variable, then
+    /// `$XDG_CONFIG_HOME/notmuch/<profile>/config` and finally
+    /// `$HOME/.notmuch-config[.<profile>]`. The profile defaults to
+    /// `default`.
+    pub fn discover_config_path(&self) -> Option<PathBuf> {
+        if let Some(path) = self.config_path.as_ref() {
+            return Some(shellexpand_path(path));
+        }
+
+        if let Some(path) = env::var_os("NOTMUCH_CONFIG").filter(|path| !path.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+
+        let profile = self.find_profile_name();
+        let home = env::var_os("HOME").map(PathBuf::from);
+
+        let xdg_config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".config")));
+
+        if let Some(dir) = xdg_config_dir {
+            let path = dir
+                .join("notmuch")
+                .join(profile.as_deref().unwrap_or("default"))
+                .join("config");
+
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+
+        let path = match profile {
+            Some(profile) => home?.join(format!(".notmuch-config.{profile}")),
+            None => home?.join(".notmuch-config"),
+        };
+
+        path.is_file().then_some(path)
+    }
+
+    /// Discover then parse the Notmuch configuration file.
+    ///
+    /// Returns an empty configuration if no file could be found.
+    ///
+    /// See [`NotmuchConfig::discover_config_path`].
+    pub fn discover_profile_config(&self) -> Result<NotmuchProfileConfig> {
+        let Some(path) = self.discover_config_path() else {
+            return Ok(Default::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(NotmuchProfileConfig::parse(&content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => Err(Error::ReadConfigError(err, path)),
+        }
+    }
+}
+
+/// The subset of the Notmuch configuration file used by the Notmuch
+/// backend.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct NotmuchProfileConfig {
+    /// The `database.path` option.
+    ///
+    /// Relative paths are resolved from the home directory.
+    pub database_path: Option<PathBuf>,
+
+    /// The `database.mail_root` option.
+    ///
+    /// Relative paths are resolved from the home directory.
+    pub mail_root: Option<PathBuf>,
+
+    /// The `search.exclude_tags` option.
+    pub exclude_tags: Vec<String>,
+}
+
+impl NotmuchProfileConfig {
+    /// Parse the given Notmuch configuration file content.
+    ///
+    /// Unknown sections and options are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_lowercase();
+                continue;
+            }
+
+            let Some((key, val)) = line.split_once('=') else {
+                continue;
+            };
+
+            let val = val.trim();
+
+            match (section.as_str(), key.trim()) {
+                ("database", "path") if !val.is_empty() => {
+                    config.database_path = Some(resolve_home_path(val));
+                }
+                ("database", "mail_root") if !val.is_empty() => {
+                    config.mail_root = Some(resolve_home_path(val));
+                }
+                ("search", "exclude_tags") => {
+                    config.exclude_tags = val
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect();
+                }
+                _ => (),
+            }
+        }
+
+        config
+    }
+}
+
+/// Resolve the given path from the home directory, if relative.
+fn resolve_home_path(path: &str) -> PathBuf {
+    let path = shellexpand_path(path);
+
+    match env::var_os("HOME") {
+        Some(home) if path.is_relative() => PathBuf::from(home).join(path),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use super::NotmuchProfileConfig;
+
+    #[test]
+    fn parse_profile_config() {
+        let config = NotmuchProfileConfig::parse(concat!(
+            "# comment\n",
+            "[database]\n",
+            "path=/tmp/mail\n",
+            "mail_root = mail\n",
+            "\n",
+            "[search]\n",
+            "exclude_tags=deleted; spam;\n",
+            "[new]\n",
+            "path=ignored\n",
+        ));
+
+        let home = PathBuf::from(env::var_os("HOME").unwrap_or_default());
+
+        let expected = NotmuchProfileConfig {
+            database_path: Some(PathBuf::from("/tmp/mail")),
+            mail_root: Some(home.join("mail")),
+            exclude_tags: vec!["deleted".into(), "spam".into()],
+        };
+
+        assert_eq!(config, expected);
+    }
 }
@@ -1,4 +1,4 @@
-use std::{any::Any, result};
+use std::{any::Any, io, path::PathBuf, result};
 
 use thiserror::Error;
 
@@ -18,6 +18,8 @@ pub enum Error {
     ExecuteQueryError(#[source] notmuch::Error),
     #[error("cannot close notmuch database")]
     CloseDatabaseError(#[source] notmuch::Error),
+    #[error("cannot read notmuch configuration file at {1}")]
+    ReadConfigError(#[source] io::Error, PathBuf),
 }
 
 impl AnyError for Error {
@@ -15,6 +15,7 @@
 use crate::{
     account::config::AccountConfig,
     backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
@@ -24,12 +25,14 @@
     },
     flag::{
         add::{notmuch::AddNotmuchFlags, AddFlags},
+        list::{notmuch::ListNotmuchFlags, ListFlags},
         remove::{notmuch::RemoveNotmuchFlags, RemoveFlags},
         set::{notmuch::SetNotmuchFlags, SetFlags},
     },
     folder::{
         add::{notmuch::AddNotmuchFolder, AddFolder},
         list::{notmuch::ListNotmuchFolders, ListFolders},
+        stats::{notmuch::GetNotmuchFolderStats, GetFolderStats},
     },
     info,
     maildir::{config::MaildirConfig, MaildirContext},
@@ -61,6 +64,9 @@ pub struct NotmuchContext {
 
     /// The Maildir context associated to the Notmuch database.
     pub mdir_ctx: MaildirContext,
+
+    /// The tags excluded from envelope listings.
+    pub exclude_tags: Vec<String>,
 }
 
 impl NotmuchContext {
@@ -83,6 +89,16 @@ pub fn open_db(&self) -> Result<Database> {
     pub fn maildirpp(&self) -> bool {
         self.notmuch_config.maildirpp
     }
+
+    /// Exclude the excluded tags from the given query, unless the
+    /// query explicitly mentions them.
+    pub fn exclude_tags_from_query(&self, query: &mut String) {
+        for tag in &self.exclude_tags {
+            if !query.contains(&format!("tag:{tag}")) && !query.contains(&format!("tag:{tag:?}")) {
+                query.push_str(&format!(" and not tag:{tag:?}"));
+            }
+        }
+    }
 }
 
 /// The sync version of the Notmuch backend context.
@@ -155,6 +171,10 @@ fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>>
         Some(Arc::new(ListNotmuchFolders::some_new_boxed))
     }
 
+    fn get_folder_stats(&self) -> Option<BackendFeature<Self::Context, dyn GetFolderStats>> {
+        Some(Arc::new(GetNotmuchFolderStats::some_new_boxed))
+    }
+
     // TODO
     // fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
     //     Some(Arc::new(ExpungeNotmuchFolder::some_new_boxed))
@@ -195,6 +215,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveNotmuchFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListNotmuchFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddNotmuchMessage::some_new_boxed))
     }
@@ -243,6 +267,7 @@ async fn build(self) -> AnyResult<Self::Context> {
             account_config: self.account_config.clone(),
             notmuch_config: self.notmuch_config.clone(),
             mdir_ctx,
+            exclude_tags: self.notmuch_config.try_get_exclude_tags()?,
         };
 
         Ok(NotmuchContextSync {
@@ -274,16 +299,21 @@ pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn CheckUp>> {
 
 #[async_trait]
 impl CheckUp for CheckUpNotmuch {
-    async fn check_up(&self) -> AnyResult<()> {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
         let ctx = self.ctx.lock().await;
 
-        let db = ctx.open_db()?;
-        db.create_query("*")
-            .map_err(Error::CreateQueryError)?
-            .count_messages()
-            .map_err(Error::ExecuteQueryError)?;
-        db.close().map_err(Error::CloseDatabaseError)?;
+        let count = async {
+            let db = ctx.open_db()?;
+            db.create_query("*")
+                .map_err(Error::CreateQueryError)?
+                .count_messages()
+                .map_err(Error::ExecuteQueryError)?;
+            db.close().map_err(Error::CloseDatabaseError)
+        };
+
+        report.probe(CheckUpProbeKind::FolderAccess, count).await;
 
-        Ok(())
+        Ok(report)
     }
 }
@@ -0,0 +1,127 @@
+//! Module dedicated to the remote Notmuch backend configuration.
+//!
+//! This module contains the configuration specific to the remote
+//! Notmuch backend.
+
+use process::Command;
+
+/// The default SSH command.
+pub const DEFAULT_SSH_CMD: &str = "ssh";
+
+/// The default Notmuch command run on the remote host.
+pub const DEFAULT_NOTMUCH_CMD: &str = "notmuch";
+
+/// The remote Notmuch backend config.
+///
+/// Notmuch queries are executed on a remote host over SSH, the same
+/// way `notmuch-remote` setups do.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct NotmuchRemoteConfig {
+    /// The SSH destination of the remote host.
+    ///
+    /// It can be anything understood by the SSH command, like
+    /// `user@example.com` or a host alias defined in the SSH
+    /// configuration.
+    pub host: String,
+
+    /// Override the default SSH command.
+    ///
+    /// Useful to pass additional options, like `ssh -p 2222`.
+    /// Defaults to `ssh`.
+    pub ssh_cmd: Option<String>,
+
+    /// Override the default Notmuch command run on the remote host.
+    ///
+    /// Useful when notmuch is not in the `PATH` of the remote host,
+    /// or to pass a custom configuration. Defaults to `notmuch`.
+    pub notmuch_cmd: Option<String>,
+
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub maildirpp: bool,
+}
+
+impl NotmuchRemoteConfig {
+    /// Get the SSH command, or return the default one.
+    pub fn ssh_cmd(&self) -> &str {
+        self.ssh_cmd.as_deref().unwrap_or(DEFAULT_SSH_CMD)
+    }
+
+    /// Get the remote Notmuch command, or return the default one.
+    pub fn notmuch_cmd(&self) -> &str {
+        self.notmuch_cmd.as_deref().unwrap_or(DEFAULT_NOTMUCH_CMD)
+    }
+
+    /// Build the local command running Notmuch with the given
+    /// arguments on the remote host.
+    ///
+    /// SSH passes the remote command to the remote shell, so
+    /// arguments are quoted twice: once for the remote shell, and
+    /// once for the local one.
+    pub fn build_cmd(&self, args: impl IntoIterator<Item = impl AsRef<str>>) -> Command {
+        let mut remote_cmd = self.notmuch_cmd().to_owned();
+
+        for arg in args {
+            remote_cmd.push(' ');
+            remote_cmd.push_str(&shell_quote(arg.as_ref()));
+        }
+
+        let cmd = format!(
+            "{} {} {}",
+            self.ssh_cmd(),
+            shell_quote(&self.host),
+            shell_quote(&remote_cmd),
+        );
+
+        Command::from(cmd)
+    }
+}
+
+/// Quote the given string so that it is interpreted literally by
+/// POSIX shells.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r#"'\''"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shell_quote, NotmuchRemoteConfig};
+
+    #[test]
+    fn quote() {
+        assert_eq!(shell_quote("abc"), "'abc'");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+    }
+
+    #[test]
+    fn build_cmd() {
+        let config = NotmuchRemoteConfig {
+            host: String::from("user@localhost"),
+            ..Default::default()
+        };
+
+        let cmd = config.build_cmd(["search", "folder:\"INBOX\""]);
+        assert_eq!(
+            cmd.to_string(),
+            r#"ssh 'user@localhost' 'notmuch '\''search'\'' '\''folder:"INBOX"'\'''"#
+        );
+
+        let config = NotmuchRemoteConfig {
+            host: String::from("remote"),
+            ssh_cmd: Some(String::from("ssh -p 2222")),
+            notmuch_cmd: Some(String::from("/opt/bin/notmuch")),
+            ..Default::default()
+        };
+
+        let cmd = config.build_cmd(["count"]);
+        assert_eq!(
+            cmd.to_string(),
+            r#"ssh -p 2222 'remote' '/opt/bin/notmuch '\''count'\'''"#
+        );
+    }
+}
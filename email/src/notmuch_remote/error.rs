@@ -0,0 +1,31 @@
+use std::{any::Any, result};
+
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot execute remote notmuch command {1:?}")]
+    ExecuteCommandError(#[source] process::Error, Vec<String>),
+    #[error("cannot parse remote notmuch JSON output")]
+    ParseJsonError(#[source] serde_json::Error),
+    #[error("cannot find remote notmuch message {1} from folder {0}")]
+    FindMessageEmptyError(String, String),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,276 @@
+//! # Remote Notmuch backend
+//!
+//! Module dedicated to the remote Notmuch backend. Notmuch queries
+//! are executed on a remote host over SSH, using the Notmuch command
+//! line interface, which allows to browse a Notmuch database without
+//! having to synchronize the whole Maildir locally.
+
+pub mod config;
+mod error;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use self::config::NotmuchRemoteConfig;
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use crate::{
+    account::config::AccountConfig,
+    backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
+        context::{BackendContext, BackendContextBuilder},
+        feature::{BackendFeature, CheckUp},
+    },
+    debug,
+    envelope::list::{notmuch_remote::ListNotmuchRemoteEnvelopes, ListEnvelopes},
+    info,
+    message::{
+        get::{notmuch_remote::GetNotmuchRemoteMessages, GetMessages},
+        peek::{notmuch_remote::PeekNotmuchRemoteMessages, PeekMessages},
+    },
+    AnyResult,
+};
+
+/// The remote Notmuch backend context.
+///
+/// The context does not hold any connection: a new SSH command is
+/// spawned for every action.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NotmuchRemoteContext {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The remote Notmuch configuration.
+    pub notmuch_remote_config: Arc<NotmuchRemoteConfig>,
+}
+
+impl NotmuchRemoteContext {
+    pub fn new(
+        account_config: Arc<AccountConfig>,
+        notmuch_remote_config: Arc<NotmuchRemoteConfig>,
+    ) -> Self {
+        Self {
+            account_config,
+            notmuch_remote_config,
+        }
+    }
+
+    pub fn maildirpp(&self) -> bool {
+        self.notmuch_remote_config.maildirpp
+    }
+
+    /// Run Notmuch on the remote host with the given arguments, and
+    /// return its standard output.
+    pub async fn run(&self, args: &[String]) -> Result<Vec<u8>> {
+        debug!("running remote notmuch command with args {args:?}");
+
+        let output = self
+            .notmuch_remote_config
+            .build_cmd(args)
+            .run()
+            .await
+            .map_err(|err| Error::ExecuteCommandError(err, args.to_vec()))?;
+
+        Ok(output.into())
+    }
+
+    /// Search identifiers of messages matching the given query, from
+    /// the most recent to the oldest one.
+    ///
+    /// A limit of 0 means no limit.
+    pub async fn search_ids(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let mut args = vec![
+            String::from("search"),
+            String::from("--format=text0"),
+            String::from("--output=messages"),
+            String::from("--sort=newest-first"),
+            format!("--offset={offset}"),
+        ];
+
+        if limit > 0 {
+            args.push(format!("--limit={limit}"));
+        }
+
+        args.push(String::from("--"));
+        args.push(query.to_owned());
+
+        let output = self.run(&args).await?;
+
+        let ids = String::from_utf8_lossy(&output)
+            .split('\0')
+            .filter_map(|id| {
+                let id = id.trim();
+                let id = id.strip_prefix("id:").unwrap_or(id);
+                (!id.is_empty()).then(|| id.to_owned())
+            })
+            .collect();
+
+        Ok(ids)
+    }
+
+    /// Get the raw content of the message matching the given
+    /// identifier.
+    pub async fn show_raw(&self, id: &str) -> Result<Vec<u8>> {
+        let args = [
+            String::from("show"),
+            String::from("--format=raw"),
+            String::from("--"),
+            id_query(id),
+        ];
+
+        self.run(&args).await
+    }
+
+    /// Add or remove tags of messages matching the given identifiers.
+    ///
+    /// Tag changes are Notmuch tag operations, like `+flagged` or
+    /// `-unread`.
+    pub async fn tag<'a>(
+        &self,
+        changes: impl IntoIterator<Item = &'a str>,
+        ids: impl IntoIterator<Item = &'a str>,
+    ) -> Result<()> {
+        let mut args = vec![String::from("tag")];
+        args.extend(changes.into_iter().map(ToOwned::to_owned));
+        args.push(String::from("--"));
+        args.push(ids_query(ids));
+
+        self.run(&args).await?;
+        Ok(())
+    }
+}
+
+/// Build a Notmuch query matching the given message identifier.
+pub fn id_query(id: &str) -> String {
+    format!("id:\"{}\"", id.replace('"', "\"\""))
+}
+
+/// Build a Notmuch query matching any of the given message
+/// identifiers.
+pub fn ids_query<'a>(ids: impl IntoIterator<Item = &'a str>) -> String {
+    ids.into_iter()
+        .map(id_query)
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+/// The sync version of the remote Notmuch backend context.
+///
+/// The remote Notmuch context is stateless, so it is the same as
+/// the unsync one.
+pub type NotmuchRemoteContextSync = NotmuchRemoteContext;
+
+impl BackendContext for NotmuchRemoteContextSync {}
+
+/// The remote Notmuch context builder.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NotmuchRemoteContextBuilder {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The remote Notmuch configuration.
+    pub notmuch_remote_config: Arc<NotmuchRemoteConfig>,
+}
+
+impl NotmuchRemoteContextBuilder {
+    pub fn new(
+        account_config: Arc<AccountConfig>,
+        notmuch_remote_config: Arc<NotmuchRemoteConfig>,
+    ) -> Self {
+        Self {
+            account_config,
+            notmuch_remote_config,
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl crate::sync::hash::SyncHash for NotmuchRemoteContextBuilder {
+    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
+        std::hash::Hash::hash(&self.notmuch_remote_config.host, state);
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for NotmuchRemoteContextBuilder {
+    type Context = NotmuchRemoteContextSync;
+
+    fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
+        Some(Arc::new(CheckUpNotmuchRemote::some_new_boxed))
+    }
+
+    fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelopes>> {
+        Some(Arc::new(ListNotmuchRemoteEnvelopes::some_new_boxed))
+    }
+
+    fn peek_messages(&self) -> Option<BackendFeature<Self::Context, dyn PeekMessages>> {
+        Some(Arc::new(PeekNotmuchRemoteMessages::some_new_boxed))
+    }
+
+    fn get_messages(&self) -> Option<BackendFeature<Self::Context, dyn GetMessages>> {
+        Some(Arc::new(GetNotmuchRemoteMessages::some_new_boxed))
+    }
+
+    async fn build(self) -> AnyResult<Self::Context> {
+        info!("building new remote notmuch context");
+
+        Ok(NotmuchRemoteContext::new(
+            self.account_config,
+            self.notmuch_remote_config,
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct CheckUpNotmuchRemote {
+    pub ctx: NotmuchRemoteContextSync,
+}
+
+impl CheckUpNotmuchRemote {
+    pub fn new(ctx: &NotmuchRemoteContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchRemoteContextSync) -> Box<dyn CheckUp> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchRemoteContextSync) -> Option<Box<dyn CheckUp>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl CheckUp for CheckUpNotmuchRemote {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+
+        let args = [String::from("count"), String::from("*")];
+        report
+            .probe(CheckUpProbeKind::Command, self.ctx.run(&args))
+            .await;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id_query, ids_query};
+
+    #[test]
+    fn id_queries() {
+        assert_eq!(id_query("abc@localhost"), r#"id:"abc@localhost""#);
+        assert_eq!(id_query(r#"a"b@localhost"#), r#"id:"a""b@localhost""#);
+        assert_eq!(
+            ids_query(["a@localhost", "b@localhost"]),
+            r#"id:"a@localhost" or id:"b@localhost""#
+        );
+    }
+}
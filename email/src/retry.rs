@@ -1,6 +1,6 @@
 use std::{future::IntoFuture, time::Duration};
 
-use tokio::time::{error::Elapsed, Timeout};
+use crate::runtime::{self, Elapsed, Timeout};
 
 #[derive(Debug)]
 pub enum RetryState<T> {
@@ -9,18 +9,35 @@ pub enum RetryState<T> {
     TimedOut,
 }
 
-#[derive(Debug, Default)]
+/// The default timeout of retried requests.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
 pub struct Retry {
     pub attempts: u8,
+    pub timeout: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
 }
 
 impl Retry {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            attempts: 0,
+            timeout,
+        }
+    }
+
     pub fn reset(&mut self) {
         self.attempts = 0;
     }
 
     pub fn timeout<F: IntoFuture>(&self, f: F) -> Timeout<F::IntoFuture> {
-        tokio::time::timeout(Duration::from_secs(30), f)
+        runtime::timeout(self.timeout, f.into_future())
     }
 
     pub fn next<T>(&mut self, res: Result<T, Elapsed>) -> RetryState<T> {
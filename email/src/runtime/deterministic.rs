@@ -0,0 +1,171 @@
+//! # Deterministic runtime
+//!
+//! Module dedicated to reproducible tests of concurrent code, like
+//! the synchronization engine. The [`DeterministicRuntime`] differs
+//! from the default runtime in two ways:
+//!
+//! - Tasks run on a single thread, and each task spawned via
+//!   [`spawn`](super::spawn) yields a number of times before
+//!   starting. This number is drawn from a generator seeded by the
+//!   runtime seed, so the interleaving of tasks only depends on the
+//!   seed.
+//!
+//! - Time is virtual: the clock is paused, then automatically
+//!   advanced when all tasks are idle. Timeouts and sleeps therefore
+//!   complete instantly, in a deterministic order.
+//!
+//! Blocking tasks and I/O still run for real, so tests should stick
+//! to local backends (like Maildir) to stay reproducible.
+//!
+//! ```rust,ignore
+//! let rt = DeterministicRuntime::from_env();
+//! rt.block_on(async {
+//!     // run the sync engine, then check the result
+//! });
+//! ```
+
+use std::{cell::RefCell, env, future::Future, time::SystemTime};
+
+/// The environment variable used to replay a given seed.
+pub const SEED_ENV: &str = "EMAIL_DETERMINISTIC_SEED";
+
+/// The maximum number of times a spawned task yields before
+/// starting.
+const MAX_YIELDS: u64 = 8;
+
+thread_local! {
+    /// The generator of the deterministic runtime running on the
+    /// current thread, if any.
+    static RNG: RefCell<Option<SplitMix64>> = const { RefCell::new(None) };
+}
+
+/// The deterministic runtime.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DeterministicRuntime {
+    seed: u64,
+}
+
+impl DeterministicRuntime {
+    /// Create a new deterministic runtime using the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Create a new deterministic runtime using the seed of the
+    /// [`SEED_ENV`] environment variable, or a random seed.
+    ///
+    /// The seed is printed on the standard error, so that a failing
+    /// test can be replayed.
+    pub fn from_env() -> Self {
+        let seed = env::var(SEED_ENV)
+            .ok()
+            .and_then(|seed| seed.trim().parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64
+            });
+
+        eprintln!("deterministic runtime seed: {SEED_ENV}={seed}");
+
+        Self::new(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Run the given future to completion.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .expect("cannot build deterministic runtime");
+
+        let prev = RNG.with(|rng| rng.replace(Some(SplitMix64(self.seed))));
+        let output = rt.block_on(future);
+        RNG.with(|rng| rng.replace(prev));
+
+        output
+    }
+}
+
+/// Return the number of times the next spawned task should yield
+/// before starting, if a deterministic runtime is running on the
+/// current thread.
+pub(crate) fn next_yields() -> Option<u64> {
+    RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        let rng = rng.as_mut()?;
+        Some(rng.next() % (MAX_YIELDS + 1))
+    })
+}
+
+/// The SplitMix64 pseudo-random generator.
+///
+/// Statistically good enough to shuffle tasks, and small enough not
+/// to require a dependency.
+#[derive(Clone, Copy, Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::DeterministicRuntime;
+    use crate::runtime;
+
+    fn interleaving(seed: u64) -> Vec<usize> {
+        DeterministicRuntime::new(seed).block_on(async {
+            let order = Arc::new(Mutex::new(Vec::new()));
+
+            let tasks: Vec<_> = (0..16)
+                .map(|i| {
+                    let order = order.clone();
+                    runtime::spawn(async move { order.lock().unwrap().push(i) })
+                })
+                .collect();
+
+            for task in tasks {
+                task.await.unwrap();
+            }
+
+            let order = order.lock().unwrap().clone();
+            order
+        })
+    }
+
+    #[test]
+    fn seeded_interleaving() {
+        assert_eq!(interleaving(42), interleaving(42));
+        assert!((0..8).any(|seed| interleaving(seed) != interleaving(seed + 8)));
+    }
+
+    #[test]
+    fn virtual_time() {
+        let elapsed = DeterministicRuntime::new(0).block_on(async {
+            let start = tokio::time::Instant::now();
+            let res = runtime::timeout(Duration::from_secs(3600), future::pending::<()>()).await;
+            assert!(res.is_err());
+            start.elapsed()
+        });
+
+        assert_eq!(elapsed, Duration::from_secs(3600));
+    }
+}
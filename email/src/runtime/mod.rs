@@ -0,0 +1,73 @@
+//! # Runtime
+//!
+//! Module dedicated to the async runtime abstraction. Every
+//! executor-specific call of the library (spawning tasks, running
+//! blocking code, timers) goes through this module.
+//!
+//! This module does not make the library runtime-agnostic yet: it
+//! only gathers executor calls in a single place. [tokio] stays a
+//! required dependency, there is no `tokio` cargo feature and no
+//! alternative runtime backend. Synchronization primitives like
+//! [`tokio::sync::Mutex`] or [`tokio::sync::oneshot`] are
+//! executor-agnostic, which is why they are still used directly.
+//! Network-based backends (IMAP, SMTP…) still rely on tokio streams,
+//! which would need an IO abstraction before tokio could be made
+//! optional.
+//!
+//! For tests, the `deterministic-runtime` cargo feature enables a
+//! [`deterministic`] runtime which makes concurrent code
+//! reproducible.
+
+#[cfg(feature = "deterministic-runtime")]
+pub mod deterministic;
+
+use std::{future::Future, time::Duration};
+
+#[doc(inline)]
+pub use tokio::{
+    task::{JoinError, JoinHandle},
+    time::{error::Elapsed, Timeout},
+};
+
+/// Spawn a new asynchronous task.
+///
+/// The task starts running in the background immediately, even if
+/// the returned handle is not awaited.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "deterministic-runtime")]
+    if let Some(yields) = deterministic::next_yields() {
+        return tokio::spawn(async move {
+            for _ in 0..yields {
+                tokio::task::yield_now().await;
+            }
+            future.await
+        });
+    }
+
+    tokio::spawn(future)
+}
+
+/// Run the given blocking function on a dedicated thread, without
+/// blocking the executor.
+pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+}
+
+/// Require the given future to complete before the given duration
+/// has elapsed.
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    tokio::time::timeout(duration, future)
+}
+
+/// Wait until the given duration has elapsed.
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
@@ -5,8 +5,13 @@
 
 use process::Command;
 
+/// The default sendmail command.
+///
+/// Recipients are read from the message headers.
+pub const DEFAULT_SENDMAIL_CMD: &str = "/usr/sbin/sendmail -t";
+
 /// The sendmail sender configuration.
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
@@ -14,5 +19,21 @@
 )]
 pub struct SendmailConfig {
     /// The sendmail command.
+    ///
+    /// The raw message is piped into the standard input of the
+    /// command. Any sendmail-compatible command can be used, like
+    /// `msmtp --read-envelope-from --read-recipients`. Defaults to
+    /// `/usr/sbin/sendmail -t`.
+    #[cfg_attr(feature = "derive", serde(default = "default_cmd"))]
     pub cmd: Command,
 }
+
+impl Default for SendmailConfig {
+    fn default() -> Self {
+        Self { cmd: default_cmd() }
+    }
+}
+
+fn default_cmd() -> Command {
+    DEFAULT_SENDMAIL_CMD.into()
+}
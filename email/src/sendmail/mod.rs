@@ -1,3 +1,9 @@
+//! Module dedicated to the sendmail sender.
+//!
+//! This module contains the sendmail backend context, which sends
+//! messages by piping them into a local sendmail-compatible command
+//! (`/usr/sbin/sendmail -t`, `msmtp`…).
+
 pub mod config;
 mod error;
 
@@ -11,6 +17,7 @@
 use crate::{
     account::config::AccountConfig,
     backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
@@ -104,13 +111,16 @@ pub fn some_new_boxed(ctx: &SendmailContext) -> Option<Box<dyn CheckUp>> {
 
 #[async_trait]
 impl CheckUp for CheckUpSendmail {
-    async fn check_up(&self) -> AnyResult<()> {
-        self.ctx
-            .sendmail_config
-            .cmd
-            .run()
-            .await
-            .map_err(Error::ExecuteCommandError)?;
-        Ok(())
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+
+        let run = async {
+            let cmd = &self.ctx.sendmail_config.cmd;
+            cmd.run().await.map_err(Error::ExecuteCommandError)
+        };
+
+        report.probe(CheckUpProbeKind::Command, run).await;
+
+        Ok(report)
     }
 }
@@ -0,0 +1,436 @@
+//! Module dedicated to the ManageSieve client.
+//!
+//! The core structure of this module is the [`SieveClient`], which
+//! implements commands defined in the [RFC
+//! 5804](https://www.rfc-editor.org/rfc/rfc5804).
+
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufStream, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+use super::{
+    config::SieveEncryptionKind,
+    response::{quote, read_response, SieveResponse, SieveStatus, SieveToken},
+    Error, Result,
+};
+use crate::{
+    debug,
+    network::{self, config::NetworkConfig},
+};
+
+/// The ManageSieve stream.
+///
+/// The stream can be either plain TCP or encrypted using TLS.
+pub enum SieveStream {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for SieveStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for SieveStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The capabilities advertised by a ManageSieve server.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SieveCapabilities {
+    /// The name and version of the server implementation.
+    pub implementation: Option<String>,
+
+    /// The supported SASL mechanisms.
+    pub sasl: Vec<String>,
+
+    /// The supported Sieve extensions.
+    pub extensions: Vec<String>,
+
+    /// Whether the server supports STARTTLS.
+    pub starttls: bool,
+
+    /// The ManageSieve protocol version.
+    pub version: Option<String>,
+}
+
+impl SieveCapabilities {
+    /// Return `true` if the given SASL mechanism is supported.
+    pub fn supports_sasl(&self, mechanism: &str) -> bool {
+        self.sasl.iter().any(|m| m.eq_ignore_ascii_case(mechanism))
+    }
+}
+
+impl From<&SieveResponse> for SieveCapabilities {
+    fn from(res: &SieveResponse) -> Self {
+        let mut caps = Self::default();
+
+        for line in &res.lines {
+            let mut tokens = line.iter().map(SieveToken::as_str);
+            let name = tokens.next().unwrap_or_default();
+            let value = tokens.next();
+
+            match name.to_ascii_uppercase().as_str() {
+                "IMPLEMENTATION" => caps.implementation = value.map(ToOwned::to_owned),
+                "SASL" => {
+                    caps.sasl = value
+                        .map(|v| v.split_whitespace().map(ToOwned::to_owned).collect())
+                        .unwrap_or_default()
+                }
+                "SIEVE" => {
+                    caps.extensions = value
+                        .map(|v| v.split_whitespace().map(ToOwned::to_owned).collect())
+                        .unwrap_or_default()
+                }
+                "STARTTLS" => caps.starttls = true,
+                "VERSION" => caps.version = value.map(ToOwned::to_owned),
+                _ => (),
+            }
+        }
+
+        caps
+    }
+}
+
+/// A Sieve script stored on the server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SieveScript {
+    /// The name of the script.
+    pub name: String,
+
+    /// Whether the script is the active one.
+    pub active: bool,
+}
+
+/// The ManageSieve client.
+pub struct SieveClient {
+    stream: BufStream<SieveStream>,
+    capabilities: SieveCapabilities,
+}
+
+impl SieveClient {
+    /// Connect to the given ManageSieve server, using the given
+    /// encryption.
+    ///
+    /// The greeting capabilities are read straight after the
+    /// connection. When STARTTLS is used, capabilities are read again
+    /// after the TLS negotiation.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        encryption: &SieveEncryptionKind,
+        network: Option<&NetworkConfig>,
+    ) -> Result<Self> {
+        debug!("connecting to ManageSieve server {host}:{port} using {encryption}");
+
+        let tcp = network::connect(network, host, port)
+            .await
+            .map_err(|err| Error::ConnectTcpError(err, host.to_owned(), port))?;
+
+        let stream = match encryption {
+            SieveEncryptionKind::Tls => {
+                SieveStream::Tls(Box::new(upgrade_tls(tcp, host, port).await?))
+            }
+            SieveEncryptionKind::StartTls | SieveEncryptionKind::None => SieveStream::Tcp(tcp),
+        };
+
+        let mut client = Self {
+            stream: BufStream::new(stream),
+            capabilities: Default::default(),
+        };
+
+        client.read_capabilities().await?;
+
+        if let SieveEncryptionKind::StartTls = encryption {
+            if !client.capabilities.starttls {
+                return Err(Error::StartTlsNotSupportedError);
+            }
+
+            client.execute("STARTTLS", "STARTTLS\r\n").await?;
+
+            let tcp = match client.stream.into_inner() {
+                SieveStream::Tcp(tcp) => tcp,
+                SieveStream::Tls(_) => unreachable!("STARTTLS requires a plain TCP stream"),
+            };
+
+            let tls = upgrade_tls(tcp, host, port).await?;
+
+            client = Self {
+                stream: BufStream::new(SieveStream::Tls(Box::new(tls))),
+                capabilities: Default::default(),
+            };
+
+            client.read_capabilities().await?;
+        }
+
+        Ok(client)
+    }
+
+    /// Get the capabilities advertised by the server.
+    pub fn capabilities(&self) -> &SieveCapabilities {
+        &self.capabilities
+    }
+
+    /// Authenticate using the given SASL mechanism and initial
+    /// response.
+    ///
+    /// The initial response is base64-encoded by this function.
+    pub async fn authenticate(&mut self, mechanism: &str, initial_response: &[u8]) -> Result<()> {
+        use base64::Engine;
+
+        if !self.capabilities.supports_sasl(mechanism) {
+            let available = self.capabilities.sasl.clone();
+            return Err(Error::AuthMechanismNotSupportedError(
+                mechanism.to_owned(),
+                available,
+            ));
+        }
+
+        let initial_response = base64::engine::general_purpose::STANDARD.encode(initial_response);
+        let cmd = format!(
+            "AUTHENTICATE {} {}\r\n",
+            quote(mechanism),
+            quote(&initial_response)
+        );
+
+        let res = self.send(cmd).await?;
+
+        if !res.is_ok() {
+            return Err(Error::AuthenticateError(res.text().to_owned()));
+        }
+
+        // some servers send capabilities again after authentication
+        if !res.lines.is_empty() {
+            self.capabilities = SieveCapabilities::from(&res);
+        }
+
+        Ok(())
+    }
+
+    /// Authenticate using the SASL PLAIN mechanism.
+    pub async fn authenticate_plain(&mut self, login: &str, passwd: &str) -> Result<()> {
+        let initial_response = format!("\0{login}\0{passwd}");
+        self.authenticate("PLAIN", initial_response.as_bytes())
+            .await
+    }
+
+    /// Authenticate using the SASL XOAUTH2 mechanism.
+    pub async fn authenticate_xoauth2(&mut self, login: &str, token: &str) -> Result<()> {
+        let initial_response = format!("user={login}\x01auth=Bearer {token}\x01\x01");
+        self.authenticate("XOAUTH2", initial_response.as_bytes())
+            .await
+    }
+
+    /// Authenticate using the SASL OAUTHBEARER mechanism.
+    pub async fn authenticate_oauthbearer(
+        &mut self,
+        login: &str,
+        host: &str,
+        port: u16,
+        token: &str,
+    ) -> Result<()> {
+        let initial_response =
+            format!("n,a={login},\x01host={host}\x01port={port}\x01auth=Bearer {token}\x01\x01");
+        self.authenticate("OAUTHBEARER", initial_response.as_bytes())
+            .await
+    }
+
+    /// List scripts stored on the server.
+    pub async fn list_scripts(&mut self) -> Result<Vec<SieveScript>> {
+        let res = self.execute("LISTSCRIPTS", "LISTSCRIPTS\r\n").await?;
+
+        let scripts = res
+            .lines
+            .iter()
+            .filter_map(|line| {
+                let mut tokens = line.iter();
+                let name = tokens.next()?.as_str().to_owned();
+                let active = tokens.any(|t| t.as_str().eq_ignore_ascii_case("ACTIVE"));
+                Some(SieveScript { name, active })
+            })
+            .collect();
+
+        Ok(scripts)
+    }
+
+    /// Get the content of the given script.
+    pub async fn get_script(&mut self, name: &str) -> Result<String> {
+        let cmd = format!("GETSCRIPT {}\r\n", quote(name));
+        let res = self.execute("GETSCRIPT", cmd).await?;
+
+        res.lines
+            .into_iter()
+            .flatten()
+            .find_map(|token| match token {
+                SieveToken::String(script) => Some(script),
+                _ => None,
+            })
+            .ok_or_else(|| Error::GetScriptMissingError(name.to_owned()))
+    }
+
+    /// Upload the given script under the given name.
+    ///
+    /// The server checks the validity of the script before storing
+    /// it. Returns the optional warnings sent by the server.
+    pub async fn put_script(&mut self, name: &str, script: &str) -> Result<Option<String>> {
+        let cmd = format!(
+            "PUTSCRIPT {} {{{}+}}\r\n{script}\r\n",
+            quote(name),
+            script.len()
+        );
+        let res = self.execute("PUTSCRIPT", cmd).await?;
+        Ok(res.code.and(res.text))
+    }
+
+    /// Check the validity of the given script, without storing it.
+    ///
+    /// Returns the optional warnings sent by the server.
+    pub async fn check_script(&mut self, script: &str) -> Result<Option<String>> {
+        let cmd = format!("CHECKSCRIPT {{{}+}}\r\n{script}\r\n", script.len());
+        let res = self.execute("CHECKSCRIPT", cmd).await?;
+        Ok(res.code.and(res.text))
+    }
+
+    /// Activate the given script.
+    ///
+    /// Only one script can be active at a time.
+    pub async fn set_active(&mut self, name: &str) -> Result<()> {
+        let cmd = format!("SETACTIVE {}\r\n", quote(name));
+        self.execute("SETACTIVE", cmd).await?;
+        Ok(())
+    }
+
+    /// Deactivate the active script, if any.
+    pub async fn deactivate(&mut self) -> Result<()> {
+        self.set_active("").await
+    }
+
+    /// Delete the given script.
+    ///
+    /// The active script cannot be deleted.
+    pub async fn delete_script(&mut self, name: &str) -> Result<()> {
+        let cmd = format!("DELETESCRIPT {}\r\n", quote(name));
+        self.execute("DELETESCRIPT", cmd).await?;
+        Ok(())
+    }
+
+    /// Execute the no-operation command.
+    pub async fn noop(&mut self) -> Result<()> {
+        self.execute("NOOP", "NOOP\r\n").await?;
+        Ok(())
+    }
+
+    /// Log out from the server.
+    pub async fn logout(&mut self) -> Result<()> {
+        self.execute("LOGOUT", "LOGOUT\r\n").await?;
+        Ok(())
+    }
+
+    async fn read_capabilities(&mut self) -> Result<()> {
+        let res = read_response(&mut self.stream).await?;
+
+        if !res.is_ok() {
+            return Err(Error::ByeError(res.text().to_owned()));
+        }
+
+        self.capabilities = SieveCapabilities::from(&res);
+        debug!(capabilities = ?self.capabilities, "ManageSieve capabilities");
+
+        Ok(())
+    }
+
+    /// Send the given command then read the response.
+    async fn send(&mut self, cmd: impl AsRef<[u8]>) -> Result<SieveResponse> {
+        self.stream
+            .write_all(cmd.as_ref())
+            .await
+            .map_err(Error::WriteCommandError)?;
+        self.stream
+            .flush()
+            .await
+            .map_err(Error::WriteCommandError)?;
+
+        read_response(&mut self.stream).await
+    }
+
+    /// Send the given command then ensure the response status is
+    /// `OK`.
+    async fn execute(&mut self, name: &str, cmd: impl AsRef<[u8]>) -> Result<SieveResponse> {
+        let res = self.send(cmd).await?;
+
+        match res.status {
+            SieveStatus::Ok => Ok(res),
+            SieveStatus::No => {
+                let text = res.text().to_owned();
+                Err(Error::CommandRejectedError(name.to_owned(), text))
+            }
+            SieveStatus::Bye => Err(Error::ByeError(res.text().to_owned())),
+        }
+    }
+}
+
+async fn upgrade_tls(tcp: TcpStream, host: &str, port: u16) -> Result<TlsStream<TcpStream>> {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| {
+            let err = io::Error::new(io::ErrorKind::Other, err);
+            Error::ConnectTlsError(err, host.to_owned(), port)
+        })?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let server_name = ServerName::try_from(host.to_owned())
+        .map_err(|err| Error::InvalidServerNameError(err, host.to_owned()))?;
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|err| Error::ConnectTlsError(err, host.to_owned(), port))
+}
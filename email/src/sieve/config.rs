@@ -0,0 +1,79 @@
+//! Module dedicated to the ManageSieve configuration.
+//!
+//! This module contains the configuration specific to the ManageSieve
+//! client. Host, login and authentication are shared with the IMAP
+//! configuration.
+
+use std::fmt;
+
+/// The default ManageSieve port, as defined in the RFC.
+pub const DEFAULT_SIEVE_PORT: u16 = 4190;
+
+/// The ManageSieve configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SieveConfig {
+    /// The ManageSieve server host name.
+    ///
+    /// Defaults to the IMAP server host name.
+    pub host: Option<String>,
+
+    /// The ManageSieve server host port.
+    ///
+    /// Defaults to 4190.
+    pub port: Option<u16>,
+
+    /// The ManageSieve encryption protocol to use.
+    ///
+    /// Supported encryption: SSL/TLS, STARTTLS or none. Defaults to
+    /// STARTTLS.
+    pub encryption: Option<SieveEncryptionKind>,
+}
+
+impl SieveConfig {
+    /// Get the ManageSieve server host name, or fall back to the
+    /// given one.
+    pub fn host_or<'a>(&'a self, host: &'a str) -> &'a str {
+        self.host.as_deref().unwrap_or(host)
+    }
+
+    /// Get the ManageSieve server host port, or return the default
+    /// one.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_SIEVE_PORT)
+    }
+
+    /// Get the ManageSieve encryption, or return the default one.
+    pub fn encryption(&self) -> SieveEncryptionKind {
+        self.encryption.clone().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum SieveEncryptionKind {
+    #[cfg_attr(feature = "derive", serde(alias = "ssl"))]
+    Tls,
+    #[default]
+    #[cfg_attr(feature = "derive", serde(alias = "starttls"))]
+    StartTls,
+    None,
+}
+
+impl fmt::Display for SieveEncryptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tls => write!(f, "SSL/TLS"),
+            Self::StartTls => write!(f, "StartTLS"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
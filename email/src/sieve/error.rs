@@ -0,0 +1,59 @@
+use std::{any::Any, io, result};
+
+use thiserror::Error;
+use tokio_rustls::rustls::pki_types::InvalidDnsNameError;
+
+use crate::{imap, network, AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot connect to ManageSieve server {1}:{2} using TCP")]
+    ConnectTcpError(#[source] network::Error, String, u16),
+    #[error("cannot connect to ManageSieve server {1}:{2} using SSL/TLS")]
+    ConnectTlsError(#[source] io::Error, String, u16),
+    #[error("cannot use {1} as ManageSieve server name")]
+    InvalidServerNameError(#[source] InvalidDnsNameError, String),
+    #[error("cannot use STARTTLS: ManageSieve server does not support it")]
+    StartTlsNotSupportedError,
+
+    #[error("cannot write ManageSieve command")]
+    WriteCommandError(#[source] io::Error),
+    #[error("cannot read ManageSieve response")]
+    ReadResponseError(#[source] io::Error),
+    #[error("cannot read ManageSieve response: connection closed")]
+    ConnectionClosedError,
+    #[error("cannot parse ManageSieve response: {0}")]
+    ParseResponseError(String),
+    #[error("ManageSieve server rejected command {0}: {1}")]
+    CommandRejectedError(String, String),
+    #[error("ManageSieve server closed the connection: {0}")]
+    ByeError(String),
+
+    #[error("cannot get ManageSieve credentials")]
+    GetCredentialsError(#[source] imap::Error),
+    #[error(
+        "cannot authenticate to ManageSieve server: mechanism {0} not supported (available: {1:?})"
+    )]
+    AuthMechanismNotSupportedError(String, Vec<String>),
+    #[error("cannot authenticate to ManageSieve server: {0}")]
+    AuthenticateError(String),
+
+    #[error("cannot find ManageSieve script {0}")]
+    GetScriptMissingError(String),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,172 @@
+//! # ManageSieve
+//!
+//! Module dedicated to the management of server-side filters, using
+//! the ManageSieve protocol defined in the [RFC
+//! 5804](https://www.rfc-editor.org/rfc/rfc5804).
+//!
+//! The ManageSieve server usually lives next to the IMAP server, this
+//! is why the context shares the IMAP host, login and authentication
+//! configuration. Only the port and the encryption can be customized
+//! via [`config::SieveConfig`].
+
+pub mod client;
+pub mod config;
+mod error;
+pub mod response;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use self::client::SieveClient;
+#[doc(inline)]
+pub use self::error::{Error, Result};
+#[cfg(feature = "oauth2")]
+use crate::account::config::oauth2::OAuth2Method;
+use crate::{
+    account::config::AccountConfig,
+    backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
+        context::{BackendContext, BackendContextBuilder},
+        feature::{BackendFeature, CheckUp},
+    },
+    imap::config::{ImapAuthConfig, ImapConfig},
+    info, AnyResult,
+};
+
+/// The ManageSieve backend context.
+///
+/// This context is unsync, which means it cannot be shared between
+/// threads. For the sync version, see [`SieveContextSync`].
+pub struct SieveContext {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The IMAP configuration.
+    pub imap_config: Arc<ImapConfig>,
+
+    /// The ManageSieve client.
+    pub client: SieveClient,
+}
+
+/// The sync version of the ManageSieve backend context.
+///
+/// This is just a ManageSieve client wrapped into a mutex, so the
+/// same client can be shared and updated across multiple threads.
+pub type SieveContextSync = Arc<Mutex<SieveContext>>;
+
+impl BackendContext for SieveContextSync {}
+
+/// The ManageSieve client builder.
+#[derive(Clone)]
+pub struct SieveContextBuilder {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    /// The IMAP configuration.
+    imap_config: Arc<ImapConfig>,
+}
+
+impl SieveContextBuilder {
+    pub fn new(account_config: Arc<AccountConfig>, imap_config: Arc<ImapConfig>) -> Self {
+        Self {
+            account_config,
+            imap_config,
+        }
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for SieveContextBuilder {
+    type Context = SieveContextSync;
+
+    fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
+        Some(Arc::new(CheckUpSieve::some_new_boxed))
+    }
+
+    /// Build a ManageSieve sync client.
+    ///
+    /// The client connects to the server and authenticates using the
+    /// IMAP login and authentication configuration.
+    async fn build(self) -> AnyResult<Self::Context> {
+        info!("building new ManageSieve context");
+
+        let sieve_config = self.imap_config.sieve.clone().unwrap_or_default();
+        let host = sieve_config.host_or(&self.imap_config.host);
+        let port = sieve_config.port();
+        let encryption = sieve_config.encryption();
+
+        let network = self.account_config.network.as_ref();
+        let mut client = SieveClient::connect(host, port, &encryption, network).await?;
+
+        let login = &self.imap_config.login;
+        let secret = self
+            .imap_config
+            .auth
+            .build_credentials()
+            .await
+            .map_err(Error::GetCredentialsError)?;
+
+        match &self.imap_config.auth {
+            ImapAuthConfig::Passwd(_) => {
+                client.authenticate_plain(login, &secret).await?;
+            }
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(config) => match config.method.resolve(
+                client.capabilities().supports_sasl("XOAUTH2"),
+                client.capabilities().supports_sasl("OAUTHBEARER"),
+            ) {
+                OAuth2Method::Auto | OAuth2Method::XOAuth2 => {
+                    client.authenticate_xoauth2(login, &secret).await?;
+                }
+                OAuth2Method::OAuthBearer => {
+                    client
+                        .authenticate_oauthbearer(login, host, port, &secret)
+                        .await?;
+                }
+            },
+        }
+
+        let ctx = SieveContext {
+            account_config: self.account_config,
+            imap_config: self.imap_config,
+            client,
+        };
+
+        Ok(Arc::new(Mutex::new(ctx)))
+    }
+}
+
+#[derive(Clone)]
+pub struct CheckUpSieve {
+    ctx: SieveContextSync,
+}
+
+impl CheckUpSieve {
+    pub fn new(ctx: &SieveContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &SieveContextSync) -> Box<dyn CheckUp> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &SieveContextSync) -> Option<Box<dyn CheckUp>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl CheckUp for CheckUpSieve {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
+        let mut ctx = self.ctx.lock().await;
+
+        report
+            .probe(CheckUpProbeKind::Session, ctx.client.noop())
+            .await;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,333 @@
+//! Module dedicated to ManageSieve responses.
+//!
+//! This module contains a minimal parser for responses sent by a
+//! ManageSieve server, as defined in the [RFC
+//! 5804](https://www.rfc-editor.org/rfc/rfc5804#section-4).
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use super::{Error, Result};
+
+/// A token of a ManageSieve response line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SieveToken {
+    /// An atom, like `OK` or `ACTIVE`.
+    Atom(String),
+
+    /// A response code, like `(NONEXISTENT)`, without parenthesis.
+    Code(String),
+
+    /// A quoted string or a literal.
+    String(String),
+}
+
+impl SieveToken {
+    /// Get the inner string of the token.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Atom(s) | Self::Code(s) | Self::String(s) => s.as_str(),
+        }
+    }
+}
+
+/// The status of a ManageSieve response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SieveStatus {
+    Ok,
+    No,
+    Bye,
+}
+
+/// A ManageSieve response.
+///
+/// A response is composed of data lines followed by a final status
+/// line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SieveResponse {
+    /// The data lines preceding the status line.
+    pub lines: Vec<Vec<SieveToken>>,
+
+    /// The status of the response.
+    pub status: SieveStatus,
+
+    /// The optional response code of the status line.
+    pub code: Option<String>,
+
+    /// The optional human-readable text of the status line.
+    pub text: Option<String>,
+}
+
+impl SieveResponse {
+    /// Return `true` if the status of the response is `OK`.
+    pub fn is_ok(&self) -> bool {
+        self.status == SieveStatus::Ok
+    }
+
+    /// Get the human-readable text of the response, or an empty
+    /// string.
+    pub fn text(&self) -> &str {
+        self.text.as_deref().unwrap_or_default()
+    }
+}
+
+/// Read a full ManageSieve response from the given reader.
+pub async fn read_response<R>(reader: &mut R) -> Result<SieveResponse>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut lines = Vec::new();
+
+    loop {
+        let mut tokens = read_tokens(reader).await?.into_iter();
+
+        let status = match tokens.next() {
+            Some(SieveToken::Atom(atom)) if atom.eq_ignore_ascii_case("OK") => SieveStatus::Ok,
+            Some(SieveToken::Atom(atom)) if atom.eq_ignore_ascii_case("NO") => SieveStatus::No,
+            Some(SieveToken::Atom(atom)) if atom.eq_ignore_ascii_case("BYE") => SieveStatus::Bye,
+            Some(token) => {
+                lines.push([token].into_iter().chain(tokens).collect());
+                continue;
+            }
+            None => {
+                continue;
+            }
+        };
+
+        let mut code = None;
+        let mut text = None;
+
+        for token in tokens {
+            match token {
+                SieveToken::Code(c) => code = Some(c),
+                SieveToken::String(s) => text = Some(s),
+                SieveToken::Atom(_) => (),
+            }
+        }
+
+        break Ok(SieveResponse {
+            lines,
+            status,
+            code,
+            text,
+        });
+    }
+}
+
+/// Read the tokens of a single logical line.
+///
+/// A logical line may span over multiple physical lines when it
+/// contains literals.
+async fn read_tokens<R>(reader: &mut R) -> Result<Vec<SieveToken>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut tokens = Vec::new();
+
+    loop {
+        let mut line = Vec::new();
+        let n = reader
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(Error::ReadResponseError)?;
+
+        if n == 0 {
+            return Err(Error::ConnectionClosedError);
+        }
+
+        while let Some(b'\r' | b'\n') = line.last() {
+            line.pop();
+        }
+
+        match tokenize(&line, &mut tokens)? {
+            None => break Ok(tokens),
+            Some(len) => {
+                let mut literal = vec![0; len];
+                reader
+                    .read_exact(&mut literal)
+                    .await
+                    .map_err(Error::ReadResponseError)?;
+                let literal = String::from_utf8_lossy(&literal).to_string();
+                tokens.push(SieveToken::String(literal));
+            }
+        }
+    }
+}
+
+/// Tokenize the given physical line.
+///
+/// Returns the length of the literal ending the line, if any.
+fn tokenize(line: &[u8], tokens: &mut Vec<SieveToken>) -> Result<Option<usize>> {
+    let mut i = 0;
+
+    while i < line.len() {
+        match line[i] {
+            b' ' => {
+                i += 1;
+            }
+            b'"' => {
+                let mut s = Vec::new();
+                i += 1;
+
+                while i < line.len() && line[i] != b'"' {
+                    if line[i] == b'\\' && i + 1 < line.len() {
+                        i += 1;
+                    }
+                    s.push(line[i]);
+                    i += 1;
+                }
+
+                if i >= line.len() {
+                    let line = String::from_utf8_lossy(line).to_string();
+                    return Err(Error::ParseResponseError(line));
+                }
+
+                i += 1;
+                tokens.push(SieveToken::String(String::from_utf8_lossy(&s).to_string()));
+            }
+            b'(' => {
+                let end = find(line, i, b')')?;
+                let code = String::from_utf8_lossy(&line[i + 1..end]).to_string();
+                tokens.push(SieveToken::Code(code));
+                i = end + 1;
+            }
+            b'{' => {
+                let end = find(line, i, b'}')?;
+                let len = String::from_utf8_lossy(&line[i + 1..end]);
+                let len = len.trim_end_matches('+').parse().map_err(|_| {
+                    let line = String::from_utf8_lossy(line).to_string();
+                    Error::ParseResponseError(line)
+                })?;
+                return Ok(Some(len));
+            }
+            _ => {
+                let end = line[i..]
+                    .iter()
+                    .position(|b| *b == b' ')
+                    .map(|pos| i + pos)
+                    .unwrap_or(line.len());
+                let atom = String::from_utf8_lossy(&line[i..end]).to_string();
+                tokens.push(SieveToken::Atom(atom));
+                i = end;
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn find(line: &[u8], from: usize, byte: u8) -> Result<usize> {
+    line[from..]
+        .iter()
+        .position(|b| *b == byte)
+        .map(|pos| from + pos)
+        .ok_or_else(|| Error::ParseResponseError(String::from_utf8_lossy(line).to_string()))
+}
+
+/// Quote the given string, so it can be used as a ManageSieve
+/// command argument.
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote, read_response, SieveStatus, SieveToken};
+
+    #[tokio::test]
+    async fn read_capabilities() {
+        let mut reader: &[u8] = concat!(
+            "\"IMPLEMENTATION\" \"Dovecot Pigeonhole\"\r\n",
+            "\"SIEVE\" \"fileinto reject envelope\"\r\n",
+            "\"STARTTLS\"\r\n",
+            "OK \"Dovecot ready.\"\r\n",
+        )
+        .as_bytes();
+
+        let res = read_response(&mut reader).await.unwrap();
+
+        assert_eq!(res.status, SieveStatus::Ok);
+        assert_eq!(res.text(), "Dovecot ready.");
+        assert_eq!(
+            res.lines,
+            vec![
+                vec![
+                    SieveToken::String("IMPLEMENTATION".into()),
+                    SieveToken::String("Dovecot Pigeonhole".into()),
+                ],
+                vec![
+                    SieveToken::String("SIEVE".into()),
+                    SieveToken::String("fileinto reject envelope".into()),
+                ],
+                vec![SieveToken::String("STARTTLS".into())],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_list_scripts() {
+        let mut reader: &[u8] = concat!(
+            "\"summer_script\"\r\n",
+            "\"vac\\\"ation\" ACTIVE\r\n",
+            "OK\r\n",
+        )
+        .as_bytes();
+
+        let res = read_response(&mut reader).await.unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(
+            res.lines,
+            vec![
+                vec![SieveToken::String("summer_script".into())],
+                vec![
+                    SieveToken::String("vac\"ation".into()),
+                    SieveToken::Atom("ACTIVE".into()),
+                ],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_literal() {
+        let mut reader: &[u8] = concat!(
+            "{26}\r\n",
+            "require \"fileinto\";\r\nkeep;\r\n",
+            "OK \"getscript completed.\"\r\n",
+        )
+        .as_bytes();
+
+        let res = read_response(&mut reader).await.unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(
+            res.lines,
+            vec![vec![SieveToken::String(
+                "require \"fileinto\";\r\nkeep;".into()
+            )]]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_no_with_code_and_literal() {
+        let mut reader: &[u8] =
+            concat!("NO (NONEXISTENT) {14}\r\n", "Script missing\r\n",).as_bytes();
+
+        let res = read_response(&mut reader).await.unwrap();
+
+        assert_eq!(res.status, SieveStatus::No);
+        assert_eq!(res.code.as_deref(), Some("NONEXISTENT"));
+        assert_eq!(res.text(), "Script missing");
+    }
+
+    #[tokio::test]
+    async fn read_closed_connection() {
+        let mut reader: &[u8] = b"\"SIEVE\" \"fileinto\"\r\n";
+        assert!(read_response(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn quote_string() {
+        assert_eq!(quote("simple"), "\"simple\"");
+        assert_eq!(quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}
@@ -3,17 +3,22 @@
 //! This module contains the configuration specific to the SMTP
 //! sender.
 
-use std::{fmt, io};
+use std::{fmt, io, path::PathBuf, time::Duration};
 #[cfg(feature = "derive")]
 use std::{marker::PhantomData, result};
 
 use mail_send::Credentials;
+use secret::Secret;
 
 #[doc(inline)]
 pub use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::{OAuth2Config, OAuth2Method};
-use crate::{account::config::passwd::PasswdConfig, debug};
+use crate::{
+    account::config::passwd::PasswdConfig,
+    debug,
+    network::{config::ClientCertificateConfig, tls::TlsOptions},
+};
 
 /// The SMTP sender configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -38,6 +43,31 @@ pub struct SmtpConfig {
     )]
     pub encryption: Option<SmtpEncryptionKind>,
 
+    /// The path to a custom CA bundle.
+    ///
+    /// When defined, the SMTP server certificate chain is verified
+    /// against the PEM-encoded certificates of this bundle instead
+    /// of the system root certificates, which is useful for
+    /// self-hosted servers using a private certificate authority.
+    /// The path is shell expanded.
+    pub ca_bundle: Option<PathBuf>,
+
+    /// The SMTP server certificate pins.
+    ///
+    /// When defined, the server certificate chain must contain at
+    /// least one certificate whose Subject Public Key Info (SPKI)
+    /// SHA-256 hash matches one of the pins, otherwise the
+    /// connection fails even if the chain is valid. Pins are
+    /// base64-encoded hashes, optionally prefixed by `sha256/`.
+    pub certificate_pins: Option<Vec<String>>,
+
+    /// The TLS client certificate.
+    ///
+    /// When defined, the client authenticates to the SMTP server
+    /// using this certificate, as required by servers enforcing
+    /// mutual TLS (mTLS).
+    pub client_certificate: Option<ClientCertificateConfig>,
+
     /// The SMTP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -49,9 +79,34 @@ pub struct SmtpConfig {
     /// Authentication can be done using password or OAuth 2.0.
     /// See [SmtpAuthConfig].
     pub auth: SmtpAuthConfig,
+
+    /// The keep-alive interval, in seconds.
+    ///
+    /// When defined, a NOOP command is sent each time the connection
+    /// has been idle for this interval, which prevents servers from
+    /// closing it between two sends. Whether this option is defined
+    /// or not, a connection closed by the server is lazily
+    /// re-established on the next send.
+    pub keep_alive: Option<u64>,
 }
 
 impl SmtpConfig {
+    /// Return the TLS options of the connection.
+    pub fn tls_options(&self) -> TlsOptions<'_> {
+        TlsOptions {
+            ca_bundle: self.ca_bundle.as_deref(),
+            pins: self.certificate_pins.as_deref(),
+            client_certificate: self.client_certificate.as_ref(),
+        }
+    }
+
+    /// Return the keep-alive interval, if enabled.
+    pub fn keep_alive_interval(&self) -> Option<Duration> {
+        self.keep_alive
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -92,15 +147,45 @@ pub async fn credentials(&self) -> Result<Credentials<String>> {
                     .await
                     .map_err(|_| Error::AccessTokenWasNotAvailable)?;
 
+                // the automatic method is resolved once the server
+                // capabilities are known, see
+                // [`SmtpConfig::oauth_bearer_credentials`]
                 match oauth2.method {
-                    OAuth2Method::XOAuth2 => {
+                    OAuth2Method::Auto | OAuth2Method::XOAuth2 => {
                         Credentials::new_xoauth2(self.login.clone(), access_token)
                     }
-                    OAuth2Method::OAuthBearer => Credentials::new_oauth(access_token),
+                    OAuth2Method::OAuthBearer => self.oauth_bearer_credentials(&access_token),
                 }
             }
         })
     }
+
+    /// Return `true` if the OAuth 2.0 method needs to be picked
+    /// automatically.
+    pub fn is_oauth2_method_auto(&self) -> bool {
+        match &self.auth {
+            #[cfg(feature = "oauth2")]
+            SmtpAuthConfig::OAuth2(oauth2) => oauth2.method == OAuth2Method::Auto,
+            _ => false,
+        }
+    }
+
+    /// Build OAUTHBEARER credentials from the given access token.
+    ///
+    /// Unlike XOAUTH2 credentials, the SASL payload is sent as is,
+    /// so it needs to be built according to the [RFC 7628].
+    ///
+    /// [RFC 7628]: https://www.rfc-editor.org/rfc/rfc7628.html#section-3.1
+    pub fn oauth_bearer_credentials(&self, access_token: &str) -> Credentials<String> {
+        // commas and equal signs need to be escaped in the GS2 header
+        let login = self.login.replace('=', "=3D").replace(',', "=2C");
+        let host = &self.host;
+        let port = self.port;
+
+        Credentials::new_oauth(format!(
+            "n,a={login},\x01host={host}\x01port={port}\x01auth=Bearer {access_token}\x01\x01"
+        ))
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -162,6 +247,15 @@ fn default() -> Self {
 }
 
 impl SmtpAuthConfig {
+    /// Gets SMTP secrets (password or OAuth 2.0 secrets).
+    pub fn secrets(&self) -> Vec<&Secret> {
+        match self {
+            Self::Passwd(passwd) => vec![&passwd.0],
+            #[cfg(feature = "oauth2")]
+            Self::OAuth2(config) => config.secrets().to_vec(),
+        }
+    }
+
     /// Resets the OAuth 2.0 authentication tokens.
     pub async fn reset(&mut self) -> Result<()> {
         debug!("resetting smtp backend configuration");
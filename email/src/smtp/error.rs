@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{network, AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -16,12 +16,29 @@ pub enum Error {
     SendMessageMissingRecipientError,
     #[error("cannot send message: request timed out")]
     SendMessageTimedOutError,
+    #[error("cannot send message to or from {0}: server does not support SMTPUTF8")]
+    SendMessageUtf8NotSupportedError(String),
+    #[error("cannot send message: all recipients were rejected")]
+    SendMessageAllRecipientsRejectedError(Vec<(String, String)>),
+    #[error("cannot send message of {0} bytes: server accepts messages up to {1} bytes")]
+    SendMessageTooLargeError(usize, usize),
+    #[error("cannot prepare message before sending it")]
+    PrepareMessageSmtpError(#[source] crate::email::Error),
+    #[cfg(feature = "dkim")]
+    #[error("cannot sign message before sending it")]
+    SignMessageSmtpError(#[source] crate::email::Error),
     #[error("cannot send message")]
     SendMessageError(#[source] mail_send::Error),
     #[error("cannot connect to smtp server using tcp")]
     ConnectTcpSmtpError(#[source] mail_send::Error),
     #[error("cannot connect to smtp server using tls")]
     ConnectTlsSmtpError(#[source] mail_send::Error),
+    #[error("cannot connect to smtp server {1}:{2}")]
+    ConnectNetworkSmtpError(#[source] network::Error, String, u16),
+    #[error("cannot build smtp tls connector")]
+    BuildTlsConnectorSmtpError(#[source] network::Error),
+    #[error("certificate of smtp server {0}:{1} does not match any pin")]
+    UnpinnedCertificateSmtpError(String, u16),
     #[error("cannot get smtp password")]
     GetPasswdSmtpError(#[source] secret::Error),
     #[error("cannot get smtp password: password is empty")]
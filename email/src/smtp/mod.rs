@@ -1,15 +1,23 @@
 pub mod config;
 mod error;
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
-use mail_parser::{Addr, Address, HeaderName, HeaderValue, Message, MessageParser};
 use mail_send::{
-    smtp::message::{Address as SmtpAddress, IntoMessage, Message as SmtpMessage},
-    SmtpClientBuilder,
+    smtp::message::{Address as SmtpAddress, IntoMessage, Message as SmtpMessage, Parameters},
+    Credentials, SmtpClient, SmtpClientBuilder,
+};
+use smtp_proto::{EhloResponse, Response, EXT_CHUNKING, EXT_DSN, EXT_SIZE, EXT_SMTP_UTF8};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::Mutex,
 };
-use tokio::{net::TcpStream, sync::Mutex};
 use tokio_rustls::client::TlsStream;
 
 use self::config::{SmtpAuthConfig, SmtpConfig};
@@ -18,15 +26,28 @@
 use crate::{
     account::config::AccountConfig,
     backend::{
+        check_up::{CheckUpProbeKind, CheckUpReport},
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
     debug, info,
-    message::send::{smtp::SendSmtpMessage, SendMessage},
+    message::send::{
+        envelope::{prepare_message, SendEnvelope},
+        smtp::SendSmtpMessage,
+        DsnOptions, SendMessage, SendMessageOptions, SendReport, SentMessage,
+    },
+    network::{self, config::NetworkConfig, stream::SharedStreamConnector},
     retry::{Retry, RetryState},
-    warn, AnyResult,
+    runtime, warn, AnyResult,
 };
 
+/// The size of the chunks of messages sent using BDAT, in bytes.
+pub const BDAT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// The idle period after which the connection is checked using NOOP
+/// before sending a message.
+pub const IDLE_CHECK_DELAY: Duration = Duration::from_secs(30);
+
 /// The SMTP backend context.
 ///
 /// This context is unsync, which means it cannot be shared between
@@ -41,42 +62,139 @@ pub struct SmtpContext {
     /// The SMTP client builder.
     client_builder: mail_send::SmtpClientBuilder<String>,
 
+    /// The custom stream connector.
+    stream_connector: Option<SharedStreamConnector>,
+
     /// The SMTP client.
     client: SmtpClientStream,
+
+    /// The server capabilities, detected the first time an optional
+    /// extension (like SMTPUTF8, DSN or SIZE) is needed.
+    capabilities: Option<EhloResponse<String>>,
+
+    /// Whether the client is considered connected.
+    ///
+    /// Set to `false` when a NOOP fails, when sending a message times
+    /// out or while a message is being sent, so that the connection is
+    /// re-established before the next send.
+    connected: bool,
+
+    /// The last time the connection was successfully used.
+    last_used: Instant,
 }
 
 impl SmtpContext {
-    pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
-        let buffer: Vec<u8>;
-
-        let mut msg = MessageParser::new().parse(msg).unwrap_or_else(|| {
-            debug!("cannot parse raw email message");
-            Default::default()
-        });
+    /// Return the server capabilities.
+    ///
+    /// Capabilities are not kept by [`mail_send`] after connecting,
+    /// so they are requested again using EHLO, which only resets
+    /// the current transaction.
+    async fn capabilities(&mut self) -> Option<&EhloResponse<String>> {
+        if self.capabilities.is_none() {
+            let local_host = &self.client_builder.local_host;
+            let is_lmtp = self.client_builder.is_lmtp;
 
-        if let Some(cmd) = self.account_config.find_message_pre_send_hook() {
-            match cmd.run_with(msg.raw_message()).await {
-                Ok(res) => {
-                    buffer = res.into();
-                    msg = MessageParser::new().parse(&buffer).unwrap_or_else(|| {
-                        debug!("cannot parse email raw message");
-                        Default::default()
-                    });
-                }
+            match self.client.capabilities(local_host, is_lmtp).await {
+                Ok(capabilities) => self.capabilities = Some(capabilities),
                 Err(_err) => {
-                    debug!("cannot execute pre-send hook: {_err}");
+                    debug!("cannot get smtp capabilities, assuming no extension is supported");
                     debug!("{_err:?}");
+                    return None;
                 }
             }
+        }
+
+        self.capabilities.as_ref()
+    }
+
+    /// Return `true` if the server supports the given extension.
+    async fn has_capability(&mut self, capability: u32) -> bool {
+        self.capabilities()
+            .await
+            .is_some_and(|capabilities| capabilities.has_capability(capability))
+    }
+
+    /// Return the maximum message size accepted by the server, in
+    /// bytes, as advertised by the SIZE extension.
+    ///
+    /// A size of 0 means that the server does not have any fixed
+    /// limit, see <https://www.rfc-editor.org/rfc/rfc1870>.
+    async fn size_limit(&mut self) -> Option<usize> {
+        self.capabilities()
+            .await
+            .filter(|capabilities| capabilities.has_capability(EXT_SIZE))
+            .map(|capabilities| capabilities.size)
+            .filter(|size| *size > 0)
+    }
+
+    /// Send the given raw message, then return the reply of the
+    /// server.
+    pub async fn send(&mut self, msg: &[u8]) -> Result<SentMessage> {
+        let report = self.send_with_options(msg, &Default::default()).await?;
+        Ok(report.sent)
+    }
+
+    /// Send the given raw message using the given options, then
+    /// return which recipients accepted it.
+    ///
+    /// Delivery status notifications are only requested if the server
+    /// advertises the DSN extension. Rejected recipients do not make
+    /// the sending fail, unless all of them are rejected. Messages
+    /// larger than the limit advertised by the SIZE extension are
+    /// rejected before being transferred.
+    pub async fn send_with_options(
+        &mut self,
+        msg: &[u8],
+        opts: &SendMessageOptions,
+    ) -> Result<SendReport> {
+        self.ensure_connected().await?;
+
+        let (msg, envelope, pre_hooks) = prepare_message(&self.account_config, msg)
+            .await
+            .map_err(Error::PrepareMessageSmtpError)?;
+        #[cfg(feature = "dkim")]
+        let msg = crate::message::send::dkim::sign_message(&self.account_config, msg)
+            .await
+            .map_err(Error::SignMessageSmtpError)?;
+
+        let size = msg.as_ref().len();
+
+        if let Some(limit) = self.size_limit().await {
+            if size > limit {
+                return Err(Error::SendMessageTooLargeError(size, limit));
+            }
+        }
+
+        let smtputf8 = if envelope.is_ascii() {
+            false
+        } else {
+            self.has_capability(EXT_SMTP_UTF8).await
         };
 
+        let dsn = match opts.dsn.as_ref() {
+            Some(dsn) if self.has_capability(EXT_DSN).await => Some(dsn),
+            Some(_) => {
+                debug!("smtp server does not support DSN, skipping notifications request");
+                None
+            }
+            None => None,
+        };
+
+        let chunking = self.has_capability(EXT_CHUNKING).await;
+
         let mut retry = Retry::default();
 
-        loop {
+        let report = loop {
             // NOTE: cannot clone the final message
-            let msg = into_smtp_msg(msg.clone())?;
+            let msg = into_smtp_msg(envelope.clone(), msg.clone(), smtputf8, dsn)?;
+
+            // a send cancelled midway leaves the connection in an
+            // unknown state, which is why it is considered broken
+            // until the server replies
+            self.connected = false;
+            let send = self.client.send_with_report(msg, chunking);
 
-            match retry.next(retry.timeout(self.client.send(msg)).await) {
+            match retry.next(retry.timeout(send).await) {
                 #[cfg(not(feature = "tracing"))]
                 RetryState::Retry => continue,
                 #[cfg(feature = "tracing")]
@@ -87,8 +205,13 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
                 RetryState::TimedOut => {
                     break Err(Error::SendMessageTimedOutError);
                 }
-                RetryState::Ok(Ok(res)) => {
-                    break Ok(res);
+                RetryState::Ok(Ok(report)) if report.accepted.is_empty() => {
+                    break Err(Error::SendMessageAllRecipientsRejectedError(
+                        report.rejected,
+                    ));
+                }
+                RetryState::Ok(Ok(report)) => {
+                    break Ok(report);
                 }
                 RetryState::Ok(Err(err)) => {
                     match err {
@@ -118,23 +241,114 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
                         }
                     };
 
-                    tracing::debug!("re-connecting…");
-
-                    self.client = if self.smtp_config.is_encryption_enabled() {
-                        build_tls_client(&self.client_builder).await
-                    } else {
-                        build_tcp_client(&self.client_builder).await
-                    }?;
-
+                    self.reconnect().await?;
                     retry.reset();
                     continue;
                 }
             }
+        };
+
+        // the server replied unless the send timed out, in which case
+        // the connection stays broken; I/O errors re-connect above
+        if !matches!(report, Err(Error::SendMessageTimedOutError)) {
+            self.connected = true;
+            self.last_used = Instant::now();
         }
+
+        let mut report = report?;
+        report.pre_hooks = pre_hooks;
+
+        Ok(report)
     }
 
     pub async fn noop(&mut self) -> Result<()> {
-        self.client.noop().await
+        match self.client.noop().await {
+            Ok(()) => {
+                self.last_used = Instant::now();
+                Ok(())
+            }
+            Err(err) => {
+                self.connected = false;
+                Err(err)
+            }
+        }
+    }
+
+    /// Re-connect the client, for example after a connection loss.
+    async fn reconnect(&mut self) -> Result<()> {
+        debug!("re-connecting…");
+
+        let config = &self.smtp_config;
+        let network = self.account_config.network.as_ref();
+        let connector = self.stream_connector.as_ref();
+        let builder = &self.client_builder;
+
+        self.client = if config.is_encryption_enabled() {
+            build_tls_client(config, network, connector, builder).await
+        } else {
+            build_tcp_client(config, network, connector, builder).await
+        }?;
+        self.capabilities = None;
+        self.connected = true;
+        self.last_used = Instant::now();
+
+        Ok(())
+    }
+
+    /// Make sure the client is still connected before using it.
+    ///
+    /// Servers close idle connections after a while, without the
+    /// client noticing. When the connection is known to be broken,
+    /// or when it has been idle for more than [`IDLE_CHECK_DELAY`],
+    /// it is checked using NOOP then lazily re-established.
+    async fn ensure_connected(&mut self) -> Result<()> {
+        if self.connected && self.last_used.elapsed() < IDLE_CHECK_DELAY {
+            return Ok(());
+        }
+
+        if self.connected {
+            match self.noop().await {
+                Ok(()) => return Ok(()),
+                Err(_err) => {
+                    debug!("smtp connection lost while idle: {_err}");
+                    debug!("{_err:?}");
+                }
+            }
+        }
+
+        self.reconnect().await
+    }
+
+    /// Keep the connection of the given context alive by sending
+    /// NOOP after each idle period of the given interval.
+    ///
+    /// The task holds a weak reference to the context, and stops as
+    /// soon as the context is dropped. A failing NOOP marks the
+    /// connection as broken, so that it is re-established on the
+    /// next send.
+    fn spawn_keep_alive(ctx: &SmtpContextSync, interval: Duration) {
+        let ctx = Arc::downgrade(ctx);
+
+        runtime::spawn(async move {
+            loop {
+                runtime::sleep(interval).await;
+
+                let Some(ctx) = ctx.upgrade() else {
+                    break;
+                };
+
+                let mut ctx = ctx.lock().await;
+
+                if !ctx.connected || ctx.last_used.elapsed() < interval {
+                    continue;
+                }
+
+                if let Err(_err) = ctx.noop().await {
+                    debug!("cannot keep smtp connection alive: {_err}");
+                    debug!("{_err:?}");
+                }
+            }
+        });
     }
 }
 
@@ -154,6 +368,9 @@ pub struct SmtpContextBuilder {
 
     /// The SMTP configuration.
     smtp_config: Arc<SmtpConfig>,
+
+    /// The custom stream connector.
+    stream_connector: Option<SharedStreamConnector>,
 }
 
 impl SmtpContextBuilder {
@@ -161,14 +378,46 @@ pub fn new(account_config: Arc<AccountConfig>, smtp_config: Arc<SmtpConfig>) ->
         Self {
             account_config,
             smtp_config,
+            stream_connector: None,
         }
     }
+
+    /// Connect to the SMTP server using the given custom stream
+    /// connector instead of TCP streams.
+    ///
+    /// The network configuration of the account is ignored.
+    pub fn with_stream_connector(mut self, connector: impl Into<SharedStreamConnector>) -> Self {
+        self.stream_connector = Some(connector.into());
+        self
+    }
 }
 
 #[async_trait]
 impl BackendContextBuilder for SmtpContextBuilder {
     type Context = SmtpContextSync;
 
+    async fn probe_connection(&self, report: &mut CheckUpReport) -> bool {
+        let config = &self.smtp_config;
+
+        // STARTTLS is negotiated by the SMTP client itself, so it is
+        // covered by the authentication probe
+        let tls = if config.is_encryption_enabled() && !config.is_start_tls_encryption_enabled() {
+            Some(config.tls_options())
+        } else {
+            None
+        };
+
+        report
+            .probe_connection(
+                self.account_config.network.as_ref(),
+                self.stream_connector.as_ref(),
+                &config.host,
+                config.port,
+                tls,
+            )
+            .await
+    }
+
     fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
         Some(Arc::new(CheckUpSmtp::some_new_boxed))
     }
@@ -190,20 +439,43 @@ async fn build(self) -> AnyResult<Self::Context> {
                 .credentials(self.smtp_config.credentials().await?)
                 .implicit_tls(!self.smtp_config.is_start_tls_encryption_enabled());
 
+        let tls_opts = self.smtp_config.tls_options();
+
         if self.smtp_config.is_encryption_disabled() {
+            if !tls_opts.is_empty() {
+                warn!("smtp encryption disabled, ignoring ca bundle, pins and client certificate");
+            }
             client_builder = client_builder.allow_invalid_certs();
+        } else if !tls_opts.is_empty() {
+            client_builder.tls_connector = network::tls::build_connector(tls_opts)
+                .map_err(Error::BuildTlsConnectorSmtpError)?;
         }
 
-        let (client_builder, client) = build_client(&self.smtp_config, client_builder).await?;
+        let network = self.account_config.network.as_ref();
+        let connector = self.stream_connector.as_ref();
+        let (client_builder, client) =
+            build_client(&self.smtp_config, network, connector, client_builder).await?;
 
         let ctx = SmtpContext {
             account_config: self.account_config,
             smtp_config: self.smtp_config,
             client_builder,
+            stream_connector: self.stream_connector,
             client,
+            capabilities: None,
+            connected: true,
+            last_used: Instant::now(),
         };
 
-        Ok(Arc::new(Mutex::new(ctx)))
+        let keep_alive = ctx.smtp_config.keep_alive_interval();
+        let ctx = Arc::new(Mutex::new(ctx));
+
+        if let Some(interval) = keep_alive {
+            debug!("keeping smtp connection alive every {interval:?}");
+            SmtpContext::spawn_keep_alive(&ctx, interval);
+        }
+
+        Ok(ctx)
     }
 }
 
@@ -220,6 +492,34 @@ pub async fn send(&mut self, msg: impl IntoMessage<'_>) -> mail_send::Result<()>
         }
     }
 
+    /// Send the given message, then return which recipients accepted
+    /// it.
+    ///
+    /// Unlike [`SmtpClientStream::send`], recipients rejected by the
+    /// server do not abort the transaction. When chunking is enabled,
+    /// the message content is sent using BDAT instead of DATA.
+    pub async fn send_with_report(
+        &mut self,
+        msg: SmtpMessage<'_>,
+        chunking: bool,
+    ) -> mail_send::Result<SendReport> {
+        match self {
+            Self::Tcp(client) => send_with_report(client, msg, chunking).await,
+            Self::Tls(client) => send_with_report(client, msg, chunking).await,
+        }
+    }
+
+    pub async fn capabilities(
+        &mut self,
+        local_host: &str,
+        is_lmtp: bool,
+    ) -> mail_send::Result<EhloResponse<String>> {
+        match self {
+            Self::Tcp(client) => client.capabilities(local_host, is_lmtp).await,
+            Self::Tls(client) => client.capabilities(local_host, is_lmtp).await,
+        }
+    }
+
     pub async fn noop(&mut self) -> Result<()> {
         match self {
             Self::Tcp(client) => client.noop().await.map_err(Error::MailSendNoOpFailed),
@@ -249,38 +549,54 @@ pub fn some_new_boxed(ctx: &SmtpContextSync) -> Option<Box<dyn CheckUp>> {
 
 #[async_trait]
 impl CheckUp for CheckUpSmtp {
-    async fn check_up(&self) -> AnyResult<()> {
+    async fn check_up(&self) -> AnyResult<CheckUpReport> {
+        let mut report = CheckUpReport::new();
         let mut ctx = self.ctx.lock().await;
-        Ok(ctx.noop().await?)
+
+        let capabilities = async {
+            match ctx.capabilities().await {
+                Some(_) => Ok(()),
+                None => Err("cannot get capabilities from the server"),
+            }
+        };
+
+        report
+            .probe(CheckUpProbeKind::Capabilities, capabilities)
+            .await;
+
+        Ok(report)
     }
 }
 
 pub async fn build_client(
     smtp_config: &SmtpConfig,
+    network: Option<&NetworkConfig>,
+    connector: Option<&SharedStreamConnector>,
     #[cfg_attr(not(feature = "oauth2"), allow(unused_mut))]
     mut client_builder: mail_send::SmtpClientBuilder<String>,
 ) -> Result<(mail_send::SmtpClientBuilder<String>, SmtpClientStream)> {
     match (&smtp_config.auth, smtp_config.is_encryption_enabled()) {
         (SmtpAuthConfig::Passwd(_), false) => {
-            let client = build_tcp_client(&client_builder).await?;
+            let client = build_tcp_client(smtp_config, network, connector, &client_builder).await?;
             Ok((client_builder, client))
         }
         (SmtpAuthConfig::Passwd(_), true) => {
-            let client = build_tls_client(&client_builder).await?;
+            let client = build_tls_client(smtp_config, network, connector, &client_builder).await?;
             Ok((client_builder, client))
         }
         #[cfg(feature = "oauth2")]
         (SmtpAuthConfig::OAuth2(oauth2_config), false) => {
-            match Ok(build_tcp_client(&client_builder).await?) {
+            match Ok(build_tcp_client(smtp_config, network, connector, &client_builder).await?) {
                 Ok(client) => Ok((client_builder, client)),
                 Err(Error::ConnectTcpSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
                     warn!("authentication failed, refreshing access token and retrying…");
                     oauth2_config
-                        .refresh_access_token()
+                        .refresh_access_token_with_network(network)
                         .await
                         .map_err(|_| Error::RefreshingAccessTokenFailed)?;
                     client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tcp_client(&client_builder).await?;
+                    let client =
+                        build_tcp_client(smtp_config, network, connector, &client_builder).await?;
                     Ok((client_builder, client))
                 }
                 Err(err) => Err(err),
@@ -288,16 +604,17 @@ pub async fn build_client(
         }
         #[cfg(feature = "oauth2")]
         (SmtpAuthConfig::OAuth2(oauth2_config), true) => {
-            match Ok(build_tls_client(&client_builder).await?) {
+            match Ok(build_tls_client(smtp_config, network, connector, &client_builder).await?) {
                 Ok(client) => Ok((client_builder, client)),
                 Err(Error::ConnectTlsSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
                     warn!("authentication failed, refreshing access token and retrying…");
                     oauth2_config
-                        .refresh_access_token()
+                        .refresh_access_token_with_network(network)
                         .await
                         .map_err(|_| Error::RefreshingAccessTokenFailed)?;
                     client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tls_client(&client_builder).await?;
+                    let client =
+                        build_tls_client(smtp_config, network, connector, &client_builder).await?;
                     Ok((client_builder, client))
                 }
                 Err(err) => Err(err),
@@ -307,101 +624,807 @@ pub async fn build_client(
 }
 
 pub async fn build_tcp_client(
+    smtp_config: &SmtpConfig,
+    network: Option<&NetworkConfig>,
+    connector: Option<&SharedStreamConnector>,
     client_builder: &mail_send::SmtpClientBuilder<String>,
 ) -> Result<SmtpClientStream> {
-    match client_builder.connect_plain().await {
+    let client = if is_plain_connection(smtp_config, network, connector) {
+        client_builder.connect_plain().await
+    } else {
+        let stream = connect_network(smtp_config, network, connector).await?;
+        connect_plain_over(smtp_config, client_builder, stream).await
+    };
+
+    match client {
         Ok(client) => Ok(SmtpClientStream::Tcp(client)),
+        Err(err) if is_unpinned_certificate_error(&err) => {
+            let host = smtp_config.host.clone();
+            Err(Error::UnpinnedCertificateSmtpError(host, smtp_config.port))
+        }
         Err(err) => Err(Error::ConnectTcpSmtpError(err)),
     }
 }
 
 pub async fn build_tls_client(
+    smtp_config: &SmtpConfig,
+    network: Option<&NetworkConfig>,
+    connector: Option<&SharedStreamConnector>,
     client_builder: &mail_send::SmtpClientBuilder<String>,
 ) -> Result<SmtpClientStream> {
-    match client_builder.connect().await {
+    let client = if is_plain_connection(smtp_config, network, connector) {
+        client_builder.connect().await
+    } else {
+        let stream = connect_network(smtp_config, network, connector).await?;
+        connect_tls_over(smtp_config, client_builder, stream).await
+    };
+
+    match client {
         Ok(client) => Ok(SmtpClientStream::Tls(client)),
+        Err(err) if is_unpinned_certificate_error(&err) => {
+            let host = smtp_config.host.clone();
+            Err(Error::UnpinnedCertificateSmtpError(host, smtp_config.port))
+        }
         Err(err) => Err(Error::ConnectTlsSmtpError(err)),
     }
 }
 
-/// Transform a [`mail_parser::Message`] into a
-/// [`mail_send::smtp::message::Message`].
+/// Return `true` if the connection can be fully handled by
+/// [`mail_send::SmtpClientBuilder`].
 ///
-/// This function returns an error if no sender or no recipient is
-/// found in the original message.
-fn into_smtp_msg(msg: Message<'_>) -> Result<SmtpMessage<'_>> {
-    let mut mail_from = None;
-    let mut rcpt_to = HashSet::new();
-
-    for header in msg.headers() {
-        let key = &header.name;
-        let val = header.value();
-
-        match key {
-            HeaderName::From => match val {
-                HeaderValue::Address(Address::List(addrs)) => {
-                    if let Some(email) = addrs.first().and_then(find_valid_email) {
-                        mail_from = email.to_string().into();
-                    }
-                }
-                HeaderValue::Address(Address::Group(groups)) => {
-                    if let Some(group) = groups.first() {
-                        if let Some(email) = group.addresses.first().and_then(find_valid_email) {
-                            mail_from = email.to_string().into();
-                        }
-                    }
-                }
-                _ => (),
-            },
-            HeaderName::To | HeaderName::Cc | HeaderName::Bcc => match val {
-                HeaderValue::Address(Address::List(addrs)) => {
-                    rcpt_to.extend(addrs.iter().filter_map(find_valid_email));
-                }
-                HeaderValue::Address(Address::Group(groups)) => {
-                    rcpt_to.extend(
-                        groups
-                            .iter()
-                            .flat_map(|group| group.addresses.iter())
-                            .filter_map(find_valid_email),
-                    );
-                }
-                _ => (),
-            },
-            _ => (),
+/// Custom streams, network configurations and automatic OAuth 2.0
+/// methods require the connection to be handled manually.
+fn is_plain_connection(
+    smtp_config: &SmtpConfig,
+    network: Option<&NetworkConfig>,
+    connector: Option<&SharedStreamConnector>,
+) -> bool {
+    network.is_none() && connector.is_none() && !smtp_config.is_oauth2_method_auto()
+}
+
+/// Open a TCP connection to the SMTP server, using the given custom
+/// stream connector or network configuration.
+async fn connect_network(
+    smtp_config: &SmtpConfig,
+    network: Option<&NetworkConfig>,
+    connector: Option<&SharedStreamConnector>,
+) -> Result<TcpStream> {
+    let host = &smtp_config.host;
+    let port = smtp_config.port;
+
+    let stream = match connector {
+        Some(connector) => connector.connect(host, port).await,
+        None => network::connect(network, host, port).await,
+    };
+
+    stream.map_err(|err| Error::ConnectNetworkSmtpError(err, host.clone(), port))
+}
+
+/// Read the greeting then authenticate over the given stream.
+///
+/// This function mirrors [`mail_send::SmtpClientBuilder::connect_plain`],
+/// which cannot be used with custom streams.
+async fn connect_plain_over(
+    smtp_config: &SmtpConfig,
+    client_builder: &mail_send::SmtpClientBuilder<String>,
+    stream: TcpStream,
+) -> mail_send::Result<SmtpClient<TcpStream>> {
+    let timeout = client_builder.timeout;
+
+    runtime::timeout(timeout, async {
+        let mut client = SmtpClient { stream, timeout };
+        read_greeting(&mut client).await?;
+        authenticate(smtp_config, client_builder, &mut client).await?;
+        Ok(client)
+    })
+    .await
+    .map_err(|_| mail_send::Error::Timeout)?
+}
+
+/// Negotiate TLS, read the greeting then authenticate over the given
+/// stream.
+///
+/// This function mirrors [`mail_send::SmtpClientBuilder::connect`],
+/// which cannot be used with custom streams.
+async fn connect_tls_over(
+    smtp_config: &SmtpConfig,
+    client_builder: &mail_send::SmtpClientBuilder<String>,
+    stream: TcpStream,
+) -> mail_send::Result<SmtpClient<TlsStream<TcpStream>>> {
+    let timeout = client_builder.timeout;
+    let connector = &client_builder.tls_connector;
+    let hostname = client_builder.tls_hostname.as_str();
+
+    runtime::timeout(timeout, async {
+        let client = SmtpClient { stream, timeout };
+
+        let mut client = if client_builder.tls_implicit {
+            let mut client = client.into_tls(connector, hostname).await?;
+            read_greeting(&mut client).await?;
+            client
+        } else {
+            let mut client = client;
+            read_greeting(&mut client).await?;
+            client.ehlo(&client_builder.local_host).await?;
+            client.start_tls(connector, hostname).await?
         };
+
+        authenticate(smtp_config, client_builder, &mut client).await?;
+        Ok(client)
+    })
+    .await
+    .map_err(|_| mail_send::Error::Timeout)?
+}
+
+async fn read_greeting<T>(client: &mut SmtpClient<T>) -> mail_send::Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let reply = client.read().await?;
+
+    if !reply.is_positive_completion() {
+        return Err(mail_send::Error::UnexpectedReply(reply));
+    }
+
+    Ok(())
+}
+
+/// Authenticate using the credentials of the given client builder.
+///
+/// When the OAuth 2.0 method needs to be picked automatically and
+/// the server does not support XOAUTH2, OAUTHBEARER is used instead.
+async fn authenticate<T>(
+    smtp_config: &SmtpConfig,
+    client_builder: &mail_send::SmtpClientBuilder<String>,
+    client: &mut SmtpClient<T>,
+) -> mail_send::Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let capabilities = client
+        .capabilities(&client_builder.local_host, client_builder.is_lmtp)
+        .await?;
+
+    let Some(credentials) = &client_builder.credentials else {
+        return Ok(());
+    };
+
+    match client.authenticate(credentials, &capabilities).await {
+        Err(mail_send::Error::UnsupportedAuthMechanism) if smtp_config.is_oauth2_method_auto() => {
+            let Credentials::XOauth2 { secret, .. } = credentials else {
+                return Err(mail_send::Error::UnsupportedAuthMechanism);
+            };
+
+            debug!("XOAUTH2 not supported, falling back to OAUTHBEARER");
+            let credentials = smtp_config.oauth_bearer_credentials(secret);
+            client.authenticate(&credentials, &capabilities).await
+        }
+        res => res,
     }
+}
+
+/// Return `true` if the given error has been caused by a server
+/// certificate chain not matching any pin.
+fn is_unpinned_certificate_error(err: &mail_send::Error) -> bool {
+    match err {
+        mail_send::Error::Tls(err) => network::tls::is_unpinned_certificate_error(&**err),
+        mail_send::Error::Io(err) => network::tls::is_unpinned_certificate_error(err),
+        _ => false,
+    }
+}
+
+/// Build a [`mail_send::smtp::message::Message`] from the given
+/// envelope and prepared raw message, see [`prepare_message`].
+///
+/// When SMTPUTF8 is used, internationalized addresses are sent as
+/// they are. Otherwise their domain is converted to punycode, which
+/// fails for addresses with a non-ASCII local part.
+///
+/// When DSN options are given, the matching `RET` and `NOTIFY`
+/// parameters are added to the `MAIL FROM` and `RCPT TO` commands.
+///
+/// This function returns an error if no sender or no recipient is
+/// found in the envelope.
+fn into_smtp_msg<'a>(
+    envelope: SendEnvelope,
+    body: Cow<'a, [u8]>,
+    smtputf8: bool,
+    dsn: Option<&DsnOptions>,
+) -> Result<SmtpMessage<'a>> {
+    let SendEnvelope { mail_from, rcpt_to } = envelope;
 
     if rcpt_to.is_empty() {
         return Err(Error::SendMessageMissingRecipientError);
     }
 
-    let msg = SmtpMessage {
-        mail_from: mail_from
-            .ok_or(Error::SendMessageMissingSenderError)?
-            .into(),
-        rcpt_to: rcpt_to
-            .into_iter()
-            .map(|email| SmtpAddress {
+    let mail_from = mail_from.ok_or(Error::SendMessageMissingSenderError)?;
+
+    let mut parameters = Parameters::new();
+
+    let mail_from = if smtputf8 {
+        parameters.add("SMTPUTF8");
+        mail_from
+    } else {
+        to_ascii_email(mail_from)?
+    };
+
+    if let Some(ret) = dsn.and_then(|dsn| dsn.ret.as_ref()) {
+        parameters.add((String::from("RET"), ret.to_string()));
+    }
+
+    let mail_from = SmtpAddress {
+        email: mail_from.into(),
+        parameters,
+    };
+
+    let notify = dsn
+        .filter(|dsn| !dsn.notify.is_empty())
+        .map(|dsn| {
+            dsn.notify
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        })
+        .map(|notify| notify.join(","));
+
+    let rcpt_to = rcpt_to
+        .into_iter()
+        .map(|email| {
+            let email = if smtputf8 {
+                email
+            } else {
+                to_ascii_email(email)?
+            };
+
+            let mut parameters = Parameters::new();
+
+            if let Some(notify) = notify.clone() {
+                parameters.add((String::from("NOTIFY"), notify));
+            }
+
+            Ok(SmtpAddress {
                 email: email.into(),
-                ..Default::default()
+                parameters,
             })
-            .collect(),
-        body: msg.raw_message,
+        })
+        .collect::<Result<_>>()?;
+
+    let msg = SmtpMessage {
+        mail_from,
+        rcpt_to,
+        body,
     };
 
     Ok(msg)
 }
 
-fn find_valid_email(addr: &Addr) -> Option<String> {
-    match &addr.address {
-        None => None,
-        Some(email) => {
-            let email = email.trim();
-            if email.is_empty() {
-                None
-            } else {
-                Some(email.to_string())
+/// Send the given message using the given client, one command at a
+/// time, so that rejected recipients can be reported.
+///
+/// The transaction is reset without sending the message content when
+/// all recipients are rejected.
+async fn send_with_report<S: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<S>,
+    msg: SmtpMessage<'_>,
+    chunking: bool,
+) -> mail_send::Result<SendReport> {
+    let mut report = SendReport::default();
+
+    client
+        .mail_from(msg.mail_from.email.as_ref(), &msg.mail_from.parameters)
+        .await?;
+
+    for rcpt in &msg.rcpt_to {
+        match client.rcpt_to(rcpt.email.as_ref(), &rcpt.parameters).await {
+            Ok(()) => {
+                report.accepted.push(rcpt.email.to_string());
+            }
+            Err(mail_send::Error::UnexpectedReply(reply)) => {
+                let reply = format!("{} {}", reply.code, reply.message);
+                debug!("recipient {} rejected: {reply}", rcpt.email);
+                report.rejected.push((rcpt.email.to_string(), reply));
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
+    }
+
+    if report.accepted.is_empty() {
+        client.rset().await?;
+        return Ok(report);
+    }
+
+    let reply = if chunking {
+        bdat(client, msg.body.as_ref()).await?
+    } else {
+        data(client, msg.body.as_ref()).await?
+    };
+
+    report.sent = SentMessage::from_response(format!("{} {}", reply.code, reply.message));
+
+    Ok(report)
+}
+
+/// Send the given message content using a DATA command, then return
+/// the final reply of the server.
+///
+/// Unlike [`SmtpClient::data`], the final reply is kept, as it
+/// usually contains the queue identifier given to the message.
+async fn data<S: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<S>,
+    body: &[u8],
+) -> mail_send::Result<Response<String>> {
+    let reply = client.cmd(b"DATA\r\n").await?;
+
+    if reply.code != 354 {
+        return Err(mail_send::Error::UnexpectedReply(reply));
+    }
+
+    client.write_message(body).await?;
+
+    let reply = client.read().await?;
+
+    if !reply.is_positive_completion() {
+        return Err(mail_send::Error::UnexpectedReply(reply));
+    }
+
+    Ok(reply)
+}
+
+/// Send the given message content in chunks of [`BDAT_CHUNK_SIZE`]
+/// bytes using BDAT commands, then return the reply of the server to
+/// the last chunk.
+///
+/// Unlike DATA, BDAT does not require the content to be dot-stuffed,
+/// which means that the content does not need to be copied: chunks
+/// are written to the stream as they are.
+///
+/// See <https://www.rfc-editor.org/rfc/rfc3030>.
+async fn bdat<S: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<S>,
+    body: &[u8],
+) -> mail_send::Result<Response<String>> {
+    let mut chunks = body.chunks(BDAT_CHUNK_SIZE);
+    // NOTE: an empty body still needs an empty last chunk
+    let last = chunks.next_back().unwrap_or_default();
+
+    for chunk in chunks {
+        bdat_chunk(client, chunk, false).await?;
+    }
+
+    bdat_chunk(client, last, true).await
+}
+
+/// Send the given chunk using a BDAT command, then return the reply
+/// of the server.
+async fn bdat_chunk<S: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<S>,
+    chunk: &[u8],
+    last: bool,
+) -> mail_send::Result<Response<String>> {
+    let size = chunk.len();
+    let cmd = if last {
+        format!("BDAT {size} LAST\r\n")
+    } else {
+        format!("BDAT {size}\r\n")
+    };
+
+    client.write(cmd.as_bytes()).await?;
+    client.write(chunk).await?;
+
+    let reply = client.read().await?;
+
+    if !reply.is_positive_completion() {
+        return Err(mail_send::Error::UnexpectedReply(reply));
+    }
+
+    Ok(reply)
+}
+
+/// Convert the domain of the given email address to punycode.
+///
+/// Non-ASCII local parts cannot be converted, they require the
+/// SMTPUTF8 extension.
+fn to_ascii_email(email: String) -> Result<String> {
+    if email.is_ascii() {
+        return Ok(email);
+    }
+
+    let Some((local_part, domain)) = email.rsplit_once('@') else {
+        return Err(Error::SendMessageUtf8NotSupportedError(email));
+    };
+
+    if !local_part.is_ascii() {
+        return Err(Error::SendMessageUtf8NotSupportedError(email));
+    }
+
+    match idna::domain_to_ascii(domain) {
+        Ok(domain) => Ok(format!("{local_part}@{domain}")),
+        Err(_) => Err(Error::SendMessageUtf8NotSupportedError(email)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        io,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use async_trait::async_trait;
+    use mail_parser::MessageParser;
+    use secret::Secret;
+    use tokio::io::{split, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::{
+        bdat,
+        config::{SmtpAuthConfig, SmtpConfig, SmtpEncryptionKind},
+        into_smtp_msg, read_greeting, send_with_report, to_ascii_email, SmtpClient,
+        SmtpContextBuilder, BDAT_CHUNK_SIZE, IDLE_CHECK_DELAY,
+    };
+    use crate::{
+        account::config::{passwd::PasswdConfig, AccountConfig},
+        backend::context::BackendContextBuilder,
+        message::send::envelope::SendEnvelope,
+        network::stream::{BoxedStream, SharedStreamConnector, StreamConnector},
+        runtime,
+    };
+
+    const MSG: &[u8] = b"From: alice@localhost\r\nTo: bob@localhost\r\n\r\nHello!\r\n";
+
+    /// A session of the scripted SMTP server.
+    #[derive(Debug, Default)]
+    struct Session {
+        /// The commands received, without their trailing CRLF.
+        commands: Vec<String>,
+
+        /// The message content received using DATA or BDAT.
+        content: Vec<u8>,
+    }
+
+    type Reply = dyn Fn(usize, &str) -> Option<&'static str> + Send + Sync;
+
+    /// Connector to a scripted SMTP server.
+    ///
+    /// Each connection opens a new session, in which every command
+    /// is recorded then answered by the reply function, given the
+    /// index of the session. The server closes the connection when
+    /// the reply function returns `None`.
+    #[derive(Clone)]
+    struct ScriptedServer {
+        sessions: Arc<Mutex<Vec<Session>>>,
+        reply: Arc<Reply>,
+    }
+
+    impl ScriptedServer {
+        fn new(
+            reply: impl Fn(usize, &str) -> Option<&'static str> + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                sessions: Default::default(),
+                reply: Arc::new(reply),
+            }
+        }
+
+        fn sessions(&self) -> usize {
+            self.sessions.lock().unwrap().len()
+        }
+
+        fn commands(&self, session: usize) -> Vec<String> {
+            self.sessions.lock().unwrap()[session].commands.clone()
+        }
+
+        fn content(&self, session: usize) -> Vec<u8> {
+            self.sessions.lock().unwrap()[session].content.clone()
+        }
+
+        fn record(&self, session: usize, cmd: &str, content: &[u8]) {
+            let mut sessions = self.sessions.lock().unwrap();
+            sessions[session].commands.push(cmd.to_owned());
+            sessions[session].content.extend_from_slice(content);
+        }
+
+        fn record_content(&self, session: usize, content: &[u8]) {
+            let mut sessions = self.sessions.lock().unwrap();
+            sessions[session].content.extend_from_slice(content);
+        }
+
+        async fn serve(self, stream: DuplexStream, session: usize) -> io::Result<()> {
+            let (reader, mut writer) = split(stream);
+            let mut reader = BufReader::new(reader);
+
+            writer.write_all(b"220 localhost ESMTP\r\n").await?;
+
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await? == 0 {
+                    return Ok(());
+                }
+
+                let cmd = line.trim_end();
+
+                // BDAT chunks directly follow the command
+                let size = cmd
+                    .strip_prefix("BDAT ")
+                    .and_then(|args| args.split(' ').next())
+                    .and_then(|size| size.parse().ok());
+                let mut chunk = vec![0; size.unwrap_or_default()];
+                reader.read_exact(&mut chunk).await?;
+
+                self.record(session, cmd, &chunk);
+                let Some(reply) = (self.reply)(session, cmd) else {
+                    return Ok(());
+                };
+                writer.write_all(reply.as_bytes()).await?;
+
+                if cmd != "DATA" || !reply.starts_with("354") {
+                    continue;
+                }
+
+                // DATA content ends with a single dot line
+                loop {
+                    let mut line = Vec::new();
+                    if reader.read_until(b'\n', &mut line).await? == 0 {
+                        return Ok(());
+                    }
+                    if line == b".\r\n" {
+                        break;
+                    }
+                    self.record_content(session, &line);
+                }
+
+                self.record(session, ".", &[]);
+                let Some(reply) = (self.reply)(session, ".") else {
+                    return Ok(());
+                };
+                writer.write_all(reply.as_bytes()).await?;
+            }
+        }
+    }
+
+    #[async_trait]
+    impl StreamConnector for ScriptedServer {
+        async fn connect(&self, _host: &str, _port: u16) -> io::Result<BoxedStream> {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+
+            let session = {
+                let mut sessions = self.sessions.lock().unwrap();
+                sessions.push(Session::default());
+                sessions.len() - 1
+            };
+
+            runtime::spawn(self.clone().serve(server, session));
+
+            Ok(Box::new(client))
+        }
+    }
+
+    /// Reply to the given command like a regular server would.
+    fn reply(cmd: &str) -> Option<&'static str> {
+        let verb = cmd.split([' ', ':']).next().unwrap_or_default();
+
+        let reply = match verb.to_ascii_uppercase().as_str() {
+            "EHLO" => "250-localhost\r\n250-CHUNKING\r\n250 AUTH PLAIN\r\n",
+            "AUTH" => "235 Authentication succeeded\r\n",
+            "DATA" => "354 End data with <CR><LF>.<CR><LF>\r\n",
+            "." => "250 Ok: queued as 42\r\n",
+            "BDAT" if cmd.ends_with(" LAST") => "250 Ok: queued as 42\r\n",
+            "MAIL" | "RCPT" | "BDAT" | "NOOP" | "RSET" => "250 Ok\r\n",
+            "QUIT" => "221 Bye\r\n",
+            _ => "500 Unknown command\r\n",
+        };
+
+        Some(reply)
+    }
+
+    fn context_builder(server: &ScriptedServer, keep_alive: Option<u64>) -> SmtpContextBuilder {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let smtp_config = Arc::new(SmtpConfig {
+            host: "localhost".into(),
+            port: 25,
+            encryption: Some(SmtpEncryptionKind::None),
+            login: "alice".into(),
+            auth: SmtpAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            keep_alive,
+            ..Default::default()
+        });
+
+        SmtpContextBuilder::new(account_config, smtp_config)
+            .with_stream_connector(SharedStreamConnector::new(server.clone()))
+    }
+
+    #[test]
+    fn ascii_emails() {
+        let email = to_ascii_email("alice@localhost".into()).unwrap();
+        assert_eq!(email, "alice@localhost");
+
+        let email = to_ascii_email("alice@bücher.example".into()).unwrap();
+        assert_eq!(email, "alice@xn--bcher-kva.example");
+
+        assert!(to_ascii_email("jürgen@localhost".into()).is_err());
+    }
+
+    #[test]
+    fn smtp_msg_recipients() {
+        let msg = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost, Carol <carol@localhost>\r\n",
+            "Cc: Team: dave@localhost, erin@localhost;, frank@localhost\r\n",
+            "Bcc: grace@localhost, heidi@localhost\r\n",
+            "\r\n",
+            "Hello!\r\n",
+        );
+
+        let envelope = SendEnvelope::from(&MessageParser::new().parse(msg.as_bytes()).unwrap());
+        let msg = into_smtp_msg(envelope, Cow::Borrowed(msg.as_bytes()), false, None).unwrap();
+
+        let mut rcpt_to: Vec<_> = msg.rcpt_to.iter().map(|rcpt| rcpt.email.as_ref()).collect();
+        rcpt_to.sort();
+
+        assert_eq!(
+            rcpt_to,
+            [
+                "bob@localhost",
+                "carol@localhost",
+                "dave@localhost",
+                "erin@localhost",
+                "frank@localhost",
+                "grace@localhost",
+                "heidi@localhost",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_alive() {
+        // the first session is closed by the server on NOOP
+        let server = ScriptedServer::new(|session, cmd| match (session, cmd) {
+            (0, "NOOP") => None,
+            (_, cmd) => reply(cmd),
+        });
+
+        let ctx = context_builder(&server, Some(1)).build().await.unwrap();
+
+        // the keep-alive task sends NOOP after 1 second of idle,
+        // which marks the connection as broken
+        let broken = async {
+            while ctx.lock().await.connected {
+                runtime::sleep(Duration::from_millis(100)).await;
             }
+        };
+        runtime::timeout(Duration::from_secs(5), broken)
+            .await
+            .unwrap();
+
+        assert_eq!(server.commands(0).last().unwrap(), "NOOP");
+        assert_eq!(server.sessions(), 1);
+
+        // the connection is re-established on the next send
+        ctx.lock().await.send(MSG).await.unwrap();
+
+        assert_eq!(server.sessions(), 2);
+        assert!(server
+            .commands(1)
+            .contains(&format!("BDAT {} LAST", MSG.len())));
+        assert_eq!(server.content(1), MSG);
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_idle() {
+        // the first session is closed by the server on NOOP
+        let server = ScriptedServer::new(|session, cmd| match (session, cmd) {
+            (0, "NOOP") => None,
+            (_, cmd) => reply(cmd),
+        });
+
+        let ctx = context_builder(&server, None).build().await.unwrap();
+        let mut ctx = ctx.lock().await;
+
+        // a connection used recently is not checked
+        ctx.send(MSG).await.unwrap();
+        assert!(!server.commands(0).contains(&"NOOP".to_owned()));
+
+        // an idle connection is checked, then re-established
+        ctx.last_used = Instant::now().checked_sub(IDLE_CHECK_DELAY).unwrap();
+        ctx.send(MSG).await.unwrap();
+
+        assert_eq!(server.commands(0).last().unwrap(), "NOOP");
+        assert_eq!(server.sessions(), 2);
+
+        let commands = server.commands(1);
+        assert!(!commands.contains(&"NOOP".to_owned()));
+        assert!(commands.contains(&format!("BDAT {} LAST", MSG.len())));
+        assert_eq!(server.content(1), MSG);
+    }
+
+    #[tokio::test]
+    async fn bdat_chunks() {
+        let server = ScriptedServer::new(|_, cmd| reply(cmd));
+        let connector = SharedStreamConnector::new(server.clone());
+
+        let cases = [
+            (0, vec![String::from("BDAT 0 LAST")]),
+            (10, vec![String::from("BDAT 10 LAST")]),
+            (
+                BDAT_CHUNK_SIZE,
+                vec![format!("BDAT {BDAT_CHUNK_SIZE} LAST")],
+            ),
+            (
+                2 * BDAT_CHUNK_SIZE + 10,
+                vec![
+                    format!("BDAT {BDAT_CHUNK_SIZE}"),
+                    format!("BDAT {BDAT_CHUNK_SIZE}"),
+                    String::from("BDAT 10 LAST"),
+                ],
+            ),
+        ];
+
+        for (session, (size, expected_commands)) in cases.into_iter().enumerate() {
+            let stream = connector.connect("localhost", 25).await.unwrap();
+            let timeout = Duration::from_secs(5);
+            let mut client = SmtpClient { stream, timeout };
+            read_greeting(&mut client).await.unwrap();
+
+            // chunks are not dot-stuffed
+            let body: Vec<u8> = b"Hello!\r\n.\r\n"
+                .iter()
+                .copied()
+                .cycle()
+                .take(size)
+                .collect();
+
+            let reply = bdat(&mut client, &body).await.unwrap();
+            assert_eq!(reply.code, 250);
+
+            assert_eq!(server.commands(session), expected_commands);
+            assert_eq!(server.content(session), body);
         }
     }
+
+    #[tokio::test]
+    async fn partially_rejected_recipients() {
+        let server = ScriptedServer::new(|_, cmd| match cmd {
+            cmd if cmd.starts_with("RCPT TO:<carol@") => Some("550 No such user here\r\n"),
+            cmd => reply(cmd),
+        });
+        let connector = SharedStreamConnector::new(server.clone());
+
+        let msg = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost, carol@localhost, dave@localhost\r\n",
+            "\r\n",
+            "Hello!\r\n",
+        );
+        let envelope = SendEnvelope::from(&MessageParser::new().parse(msg.as_bytes()).unwrap());
+        let msg = into_smtp_msg(envelope, Cow::Borrowed(msg.as_bytes()), false, None).unwrap();
+
+        let stream = connector.connect("localhost", 25).await.unwrap();
+        let timeout = Duration::from_secs(5);
+        let mut client = SmtpClient { stream, timeout };
+        read_greeting(&mut client).await.unwrap();
+
+        let mut report = send_with_report(&mut client, msg, false).await.unwrap();
+        report.accepted.sort();
+
+        assert_eq!(report.accepted, ["bob@localhost", "dave@localhost"]);
+        assert_eq!(
+            report.rejected,
+            [(
+                String::from("carol@localhost"),
+                String::from("550 No such user here")
+            )]
+        );
+        assert_eq!(report.sent.queue_id.as_deref(), Some("42"));
+
+        // the message is sent to the accepted recipients only
+        let commands = server.commands(0);
+        assert_eq!(commands.iter().filter(|c| c.starts_with("RCPT")).count(), 3);
+        assert!(commands.contains(&String::from("DATA")));
+        assert!(!commands.contains(&String::from("RSET")));
+        assert!(String::from_utf8_lossy(&server.content(0)).contains("Hello!"));
+    }
 }
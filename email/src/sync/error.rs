@@ -33,6 +33,10 @@ pub enum Error {
     LeftContextNotConfiguredError(#[source] AnyBoxedError),
     #[error("cannot sync: right context is not configured")]
     RightContextNotConfiguredError(#[source] AnyBoxedError),
+    #[error("cannot read sync stats history at {1}")]
+    ReadStatsHistoryError(#[source] io::Error, PathBuf),
+    #[error("cannot write sync stats history at {1}")]
+    WriteStatsHistoryError(#[source] io::Error, PathBuf),
     #[error("cannot build sync pool context")]
     BuildSyncPoolContextError(#[source] AnyBoxedError),
 }
@@ -8,6 +8,7 @@
 pub mod hash;
 pub mod pool;
 pub mod report;
+pub mod stats;
 
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -18,15 +19,21 @@
     path::PathBuf,
     pin::Pin,
     sync::Arc,
+    time::Instant,
 };
 
 use advisory_lock::{AdvisoryFileLock, FileLockMode};
+use chrono::Utc;
 use dirs::{cache_dir, runtime_dir};
 use once_cell::sync::Lazy;
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
-use self::{hash::SyncHash, report::SyncReport};
+use self::{
+    hash::SyncHash,
+    report::SyncReport,
+    stats::{SyncStats, SyncStatsHistory},
+};
 use crate::{
     backend::{context::BackendContextBuilder, BackendBuilder},
     debug,
@@ -37,6 +44,7 @@
         self,
         sync::{
             config::{FolderSyncPermissions, FolderSyncStrategy},
+            deletion::FolderDeletion,
             hunk::{FolderName, FolderSyncHunk},
             patch::FolderSyncPatch,
         },
@@ -173,6 +181,26 @@ pub fn get_dry_run(&self) -> bool {
         self.config.dry_run.unwrap_or_default()
     }
 
+    // strict flags setters
+
+    pub fn set_some_strict_flags(&mut self, strict: Option<bool>) {
+        self.config.strict_flags = strict;
+    }
+
+    pub fn set_strict_flags(&mut self, strict: bool) {
+        self.set_some_strict_flags(Some(strict));
+    }
+
+    pub fn with_some_strict_flags(mut self, strict: Option<bool>) -> Self {
+        self.set_some_strict_flags(strict);
+        self
+    }
+
+    pub fn with_strict_flags(mut self, strict: bool) -> Self {
+        self.set_strict_flags(strict);
+        self
+    }
+
     // folder filters setters
 
     pub fn set_some_folder_filters(&mut self, f: Option<impl Into<FolderSyncStrategy>>) {
@@ -193,6 +221,64 @@ pub fn with_folder_filters(mut self, f: impl Into<FolderSyncStrategy>) -> Self {
         self
     }
 
+    // folder deletion setters
+
+    /// Set the maximum number of messages a folder can contain to be
+    /// deleted without confirmation.
+    pub fn set_some_folder_delete_threshold(&mut self, threshold: Option<usize>) {
+        self.config.folder_delete_threshold = threshold;
+    }
+
+    pub fn set_folder_delete_threshold(&mut self, threshold: usize) {
+        self.set_some_folder_delete_threshold(Some(threshold));
+    }
+
+    pub fn with_some_folder_delete_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.set_some_folder_delete_threshold(threshold);
+        self
+    }
+
+    pub fn with_folder_delete_threshold(mut self, threshold: usize) -> Self {
+        self.set_folder_delete_threshold(threshold);
+        self
+    }
+
+    /// Set the handler confirming folder deletions.
+    ///
+    /// See [`folder::sync::deletion::FolderDeletionPolicy`].
+    pub fn set_some_folder_deletion_confirmation<F: Future<Output = bool> + Send + 'static>(
+        &mut self,
+        confirm: Option<impl Fn(FolderDeletion) -> F + Send + Sync + 'static>,
+    ) {
+        self.config.folder_deletion_confirmation = match confirm {
+            Some(confirm) => Some(Arc::new(move |deletion| Box::pin(confirm(deletion)))),
+            None => None,
+        };
+    }
+
+    pub fn set_folder_deletion_confirmation<F: Future<Output = bool> + Send + 'static>(
+        &mut self,
+        confirm: impl Fn(FolderDeletion) -> F + Send + Sync + 'static,
+    ) {
+        self.set_some_folder_deletion_confirmation(Some(confirm));
+    }
+
+    pub fn with_some_folder_deletion_confirmation<F: Future<Output = bool> + Send + 'static>(
+        mut self,
+        confirm: Option<impl Fn(FolderDeletion) -> F + Send + Sync + 'static>,
+    ) -> Self {
+        self.set_some_folder_deletion_confirmation(confirm);
+        self
+    }
+
+    pub fn with_folder_deletion_confirmation<F: Future<Output = bool> + Send + 'static>(
+        mut self,
+        confirm: impl Fn(FolderDeletion) -> F + Send + Sync + 'static,
+    ) -> Self {
+        self.set_folder_deletion_confirmation(confirm);
+        self
+    }
+
     // left folder permissions setters
 
     pub fn set_some_left_folder_permissions(
@@ -377,6 +463,16 @@ pub fn get_cache_dir(&self) -> Result<PathBuf> {
             .ok_or(Error::GetCacheDirectorySyncError.into())
     }
 
+    /// Get the statistics history of the synchronization between
+    /// the left and the right backends.
+    ///
+    /// The history is stored in the cache directory.
+    pub fn get_stats_history(&self) -> Result<SyncStatsHistory> {
+        let name = format!("{}-{}.stats", self.left_hash, self.right_hash);
+        let path = self.get_cache_dir()?.join(name);
+        Ok(SyncStatsHistory::new(path))
+    }
+
     pub fn get_left_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBuilder>> {
         let left_config = self.left_builder.account_config.clone();
         let root_dir = self.get_cache_dir()?.join(&self.left_hash);
@@ -408,6 +504,11 @@ pub fn get_right_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBui
     // build
 
     pub async fn sync(self) -> Result<SyncReport> {
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let dry_run = self.get_dry_run();
+        let stats_history = self.get_stats_history()?;
+
         let left_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.left_hash));
         debug!("locking left sync file {left_lock_file_path:?}");
         let left_lock_file = OpenOptions::new()
@@ -520,6 +621,16 @@ pub async fn sync(self) -> Result<SyncReport> {
 
         folder::sync::expunge::<L, R>(ctx.clone(), &report.folder.names).await;
 
+        report.stats = SyncStats::new(started_at, timer.elapsed(), &report);
+
+        if !dry_run {
+            debug!("saving sync stats to {:?}", stats_history.path());
+            if let Err(_err) = stats_history.push(&report.stats) {
+                debug!("cannot save sync stats: {_err}");
+                debug!("{_err:?}");
+            }
+        }
+
         debug!("unlocking sync files");
         left_lock_file
             .unlock()
@@ -547,6 +658,7 @@ pub enum SyncEvent {
     ListedRightCachedFolders(usize),
     ListedRightFolders(usize),
     ListedAllFolders,
+    InvalidatedFolderCache(FolderName, SyncDestination),
     GeneratedFolderPatch(BTreeMap<FolderName, FolderSyncPatch>),
     ProcessedFolderHunk(FolderSyncHunk),
     ProcessedAllFolderHunks,
@@ -591,6 +703,9 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             SyncEvent::ListedAllFolders => {
                 write!(f, "Listed all folders")
             }
+            SyncEvent::InvalidatedFolderCache(folder, dest) => {
+                write!(f, "Invalidated {dest} cache of {folder}")
+            }
             SyncEvent::GeneratedFolderPatch(patch) => {
                 let n = patch.keys().count();
                 let p = patch.values().flatten().count();
@@ -13,6 +13,7 @@
     flag::sync::config::FlagSyncPermissions,
     folder::sync::{
         config::{FolderSyncPermissions, FolderSyncStrategy},
+        deletion::{FolderDeletionConfirmationHandler, FolderDeletionPolicy},
         hunk::FolderSyncHunk,
         patch::FolderSyncPatches,
     },
@@ -31,9 +32,12 @@ pub struct SyncPoolConfig {
     pub right_message_permissions: Option<MessageSyncPermissions>,
     pub pool_size: Option<usize>,
     pub folder_filters: Option<FolderSyncStrategy>,
+    pub folder_delete_threshold: Option<usize>,
+    pub folder_deletion_confirmation: Option<Arc<FolderDeletionConfirmationHandler>>,
     pub envelope_filters: Option<EnvelopeSyncFilters>,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: Option<bool>,
+    pub strict_flags: Option<bool>,
 }
 
 #[derive(Clone)]
@@ -169,6 +173,20 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let folder_delete_threshold = self.config.folder_delete_threshold.or_else(|| {
+            self.right_builder
+                .account_config
+                .folder
+                .as_ref()
+                .and_then(|c| c.sync.as_ref())
+                .and_then(|c| c.delete_threshold)
+        });
+
+        let folder_deletion_policy = FolderDeletionPolicy {
+            threshold: folder_delete_threshold,
+            confirmation: self.config.folder_deletion_confirmation.clone(),
+        };
+
         let envelope_filters = self
             .config
             .envelope_filters
@@ -183,6 +201,16 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let strict_flags = self.config.strict_flags.unwrap_or_else(|| {
+            self.left_builder
+                .account_config
+                .is_flag_strict_mode_enabled()
+                || self
+                    .right_builder
+                    .account_config
+                    .is_flag_strict_mode_enabled()
+        });
+
         let (left_cache, left, right_cache, right) = tokio::try_join!(
             self.left_cache_builder.build(),
             self.left_builder.build(),
@@ -202,9 +230,11 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             right_flag_permissions,
             right_message_permissions,
             folder_filters,
+            folder_deletion_policy,
             envelope_filters,
             handler: self.config.handler,
             dry_run: self.config.dry_run.unwrap_or_default(),
+            strict_flags,
         })
     }
 }
@@ -221,9 +251,11 @@ pub struct SyncPoolContext<L: BackendContext, R: BackendContext> {
     pub right_flag_permissions: FlagSyncPermissions,
     pub right_message_permissions: MessageSyncPermissions,
     pub folder_filters: FolderSyncStrategy,
+    pub folder_deletion_policy: FolderDeletionPolicy,
     pub envelope_filters: EnvelopeSyncFilters,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: bool,
+    pub strict_flags: bool,
 }
 
 impl<L: BackendContext, R: BackendContext> SyncPoolContext<L, R> {
@@ -3,12 +3,13 @@
 //! Module dedicated to synchronization reporting. The main structure
 //! of thi module is [`SyncReport`].
 
+use super::stats::SyncStats;
 use crate::{email::sync::report::EmailSyncReport, folder::sync::report::FolderSyncReport};
 
 /// The synchronization report.
 ///
 /// A report is just a struct containing reports from the folders and
-/// the emails synchronization.
+/// the emails synchronization, plus summary statistics.
 #[derive(Debug, Default)]
 pub struct SyncReport {
     /// The report of folder synchronization.
@@ -16,4 +17,7 @@ pub struct SyncReport {
 
     /// The report of email synchronization.
     pub email: EmailSyncReport,
+
+    /// The summary statistics of the synchronization run.
+    pub stats: SyncStats,
 }
@@ -0,0 +1,318 @@
+//! # Sync statistics
+//!
+//! Module dedicated to synchronization statistics. Summary statistics
+//! of every synchronization run are persisted next to the sync cache,
+//! so that apps can display sync health trends. The main structures
+//! of this module are [`SyncStats`] and [`SyncStatsHistory`].
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use super::{report::SyncReport, Error, Result};
+use crate::debug;
+
+/// The summary statistics of a synchronization run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncStats {
+    /// The date the synchronization started at.
+    pub started_at: DateTime<Utc>,
+
+    /// The time the synchronization took.
+    pub duration: Duration,
+
+    /// The number of processed folder hunks.
+    pub folder_hunks: usize,
+
+    /// The number of processed email hunks.
+    pub email_hunks: usize,
+
+    /// The number of hunks that could not be applied.
+    pub errors: usize,
+}
+
+impl SyncStats {
+    /// Build statistics from the given synchronization report.
+    pub fn new(started_at: DateTime<Utc>, duration: Duration, report: &SyncReport) -> Self {
+        let folder_errors = report.folder.patch.iter().filter(|(_, err)| err.is_some());
+        let email_errors = report.email.patch.iter().filter(|(_, err)| err.is_some());
+
+        Self {
+            started_at,
+            duration,
+            folder_hunks: report.folder.patch.len(),
+            email_hunks: report.email.patch.len(),
+            errors: folder_errors.count() + email_errors.count(),
+        }
+    }
+
+    /// Get the total number of processed hunks.
+    pub fn hunks(&self) -> usize {
+        self.folder_hunks + self.email_hunks
+    }
+
+    /// Get the number of successfully applied hunks.
+    pub fn applied_hunks(&self) -> usize {
+        self.hunks().saturating_sub(self.errors)
+    }
+
+    /// Serialize the statistics into a single history line.
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.started_at.to_rfc3339(),
+            self.duration.as_millis(),
+            self.folder_hunks,
+            self.email_hunks,
+            self.errors,
+        )
+    }
+
+    /// Parse statistics from a single history line.
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        let started_at = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let duration = Duration::from_millis(fields.next()?.parse().ok()?);
+        let folder_hunks = fields.next()?.parse().ok()?;
+        let email_hunks = fields.next()?.parse().ok()?;
+        let errors = fields.next()?.parse().ok()?;
+
+        Some(Self {
+            started_at: started_at.with_timezone(&Utc),
+            duration,
+            folder_hunks,
+            email_hunks,
+            errors,
+        })
+    }
+}
+
+/// The synchronization statistics history.
+///
+/// The history is stored as a plain text file, one run per line,
+/// from the oldest to the most recent one. Only the last
+/// [`SyncStatsHistory::MAX_RUNS`] runs are kept.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncStatsHistory {
+    path: PathBuf,
+}
+
+impl SyncStatsHistory {
+    /// The maximum number of runs kept in the history.
+    pub const MAX_RUNS: usize = 100;
+
+    /// Create a new history stored at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get the path of the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the given statistics to the history.
+    pub fn push(&self, stats: &SyncStats) -> Result<()> {
+        let mut lines = self.read_lines()?;
+        lines.push(stats.to_line());
+
+        let overflow = lines.len().saturating_sub(Self::MAX_RUNS);
+        let mut contents = lines[overflow..].join("\n");
+        contents.push('\n');
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| Error::WriteStatsHistoryError(err, self.path.clone()))?;
+        }
+
+        fs::write(&self.path, contents)
+            .map_err(|err| Error::WriteStatsHistoryError(err, self.path.clone()))
+    }
+
+    /// Get statistics of the last `n` runs, from the most recent to
+    /// the oldest one.
+    pub fn last(&self, n: usize) -> Result<Vec<SyncStats>> {
+        let stats = self
+            .read_lines()?
+            .iter()
+            .rev()
+            .filter_map(|line| {
+                let stats = SyncStats::from_line(line);
+                if stats.is_none() {
+                    debug!("cannot parse sync stats line {line:?}, skipping it");
+                }
+                stats
+            })
+            .take(n)
+            .collect();
+
+        Ok(stats)
+    }
+
+    /// Get the trend of the last `n` runs.
+    pub fn trend(&self, n: usize) -> Result<SyncStatsTrend> {
+        Ok(SyncStatsTrend::new(&self.last(n)?))
+    }
+
+    fn read_lines(&self) -> Result<Vec<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(ToOwned::to_owned)
+                .collect()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(Error::ReadStatsHistoryError(err, self.path.clone())),
+        }
+    }
+}
+
+/// The trend of synchronization runs.
+///
+/// The trend compares the most recent half of the runs with the
+/// oldest half, which helps to detect regressions like growing
+/// durations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncStatsTrend {
+    /// The number of runs the trend is based on.
+    pub runs: usize,
+
+    /// The average duration of the runs.
+    pub average_duration: Duration,
+
+    /// The average number of processed hunks per run.
+    pub average_hunks: f64,
+
+    /// The ratio of runs containing at least one error.
+    pub error_rate: f64,
+
+    /// The ratio between the average duration of the most recent
+    /// half of the runs and the oldest half.
+    ///
+    /// A ratio greater than 1 means that durations are growing. It
+    /// is `None` when there are less than 2 runs.
+    pub duration_growth: Option<f64>,
+}
+
+impl SyncStatsTrend {
+    /// Compute the trend of the given runs, ordered from the most
+    /// recent to the oldest one.
+    pub fn new(stats: &[SyncStats]) -> Self {
+        if stats.is_empty() {
+            return Self::default();
+        }
+
+        let runs = stats.len();
+        let hunks: usize = stats.iter().map(SyncStats::hunks).sum();
+        let failed = stats.iter().filter(|s| s.errors > 0).count();
+
+        let duration_growth = if runs < 2 {
+            None
+        } else {
+            let (recent, oldest) = stats.split_at(runs / 2);
+            let recent = average_duration(recent).as_secs_f64();
+            let oldest = average_duration(oldest).as_secs_f64();
+
+            if oldest > 0.0 {
+                Some(recent / oldest)
+            } else {
+                None
+            }
+        };
+
+        Self {
+            runs,
+            average_duration: average_duration(stats),
+            average_hunks: hunks as f64 / runs as f64,
+            error_rate: failed as f64 / runs as f64,
+            duration_growth,
+        }
+    }
+
+    /// Return `true` if the average duration of the most recent runs
+    /// is greater than the average duration of the oldest runs by
+    /// the given ratio (for example 1.5 for 50%).
+    pub fn is_duration_growing(&self, ratio: f64) -> bool {
+        self.duration_growth
+            .map(|growth| growth > ratio)
+            .unwrap_or_default()
+    }
+}
+
+fn average_duration(stats: &[SyncStats]) -> Duration {
+    if stats.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let total: Duration = stats.iter().map(|s| s.duration).sum();
+    total / stats.len() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{SyncStats, SyncStatsHistory, SyncStatsTrend};
+
+    fn stats(secs: u64, errors: usize) -> SyncStats {
+        SyncStats {
+            started_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            duration: Duration::from_secs(secs),
+            folder_hunks: 2,
+            email_hunks: 8,
+            errors,
+        }
+    }
+
+    #[test]
+    fn line_roundtrip() {
+        let stats = stats(3, 1);
+        let line = stats.to_line();
+
+        assert_eq!(line, "2024-01-01T00:00:00+00:00\t3000\t2\t8\t1");
+        assert_eq!(SyncStats::from_line(&line), Some(stats));
+        assert_eq!(SyncStats::from_line("invalid"), None);
+    }
+
+    #[test]
+    fn history_push_and_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = SyncStatsHistory::new(dir.path().join("sync.stats"));
+
+        assert_eq!(history.last(10).unwrap(), vec![]);
+
+        for secs in 0..(SyncStatsHistory::MAX_RUNS as u64 + 5) {
+            history.push(&stats(secs, 0)).unwrap();
+        }
+
+        let last = history.last(usize::MAX).unwrap();
+        assert_eq!(last.len(), SyncStatsHistory::MAX_RUNS);
+        assert_eq!(last[0].duration, Duration::from_secs(104));
+
+        let last = history.last(2).unwrap();
+        assert_eq!(last, vec![stats(104, 0), stats(103, 0)]);
+    }
+
+    #[test]
+    fn trend() {
+        assert_eq!(SyncStatsTrend::new(&[]), SyncStatsTrend::default());
+
+        let trend = SyncStatsTrend::new(&[stats(4, 1), stats(4, 0), stats(2, 0), stats(2, 0)]);
+
+        assert_eq!(trend.runs, 4);
+        assert_eq!(trend.average_duration, Duration::from_secs(3));
+        assert_eq!(trend.average_hunks, 10.0);
+        assert_eq!(trend.error_rate, 0.25);
+        assert_eq!(trend.duration_growth, Some(2.0));
+        assert!(trend.is_duration_growing(1.5));
+        assert!(!trend.is_duration_growing(2.5));
+    }
+}
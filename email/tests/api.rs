@@ -0,0 +1,79 @@
+//! Semver checks of the public API.
+//!
+//! This suite fails to compile as soon as a public path used by
+//! downstream consumers moves or disappears. Moving an item is fine,
+//! as long as its old path is kept as a deprecated shim (see the
+//! `compat` module) and checked below.
+
+use std::any::TypeId;
+
+/// Assert that the given type is reachable from the public API.
+fn stable<T: ?Sized + 'static>() {
+    //
+}
+
+/// Assert that the given deprecated type is the same as the given
+/// stable type.
+fn same<Old: ?Sized + 'static, New: ?Sized + 'static>() {
+    assert_eq!(TypeId::of::<Old>(), TypeId::of::<New>());
+}
+
+#[test]
+fn stable_paths() {
+    stable::<email::account::config::AccountConfig>();
+    stable::<dyn email::backend::context::BackendContext>();
+    stable::<email::envelope::Envelope>();
+    stable::<email::envelope::Envelopes>();
+    stable::<email::flag::Flag>();
+    stable::<email::flag::Flags>();
+    stable::<email::folder::Folder>();
+    stable::<email::folder::FolderKind>();
+    stable::<email::folder::Folders>();
+    stable::<email::message::Message<'static>>();
+    stable::<email::message::send::SendMessageOptions>();
+    stable::<email::message::send::SendReport>();
+    stable::<email::message::send::SentMessage>();
+    stable::<email::template::Template>();
+    stable::<email::template::new::NewTemplateBuilder>();
+    stable::<email::template::reply::ReplyTemplateBuilder<'static>>();
+    stable::<email::template::forward::ForwardTemplateBuilder<'static>>();
+    stable::<dyn email::folder::list::ListFolders>();
+    stable::<dyn email::envelope::list::ListEnvelopes>();
+    stable::<dyn email::message::send::SendMessage>();
+    stable::<email::AnyBoxedError>();
+
+    #[cfg(feature = "imap")]
+    stable::<email::imap::config::ImapConfig>();
+    #[cfg(feature = "maildir")]
+    stable::<email::maildir::config::MaildirConfig>();
+    #[cfg(feature = "smtp")]
+    stable::<email::smtp::config::SmtpConfig>();
+    #[cfg(feature = "sendmail")]
+    stable::<email::sendmail::config::SendmailConfig>();
+    #[cfg(feature = "autoconfig")]
+    stable::<email::autoconfig::config::AutoConfig>();
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_paths() {
+    same::<email::template::new::NewTplBuilder, email::template::new::NewTemplateBuilder>();
+    same::<
+        email::template::reply::ReplyTplBuilder<'static>,
+        email::template::reply::ReplyTemplateBuilder<'static>,
+    >();
+    same::<
+        email::template::forward::ForwardTplBuilder<'static>,
+        email::template::forward::ForwardTemplateBuilder<'static>,
+    >();
+
+    #[cfg(feature = "smtp")]
+    same::<email::sender::smtp::config::SmtpConfig, email::smtp::config::SmtpConfig>();
+    #[cfg(feature = "sendmail")]
+    {
+        use email::{sender::sendmail::config as old, sendmail::config as new};
+        same::<old::SendmailConfig, new::SendmailConfig>();
+    }
+    #[cfg(feature = "autoconfig")]
+    same::<email::account::discover::config::AutoConfig, email::autoconfig::config::AutoConfig>();
+}
@@ -0,0 +1,112 @@
+#![cfg(all(feature = "imap", feature = "email-testing-server"))]
+
+use std::sync::Arc;
+
+use concat_with::concat_line;
+use email::{
+    account::config::{passwd::PasswdConfig, AccountConfig},
+    backend::{Backend, BackendBuilder},
+    envelope::{list::ListEnvelopes, Id},
+    flag::{add::AddFlags, Flag},
+    folder::add::AddFolder,
+    imap::{
+        config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
+        metrics::{ImapCommand, MemoryImapMetricsSink},
+        ImapContext, ImapContextBuilder,
+    },
+    message::{add::AddMessage, get::GetMessages, peek::PeekMessages},
+};
+use email_testing_server::with_email_testing_server;
+use mml::MmlCompilerBuilder;
+use secret::Secret;
+
+/// Assert the command budgets of key operations, in order to prevent
+/// performance regressions like one FETCH per message.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_command_budgets() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(ImapEncryptionKind::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let sink = Arc::new(MemoryImapMetricsSink::default());
+
+        let imap_ctx = ImapContextBuilder::new(account_config.clone(), imap_config)
+            .with_metrics_sink(sink.clone());
+        let imap = BackendBuilder::new(account_config.clone(), imap_ctx)
+            .build::<Backend<ImapContext>>()
+            .await
+            .unwrap();
+
+        imap.add_folder("Budget").await.unwrap();
+
+        let tpl = concat_line!(
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "Subject: subject",
+            "",
+            "<#part type=text/plain>",
+            "Hello, world!",
+            "<#/part>",
+        );
+        let compiler = MmlCompilerBuilder::new().build(tpl).unwrap();
+        let email = compiler.compile().await.unwrap().into_vec().unwrap();
+
+        for _ in 0..3 {
+            imap.add_message("Budget", &email).await.unwrap();
+        }
+
+        // APPEND does not need any mailbox to be selected
+        let commands = sink.take("add_message_with_flags");
+        assert_eq!(commands.get(ImapCommand::Append), 3);
+        assert_eq!(commands.get(ImapCommand::Select), 0);
+
+        let envelopes = imap
+            .list_envelopes("Budget", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(envelopes.len(), 3);
+
+        let commands = sink.take("list_envelopes");
+        assert_eq!(commands.get(ImapCommand::Select), 1, "{commands}");
+        assert_eq!(commands.get(ImapCommand::Fetch), 1, "{commands}");
+        assert_eq!(commands.total(), 2, "{commands}");
+
+        let ids: Vec<_> = envelopes.iter().map(|e| e.id.clone()).collect();
+        let id = Id::multiple(ids);
+
+        imap.get_messages("Budget", &id).await.unwrap();
+        let commands = sink.take("get_messages");
+        assert_eq!(commands.get(ImapCommand::Select), 1, "{commands}");
+        assert!(commands.get(ImapCommand::Fetch) <= 2, "{commands}");
+
+        imap.peek_messages("Budget", &id).await.unwrap();
+        let commands = sink.take("peek_messages");
+        assert_eq!(commands.get(ImapCommand::Select), 1, "{commands}");
+        assert_eq!(commands.get(ImapCommand::Fetch), 1, "{commands}");
+
+        imap.add_flag("Budget", &id, Flag::Flagged).await.unwrap();
+        let commands = sink.take("add_flags");
+        assert_eq!(commands.get(ImapCommand::Select), 1, "{commands}");
+        assert_eq!(commands.get(ImapCommand::Store), 1, "{commands}");
+
+        // batch uploads lock a single client of the pool
+        let ids = imap
+            .add_messages("Budget", &[email.as_slice(), email.as_slice()])
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 2);
+
+        let commands = sink.take("add_messages_with_flags");
+        assert_eq!(commands.get(ImapCommand::Append), 2, "{commands}");
+        assert_eq!(commands.get(ImapCommand::Select), 0, "{commands}");
+    })
+    .await
+}
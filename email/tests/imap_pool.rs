@@ -0,0 +1,100 @@
+#![cfg(all(feature = "imap", feature = "email-testing-server"))]
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use email::{
+    account::config::{passwd::PasswdConfig, AccountConfig},
+    backend::context::BackendContextBuilder,
+    imap::{
+        config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
+        ImapContextBuilder,
+    },
+};
+use email_testing_server::with_email_testing_server;
+use secret::Secret;
+use tokio::{task::JoinSet, time};
+
+const POOL_SIZE: u8 = 3;
+const CALLERS: usize = 12;
+
+/// Assert that callers outnumbering the clients of the pool share
+/// them without panicking, and that every client is given back to the
+/// pool once released.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_pool() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(ImapEncryptionKind::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let imap_ctx = ImapContextBuilder::new(account_config, imap_config)
+            .with_pool_size(POOL_SIZE)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(imap_ctx.pool_size(), POOL_SIZE as usize);
+
+        let busy = Arc::new(AtomicUsize::new(0));
+        let max_busy = Arc::new(AtomicUsize::new(0));
+        let ids = Arc::new(Mutex::new(HashSet::new()));
+        let mut callers = JoinSet::new();
+
+        for _ in 0..CALLERS {
+            let imap_ctx = imap_ctx.clone();
+            let busy = busy.clone();
+            let max_busy = max_busy.clone();
+            let ids = ids.clone();
+
+            callers.spawn(async move {
+                let mut client = imap_ctx.client().await;
+
+                let n = busy.fetch_add(1, Ordering::SeqCst) + 1;
+                max_busy.fetch_max(n, Ordering::SeqCst);
+                ids.lock().unwrap().insert(client.id);
+
+                client.noop().await.unwrap();
+                time::sleep(Duration::from_millis(50)).await;
+
+                busy.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+
+        while let Some(res) = callers.join_next().await {
+            res.unwrap();
+        }
+
+        // no more clients than the pool size are used at once
+        assert!(max_busy.load(Ordering::SeqCst) <= POOL_SIZE as usize);
+        assert!(ids.lock().unwrap().len() <= POOL_SIZE as usize);
+        assert_eq!(busy.load(Ordering::SeqCst), 0);
+
+        // every client has been given back to the pool
+        let clients = time::timeout(Duration::from_secs(5), async {
+            let mut clients = Vec::new();
+            for _ in 0..POOL_SIZE {
+                clients.push(imap_ctx.client().await);
+            }
+            clients
+        })
+        .await
+        .expect("all clients should be free");
+
+        let ids: HashSet<_> = clients.iter().map(|client| client.id).collect();
+        assert_eq!(ids.len(), POOL_SIZE as usize);
+    })
+    .await
+}
@@ -0,0 +1,291 @@
+#![cfg(all(feature = "imap", feature = "email-testing-server"))]
+
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use email::{
+    account::config::{passwd::PasswdConfig, AccountConfig},
+    backend::{
+        cancel::{cancellable, CancellationToken},
+        context::BackendContextBuilder,
+    },
+    imap::{
+        config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
+        ImapContextBuilder,
+    },
+    network::stream::{BoxedStream, SharedStreamConnector, StreamConnector},
+    AnyResult,
+};
+use email_testing_server::with_email_testing_server;
+use imap_next::imap_types::{flag::Flag, sequence::SequenceSet};
+use secret::Secret;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    time,
+};
+
+/// The controls of a [`KillableStream`].
+#[derive(Default)]
+struct StreamControls {
+    killed: AtomicBool,
+    stalled: AtomicBool,
+}
+
+/// TCP stream that can be killed in order to simulate a connection
+/// loss, or stalled in order to simulate a hung server.
+struct KillableStream {
+    inner: TcpStream,
+    controls: Arc<StreamControls>,
+}
+
+impl AsyncRead for KillableStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.controls.killed.load(Ordering::SeqCst) {
+            return Poll::Ready(Ok(()));
+        }
+
+        // stalled streams are never resumed, so there is no need to
+        // register the waker
+        if self.controls.stalled.load(Ordering::SeqCst) {
+            return Poll::Pending;
+        }
+
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for KillableStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.controls.killed.load(Ordering::SeqCst) {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Connector opening killable streams to the testing server.
+#[derive(Clone, Default)]
+struct KillableConnector {
+    connections: Arc<AtomicUsize>,
+    controls: Arc<Mutex<Arc<StreamControls>>>,
+}
+
+impl KillableConnector {
+    /// Kill the last opened connection.
+    fn kill(&self) {
+        let controls = self.controls.lock().unwrap();
+        controls.killed.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop reading from the last opened connection.
+    fn stall(&self) {
+        let controls = self.controls.lock().unwrap();
+        controls.stalled.store(true, Ordering::SeqCst);
+    }
+
+    fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl StreamConnector for KillableConnector {
+    async fn connect(&self, host: &str, port: u16) -> io::Result<BoxedStream> {
+        let inner = TcpStream::connect((host, port)).await?;
+        let controls = Arc::new(StreamControls::default());
+
+        *self.controls.lock().unwrap() = controls.clone();
+        self.connections.fetch_add(1, Ordering::SeqCst);
+
+        Ok(Box::new(KillableStream { inner, controls }))
+    }
+}
+
+/// Assert that commands are replayed after re-connecting, except the
+/// ones that may have been processed before the connection got lost
+/// (like COPY or CREATE).
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_reconnect() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(ImapEncryptionKind::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let connector = KillableConnector::default();
+
+        let imap_ctx = ImapContextBuilder::new(account_config, imap_config)
+            .with_pool_size(1)
+            .with_stream_connector(SharedStreamConnector::new(connector.clone()));
+        let imap_ctx = imap_ctx.build().await.unwrap();
+        let mut client = imap_ctx.client().await;
+
+        let email = b"From: alice@localhost\r\nTo: bob@localhost\r\n\r\nHello!\r\n";
+
+        client.create_mailbox("Reconnect").await.unwrap();
+        let uid = client
+            .add_message("INBOX", Vec::<Flag<'static>>::new(), email)
+            .await
+            .unwrap();
+        client.select_mailbox("INBOX").await.unwrap();
+        assert_eq!(connector.connections(), 1);
+
+        // NOOP is replayed on the new connection
+
+        connector.kill();
+        client.noop().await.unwrap();
+        assert_eq!(connector.connections(), 2);
+
+        // COPY fails, but the client is connected back
+
+        connector.kill();
+        let uids = SequenceSet::try_from(vec![uid]).unwrap();
+        assert!(client.copy_messages(uids, "Reconnect").await.is_err());
+        assert_eq!(connector.connections(), 3);
+
+        // COPY has not been replayed
+
+        let status = client.mailbox_status("Reconnect").await.unwrap();
+        assert_eq!(status.messages, Some(0));
+        assert_eq!(connector.connections(), 3);
+
+        // CREATE fails, but the client is connected back
+
+        connector.kill();
+        assert!(client.create_mailbox("Reconnect2").await.is_err());
+        assert_eq!(connector.connections(), 4);
+
+        // CREATE has not been replayed
+
+        assert!(client.mailbox_status("Reconnect2").await.is_err());
+        assert_eq!(connector.connections(), 4);
+    })
+    .await
+}
+
+/// Assert that a client dropped in the middle of a command, like
+/// when a call times out, is re-connected before being used again.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_reconnect_after_timeout() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(ImapEncryptionKind::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let connector = KillableConnector::default();
+
+        let imap_ctx = ImapContextBuilder::new(account_config, imap_config)
+            .with_pool_size(1)
+            .with_stream_connector(SharedStreamConnector::new(connector.clone()));
+        let imap_ctx = imap_ctx.build().await.unwrap();
+
+        imap_ctx.client().await.noop().await.unwrap();
+        assert_eq!(connector.connections(), 1);
+
+        // the response of the NOOP never arrives
+
+        connector.stall();
+        let noop = async { imap_ctx.client().await.noop().await };
+        assert!(time::timeout(Duration::from_millis(200), noop)
+            .await
+            .is_err());
+
+        // the next call runs on a new connection
+
+        let status = imap_ctx.client().await.mailbox_status("INBOX").await;
+        assert!(status.is_ok());
+        assert_eq!(connector.connections(), 2);
+    })
+    .await
+}
+
+/// Assert that a client dropped in the middle of a command because
+/// the call got cancelled is re-connected before being used again.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_reconnect_after_cancellation() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(ImapEncryptionKind::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let connector = KillableConnector::default();
+
+        let imap_ctx = ImapContextBuilder::new(account_config, imap_config)
+            .with_pool_size(1)
+            .with_stream_connector(SharedStreamConnector::new(connector.clone()));
+        let imap_ctx = imap_ctx.build().await.unwrap();
+
+        // the response of the NOOP never arrives, the call is
+        // cancelled instead
+
+        connector.stall();
+        let token = CancellationToken::new();
+
+        let noop = async {
+            imap_ctx.client().await.noop().await?;
+            AnyResult::Ok(())
+        };
+
+        let cancel = async {
+            time::sleep(Duration::from_millis(200)).await;
+            token.cancel();
+        };
+
+        let (res, ()) = tokio::join!(cancellable(&token, "noop", noop), cancel);
+        assert!(res.is_err());
+
+        // the next call runs on a new connection
+
+        let status = imap_ctx.client().await.mailbox_status("INBOX").await;
+        assert!(status.is_ok());
+        assert_eq!(connector.connections(), 2);
+    })
+    .await
+}
@@ -7,7 +7,7 @@
     account::config::AccountConfig,
     backend::{Backend, BackendBuilder},
     envelope::{list::ListEnvelopes, Id},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flag},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags, Flag, Flags},
     folder::{
         add::AddFolder, config::FolderConfig, delete::DeleteFolder, expunge::ExpungeFolder,
         list::ListFolders, Folder, FolderKind, Folders,
@@ -66,11 +66,13 @@ async fn test_maildir_features() {
             name: "Inbox".into(),
             kind: Some(FolderKind::Inbox),
             desc: tmp_dir.join("Inbox").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Nested".into(),
             kind: None,
             desc: tmp_dir.join("Nested").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Nested/Folder".into(),
@@ -80,16 +82,19 @@ async fn test_maildir_features() {
                 .join("Folder")
                 .to_string_lossy()
                 .to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Trash".into(),
             kind: Some(FolderKind::Trash),
             desc: tmp_dir.join("Trash").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Subdir".into(),
             kind: Some(FolderKind::UserDefined("subdir".into())),
             desc: tmp_dir.join("Subdir").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Subdir/Subdir".into(),
@@ -99,6 +104,7 @@ async fn test_maildir_features() {
                 .join("Subdir")
                 .to_string_lossy()
                 .to_string(),
+            ..Default::default()
         },
     ]);
 
@@ -114,6 +120,7 @@ async fn test_maildir_features() {
             name: "Inbox".into(),
             kind: Some(FolderKind::Inbox),
             desc: tmp_dir.join("Inbox").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Nested/Folder".into(),
@@ -123,16 +130,19 @@ async fn test_maildir_features() {
                 .join("Folder")
                 .to_string_lossy()
                 .to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Trash".into(),
             kind: Some(FolderKind::Trash),
             desc: tmp_dir.join("Trash").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Subdir".into(),
             kind: Some(FolderKind::UserDefined("subdir".into())),
             desc: tmp_dir.join("Subdir").to_string_lossy().to_string(),
+            ..Default::default()
         },
         Folder {
             name: "Subdir/Subdir".into(),
@@ -142,6 +152,7 @@ async fn test_maildir_features() {
                 .join("Subdir")
                 .to_string_lossy()
                 .to_string(),
+            ..Default::default()
         },
     ]);
 
@@ -202,6 +213,10 @@ async fn test_maildir_features() {
     assert!(envelope.flags.contains(&Flag::Seen));
     assert!(envelope.flags.contains(&Flag::Flagged));
 
+    // check that flags in use in the folder can be listed
+    let flags = mdir.list_flags("INBOX").await.unwrap();
+    assert_eq!(flags, Flags::from_iter([Flag::Seen, Flag::Flagged]));
+
     // check that the message flags can be changed
     mdir.set_flag("INBOX", &Id::single(&envelope.id), Flag::Answered)
         .await
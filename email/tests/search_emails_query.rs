@@ -293,6 +293,8 @@ async fn test_query(
                 page_size: 0,
                 page: 0,
                 query: Some(query),
+                changed_since: None,
+                after: None,
             },
         )
         .await
@@ -42,6 +42,7 @@ async fn test_smtp_features() {
             encryption: Some(SmtpEncryptionKind::None),
             login: "alice".into(),
             auth: SmtpAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         let imap_ctx = ImapContextBuilder::new(account_config.clone(), imap_config);
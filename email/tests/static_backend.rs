@@ -14,7 +14,7 @@
         config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
         ImapContext, ImapContextBuilder,
     },
-    message::send::{smtp::SendSmtpMessage, SendMessage},
+    message::send::{smtp::SendSmtpMessage, SendMessage, SentMessage},
     smtp::{
         config::{SmtpAuthConfig, SmtpConfig, SmtpEncryptionKind},
         SmtpContextBuilder, SmtpContextSync,
@@ -46,6 +46,7 @@ async fn test_static_backend() {
             encryption: Some(SmtpEncryptionKind::None),
             login: "alice".into(),
             auth: SmtpAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         // 1. define custom context made of subcontexts
@@ -71,7 +72,7 @@ async fn list_folders(&self) -> AnyResult<Folders> {
 
         #[async_trait]
         impl SendMessage for StaticBackend {
-            async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
+            async fn send_message(&self, msg: &[u8]) -> AnyResult<SentMessage> {
                 SendSmtpMessage::new(&self.0.smtp).send_message(msg).await
             }
         }
@@ -94,7 +95,8 @@ async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
         assert!(folders.contains(&Folder {
             kind: Some(FolderKind::Inbox),
             name: "INBOX".into(),
-            desc: "".into()
+            desc: "".into(),
+            ..Default::default()
         }));
     })
     .await
@@ -791,7 +791,7 @@ async fn test_sync() {
             ..Default::default()
         },
         Folder {
-            kind: Some(FolderKind::UserDefined("Junk".into())),
+            kind: Some(FolderKind::Junk),
             name: "Junk Mail".into(),
             ..Default::default()
         },
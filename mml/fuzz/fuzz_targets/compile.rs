@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mml::message::body::MmlBodyCompiler;
+use tokio::runtime::Builder;
+
+fuzz_target!(|mml: &str| {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let _ = rt.block_on(MmlBodyCompiler::new().compile(mml));
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mml::message::body::MimeBodyInterpreter;
+use tokio::runtime::Builder;
+
+fuzz_target!(|mime: &[u8]| {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let interpreter = MimeBodyInterpreter::new()
+        .with_show_multiparts(true)
+        .with_save_attachments(false);
+    let _ = rt.block_on(interpreter.interpret_bytes(mime));
+});
@@ -20,10 +20,9 @@
 use crate::{Error, Result};
 
 use super::{
-    ALTERNATIVE, ATTACHMENT, DISPOSITION, ENCODING, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64,
-    ENCODING_QUOTED_PRINTABLE, FILENAME, INLINE, MIXED, MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED,
-    MULTIPART_END, MULTIPART_END_ESCAPED, NAME, PART_BEGIN, PART_BEGIN_ESCAPED, PART_END,
-    PART_END_ESCAPED, RECIPIENT_FILENAME, RELATED, TYPE,
+    unescape_markup, ALTERNATIVE, ATTACHMENT, DISPOSITION, ENCODING, ENCODING_7BIT, ENCODING_8BIT,
+    ENCODING_BASE64, ENCODING_QUOTED_PRINTABLE, FILENAME, INLINE, MIXED, NAME, RECIPIENT_FILENAME,
+    RELATED, TYPE,
 };
 #[cfg(feature = "pgp")]
 use super::{ENCRYPT, PGP_MIME, SIGN};
@@ -209,11 +208,7 @@ async fn try_sign_part(&self, clear_part: MimePart<'a>) -> MimePart<'a> {
     /// Replace escaped opening and closing tags by normal opening and
     /// closing tags.
     fn unescape_mml_markup(text: impl AsRef<str>) -> String {
-        text.as_ref()
-            .replace(PART_BEGIN_ESCAPED, PART_BEGIN)
-            .replace(PART_END_ESCAPED, PART_END)
-            .replace(MULTIPART_BEGIN_ESCAPED, MULTIPART_BEGIN)
-            .replace(MULTIPART_END_ESCAPED, MULTIPART_END)
+        unescape_markup(text.as_ref())
     }
 
     /// Compile given parts parsed from a MML body to a
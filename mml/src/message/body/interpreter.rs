@@ -14,10 +14,7 @@
 use crate::pgp::Pgp;
 use crate::{Error, Result};
 
-use super::{
-    MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED, MULTIPART_END, MULTIPART_END_ESCAPED, PART_BEGIN,
-    PART_BEGIN_ESCAPED, PART_END, PART_END_ESCAPED,
-};
+use super::escape_markup;
 
 /// Filters parts to show by MIME type.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -214,10 +211,7 @@ pub fn with_pgp_recipient(mut self, recipient: Option<String>) -> Self {
     /// Replace normal opening and closing tags by escaped opening and
     /// closing tags.
     fn escape_mml_markup(text: String) -> String {
-        text.replace(PART_BEGIN, PART_BEGIN_ESCAPED)
-            .replace(PART_END, PART_END_ESCAPED)
-            .replace(MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED)
-            .replace(MULTIPART_END, MULTIPART_END_ESCAPED)
+        escape_markup(&text)
     }
 
     /// Decrypt the given [MessagePart] using PGP.
@@ -19,14 +19,10 @@
 pub use self::interpreter::{FilterParts, MimeBodyInterpreter};
 
 pub(crate) const PART_BEGIN: &str = "<#part";
-pub(crate) const PART_BEGIN_ESCAPED: &str = "<#!part";
 pub(crate) const PART_END: &str = "<#/part>";
-pub(crate) const PART_END_ESCAPED: &str = "<#!/part>";
 
 pub(crate) const MULTIPART_BEGIN: &str = "<#multipart";
-pub(crate) const MULTIPART_BEGIN_ESCAPED: &str = "<#!multipart";
 pub(crate) const MULTIPART_END: &str = "<#/multipart>";
-pub(crate) const MULTIPART_END_ESCAPED: &str = "<#!/multipart>";
 
 pub(crate) const ALTERNATIVE: &str = "alternative";
 pub(crate) const ATTACHMENT: &str = "attachment";
@@ -66,3 +62,154 @@
 pub(crate) const GREATER_THAN: char = '>';
 pub(crate) const NEW_LINE: char = '\n';
 pub(crate) const SPACE: char = ' ';
+
+/// The keywords of the MML tags that need to be escaped.
+const MARKUP_KEYWORDS: [&str; 2] = ["part", "multipart"];
+
+/// Return the length of the MML tag prefix `<#`, followed by the
+/// given number of exclamation marks, an optional slash and a tag
+/// keyword, if the given text starts with one.
+fn markup_prefix_len(text: &str, min_bangs: usize) -> Option<usize> {
+    let rest = text.strip_prefix("<#")?;
+    let bangs = rest.len() - rest.trim_start_matches('!').len();
+
+    if bangs < min_bangs {
+        return None;
+    }
+
+    let rest = &rest[bangs..];
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+
+    MARKUP_KEYWORDS
+        .iter()
+        .any(|keyword| rest.starts_with(keyword))
+        .then_some(2 + bangs)
+}
+
+/// Escape the MML tags of the given text, so that it can be safely
+/// used as a MML body.
+///
+/// Like Emacs, an exclamation mark is inserted after the tag prefix
+/// `<#`, so that `<#part>` becomes `<#!part>`. Tags that are already
+/// escaped get one more exclamation mark, which makes escaping
+/// reversible.
+pub(crate) fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut prev = None;
+
+    for (i, c) in text.char_indices() {
+        escaped.push(c);
+
+        if c == '#' && prev == Some('<') && markup_prefix_len(&text[i - 1..], 0).is_some() {
+            escaped.push('!');
+        }
+
+        prev = Some(c);
+    }
+
+    escaped
+}
+
+/// Unescape the MML tags of the given text.
+///
+/// This is the reverse of [`escape_markup`]: one exclamation mark is
+/// removed from escaped tags.
+pub(crate) fn unescape_markup(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut skip = None;
+
+    for (i, c) in text.char_indices() {
+        if skip == Some(i) {
+            continue;
+        }
+
+        if c == '<' && markup_prefix_len(&text[i..], 1).is_some() {
+            skip = Some(i + 2);
+        }
+
+        unescaped.push(c);
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{
+        escape_markup, unescape_markup, MULTIPART_BEGIN, MULTIPART_END, PART_BEGIN, PART_END,
+    };
+
+    /// Text made of plain characters and (possibly escaped) MML tag
+    /// fragments.
+    const MARKUP_TEXT: &str = "(<#!{0,2}/?(multi)?part>?|[a-z <#!/>\n])*";
+
+    #[test]
+    fn escape_unescape() {
+        assert_eq!(escape_markup("<#part>"), "<#!part>");
+        assert_eq!(escape_markup("<#/multipart>"), "<#!/multipart>");
+        assert_eq!(escape_markup("<#!part>"), "<#!!part>");
+        assert_eq!(escape_markup("<#parts <# part>"), "<#!parts <# part>");
+        assert_eq!(unescape_markup("<#!!/part>"), "<#!/part>");
+        assert_eq!(unescape_markup("<#!part>"), "<#part>");
+        assert_eq!(unescape_markup("<#! part>"), "<#! part>");
+    }
+
+    proptest! {
+        #[test]
+        fn escape_is_reversible(text in MARKUP_TEXT) {
+            prop_assert_eq!(unescape_markup(&escape_markup(&text)), text);
+        }
+
+        #[test]
+        fn escape_removes_tags(text in MARKUP_TEXT) {
+            let escaped = escape_markup(&text);
+
+            for tag in [PART_BEGIN, PART_END, MULTIPART_BEGIN, MULTIPART_END] {
+                prop_assert!(!escaped.contains(tag));
+            }
+        }
+
+        #[test]
+        fn escape_any_text(text in any::<String>()) {
+            prop_assert_eq!(unescape_markup(&escape_markup(&text)), text);
+        }
+    }
+
+    #[cfg(all(feature = "compiler", feature = "interpreter"))]
+    mod roundtrip {
+        use proptest::prelude::*;
+        use tokio::runtime::Runtime;
+
+        use super::MARKUP_TEXT;
+        use crate::message::body::{escape_markup, MimeBodyInterpreter, MmlBodyCompiler};
+
+        /// Compile the given MML body, then interpret it back.
+        fn roundtrip(rt: &Runtime, mml: &str) -> String {
+            rt.block_on(async {
+                let builder = MmlBodyCompiler::new().compile(mml).await.unwrap();
+                MimeBodyInterpreter::new()
+                    .interpret_msg_builder(builder)
+                    .await
+                    .unwrap()
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn compile_any_text(text in any::<String>()) {
+                let rt = Runtime::new().unwrap();
+                let _ = rt.block_on(MmlBodyCompiler::new().compile(&text));
+            }
+
+            #[test]
+            fn interpret_compile_is_stable(text in MARKUP_TEXT) {
+                let rt = Runtime::new().unwrap();
+                let once = roundtrip(&rt, &escape_markup(&text));
+                let twice = roundtrip(&rt, &once);
+                prop_assert_eq!(once, twice);
+            }
+        }
+    }
+}
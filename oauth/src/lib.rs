@@ -1,5 +1,8 @@
 pub mod v2_0;
 
+#[doc(no_inline)]
+pub use reqwest;
+
 use std::result;
 use thiserror::Error;
 
@@ -1,16 +1,23 @@
 //! Authorization Grant Code flow helper, as defined in the
 //! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-1.3.1)
 
+use std::{io, time::Duration};
+
+use log::debug;
 use oauth2::{
     basic::BasicClient, url::Url, AuthorizationCode, CsrfToken, PkceCodeChallenge,
     PkceCodeVerifier, RequestTokenError, Scope, TokenResponse,
 };
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    net::TcpListener,
+    net::{TcpListener, TcpStream},
+    select,
+    task::JoinSet,
+    time::timeout,
 };
+use tokio_util::sync::CancellationToken;
 
-use super::{Error, Result};
+use super::{http_client, Error, Result};
 
 /// OAuth 2.0 Authorization Code Grant flow builder.
 ///
@@ -30,6 +37,9 @@ pub struct AuthorizationCodeGrant {
     pub pkce: Option<(PkceCodeChallenge, PkceCodeVerifier)>,
     pub redirect_host: String,
     pub redirect_port: u16,
+    pub redirect_timeout: Option<Duration>,
+    pub cancellation_token: Option<CancellationToken>,
+    pub http_client: Option<reqwest::Client>,
 }
 
 impl AuthorizationCodeGrant {
@@ -66,6 +76,27 @@ pub fn with_redirect_port<T>(mut self, port: T) -> Self
         self
     }
 
+    /// Abort the wait for redirection after the given duration.
+    pub fn with_redirect_timeout(mut self, timeout: Duration) -> Self {
+        self.redirect_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort the wait for redirection as soon as the given token is
+    /// cancelled.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Send token requests using the given HTTP client.
+    ///
+    /// The client should not follow redirections.
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
     /// Generate the redirect URL used to complete the OAuth 2.0
     /// Authorization Code Grant flow.
     pub fn get_redirect_url(&self, client: &BasicClient) -> (Url, CsrfToken) {
@@ -84,66 +115,32 @@ pub fn get_redirect_url(&self, client: &BasicClient) -> (Url, CsrfToken) {
     /// [`AuthorizationCodeGrant::get_redirect_url`], then exchange
     /// the received code with an access token and maybe a refresh
     /// token.
+    ///
+    /// The wait stops with an error if the redirect timeout elapses
+    /// or if the cancellation token is cancelled, see
+    /// [`AuthorizationCodeGrant::with_redirect_timeout`] and
+    /// [`AuthorizationCodeGrant::with_cancellation_token`].
     pub async fn wait_for_redirection(
         self,
         client: &BasicClient,
         csrf_state: CsrfToken,
     ) -> Result<(String, Option<String>)> {
-        let host = self.redirect_host;
-        let port = self.redirect_port;
+        let cancellation_token = self.cancellation_token.clone().unwrap_or_default();
 
-        // listen for one single connection
-        let (mut stream, _) = TcpListener::bind((host.clone(), port))
-            .await
-            .map_err(|err| Error::BindRedirectServerError(host, port, err))?
-            .accept()
-            .await
-            .map_err(Error::AcceptRedirectServerError)?;
-
-        // extract the code from the url
-        let code = {
-            let mut reader = BufReader::new(&mut stream);
-
-            let mut request_line = String::new();
-            reader.read_line(&mut request_line).await?;
-
-            let redirect_url = request_line
-                .split_whitespace()
-                .nth(1)
-                .ok_or_else(|| Error::MissingRedirectUrlError(request_line.clone()))?;
-            let redirect_url = format!("http://localhost{redirect_url}");
-            let redirect_url = Url::parse(&redirect_url)
-                .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.clone()))?;
-
-            let (_, state) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "state")
-                .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
-            let state = CsrfToken::new(state.into_owned());
-
-            if state.secret() != csrf_state.secret() {
-                return Err(Error::InvalidStateError(
-                    state.secret().to_owned(),
-                    csrf_state.secret().to_owned(),
-                ));
+        let wait = async {
+            select! {
+                biased;
+                _ = cancellation_token.cancelled() => Err(Error::CancelRedirectionError),
+                code = self.wait_for_code(&csrf_state) => code,
             }
-
-            let (_, code) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "code")
-                .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
-
-            AuthorizationCode::new(code.into_owned())
         };
 
-        // write a basic http response in plain text
-        let res = "Authentication successful!";
-        let res = format!(
-            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
-            res.len(),
-            res
-        );
-        stream.write_all(res.as_bytes()).await?;
+        let code = match self.redirect_timeout {
+            None => wait.await?,
+            Some(duration) => timeout(duration, wait)
+                .await
+                .map_err(|_| Error::WaitForRedirectionTimedOutError(duration))??,
+        };
 
         // exchange the code for an access token and a refresh token
         let mut res = client.exchange_code(code);
@@ -153,7 +150,7 @@ pub async fn wait_for_redirection(
         }
 
         let res = res
-            .request_async(oauth2::reqwest::async_http_client)
+            .request_async(|req| http_client::send(self.http_client.as_ref(), req))
             .await
             .map_err(|err| match err {
                 RequestTokenError::Request(req) => Error::ExchangeCodeError(req.to_string()),
@@ -167,6 +164,137 @@ pub async fn wait_for_redirection(
 
         Ok((access_token, refresh_token))
     }
+
+    /// Spawn the redirect server and wait for the redirection
+    /// carrying the authorization code.
+    ///
+    /// Connections are handled concurrently, so that unrelated
+    /// requests (like the favicon requested by browsers) or idle
+    /// connections do not prevent the redirection from being
+    /// received.
+    async fn wait_for_code(&self, csrf_state: &CsrfToken) -> Result<AuthorizationCode> {
+        let host = &self.redirect_host;
+        let port = self.redirect_port;
+
+        let listener = TcpListener::bind((host.as_str(), port))
+            .await
+            .map_err(|err| Error::BindRedirectServerError(host.clone(), port, err))?;
+
+        let mut connections = JoinSet::new();
+
+        loop {
+            select! {
+                conn = listener.accept() => {
+                    let (stream, _) = conn.map_err(Error::AcceptRedirectServerError)?;
+                    connections.spawn(read_request_line(stream));
+                }
+                Some(conn) = connections.join_next() => {
+                    let (mut stream, request_line) = match conn {
+                        Ok(Ok(conn)) => conn,
+                        Ok(Err(err)) => {
+                            debug!("skipping invalid redirect server connection: {err}");
+                            continue;
+                        }
+                        Err(err) => {
+                            debug!("skipping aborted redirect server connection: {err}");
+                            continue;
+                        }
+                    };
+
+                    let redirect_url = match parse_redirect_url(&request_line) {
+                        Ok(Some(url)) => url,
+                        Ok(None) => {
+                            debug!("skipping unrelated request {}", request_line.trim());
+                            write_response(&mut stream, "404 Not Found", "Not found").await?;
+                            continue;
+                        }
+                        Err(err) => {
+                            write_response(&mut stream, "400 Bad Request", "Bad request").await?;
+                            return Err(err);
+                        }
+                    };
+
+                    let code = extract_code(redirect_url, csrf_state);
+
+                    match &code {
+                        Ok(_) => {
+                            let res = "Authentication successful!";
+                            write_response(&mut stream, "200 OK", res).await?;
+                        }
+                        Err(_) => {
+                            let res = "Authentication failed!";
+                            write_response(&mut stream, "400 Bad Request", res).await?;
+                        }
+                    }
+
+                    return code;
+                }
+            }
+        }
+    }
+}
+
+/// Read the request line of the given redirect server connection.
+async fn read_request_line(mut stream: TcpStream) -> io::Result<(TcpStream, String)> {
+    let mut request_line = String::new();
+    BufReader::new(&mut stream)
+        .read_line(&mut request_line)
+        .await?;
+    Ok((stream, request_line))
+}
+
+/// Parse the redirect URL from the given request line.
+///
+/// Returns `None` if the URL does not look like an OAuth 2.0
+/// redirection, i.e. when it contains neither a state nor a code.
+fn parse_redirect_url(request_line: &str) -> Result<Option<Url>> {
+    let redirect_url = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| Error::MissingRedirectUrlError(request_line.to_owned()))?;
+    let redirect_url = format!("http://localhost{redirect_url}");
+    let redirect_url = Url::parse(&redirect_url)
+        .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.clone()))?;
+
+    let is_redirection = redirect_url
+        .query_pairs()
+        .any(|(key, _)| key == "state" || key == "code");
+
+    Ok(is_redirection.then_some(redirect_url))
+}
+
+/// Extract the authorization code from the given redirect URL, after
+/// checking that its state matches the given CSRF state.
+fn extract_code(redirect_url: Url, csrf_state: &CsrfToken) -> Result<AuthorizationCode> {
+    let (_, state) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
+    let state = CsrfToken::new(state.into_owned());
+
+    if state.secret() != csrf_state.secret() {
+        return Err(Error::InvalidStateError(
+            state.secret().to_owned(),
+            csrf_state.secret().to_owned(),
+        ));
+    }
+
+    let (_, code) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "code")
+        .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
+
+    Ok(AuthorizationCode::new(code.into_owned()))
+}
+
+/// Write a basic HTTP response in plain text.
+async fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    let res = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    stream.write_all(res.as_bytes()).await?;
+    Ok(())
 }
 
 impl Default for AuthorizationCodeGrant {
@@ -176,6 +304,9 @@ fn default() -> Self {
             pkce: None,
             redirect_host: String::from("localhost"),
             redirect_port: 9999,
+            redirect_timeout: None,
+            cancellation_token: None,
+            http_client: None,
         }
     }
 }
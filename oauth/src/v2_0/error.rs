@@ -1,5 +1,5 @@
 use oauth2::{basic::BasicErrorResponseType, RequestTokenError, StandardErrorResponse};
-use std::{io, result};
+use std::{io, result, time::Duration};
 use thiserror::Error;
 use url::Url;
 
@@ -31,6 +31,10 @@ pub enum Error {
     FindCodeInRedirectUrlError(Url),
     #[error("cannot find state from redirect url {0}")]
     FindStateInRedirectUrlError(Url),
+    #[error("cannot wait for redirection: timed out after {0:?}")]
+    WaitForRedirectionTimedOutError(Duration),
+    #[error("cannot wait for redirection: cancelled")]
+    CancelRedirectionError,
     #[error("cannot exchange code for access and refresh tokens: {0}")]
     ExchangeCodeError(String),
 
@@ -0,0 +1,51 @@
+//! Module dedicated to HTTP clients.
+//!
+//! This internal module adapts custom [`reqwest::Client`]s to the
+//! HTTP client interface expected by the `oauth2` crate.
+
+use oauth2::{HttpRequest, HttpResponse};
+
+/// The error returned by HTTP clients.
+pub(crate) type HttpClientError = oauth2::reqwest::Error<reqwest::Error>;
+
+/// Send the given request, using the given HTTP client if any or
+/// the default `oauth2` one.
+///
+/// Custom HTTP clients should not follow redirections, in order to
+/// prevent SSRF vulnerabilities.
+pub(crate) async fn send(
+    client: Option<&reqwest::Client>,
+    request: HttpRequest,
+) -> Result<HttpResponse, HttpClientError> {
+    let Some(client) = client else {
+        return oauth2::reqwest::async_http_client(request).await;
+    };
+
+    let mut builder = client
+        .request(request.method, request.url.as_str())
+        .body(request.body);
+
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_bytes());
+    }
+
+    let request = builder.build().map_err(HttpClientError::Reqwest)?;
+    let response = client
+        .execute(request)
+        .await
+        .map_err(HttpClientError::Reqwest)?;
+
+    let status_code = response.status();
+    let headers = response.headers().to_owned();
+    let body = response
+        .bytes()
+        .await
+        .map_err(HttpClientError::Reqwest)?
+        .to_vec();
+
+    Ok(HttpResponse {
+        status_code,
+        headers,
+        body,
+    })
+}
@@ -8,6 +8,7 @@
 mod authorization_code_grant;
 mod client;
 mod error;
+mod http_client;
 mod refresh_access_token;
 
 #[doc(inline)]
@@ -17,3 +18,5 @@
     error::{Error, Result},
     refresh_access_token::RefreshAccessToken,
 };
+#[doc(inline)]
+pub use tokio_util::sync::CancellationToken;
@@ -3,18 +3,27 @@
 
 use oauth2::{basic::BasicClient, RefreshToken, TokenResponse};
 
-use super::{Error, Result};
+use super::{http_client, Error, Result};
 
-/// OAuth 2.0 Refresh Access Token flow builder. The builder is empty
-/// for now but scopes will be added in the future. This flow exchange
-/// a refresh token for a new pair of access token and maybe a refresh
+/// OAuth 2.0 Refresh Access Token flow builder. This flow exchange a
+/// refresh token for a new pair of access token and maybe a refresh
 /// token.
 #[derive(Debug, Default)]
-pub struct RefreshAccessToken;
+pub struct RefreshAccessToken {
+    pub http_client: Option<reqwest::Client>,
+}
 
 impl RefreshAccessToken {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Send token requests using the given HTTP client.
+    ///
+    /// The client should not follow redirections.
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
     }
 
     pub async fn refresh_access_token(
@@ -24,7 +33,7 @@ pub async fn refresh_access_token(
     ) -> Result<(String, Option<String>)> {
         let res = client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
-            .request_async(oauth2::reqwest::async_http_client)
+            .request_async(|req| http_client::send(self.http_client.as_ref(), req))
             .await
             .map_err(Error::RefreshAccessTokenError)?;
 
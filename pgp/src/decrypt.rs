@@ -1,6 +1,7 @@
 //! Module dedicated to PGP decryption.
 //!
-//! This module exposes a simple function [`decrypt`] and its
+//! This module exposes a simple function [`decrypt`], its
+//! passphrase-based variant [`decrypt_with_passphrase`] and their
 //! associated [`Error`]s.
 
 use pgp_native::{Deserializable, Message, SignedSecretKey};
@@ -38,9 +39,39 @@ pub async fn decrypt(
     .await?
 }
 
+/// Decrypts bytes previously encrypted with the given passphrase.
+///
+/// See [`crate::encrypt_with_passphrase`].
+pub async fn decrypt_with_passphrase(
+    passphrase: impl ToString,
+    encrypted_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let passphrase = passphrase.to_string();
+    task::spawn_blocking(move || {
+        let (msg, _) = Message::from_armor_single(Cursor::new(&encrypted_bytes))
+            .map_err(Error::ImportMessageFromArmorError)?;
+        let decryptor = msg
+            .decrypt_with_password(|| passphrase)
+            .map_err(Error::DecryptMessageError)?;
+        let msgs = decryptor
+            .collect::<pgp_native::errors::Result<Vec<_>>>()
+            .map_err(Error::DecryptMessageError)?;
+        let msg = msgs.into_iter().next().ok_or(Error::GetMessageEmptyError)?;
+        let msg = msg.decompress().map_err(Error::DecompressMessageError)?;
+
+        let plain_bytes = msg
+            .get_content()
+            .map_err(Error::GetMessageContentError)?
+            .ok_or(Error::GetMessageContentEmptyError)?;
+
+        Ok(plain_bytes)
+    })
+    .await?
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{decrypt, encrypt, gen_key_pair};
+    use crate::{decrypt, decrypt_with_passphrase, encrypt, encrypt_with_passphrase, gen_key_pair};
 
     #[tokio::test]
     async fn encrypt_then_decrypt() {
@@ -69,4 +100,22 @@ async fn encrypt_then_decrypt() {
             super::Error::DecryptMessageError(pgp_native::errors::Error::MissingKey),
         ));
     }
+
+    #[tokio::test]
+    async fn encrypt_then_decrypt_with_passphrase() {
+        let msg = b"encrypted message".to_vec();
+        let encrypted_msg = encrypt_with_passphrase("passphrase", msg.clone())
+            .await
+            .unwrap();
+
+        let plain_msg = decrypt_with_passphrase("passphrase", encrypted_msg.clone())
+            .await
+            .unwrap();
+        assert_eq!(plain_msg, msg);
+
+        let err = decrypt_with_passphrase("invalid", encrypted_msg)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, super::Error::DecryptMessageError(_)));
+    }
 }
@@ -1,11 +1,12 @@
 //! Module dedicated to PGP encryption.
 //!
-//! This module exposes a simple function [`encrypt`] and its
+//! This module exposes a simple function [`encrypt`], its
+//! passphrase-based variant [`encrypt_with_passphrase`] and their
 //! associated [`Error`]s.
 
 use pgp_native::{
-    crypto::{hash::HashAlgorithm, public_key::PublicKeyAlgorithm},
-    types::{CompressionAlgorithm, KeyId, KeyTrait, Mpi, PublicKeyTrait},
+    crypto::{hash::HashAlgorithm, public_key::PublicKeyAlgorithm, sym::SymmetricKeyAlgorithm},
+    types::{CompressionAlgorithm, KeyId, KeyTrait, Mpi, PublicKeyTrait, StringToKey},
     Message, SignedPublicKey, SignedPublicSubKey,
 };
 use rand::{thread_rng, CryptoRng, Rng};
@@ -118,3 +119,32 @@ pub async fn encrypt(pkeys: Vec<SignedPublicKey>, plain_bytes: Vec<u8>) -> Resul
     })
     .await?
 }
+
+/// Encrypts given bytes using the given passphrase.
+///
+/// The message is symmetrically encrypted, which means that the same
+/// passphrase is needed to decrypt it. See
+/// [`crate::decrypt_with_passphrase`].
+pub async fn encrypt_with_passphrase(
+    passphrase: impl ToString,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let passphrase = passphrase.to_string();
+    task::spawn_blocking(move || {
+        let mut rng = thread_rng();
+
+        let msg = Message::new_literal_bytes("", &plain_bytes);
+        let s2k = StringToKey::new_default(&mut rng);
+
+        let encrypted_bytes = msg
+            .compress(CompressionAlgorithm::ZLIB)
+            .map_err(Error::CompressMessageError)?
+            .encrypt_with_password(&mut rng, s2k, SymmetricKeyAlgorithm::AES256, || passphrase)
+            .map_err(Error::EncryptMessageError)?
+            .to_armored_bytes(None)
+            .map_err(Error::ExportEncryptedMessageToArmorError)?;
+
+        Ok(encrypted_bytes)
+    })
+    .await?
+}
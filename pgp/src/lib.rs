@@ -15,8 +15,8 @@
 
 #[doc(inline)]
 pub use crate::{
-    decrypt::decrypt,
-    encrypt::encrypt,
+    decrypt::{decrypt, decrypt_with_passphrase},
+    encrypt::{encrypt, encrypt_with_passphrase},
     error::{Error, Result},
     sign::sign,
     utils::{